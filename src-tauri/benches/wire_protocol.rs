@@ -0,0 +1,136 @@
+//! Benchmarks for encoding and decoding Jupyter wire protocol messages.
+//!
+//! Fixtures are modeled on large outputs: a plot as a base64 PNG, a long
+//! stream of text, and a widget message with a binary buffer.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use jute::backend::wire_protocol::{
+    from_ws_payload, from_zmq_payload, sign_message, to_ws_payload, to_zmq_payload, DisplayData,
+    FrameBuffer, KernelMessage, KernelMessageType, Stream,
+};
+use serde_json::json;
+
+/// Deterministic text of a given length, drawn from the base64 alphabet.
+fn filler(len: usize) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    (0..len)
+        .map(|i| ALPHABET[(i * 7 + i / 64) % 64] as char)
+        .collect()
+}
+
+/// Messages of each kind of large output, with their names.
+fn fixtures() -> Vec<(&'static str, KernelMessage)> {
+    let plot = KernelMessage::new(
+        KernelMessageType::DisplayData,
+        json!({
+            "data": {
+                "image/png": filler(1 << 20),
+                "text/plain": "<Figure size 640x480 with 1 Axes>",
+            },
+            "metadata": {},
+            "transient": {},
+        }),
+    );
+
+    let lines: String = (0..20_000)
+        .map(|i| format!("step {i}: loss=0.{i:05}\n"))
+        .collect();
+    let stream = KernelMessage::new(
+        KernelMessageType::Stream,
+        json!({ "name": "stdout", "text": lines }),
+    );
+
+    let mut widget = KernelMessage::new(
+        KernelMessageType::CommMsg,
+        json!({
+            "comm_id": "8f0d8c1e-2b4a-4e8e-9a57-3f1d6f0e2c11",
+            "data": { "method": "update", "state": {}, "buffer_paths": [["value"]] },
+        }),
+    );
+    widget.buffers = vec![Bytes::from(filler(4 << 20))];
+
+    vec![("plot", plot), ("stream", stream), ("widget", widget)]
+}
+
+fn websocket(c: &mut Criterion) {
+    let mut group = c.benchmark_group("websocket");
+    let mut frames = FrameBuffer::default();
+    for (name, msg) in fixtures() {
+        let payload = Bytes::from(to_ws_payload(&msg, "iopub", &mut frames).unwrap());
+        group.throughput(Throughput::Bytes(payload.len() as u64));
+        group.bench_function(format!("encode/{name}"), |b| {
+            b.iter(|| to_ws_payload(&msg, "iopub", &mut frames).unwrap())
+        });
+        group.bench_function(format!("decode/{name}"), |b| {
+            b.iter(|| from_ws_payload(payload.clone()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn zeromq(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zeromq");
+    let mut frames = FrameBuffer::default();
+    for (name, msg) in fixtures() {
+        let payload = to_zmq_payload(&msg, "key", &mut frames).unwrap();
+        let len: usize = payload.iter().map(|frame| frame.len()).sum();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(format!("encode/{name}"), |b| {
+            b.iter(|| to_zmq_payload(&msg, "key", &mut frames).unwrap())
+        });
+        group.bench_function(format!("decode/{name}"), |b| {
+            b.iter_batched(
+                || payload.clone(),
+                |payload| from_zmq_payload(payload).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn signing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hmac");
+    for size in [1 << 10, 1 << 20, 16 << 20] {
+        let frames = [Bytes::from(filler(size))];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(format!("sign/{size}"), |b| {
+            b.iter(|| sign_message("f4d6b2b9-8bd4-4f3e-a1a4-6d1ab2c1e0f7", &frames))
+        });
+    }
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kernel_message");
+    let fixtures = fixtures();
+    let (_, plot) = &fixtures[0];
+    let (_, stream) = &fixtures[1];
+
+    group.bench_function("typed/display_data", |b| {
+        b.iter_batched(
+            || plot.clone(),
+            |msg| msg.into_typed::<DisplayData>().unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("typed/stream", |b| {
+        b.iter_batched(
+            || stream.clone(),
+            |msg| msg.into_typed::<Stream>().unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("json/display_data", |b| {
+        b.iter_batched(
+            || plot.clone().into_typed::<DisplayData>().unwrap(),
+            |msg| msg.into_json(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, websocket, zeromq, signing, serialization);
+criterion_main!(benches);
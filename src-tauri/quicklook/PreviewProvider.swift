@@ -0,0 +1,34 @@
+// Quick Look preview extension for Jupyter notebooks.
+//
+// The HTML is rendered by the `jute-preview` helper bundled in the extension's
+// resources, using the same notebook parser and exporter as the app.
+
+import Foundation
+import QuickLookUI
+import UniformTypeIdentifiers
+
+class PreviewProvider: QLPreviewProvider, QLPreviewingController {
+  func providePreview(for request: QLFilePreviewRequest) async throws -> QLPreviewReply {
+    guard let helper = Bundle.main.url(forResource: "jute-preview", withExtension: nil) else {
+      throw CocoaError(.fileNoSuchFile)
+    }
+
+    let process = Process()
+    process.executableURL = helper
+    process.arguments = [request.fileURL.path]
+    let stdout = Pipe()
+    process.standardOutput = stdout
+    try process.run()
+    let html = stdout.fileHandleForReading.readDataToEndOfFile()
+    process.waitUntilExit()
+    guard process.terminationStatus == 0 else {
+      throw CocoaError(.fileReadCorruptFile)
+    }
+
+    return QLPreviewReply(dataOfContentType: .html, contentSize: CGSize(width: 800, height: 800)) {
+      reply in
+      reply.stringEncoding = .utf8
+      return html
+    }
+  }
+}
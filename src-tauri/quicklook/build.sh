@@ -0,0 +1,32 @@
+#!/bin/sh
+# Build the Quick Look preview extension, which is copied into the app bundle
+# by `tauri.macos.conf.json`. This runs as the `beforeBundleCommand`.
+set -eu
+
+dir="$(cd "$(dirname "$0")" && pwd)"
+appex="$dir/build/JutePreview.appex"
+identity="${APPLE_SIGNING_IDENTITY:--}"
+
+rm -rf "$appex"
+mkdir -p "$appex/Contents/MacOS" "$appex/Contents/Resources"
+cp "$dir/Info.plist" "$appex/Contents/Info.plist"
+
+# The helper renders notebooks with the same exporter as the app.
+cargo build --release --manifest-path "$dir/../Cargo.toml" --bin jute-preview
+cp "$dir/../target/release/jute-preview" "$appex/Contents/Resources/jute-preview"
+
+swiftc -parse-as-library -application-extension -O \
+  -module-name JutePreview \
+  -target "$(uname -m)-apple-macos12.0" \
+  -framework QuickLookUI \
+  -Xlinker -e -Xlinker _NSExtensionMain \
+  "$dir/PreviewProvider.swift" \
+  -o "$appex/Contents/MacOS/JutePreview"
+
+# Nested code is signed first, since extensions must be sandboxed.
+codesign --force --options runtime --sign "$identity" \
+  --entitlements "$dir/helper.entitlements" \
+  "$appex/Contents/Resources/jute-preview"
+codesign --force --options runtime --sign "$identity" \
+  --entitlements "$dir/JutePreview.entitlements" \
+  "$appex"
@@ -0,0 +1,41 @@
+// Spotlight import extension for Jupyter notebooks.
+//
+// The metadata is extracted by the `jute-index` helper bundled in the
+// extension's resources, using the same notebook parser as the app.
+
+import CoreSpotlight
+import Foundation
+
+/// Output of the `jute-index` helper.
+struct SearchMetadata: Decodable {
+  let title: String?
+  let headings: [String]
+  let language: String
+  let text: String
+}
+
+class ImportExtension: CSImportExtension {
+  override func update(_ attributes: CSSearchableItemAttributeSet, forFileAt contentURL: URL) throws
+  {
+    guard let helper = Bundle.main.url(forResource: "jute-index", withExtension: nil) else {
+      throw CocoaError(.fileNoSuchFile)
+    }
+
+    let process = Process()
+    process.executableURL = helper
+    process.arguments = [contentURL.path]
+    let stdout = Pipe()
+    process.standardOutput = stdout
+    try process.run()
+    let json = stdout.fileHandleForReading.readDataToEndOfFile()
+    process.waitUntilExit()
+    guard process.terminationStatus == 0 else {
+      throw CocoaError(.fileReadCorruptFile)
+    }
+
+    let metadata = try JSONDecoder().decode(SearchMetadata.self, from: json)
+    attributes.title = metadata.title
+    attributes.keywords = metadata.headings + [metadata.language]
+    attributes.textContent = metadata.text
+  }
+}
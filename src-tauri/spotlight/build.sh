@@ -0,0 +1,32 @@
+#!/bin/sh
+# Build the Spotlight import extension, which is copied into the app bundle
+# by `tauri.macos.conf.json`. This runs as part of the `beforeBundleCommand`.
+set -eu
+
+dir="$(cd "$(dirname "$0")" && pwd)"
+appex="$dir/build/JuteIndex.appex"
+identity="${APPLE_SIGNING_IDENTITY:--}"
+
+rm -rf "$appex"
+mkdir -p "$appex/Contents/MacOS" "$appex/Contents/Resources"
+cp "$dir/Info.plist" "$appex/Contents/Info.plist"
+
+# The helper extracts metadata with the same notebook parser as the app.
+cargo build --release --manifest-path "$dir/../Cargo.toml" --bin jute-index
+cp "$dir/../target/release/jute-index" "$appex/Contents/Resources/jute-index"
+
+swiftc -parse-as-library -application-extension -O \
+  -module-name JuteIndex \
+  -target "$(uname -m)-apple-macos12.0" \
+  -framework CoreSpotlight \
+  -Xlinker -e -Xlinker _NSExtensionMain \
+  "$dir/ImportExtension.swift" \
+  -o "$appex/Contents/MacOS/JuteIndex"
+
+# Nested code is signed first, since extensions must be sandboxed.
+codesign --force --options runtime --sign "$identity" \
+  --entitlements "$dir/helper.entitlements" \
+  "$appex/Contents/Resources/jute-index"
+codesign --force --options runtime --sign "$identity" \
+  --entitlements "$dir/JuteIndex.entitlements" \
+  "$appex"
@@ -0,0 +1,195 @@
+//! Autosave of whole notebooks to the crash recovery directory.
+//!
+//! Changes to a notebook are recorded as they happen, and a snapshot of it is
+//! taken and written once the changes pause for [`DEBOUNCE`], or at least
+//! every [`MAX_DELAY`] while they keep coming. Snapshots are stored with the
+//! edit journal, see [`crate::recovery`], and offered for recovery when the
+//! notebook is next opened, if they are newer than the file.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use time::OffsetDateTime;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::{backend::notebook::NotebookRoot, recovery::RecoveryJournal};
+
+/// How long changes must pause before a snapshot is written.
+pub const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Longest time a change waits to be written while others keep coming.
+pub const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Takes a snapshot of a notebook's current contents, or returns `None` if
+/// it is no longer open.
+pub type SnapshotFn = Arc<dyn Fn() -> Option<NotebookRoot> + Send + Sync>;
+
+/// Unwritten changes to a notebook.
+struct PendingSnapshot {
+    /// How to take a snapshot of the changes, or `None` if it was taken by the
+    /// writer.
+    snapshot: Option<SnapshotFn>,
+    first_change: Instant,
+    last_change: Instant,
+}
+
+impl fmt::Debug for PendingSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingSnapshot")
+            .field("changed", &self.snapshot.is_some())
+            .field("first_change", &self.first_change)
+            .field("last_change", &self.last_change)
+            .finish()
+    }
+}
+
+impl PendingSnapshot {
+    /// When the snapshot should be written.
+    fn deadline(&self, debounce: Duration, max_delay: Duration) -> Instant {
+        (self.last_change + debounce).min(self.first_change + max_delay)
+    }
+}
+
+/// Writes debounced snapshots of notebooks, with one background task for each
+/// notebook that has unwritten changes.
+#[derive(Clone, Debug)]
+pub struct Autosaver {
+    pending: Arc<DashMap<PathBuf, PendingSnapshot>>,
+    debounce: Duration,
+    max_delay: Duration,
+}
+
+impl Default for Autosaver {
+    fn default() -> Self {
+        Self::new(DEBOUNCE, MAX_DELAY)
+    }
+}
+
+impl Autosaver {
+    /// Create an autosaver with the given delays.
+    pub fn new(debounce: Duration, max_delay: Duration) -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            debounce,
+            max_delay,
+        }
+    }
+
+    /// Record a change to the notebook at `path`, to be written to `journal`
+    /// later. The snapshot is only taken when it is written, so changes that
+    /// keep coming are cheap.
+    pub fn update(&self, journal: RecoveryJournal, path: PathBuf, snapshot: SnapshotFn) {
+        let now = Instant::now();
+        match self.pending.entry(path.clone()) {
+            Entry::Occupied(mut entry) => {
+                let pending = entry.get_mut();
+                if pending.snapshot.is_none() {
+                    pending.first_change = now;
+                }
+                pending.snapshot = Some(snapshot);
+                pending.last_change = now;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(PendingSnapshot {
+                    snapshot: Some(snapshot),
+                    first_change: now,
+                    last_change: now,
+                });
+                tokio::spawn(self.clone().run(journal, path));
+            }
+        }
+    }
+
+    /// Drop the unwritten changes to a notebook, as when it is saved.
+    pub fn cancel(&self, path: &Path) {
+        self.pending.remove(path);
+    }
+
+    /// Write snapshots of a notebook until it has no unwritten changes.
+    async fn run(self, journal: RecoveryJournal, path: PathBuf) {
+        loop {
+            let Some(deadline) = (self.pending.get(&path))
+                .map(|pending| pending.deadline(self.debounce, self.max_delay))
+            else {
+                return;
+            };
+            if Instant::now() < deadline {
+                tokio::time::sleep_until(deadline).await;
+                continue;
+            }
+
+            let snapshot =
+                (self.pending.get_mut(&path)).and_then(|mut pending| pending.snapshot.take());
+            let taken = OffsetDateTime::now_utc();
+            if let Some(notebook) = snapshot.and_then(|snapshot| snapshot()) {
+                if let Err(err) = journal.save_snapshot(&path, &notebook, taken).await {
+                    warn!("failed to autosave {path:?}: {err}");
+                }
+            }
+
+            // Stop unless the notebook changed again while it was written.
+            let written = self
+                .pending
+                .remove_if(&path, |_, pending| pending.snapshot.is_none());
+            if written.is_some() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn notebook(cells: usize) -> NotebookRoot {
+        let cells: Vec<_> = (0..cells)
+            .map(|i| serde_json::json!({ "cell_type": "raw", "metadata": {}, "source": i.to_string() }))
+            .collect();
+        NotebookRoot::from_cells(cells)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debounce_snapshots() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let journal = RecoveryJournal::new(dir.join("recovery"));
+        let path = dir.join("notebook.ipynb");
+        let autosaver = Autosaver::new(Duration::from_millis(100), Duration::from_millis(250));
+        let cells = Arc::new(AtomicUsize::new(0));
+        let snapshot: SnapshotFn = {
+            let cells = cells.clone();
+            Arc::new(move || Some(notebook(cells.load(Ordering::SeqCst))))
+        };
+
+        // Changes that keep coming are still written after the longest delay.
+        for count in 1..=8 {
+            cells.store(count, Ordering::SeqCst);
+            autosaver.update(journal.clone(), path.clone(), snapshot.clone());
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let written = journal.recover(&path).await.unwrap().unwrap().snapshot;
+        assert!(written.unwrap().cells.len() < 8);
+
+        // The last change is written after a pause.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let written = journal.recover(&path).await.unwrap().unwrap().snapshot;
+        assert_eq!(written.unwrap().cells.len(), 8);
+        assert!(autosaver.pending.is_empty());
+
+        // Cancelled changes are never written.
+        journal.clear(&path).await.unwrap();
+        autosaver.update(journal.clone(), path.clone(), snapshot);
+        autosaver.cancel(&path);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(journal.recover(&path).await.unwrap(), None);
+    }
+}
@@ -5,8 +5,15 @@
 
 pub use wire_protocol::{create_websocket_connection, create_zeromq_connection, KernelConnection};
 
+pub mod attachments;
 pub mod commands;
+pub mod diff;
 pub mod local;
+pub mod merge;
 pub mod notebook;
+pub mod outputs;
+pub mod queue;
 pub mod remote;
+pub mod truncate;
+pub mod variables;
 pub mod wire_protocol;
@@ -0,0 +1,297 @@
+//! Attachments of Markdown cells, which are images stored in the notebook as
+//! MIME bundles and shown in the cell with `attachment:<name>` URLs.
+//!
+//! Images pasted or dropped into a cell are attached under a unique name, and
+//! attachments that the source no longer mentions are removed when the
+//! notebook is saved, as in JupyterLab.
+
+use std::collections::HashSet;
+
+use base64::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use ts_rs::TS;
+
+use super::notebook::{Cell, CellAttachments, MimeBundle, NotebookRoot};
+use crate::Error;
+
+/// Image types that can be attached, with their file extension.
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/bmp", "bmp"),
+    ("image/svg+xml", "svg"),
+];
+
+/// An attachment of a cell, as listed for the user.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct AttachmentInfo {
+    /// Name of the attachment, as in `attachment:<name>` URLs.
+    pub name: String,
+
+    /// MIME type of the attachment.
+    pub mime_type: String,
+
+    /// Size of the attachment's data, in bytes.
+    #[ts(type = "number")]
+    pub size: usize,
+
+    /// Whether the source of the cell mentions the attachment. Attachments
+    /// that it doesn't are removed on save.
+    pub referenced: bool,
+}
+
+crate::export_bindings!(AttachmentInfo);
+
+/// An image to be added to a cell's attachments.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct NewAttachment {
+    /// Name of the attachment, unique in the cell.
+    pub name: String,
+
+    /// MIME bundle to store under the name.
+    pub bundle: MimeBundle,
+
+    /// Markdown that shows the image, to insert into the source.
+    pub markdown: String,
+}
+
+crate::export_bindings!(NewAttachment);
+
+/// Detect the type of an image from its data, returning its MIME type and
+/// file extension.
+fn image_type(data: &[u8]) -> Option<(&'static str, &'static str)> {
+    let mime_type = if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else if data.starts_with(b"BM") {
+        "image/bmp"
+    } else if is_svg(data) {
+        "image/svg+xml"
+    } else {
+        return None;
+    };
+    IMAGE_TYPES.iter().copied().find(|(m, _)| *m == mime_type)
+}
+
+/// Check whether data is an SVG document, whose root element is `<svg>` after
+/// any XML declaration, comments, and doctype.
+fn is_svg(data: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return false;
+    };
+    let mut rest = text.trim_start_matches('\u{feff}');
+    loop {
+        rest = rest.trim_start();
+        let end = if rest.starts_with("<?") {
+            "?>"
+        } else if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<!") {
+            ">"
+        } else {
+            break;
+        };
+        match rest.find(end) {
+            Some(i) => rest = &rest[i + end.len()..],
+            None => return false,
+        }
+    }
+    rest.strip_prefix("<svg")
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/'))
+}
+
+/// Choose a name for an attachment that isn't taken, keeping only characters
+/// that are safe in a URL.
+fn unique_name(existing: &[String], file_name: &str, extension: &str) -> String {
+    let stem = match file_name.rsplit_once('.') {
+        Some((stem, _)) => stem,
+        None => file_name,
+    };
+    let stem: String = (stem.chars())
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '-',
+        })
+        .collect();
+    let stem = if stem.is_empty() { "image" } else { &stem };
+
+    let mut name = format!("{stem}.{extension}");
+    for n in 1.. {
+        if !existing.contains(&name) {
+            break;
+        }
+        name = format!("{stem}-{n}.{extension}");
+    }
+    name
+}
+
+/// Create an attachment for an image pasted or dropped into a cell, whose
+/// existing attachments are named `existing`.
+pub fn create_attachment(
+    existing: &[String],
+    file_name: &str,
+    data: &[u8],
+) -> Result<NewAttachment, Error> {
+    let (mime_type, extension) = image_type(data)
+        .ok_or_else(|| Error::InvalidAttachment(format!("{file_name} is not a supported image")))?;
+    let name = unique_name(existing, file_name, extension);
+
+    // Text types are stored as is, and others as base64, like nbformat.
+    let value = if mime_type == "image/svg+xml" {
+        Value::String(String::from_utf8_lossy(data).into_owned())
+    } else {
+        Value::String(BASE64_STANDARD.encode(data))
+    };
+    Ok(NewAttachment {
+        markdown: format!("![{name}](attachment:{name})"),
+        bundle: MimeBundle::from([(mime_type.into(), value)]),
+        name,
+    })
+}
+
+/// Names of the attachments mentioned by `attachment:` URLs in a source.
+pub fn referenced_attachments(source: &str) -> HashSet<&str> {
+    source
+        .match_indices("attachment:")
+        .map(|(i, prefix)| {
+            let rest = &source[i + prefix.len()..];
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, ')' | '"' | '\'' | '>'))
+                .unwrap_or(rest.len());
+            &rest[..end]
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// List the attachments of a cell, in order of name.
+pub fn list_attachments(source: &str, attachments: &CellAttachments) -> Vec<AttachmentInfo> {
+    let referenced = referenced_attachments(source);
+    (attachments.iter())
+        .map(|(name, bundle)| {
+            let (mime_type, size) = (bundle.iter())
+                .find(|(mime_type, _)| mime_type.starts_with("image/"))
+                .or_else(|| bundle.iter().next())
+                .map(|(mime_type, value)| (mime_type.clone(), data_size(mime_type, value)))
+                .unwrap_or_default();
+            AttachmentInfo {
+                name: name.clone(),
+                mime_type,
+                size,
+                referenced: referenced.contains(name.as_str()),
+            }
+        })
+        .collect()
+}
+
+/// Size of the data of a MIME bundle entry, decoding base64 for binary types.
+fn data_size(mime_type: &str, value: &Value) -> usize {
+    let text: String = match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => return value.to_string().len(),
+    };
+    if mime_type.starts_with("text/") || mime_type.ends_with("+xml") || mime_type.ends_with("json")
+    {
+        return text.len();
+    }
+    let encoded = text.bytes().filter(|b| !b.is_ascii_whitespace()).count();
+    (encoded * 3 / 4).saturating_sub(text.matches('=').count())
+}
+
+/// Remove the attachments that no cell source mentions, returning how many
+/// were removed.
+pub fn remove_unused_attachments(notebook: &mut NotebookRoot) -> usize {
+    let mut removed = 0;
+    for cell in &mut notebook.cells {
+        let source = cell.source();
+        let attachments = match cell {
+            Cell::Raw(cell) => &mut cell.attachments,
+            Cell::Markdown(cell) => &mut cell.attachments,
+            Cell::Code(_) => continue,
+        };
+        let Some(bundles) = attachments else {
+            continue;
+        };
+        let referenced = referenced_attachments(&source);
+        let count = bundles.len();
+        bundles.retain(|name, _| referenced.contains(name.as_str()));
+        removed += count - bundles.len();
+        if bundles.is_empty() {
+            *attachments = None;
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn attach_and_collect() {
+        let attachment = create_attachment(&[], "Screen Shot.png", PNG).unwrap();
+        assert_eq!(attachment.name, "Screen-Shot.png");
+        assert_eq!(
+            attachment.markdown,
+            "![Screen-Shot.png](attachment:Screen-Shot.png)"
+        );
+        assert!(attachment.bundle.contains_key("image/png"));
+        let existing = vec![attachment.name.clone()];
+        let second = create_attachment(&existing, "Screen Shot.png", PNG).unwrap();
+        assert_eq!(second.name, "Screen-Shot-1.png");
+        assert!(create_attachment(&[], "notes.txt", b"hello").is_err());
+
+        let svg =
+            "<?xml version=\"1.0\"?>\n<!-- icon -->\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+        assert_eq!(image_type(svg.as_bytes()), Some(("image/svg+xml", "svg")));
+        assert_eq!(image_type(b"<html><svg></svg></html>"), None);
+        assert_eq!(image_type(b"<svgfoo/>"), None);
+
+        let source = format!("{}\n<img src=\"attachment:b.gif\">\n", attachment.markdown);
+        let mut notebook = NotebookRoot::from_cells(vec![serde_json::json!({
+            "cell_type": "markdown",
+            "metadata": {},
+            "source": source,
+            "attachments": {
+                "Screen-Shot.png": attachment.bundle,
+                "b.gif": { "image/gif": "R0lGODlh" },
+                "unused.png": attachment.bundle,
+            },
+        })]);
+        let Cell::Markdown(cell) = &notebook.cells[0] else {
+            panic!("expected markdown cell");
+        };
+        let listed = list_attachments(&source, cell.attachments.as_ref().unwrap());
+        let names: Vec<_> = listed
+            .iter()
+            .map(|a| (a.name.as_str(), a.referenced))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Screen-Shot.png", true),
+                ("b.gif", true),
+                ("unused.png", false)
+            ]
+        );
+        assert_eq!(listed[0].size, PNG.len());
+        assert_eq!(listed[1].size, 6);
+
+        assert_eq!(remove_unused_attachments(&mut notebook), 1);
+        let Cell::Markdown(cell) = &notebook.cells[0] else {
+            panic!("expected markdown cell");
+        };
+        assert_eq!(cell.attachments.as_ref().unwrap().len(), 2);
+    }
+}
@@ -1,18 +1,31 @@
 //! High-level APIs for doing operations over [`KernelConnection`] objects.
 
-use serde::Serialize;
+use async_channel::Sender;
+use base64::prelude::*;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
 use ts_rs::TS;
 
 use super::{
+    outputs::OutputUpdate,
+    truncate::TruncatedOutput,
     wire_protocol::{
-        ClearOutput, DisplayData, ErrorReply, ExecuteRequest, ExecuteResult, KernelInfoReply,
-        KernelInfoRequest, KernelMessage, KernelMessageType, KernelStatus, Reply, Status, Stream,
+        ClearOutput, DisplayData, ErrorReply, ExecuteInput, ExecuteReply, ExecuteRequest,
+        ExecuteResult, HistoryEntry, HistoryReply, HistoryRequest, InputRequest, InterruptReply,
+        InterruptRequest, IopubSubscription, IsCompleteReply, IsCompleteRequest, KernelInfoReply,
+        KernelInfoRequest, KernelMessage, KernelMessageType, KernelStatus, OverflowStrategy,
+        PendingRequest, Reply, Status, Stream, Subshell,
     },
     KernelConnection,
 };
 use crate::Error;
 
 /// Get information through the KernelInfo command.
+///
+/// This also negotiates the protocol version used with the kernel, and records
+/// the optional features it supports.
 pub async fn kernel_info(conn: &KernelConnection) -> Result<KernelInfoReply, Error> {
     let mut req = conn
         .call_shell(KernelMessage::new(
@@ -22,7 +35,57 @@ pub async fn kernel_info(conn: &KernelConnection) -> Result<KernelInfoReply, Err
         .await?;
     let msg = req.get_reply::<KernelInfoReply>().await?;
     match msg.content {
-        Reply::Ok(info) => Ok(info),
+        Reply::Ok(info) => {
+            conn.negotiate_version(&info.protocol_version);
+            conn.set_supported_features(&info.supported_features);
+            Ok(info)
+        }
+        Reply::Error(_) | Reply::Abort => Err(Error::KernelDisconnect),
+    }
+}
+
+/// Interrupt the kernel by sending a request on the control channel.
+pub async fn interrupt(conn: &KernelConnection) -> Result<(), Error> {
+    let mut req = conn
+        .call_control(KernelMessage::new(
+            KernelMessageType::InterruptRequest,
+            InterruptRequest {},
+        ))
+        .await?;
+    let msg = req.get_reply::<InterruptReply>().await?;
+    match msg.content {
+        Reply::Ok(_) => Ok(()),
+        Reply::Error(_) | Reply::Abort => Err(Error::KernelDisconnect),
+    }
+}
+
+/// Ask the kernel whether code is complete, or if it needs more lines before
+/// it can be executed.
+pub async fn is_complete(conn: &KernelConnection, code: &str) -> Result<IsCompleteReply, Error> {
+    let mut req = conn
+        .call_shell(KernelMessage::new(
+            KernelMessageType::IsCompleteRequest,
+            IsCompleteRequest { code: code.into() },
+        ))
+        .await?;
+    let msg = req.get_raw_reply::<IsCompleteReply>().await?;
+    Ok(msg.content)
+}
+
+/// Get entries from the kernel's input history.
+pub async fn history(
+    conn: &KernelConnection,
+    request: HistoryRequest,
+) -> Result<Vec<HistoryEntry>, Error> {
+    let mut req = conn
+        .call_shell(KernelMessage::new(
+            KernelMessageType::HistoryRequest,
+            request,
+        ))
+        .await?;
+    let msg = req.get_reply::<HistoryReply>().await?;
+    match msg.content {
+        Reply::Ok(reply) => Ok(reply.history),
         Reply::Error(_) | Reply::Abort => Err(Error::KernelDisconnect),
     }
 }
@@ -46,7 +109,9 @@ pub enum RunCellEvent {
     /// Update previously-displayed data with a display ID.
     UpdateDisplayData(DisplayData),
 
-    /// Clear the output of a cell.
+    /// Clear the output of a cell right away. When the kernel asks to wait,
+    /// this is held until the next output and sent right before it, so
+    /// `wait` is always false.
     ClearOutput(ClearOutput),
 
     /// Error if the cell raised an exception.
@@ -54,87 +119,556 @@ pub enum RunCellEvent {
 
     /// Special message indicating the kernel disconnected.
     Disconnect(String),
+
+    /// The kernel skipped the cell without running it, because an earlier
+    /// request in its queue raised an error.
+    Aborted,
+
+    /// Number of outputs that were dropped because the receiver fell behind,
+    /// with [`OverflowStrategy::Drop`].
+    OutputDropped(#[ts(type = "number")] u64),
+
+    /// The kernel asked for a line of input, like from `input()`. Reply with
+    /// [`KernelConnection::reply_input`].
+    InputRequest(InputPrompt),
+
+    /// The kernel asked for a password, like from `getpass.getpass()`. The
+    /// frontend should mask it as it is typed.
+    PasswordRequest(InputPrompt),
+
+    /// A display output in any cell that was changed by an update to its
+    /// display ID. The app sends this instead of `UpdateDisplayData` when it
+    /// knows which cell is running. See
+    /// [`OutputStore`](super::outputs::OutputStore).
+    UpdateOutput(OutputUpdate),
+
+    /// The kernel started running the cell. This is sent once the kernel
+    /// echoes the code, or before the first output for silent runs, which
+    /// have no execution count.
+    Started {
+        /// The execution count of the cell.
+        execution_count: Option<i32>,
+
+        /// When the kernel became busy, by its own clock, as a Unix time in
+        /// milliseconds.
+        #[ts(type = "number")]
+        timestamp: i64,
+    },
+
+    /// The execution count assigned to the cell, for its `In [n]` label. This
+    /// is sent when the kernel echoes the code, or else from its reply, so
+    /// cells without a result have it too.
+    ExecutionCount(i32),
+
+    /// The kernel finished the cell, sent after all of its other events.
+    Finished {
+        /// Wall time from when the kernel became busy until it was idle
+        /// again, in milliseconds.
+        #[ts(type = "number")]
+        duration: u64,
+
+        /// How the cell finished, from the kernel's reply.
+        status: ExecuteStatus,
+    },
+
+    /// The output before this was cut short, because it passed the limit on
+    /// output size. See [`OutputTruncator`](super::truncate::OutputTruncator).
+    OutputTruncated(TruncatedOutput),
+
+    /// An iopub message that Jute doesn't handle itself, like a comm message
+    /// for a widget or a progress event from an extension, passed through for
+    /// the frontend.
+    Custom {
+        /// The type of the message.
+        msg_type: KernelMessageType,
+
+        /// Content of the message.
+        content: Value,
+
+        /// Binary buffers of the message, encoded as base64.
+        buffers: Vec<String>,
+    },
+}
+
+crate::export_bindings!(RunCellEvent);
+
+/// How a cell finished, in [`RunCellEvent::Finished`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteStatus {
+    /// The cell ran without an error.
+    Ok,
+
+    /// The cell raised an error, or no reply was received.
+    Error,
+
+    /// The kernel skipped the cell without running it.
+    Aborted,
+}
+
+crate::export_bindings!(ExecuteStatus);
+
+/// A request from the kernel for input while running a cell.
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct InputPrompt {
+    /// ID of the request, to send with the reply.
+    pub request_id: String,
+
+    /// Text to show the user.
+    pub prompt: String,
+}
+
+crate::export_bindings!(InputPrompt);
+
+/// A code cell to run as part of a batch.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct BatchCell {
+    /// ID of the cell, used to track its outputs and place in the queue.
+    pub cell_id: String,
+
+    /// Source code of the cell.
+    pub source: String,
+}
+
+crate::export_bindings!(BatchCell);
+
+/// Cells to run as a batch, either given directly or read from a notebook.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum CellBatch {
+    /// The code cells of a notebook on disk, in order. Cells without an ID
+    /// are identified by their index in the notebook.
+    Path(String),
+
+    /// A list of cells, such as those above or below a cell.
+    Cells(Vec<BatchCell>),
+}
+
+crate::export_bindings!(CellBatch);
+
+/// An event from running one of a batch of cells.
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct BatchEvent {
+    /// Index of the cell in the batch.
+    pub cell_index: usize,
+
+    /// The event from running the cell.
+    #[serde(flatten)]
+    pub event: RunCellEvent,
+}
+
+crate::export_bindings!(BatchEvent);
+
+/// Options for running a cell.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct RunCellOptions {
+    /// What to do when output arrives faster than it is received, once the
+    /// connection's queue of iopub messages for the cell is full.
+    pub overflow: OverflowStrategy,
+
+    /// Run the code in the background, for helper code rather than a cell of
+    /// the notebook. The kernel doesn't count it or store it in its history,
+    /// and doesn't send its result.
+    pub silent: bool,
+
+    /// Let the kernel ask for input while the cell runs. Otherwise, reading
+    /// input raises an error in the kernel. Only the app sets this, since
+    /// something has to reply to the requests.
+    #[serde(skip)]
+    #[ts(skip)]
+    pub allow_stdin: bool,
+}
+
+crate::export_bindings!(RunCellOptions);
+
+/// Number of events from a running cell that can be queued for the receiver.
+/// Once it is full, the cell's [`OverflowStrategy`] applies to its iopub
+/// messages.
+const EVENT_CAPACITY: usize = 16;
+
+/// Sends events from a running cell.
+struct EventSender {
+    tx: Sender<RunCellEvent>,
+
+    /// Whether the kernel asked to clear output once the next output arrives.
+    clear_pending: bool,
+}
+
+impl EventSender {
+    /// Send an event, waiting for room if the channel is full.
+    async fn send(&mut self, event: RunCellEvent) {
+        // It's not an error if the receiver was dropped meanwhile.
+        _ = self.tx.send(event).await;
+    }
+
+    /// Send an output, first clearing the previous outputs if the kernel asked
+    /// to wait for it.
+    async fn send_output(&mut self, event: RunCellEvent) {
+        self.apply_pending_clear().await;
+        self.send(event).await;
+    }
+
+    /// Send a clear that was held until the next output, if there is one.
+    async fn apply_pending_clear(&mut self) {
+        if self.clear_pending {
+            self.clear_pending = false;
+            self.send(RunCellEvent::ClearOutput(ClearOutput { wait: false }))
+                .await;
+        }
+    }
+
+    /// Clear output, or with `wait`, hold the clear until the next output.
+    ///
+    /// Holding it here means clears are only sent right before the output
+    /// that replaces them, so progress bars that redraw this way don't
+    /// flicker.
+    async fn clear_output(&mut self, wait: bool) {
+        self.clear_pending = wait;
+        if !wait {
+            self.send(RunCellEvent::ClearOutput(ClearOutput { wait }))
+                .await;
+        }
+    }
 }
 
 /// Run a code cell, returning the events received in the meantime.
+///
+/// Events are sent on a bounded channel, so a cell with a lot of output can't
+/// use unbounded memory if the receiver is slow. See [`RunCellOptions`].
 pub async fn run_cell(
     conn: &KernelConnection,
     code: &str,
+    options: RunCellOptions,
 ) -> Result<async_channel::Receiver<RunCellEvent>, Error> {
-    // Clear out existing iopub messages before running the cell, in case there are
-    // any lingering messages from previous runs.
-    while conn.try_recv_iopub().is_some() {}
+    let msg = execute_request(code, !options.silent, &options);
+    // Only receive iopub messages from this cell, not from other cells running
+    // at the same time or other clients of the kernel.
+    let iopub = conn.subscribe_iopub(&msg.header.msg_id, options.overflow);
+    let req = conn.call_shell(msg).await?;
+    Ok(spawn_events(iopub, req, options))
+}
+
+/// Run code in a subshell, which can happen while a cell is running in the
+/// main shell, returning events like [`run_cell`].
+///
+/// The code is not stored in the kernel's history, since it is not a cell of
+/// the notebook.
+pub async fn run_in_subshell(
+    subshell: &Subshell,
+    code: &str,
+    options: RunCellOptions,
+) -> Result<async_channel::Receiver<RunCellEvent>, Error> {
+    let msg = execute_request(code, false, &options);
+    let iopub = subshell.subscribe_iopub(&msg.header.msg_id, options.overflow);
+    let req = subshell.call_shell(msg).await?;
+    Ok(spawn_events(iopub, req, options))
+}
 
-    conn.call_shell(KernelMessage::new(
+/// Make a request to execute code.
+fn execute_request(
+    code: &str,
+    store_history: bool,
+    options: &RunCellOptions,
+) -> KernelMessage<ExecuteRequest> {
+    KernelMessage::new(
         KernelMessageType::ExecuteRequest,
         ExecuteRequest {
             code: code.into(),
-            silent: false,
-            store_history: true,
+            silent: options.silent,
+            store_history,
             user_expressions: Default::default(),
-            allow_stdin: false,
+            allow_stdin: options.allow_stdin,
             stop_on_error: true,
         },
-    ))
-    .await?;
-
-    let (tx, rx) = async_channel::unbounded();
-    let conn = conn.clone();
-
-    let tx2 = tx.clone();
-    let stream_results_fut = async move {
-        let mut status = KernelStatus::Busy;
-
-        while status != KernelStatus::Idle {
-            let msg = conn.recv_iopub().await?;
-            match msg.header.msg_type {
-                KernelMessageType::Status => {
-                    let msg = msg.into_typed::<Status>()?;
-                    status = msg.content.execution_state;
-                }
-                KernelMessageType::Stream => {
-                    let msg = msg.into_typed::<Stream>()?;
-                    if msg.content.name == "stdout" {
-                        _ = tx.send(RunCellEvent::Stdout(msg.content.text)).await;
-                    } else {
-                        _ = tx.send(RunCellEvent::Stderr(msg.content.text)).await;
-                    }
-                }
-                // We ignore ExecuteInput messages since they just echo the input code.
-                KernelMessageType::ExecuteInput => {}
-                KernelMessageType::ExecuteResult => {
-                    let msg = msg.into_typed::<ExecuteResult>()?;
-                    _ = tx.send(RunCellEvent::ExecuteResult(msg.content)).await;
-                }
-                KernelMessageType::DisplayData => {
-                    let msg = msg.into_typed::<DisplayData>()?;
-                    _ = tx.send(RunCellEvent::DisplayData(msg.content)).await;
-                }
-                KernelMessageType::UpdateDisplayData => {
-                    let msg = msg.into_typed::<DisplayData>()?;
-                    _ = tx.send(RunCellEvent::UpdateDisplayData(msg.content)).await;
-                }
-                KernelMessageType::ClearOutput => {
-                    let msg = msg.into_typed::<ClearOutput>()?;
-                    _ = tx.send(RunCellEvent::ClearOutput(msg.content)).await;
-                }
-                KernelMessageType::Error => {
-                    let msg = msg.into_typed::<ErrorReply>()?;
-                    _ = tx.send(RunCellEvent::Error(msg.content)).await;
-                }
-                _ => {}
-            }
-        }
+    )
+}
 
-        Ok::<_, Error>(())
+/// Spawn a task sending events from an execute request on a new channel.
+fn spawn_events(
+    iopub: IopubSubscription,
+    mut req: PendingRequest,
+    options: RunCellOptions,
+) -> async_channel::Receiver<RunCellEvent> {
+    let (tx, rx) = async_channel::bounded(EVENT_CAPACITY);
+
+    let mut tx = EventSender {
+        tx,
+        clear_pending: false,
     };
 
     tokio::spawn(async move {
+        let mut clock = ExecutionClock::default();
         // Translate any errors into a disconnect message.
-        if let Err(err) = stream_results_fut.await {
-            _ = tx2.send(RunCellEvent::Disconnect(err.to_string())).await;
+        match stream_results(&iopub, &mut tx, &mut clock).await {
+            Ok(()) => {
+                let reply = req.get_reply::<ExecuteReply>().await;
+                let status = match reply.map(|reply| reply.content) {
+                    Ok(Reply::Ok(reply)) => {
+                        // Silent runs are not counted, so the count is stale.
+                        if !clock.counted && !options.silent {
+                            let count = reply.execution_count;
+                            tx.send(RunCellEvent::ExecutionCount(count)).await;
+                        }
+                        ExecuteStatus::Ok
+                    }
+                    Ok(Reply::Error(_)) | Err(_) => ExecuteStatus::Error,
+                    Ok(Reply::Abort) => {
+                        // Aborted cells have no error on IOPub, only in their reply.
+                        tx.send(RunCellEvent::Aborted).await;
+                        ExecuteStatus::Aborted
+                    }
+                };
+                let duration = clock.duration.map_or(0, |duration| {
+                    u64::try_from(duration.whole_milliseconds()).unwrap_or(0)
+                });
+                tx.send(RunCellEvent::Finished { duration, status }).await;
+            }
+            Err(err) => tx.send(RunCellEvent::Disconnect(err.to_string())).await,
         }
     });
 
-    Ok(rx)
+    rx
+}
+
+/// Times a cell from the dates of the kernel's busy and idle statuses, and
+/// tracks which events about its progress were sent.
+#[derive(Default)]
+struct ExecutionClock {
+    busy_at: Option<OffsetDateTime>,
+    started: bool,
+    counted: bool,
+    duration: Option<Duration>,
+}
+
+impl ExecutionClock {
+    /// Send [`RunCellEvent::Started`], if it wasn't sent yet.
+    async fn start(&mut self, tx: &mut EventSender, execution_count: Option<i32>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        let busy_at = self.busy_at.unwrap_or_else(OffsetDateTime::now_utc);
+        let timestamp = (busy_at.unix_timestamp_nanos() / 1_000_000) as i64;
+        tx.send(RunCellEvent::Started {
+            execution_count,
+            timestamp,
+        })
+        .await;
+    }
+}
+
+/// Forward IOPub messages from running a cell as events, until the kernel is
+/// idle again.
+async fn stream_results(
+    iopub: &IopubSubscription,
+    tx: &mut EventSender,
+    clock: &mut ExecutionClock,
+) -> Result<(), Error> {
+    let mut status = KernelStatus::Busy;
+
+    while status != KernelStatus::Idle {
+        let msg = iopub.recv().await?;
+        if !matches!(
+            msg.header.msg_type,
+            KernelMessageType::Status | KernelMessageType::ExecuteInput
+        ) {
+            clock.start(tx, None).await;
+        }
+        match msg.header.msg_type {
+            KernelMessageType::Status => {
+                let date = msg.header.date;
+                let msg = msg.into_typed::<Status>()?;
+                status = msg.content.execution_state;
+                match status {
+                    KernelStatus::Busy => _ = clock.busy_at.get_or_insert(date),
+                    KernelStatus::Idle => {
+                        clock.start(tx, None).await;
+                        clock.duration = clock.busy_at.map(|busy_at| date - busy_at);
+                    }
+                    _ => {}
+                }
+            }
+            KernelMessageType::Stream => {
+                let msg = msg.into_typed::<Stream>()?;
+                if msg.content.name == "stdout" {
+                    tx.send_output(RunCellEvent::Stdout(msg.content.text)).await;
+                } else {
+                    tx.send_output(RunCellEvent::Stderr(msg.content.text)).await;
+                }
+            }
+            // The input is just an echo of the code, but it has the count.
+            KernelMessageType::ExecuteInput => {
+                let count = msg.into_typed::<ExecuteInput>()?.content.execution_count;
+                clock.start(tx, Some(count)).await;
+                clock.counted = true;
+                tx.send(RunCellEvent::ExecutionCount(count)).await;
+            }
+            KernelMessageType::ExecuteResult => {
+                let mut msg = msg.into_typed::<ExecuteResult>()?;
+                msg.content.message_metadata = msg.metadata;
+                msg.content.buffers = encode_buffers(&msg.buffers);
+                tx.send_output(RunCellEvent::ExecuteResult(msg.content))
+                    .await;
+            }
+            KernelMessageType::DisplayData => {
+                let mut msg = msg.into_typed::<DisplayData>()?;
+                msg.content.message_metadata = msg.metadata;
+                msg.content.buffers = encode_buffers(&msg.buffers);
+                tx.send_output(RunCellEvent::DisplayData(msg.content)).await;
+            }
+            KernelMessageType::UpdateDisplayData => {
+                let mut msg = msg.into_typed::<DisplayData>()?;
+                msg.content.message_metadata = msg.metadata;
+                msg.content.buffers = encode_buffers(&msg.buffers);
+                tx.send(RunCellEvent::UpdateDisplayData(msg.content)).await;
+            }
+            KernelMessageType::ClearOutput => {
+                let msg = msg.into_typed::<ClearOutput>()?;
+                tx.clear_output(msg.content.wait).await;
+            }
+            KernelMessageType::Error => {
+                let msg = msg.into_typed::<ErrorReply>()?;
+                tx.apply_pending_clear().await;
+                tx.send(RunCellEvent::Error(msg.content)).await;
+            }
+            KernelMessageType::InputRequest => {
+                let msg = msg.into_typed::<InputRequest>()?;
+                let prompt = InputPrompt {
+                    request_id: msg.header.msg_id,
+                    prompt: msg.content.prompt,
+                };
+                let event = match msg.content.password {
+                    true => RunCellEvent::PasswordRequest(prompt),
+                    false => RunCellEvent::InputRequest(prompt),
+                };
+                tx.send(event).await;
+            }
+            KernelMessageType::CommOpen
+            | KernelMessageType::CommMsg
+            | KernelMessageType::CommClose
+            | KernelMessageType::Other(_) => {
+                tx.send(RunCellEvent::Custom {
+                    buffers: encode_buffers(&msg.buffers),
+                    msg_type: msg.header.msg_type,
+                    content: msg.content,
+                })
+                .await;
+            }
+            _ => {}
+        }
+
+        let dropped = iopub.take_dropped();
+        if dropped > 0 {
+            tx.send(RunCellEvent::OutputDropped(dropped)).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encode the binary buffers of a message as base64, to send them to the
+/// frontend as JSON.
+fn encode_buffers(buffers: &[Bytes]) -> Vec<String> {
+    buffers
+        .iter()
+        .map(|buf| BASE64_STANDARD.encode(buf))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn silent_options() {
+        let options: RunCellOptions = serde_json::from_str(r#"{ "silent": true }"#).unwrap();
+        assert_eq!(options.overflow, OverflowStrategy::Pause);
+        let msg = execute_request("x", !options.silent, &options);
+        assert!(msg.content.silent);
+        assert!(!msg.content.store_history);
+    }
+
+    #[test]
+    fn custom_event() {
+        let event = RunCellEvent::Custom {
+            msg_type: KernelMessageType::Other("progress".into()),
+            content: json!({ "value": 0.5 }),
+            buffers: vec![],
+        };
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            json!({
+                "event": "custom",
+                "data": {
+                    "msg_type": "progress",
+                    "content": { "value": 0.5 },
+                    "buffers": [],
+                },
+            })
+        );
+
+        let event = RunCellEvent::Custom {
+            msg_type: KernelMessageType::CommMsg,
+            content: json!({ "comm_id": "c1", "data": {} }),
+            buffers: encode_buffers(&[Bytes::from_static(b"\x01\x02")]),
+        };
+        let value = serde_json::to_value(event).unwrap();
+        assert_eq!(value["data"]["msg_type"], "comm_msg");
+        assert_eq!(value["data"]["buffers"], json!(["AQI="]));
+    }
+
+    #[tokio::test]
+    async fn hold_clear_until_next_output() {
+        let (tx, rx) = async_channel::unbounded();
+        let mut tx = EventSender {
+            tx,
+            clear_pending: false,
+        };
+        tx.send_output(RunCellEvent::Stdout("10%".into())).await;
+        tx.clear_output(true).await;
+        assert_eq!(rx.len(), 1);
+
+        // The clear is sent right before the output that replaces it.
+        tx.send_output(RunCellEvent::Stdout("20%".into())).await;
+        rx.recv().await.unwrap();
+        let Ok(RunCellEvent::ClearOutput(ClearOutput { wait: false })) = rx.recv().await else {
+            panic!("expected a clear before the next output");
+        };
+        let Ok(RunCellEvent::Stdout(text)) = rx.recv().await else {
+            panic!("expected the next output after the clear");
+        };
+        assert_eq!(text, "20%");
+
+        // A clear that is never followed by output is not sent.
+        tx.clear_output(true).await;
+        assert!(rx.is_empty());
+    }
+
+    #[tokio::test]
+    async fn start_once_from_busy_date() {
+        let (tx, rx) = async_channel::unbounded();
+        let mut tx = EventSender {
+            tx,
+            clear_pending: false,
+        };
+        let mut clock = ExecutionClock {
+            busy_at: Some(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()),
+            ..Default::default()
+        };
+        clock.start(&mut tx, Some(3)).await;
+        clock.start(&mut tx, None).await;
+        assert_eq!(rx.len(), 1);
+        let Ok(RunCellEvent::Started {
+            execution_count,
+            timestamp,
+        }) = rx.recv().await
+        else {
+            panic!("expected a started event");
+        };
+        assert_eq!(execution_count, Some(3));
+        assert_eq!(timestamp, 1_700_000_000_000);
+    }
 }
@@ -0,0 +1,733 @@
+//! Structural diffs between two versions of a notebook, cell by cell.
+//!
+//! Line diffs of `.ipynb` files are hard to read, since they are full of JSON
+//! syntax and base64 images. Instead, cells are matched between the versions
+//! and compared by their source and outputs. Cells are matched by ID when
+//! every cell has one, as in nbformat 4.5. Otherwise, such as for notebooks
+//! read from text formats, they are matched by the longest common subsequence
+//! of their sources, and cells left over between matches are paired in order.
+//!
+//! Matched cells that are out of order are marked as moved, and changed
+//! sources are compared line by line into hunks, like a unified diff. Both
+//! comparisons use Myers' algorithm in linear space, so that large cells with
+//! few changes are cheap to compare.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use super::notebook::{Cell, NotebookRoot, Output};
+
+/// Number of unchanged lines shown around changes in a [`DiffHunk`].
+const CONTEXT_LINES: usize = 3;
+
+/// How a cell changed between two versions of a notebook.
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum CellChange {
+    /// The cell is only in the new version.
+    Added,
+
+    /// The cell is only in the old version.
+    Removed,
+
+    /// The cell is in both versions, with a different source or outputs.
+    Modified,
+
+    /// The cell is the same in both versions.
+    Unchanged,
+}
+
+crate::export_bindings!(CellChange);
+
+/// Difference in one cell between two versions of a notebook.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CellDiff {
+    /// Identifier of the cell, from the new version if it is in both.
+    pub id: Option<String>,
+
+    /// Type of the cell, like `"code"` or `"markdown"`.
+    pub cell_type: String,
+
+    /// How the cell changed.
+    pub change: CellChange,
+
+    /// Source of the cell in the old version, if it is in it.
+    pub old_source: Option<String>,
+
+    /// Source of the cell in the new version, if it is in it.
+    pub new_source: Option<String>,
+
+    /// Whether the outputs of a code cell changed, ignoring execution counts.
+    pub outputs_changed: bool,
+
+    /// Whether the cell is in both versions, but moved relative to the cells
+    /// around it. Moves are only found between cells matched by ID.
+    pub moved: bool,
+
+    /// Changed lines of the source, if it is in both versions.
+    pub hunks: Vec<DiffHunk>,
+
+    /// Changed outputs of a code cell, compared by position.
+    pub output_changes: Vec<OutputDiff>,
+}
+
+crate::export_bindings!(CellDiff);
+
+/// Kind of a line in a [`DiffHunk`].
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum LineChange {
+    /// The line is in both versions, shown around changes.
+    Context,
+
+    /// The line is only in the new version.
+    Added,
+
+    /// The line is only in the old version.
+    Removed,
+}
+
+crate::export_bindings!(LineChange);
+
+/// A line in a [`DiffHunk`].
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct DiffLine {
+    /// Whether the line was added, removed, or is context.
+    pub change: LineChange,
+
+    /// Text of the line, without its newline.
+    pub text: String,
+}
+
+crate::export_bindings!(DiffLine);
+
+/// Changed lines in the source of a cell, with a few unchanged lines around
+/// them, like a hunk of a unified diff.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct DiffHunk {
+    /// First line of the hunk in the old source, counting from 1.
+    pub old_start: usize,
+
+    /// Number of lines of the old source in the hunk.
+    pub old_lines: usize,
+
+    /// First line of the hunk in the new source, counting from 1.
+    pub new_start: usize,
+
+    /// Number of lines of the new source in the hunk.
+    pub new_lines: usize,
+
+    /// Lines of the hunk, in order.
+    pub lines: Vec<DiffLine>,
+}
+
+crate::export_bindings!(DiffHunk);
+
+/// Difference in one output of a code cell.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct OutputDiff {
+    /// Index of the output in the cell.
+    pub index: usize,
+
+    /// Type of the output, from the new version if it is in both.
+    pub output_type: String,
+
+    /// How the output changed, which is never [`CellChange::Unchanged`].
+    pub change: CellChange,
+}
+
+crate::export_bindings!(OutputDiff);
+
+/// Cell-by-cell difference between two versions of a notebook.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct NotebookDiff {
+    /// Cells of both versions, in the order of the new version, with removed
+    /// cells placed where they were in the old version.
+    pub cells: Vec<CellDiff>,
+}
+
+crate::export_bindings!(NotebookDiff);
+
+impl NotebookDiff {
+    /// Whether any cell changed or moved.
+    pub fn has_changes(&self) -> bool {
+        self.cells
+            .iter()
+            .any(|cell| cell.change != CellChange::Unchanged || cell.moved)
+    }
+}
+
+/// Compute the difference between two versions of a notebook.
+pub fn diff_notebooks(old: &NotebookRoot, new: &NotebookRoot) -> NotebookDiff {
+    diff_cells(&old.cells, &new.cells)
+}
+
+/// Compute the difference between two lists of cells.
+pub fn diff_cells(old: &[Cell], new: &[Cell]) -> NotebookDiff {
+    let all_ids = old.iter().chain(new).all(|cell| cell.id().is_some());
+    let pairs = if all_ids {
+        match_by_id(old, new)
+    } else {
+        match_by_source(old, new)
+    };
+    let moved = moved_cells(&pairs);
+    let cells = pairs
+        .into_iter()
+        .zip(moved)
+        .map(|(pair, moved)| match pair {
+            (Some(i), Some(j)) => {
+                let (old_cell, new_cell) = (&old[i], &new[j]);
+                let output_changes = match (old_cell, new_cell) {
+                    (Cell::Code(a), Cell::Code(b)) => diff_outputs(&a.outputs, &b.outputs),
+                    (Cell::Code(a), _) => diff_outputs(&a.outputs, &[]),
+                    (_, Cell::Code(b)) => diff_outputs(&[], &b.outputs),
+                    _ => Vec::new(),
+                };
+                let outputs_changed = !output_changes.is_empty();
+                let (old_source, new_source) = (old_cell.source(), new_cell.source());
+                let unchanged = old_source == new_source
+                    && !outputs_changed
+                    && old_cell.cell_type() == new_cell.cell_type();
+                CellDiff {
+                    id: new_cell.id().map(String::from),
+                    cell_type: new_cell.cell_type().into(),
+                    change: if unchanged {
+                        CellChange::Unchanged
+                    } else {
+                        CellChange::Modified
+                    },
+                    hunks: match old_source == new_source {
+                        true => Vec::new(),
+                        false => diff_lines(&old_source, &new_source),
+                    },
+                    old_source: Some(old_source),
+                    new_source: Some(new_source),
+                    outputs_changed,
+                    moved,
+                    output_changes,
+                }
+            }
+            (Some(i), None) => CellDiff {
+                id: old[i].id().map(String::from),
+                cell_type: old[i].cell_type().into(),
+                change: CellChange::Removed,
+                old_source: Some(old[i].source()),
+                new_source: None,
+                outputs_changed: false,
+                moved: false,
+                hunks: Vec::new(),
+                output_changes: Vec::new(),
+            },
+            (None, Some(j)) => CellDiff {
+                id: new[j].id().map(String::from),
+                cell_type: new[j].cell_type().into(),
+                change: CellChange::Added,
+                old_source: None,
+                new_source: Some(new[j].source()),
+                outputs_changed: false,
+                moved: false,
+                hunks: Vec::new(),
+                output_changes: Vec::new(),
+            },
+            (None, None) => unreachable!("pairs have at least one cell"),
+        })
+        .collect();
+    NotebookDiff { cells }
+}
+
+/// Pair cells with the same ID, as indices into the old and new cells.
+fn match_by_id(old: &[Cell], new: &[Cell]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut old_index = HashMap::new();
+    for (i, cell) in old.iter().enumerate() {
+        if let Some(id) = cell.id() {
+            old_index.entry(id).or_insert(i);
+        }
+    }
+    let matches: Vec<Option<usize>> = (new.iter())
+        .map(|cell| cell.id().and_then(|id| old_index.get(id).copied()))
+        .collect();
+    let mut kept = vec![false; old.len()];
+    for &i in matches.iter().flatten() {
+        kept[i] = true;
+    }
+
+    let mut pairs = Vec::new();
+    let mut next_old = 0;
+    for (j, matched) in matches.into_iter().enumerate() {
+        if let Some(i) = matched {
+            // Removed cells go before the next cell that was after them.
+            while next_old < i {
+                if !kept[next_old] {
+                    pairs.push((Some(next_old), None));
+                }
+                next_old += 1;
+            }
+            next_old = next_old.max(i + 1);
+        }
+        pairs.push((matched, Some(j)));
+    }
+    let removed = (next_old..old.len()).filter(|&i| !kept[i]);
+    pairs.extend(removed.map(|i| (Some(i), None)));
+    pairs
+}
+
+/// Pair cells by the longest common subsequence of their sources, then pair
+/// the cells left between matches in order.
+pub(crate) fn match_by_source(old: &[Cell], new: &[Cell]) -> Vec<(Option<usize>, Option<usize>)> {
+    let old_sources: Vec<String> = old.iter().map(Cell::source).collect();
+    let new_sources: Vec<String> = new.iter().map(Cell::source).collect();
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_old, mut gap_new) = (Vec::new(), Vec::new());
+    for edit in diff_sequences(&old_sources, &new_sources) {
+        match edit {
+            LineChange::Context => {
+                pair_gap(&mut pairs, &mut gap_old, &mut gap_new);
+                pairs.push((Some(i), Some(j)));
+                (i, j) = (i + 1, j + 1);
+            }
+            LineChange::Removed => {
+                gap_old.push(i);
+                i += 1;
+            }
+            LineChange::Added => {
+                gap_new.push(j);
+                j += 1;
+            }
+        }
+    }
+    pair_gap(&mut pairs, &mut gap_old, &mut gap_new);
+    pairs
+}
+
+/// Pair up unmatched cells between two matches in order, as modified cells,
+/// with any extra cells added or removed.
+fn pair_gap(
+    pairs: &mut Vec<(Option<usize>, Option<usize>)>,
+    gap_old: &mut Vec<usize>,
+    gap_new: &mut Vec<usize>,
+) {
+    for k in 0..gap_old.len().max(gap_new.len()) {
+        pairs.push((gap_old.get(k).copied(), gap_new.get(k).copied()));
+    }
+    gap_old.clear();
+    gap_new.clear();
+}
+
+/// Find the shortest edit script from `old` to `new`, as a list of items
+/// kept ([`LineChange::Context`]), removed, or added, in order.
+///
+/// This is Myers' algorithm in linear space: the middle snake of an optimal
+/// path splits the sequences in two, which are compared recursively. It takes
+/// `O((n + m) d)` time for `d` edits.
+pub(crate) fn diff_sequences<T: PartialEq>(old: &[T], new: &[T]) -> Vec<LineChange> {
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, &mut edits);
+    edits
+}
+
+fn diff_range<T: PartialEq>(old: &[T], new: &[T], edits: &mut Vec<LineChange>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = (old.iter().rev().zip(new.iter().rev()))
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    edits.resize(edits.len() + prefix, LineChange::Context);
+    if old.is_empty() {
+        edits.resize(edits.len() + new.len(), LineChange::Added);
+    } else if new.is_empty() {
+        edits.resize(edits.len() + old.len(), LineChange::Removed);
+    } else {
+        // The ends differ, so there are at least two edits, and each half has
+        // fewer than the whole.
+        let (x0, y0, x1, y1) = middle_snake(old, new);
+        diff_range(&old[..x0], &new[..y0], edits);
+        edits.resize(edits.len() + x1 - x0, LineChange::Context);
+        diff_range(&old[x1..], &new[y1..], edits);
+    }
+    edits.resize(edits.len() + suffix, LineChange::Context);
+}
+
+/// Find the middle snake of a shortest edit path, the diagonal run of equal
+/// items where the paths searched from both ends meet, as its start and end
+/// positions in `old` and `new`.
+fn middle_snake<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize, usize, usize) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+
+    // Furthest `x` reached on each diagonal `k = x - y`, from the start and
+    // from the end, where the backward search runs on reversed sequences.
+    let offset = max + 1;
+    let mut forward = vec![0; (2 * max + 3) as usize];
+    let mut backward = vec![0; (2 * max + 3) as usize];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            forward[at(k)] = x;
+            let back_k = delta - k;
+            if odd && back_k.abs() < d && x + backward[at(back_k)] >= n {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && old[(n - 1 - x) as usize] == new[(m - 1 - y) as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            backward[at(k)] = x;
+            let forward_k = delta - k;
+            if !odd && forward_k.abs() <= d && x + forward[at(forward_k)] >= n {
+                let (x, y, x0, y0) = (n - x, m - y, n - x0, m - y0);
+                return (x as usize, y as usize, x0 as usize, y0 as usize);
+            }
+        }
+    }
+    unreachable!("paths from both ends meet within (n + m) / 2 steps")
+}
+
+/// Find the matched cells that moved, which are those outside the longest
+/// sequence of matches that stayed in the same order.
+fn moved_cells(pairs: &[(Option<usize>, Option<usize>)]) -> Vec<bool> {
+    let matched: Vec<(usize, usize)> = (pairs.iter().enumerate())
+        .filter_map(|(k, pair)| match pair {
+            (Some(i), Some(_)) => Some((k, *i)),
+            _ => None,
+        })
+        .collect();
+
+    // Longest increasing subsequence of the old indices, in new order, found
+    // by patience sorting. It is built from the end, keeping the head with the
+    // largest index for each length, so that earlier cells stay in place when
+    // there is a tie.
+    let mut heads: Vec<usize> = Vec::new();
+    let mut next = vec![None; matched.len()];
+    for m in (0..matched.len()).rev() {
+        let index = matched[m].1;
+        let length = heads.partition_point(|&h| matched[h].1 > index);
+        if length > 0 {
+            next[m] = Some(heads[length - 1]);
+        }
+        match heads.get_mut(length) {
+            Some(head) => *head = m,
+            None => heads.push(m),
+        }
+    }
+
+    let mut moved = vec![false; pairs.len()];
+    for &(k, _) in &matched {
+        moved[k] = true;
+    }
+    let mut m = heads.last().copied();
+    while let Some(current) = m {
+        moved[matched[current].0] = false;
+        m = next[current];
+    }
+    moved
+}
+
+/// Compare two sources line by line, returning the hunks of changed lines.
+fn diff_lines(old: &str, new: &str) -> Vec<DiffHunk> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    // Every line of both sources in order, with its position in each.
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for change in diff_sequences(&old_lines, &new_lines) {
+        let text = match change {
+            LineChange::Added => new_lines[j],
+            _ => old_lines[i],
+        };
+        ops.push((change, text, i, j));
+        match change {
+            LineChange::Context => (i, j) = (i + 1, j + 1),
+            LineChange::Removed => i += 1,
+            LineChange::Added => j += 1,
+        }
+    }
+
+    // Group changes with the context around them, merging groups that touch.
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (k, op) in ops.iter().enumerate() {
+        if op.0 == LineChange::Context {
+            continue;
+        }
+        let start = k.saturating_sub(CONTEXT_LINES);
+        let end = (k + CONTEXT_LINES + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let ops = &ops[start..end];
+            let count = |change| ops.iter().filter(|op| op.0 != change).count();
+            DiffHunk {
+                old_start: ops[0].2 + 1,
+                old_lines: count(LineChange::Added),
+                new_start: ops[0].3 + 1,
+                new_lines: count(LineChange::Removed),
+                lines: (ops.iter())
+                    .map(|&(change, text, _, _)| DiffLine {
+                        change,
+                        text: text.into(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Compare the outputs of a code cell by position.
+fn diff_outputs(old: &[Output], new: &[Output]) -> Vec<OutputDiff> {
+    (0..old.len().max(new.len()))
+        .filter_map(|index| {
+            let (change, output) = match (old.get(index), new.get(index)) {
+                (Some(a), Some(b)) if a == b => return None,
+                (Some(_), Some(b)) => (CellChange::Modified, b),
+                (Some(a), None) => (CellChange::Removed, a),
+                (None, Some(b)) => (CellChange::Added, b),
+                (None, None) => unreachable!("index is within one of the outputs"),
+            };
+            Some(OutputDiff {
+                index,
+                output_type: output.output_type().into(),
+                change,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::notebook::MultilineString, import::NotebookFormat};
+
+    fn changes(diff: &NotebookDiff) -> Vec<CellChange> {
+        diff.cells.iter().map(|cell| cell.change).collect()
+    }
+
+    #[test]
+    fn diff_without_ids() {
+        let old = NotebookFormat::Percent
+            .read(
+                "# %%
+a = 1
+
+# %%
+b = 2
+
+# %%
+c = 3
+",
+            )
+            .unwrap();
+        let new = NotebookFormat::Percent
+            .read(
+                "# %%
+a = 1
+
+# %%
+b = 20
+
+# %%
+d = 4
+
+# %%
+c = 3
+",
+            )
+            .unwrap();
+        let diff = diff_notebooks(&old, &new);
+        assert_eq!(
+            changes(&diff),
+            [
+                CellChange::Unchanged,
+                CellChange::Modified,
+                CellChange::Added,
+                CellChange::Unchanged
+            ]
+        );
+        assert_eq!(diff.cells[1].old_source.as_deref(), Some("b = 2"));
+        assert!(diff.has_changes());
+        assert!(!diff_notebooks(&old, &old).has_changes());
+    }
+
+    #[test]
+    fn diff_by_id() {
+        let mut old = NotebookFormat::Percent
+            .read(
+                "# %%
+a = 1
+
+# %%
+b = 2
+
+# %%
+c = 3
+",
+            )
+            .unwrap();
+        for (i, cell) in old.cells.iter_mut().enumerate() {
+            if let Cell::Code(cell) = cell {
+                cell.id = Some(format!("c{i}"));
+            }
+        }
+        let mut new = old.clone();
+        new.cells.remove(1);
+        if let Cell::Code(cell) = &mut new.cells[1] {
+            cell.execution_count = Some(1);
+        }
+        let diff = diff_notebooks(&old, &new);
+        assert_eq!(
+            changes(&diff),
+            [
+                CellChange::Unchanged,
+                CellChange::Removed,
+                CellChange::Unchanged
+            ]
+        );
+        assert_eq!(diff.cells[1].id.as_deref(), Some("c1"));
+    }
+
+    #[test]
+    fn diff_moves_and_lines() {
+        let mut old = NotebookFormat::Percent
+            .read(
+                "# %%
+a = 1
+
+# %%
+b = 2
+
+# %%
+for i in range(10):
+    print(i)
+    print(i + 1)
+    print(i + 2)
+    print(i + 3)
+    print(i + 4)
+",
+            )
+            .unwrap();
+        for (i, cell) in old.cells.iter_mut().enumerate() {
+            if let Cell::Code(cell) = cell {
+                cell.id = Some(format!("c{i}"));
+            }
+        }
+        let mut new = old.clone();
+        new.cells.swap(0, 1);
+        if let Cell::Code(cell) = &mut new.cells[2] {
+            let source = String::from(cell.source.clone());
+            cell.source = MultilineString::Single(source.replace("i + 4", "i + 5"));
+        }
+
+        let diff = diff_notebooks(&old, &new);
+        let moved: Vec<_> = diff.cells.iter().map(|cell| cell.moved).collect();
+        assert_eq!(moved, [false, true, false]);
+        assert!(diff.has_changes());
+
+        let hunks = &diff.cells[2].hunks;
+        assert_eq!(hunks.len(), 1);
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines), (3, 4));
+        assert_eq!((hunks[0].new_start, hunks[0].new_lines), (3, 4));
+        let changes: Vec<_> = hunks[0].lines.iter().map(|line| line.change).collect();
+        assert_eq!(
+            changes,
+            [
+                LineChange::Context,
+                LineChange::Context,
+                LineChange::Context,
+                LineChange::Removed,
+                LineChange::Added
+            ]
+        );
+        assert_eq!(hunks[0].lines[4].text, "    print(i + 5)");
+    }
+
+    #[test]
+    fn shortest_edit_scripts() {
+        fn lcs_length(old: &[u8], new: &[u8]) -> usize {
+            let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in (0..old.len()).rev() {
+                for j in (0..new.len()).rev() {
+                    lcs[i][j] = match old[i] == new[j] {
+                        true => lcs[i + 1][j + 1] + 1,
+                        false => lcs[i + 1][j].max(lcs[i][j + 1]),
+                    };
+                }
+            }
+            lcs[0][0]
+        }
+
+        // Short sequences over a small alphabet, from a fixed generator.
+        let mut seed = 7_u32;
+        let mut sequence = |len: u32| -> Vec<u8> {
+            let mut next = || {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                seed >> 16
+            };
+            let len = next() % len;
+            (0..len).map(|_| b'a' + (next() % 4) as u8).collect()
+        };
+        for _ in 0..500 {
+            let (old, new) = (sequence(16), sequence(16));
+            let edits = diff_sequences(&old, &new);
+
+            // The edits rebuild both sequences, keeping as many items as an LCS.
+            let (mut i, mut j, mut rebuilt) = (0, 0, Vec::new());
+            for edit in &edits {
+                match edit {
+                    LineChange::Context => {
+                        assert_eq!(old[i], new[j]);
+                        rebuilt.push(new[j]);
+                        (i, j) = (i + 1, j + 1);
+                    }
+                    LineChange::Removed => i += 1,
+                    LineChange::Added => {
+                        rebuilt.push(new[j]);
+                        j += 1;
+                    }
+                }
+            }
+            assert_eq!((i, j), (old.len(), new.len()));
+            assert_eq!(rebuilt, new);
+            let kept = edits.iter().filter(|e| **e == LineChange::Context).count();
+            assert_eq!(kept, lcs_length(&old, &new), "{old:?} -> {new:?}");
+        }
+
+        // A large cell with one changed line is cheap to compare.
+        let old: String = (0..50_000).map(|i| format!("x = {i}\n")).collect();
+        let new = old.replace("x = 25000\n", "x = -1\n");
+        let hunks = diff_lines(&old, &new);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_start, 24_998);
+    }
+}
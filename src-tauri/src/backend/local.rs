@@ -4,55 +4,92 @@
 //! future it could replace the Jupyter installation by directly invoking
 //! kernels, or introduce new APIs for developer experience.
 
-use std::process::Stdio;
+use std::{process::Stdio, time::Instant};
 
-use serde_json::json;
 use tokio::fs;
 use tokio::net::TcpListener;
 use uuid::Uuid;
 
-use self::environment::KernelSpec;
-use super::{create_zeromq_connection, KernelConnection};
+use self::{
+    environment::{KernelInterruptMode, KernelSpec, SEP},
+    interrupt::InterruptSignal,
+};
+use super::{
+    commands, create_zeromq_connection,
+    wire_protocol::{ConnectionInfo, ConnectionOptions, Transport},
+    KernelConnection,
+};
 use crate::Error;
 
 pub mod environment;
+pub mod evcxr;
+mod interrupt;
 
 /// Represents a connection to an active kernel.
 pub struct LocalKernel {
     child: tokio::process::Child,
     kernel_id: String,
+    started_at: Instant,
 
     spec: KernelSpec,
     conn: KernelConnection,
+    signal: InterruptSignal,
 }
 
 impl LocalKernel {
-    /// Start a new kernel based on a spec, and connect to it.
+    /// Start a new kernel based on a spec, and connect to it over TCP.
     pub async fn start(spec: &KernelSpec) -> Result<Self, Error> {
-        let (control_port, shell_port, iopub_port, stdin_port, heartbeat_port) = tokio::try_join!(
-            get_available_port(),
-            get_available_port(),
-            get_available_port(),
-            get_available_port(),
-            get_available_port(),
-        )?;
-        let signing_key = Uuid::new_v4().to_string();
-        let connection_file = json!({
-            "control_port": control_port,
-            "shell_port": shell_port,
-            "iopub_port": iopub_port,
-            "stdin_port": stdin_port,
-            "hb_port": heartbeat_port,
-            "transport": "tcp",
-            "ip": "127.0.0.1",
-            "signature_scheme": "hmac-sha256",
-            "key": signing_key,
-        });
+        Self::start_with_transport(spec, Transport::Tcp).await
+    }
 
+    /// Start a new kernel based on a spec, and connect to it over a specific
+    /// transport.
+    ///
+    /// With IPC, the kernel's sockets are files in the runtime directory, so no
+    /// ports need to be reserved. This is only supported on Unix.
+    pub async fn start_with_transport(
+        spec: &KernelSpec,
+        transport: Transport,
+    ) -> Result<Self, Error> {
+        if cfg!(windows) && transport == Transport::Ipc {
+            return Err(Error::KernelConnect(
+                "IPC transport is not supported on Windows".into(),
+            ));
+        }
         let kernel_id = Uuid::new_v4().to_string();
         let runtime_dir = environment::runtime_dir();
-        let connection_filename = runtime_dir + &format!("jute-{kernel_id}.json");
-        fs::write(&connection_filename, connection_file.to_string())
+
+        let (ip, (control_port, shell_port, iopub_port, stdin_port, hb_port)) = match transport {
+            Transport::Tcp => {
+                let ports = tokio::try_join!(
+                    get_available_port(),
+                    get_available_port(),
+                    get_available_port(),
+                    get_available_port(),
+                    get_available_port(),
+                )?;
+                ("127.0.0.1".into(), ports)
+            }
+            // Ports are only suffixes of the socket files, as in `jupyter_client`.
+            Transport::Ipc => (
+                format!("{runtime_dir}{SEP}jute-{kernel_id}-ipc"),
+                (1, 2, 3, 4, 5),
+            ),
+        };
+        let info = ConnectionInfo {
+            transport,
+            ip,
+            shell_port,
+            control_port,
+            iopub_port,
+            stdin_port,
+            hb_port,
+            signature_scheme: "hmac-sha256".into(),
+            key: Uuid::new_v4().to_string(),
+        };
+
+        let connection_filename = format!("{runtime_dir}{SEP}jute-{kernel_id}.json");
+        fs::write(&connection_filename, serde_json::to_string(&info)?)
             .await
             .map_err(|err| {
                 Error::KernelConnect(format!("could not write connection file: {err}"))
@@ -66,30 +103,28 @@ impl LocalKernel {
             .iter()
             .map(|s| s.replace("{connection_file}", &connection_filename))
             .collect();
-        // TODO: Handle spec.env
-        let child = tokio::process::Command::new(&argv[0])
-            .args(&argv[1..])
-            .kill_on_drop(true)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
+        let (signal, signal_env) = InterruptSignal::new()?;
+        let child = signal
+            .spawn(
+                tokio::process::Command::new(&argv[0])
+                    .args(&argv[1..])
+                    .envs(signal_env)
+                    .envs(&spec.env)
+                    .kill_on_drop(true)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null()),
+            )
             .map_err(Error::Subprocess)?;
 
-        let conn = create_zeromq_connection(
-            shell_port,
-            control_port,
-            iopub_port,
-            stdin_port,
-            heartbeat_port,
-            &signing_key,
-        )
-        .await?;
+        let conn = create_zeromq_connection(&info, &ConnectionOptions::default()).await?;
 
         Ok(Self {
             child,
             kernel_id,
+            started_at: Instant::now(),
             spec: spec.clone(),
             conn,
+            signal,
         })
     }
 
@@ -108,17 +143,56 @@ impl LocalKernel {
         &self.spec
     }
 
+    /// Get the process ID of the kernel, if it has not exited.
+    pub fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Return the time when the kernel was started.
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
     /// Check if the kernel is still alive.
     pub fn is_alive(&mut self) -> bool {
         matches!(self.child.try_wait(), Ok(None))
     }
 
-    /// Kill the kernel by sending a SIGKILL signal.
+    /// Interrupt the code running in the kernel, in the way given by the
+    /// `interrupt_mode` of its spec.
+    pub async fn interrupt(&self) -> Result<(), Error> {
+        match self.spec.interrupt_mode {
+            KernelInterruptMode::Signal => self.interrupt_signal(),
+            KernelInterruptMode::Message => commands::interrupt(&self.conn).await,
+        }
+    }
+
+    /// Interrupt the kernel by sending it a signal, regardless of its
+    /// `interrupt_mode`.
+    pub fn interrupt_signal(&self) -> Result<(), Error> {
+        let pid = self.pid().ok_or(Error::KernelDisconnect)?;
+        self.signal.send(pid)
+    }
+
+    /// Kill the kernel by sending a SIGKILL signal, along with the processes
+    /// it started.
     pub async fn kill(&mut self) -> Result<(), Error> {
+        if let Some(pid) = self.pid() {
+            self.signal.kill(pid);
+        }
         self.child.kill().await.map_err(Error::Subprocess)
     }
 }
 
+impl Drop for LocalKernel {
+    fn drop(&mut self) {
+        // The kernel itself is killed on drop, but not the processes it started.
+        if let Some(pid) = self.pid() {
+            self.signal.kill(pid);
+        }
+    }
+}
+
 async fn get_available_port() -> Result<u16, Error> {
     let addr = TcpListener::bind("127.0.0.1:0")
         .await
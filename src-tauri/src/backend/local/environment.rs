@@ -4,12 +4,15 @@ use std::{
     collections::BTreeMap,
     env,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use futures_util::future::join_all;
 use serde::Deserialize;
 use tokio::fs;
 
+use super::evcxr;
+
 /// The path separator for the current platform.
 pub const SEP: &str = if cfg!(windows) { "\\" } else { "/" };
 
@@ -37,6 +40,39 @@ pub struct KernelSpec {
     pub env: BTreeMap<String, String>,
 }
 
+impl KernelSpec {
+    /// How Jute should account for the behavior of this kernel.
+    pub fn quirks(&self) -> KernelQuirks {
+        if evcxr::is_evcxr(self) {
+            KernelQuirks {
+                startup_timeout: evcxr::STARTUP_TIMEOUT,
+                startup_code: evcxr::startup_code(),
+            }
+        } else {
+            KernelQuirks::default()
+        }
+    }
+}
+
+/// Differences in how kernels behave, which Jute accounts for when using them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KernelQuirks {
+    /// How long to wait for the kernel to reply after it is started.
+    pub startup_timeout: Duration,
+
+    /// Code to run after the kernel starts, before any cells.
+    pub startup_code: Option<String>,
+}
+
+impl Default for KernelQuirks {
+    fn default() -> Self {
+        Self {
+            startup_timeout: Duration::from_secs(60),
+            startup_code: None,
+        }
+    }
+}
+
 /// The interrupt mode of the kernel.
 #[derive(Default, Copy, Clone, Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -134,6 +170,21 @@ pub fn data_dir() -> String {
     }
 }
 
+/// Get the configured directory for Jupyter config files.
+pub fn config_dir() -> String {
+    if let Ok(jupyter_config_dir) = env::var("JUPYTER_CONFIG_DIR") {
+        return jupyter_config_dir.trim_end_matches(SEP).into();
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            env::var("USERPROFILE").unwrap() + "\\.jupyter"
+        } else {
+            env::var("HOME").unwrap() + "/.jupyter"
+        }
+    }
+}
+
 /// Get the configured directory where runtime connection files are stored.
 pub fn runtime_dir() -> String {
     match env::var("JUPYTER_RUNTIME_DIR") {
@@ -0,0 +1,203 @@
+//! Setup of the `evcxr_jupyter` kernel, for notebooks written in Rust.
+//!
+//! Evcxr compiles each cell with `cargo` and loads it into the running
+//! process, so it needs a Rust toolchain, and it is installed from crates.io
+//! with `cargo install` rather than with Python packages. Compiling also makes
+//! it slower to start than other kernels, and dependencies added with `:dep`
+//! are rebuilt in every session unless `sccache` is turned on.
+//!
+//! The app is often launched without the user's shell profile, so binaries are
+//! also looked up in `~/.cargo/bin`, where `cargo install` puts them.
+
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::{fs, process::Command};
+use ts_rs::TS;
+
+use super::environment::{self, KernelSpec, SEP};
+use crate::Error;
+
+/// Name of the kernel spec registered for evcxr.
+pub const KERNEL_NAME: &str = "rust";
+
+/// Name of the crate and binary of the kernel.
+const EVCXR: &str = "evcxr_jupyter";
+
+/// How long to wait for evcxr to start, which includes starting its compiler.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Tools found for running Rust notebooks, sent to the frontend.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct RustKernelStatus {
+    /// Path to `cargo`, if a Rust toolchain is installed.
+    pub cargo: Option<PathBuf>,
+
+    /// Path to `evcxr_jupyter`, if it is installed.
+    pub evcxr: Option<PathBuf>,
+
+    /// Whether the `rust` kernel spec is registered for Jupyter.
+    pub registered: bool,
+
+    /// Path to `sccache`, if it is installed. Without it, dependencies of
+    /// notebooks are compiled again in every session.
+    pub sccache: Option<PathBuf>,
+}
+
+crate::export_bindings!(RustKernelStatus);
+
+/// Find the tools for running Rust notebooks.
+pub async fn status() -> RustKernelStatus {
+    let registered = environment::list_kernels(None)
+        .await
+        .iter()
+        .any(|(path, _)| path.file_name().and_then(|name| name.to_str()) == Some(KERNEL_NAME));
+    RustKernelStatus {
+        cargo: find_binary("cargo"),
+        evcxr: find_binary(EVCXR),
+        registered,
+        sccache: find_binary("sccache"),
+    }
+}
+
+/// Install `evcxr_jupyter` with `cargo`, returning the path to its binary.
+///
+/// This compiles it from source, which takes a few minutes.
+pub async fn install(cargo: &Path) -> Result<PathBuf, Error> {
+    let output = Command::new(cargo)
+        .args(["install", "--locked", EVCXR])
+        .output()
+        .await
+        .map_err(Error::Subprocess)?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        // Cargo prints its progress before the error, which is at the end.
+        let message = message.trim().lines().last().unwrap_or_default();
+        return Err(Error::Subprocess(io::Error::other(message)));
+    }
+    find_binary(EVCXR).ok_or_else(|| {
+        Error::Subprocess(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{EVCXR} was installed, but it is not on the PATH"),
+        ))
+    })
+}
+
+/// Register the kernel spec for an `evcxr_jupyter` binary in the user's
+/// Jupyter data directory, returning the spec's directory.
+pub async fn register(binary: &Path) -> Result<PathBuf, Error> {
+    let dir = PathBuf::from(environment::data_dir() + SEP + "kernels" + SEP + KERNEL_NAME);
+    fs::create_dir_all(&dir).await.map_err(Error::Filesystem)?;
+    let spec = serde_json::to_string_pretty(&kernel_json(binary))?;
+    fs::write(dir.join("kernel.json"), spec)
+        .await
+        .map_err(Error::Filesystem)?;
+    Ok(dir)
+}
+
+/// Contents of the `kernel.json` file for an `evcxr_jupyter` binary, the same
+/// as written by `evcxr_jupyter --install`.
+///
+/// The binary is an absolute path, since it is usually not on the `PATH` of
+/// the app. Evcxr handles interrupts as messages, not signals.
+pub fn kernel_json(binary: &Path) -> Value {
+    json!({
+        "argv": [binary, "--control_file", "{connection_file}"],
+        "display_name": "Rust",
+        "language": "rust",
+        "interrupt_mode": "message",
+    })
+}
+
+/// Whether a kernel spec runs evcxr.
+pub fn is_evcxr(spec: &KernelSpec) -> bool {
+    spec.argv.first().is_some_and(|program| {
+        Path::new(program)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            == Some(EVCXR)
+    })
+}
+
+/// Code to run when evcxr starts, turning on `sccache` if it is installed.
+pub fn startup_code() -> Option<String> {
+    find_binary("sccache").map(|_| ":sccache 1".into())
+}
+
+/// Find a binary on the `PATH`, or in the directory of binaries installed by
+/// `cargo install`.
+fn find_binary(name: &str) -> Option<PathBuf> {
+    let file = format!("{name}{}", env::consts::EXE_SUFFIX);
+    let cargo_home = env::var_os("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        let home = env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })?;
+        Some(Path::new(&home).join(".cargo"))
+    });
+    let path = env::var_os("PATH").unwrap_or_default();
+    env::split_paths(&path)
+        .chain(cargo_home.map(|home| home.join("bin")))
+        .map(|dir| dir.join(&file))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{
+        commands::{self, RunCellEvent},
+        local::LocalKernel,
+    };
+
+    #[test]
+    fn evcxr_kernel_spec() {
+        let binary = Path::new("/home/me/.cargo/bin/evcxr_jupyter");
+        let spec: KernelSpec = serde_json::from_value(kernel_json(binary)).unwrap();
+        assert_eq!(spec.argv[0], "/home/me/.cargo/bin/evcxr_jupyter");
+        assert_eq!(spec.language, "rust");
+        assert!(is_evcxr(&spec));
+        assert_eq!(spec.quirks().startup_timeout, STARTUP_TIMEOUT);
+
+        let python: KernelSpec = serde_json::from_value(json!({
+            "argv": ["python", "-m", "ipykernel_launcher", "-f", "{connection_file}"],
+            "display_name": "Python 3",
+            "language": "python",
+        }))
+        .unwrap();
+        assert!(!is_evcxr(&python));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs evcxr_jupyter and a Rust toolchain"]
+    async fn run_rust_cells() {
+        let binary = find_binary(EVCXR).expect("evcxr_jupyter is not installed");
+        let spec: KernelSpec = serde_json::from_value(kernel_json(&binary)).unwrap();
+        let kernel = LocalKernel::start(&spec).await.unwrap();
+        let info = tokio::time::timeout(STARTUP_TIMEOUT, commands::kernel_info(kernel.conn()))
+            .await
+            .expect("evcxr did not start")
+            .unwrap();
+        assert_eq!(info.language_info.name, "rust");
+
+        // Variables persist between cells, as in other kernels.
+        for (code, expected) in [("let x = 40;", None), ("x + 2", Some("42"))] {
+            let rx = commands::run_cell(kernel.conn(), code, Default::default())
+                .await
+                .unwrap();
+            let mut result = None;
+            while let Ok(event) = rx.recv().await {
+                match event {
+                    RunCellEvent::ExecuteResult(msg) => {
+                        result = msg.data["text/plain"].as_str().map(String::from);
+                    }
+                    RunCellEvent::Error(err) => panic!("{}: {}", err.ename, err.evalue),
+                    _ => {}
+                }
+            }
+            assert_eq!(result.as_deref(), expected);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Interrupts of local kernels by signal, the default `interrupt_mode`.
+//!
+//! On Unix, the kernel starts in its own process group, which is sent `SIGINT`
+//! so that processes started by the kernel are interrupted too, as with
+//! `killpg` in `jupyter_client`. Windows has no such signal, so the kernel
+//! inherits an event whose handle is passed in the `JPY_INTERRUPT_EVENT`
+//! environment variable, and setting the event interrupts it.
+
+#![allow(unsafe_code)]
+
+use std::io;
+
+use tokio::process::{Child, Command};
+
+use crate::Error;
+
+/// Sends interrupt signals to a kernel process.
+#[derive(Debug)]
+pub(super) struct InterruptSignal {
+    /// Handle of the event shared with the kernel, as an integer so that this
+    /// can be sent between threads.
+    #[cfg(windows)]
+    event: isize,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(unix)] {
+        impl InterruptSignal {
+            /// Set up signals, returning environment variables for the kernel.
+            pub fn new() -> Result<(Self, Vec<(String, String)>), Error> {
+                Ok((Self {}, Vec::new()))
+            }
+
+            /// Start the kernel in a new process group, led by the kernel.
+            pub fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+                command.process_group(0).spawn()
+            }
+
+            /// Interrupt the process group of the kernel with a pid.
+            pub fn send(&self, pid: u32) -> Result<(), Error> {
+                // SAFETY: `killpg` has no memory safety requirements.
+                if unsafe { libc::killpg(pid as libc::pid_t, libc::SIGINT) } != 0 {
+                    return Err(Error::Subprocess(io::Error::last_os_error()));
+                }
+                Ok(())
+            }
+
+            /// Kill the processes left in the process group of the kernel.
+            pub fn kill(&self, pid: u32) {
+                // SAFETY: `killpg` has no memory safety requirements.
+                _ = unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+            }
+        }
+    } else if #[cfg(windows)] {
+        use std::{ffi::c_void, mem};
+
+        use windows::{
+            core::PCWSTR,
+            Win32::{
+                Foundation::{
+                    CloseHandle, SetHandleInformation, FALSE, HANDLE, HANDLE_FLAGS,
+                    HANDLE_FLAG_INHERIT,
+                },
+                Security::SECURITY_ATTRIBUTES,
+                System::Threading::{CreateEventW, SetEvent},
+            },
+        };
+
+        impl InterruptSignal {
+            /// Create an event for the kernel, returning environment variables
+            /// that tell the kernel its handle.
+            pub fn new() -> Result<(Self, Vec<(String, String)>), Error> {
+                let attributes = SECURITY_ATTRIBUTES {
+                    nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+                    lpSecurityDescriptor: std::ptr::null_mut(),
+                    bInheritHandle: FALSE,
+                };
+                // SAFETY: The attributes are valid for the duration of the call.
+                let event = unsafe {
+                    CreateEventW(Some(&attributes), FALSE, FALSE, PCWSTR::null())
+                }
+                .map_err(|err| Error::Subprocess(err.into()))?;
+                let event = event.0 as isize;
+                let env = vec![("JPY_INTERRUPT_EVENT".into(), event.to_string())];
+                Ok((Self { event }, env))
+            }
+
+            /// Start the kernel, which inherits the event. The handle is only
+            /// inheritable while the kernel is spawned, so that other
+            /// processes don't inherit it.
+            pub fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+                let handle = HANDLE(self.event as *mut c_void);
+                let inherit = |flags| {
+                    // SAFETY: The handle is open until this is dropped.
+                    unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT.0, flags) }
+                        .map_err(io::Error::from)
+                };
+                inherit(HANDLE_FLAG_INHERIT)?;
+                let child = command.spawn();
+                inherit(HANDLE_FLAGS(0))?;
+                child
+            }
+
+            /// Interrupt the kernel by setting its event.
+            pub fn send(&self, _pid: u32) -> Result<(), Error> {
+                // SAFETY: The handle is open until this is dropped.
+                unsafe { SetEvent(HANDLE(self.event as *mut c_void)) }
+                    .map_err(|err| Error::Subprocess(err.into()))
+            }
+
+            /// Windows has no process groups to kill, so only the kernel is
+            /// killed, as in `jupyter_client`.
+            pub fn kill(&self, _pid: u32) {}
+        }
+
+        impl Drop for InterruptSignal {
+            fn drop(&mut self) {
+                // SAFETY: The handle is open, and not used after this.
+                _ = unsafe { CloseHandle(HANDLE(self.event as *mut c_void)) };
+            }
+        }
+    } else {
+        impl InterruptSignal {
+            /// Set up signals, returning environment variables for the kernel.
+            pub fn new() -> Result<(Self, Vec<(String, String)>), Error> {
+                Ok((Self {}, Vec::new()))
+            }
+
+            /// Start the kernel.
+            pub fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+                command.spawn()
+            }
+
+            /// Signals are not supported on this platform.
+            pub fn send(&self, _pid: u32) -> Result<(), Error> {
+                Err(Error::KernelUnsupported("interrupt signals".into()))
+            }
+
+            /// Only the kernel is killed on this platform.
+            pub fn kill(&self, _pid: u32) {}
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn interrupt_process_group() {
+        let (signal, env) = InterruptSignal::new().unwrap();
+        assert!(env.is_empty());
+        let mut child = signal.spawn(Command::new("sleep").arg("10")).unwrap();
+        signal.send(child.id().unwrap()).unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGINT));
+    }
+}
@@ -0,0 +1,352 @@
+//! Resolving git merge conflicts in notebooks, cell by cell.
+//!
+//! Git merges `.ipynb` files line by line, so a conflicted notebook is JSON
+//! with conflict markers in it, which can't be parsed. Instead, the file is
+//! split into the versions on each side of the markers, and those are merged
+//! as notebooks: cells are matched by ID, or by source in notebooks without
+//! IDs, changes made on only one side are taken, and cells whose source
+//! changed on both sides are reported as conflicts. Outputs never conflict,
+//! and follow the side whose source was taken.
+//!
+//! The common ancestor is known when the file was merged with the `diff3`
+//! conflict style. Otherwise, every difference between the sides conflicts,
+//! and cells on only one side are kept.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use super::diff::match_by_source;
+use super::notebook::{Cell, NotebookRoot};
+
+/// A cell whose source was changed differently on each side of a merge.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CellConflict {
+    /// Identifier of the cell in the merged notebook.
+    pub cell_id: String,
+
+    /// Source of the cell in the common ancestor, if known and in it.
+    pub base: Option<String>,
+
+    /// Source of the cell on our side, or `None` if we deleted it.
+    pub ours: Option<String>,
+
+    /// Source of the cell on their side, or `None` if they deleted it.
+    pub theirs: Option<String>,
+}
+
+crate::export_bindings!(CellConflict);
+
+/// Result of merging the two sides of a conflicted notebook.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct NotebookMerge {
+    /// The merged notebook. Conflicting cells have our source, or their
+    /// source if we deleted the cell.
+    pub notebook: NotebookRoot,
+
+    /// Cells that need to be resolved by hand.
+    pub conflicts: Vec<CellConflict>,
+}
+
+crate::export_bindings!(NotebookMerge);
+
+/// The versions of a file on each side of its conflict markers.
+#[derive(Debug, PartialEq, Eq)]
+struct ConflictSides {
+    base: Option<String>,
+    ours: String,
+    theirs: String,
+}
+
+/// Part of a conflicted file being read.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Section {
+    Common,
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// Split a file with git conflict markers into the versions of each side,
+/// returning `None` if it has no complete conflicts.
+fn split_conflicts(text: &str) -> Option<ConflictSides> {
+    let mut section = Section::Common;
+    let (mut ours, mut base, mut theirs) = (String::new(), String::new(), String::new());
+    let mut found = false;
+    let mut has_base = true;
+    for line in text.split_inclusive('\n') {
+        match section {
+            Section::Common if line.starts_with("<<<<<<<") => {
+                section = Section::Ours;
+                found = true;
+            }
+            Section::Ours if line.starts_with("|||||||") => section = Section::Base,
+            Section::Ours | Section::Base if line.starts_with("=======") => {
+                has_base &= section == Section::Base;
+                section = Section::Theirs;
+            }
+            Section::Theirs if line.starts_with(">>>>>>>") => section = Section::Common,
+            Section::Common => {
+                ours += line;
+                base += line;
+                theirs += line;
+            }
+            Section::Ours => ours += line,
+            Section::Base => base += line,
+            Section::Theirs => theirs += line,
+        }
+    }
+    (found && section == Section::Common).then(|| ConflictSides {
+        base: has_base.then_some(base),
+        ours,
+        theirs,
+    })
+}
+
+/// Merge the sides of a notebook file with git conflict markers, returning
+/// `None` if it has none.
+pub fn resolve_conflicts(text: &str) -> Result<Option<NotebookMerge>, serde_json::Error> {
+    let Some(sides) = split_conflicts(text) else {
+        return Ok(None);
+    };
+    let base = match &sides.base {
+        Some(base) => Some(NotebookRoot::from_ipynb(base)?),
+        None => None,
+    };
+    let ours = NotebookRoot::from_ipynb(&sides.ours)?;
+    let theirs = NotebookRoot::from_ipynb(&sides.theirs)?;
+    Ok(Some(merge_notebooks(base.as_ref(), &ours, &theirs)))
+}
+
+/// Merge two versions of a notebook, given their common ancestor if known.
+///
+/// Cells are in our order, with cells added on their side placed after the
+/// cell before them in their version.
+pub fn merge_notebooks(
+    base: Option<&NotebookRoot>,
+    ours: &NotebookRoot,
+    theirs: &NotebookRoot,
+) -> NotebookMerge {
+    let normalized_base = base.map(|base| with_ids(base, None));
+    let mut notebook = with_ids(ours, base.zip(normalized_base.as_ref()));
+    let theirs = match base.zip(normalized_base.as_ref()) {
+        Some(base) => with_ids(theirs, Some(base)),
+        None => with_ids(theirs, Some((ours, &notebook))),
+    };
+    let base = normalized_base;
+
+    let by_id = |notebook: &NotebookRoot| -> HashMap<String, Cell> {
+        (notebook.cells.iter())
+            .map(|cell| (cell.id().unwrap_or_default().to_string(), cell.clone()))
+            .collect()
+    };
+    let base_cells = base.as_ref().map(by_id).unwrap_or_default();
+    let their_cells = by_id(&theirs);
+
+    let mut conflicts = Vec::new();
+    let mut cells = Vec::new();
+    for cell in notebook.cells.drain(..) {
+        let id = cell.id().unwrap_or_default().to_string();
+        let base_cell = base_cells.get(&id);
+        match their_cells.get(&id) {
+            Some(their_cell) => {
+                cells.push(merge_cell(base_cell, &cell, their_cell, &mut conflicts));
+            }
+            // They deleted a cell that we left alone.
+            None if base_cell == Some(&cell) => {}
+            None => {
+                if base_cell.is_some() {
+                    conflicts.push(CellConflict {
+                        cell_id: id,
+                        base: base_cell.map(Cell::source),
+                        ours: Some(cell.source()),
+                        theirs: None,
+                    });
+                }
+                cells.push(cell);
+            }
+        }
+    }
+
+    // Add their cells that we don't have, after the cell before them.
+    let mut position = 0;
+    for cell in &theirs.cells {
+        let id = cell.id().unwrap_or_default();
+        if let Some(index) = cells.iter().position(|c| c.id() == Some(id)) {
+            position = index + 1;
+            continue;
+        }
+        let base_cell = base_cells.get(id);
+        if base_cell == Some(cell) {
+            // We deleted a cell that they left alone.
+            continue;
+        }
+        if base_cell.is_some() {
+            conflicts.push(CellConflict {
+                cell_id: id.into(),
+                base: base_cell.map(Cell::source),
+                ours: None,
+                theirs: Some(cell.source()),
+            });
+        }
+        cells.insert(position, cell.clone());
+        position += 1;
+    }
+
+    notebook.cells = cells;
+    NotebookMerge {
+        notebook,
+        conflicts,
+    }
+}
+
+/// Give the cells of a notebook unique IDs.
+///
+/// Cells without an ID, as in notebooks older than nbformat 4.5, get an ID
+/// from their source, which differs on each side once the cell is edited. So
+/// they take the ID of the cell matched to them by source in another version
+/// instead, given before and after normalizing, if that cell had none either.
+fn with_ids(
+    notebook: &NotebookRoot,
+    other: Option<(&NotebookRoot, &NotebookRoot)>,
+) -> NotebookRoot {
+    let mut notebook = notebook.clone();
+    if let Some((other, normalized)) = other {
+        for pair in match_by_source(&other.cells, &notebook.cells) {
+            let (Some(i), Some(j)) = pair else {
+                continue;
+            };
+            let cell = &mut notebook.cells[j];
+            if cell.id().is_none() && other.cells[i].id().is_none() {
+                *cell.id_mut() = normalized.cells[i].id().map(String::from);
+            }
+        }
+    }
+    notebook.normalize_cell_ids();
+    notebook
+}
+
+/// Merge a cell that is in both versions.
+fn merge_cell(
+    base: Option<&Cell>,
+    ours: &Cell,
+    theirs: &Cell,
+    conflicts: &mut Vec<CellConflict>,
+) -> Cell {
+    if ours == theirs || base == Some(theirs) {
+        return ours.clone();
+    }
+    if base == Some(ours) {
+        return theirs.clone();
+    }
+
+    let base_source = base.map(Cell::source);
+    let (our_source, their_source) = (ours.source(), theirs.source());
+    let take_theirs = our_source != their_source && base_source.as_ref() == Some(&our_source);
+    let mut merged = ours.clone();
+    if take_theirs {
+        merged.set_source(their_source);
+    } else if our_source != their_source && base_source.as_ref() != Some(&their_source) {
+        conflicts.push(CellConflict {
+            cell_id: ours.id().unwrap_or_default().into(),
+            base: base_source,
+            ours: Some(our_source),
+            theirs: Some(their_source),
+        });
+    }
+    if let (Cell::Code(merged), Cell::Code(ours), Cell::Code(theirs)) = (&mut merged, ours, theirs)
+    {
+        let base_outputs = match base {
+            Some(Cell::Code(base)) => Some(&base.outputs),
+            _ => None,
+        };
+        if take_theirs || base_outputs == Some(&ours.outputs) {
+            merged.outputs.clone_from(&theirs.outputs);
+            merged.execution_count = theirs.execution_count;
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notebook(cells: &[(&str, &str)]) -> NotebookRoot {
+        let cells: Vec<_> = (cells.iter())
+            .map(|(id, source)| {
+                let mut cell = serde_json::json!({
+                    "cell_type": "code",
+                    "id": id,
+                    "metadata": {},
+                    "source": source,
+                    "execution_count": null,
+                    "outputs": [],
+                });
+                if id.is_empty() {
+                    cell.as_object_mut().unwrap().remove("id");
+                }
+                cell
+            })
+            .collect();
+        NotebookRoot::from_cells(cells)
+    }
+
+    fn sources(notebook: &NotebookRoot) -> Vec<String> {
+        notebook.cells.iter().map(Cell::source).collect()
+    }
+
+    #[test]
+    fn split_conflict_markers() {
+        let text = "a\n<<<<<<< HEAD\nb\n||||||| base\nc\n=======\nd\n>>>>>>> branch\ne\n";
+        let sides = split_conflicts(text).unwrap();
+        assert_eq!(sides.ours, "a\nb\ne\n");
+        assert_eq!(sides.base.as_deref(), Some("a\nc\ne\n"));
+        assert_eq!(sides.theirs, "a\nd\ne\n");
+
+        let sides = split_conflicts("<<<<<<< HEAD\nb\n=======\nd\n>>>>>>> branch\n").unwrap();
+        assert_eq!(sides.base, None);
+        assert_eq!(split_conflicts("no conflicts\n"), None);
+    }
+
+    #[test]
+    fn merge_three_ways() {
+        let base = notebook(&[("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")]);
+        let ours = notebook(&[("a", "10"), ("b", "2"), ("c", "30"), ("d", "4")]);
+        let theirs = notebook(&[("a", "1"), ("b", "20"), ("c", "300"), ("e", "5")]);
+
+        let merge = merge_notebooks(Some(&base), &ours, &theirs);
+        assert_eq!(sources(&merge.notebook), ["10", "20", "30", "5"]);
+        assert_eq!(
+            merge.conflicts,
+            [CellConflict {
+                cell_id: "c".into(),
+                base: Some("3".into()),
+                ours: Some("30".into()),
+                theirs: Some("300".into()),
+            }]
+        );
+
+        // Without the ancestor, every difference conflicts.
+        let merge = merge_notebooks(None, &ours, &theirs);
+        assert_eq!(sources(&merge.notebook), ["10", "2", "30", "5", "4"]);
+        assert_eq!(merge.conflicts.len(), 3);
+    }
+
+    #[test]
+    fn merge_cells_without_ids() {
+        let base = notebook(&[("", "1"), ("", "2"), ("", "3")]);
+        let ours = notebook(&[("", "10"), ("", "2"), ("", "3")]);
+        let theirs = notebook(&[("", "100"), ("", "2"), ("", "30")]);
+
+        let merge = merge_notebooks(Some(&base), &ours, &theirs);
+        assert_eq!(sources(&merge.notebook), ["10", "2", "30"]);
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].theirs.as_deref(), Some("100"));
+
+        let merge = merge_notebooks(None, &ours, &theirs);
+        assert_eq!(sources(&merge.notebook), ["10", "2", "3"]);
+        assert_eq!(merge.conflicts.len(), 2);
+    }
+}
@@ -5,10 +5,12 @@
 //!
 //! [nbformat v4]: https://github.com/jupyter/nbformat/blob/v5.10.4/nbformat/v4/nbformat.v4.schema.json
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use ts_rs::TS;
 
 /// Represents the root structure of a Jupyter Notebook file.
@@ -28,26 +30,165 @@ pub struct NotebookRoot {
     pub cells: Vec<Cell>,
 }
 
+crate::export_bindings!(NotebookRoot);
+
+impl NotebookRoot {
+    /// Merge adjacent stream outputs in every code cell. See [`merge_streams`].
+    pub fn merge_streams(&mut self) {
+        for cell in &mut self.cells {
+            if let Cell::Code(cell) = cell {
+                merge_streams(&mut cell.outputs);
+            }
+        }
+    }
+
+    /// Give every cell a unique, valid ID, as required since nbformat 4.5,
+    /// and bump the minor version to match.
+    ///
+    /// Cells keep their IDs when they can. A cell without one gets a hash of
+    /// its source, so that loading a file twice gives the same IDs, and later
+    /// cells with a duplicate ID get a numbered suffix.
+    pub fn normalize_cell_ids(&mut self) {
+        let mut used = HashSet::new();
+        let keep: Vec<bool> = (self.cells.iter())
+            .map(|cell| {
+                cell.id()
+                    .is_some_and(|id| is_valid_cell_id(id) && used.insert(id))
+            })
+            .collect();
+        let mut used: HashSet<String> = used.into_iter().map(String::from).collect();
+
+        for (cell, keep) in self.cells.iter_mut().zip(keep) {
+            if keep {
+                continue;
+            }
+            let base = match cell.id() {
+                Some(id) if is_valid_cell_id(id) => id.to_string(),
+                _ => {
+                    let hash = Sha256::digest(cell.source().as_bytes());
+                    format!("{hash:x}")[..8].to_string()
+                }
+            };
+            let mut id = base.clone();
+            let mut suffix = 1;
+            while used.contains(&id) {
+                let base = &base[..base.len().min(MAX_CELL_ID_LEN - 8)];
+                id = format!("{base}-{suffix}");
+                suffix += 1;
+            }
+            used.insert(id.clone());
+            *cell.id_mut() = Some(id);
+        }
+
+        if self.nbformat == 4 && self.nbformat_minor < 5 {
+            self.nbformat_minor = 5;
+        }
+    }
+
+    /// Split the sources and text outputs of every cell into lines, as
+    /// Jupyter does when it writes a notebook.
+    pub fn split_lines(&mut self) {
+        for cell in &mut self.cells {
+            match cell {
+                Cell::Raw(cell) => {
+                    cell.source = cell.source.normalize();
+                    for bundle in cell.attachments.iter_mut().flat_map(|a| a.values_mut()) {
+                        split_mime_bundle(bundle);
+                    }
+                }
+                Cell::Markdown(cell) => {
+                    cell.source = cell.source.normalize();
+                    for bundle in cell.attachments.iter_mut().flat_map(|a| a.values_mut()) {
+                        split_mime_bundle(bundle);
+                    }
+                }
+                Cell::Code(cell) => {
+                    cell.source = cell.source.normalize();
+                    for output in &mut cell.outputs {
+                        match output {
+                            Output::ExecuteResult(result) => split_mime_bundle(&mut result.data),
+                            Output::DisplayData(display) => split_mime_bundle(&mut display.data),
+                            Output::Stream(stream) => stream.text = stream.text.normalize(),
+                            Output::Error(_) => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parse a notebook from `.ipynb` JSON.
+    ///
+    /// Notebooks in nbformat 3 are upgraded to version 4 first, with their
+    /// original version kept in `metadata.orig_nbformat`, so that they are
+    /// saved as version 4. See [`upgrade_v3`].
+    pub fn from_ipynb(contents: &str) -> Result<Self, serde_json::Error> {
+        let mut value: Value = serde_json::from_str(contents)?;
+        if value.get("nbformat").and_then(Value::as_u64) == Some(3) {
+            upgrade_v3(&mut value);
+        }
+        serde_json::from_value(value)
+    }
+
+    /// Serialize the notebook as `.ipynb` JSON, formatted like Jupyter writes
+    /// it: text split into lines, sorted keys, one-space indentation, and a
+    /// trailing newline. This keeps diffs small when a notebook is edited in
+    /// both Jute and Jupyter.
+    pub fn to_ipynb(&self) -> Result<String, serde_json::Error> {
+        let mut notebook = self.clone();
+        notebook.split_lines();
+        // Keys are sorted like Jupyter writes them, while maps otherwise keep
+        // the order they were read in.
+        let mut value = serde_json::to_value(&notebook)?;
+        value.sort_all_objects();
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        value.serialize(&mut serializer)?;
+        buf.push(b'\n');
+        Ok(String::from_utf8(buf).expect("serde_json writes valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+impl NotebookRoot {
+    /// Create a notebook in nbformat 4.5 from cells written as JSON, for tests.
+    pub(crate) fn from_cells(cells: Vec<Value>) -> Self {
+        let json = serde_json::json!({
+            "metadata": {},
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "cells": cells,
+        });
+        serde_json::from_value(json).expect("test cells should be valid")
+    }
+}
+
 /// Root-level metadata for the notebook.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct NotebookMetadata {
     /// Kernel information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub kernelspec: Option<KernelSpec>,
 
     /// Programming language information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub language_info: Option<LanguageInfo>,
 
     /// Original notebook format before conversion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub orig_nbformat: Option<u8>,
 
     /// Title of the notebook document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub title: Option<String>,
 
     /// Authors of the notebook document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub authors: Option<Vec<Author>>,
 
@@ -79,18 +220,22 @@ pub struct LanguageInfo {
     pub name: String,
 
     /// CodeMirror mode to use for the language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub codemirror_mode: Option<CodeMirrorMode>,
 
     /// File extension for files in this language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub file_extension: Option<String>,
 
     /// MIME type for files in this language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub mimetype: Option<String>,
 
     /// Pygments lexer for syntax highlighting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub pygments_lexer: Option<String>,
 
@@ -114,6 +259,7 @@ pub enum CodeMirrorMode {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct Author {
     /// Name of the author.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub name: Option<String>,
 
@@ -137,10 +283,70 @@ pub enum Cell {
     Code(CodeCell),
 }
 
+impl Cell {
+    /// Identifier of the cell, if it has one.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Cell::Raw(cell) => cell.id.as_deref(),
+            Cell::Markdown(cell) => cell.id.as_deref(),
+            Cell::Code(cell) => cell.id.as_deref(),
+        }
+    }
+
+    /// Mutable identifier of the cell.
+    pub fn id_mut(&mut self) -> &mut Option<String> {
+        match self {
+            Cell::Raw(cell) => &mut cell.id,
+            Cell::Markdown(cell) => &mut cell.id,
+            Cell::Code(cell) => &mut cell.id,
+        }
+    }
+
+    /// Content of the cell, as a single string.
+    pub fn source(&self) -> String {
+        let source = match self {
+            Cell::Raw(cell) => &cell.source,
+            Cell::Markdown(cell) => &cell.source,
+            Cell::Code(cell) => &cell.source,
+        };
+        source.clone().into()
+    }
+
+    /// Replace the content of the cell.
+    pub fn set_source(&mut self, source: String) {
+        let source = MultilineString::Single(source);
+        match self {
+            Cell::Raw(cell) => cell.source = source,
+            Cell::Markdown(cell) => cell.source = source,
+            Cell::Code(cell) => cell.source = source,
+        }
+    }
+
+    /// Name of the cell's type, as in the `cell_type` field.
+    pub fn cell_type(&self) -> &'static str {
+        match self {
+            Cell::Raw(_) => "raw",
+            Cell::Markdown(_) => "markdown",
+            Cell::Code(_) => "code",
+        }
+    }
+}
+
+/// Longest cell ID allowed by nbformat.
+const MAX_CELL_ID_LEN: usize = 64;
+
+/// Whether a cell ID is allowed by nbformat: 1 to 64 letters, digits, dashes,
+/// or underscores.
+fn is_valid_cell_id(id: &str) -> bool {
+    (1..=MAX_CELL_ID_LEN).contains(&id.len())
+        && (id.bytes()).all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
 /// Raw cell in the notebook.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct RawCell {
     /// Identifier of the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub id: Option<String>,
 
@@ -151,6 +357,7 @@ pub struct RawCell {
     pub source: MultilineString,
 
     /// Attachments (e.g., images) in the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub attachments: Option<CellAttachments>,
 }
@@ -159,6 +366,7 @@ pub struct RawCell {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct MarkdownCell {
     /// Identifier of the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub id: Option<String>,
 
@@ -169,6 +377,7 @@ pub struct MarkdownCell {
     pub source: MultilineString,
 
     /// Attachments (e.g., images) in the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub attachments: Option<CellAttachments>,
 }
@@ -177,6 +386,7 @@ pub struct MarkdownCell {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct CodeCell {
     /// Identifier of the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub id: Option<String>,
 
@@ -194,14 +404,76 @@ pub struct CodeCell {
 }
 
 /// Metadata for a cell.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
 pub struct CellMetadata {
+    /// Role of the cell in a slideshow, as used by nbconvert and RISE.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub slideshow: Option<SlideshowMetadata>,
+
+    /// Whether the outputs of a code cell are collapsed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub collapsed: Option<bool>,
+
+    /// Whether long outputs of a code cell are shown in a scrolling area.
+    ///
+    /// Jupyter also allows `"auto"`, which is read as a missing value since
+    /// that is the default.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_scrolled",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[ts(optional)]
+    pub scrolled: Option<bool>,
+
     /// Additional unrecognized attributes in cell metadata.
     #[serde(flatten)]
     #[ts(skip)]
     pub other: Map<String, Value>,
 }
 
+/// Read the `scrolled` attribute of cell metadata, which may be `"auto"`.
+fn deserialize_scrolled<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    Ok(Value::deserialize(deserializer)?.as_bool())
+}
+
+/// Slideshow metadata for a cell.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct SlideshowMetadata {
+    /// How the cell is placed in the slideshow.
+    pub slide_type: SlideType,
+}
+
+/// How a cell is placed in a slideshow.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum SlideType {
+    /// Start a new slide.
+    Slide,
+
+    /// Start a new subslide, below the current slide.
+    Subslide,
+
+    /// Appear on the current slide in a later step.
+    Fragment,
+
+    /// Leave the cell out of the slideshow.
+    Skip,
+
+    /// Show the cell in the speaker notes of the current slide.
+    Notes,
+
+    /// Continue the current slide, which is also used for unknown values.
+    #[default]
+    #[serde(rename = "-")]
+    #[serde(other)]
+    Continue,
+}
+
 /// Attachments for a cell, represented as MIME bundles keyed by filenames.
 pub type CellAttachments = BTreeMap<String, MimeBundle>;
 
@@ -240,15 +512,46 @@ impl MultilineString {
             MultilineString::Single(s) => s,
             MultilineString::Multi(v) => &v.join(""),
         };
+        MultilineString::Multi(split_lines(value))
+    }
 
-        let mut lines = Vec::new();
-        let mut remaining = &value[..];
-        while !remaining.is_empty() {
-            let next_break = remaining.find('\n').map_or(remaining.len(), |i| i + 1);
-            lines.push(remaining[..next_break].to_string());
-            remaining = &remaining[next_break..];
+    /// Append another string. Lines are joined into a single string the first
+    /// time, so appending repeatedly takes linear time.
+    pub fn push(&mut self, other: MultilineString) {
+        if let MultilineString::Multi(lines) = self {
+            let joined = mem::take(lines).join("");
+            *self = MultilineString::Single(joined);
+        }
+        if let MultilineString::Single(text) = self {
+            match other {
+                MultilineString::Single(s) => text.push_str(&s),
+                MultilineString::Multi(v) => v.iter().for_each(|line| text.push_str(line)),
+            }
+        }
+    }
+}
+
+/// Break a string after each newline character, keeping the newlines.
+fn split_lines(value: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut remaining = value;
+    while !remaining.is_empty() {
+        let next_break = remaining.find('\n').map_or(remaining.len(), |i| i + 1);
+        lines.push(remaining[..next_break].to_string());
+        remaining = &remaining[next_break..];
+    }
+    lines
+}
+
+/// Split the text values of a MIME bundle into lines, like nbformat. JSON
+/// values and binary data in base64 are left as they are.
+fn split_mime_bundle(data: &mut MimeBundle) {
+    for (key, value) in data.iter_mut() {
+        let text =
+            key.starts_with("text/") || key == "application/javascript" || key == "image/svg+xml";
+        if let (true, Value::String(s)) = (text, &*value) {
+            *value = Value::from(split_lines(s));
         }
-        MultilineString::Multi(lines)
     }
 }
 
@@ -269,6 +572,18 @@ pub enum Output {
     Error(OutputError),
 }
 
+impl Output {
+    /// Name of the output's type, as in the `output_type` field.
+    pub fn output_type(&self) -> &'static str {
+        match self {
+            Output::ExecuteResult(_) => "execute_result",
+            Output::DisplayData(_) => "display_data",
+            Output::Stream(_) => "stream",
+            Output::Error(_) => "error",
+        }
+    }
+}
+
 /// Result of executing a code cell.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct OutputExecuteResult {
@@ -338,6 +653,207 @@ pub struct OutputError {
 /// Metadata associated with outputs.
 pub type OutputMetadata = BTreeMap<String, Value>;
 
+/// Save the base64-encoded binary buffers of a kernel message in the metadata
+/// of its output, under `jute.buffers`, since nbformat has no field for them.
+/// Other fields under the `jute` key are kept.
+pub fn save_output_buffers(metadata: &mut OutputMetadata, buffers: Vec<String>) {
+    if buffers.is_empty() {
+        return;
+    }
+    let jute = metadata
+        .entry("jute".into())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !jute.is_object() {
+        *jute = Value::Object(Map::new());
+    }
+    jute["buffers"] = buffers.into();
+}
+
+/// MIME types of output data in nbformat 3, which used short keys.
+const V3_MIME_TYPES: &[(&str, &str)] = &[
+    ("text", "text/plain"),
+    ("html", "text/html"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpeg", "image/jpeg"),
+    ("latex", "text/latex"),
+    ("json", "application/json"),
+    ("javascript", "application/javascript"),
+];
+
+/// Upgrade a notebook in nbformat 3 to version 4, following nbformat's own
+/// conversion.
+///
+/// Version 3 keeps cells in a list of worksheets, has heading cells, names
+/// the fields of code cells and outputs differently, and keys output data by
+/// short names instead of MIME types.
+pub fn upgrade_v3(notebook: &mut Value) {
+    let Some(root) = notebook.as_object_mut() else {
+        return;
+    };
+    let worksheets = match root.remove("worksheets") {
+        Some(Value::Array(worksheets)) => worksheets,
+        _ => Vec::new(),
+    };
+    let cells: Vec<Value> = (worksheets.into_iter())
+        .filter_map(|mut worksheet| match worksheet.get_mut("cells")?.take() {
+            Value::Array(cells) => Some(cells),
+            _ => None,
+        })
+        .flatten()
+        .map(upgrade_v3_cell)
+        .collect();
+    root.insert("cells".into(), cells.into());
+    root.insert("nbformat".into(), 4.into());
+    root.insert("nbformat_minor".into(), 0.into());
+
+    let metadata = root.entry("metadata").or_insert_with(|| Map::new().into());
+    if let Some(metadata) = metadata.as_object_mut() {
+        metadata.insert("orig_nbformat".into(), 3.into());
+    }
+}
+
+/// Upgrade a cell from nbformat 3.
+fn upgrade_v3_cell(mut cell: Value) -> Value {
+    let Some(fields) = cell.as_object_mut() else {
+        return cell;
+    };
+    fields
+        .entry("metadata")
+        .or_insert_with(|| Map::new().into());
+    let cell_type = fields
+        .get("cell_type")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    match cell_type {
+        "code" => {
+            fields.remove("language");
+            let source = fields.remove("input").unwrap_or_else(|| "".into());
+            fields.insert("source".into(), source);
+            let count = fields.remove("prompt_number").unwrap_or(Value::Null);
+            fields.insert("execution_count".into(), count);
+            if let Some(collapsed) = fields.remove("collapsed") {
+                fields["metadata"]["collapsed"] = collapsed;
+            }
+            let outputs = match fields.remove("outputs") {
+                Some(Value::Array(outputs)) => outputs,
+                _ => Vec::new(),
+            };
+            let outputs: Vec<Value> = outputs.into_iter().map(upgrade_v3_output).collect();
+            fields.insert("outputs".into(), outputs.into());
+        }
+        "heading" => {
+            let level = fields.remove("level").and_then(|l| l.as_u64()).unwrap_or(1);
+            let source = match fields.remove("source") {
+                Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+                Some(Value::String(source)) => source,
+                _ => String::new(),
+            };
+            // Headings are a single line in Markdown.
+            let source = source.lines().collect::<Vec<_>>().join(" ");
+            let prefix = "#".repeat(level.clamp(1, 6) as usize);
+            fields.insert("source".into(), format!("{prefix} {source}").into());
+            fields.insert("cell_type".into(), "markdown".into());
+        }
+        "html" => {
+            fields.insert("cell_type".into(), "markdown".into());
+        }
+        _ => {}
+    }
+    cell
+}
+
+/// Upgrade an output of a code cell from nbformat 3.
+fn upgrade_v3_output(mut output: Value) -> Value {
+    let Some(fields) = output.as_object_mut() else {
+        return output;
+    };
+    let output_type = fields
+        .get("output_type")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    match output_type {
+        "pyout" | "display_data" => {
+            if output_type == "pyout" {
+                fields.insert("output_type".into(), "execute_result".into());
+                let count = fields.remove("prompt_number").unwrap_or(Value::Null);
+                fields.insert("execution_count".into(), count);
+            }
+            let mut data = Map::new();
+            for (short, mime) in V3_MIME_TYPES {
+                if let Some(value) = fields.remove(*short) {
+                    data.insert(mime.to_string(), value);
+                }
+            }
+            // JSON data is a string in version 3, but an object in version 4.
+            if let Some(json) = data.get_mut("application/json") {
+                let text = match json {
+                    Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+                    Value::String(text) => text.clone(),
+                    _ => String::new(),
+                };
+                if let Ok(value) = serde_json::from_str(&text) {
+                    *json = value;
+                }
+            }
+            fields.insert("data".into(), data.into());
+            let mut metadata = match fields.remove("metadata") {
+                Some(Value::Object(metadata)) => metadata,
+                _ => Map::new(),
+            };
+            for (short, mime) in V3_MIME_TYPES {
+                if let Some(value) = metadata.remove(*short) {
+                    metadata.insert(mime.to_string(), value);
+                }
+            }
+            fields.insert("metadata".into(), metadata.into());
+        }
+        "pyerr" => {
+            fields.insert("output_type".into(), "error".into());
+        }
+        "stream" => {
+            let name = fields.remove("stream").unwrap_or_else(|| "stdout".into());
+            fields.insert("name".into(), name);
+        }
+        _ => {}
+    }
+    output
+}
+
+/// Merge consecutive stream outputs with the same name into one, like Jupyter
+/// does, so that printing in a loop doesn't make an output for every line.
+pub fn merge_streams(outputs: &mut Vec<Output>) {
+    let mut merged: Vec<Output> = Vec::with_capacity(outputs.len());
+    let mut joined = false;
+    for output in outputs.drain(..) {
+        match (merged.last_mut(), output) {
+            (Some(Output::Stream(last)), Output::Stream(next)) if last.name == next.name => {
+                last.text.push(next.text);
+                joined = true;
+                continue;
+            }
+            (_, output) => {
+                if joined {
+                    normalize_last(&mut merged);
+                    joined = false;
+                }
+                merged.push(output);
+            }
+        }
+    }
+    if joined {
+        normalize_last(&mut merged);
+    }
+    *outputs = merged;
+}
+
+/// Split the text of a merged stream output into lines.
+fn normalize_last(outputs: &mut [Output]) {
+    if let Some(Output::Stream(stream)) = outputs.last_mut() {
+        stream.text = stream.text.normalize();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -464,4 +980,248 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn merge_stream_outputs() {
+        let stream = |name: &str, text: &str| {
+            Output::Stream(OutputStream {
+                name: name.into(),
+                text: MultilineString::Single(text.into()),
+                other: Map::new(),
+            })
+        };
+        let error = Output::Error(OutputError {
+            ename: "ValueError".into(),
+            evalue: String::new(),
+            traceback: vec![],
+            other: Map::new(),
+        });
+        let mut outputs: Vec<_> = (0..1000)
+            .map(|i| stream("stdout", &format!("{i}\n")))
+            .collect();
+        outputs.push(stream("stderr", "oops\n"));
+        outputs.push(error.clone());
+        outputs.push(stream("stderr", "a"));
+        outputs.push(stream("stderr", "b\n"));
+
+        merge_streams(&mut outputs);
+        assert_eq!(outputs.len(), 4);
+        let Output::Stream(stdout) = &outputs[0] else {
+            panic!("expected stream output");
+        };
+        let MultilineString::Multi(lines) = &stdout.text else {
+            panic!("expected merged lines");
+        };
+        assert_eq!(lines.len(), 1000);
+        assert_eq!(lines[999], "999\n");
+        assert_eq!(outputs[1], stream("stderr", "oops\n"));
+        assert_eq!(outputs[2], error);
+        let Output::Stream(stderr) = &outputs[3] else {
+            panic!("expected stream output");
+        };
+        assert_eq!(stderr.text, MultilineString::Multi(vec!["ab\n".into()]));
+    }
+
+    #[test]
+    fn parse_slide_types() {
+        let metadata: CellMetadata =
+            serde_json::from_str(r#"{"slideshow": {"slide_type": "subslide"}}"#).unwrap();
+        assert_eq!(metadata.slideshow.unwrap().slide_type, SlideType::Subslide);
+
+        let metadata: CellMetadata =
+            serde_json::from_str(r#"{"slideshow": {"slide_type": "-"}}"#).unwrap();
+        assert_eq!(metadata.slideshow.unwrap().slide_type, SlideType::Continue);
+
+        let metadata: CellMetadata =
+            serde_json::from_str(r#"{"slideshow": {"slide_type": "unknown"}}"#).unwrap();
+        assert_eq!(metadata.slideshow.unwrap().slide_type, SlideType::Continue);
+
+        // Cells without slideshow metadata do not gain it when saved.
+        let metadata: CellMetadata = serde_json::from_str(r#"{"tags": []}"#).unwrap();
+        assert_eq!(metadata.slideshow, None);
+        assert_eq!(serde_json::to_string(&metadata).unwrap(), r#"{"tags":[]}"#);
+    }
+
+    #[test]
+    fn parse_output_visibility() {
+        let metadata: CellMetadata =
+            serde_json::from_str(r#"{"collapsed": true, "scrolled": false}"#).unwrap();
+        assert_eq!(metadata.collapsed, Some(true));
+        assert_eq!(metadata.scrolled, Some(false));
+
+        let metadata: CellMetadata = serde_json::from_str(r#"{"scrolled": "auto"}"#).unwrap();
+        assert_eq!(metadata.scrolled, None);
+        assert_eq!(serde_json::to_string(&metadata).unwrap(), "{}");
+    }
+
+    #[test]
+    fn write_ipynb() {
+        let json = r#"{
+            "metadata": {},
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "cells": [
+                {
+                    "cell_type": "code",
+                    "id": "a",
+                    "metadata": {},
+                    "source": "x = 1\nx",
+                    "execution_count": 1,
+                    "outputs": [
+                        {
+                            "output_type": "execute_result",
+                            "execution_count": 1,
+                            "data": {"text/plain": "1\n2", "application/json": "a\nb"},
+                            "metadata": {}
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let notebook: NotebookRoot = serde_json::from_str(json).unwrap();
+        let ipynb = notebook.to_ipynb().unwrap();
+        let expected = r#"{
+ "cells": [
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "id": "a",
+   "metadata": {},
+   "outputs": [
+    {
+     "data": {
+      "application/json": "a\nb",
+      "text/plain": [
+       "1\n",
+       "2"
+      ]
+     },
+     "execution_count": 1,
+     "metadata": {},
+     "output_type": "execute_result"
+    }
+   ],
+   "source": [
+    "x = 1\n",
+    "x"
+   ]
+  }
+ ],
+ "metadata": {},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"#;
+        assert_eq!(ipynb, expected);
+        assert_eq!(
+            serde_json::from_str::<NotebookRoot>(&ipynb)
+                .unwrap()
+                .cells
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn output_buffers_round_trip() {
+        use base64::prelude::*;
+
+        let buffer = b"\x00\x01binary";
+        let mut metadata = OutputMetadata::new();
+        metadata.insert("jute".into(), serde_json::json!({ "trusted": true }));
+        save_output_buffers(&mut metadata, vec![BASE64_STANDARD.encode(buffer)]);
+        let output = Output::DisplayData(OutputDisplayData {
+            data: MimeBundle::new(),
+            metadata,
+            other: Map::new(),
+        });
+
+        let json = serde_json::to_string(&output).unwrap();
+        let Output::DisplayData(display) = serde_json::from_str(&json).unwrap() else {
+            panic!("expected display data");
+        };
+        let jute = &display.metadata["jute"];
+        assert_eq!(jute["trusted"], true);
+        let saved = jute["buffers"][0].as_str().unwrap();
+        assert_eq!(BASE64_STANDARD.decode(saved).unwrap(), buffer);
+    }
+
+    #[test]
+    fn upgrade_from_v3() {
+        let json = r#"{
+            "metadata": {"name": "old"},
+            "nbformat": 3,
+            "nbformat_minor": 0,
+            "worksheets": [{
+                "cells": [
+                    {"cell_type": "heading", "level": 2, "metadata": {}, "source": ["Intro"]},
+                    {
+                        "cell_type": "code",
+                        "collapsed": false,
+                        "input": ["print(1)\n", "1"],
+                        "language": "python",
+                        "metadata": {},
+                        "prompt_number": 3,
+                        "outputs": [
+                            {"output_type": "stream", "stream": "stdout", "text": ["1\n"]},
+                            {
+                                "output_type": "pyout",
+                                "prompt_number": 3,
+                                "text": ["1"],
+                                "png": "iVBOR",
+                                "json": ["{\"a\": ", "[1, 2]}"],
+                                "metadata": {"png": {"width": 10}}
+                            },
+                            {"output_type": "pyerr", "ename": "E", "evalue": "", "traceback": []}
+                        ]
+                    }
+                ]
+            }]
+        }"#;
+        let notebook = NotebookRoot::from_ipynb(json).unwrap();
+        assert_eq!(notebook.nbformat, 4);
+        assert_eq!(notebook.metadata.orig_nbformat, Some(3));
+        let [Cell::Markdown(heading), Cell::Code(code)] = &notebook.cells[..] else {
+            panic!("expected a heading and a code cell");
+        };
+        assert_eq!(String::from(heading.source.clone()), "## Intro");
+        assert_eq!(String::from(code.source.clone()), "print(1)\n1");
+        assert_eq!(code.execution_count, Some(3));
+        assert_eq!(code.metadata.collapsed, Some(false));
+        let [Output::Stream(stream), Output::ExecuteResult(result), Output::Error(_)] =
+            &code.outputs[..]
+        else {
+            panic!("expected upgraded outputs");
+        };
+        assert_eq!(stream.name, "stdout");
+        assert_eq!(result.execution_count, Some(3));
+        assert_eq!(result.data["image/png"], "iVBOR");
+        assert_eq!(result.data["application/json"]["a"][1], 2);
+        assert_eq!(result.metadata["image/png"]["width"], 10);
+    }
+
+    #[test]
+    fn normalize_ids() {
+        let json = r#"{
+            "metadata": {},
+            "nbformat": 4,
+            "nbformat_minor": 4,
+            "cells": [
+                {"cell_type": "raw", "metadata": {}, "source": "x"},
+                {"cell_type": "raw", "id": "a", "metadata": {}, "source": ""},
+                {"cell_type": "raw", "id": "a", "metadata": {}, "source": ""},
+                {"cell_type": "raw", "id": "a-1", "metadata": {}, "source": ""},
+                {"cell_type": "raw", "id": "not valid!", "metadata": {}, "source": "x"}
+            ]
+        }"#;
+        let mut notebook: NotebookRoot = serde_json::from_str(json).unwrap();
+        notebook.normalize_cell_ids();
+        let ids: Vec<_> = notebook.cells.iter().map(|c| c.id().unwrap()).collect();
+        assert_eq!(ids, ["2d711642", "a", "a-2", "a-1", "2d711642-1"]);
+        assert_eq!(notebook.nbformat_minor, 5);
+
+        let before = notebook.clone();
+        notebook.normalize_cell_ids();
+        assert_eq!(notebook, before);
+    }
 }
@@ -0,0 +1,323 @@
+//! Positions of the outputs of cells run on a kernel, kept so that updates to
+//! a display ID reach every output shown with it, even in other cells.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use super::{
+    commands::RunCellEvent,
+    notebook::{
+        save_output_buffers, MultilineString, Output, OutputDisplayData, OutputError,
+        OutputExecuteResult, OutputStream,
+    },
+    wire_protocol::DisplayData,
+};
+
+/// Position of an output in a notebook.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct OutputLocation {
+    cell_id: String,
+    index: usize,
+}
+
+/// A display output that was changed by `update_display_data`, to be updated
+/// in place by the frontend.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct OutputUpdate {
+    /// ID of the cell with the output, which may not be the running cell.
+    pub cell_id: String,
+
+    /// Index of the output in the cell.
+    pub index: usize,
+
+    /// The new contents of the output.
+    pub output: OutputDisplayData,
+}
+
+crate::export_bindings!(OutputUpdate);
+
+/// Where the outputs of a cell are, without their contents.
+#[derive(Default, Debug)]
+struct CellLayout {
+    /// Number of outputs in the cell.
+    len: usize,
+
+    /// Name of the stream of the last output, if it is a stream.
+    stream: Option<String>,
+}
+
+/// Positions of the outputs of cells by cell ID, built from the events
+/// received while running them, with an index of the outputs shown with each
+/// display ID.
+///
+/// Only positions are kept, since the frontend has the outputs themselves, and
+/// an update to a display ID replaces its data and metadata.
+#[derive(Default, Debug)]
+pub struct OutputStore {
+    cells: HashMap<String, CellLayout>,
+    display_ids: HashMap<String, Vec<OutputLocation>>,
+}
+
+impl OutputStore {
+    /// Forget the outputs of a cell, as when it starts running again.
+    pub fn clear(&mut self, cell_id: &str) {
+        self.cells.remove(cell_id);
+        for locations in self.display_ids.values_mut() {
+            locations.retain(|location| location.cell_id != cell_id);
+        }
+        self.display_ids
+            .retain(|_, locations| !locations.is_empty());
+    }
+
+    /// Apply an event from running a cell to its outputs.
+    ///
+    /// Updates to a display ID change outputs in any cell, which are returned.
+    pub fn apply(&mut self, cell_id: &str, event: &RunCellEvent) -> Vec<OutputUpdate> {
+        match event {
+            RunCellEvent::Stdout(_) => _ = self.push(cell_id, Some("stdout")),
+            RunCellEvent::Stderr(_) | RunCellEvent::OutputDropped(_) => {
+                self.push(cell_id, Some("stderr"));
+            }
+            RunCellEvent::ExecuteResult(_) | RunCellEvent::Error(_) => {
+                self.push(cell_id, None);
+            }
+            RunCellEvent::DisplayData(display) => {
+                let index = self.push(cell_id, None);
+                let display_id = display
+                    .transient
+                    .as_ref()
+                    .and_then(|t| t.display_id.clone());
+                if let Some(display_id) = display_id {
+                    let locations = self.display_ids.entry(display_id).or_default();
+                    locations.push(OutputLocation {
+                        cell_id: cell_id.into(),
+                        index,
+                    });
+                }
+            }
+            RunCellEvent::UpdateDisplayData(display) => {
+                let Some(locations) = display
+                    .transient
+                    .as_ref()
+                    .and_then(|t| t.display_id.as_ref())
+                    .and_then(|display_id| self.display_ids.get(display_id))
+                else {
+                    return Vec::new();
+                };
+                let output = display_output(display);
+                return locations
+                    .iter()
+                    .map(|location| OutputUpdate {
+                        cell_id: location.cell_id.clone(),
+                        index: location.index,
+                        output: output.clone(),
+                    })
+                    .collect();
+            }
+            RunCellEvent::ClearOutput(_) => self.clear(cell_id),
+            RunCellEvent::Disconnect(_)
+            | RunCellEvent::Aborted
+            | RunCellEvent::InputRequest(_)
+            | RunCellEvent::PasswordRequest(_)
+            | RunCellEvent::Started { .. }
+            | RunCellEvent::ExecutionCount(_)
+            | RunCellEvent::Finished { .. }
+            | RunCellEvent::OutputTruncated(_)
+            | RunCellEvent::Custom { .. }
+            | RunCellEvent::UpdateOutput(_) => {}
+        }
+        Vec::new()
+    }
+
+    /// Add an output to a cell, returning its index. Text on the same stream
+    /// as the last output is merged with it, as the frontend does.
+    fn push(&mut self, cell_id: &str, stream: Option<&str>) -> usize {
+        let layout = self.cells.entry(cell_id.into()).or_default();
+        if stream.is_none() || layout.stream.as_deref() != stream {
+            layout.len += 1;
+            layout.stream = stream.map(String::from);
+        }
+        layout.len - 1
+    }
+}
+
+/// The output that an event adds to a cell, if any.
+fn to_output(event: &RunCellEvent) -> Option<Output> {
+    let stream = |name: &str, text: &str| {
+        Output::Stream(OutputStream {
+            name: name.into(),
+            text: MultilineString::Single(text.into()),
+            other: Default::default(),
+        })
+    };
+    Some(match event {
+        RunCellEvent::Stdout(text) => stream("stdout", text),
+        RunCellEvent::Stderr(text) => stream("stderr", text),
+        RunCellEvent::OutputDropped(count) => {
+            stream("stderr", &format!("[{count} outputs dropped]\n"))
+        }
+        RunCellEvent::ExecuteResult(result) => {
+            let mut metadata = result.metadata.clone();
+            save_output_buffers(&mut metadata, result.buffers.clone());
+            Output::ExecuteResult(OutputExecuteResult {
+                execution_count: u32::try_from(result.execution_count).ok(),
+                data: result.data.clone(),
+                metadata,
+                other: Default::default(),
+            })
+        }
+        RunCellEvent::DisplayData(display) => Output::DisplayData(display_output(display)),
+        RunCellEvent::Error(err) => Output::Error(OutputError {
+            ename: err.ename.clone(),
+            evalue: err.evalue.clone(),
+            traceback: err.traceback.clone(),
+            other: Default::default(),
+        }),
+        _ => return None,
+    })
+}
+
+/// The output for display data, with its buffers in the metadata.
+fn display_output(display: &DisplayData) -> OutputDisplayData {
+    let mut metadata = display.metadata.clone();
+    save_output_buffers(&mut metadata, display.buffers.clone());
+    OutputDisplayData {
+        data: display.data.clone(),
+        metadata,
+        other: Default::default(),
+    }
+}
+
+/// Outputs of cells by cell ID, with their contents, for running notebooks
+/// without the frontend.
+#[derive(Default, Debug)]
+pub struct OutputCollector {
+    store: OutputStore,
+    cells: HashMap<String, Vec<Output>>,
+}
+
+impl OutputCollector {
+    /// Return the outputs of a cell, if it was run, with stream text split
+    /// into lines like Jupyter.
+    pub fn outputs(&self, cell_id: &str) -> Option<Vec<Output>> {
+        let mut outputs = self.cells.get(cell_id)?.clone();
+        for output in &mut outputs {
+            if let Output::Stream(stream) = output {
+                stream.text = stream.text.normalize();
+            }
+        }
+        Some(outputs)
+    }
+
+    /// Remove the outputs of a cell, as when it starts running again.
+    pub fn clear(&mut self, cell_id: &str) {
+        self.store.clear(cell_id);
+        self.cells.insert(cell_id.into(), Vec::new());
+    }
+
+    /// Apply an event from running a cell to the outputs of every cell.
+    pub fn apply(&mut self, cell_id: &str, event: &RunCellEvent) {
+        for update in self.store.apply(cell_id, event) {
+            let output = (self.cells.get_mut(&update.cell_id))
+                .and_then(|outputs| outputs.get_mut(update.index));
+            if let Some(output) = output {
+                *output = Output::DisplayData(update.output);
+            }
+        }
+        if let RunCellEvent::ClearOutput(_) = event {
+            self.cells.insert(cell_id.into(), Vec::new());
+            return;
+        }
+        let Some(output) = to_output(event) else {
+            return;
+        };
+        let outputs = self.cells.entry(cell_id.into()).or_default();
+        match (outputs.last_mut(), output) {
+            (Some(Output::Stream(last)), Output::Stream(next)) if last.name == next.name => {
+                last.text.push(next.text);
+            }
+            (_, output) => outputs.push(output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::backend::wire_protocol::{ClearOutput, DisplayDataTransient};
+
+    fn display(text: &str, id: &str) -> DisplayData {
+        DisplayData {
+            data: BTreeMap::from([("text/plain".into(), json!(text))]),
+            metadata: BTreeMap::new(),
+            transient: Some(DisplayDataTransient {
+                display_id: Some(id.into()),
+            }),
+            message_metadata: json!({}),
+            buffers: vec![],
+        }
+    }
+
+    #[test]
+    fn collect_outputs() {
+        let mut store = OutputCollector::default();
+        store.apply("a", &RunCellEvent::Stdout("a\n".into()));
+        store.apply("a", &RunCellEvent::Stdout("b\n".into()));
+        store.apply("a", &RunCellEvent::Stderr("warning\n".into()));
+        store.apply("a", &RunCellEvent::DisplayData(display("1%", "bar")));
+        store.apply("a", &RunCellEvent::UpdateDisplayData(display("50%", "bar")));
+
+        let outputs = store.outputs("a").unwrap();
+        assert_eq!(outputs.len(), 3);
+        let Output::Stream(stdout) = &outputs[0] else {
+            panic!("expected stream output");
+        };
+        assert_eq!(
+            stdout.text,
+            MultilineString::Multi(vec!["a\n".into(), "b\n".into()])
+        );
+        let Output::DisplayData(bar) = &outputs[2] else {
+            panic!("expected display data");
+        };
+        assert_eq!(bar.data["text/plain"], json!("50%"));
+
+        // A later cell can update the display in the first one.
+        store.apply(
+            "b",
+            &RunCellEvent::UpdateDisplayData(display("100%", "bar")),
+        );
+        let Output::DisplayData(bar) = &store.outputs("a").unwrap()[2] else {
+            panic!("expected display data");
+        };
+        assert_eq!(bar.data["text/plain"], json!("100%"));
+        assert_eq!(store.outputs("b"), None);
+    }
+
+    #[test]
+    fn locate_display_updates() {
+        let mut store = OutputStore::default();
+        store.apply("a", &RunCellEvent::Stdout("a\n".into()));
+        store.apply("a", &RunCellEvent::Stdout("b\n".into()));
+        store.apply("a", &RunCellEvent::Stderr("warning\n".into()));
+        store.apply("a", &RunCellEvent::DisplayData(display("1%", "bar")));
+
+        let updates = store.apply(
+            "b",
+            &RunCellEvent::UpdateDisplayData(display("100%", "bar")),
+        );
+        assert_eq!(updates.len(), 1);
+        assert_eq!((updates[0].cell_id.as_str(), updates[0].index), ("a", 2));
+        assert_eq!(updates[0].output.data["text/plain"], json!("100%"));
+
+        // Clearing a cell forgets the displays in it.
+        store.apply("a", &RunCellEvent::ClearOutput(ClearOutput { wait: false }));
+        let updates = store.apply("b", &RunCellEvent::UpdateDisplayData(display("0%", "bar")));
+        assert!(updates.is_empty());
+    }
+}
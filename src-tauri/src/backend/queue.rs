@@ -0,0 +1,315 @@
+//! Queue of cells waiting to run on a kernel, so that cells submitted while
+//! another is running wait their turn instead of overlapping.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use tokio::sync::{broadcast, Notify};
+use ts_rs::TS;
+
+use super::commands::RunCellEvent;
+
+/// State of a cell submitted to an [`ExecutionQueue`].
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum CellState {
+    /// Waiting for the cells before it to finish.
+    Queued,
+
+    /// Running on the kernel.
+    Running,
+
+    /// Finished without an error.
+    Ok,
+
+    /// Finished with an error, or lost the connection to the kernel.
+    Error,
+
+    /// Removed from the queue before it ran, or skipped by the kernel.
+    Aborted,
+}
+
+crate::export_bindings!(CellState);
+
+/// A change in the state of a cell in an [`ExecutionQueue`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellStateChange {
+    /// ID of the cell.
+    pub cell_id: String,
+
+    /// The new state of the cell.
+    pub state: CellState,
+}
+
+/// A cell in the queue, with a number to tell apart runs of the same cell.
+#[derive(Debug)]
+struct Entry {
+    ticket: u64,
+    cell_id: String,
+    state: CellState,
+    stop_on_error: bool,
+}
+
+/// Contents of an [`ExecutionQueue`], behind its lock.
+#[derive(Debug, Default)]
+struct Inner {
+    next_ticket: u64,
+    entries: VecDeque<Entry>,
+}
+
+/// Runs cells on a kernel one at a time, in the order they were submitted.
+///
+/// When a cell fails, the cells queued after it are aborted, like a kernel
+/// does with requests queued after an error, unless it was enqueued with
+/// `stop_on_error` off.
+#[derive(Debug)]
+pub struct ExecutionQueue {
+    inner: Mutex<Inner>,
+    notify: Notify,
+    changes: broadcast::Sender<CellStateChange>,
+}
+
+impl Default for ExecutionQueue {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::default(),
+            notify: Notify::new(),
+            changes: broadcast::channel(256).0,
+        }
+    }
+}
+
+impl ExecutionQueue {
+    /// Add a cell to the end of the queue. It runs once the ticket's turn
+    /// comes, see [`QueueTicket::wait`].
+    ///
+    /// With `stop_on_error`, the cells queued after it are aborted if it fails.
+    pub fn enqueue(self: &Arc<Self>, cell_id: &str, stop_on_error: bool) -> QueueTicket {
+        let mut inner = self.inner.lock().unwrap();
+        let ticket = inner.next_ticket;
+        inner.next_ticket += 1;
+        inner.entries.push_back(Entry {
+            ticket,
+            cell_id: cell_id.into(),
+            state: CellState::Queued,
+            stop_on_error,
+        });
+        self.emit(cell_id, CellState::Queued);
+        QueueTicket {
+            queue: self.clone(),
+            ticket,
+            outcome: CellState::Ok,
+            completed: false,
+        }
+    }
+
+    /// The cells that are queued or running, in order.
+    pub fn snapshot(&self) -> Vec<CellStateChange> {
+        let inner = self.inner.lock().unwrap();
+        (inner.entries.iter())
+            .map(|entry| CellStateChange {
+                cell_id: entry.cell_id.clone(),
+                state: entry.state,
+            })
+            .collect()
+    }
+
+    /// Abort every cell that is waiting to run. A running cell is left to
+    /// finish.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        self.abort_queued(&mut inner);
+        drop(inner);
+        self.notify.notify_waiters();
+    }
+
+    /// Subscribe to changes in the states of cells.
+    pub fn subscribe(&self) -> broadcast::Receiver<CellStateChange> {
+        self.changes.subscribe()
+    }
+
+    fn abort_queued(&self, inner: &mut Inner) {
+        inner.entries.retain(|entry| {
+            let queued = entry.state == CellState::Queued;
+            if queued {
+                self.emit(&entry.cell_id, CellState::Aborted);
+            }
+            !queued
+        });
+    }
+
+    fn emit(&self, cell_id: &str, state: CellState) {
+        _ = self.changes.send(CellStateChange {
+            cell_id: cell_id.into(),
+            state,
+        });
+    }
+
+    /// Start the ticket if it is first in line, returning `None` if it is
+    /// no longer in the queue.
+    fn try_start(&self, ticket: u64) -> Option<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        let index = inner.entries.iter().position(|e| e.ticket == ticket)?;
+        if index > 0 {
+            return Some(false);
+        }
+        let entry = &mut inner.entries[0];
+        entry.state = CellState::Running;
+        self.emit(&entry.cell_id, CellState::Running);
+        Some(true)
+    }
+
+    fn finish(&self, ticket: u64, outcome: CellState) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(index) = inner.entries.iter().position(|e| e.ticket == ticket) else {
+            return;
+        };
+        let entry = inner.entries.remove(index).unwrap();
+        match entry.state {
+            CellState::Running => {
+                self.emit(&entry.cell_id, outcome);
+                if entry.stop_on_error && outcome != CellState::Ok {
+                    self.abort_queued(&mut inner);
+                }
+            }
+            _ => self.emit(&entry.cell_id, CellState::Aborted),
+        }
+        drop(inner);
+        self.notify.notify_waiters();
+    }
+}
+
+/// A cell's place in an [`ExecutionQueue`]. Dropping it finishes the cell, as
+/// an error unless [`QueueTicket::complete`] was called.
+pub struct QueueTicket {
+    queue: Arc<ExecutionQueue>,
+    ticket: u64,
+    outcome: CellState,
+    completed: bool,
+}
+
+impl QueueTicket {
+    /// Wait for the cells before this one to finish, then mark it running.
+    ///
+    /// Returns false if the cell was aborted before its turn came, in which
+    /// case it should not be run.
+    pub async fn wait(&self) -> bool {
+        loop {
+            let notified = self.queue.notify.notified();
+            match self.queue.try_start(self.ticket) {
+                Some(true) => return true,
+                Some(false) => notified.await,
+                None => return false,
+            }
+        }
+    }
+
+    /// Record an event from running the cell, to decide how it finished.
+    pub fn record(&mut self, event: &RunCellEvent) {
+        match event {
+            RunCellEvent::Error(_) | RunCellEvent::Disconnect(_) => {
+                self.outcome = CellState::Error;
+            }
+            RunCellEvent::Aborted if self.outcome == CellState::Ok => {
+                self.outcome = CellState::Aborted;
+            }
+            _ => {}
+        }
+    }
+
+    /// Finish the cell after all of its events were recorded.
+    pub fn complete(mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        let outcome = match self.completed {
+            true => self.outcome,
+            false => CellState::Error,
+        };
+        self.queue.finish(self.ticket, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::wire_protocol::ErrorReply;
+
+    #[tokio::test]
+    async fn run_cells_in_order() {
+        let queue = Arc::new(ExecutionQueue::default());
+        let mut changes = queue.subscribe();
+        let first = queue.enqueue("a", true);
+        let second = queue.enqueue("b", true);
+        let third = queue.enqueue("c", true);
+
+        assert!(first.wait().await);
+        let waiting = tokio::spawn(async move { (second.wait().await, second) });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        let states: Vec<_> = queue.snapshot().into_iter().map(|c| c.state).collect();
+        assert_eq!(
+            states,
+            [CellState::Running, CellState::Queued, CellState::Queued]
+        );
+
+        first.complete();
+        let (started, mut second) = waiting.await.unwrap();
+        assert!(started);
+
+        // An error aborts the cells queued after it.
+        second.record(&RunCellEvent::Error(ErrorReply {
+            ename: "ValueError".into(),
+            evalue: String::new(),
+            traceback: vec![],
+        }));
+        second.complete();
+        assert!(!third.wait().await);
+        assert!(queue.snapshot().is_empty());
+
+        let mut log = Vec::new();
+        while let Ok(change) = changes.try_recv() {
+            log.push((change.cell_id, change.state));
+        }
+        let expected = [
+            ("a", CellState::Queued),
+            ("b", CellState::Queued),
+            ("c", CellState::Queued),
+            ("a", CellState::Running),
+            ("a", CellState::Ok),
+            ("b", CellState::Running),
+            ("b", CellState::Error),
+            ("c", CellState::Aborted),
+        ];
+        let expected: Vec<_> = (expected.iter())
+            .map(|(id, state)| (id.to_string(), *state))
+            .collect();
+        assert_eq!(log, expected);
+    }
+
+    #[tokio::test]
+    async fn keep_running_after_error() {
+        let queue = Arc::new(ExecutionQueue::default());
+        let mut first = queue.enqueue("a", false);
+        let second = queue.enqueue("b", false);
+        let third = queue.enqueue("c", false);
+
+        assert!(first.wait().await);
+        first.record(&RunCellEvent::Disconnect("lost".into()));
+        first.complete();
+        assert!(second.wait().await);
+        assert_eq!(queue.snapshot().len(), 2);
+
+        // Clearing the queue still aborts the cells waiting to run.
+        queue.clear();
+        assert!(!third.wait().await);
+        second.complete();
+        assert!(queue.snapshot().is_empty());
+    }
+}
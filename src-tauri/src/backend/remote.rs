@@ -9,9 +9,10 @@ use reqwest::{
 use serde::Deserialize;
 use serde_json::json;
 use time::OffsetDateTime;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, handshake::client::Request};
 use url::Url;
 
-use super::{create_websocket_connection, KernelConnection};
+use super::{create_websocket_connection, wire_protocol::ConnectionOptions, KernelConnection};
 use crate::Error;
 
 /// A running Jupyter kernel connected over the WebSocket wire protocol.
@@ -27,17 +28,14 @@ impl RemoteKernel {
     pub async fn start(client: &JupyterClient, spec_name: &str) -> Result<Self, Error> {
         let kernel_info = client.create_kernel(spec_name).await?;
 
-        let ws_url = client
-            .server_url
-            .join(&format!("/api/kernels/{}/channels", kernel_info.id))?;
-        let mut ws_url = ws_url.to_string();
-        if ws_url.starts_with("https://") {
-            ws_url = ws_url.replacen("https://", "wss://", 1);
-        } else {
-            ws_url = ws_url.replacen("http://", "ws://", 1);
-        }
-
-        let conn = create_websocket_connection(&ws_url, &client.token).await?;
+        // The server tells clients apart by the session ID, which must match
+        // the one in message headers.
+        let options = ConnectionOptions::default();
+        let ws_url = client.websocket_url(&format!(
+            "/api/kernels/{}/channels?session_id={}",
+            kernel_info.id, options.session,
+        ))?;
+        let conn = create_websocket_connection(&ws_url, &client.token, &options).await?;
 
         Ok(Self {
             client: client.clone(),
@@ -95,6 +93,16 @@ impl JupyterClient {
         })
     }
 
+    /// Get the WebSocket URL of an endpoint on the server.
+    fn websocket_url(&self, path: &str) -> Result<String, Error> {
+        let url = self.server_url.join(path)?.to_string();
+        Ok(if url.starts_with("https://") {
+            url.replacen("https://", "wss://", 1)
+        } else {
+            url.replacen("http://", "ws://", 1)
+        })
+    }
+
     /// Get the API version of the Jupyter server.
     pub async fn get_api_version(&self) -> Result<String, Error> {
         let url = self.server_url.join("/api")?;
@@ -147,6 +155,55 @@ impl JupyterClient {
             .error_for_status()?;
         Ok(())
     }
+
+    /// Open a notebook in the server's collaboration session, returning the
+    /// request to connect to its room.
+    ///
+    /// This needs the `jupyter-collaboration` extension on the server. The
+    /// room syncs a Yjs document of the notebook with everyone who has it open,
+    /// including JupyterLab users, and the server saves it to disk.
+    pub async fn collab_room(&self, path: &str) -> Result<Request, Error> {
+        let url = self
+            .server_url
+            .join(&format!("/api/collaboration/session/{path}"))?;
+        let resp = self
+            .http_client
+            .put(url)
+            .json(&json!({ "format": "json", "type": "notebook" }))
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(Error::Collab(
+                "the server does not have jupyter-collaboration installed".into(),
+            ));
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct CollabSession {
+            format: String,
+            r#type: String,
+            file_id: String,
+            session_id: String,
+        }
+        let session: CollabSession = resp.error_for_status()?.json().await?;
+        let room = format!("{}:{}:{}", session.format, session.r#type, session.file_id);
+        let mut ws_url =
+            Url::parse(&self.websocket_url(&format!("/api/collaboration/room/{room}"))?)?;
+        ws_url
+            .query_pairs_mut()
+            .append_pair("sessionId", &session.session_id);
+
+        let mut req = ws_url
+            .as_str()
+            .into_client_request()
+            .map_err(|err| Error::Collab(err.to_string()))?;
+        let token = format!("token {}", self.token)
+            .parse()
+            .map_err(|_| Error::Collab("invalid server token".into()))?;
+        req.headers_mut().insert(header::AUTHORIZATION, token);
+        Ok(req)
+    }
 }
 
 /// Information about a remote Jupyter kernel.
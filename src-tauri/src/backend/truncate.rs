@@ -0,0 +1,389 @@
+//! Truncation of large outputs, so that a cell printing a lot of text or
+//! showing a large result can't flood the channel to the window. The text
+//! past the limit is kept, for the frontend to fetch in chunks when the user
+//! expands the output or saves the notebook.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use super::commands::RunCellEvent;
+
+/// An output that was cut short, sent right after the part of it that is
+/// shown.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct TruncatedOutput {
+    /// ID of the text that was cut, to fetch in chunks.
+    pub id: String,
+
+    /// Name of the stream, or the MIME type of a result's data.
+    pub name: String,
+
+    /// Whether the whole value was left out, since part of it can't be shown,
+    /// like an image. Its text is JSON, to parse once all of it is fetched.
+    pub withheld: bool,
+}
+
+crate::export_bindings!(TruncatedOutput);
+
+/// A chunk of the text cut from a truncated output.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct OutputChunk {
+    /// Text of the chunk.
+    pub text: String,
+
+    /// Offset of the next chunk, in bytes.
+    #[ts(type = "number")]
+    pub next_offset: usize,
+
+    /// Number of bytes after this chunk. A stream that is still running may
+    /// add more.
+    #[ts(type = "number")]
+    pub remaining: usize,
+}
+
+crate::export_bindings!(OutputChunk);
+
+/// Most text kept from a stream that was cut, so that a cell printing forever
+/// doesn't use up memory. The rest is dropped, with [`DROPPED_MARKER`].
+const MAX_CUT_STREAM: usize = 32 << 20;
+
+/// Line added to a stream's cut text where the rest of it was dropped.
+const DROPPED_MARKER: &str = "\n[Output too large, the rest was dropped.]\n";
+
+/// Text cut from an output, and the cell it came from.
+#[derive(Debug)]
+struct CutText {
+    cell_id: String,
+    text: String,
+}
+
+/// Text cut from the outputs of the cells run on a kernel, by ID.
+#[derive(Default, Debug)]
+pub struct TruncatedOutputs {
+    texts: HashMap<String, CutText>,
+}
+
+impl TruncatedOutputs {
+    /// Forget the text cut from a cell's outputs, as when they are cleared.
+    pub fn clear(&mut self, cell_id: &str) {
+        self.texts.retain(|_, cut| cut.cell_id != cell_id);
+    }
+
+    /// Return a chunk of about `length` bytes of cut text from `offset`, or
+    /// `None` if the text is no longer kept. The chunk ends on a character
+    /// boundary, and has at least one character if any are left.
+    pub fn chunk(&self, id: &str, offset: usize, length: usize) -> Option<OutputChunk> {
+        let text = &self.texts.get(id)?.text;
+        let start = floor_char_boundary(text, offset);
+        let mut end = floor_char_boundary(text, start.saturating_add(length));
+        if end == start {
+            end += text[start..].chars().next().map_or(0, char::len_utf8);
+        }
+        Some(OutputChunk {
+            text: text[start..end].into(),
+            next_offset: end,
+            remaining: text.len() - end,
+        })
+    }
+
+    /// Keep text cut from an output of a cell, returning its ID. Without a
+    /// cell, the text is dropped.
+    fn insert(&mut self, cell_id: Option<&str>, text: String) -> String {
+        let id = Uuid::new_v4().to_string();
+        if let Some(cell_id) = cell_id {
+            let cut = CutText {
+                cell_id: cell_id.into(),
+                text,
+            };
+            self.texts.insert(id.clone(), cut);
+        }
+        id
+    }
+
+    /// Add text to the end of a stream's cut text, up to [`MAX_CUT_STREAM`].
+    fn append(&mut self, id: &str, text: &str) {
+        let Some(cut) = self.texts.get_mut(id) else {
+            return;
+        };
+        let room = MAX_CUT_STREAM.saturating_sub(cut.text.len());
+        if text.len() <= room {
+            cut.text.push_str(text);
+        } else if room > 0 {
+            let end = floor_char_boundary(text, room);
+            cut.text.push_str(&text[..end]);
+            cut.text.push_str(DROPPED_MARKER);
+        }
+    }
+}
+
+/// Truncates the outputs of one run of a cell past a limit in bytes.
+///
+/// Each stream is shown up to the limit, and the rest of it is cut. Each
+/// value in the data of a result is limited separately: text is cut, while
+/// other values, like images and JSON, are withheld whole.
+#[derive(Debug)]
+pub struct OutputTruncator {
+    cell_id: Option<String>,
+    limit: usize,
+    streams: HashMap<String, StreamState>,
+}
+
+/// How much of a stream was shown, and the ID of its cut text once it passes
+/// the limit.
+#[derive(Debug, Default)]
+struct StreamState {
+    shown: usize,
+    cut: Option<String>,
+}
+
+impl OutputTruncator {
+    /// Create a truncator for a run of a cell.
+    pub fn new(cell_id: &str, limit: usize) -> Self {
+        Self {
+            cell_id: Some(cell_id.into()),
+            limit,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Create a truncator for a run that isn't tied to a cell, like in a
+    /// subshell. There is no output to expand, so the cut text is not kept.
+    pub fn without_cell(limit: usize) -> Self {
+        Self {
+            cell_id: None,
+            limit,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Truncate an event, keeping the text cut from it in `store`.
+    ///
+    /// Returns the events to send in its place, which are none once a stream
+    /// is cut, and followed by [`RunCellEvent::OutputTruncated`] when this
+    /// event is cut.
+    pub fn apply(
+        &mut self,
+        event: RunCellEvent,
+        store: &mut TruncatedOutputs,
+    ) -> Vec<RunCellEvent> {
+        match event {
+            RunCellEvent::Stdout(text) => self.stream("stdout", text, store),
+            RunCellEvent::Stderr(text) => self.stream("stderr", text, store),
+            RunCellEvent::ExecuteResult(mut result) => {
+                let cut = self.truncate_data(&mut result.data, store);
+                [RunCellEvent::ExecuteResult(result)]
+                    .into_iter()
+                    .chain(cut)
+                    .collect()
+            }
+            RunCellEvent::DisplayData(mut display) => {
+                let cut = self.truncate_data(&mut display.data, store);
+                [RunCellEvent::DisplayData(display)]
+                    .into_iter()
+                    .chain(cut)
+                    .collect()
+            }
+            RunCellEvent::ClearOutput(_) => {
+                self.streams.clear();
+                if let Some(cell_id) = &self.cell_id {
+                    store.clear(cell_id);
+                }
+                vec![event]
+            }
+            event => vec![event],
+        }
+    }
+
+    fn stream(
+        &mut self,
+        name: &str,
+        mut text: String,
+        store: &mut TruncatedOutputs,
+    ) -> Vec<RunCellEvent> {
+        let stream = self.streams.entry(name.into()).or_default();
+        if let Some(id) = &stream.cut {
+            store.append(id, &text);
+            return Vec::new();
+        }
+        let to_event = |text| match name {
+            "stdout" => RunCellEvent::Stdout(text),
+            _ => RunCellEvent::Stderr(text),
+        };
+        if stream.shown + text.len() <= self.limit {
+            stream.shown += text.len();
+            return vec![to_event(text)];
+        }
+
+        // The shown part is sent even if empty, so that the frontend has a
+        // stream output to add the cut text to.
+        let end = floor_char_boundary(&text, self.limit - stream.shown);
+        let mut rest = text.split_off(end);
+        if rest.len() > MAX_CUT_STREAM {
+            rest.truncate(floor_char_boundary(&rest, MAX_CUT_STREAM));
+            rest.push_str(DROPPED_MARKER);
+        }
+        let id = store.insert(self.cell_id.as_deref(), rest);
+        stream.shown = self.limit;
+        stream.cut = Some(id.clone());
+        vec![
+            to_event(text),
+            RunCellEvent::OutputTruncated(TruncatedOutput {
+                id,
+                name: name.into(),
+                withheld: false,
+            }),
+        ]
+    }
+
+    fn truncate_data(
+        &self,
+        data: &mut BTreeMap<String, Value>,
+        store: &mut TruncatedOutputs,
+    ) -> Vec<RunCellEvent> {
+        let mut events = Vec::new();
+        let mut withheld = Vec::new();
+        for (mime, value) in data.iter_mut() {
+            match value {
+                Value::String(text) if is_text(mime) => {
+                    if text.len() <= self.limit {
+                        continue;
+                    }
+                    let end = floor_char_boundary(text, self.limit);
+                    let id = store.insert(self.cell_id.as_deref(), text.split_off(end));
+                    events.push(RunCellEvent::OutputTruncated(TruncatedOutput {
+                        id,
+                        name: mime.clone(),
+                        withheld: false,
+                    }));
+                }
+                Value::String(text) if text.len() <= self.limit => {}
+                _ => {
+                    let text = value.to_string();
+                    if text.len() > self.limit {
+                        withheld.push(mime.clone());
+                        let id = store.insert(self.cell_id.as_deref(), text);
+                        events.push(RunCellEvent::OutputTruncated(TruncatedOutput {
+                            id,
+                            name: mime.clone(),
+                            withheld: true,
+                        }));
+                    }
+                }
+            }
+        }
+        for mime in withheld {
+            data.remove(&mime);
+        }
+        events
+    }
+}
+
+/// Whether the data of a MIME type is text that can be shown in part.
+fn is_text(mime: &str) -> bool {
+    mime.starts_with("text/")
+}
+
+/// The largest character boundary in `text` at or before `index`.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::backend::wire_protocol::DisplayData;
+
+    #[test]
+    fn truncate_streams() {
+        let mut store = TruncatedOutputs::default();
+        let mut truncator = OutputTruncator::new("a", 8);
+        let events = truncator.apply(RunCellEvent::Stdout("hello ".into()), &mut store);
+        assert!(matches!(&events[..], [RunCellEvent::Stdout(text)] if text == "hello "));
+
+        // The stream is cut at the limit, on a character boundary.
+        let events = truncator.apply(RunCellEvent::Stdout("wörld".into()), &mut store);
+        let [RunCellEvent::Stdout(shown), RunCellEvent::OutputTruncated(truncated)] = &events[..]
+        else {
+            panic!("expected the stream to be truncated");
+        };
+        assert_eq!(shown, "w");
+        assert_eq!(truncated.name, "stdout");
+
+        // Later text is kept, while other streams are still sent.
+        let events = truncator.apply(RunCellEvent::Stdout("!\n".into()), &mut store);
+        assert!(events.is_empty());
+        let events = truncator.apply(RunCellEvent::Stderr("warning\n".into()), &mut store);
+        assert_eq!(events.len(), 1);
+
+        let chunk = store.chunk(&truncated.id, 0, 2).unwrap();
+        assert_eq!((chunk.text.as_str(), chunk.remaining), ("ö", 5));
+        let chunk = store.chunk(&truncated.id, chunk.next_offset, 100).unwrap();
+        assert_eq!((chunk.text.as_str(), chunk.remaining), ("rld!\n", 0));
+
+        store.clear("a");
+        assert_eq!(store.chunk(&truncated.id, 0, 100), None);
+    }
+
+    #[test]
+    fn truncate_results() {
+        let mut store = TruncatedOutputs::default();
+        let mut truncator = OutputTruncator::new("a", 8);
+        let data = BTreeMap::from([
+            ("text/plain".into(), json!("<Figure>")),
+            ("text/html".into(), json!("<p>a large table</p>")),
+            ("image/png".into(), json!("iVBORw0KGgoAAAANSUhEUgAA")),
+            ("application/json".into(), json!({"a": 1})),
+        ]);
+        let display = DisplayData {
+            data,
+            metadata: BTreeMap::new(),
+            transient: None,
+            message_metadata: Value::Null,
+            buffers: Vec::new(),
+        };
+        let events = truncator.apply(RunCellEvent::DisplayData(display), &mut store);
+        let [RunCellEvent::DisplayData(display), cut @ ..] = &events[..] else {
+            panic!("expected the display data first");
+        };
+
+        // Text is cut, and values that can't be shown in part are withheld.
+        assert_eq!(display.data["text/html"], "<p>a lar");
+        assert_eq!(display.data["text/plain"], "<Figure>");
+        assert!(!display.data.contains_key("image/png"));
+        assert!(display.data.contains_key("application/json"));
+        let cut: Vec<_> = (cut.iter())
+            .map(|event| match event {
+                RunCellEvent::OutputTruncated(truncated) => truncated,
+                _ => panic!("expected a truncated output"),
+            })
+            .collect();
+        assert_eq!(cut.len(), 2);
+        assert_eq!((cut[0].name.as_str(), cut[0].withheld), ("image/png", true));
+        assert_eq!(
+            (cut[1].name.as_str(), cut[1].withheld),
+            ("text/html", false)
+        );
+        let image = store.chunk(&cut[0].id, 0, 100).unwrap().text;
+        assert_eq!(image, "\"iVBORw0KGgoAAAANSUhEUgAA\"");
+
+        // Runs without a cell are truncated, but the cut text isn't kept.
+        let mut truncator = OutputTruncator::without_cell(4);
+        let events = truncator.apply(RunCellEvent::Stderr("warning\n".into()), &mut store);
+        let [RunCellEvent::Stderr(shown), RunCellEvent::OutputTruncated(truncated)] = &events[..]
+        else {
+            panic!("expected the stream to be truncated");
+        };
+        assert_eq!(shown, "warn");
+        assert_eq!(store.chunk(&truncated.id, 0, 100), None);
+    }
+}
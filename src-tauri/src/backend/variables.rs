@@ -0,0 +1,178 @@
+//! Variable explorer, which lists the variables in a kernel's namespace by
+//! running introspection code for the kernel's language.
+//!
+//! Each supported language has a [`VariableQuery`], whose code prints the
+//! variables as JSON on a line of standard output, after a marker.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::{
+    commands::{self, RunCellEvent, RunCellOptions},
+    KernelConnection,
+};
+use crate::Error;
+
+/// Printed before the JSON list of variables, so that other output from the
+/// kernel is ignored.
+const MARKER: &str = "__jute_variables__:";
+
+/// A variable in the user namespace of a kernel.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct Variable {
+    /// Name of the variable.
+    pub name: String,
+
+    /// Name of the type of its value, like `DataFrame`.
+    pub type_name: String,
+
+    /// Shape of an array or table, or length of a collection, if it has one.
+    pub shape: Option<String>,
+
+    /// Approximate size of the value in memory, in bytes, if known.
+    #[ts(type = "number | null")]
+    pub size: Option<u64>,
+
+    /// Short preview of the value.
+    pub summary: String,
+}
+
+crate::export_bindings!(Variable);
+
+/// Introspection code that lists the variables of kernels in a language.
+#[derive(Debug)]
+pub struct VariableQuery {
+    /// Name of the language, as in kernel specs.
+    pub language: &'static str,
+
+    /// Code to run silently in the kernel. It prints [`MARKER`] and a JSON
+    /// list of [`Variable`] objects on one line, and leaves no names behind.
+    pub code: &'static str,
+}
+
+/// Queries for each supported language. Add an entry to support another.
+const QUERIES: &[VariableQuery] = &[VariableQuery {
+    language: "python",
+    code: r#"def _jute_variables():
+    import json, sys, types
+    ip = get_ipython()
+    hidden = ip.user_ns_hidden
+    skip = (types.ModuleType, types.FunctionType, types.BuiltinFunctionType, type)
+    variables = []
+    for name, value in list(ip.user_ns.items()):
+        if name.startswith("_") or name in hidden or isinstance(value, skip):
+            continue
+        shape = getattr(value, "shape", None)
+        if shape is not None:
+            shape = str(tuple(shape)) if isinstance(shape, (tuple, list)) else str(shape)
+        elif isinstance(value, (str, bytes, list, tuple, dict, set, frozenset)):
+            shape = str(len(value))
+        try:
+            size = int(value.memory_usage(deep=True).sum())
+        except Exception:
+            size = getattr(value, "nbytes", None)
+            if not isinstance(size, int):
+                try:
+                    size = sys.getsizeof(value)
+                except Exception:
+                    size = None
+        try:
+            summary = repr(value)
+        except Exception as e:
+            summary = f"<repr failed: {e!r}>"
+        if len(summary) > 200:
+            summary = summary[:199] + "…"
+        variables.append({
+            "name": name,
+            "type_name": type(value).__name__,
+            "shape": shape,
+            "size": size,
+            "summary": summary,
+        })
+    print("__jute_variables__:" + json.dumps(variables))
+try:
+    _jute_variables()
+finally:
+    del _jute_variables
+"#,
+}];
+
+impl VariableQuery {
+    /// The query for a language, if it is supported.
+    pub fn for_language(language: &str) -> Option<&'static Self> {
+        QUERIES
+            .iter()
+            .find(|query| query.language.eq_ignore_ascii_case(language))
+    }
+
+    /// Parse the variables from the standard output of the query.
+    fn parse(stdout: &str) -> Result<Vec<Variable>, Error> {
+        let json = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix(MARKER))
+            .ok_or_else(|| Error::Variables("the kernel printed no variables".into()))?;
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// List the variables in a kernel's namespace.
+///
+/// The query runs in a subshell if the kernel supports them, so that it
+/// doesn't wait for a busy cell. Otherwise, it runs after the cells queued in
+/// the kernel.
+pub async fn list_variables(
+    conn: &KernelConnection,
+    query: &VariableQuery,
+) -> Result<Vec<Variable>, Error> {
+    let options = RunCellOptions {
+        silent: true,
+        ..Default::default()
+    };
+    let rx = match conn.create_subshell().await {
+        Ok(subshell) => commands::run_in_subshell(&subshell, query.code, options).await?,
+        Err(Error::KernelUnsupported(_)) => commands::run_cell(conn, query.code, options).await?,
+        Err(err) => return Err(err),
+    };
+    let mut stdout = String::new();
+    while let Ok(event) = rx.recv().await {
+        match event {
+            RunCellEvent::Stdout(text) => stdout.push_str(&text),
+            RunCellEvent::Error(err) => {
+                return Err(Error::Variables(format!("{}: {}", err.ename, err.evalue)));
+            }
+            RunCellEvent::Disconnect(_) => return Err(Error::KernelDisconnect),
+            _ => {}
+        }
+    }
+    VariableQuery::parse(&stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_variables() {
+        assert!(VariableQuery::for_language("Python").is_some());
+        assert!(VariableQuery::for_language("brainfuck").is_none());
+
+        let stdout = concat!(
+            "warning: something\n",
+            r#"__jute_variables__:[{"name": "df", "type_name": "DataFrame", "shape": "(3, 2)", "#,
+            r#""size": 176, "summary": "   a  b"}]"#,
+            "\n",
+        );
+        let variables = VariableQuery::parse(stdout).unwrap();
+        assert_eq!(
+            variables,
+            [Variable {
+                name: "df".into(),
+                type_name: "DataFrame".into(),
+                shape: Some("(3, 2)".into()),
+                size: Some(176),
+                summary: "   a  b".into(),
+            }]
+        );
+        assert!(VariableQuery::parse("").is_err());
+    }
+}
@@ -5,23 +5,38 @@
 //! communicate with Jupyter kernels over ZeroMQ or WebSocket.
 
 use std::collections::BTreeMap;
-use std::sync::Arc;
+use std::fmt;
+use std::sync::{Arc, RwLock};
 
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use dashmap::DashMap;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use time::OffsetDateTime;
-use tokio::sync::oneshot;
+use time::{
+    format_description::well_known::{Iso8601, Rfc3339},
+    Date, OffsetDateTime, PrimitiveDateTime,
+};
+use tokio::sync::{oneshot, watch};
 use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::warn;
 use ts_rs::TS;
 use uuid::Uuid;
 
-pub use self::driver_websocket::create_websocket_connection;
-pub use self::driver_zeromq::create_zeromq_connection;
+pub use self::driver_websocket::{create_websocket_connection, from_ws_payload, to_ws_payload};
+pub use self::driver_zeromq::{
+    create_zeromq_connection, from_zmq_payload, sign_message, to_zmq_payload, ConnectionInfo,
+    Transport,
+};
+use self::iopub::{IopubQueue, IopubSubscribers, PendingInput};
+pub use self::iopub::{IopubSubscription, OverflowStrategy};
+pub use self::subshell::{Subshell, SUBSHELLS_FEATURE};
+pub use self::tap::{MessageDirection, MessageTap, TappedMessage};
 use crate::Error;
 
 mod driver_websocket;
 mod driver_zeromq;
+mod iopub;
+mod subshell;
+mod tap;
 
 /// Type of a kernel wire protocol message, either request or reply.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
@@ -88,6 +103,30 @@ pub enum KernelMessageType {
     /// Reply with debugger status.
     DebugReply,
 
+    /// Request to create a subshell, which runs shell requests concurrently.
+    CreateSubshellRequest,
+
+    /// Reply with the ID of a new subshell.
+    CreateSubshellReply,
+
+    /// Request to delete a subshell.
+    DeleteSubshellRequest,
+
+    /// Reply to confirm a subshell was deleted.
+    DeleteSubshellReply,
+
+    /// Request the IDs of existing subshells.
+    ListSubshellRequest,
+
+    /// Reply with the IDs of existing subshells.
+    ListSubshellReply,
+
+    /// Request from the kernel for a line of input, like from `input()`.
+    InputRequest,
+
+    /// Reply with the input entered by the user.
+    InputReply,
+
     /// Streams of output (stdout, stderr) from the kernel.
     Stream,
 
@@ -143,7 +182,10 @@ pub struct KernelHeader {
     pub username: String,
 
     /// ISO 8601 timestamp for when the message is created.
-    #[serde(with = "time::serde::iso8601")]
+    ///
+    /// Some kernels send dates without an offset or in other formats. These
+    /// are parsed leniently by [`parse_date`], so their messages aren't lost.
+    #[serde(with = "tolerant_date", default = "OffsetDateTime::now_utc")]
     #[ts(type = "string")]
     pub date: OffsetDateTime,
 
@@ -152,6 +194,95 @@ pub struct KernelHeader {
 
     /// Message protocol version.
     pub version: String,
+
+    /// The subshell that should handle a shell request, or that handled the
+    /// request a reply is for. Requests without one go to the main shell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subshell_id: Option<String>,
+}
+
+/// Parse a timestamp from a kernel, accepting formats that aren't strict ISO
+/// 8601 with an offset.
+///
+/// Dates without an offset are assumed to be in UTC, and a space may separate
+/// the date and time, as Python's `str(datetime)` does.
+pub fn parse_date(text: &str) -> Option<OffsetDateTime> {
+    let text = text.trim();
+    if let Ok(date) = OffsetDateTime::parse(text, &Iso8601::DEFAULT) {
+        return Some(date);
+    }
+    if let Ok(date) = OffsetDateTime::parse(text, &Rfc3339) {
+        return Some(date);
+    }
+    let text = text.replacen(' ', "T", 1);
+    if let Ok(date) = PrimitiveDateTime::parse(&text, &Iso8601::DEFAULT) {
+        return Some(date.assume_utc());
+    }
+    let date = Date::parse(&text, &Iso8601::DEFAULT).ok()?;
+    Some(date.midnight().assume_utc())
+}
+
+/// Serde helpers for the dates of kernel headers, which fall back to the
+/// current time if a date can't be parsed.
+mod tolerant_date {
+    use serde::{Deserialize, Deserializer};
+    pub use time::serde::iso8601::serialize;
+    use time::OffsetDateTime;
+    use tracing::warn;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OffsetDateTime, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let date = value.as_str().and_then(super::parse_date);
+        Ok(date.unwrap_or_else(|| {
+            warn!("could not parse date in kernel header: {value}");
+            OffsetDateTime::now_utc()
+        }))
+    }
+}
+
+/// Version of the messaging protocol, like 5.3.
+///
+/// Jute speaks [`ProtocolVersion::CURRENT`], and uses an older version with
+/// kernels that report one in their `kernel_info_reply`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion {
+    /// Major version, which is 5 for all current kernels.
+    pub major: u32,
+
+    /// Minor version, which adds messages and fields.
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    /// The newest version of the protocol that Jute speaks.
+    pub const CURRENT: Self = Self::new(5, 4);
+
+    /// Create a version from its parts.
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Parse a version like "5.3", ignoring any patch number.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+        Some(Self::new(major, minor))
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
 }
 
 /// A message sent to or received from a Jupyter kernel.
@@ -163,6 +294,11 @@ pub struct KernelMessage<T = serde_json::Value> {
     /// The parent message header, if any.
     pub parent_header: Option<KernelHeader>,
 
+    /// Metadata about the message, a JSON object that is usually empty. Some
+    /// kernels and extensions put extra information here, like hints for how
+    /// to show rich outputs.
+    pub metadata: serde_json::Value,
+
     /// The content of the message.
     pub content: T,
 
@@ -172,17 +308,22 @@ pub struct KernelMessage<T = serde_json::Value> {
 
 impl<T> KernelMessage<T> {
     /// Create a basic kernel message with the given header and content.
+    ///
+    /// The session and username are left empty, and filled in from the
+    /// [`ConnectionOptions`] of the connection that sends the message.
     pub fn new(msg_type: KernelMessageType, content: T) -> Self {
         Self {
             header: KernelHeader {
                 msg_id: Uuid::new_v4().to_string(),
-                session: "jute-session".to_string(),
-                username: "jute-user".to_string(),
+                session: String::new(),
+                username: String::new(),
                 date: OffsetDateTime::now_utc(),
                 msg_type,
-                version: "5.4".into(),
+                version: ProtocolVersion::CURRENT.to_string(),
+                subshell_id: None,
             },
             parent_header: None,
+            metadata: serde_json::Value::Object(Default::default()),
             content,
             buffers: Vec::new(),
         }
@@ -195,6 +336,7 @@ impl<T: Serialize> KernelMessage<T> {
         KernelMessage {
             header: self.header,
             parent_header: self.parent_header,
+            metadata: self.metadata,
             content: serde_json::to_value(&self.content).expect("KernelMessage JSON serialization"),
             buffers: self.buffers,
         }
@@ -207,6 +349,7 @@ impl KernelMessage {
         Ok(KernelMessage {
             header: self.header,
             parent_header: self.parent_header,
+            metadata: self.metadata,
             content: serde_json::from_value(self.content)
                 .map_err(|err| Error::DeserializeMessage(err.to_string()))?,
             buffers: self.buffers,
@@ -351,6 +494,153 @@ pub struct CompleteReply {
     pub metadata: BTreeMap<String, serde_json::Value>,
 }
 
+/// Request to check if code is complete and ready to execute, as in a console
+/// deciding whether Enter should run the input or start a new line.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct IsCompleteRequest {
+    /// The code entered so far, possibly a multiline string.
+    pub code: String,
+}
+
+/// Represents a reply to an is_complete request.
+///
+/// Unlike other replies, the status field holds the answer, so this is not
+/// wrapped in [`Reply`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum IsCompleteReply {
+    /// The code is ready to be executed.
+    Complete,
+
+    /// The code needs more input, such as the body of a `for` loop.
+    Incomplete {
+        /// Characters to indent the next line with, as a hint to the frontend.
+        #[serde(default)]
+        indent: String,
+    },
+
+    /// The code will raise a syntax error if executed. Frontends should
+    /// usually run it anyway so that the error is shown.
+    Invalid,
+
+    /// The kernel cannot tell whether the code is complete.
+    Unknown,
+}
+
+crate::export_bindings!(IsCompleteReply);
+
+/// Request for entries of the kernel's input history, such as IPython's
+/// history database.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct HistoryRequest {
+    /// If true, also return the output of each input.
+    pub output: bool,
+
+    /// If true, return the raw input as typed, rather than the input after
+    /// transformations like magics are expanded.
+    pub raw: bool,
+
+    /// Which entries to access, which determines the fields below that are
+    /// used.
+    pub hist_access_type: HistoryAccessType,
+
+    /// For a range request, the session number to read from. Negative values
+    /// count back from the current session, which is 0.
+    pub session: i32,
+
+    /// For a range request, the first line number in the session.
+    pub start: u32,
+
+    /// For a range request, the line number after the last line to return.
+    pub stop: u32,
+
+    /// For tail and search requests, the number of entries to return.
+    pub n: u32,
+
+    /// For a search request, a glob pattern to match inputs against.
+    pub pattern: String,
+
+    /// For a search request, whether to skip duplicate inputs.
+    pub unique: bool,
+}
+
+impl HistoryRequest {
+    /// Get the last `n` inputs, most recent last.
+    pub fn tail(n: u32) -> Self {
+        Self {
+            hist_access_type: HistoryAccessType::Tail,
+            n,
+            ..Self::default()
+        }
+    }
+
+    /// Search for the last `n` unique inputs matching a glob pattern.
+    pub fn search(pattern: &str, n: u32) -> Self {
+        Self {
+            hist_access_type: HistoryAccessType::Search,
+            n,
+            pattern: pattern.into(),
+            unique: true,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for HistoryRequest {
+    fn default() -> Self {
+        Self {
+            output: false,
+            raw: true,
+            hist_access_type: HistoryAccessType::Tail,
+            session: 0,
+            start: 0,
+            stop: 0,
+            n: 0,
+            pattern: String::new(),
+            unique: false,
+        }
+    }
+}
+
+/// Ways to access the kernel's history in a [`HistoryRequest`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryAccessType {
+    /// Lines from `start` to `stop` in a session.
+    Range,
+
+    /// The most recent `n` lines.
+    Tail,
+
+    /// The most recent `n` lines matching a pattern.
+    Search,
+}
+
+/// Represents a reply to a history request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct HistoryReply {
+    /// The entries found, oldest first.
+    pub history: Vec<HistoryEntry>,
+}
+
+/// An entry in the kernel's history, as a tuple of session number, line number
+/// and input.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct HistoryEntry(pub i32, pub u32, pub HistoryInput);
+
+crate::export_bindings!(HistoryEntry);
+
+/// The input of a history entry, with its output if it was requested.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(untagged)]
+pub enum HistoryInput {
+    /// Only the input source.
+    Input(String),
+
+    /// The input source and its output, which may be missing.
+    InputOutput(String, Option<String>),
+}
+
 /// Request for information about the kernel.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct KernelInfoRequest {}
@@ -377,10 +667,19 @@ pub struct KernelInfoReply {
     /// Indicates if the kernel supports debugging.
     #[serde(default)]
     pub debugger: bool,
+
+    /// Optional features supported by the kernel, like "kernel subshells".
+    #[serde(default)]
+    pub supported_features: Vec<String>,
 }
 
+crate::export_bindings!(KernelInfoReply);
+
 /// Detailed information about the programming language of the kernel.
+///
+/// Renamed in bindings, since notebook metadata has its own `LanguageInfo`.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+#[ts(rename = "KernelLanguageInfo")]
 pub struct LanguageInfo {
     /// Name of the programming language.
     pub name: String,
@@ -396,9 +695,19 @@ pub struct LanguageInfo {
 
     /// Nbconvert exporter, if notebooks should be exported differently than the
     /// general script.
-    pub nbconvert_exporter: String,
+    pub nbconvert_exporter: Option<String>,
+
+    /// CodeMirror mode for highlighting the language, either a name or an
+    /// object with options, if it differs from the name of the language.
+    pub codemirror_mode: Option<serde_json::Value>,
+
+    /// Pygments lexer for highlighting the language, if it differs from the
+    /// name of the language.
+    pub pygments_lexer: Option<String>,
 }
 
+crate::export_bindings!(LanguageInfo);
+
 /// Request to shut down the kernel, possibly to prepare for a restart.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct ShutdownRequest {
@@ -422,6 +731,47 @@ pub struct InterruptRequest {}
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct InterruptReply {}
 
+/// Request to create a subshell of the kernel.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CreateSubshellRequest {}
+
+/// Represents a reply to a create_subshell request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CreateSubshellReply {
+    /// ID of the new subshell, to set on the header of requests it handles.
+    pub subshell_id: String,
+}
+
+/// Request to delete a subshell of the kernel.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct DeleteSubshellRequest {
+    /// ID of the subshell to delete.
+    pub subshell_id: String,
+}
+
+/// Represents a reply to a delete_subshell request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct DeleteSubshellReply {}
+
+/// Request from the kernel on the stdin channel to read a line of input.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct InputRequest {
+    /// Text to show the user, like the argument to `input()`.
+    pub prompt: String,
+
+    /// Whether the input is a password, which should not be shown as it is
+    /// typed, like from `getpass.getpass()`.
+    #[serde(default)]
+    pub password: bool,
+}
+
+/// Reply to an input request with the line entered by the user.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, TS)]
+pub struct InputReply {
+    /// The line of input, without a trailing newline.
+    pub value: String,
+}
+
 /// Streams of output from the kernel, such as stdout and stderr.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
 pub struct Stream {
@@ -443,6 +793,16 @@ pub struct DisplayData {
 
     /// Any information not to be persisted to a notebook.
     pub transient: Option<DisplayDataTransient>,
+
+    /// Metadata of the kernel message that carried the data, separate from the
+    /// metadata of the data itself. This is set when running a cell.
+    #[serde(default)]
+    pub message_metadata: serde_json::Value,
+
+    /// Binary buffers of the kernel message that carried the data, encoded as
+    /// base64. This is set when running a cell.
+    #[serde(default)]
+    pub buffers: Vec<String>,
 }
 
 /// Transient data associated with display data, such as display IDs.
@@ -477,6 +837,16 @@ pub struct ExecuteResult {
 
     /// Metadata associated with the data, can be empty.
     pub metadata: BTreeMap<String, serde_json::Value>,
+
+    /// Metadata of the kernel message that carried the result, separate from
+    /// the metadata of the data itself. This is set when running a cell.
+    #[serde(default)]
+    pub message_metadata: serde_json::Value,
+
+    /// Binary buffers of the kernel message that carried the data, encoded as
+    /// base64. This is set when running a cell.
+    #[serde(default)]
+    pub buffers: Vec<String>,
 }
 
 /// Used by frontends to monitor the status of the kernel.
@@ -535,6 +905,65 @@ pub struct CommMessage {
     pub data: serde_json::Value,
 }
 
+/// Reusable buffer for serializing the JSON frames of outgoing messages.
+///
+/// Frames are split off as [`Bytes`] that share one allocation. Once they have
+/// been sent and dropped, [`BytesMut::reserve`] reclaims the memory, so each
+/// driver serializes messages without allocating in the steady state.
+#[derive(Default)]
+pub struct FrameBuffer(BytesMut);
+
+impl FrameBuffer {
+    /// Capacity reserved before each frame, enough for most headers.
+    const RESERVE: usize = 4096;
+
+    /// Serialize a value as a JSON frame.
+    pub fn json(&mut self, value: &impl Serialize) -> Option<Bytes> {
+        self.0.reserve(Self::RESERVE);
+        if serde_json::to_writer((&mut self.0).writer(), value).is_err() {
+            self.0.clear();
+            return None;
+        }
+        Some(self.0.split().freeze())
+    }
+}
+
+/// Options for a connection to a kernel, shared by all drivers.
+#[derive(Clone, Debug)]
+pub struct ConnectionOptions {
+    /// Number of shell messages that can be queued to be sent.
+    pub shell_capacity: usize,
+
+    /// Number of control messages that can be queued to be sent.
+    pub control_capacity: usize,
+
+    /// Number of iopub messages that can be queued for each subscriber, and
+    /// for unsubscribed messages.
+    pub iopub_capacity: usize,
+
+    /// Session ID in the header of every message sent. Kernels and servers
+    /// use it to tell clients apart, so it defaults to a new UUID.
+    pub session: String,
+
+    /// Username in the header of every message sent, which defaults to the
+    /// user running the app.
+    pub username: String,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            shell_capacity: 8,
+            control_capacity: 8,
+            iopub_capacity: 64,
+            session: Uuid::new_v4().to_string(),
+            username: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "jute".into()),
+        }
+    }
+}
+
 /// Represents a stateful kernel connection that can be used to communicate with
 /// a running Jupyter kernel.
 ///
@@ -547,8 +976,9 @@ pub struct CommMessage {
 ///   from any client over the shell channel.
 /// - Stdin: Requests from the kernel to the client for standard input.
 /// - Control: Just like Shell, but separated to avoid queueing.
-/// - Heartbeat: Periodic ping/pong to ensure the connection is alive. This
-///   appears to only be supported by ZeroMQ, so we don't implement it here.
+/// - Heartbeat: Periodic ping/pong to ensure the kernel is alive. This is only
+///   supported by ZeroMQ, so WebSocket connections rely on the server instead,
+///   and are considered alive until the socket closes.
 ///
 /// The specific details of which messages are sent on which channels are left
 /// to the user. Functions will block if disconnected or return an error after
@@ -557,8 +987,14 @@ pub struct CommMessage {
 pub struct KernelConnection {
     shell_tx: async_channel::Sender<KernelMessage>,
     control_tx: async_channel::Sender<KernelMessage>,
-    iopub_rx: async_channel::Receiver<KernelMessage>,
+    stdin_tx: async_channel::Sender<KernelMessage>,
+    iopub_subscribers: Arc<IopubSubscribers>,
     reply_tx_map: Arc<DashMap<String, oneshot::Sender<KernelMessage>>>,
+    alive_rx: watch::Receiver<bool>,
+    protocol_version: Arc<RwLock<ProtocolVersion>>,
+    supported_features: Arc<RwLock<Vec<String>>>,
+    tap: Arc<MessageTap>,
+    options: ConnectionOptions,
     signal: CancellationToken,
     _drop_guard: Arc<DropGuard>,
 }
@@ -570,14 +1006,17 @@ impl KernelConnection {
     /// channel, when it is finished.
     pub async fn call_shell<T: Serialize>(
         &self,
-        message: KernelMessage<T>,
+        mut message: KernelMessage<T>,
     ) -> Result<PendingRequest, Error> {
+        self.fill_header(&mut message.header);
         let (reply_tx, reply_rx) = oneshot::channel();
         let msg_id = message.header.msg_id.clone();
         self.reply_tx_map.insert(msg_id.clone(), reply_tx);
 
+        let message = message.into_json();
+        self.tap.record("shell", MessageDirection::Sent, &message);
         self.shell_tx
-            .send(message.into_json())
+            .send(message)
             .await
             .map_err(|_| Error::KernelDisconnect)?;
 
@@ -591,14 +1030,17 @@ impl KernelConnection {
     /// Send a message to the kernel over the control channel.
     pub async fn call_control<T: Serialize>(
         &self,
-        message: KernelMessage<T>,
+        mut message: KernelMessage<T>,
     ) -> Result<PendingRequest, Error> {
+        self.fill_header(&mut message.header);
         let (reply_tx, reply_rx) = oneshot::channel();
         let msg_id = message.header.msg_id.clone();
         self.reply_tx_map.insert(msg_id.clone(), reply_tx);
 
+        let message = message.into_json();
+        self.tap.record("control", MessageDirection::Sent, &message);
         self.control_tx
-            .send(message.into_json())
+            .send(message)
             .await
             .map_err(|_| Error::KernelDisconnect)?;
 
@@ -609,24 +1051,150 @@ impl KernelConnection {
         })
     }
 
-    /// Receieve a message from the kernel over the iopub channel.
-    pub async fn recv_iopub(&self) -> Result<KernelMessage, Error> {
-        self.iopub_rx
-            .recv()
+    /// Fill in the parts of a message header that depend on the connection:
+    /// the protocol version, session, and username.
+    fn fill_header(&self, header: &mut KernelHeader) {
+        header.version = self.protocol_version().to_string();
+        header.session.clone_from(&self.options.session);
+        header.username.clone_from(&self.options.username);
+    }
+
+    /// Subscribe to iopub messages sent in response to a request, by the
+    /// message ID of the request.
+    ///
+    /// Subscribe before sending the request, so that no messages are missed.
+    /// Listeners from [`KernelConnection::listen_iopub`] get copies of these
+    /// messages too. Input requests from the kernel on the stdin channel are
+    /// received here as well, and answered with
+    /// [`KernelConnection::reply_input`].
+    ///
+    /// Each subscription queues up to [`ConnectionOptions::iopub_capacity`]
+    /// messages, then applies the `overflow` strategy.
+    pub fn subscribe_iopub(&self, msg_id: &str, overflow: OverflowStrategy) -> IopubSubscription {
+        let queue = IopubQueue::new(self.options.iopub_capacity, Some(overflow));
+        self.iopub_subscribers.insert(msg_id.into(), false, queue)
+    }
+
+    /// Listen to every message from the kernel over the iopub channel,
+    /// including those for subscribed requests, broadcasts, and replies to
+    /// other clients.
+    ///
+    /// Each listener gets its own copy of the messages, so a status monitor
+    /// or variable explorer can listen alongside cells that are running. So
+    /// that a listener never holds up the kernel, the oldest messages are
+    /// dropped once there are [`ConnectionOptions::iopub_capacity`] of them.
+    pub fn listen_iopub(&self) -> IopubSubscription {
+        let queue = IopubQueue::new(self.options.iopub_capacity, None);
+        let id = Uuid::new_v4().to_string();
+        self.iopub_subscribers.insert(id, true, queue)
+    }
+
+    /// Reply to an input request from the kernel, by its message ID.
+    ///
+    /// Input requests are sent to the subscription of the request that made
+    /// them, which also receives the prompt and value as `stdout`, like the
+    /// echo of a terminal. Replies to password prompts are not recorded by the
+    /// tap, and their values are not echoed.
+    pub async fn reply_input(&self, request_id: &str, value: String) -> Result<(), Error> {
+        let (_, input) = (self.iopub_subscribers.inputs)
+            .remove(request_id)
+            .ok_or_else(|| Error::InputRequestNotFound(request_id.into()))?;
+        let subscriber =
+            (self.iopub_subscribers.requests.get(&input.parent)).map(|queue| queue.clone());
+        if let Some(queue) = subscriber {
+            let echo = match input.password {
+                true => format!("{}\n", input.prompt),
+                false => format!("{}{value}\n", input.prompt),
+            };
+            let stream = Stream {
+                name: "stdout".into(),
+                text: echo,
+            };
+            queue
+                .send(KernelMessage::new(KernelMessageType::Stream, stream).into_json())
+                .await;
+        }
+
+        let mut message = KernelMessage::new(KernelMessageType::InputReply, InputReply { value });
+        self.fill_header(&mut message.header);
+        message.parent_header = Some(input.header);
+
+        let message = message.into_json();
+        if input.password {
+            // Passwords are never recorded, even for debugging.
+            let mut redacted = message.clone();
+            redacted.content = serde_json::json!({ "value": "********" });
+            self.tap.record("stdin", MessageDirection::Sent, &redacted);
+        } else {
+            self.tap.record("stdin", MessageDirection::Sent, &message);
+        }
+        self.stdin_tx
+            .send(message)
             .await
             .map_err(|_| Error::KernelDisconnect)
     }
 
-    /// Receive an immediate message over the iopub channel without waiting.
-    pub fn try_recv_iopub(&self) -> Option<KernelMessage> {
-        self.iopub_rx.try_recv().ok()
+    /// Version of the messaging protocol used with the kernel, stamped on the
+    /// header of each message sent.
+    ///
+    /// This is [`ProtocolVersion::CURRENT`] until it is negotiated.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        *self.protocol_version.read().unwrap()
+    }
+
+    /// Agree on a protocol version with the kernel, from the version in its
+    /// `kernel_info_reply`. This is the older of that and the current version.
+    ///
+    /// Versions that can't be parsed are ignored.
+    pub fn negotiate_version(&self, kernel_version: &str) {
+        match ProtocolVersion::parse(kernel_version) {
+            Some(version) => {
+                *self.protocol_version.write().unwrap() = version.min(ProtocolVersion::CURRENT);
+            }
+            None => warn!("kernel sent invalid protocol version {kernel_version:?}"),
+        }
+    }
+
+    /// Recorder of the messages on this connection, for debugging.
+    pub fn tap(&self) -> &MessageTap {
+        &self.tap
+    }
+
+    /// Record the optional features the kernel supports, from its
+    /// `kernel_info_reply`.
+    pub fn set_supported_features(&self, features: &[String]) {
+        features.clone_into(&mut self.supported_features.write().unwrap());
+    }
+
+    /// Whether the kernel supports an optional feature, like "kernel
+    /// subshells". This is false until the kernel's info is requested.
+    pub fn supports_feature(&self, feature: &str) -> bool {
+        (self.supported_features.read().unwrap())
+            .iter()
+            .any(|f| f == feature)
+    }
+
+    /// Whether the kernel answered its last heartbeat.
+    ///
+    /// A kernel that stops answering is likely hung or deadlocked, so requests
+    /// to it may never get a reply.
+    pub fn alive(&self) -> bool {
+        *self.alive_rx.borrow()
+    }
+
+    /// Watch for changes to whether the kernel is alive.
+    ///
+    /// The receiver's value is `false` while the kernel is unresponsive, and
+    /// its sender is dropped when the connection is closed.
+    pub fn watch_alive(&self) -> watch::Receiver<bool> {
+        self.alive_rx.clone()
     }
 
     /// Close the connection to the kernel, shutting down all channels.
     pub fn close(&self) {
         self.shell_tx.close();
         self.control_tx.close();
-        self.iopub_rx.close();
+        self.stdin_tx.close();
         self.signal.cancel(); // This is the only necessary line, but we close
                               // the channels for good measure regardless.
     }
@@ -644,6 +1212,13 @@ impl PendingRequest {
     pub async fn get_reply<U: DeserializeOwned>(
         &mut self,
     ) -> Result<KernelMessage<Reply<U>>, Error> {
+        self.get_raw_reply().await
+    }
+
+    /// Wait for the reply to the previous request, without parsing its status
+    /// as a [`Reply`]. This is for replies that use the status field for other
+    /// values, like `is_complete_reply`.
+    pub async fn get_raw_reply<U: DeserializeOwned>(&mut self) -> Result<KernelMessage<U>, Error> {
         (&mut self.reply_rx)
             .await
             .map_err(|_| Error::KernelDisconnect)?
@@ -657,3 +1232,367 @@ impl Drop for PendingRequest {
         self.reply_tx_map.remove(&self.msg_id);
     }
 }
+
+/// Used by drivers to send messages from the kernel to where they are awaited.
+/// Replies go to their pending request, and iopub messages go to the
+/// subscription for their parent request, or else to the feed of unsubscribed
+/// messages. Input requests on stdin also go to the subscription for their
+/// parent, since they are part of running it.
+///
+/// Dropping the router when the driver stops closes all subscriptions.
+struct MessageRouter {
+    reply_tx_map: Arc<DashMap<String, oneshot::Sender<KernelMessage>>>,
+    iopub_subscribers: Arc<IopubSubscribers>,
+    tap: Arc<MessageTap>,
+}
+
+impl MessageRouter {
+    /// Route a reply received on the shell or control channel.
+    fn route_reply(&self, channel: &str, msg: KernelMessage) {
+        self.tap.record(channel, MessageDirection::Received, &msg);
+        if let Some(KernelHeader { msg_id, .. }) = &msg.parent_header {
+            if let Some((_, reply_tx)) = self.reply_tx_map.remove(msg_id) {
+                // Optional, it's not an error if this receiver has been dropped.
+                _ = reply_tx.send(msg);
+            }
+        }
+    }
+
+    /// Route a message received on the iopub channel to every listener, and
+    /// to the subscriber for its parent request, if any.
+    ///
+    /// Subscriptions are bounded, so with [`OverflowStrategy::Pause`] this
+    /// waits while a subscriber is behind.
+    async fn route_iopub(&self, msg: KernelMessage) {
+        self.tap.record("iopub", MessageDirection::Received, &msg);
+        let listeners: Vec<_> = (self.iopub_subscribers.listeners.iter())
+            .map(|queue| queue.clone())
+            .collect();
+        for queue in listeners {
+            queue.send(msg.clone()).await;
+        }
+        let subscriber = msg
+            .parent_header
+            .as_ref()
+            .and_then(|parent| self.iopub_subscribers.requests.get(&parent.msg_id))
+            .map(|queue| queue.clone());
+        if let Some(queue) = subscriber {
+            queue.send(msg).await;
+        }
+    }
+
+    /// Route a request from the kernel received on the stdin channel.
+    ///
+    /// Requests without a subscriber are dropped, since nothing can reply to
+    /// them. The kernel waits until it is interrupted.
+    async fn route_stdin(&self, msg: KernelMessage) {
+        self.tap.record("stdin", MessageDirection::Received, &msg);
+        let subscriber = msg
+            .parent_header
+            .as_ref()
+            .and_then(|parent| self.iopub_subscribers.requests.get(&parent.msg_id))
+            .map(|queue| queue.clone());
+        let Some(queue) = subscriber else {
+            warn!("dropping stdin message without a subscriber");
+            return;
+        };
+        if let (KernelMessageType::InputRequest, Some(parent)) =
+            (&msg.header.msg_type, &msg.parent_header)
+        {
+            let input = PendingInput {
+                header: msg.header.clone(),
+                parent: parent.msg_id.clone(),
+                prompt: msg.content["prompt"].as_str().unwrap_or_default().into(),
+                password: msg.content["password"].as_bool().unwrap_or_default(),
+            };
+            (self.iopub_subscribers.inputs).insert(msg.header.msg_id.clone(), input);
+        }
+        queue.send(msg).await;
+    }
+}
+
+impl Drop for MessageRouter {
+    fn drop(&mut self) {
+        self.iopub_subscribers.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// The other end of a connection made by [`test_connection`], standing in
+    /// for its driver.
+    pub(super) struct TestDriver {
+        pub router: MessageRouter,
+        pub shell_rx: async_channel::Receiver<KernelMessage>,
+        pub control_rx: async_channel::Receiver<KernelMessage>,
+        pub stdin_rx: async_channel::Receiver<KernelMessage>,
+    }
+
+    /// Make a connection without a kernel, whose messages are handled by the
+    /// test.
+    pub(super) fn test_connection(options: ConnectionOptions) -> (KernelConnection, TestDriver) {
+        let (shell_tx, shell_rx) = async_channel::unbounded();
+        let (control_tx, control_rx) = async_channel::unbounded();
+        let (stdin_tx, stdin_rx) = async_channel::unbounded();
+        let signal = CancellationToken::new();
+        let conn = KernelConnection {
+            shell_tx,
+            control_tx,
+            stdin_tx,
+            iopub_subscribers: Default::default(),
+            reply_tx_map: Default::default(),
+            alive_rx: watch::channel(true).1,
+            protocol_version: Default::default(),
+            supported_features: Default::default(),
+            tap: Default::default(),
+            options,
+            signal: signal.clone(),
+            _drop_guard: Arc::new(signal.drop_guard()),
+        };
+        let router = MessageRouter {
+            reply_tx_map: conn.reply_tx_map.clone(),
+            iopub_subscribers: conn.iopub_subscribers.clone(),
+            tap: conn.tap.clone(),
+        };
+        let driver = TestDriver {
+            router,
+            shell_rx,
+            control_rx,
+            stdin_rx,
+        };
+        (conn, driver)
+    }
+
+    #[test]
+    fn lenient_dates() {
+        let utc = OffsetDateTime::from_unix_timestamp_nanos(1_709_296_245_500_000_000).unwrap();
+        assert_eq!(parse_date("2024-03-01T12:30:45.500Z"), Some(utc));
+        assert_eq!(parse_date("2024-03-01T14:30:45.5+02:00"), Some(utc));
+        assert_eq!(parse_date("2024-03-01T12:30:45.5"), Some(utc));
+        assert_eq!(parse_date("2024-03-01 12:30:45.500000"), Some(utc));
+
+        let midnight = parse_date("2024-03-01").unwrap();
+        assert_eq!(midnight.date(), utc.date());
+        assert_eq!(midnight.time(), time::Time::MIDNIGHT);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn header_with_bad_date() {
+        let header: KernelHeader = serde_json::from_value(serde_json::json!({
+            "msg_id": "1",
+            "session": "s",
+            "username": "u",
+            "date": "not a date",
+            "msg_type": "status",
+            "version": "5.3",
+        }))
+        .unwrap();
+        assert!(OffsetDateTime::now_utc() - header.date < time::Duration::minutes(1));
+    }
+
+    #[test]
+    fn protocol_versions() {
+        assert_eq!(
+            ProtocolVersion::parse("5.3"),
+            Some(ProtocolVersion::new(5, 3))
+        );
+        assert_eq!(
+            ProtocolVersion::parse("5.0.1"),
+            Some(ProtocolVersion::new(5, 0))
+        );
+        assert_eq!(
+            ProtocolVersion::parse("5"),
+            Some(ProtocolVersion::new(5, 0))
+        );
+        assert_eq!(ProtocolVersion::parse("five"), None);
+        assert!(ProtocolVersion::new(5, 10) > ProtocolVersion::new(5, 4));
+
+        let old = ProtocolVersion::parse("5.2").unwrap();
+        assert!(old < ProtocolVersion::CURRENT);
+        assert_eq!(old.to_string(), "5.2");
+    }
+
+    #[test]
+    fn is_complete_reply() {
+        let reply: IsCompleteReply =
+            serde_json::from_value(json!({ "status": "incomplete", "indent": "    " })).unwrap();
+        assert_eq!(
+            reply,
+            IsCompleteReply::Incomplete {
+                indent: "    ".into()
+            }
+        );
+        let reply: IsCompleteReply =
+            serde_json::from_value(json!({ "status": "incomplete" })).unwrap();
+        assert_eq!(reply, IsCompleteReply::Incomplete { indent: "".into() });
+        let reply: IsCompleteReply =
+            serde_json::from_value(json!({ "status": "invalid" })).unwrap();
+        assert_eq!(reply, IsCompleteReply::Invalid);
+    }
+
+    #[test]
+    fn language_info() {
+        let info: LanguageInfo = serde_json::from_value(json!({
+            "name": "python",
+            "version": "3.12.4",
+            "mimetype": "text/x-python",
+            "file_extension": ".py",
+            "codemirror_mode": { "name": "ipython", "version": 3 },
+            "pygments_lexer": "ipython3",
+        }))
+        .unwrap();
+        assert_eq!(info.nbconvert_exporter, None);
+        assert_eq!(info.codemirror_mode.unwrap()["name"], "ipython");
+        assert_eq!(info.pygments_lexer.as_deref(), Some("ipython3"));
+    }
+
+    #[test]
+    fn history_reply() {
+        let reply: HistoryReply = serde_json::from_value(json!({
+            "history": [[1, 1, "x = 1"], [1, 2, ["x + 1", "2"]], [2, 1, ["print(x)", null]]],
+        }))
+        .unwrap();
+        assert_eq!(
+            reply.history,
+            [
+                HistoryEntry(1, 1, HistoryInput::Input("x = 1".into())),
+                HistoryEntry(
+                    1,
+                    2,
+                    HistoryInput::InputOutput("x + 1".into(), Some("2".into()))
+                ),
+                HistoryEntry(2, 1, HistoryInput::InputOutput("print(x)".into(), None)),
+            ]
+        );
+
+        let req = serde_json::to_value(HistoryRequest::search("import *", 10)).unwrap();
+        assert_eq!(req["hist_access_type"], "search");
+        assert_eq!(req["pattern"], "import *");
+    }
+
+    #[tokio::test]
+    async fn session_identity() {
+        let (conn, driver) = test_connection(Default::default());
+        let (other, _) = test_connection(ConnectionOptions {
+            session: "my-session".into(),
+            username: "ada".into(),
+            ..Default::default()
+        });
+        assert_ne!(conn.options.session, ConnectionOptions::default().session);
+
+        let msg = KernelMessage::new(KernelMessageType::KernelInfoRequest, json!({}));
+        conn.call_shell(msg).await.unwrap();
+        let sent = driver.shell_rx.recv().await.unwrap();
+        assert_eq!(sent.header.session, conn.options.session);
+        assert!(!sent.header.username.is_empty());
+
+        let mut header = KernelMessage::new(KernelMessageType::KernelInfoRequest, ()).header;
+        other.fill_header(&mut header);
+        assert_eq!(
+            (header.session.as_str(), header.username.as_str()),
+            ("my-session", "ada")
+        );
+    }
+
+    #[tokio::test]
+    async fn route_iopub_by_parent() {
+        let (conn, TestDriver { router, .. }) = test_connection(ConnectionOptions {
+            iopub_capacity: 2,
+            ..Default::default()
+        });
+
+        let first = KernelMessage::new(KernelMessageType::ExecuteRequest, json!({}));
+        let second = KernelMessage::new(KernelMessageType::ExecuteRequest, json!({}));
+        let output = |parent: Option<&KernelMessage<serde_json::Value>>, text: &str| {
+            let mut msg = KernelMessage::new(KernelMessageType::Stream, json!({ "text": text }));
+            msg.parent_header = parent.map(|parent| parent.header.clone());
+            msg
+        };
+
+        let first_iopub = conn.subscribe_iopub(&first.header.msg_id, Default::default());
+        let second_iopub = conn.subscribe_iopub(&second.header.msg_id, Default::default());
+        let monitor = conn.listen_iopub();
+        let status = conn.listen_iopub();
+        router.route_iopub(output(Some(&second), "b")).await;
+        router.route_iopub(output(Some(&first), "a")).await;
+        assert_eq!(first_iopub.recv().await.unwrap().content["text"], "a");
+        assert_eq!(second_iopub.recv().await.unwrap().content["text"], "b");
+
+        // Each listener gets every message, without taking them from others.
+        for listener in [&monitor, &status] {
+            assert_eq!(listener.recv().await.unwrap().content["text"], "b");
+            assert_eq!(listener.recv().await.unwrap().content["text"], "a");
+        }
+
+        // Listeners that fall behind drop their oldest messages.
+        drop(first_iopub);
+        for text in ["0", "1", "2"] {
+            router.route_iopub(output(None, text)).await;
+        }
+        router.route_iopub(output(Some(&first), "late")).await;
+        assert_eq!(monitor.recv().await.unwrap().content["text"], "2");
+        assert_eq!(monitor.recv().await.unwrap().content["text"], "late");
+        assert!(monitor.try_recv().is_none());
+
+        drop(status);
+        drop(router);
+        assert!(second_iopub.recv().await.is_err());
+        assert!(monitor.recv().await.is_err());
+        assert!(conn
+            .subscribe_iopub("other", Default::default())
+            .recv()
+            .await
+            .is_err());
+        assert!(conn.listen_iopub().recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reply_to_password_prompt() {
+        let (conn, driver) = test_connection(Default::default());
+        conn.tap().set_enabled(true);
+        let execute = KernelMessage::new(KernelMessageType::ExecuteRequest, json!({}));
+        let iopub = conn.subscribe_iopub(&execute.header.msg_id, Default::default());
+
+        let mut request = KernelMessage::new(
+            KernelMessageType::InputRequest,
+            json!({ "prompt": "Password: ", "password": true }),
+        );
+        request.parent_header = Some(execute.header.clone());
+        driver.router.route_stdin(request.clone()).await;
+        let received = iopub.recv().await.unwrap();
+        assert_eq!(received.header.msg_type, KernelMessageType::InputRequest);
+
+        conn.reply_input(&request.header.msg_id, "hunter2".into())
+            .await
+            .unwrap();
+        let echo = iopub.recv().await.unwrap();
+        assert_eq!(echo.content["text"], "Password: \n");
+        let reply = driver.stdin_rx.recv().await.unwrap();
+        assert_eq!(reply.content["value"], "hunter2");
+        assert_eq!(reply.parent_header, Some(request.header.clone()));
+        let tapped = conn.tap().messages();
+        assert!(tapped.iter().all(|msg| !msg.content.contains("hunter2")));
+
+        // Each request is answered once.
+        assert!(matches!(
+            conn.reply_input(&request.header.msg_id, "again".into())
+                .await,
+            Err(Error::InputRequestNotFound(_))
+        ));
+
+        // Requests are forgotten once the run that made them is done.
+        let mut request = request.clone();
+        request.header.msg_id = "second".into();
+        driver.router.route_stdin(request).await;
+        drop(iopub);
+        assert!(matches!(
+            conn.reply_input("second", "late".into()).await,
+            Err(Error::InputRequestNotFound(_))
+        ));
+    }
+}
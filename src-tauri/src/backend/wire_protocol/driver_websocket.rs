@@ -6,6 +6,11 @@
 //! It is very similar to the ZeroMQ protocol, but there is a thin framing layer
 //! that allows messages to be sent over WebSocket binary payloads instead of
 //! raw TCP sockets.
+//!
+//! Servers older than Jupyter Server 2.0 don't accept this protocol, and
+//! instead send each message as JSON with its channel. That legacy protocol is
+//! used when the server doesn't agree to the `v1.kernel.websocket.jupyter.org`
+//! subprotocol.
 
 use std::sync::Arc;
 
@@ -13,11 +18,16 @@ use bytes::Bytes;
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use reqwest::header::{HeaderValue, AUTHORIZATION, SEC_WEBSOCKET_PROTOCOL};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
 use tokio_util::sync::CancellationToken;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
-use super::{KernelConnection, KernelHeader, KernelMessage};
+use super::{
+    ConnectionOptions, FrameBuffer, IopubSubscribers, KernelConnection, KernelHeader,
+    KernelMessage, MessageRouter,
+};
 use crate::Error;
 
 // In this protocol, a kernel message is serialized over WebSocket as follows,
@@ -35,104 +45,222 @@ use crate::Error;
 // offset_5: buffer_0
 // (offset_6: buffer_1 ... and so on)
 
-fn to_ws_payload(msg: &KernelMessage, channel: &str) -> Option<Vec<u8>> {
-    let offset_number = 5 + msg.buffers.len() as u64;
-    let offset_0 = 8 * (offset_number + 1);
-    let mut offsets = vec![offset_number];
-
-    let mut payload = Vec::new();
-
-    // offset_0: channel
-    offsets.push(offset_0 + payload.len() as u64);
-    payload.extend_from_slice(channel.as_bytes());
+// Buffers are sliced out of the received payload without copying, and the
+// JSON frames of sent messages are serialized into a reused [`FrameBuffer`].
 
-    // offset_1: header
-    offsets.push(offset_0 + payload.len() as u64);
-    payload.append(&mut serde_json::to_vec(&msg.header).ok()?);
+/// Subprotocol of the current kernel WebSocket protocol.
+const KERNEL_PROTOCOL: &str = "v1.kernel.websocket.jupyter.org";
 
-    // offset_2: parent_header
-    offsets.push(offset_0 + payload.len() as u64);
-    payload.append(&mut serde_json::to_vec(&msg.parent_header).ok()?);
+/// Encode a message as a binary WebSocket payload on a channel.
+pub fn to_ws_payload(
+    msg: &KernelMessage,
+    channel: &str,
+    frames: &mut FrameBuffer,
+) -> Option<Vec<u8>> {
+    let header = frames.json(&msg.header)?;
+    let parent_header = frames.json(&msg.parent_header)?;
+    let metadata = frames.json(&msg.metadata)?;
+    let content = frames.json(&msg.content)?;
 
-    // offset_3: metadata
-    offsets.push(offset_0 + payload.len() as u64);
-    payload.extend_from_slice(b"{}");
+    let parts: Vec<&[u8]> = [
+        channel.as_bytes(),
+        &header,
+        &parent_header,
+        &metadata,
+        &content,
+    ]
+    .into_iter()
+    .chain(msg.buffers.iter().map(|buffer| &buffer[..]))
+    .collect();
 
-    // offset_4: content
-    offsets.push(offset_0 + payload.len() as u64);
-    payload.append(&mut serde_json::to_vec(&msg.content).ok()?);
+    let offset_number = parts.len() as u64;
+    let offset_0 = 8 * (offset_number as usize + 1);
+    let len = offset_0 + parts.iter().map(|part| part.len()).sum::<usize>();
 
-    for buffer in &msg.buffers {
-        offsets.push(offset_0 + payload.len() as u64);
-        payload.extend_from_slice(buffer);
+    let mut payload = Vec::with_capacity(len);
+    payload.extend_from_slice(&offset_number.to_le_bytes());
+    let mut offset = offset_0 as u64;
+    for part in &parts {
+        payload.extend_from_slice(&offset.to_le_bytes());
+        offset += part.len() as u64;
     }
-
-    Some(
-        offsets
-            .into_iter()
-            .flat_map(|n| n.to_le_bytes())
-            .chain(payload)
-            .collect::<Vec<u8>>(),
-    )
+    for part in parts {
+        payload.extend_from_slice(part);
+    }
+    Some(payload)
 }
 
-fn from_ws_payload(payload: &[u8]) -> Option<(KernelMessage, String)> {
+/// Decode a binary WebSocket payload into a message and its channel.
+pub fn from_ws_payload(payload: Bytes) -> Option<(KernelMessage, String)> {
     let offset_number: usize = u64::from_le_bytes(payload.get(0..8)?.try_into().ok()?)
         .try_into()
         .ok()?;
 
-    let mut offsets = Vec::with_capacity(offset_number);
+    let mut offsets = Vec::with_capacity(offset_number + 1);
     for i in 0..offset_number {
         let index = 8 * (i + 1);
-        offsets.push(
-            u64::from_le_bytes(payload.get(index..index + 8)?.try_into().ok()?)
-                .try_into()
-                .ok()?,
-        );
+        let offset: usize = u64::from_le_bytes(payload.get(index..index + 8)?.try_into().ok()?)
+            .try_into()
+            .ok()?;
+        offsets.push(offset);
     }
     offsets.push(payload.len());
 
-    let channel = String::from_utf8(payload.get(offsets[0]..offsets[1])?.to_vec()).ok()?;
-    let header = serde_json::from_slice(payload.get(offsets[1]..offsets[2])?).ok()?;
-    let parent_header = serde_json::from_slice(payload.get(offsets[2]..offsets[3])?).ok()?;
-    // serde_json::from_slice(payload.get(offsets[3]..offsets[4])?).ok()?;
-    let content = serde_json::from_slice(payload.get(offsets[4]..offsets[5])?).ok()?;
-
-    let mut buffers = Vec::new();
-    for i in 5..offset_number {
-        buffers.push(Bytes::from(
-            payload.get(offsets[i]..offsets[i + 1])?.to_vec(),
-        ));
+    // Check that offsets are in order, so that slicing below can't panic.
+    if offsets.len() < 6 || offsets.windows(2).any(|w| w[0] > w[1]) {
+        return None;
     }
 
+    let channel = std::str::from_utf8(&payload[offsets[0]..offsets[1]])
+        .ok()?
+        .to_owned();
+    let header = serde_json::from_slice(&payload[offsets[1]..offsets[2]]).ok()?;
+    let parent_header = serde_json::from_slice(&payload[offsets[2]..offsets[3]]).ok()?;
+    let metadata = serde_json::from_slice(&payload[offsets[3]..offsets[4]]).ok()?;
+    let content = serde_json::from_slice(&payload[offsets[4]..offsets[5]]).ok()?;
+
+    let buffers = (5..offset_number)
+        .map(|i| payload.slice(offsets[i]..offsets[i + 1]))
+        .collect();
+
     let msg = KernelMessage {
         header,
         parent_header,
+        metadata,
         content,
         buffers,
     };
     Some((msg, channel))
 }
 
-/// Connect to Jupyter via the `v1.kernel.websocket.jupyter.org` protocol.
+// In the legacy protocol, a message without buffers is a text payload with
+// its JSON. Messages with buffers are binary, where all integers are 32-bit
+// big-endian (indices refer to bytes):
+//
+// 0: part_number (n+1)
+// 4: offset_0
+// 8: offset_1
+// 4*part_number: offset_n
+// offset_0: JSON of the message
+// offset_1: buffer_0
+// (offset_2: buffer_1 ... and so on)
+
+/// A message in the legacy protocol, without its buffers.
+#[derive(Serialize, Deserialize)]
+struct LegacyMessage {
+    channel: String,
+    header: KernelHeader,
+    /// An empty object if the message has no parent.
+    #[serde(default)]
+    parent_header: serde_json::Value,
+    #[serde(default)]
+    metadata: serde_json::Value,
+    content: serde_json::Value,
+}
+
+/// Encode a message as a WebSocket payload in the legacy protocol.
+fn to_legacy_payload(msg: &KernelMessage, channel: &str) -> Option<Message> {
+    let json = serde_json::to_string(&LegacyMessage {
+        channel: channel.into(),
+        header: msg.header.clone(),
+        parent_header: match &msg.parent_header {
+            Some(parent_header) => serde_json::to_value(parent_header).ok()?,
+            None => serde_json::json!({}),
+        },
+        metadata: msg.metadata.clone(),
+        content: msg.content.clone(),
+    })
+    .ok()?;
+    if msg.buffers.is_empty() {
+        return Some(Message::Text(json));
+    }
+
+    let parts: Vec<&[u8]> = [json.as_bytes()]
+        .into_iter()
+        .chain(msg.buffers.iter().map(|buffer| &buffer[..]))
+        .collect();
+    let part_number = u32::try_from(parts.len()).ok()?;
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&part_number.to_be_bytes());
+    let mut offset = 4 * (part_number + 1);
+    for part in &parts {
+        payload.extend_from_slice(&offset.to_be_bytes());
+        offset = offset.checked_add(u32::try_from(part.len()).ok()?)?;
+    }
+    for part in parts {
+        payload.extend_from_slice(part);
+    }
+    Some(Message::Binary(payload))
+}
+
+/// Decode a WebSocket payload in the legacy protocol into a message and its
+/// channel.
+fn from_legacy_payload(payload: Message) -> Option<(KernelMessage, String)> {
+    let (json, buffers) = match payload {
+        Message::Text(text) => (Bytes::from(text), Vec::new()),
+        Message::Binary(payload) => {
+            let payload = Bytes::from(payload);
+            let read = |index: usize| -> Option<usize> {
+                let bytes = payload.get(4 * index..4 * index + 4)?;
+                usize::try_from(u32::from_be_bytes(bytes.try_into().ok()?)).ok()
+            };
+            let part_number = read(0)?;
+            let mut offsets = (1..=part_number).map(read).collect::<Option<Vec<_>>>()?;
+            offsets.push(payload.len());
+            if part_number == 0 || offsets.windows(2).any(|w| w[0] > w[1]) {
+                return None;
+            }
+            let mut parts = offsets.windows(2).map(|w| payload.slice(w[0]..w[1]));
+            (parts.next()?, parts.collect())
+        }
+        _ => return None,
+    };
+
+    let legacy: LegacyMessage = serde_json::from_slice(&json).ok()?;
+    let msg = KernelMessage {
+        header: legacy.header,
+        parent_header: serde_json::from_value(legacy.parent_header).ok().flatten(),
+        metadata: legacy.metadata,
+        content: legacy.content,
+        buffers,
+    };
+    Some((msg, legacy.channel))
+}
+
+/// Connect to Jupyter via the `v1.kernel.websocket.jupyter.org` protocol, or
+/// the legacy protocol if the server doesn't support it.
 pub async fn create_websocket_connection(
     websocket_url: &str,
     token: &str,
+    options: &ConnectionOptions,
 ) -> Result<KernelConnection, Error> {
-    let (shell_tx, shell_rx) = async_channel::bounded(8);
-    let (control_tx, control_rx) = async_channel::bounded(8);
-    let (iopub_tx, iopub_rx) = async_channel::bounded(64);
+    let (shell_tx, shell_rx) = async_channel::bounded(options.shell_capacity.max(1));
+    let (control_tx, control_rx) = async_channel::bounded(options.control_capacity.max(1));
+    let (stdin_tx, stdin_rx) = async_channel::bounded(1);
+    let iopub_subscribers = Arc::new(IopubSubscribers::default());
     let reply_tx_map = Arc::new(DashMap::new());
+    let (alive_tx, alive_rx) = watch::channel(true);
     let signal = CancellationToken::new();
 
     let conn = KernelConnection {
         shell_tx,
         control_tx,
-        iopub_rx,
+        stdin_tx,
+        iopub_subscribers: iopub_subscribers.clone(),
         reply_tx_map: reply_tx_map.clone(),
+        alive_rx,
+        protocol_version: Default::default(),
+        supported_features: Default::default(),
+        tap: Default::default(),
+        options: options.clone(),
         signal: signal.clone(),
         _drop_guard: Arc::new(signal.clone().drop_guard()),
     };
+    let router = MessageRouter {
+        reply_tx_map,
+        iopub_subscribers,
+        tap: conn.tap.clone(),
+    };
 
     let mut req = websocket_url
         .into_client_request()
@@ -140,7 +268,7 @@ pub async fn create_websocket_connection(
 
     req.headers_mut().insert(
         SEC_WEBSOCKET_PROTOCOL,
-        HeaderValue::from_static("v1.kernel.websocket.jupyter.org"),
+        HeaderValue::from_static(KERNEL_PROTOCOL),
     );
     req.headers_mut().insert(
         AUTHORIZATION,
@@ -149,26 +277,38 @@ pub async fn create_websocket_connection(
             .map_err(|err| Error::KernelConnect(err.to_string()))?,
     );
 
-    let (ws, _resp) = tokio_tungstenite::connect_async(req)
+    let (ws, resp) = tokio_tungstenite::connect_async(req)
         .await
         .map_err(|err| Error::KernelConnect(err.to_string()))?;
+    let legacy = resp.headers().get(SEC_WEBSOCKET_PROTOCOL)
+        != Some(&HeaderValue::from_static(KERNEL_PROTOCOL));
+    if legacy {
+        info!("server does not support {KERNEL_PROTOCOL}, using the legacy protocol");
+    }
 
     let (mut ws_tx, mut ws_rx) = ws.split();
     let send_fut = async move {
-        // Send shell and control messages over the WebSocket.
+        // Send shell, control, and stdin messages over the WebSocket.
+        let mut frames = FrameBuffer::default();
         loop {
             let (msg, channel) = tokio::select! {
                 Ok(msg) = shell_rx.recv() => (msg, "shell"),
                 Ok(msg) = control_rx.recv() => (msg, "control"),
+                Ok(msg) = stdin_rx.recv() => (msg, "stdin"),
                 else => break,
             };
 
-            let Some(payload) = to_ws_payload(&msg, channel) else {
+            let payload = if legacy {
+                to_legacy_payload(&msg, channel)
+            } else {
+                to_ws_payload(&msg, channel, &mut frames).map(Message::Binary)
+            };
+            let Some(payload) = payload else {
                 error!("error converting message to ws payload");
                 continue;
             };
 
-            if ws_tx.send(Message::Binary(payload)).await.is_err() {
+            if ws_tx.send(payload).await.is_err() {
                 // The WebSocket has been closed.
                 // TODO: Handle reconnection.
                 error!("WebSocket closed, reconnection not yet implemented");
@@ -178,35 +318,29 @@ pub async fn create_websocket_connection(
     };
 
     let receive_fut = async move {
-        // Receieve shell, control, and iopub messages from the WebSocket.
+        // Receieve shell, control, iopub, and stdin messages from the WebSocket.
         while let Some(Ok(ws_payload)) = ws_rx.next().await {
-            let payload = match ws_payload {
-                Message::Binary(payload) => payload,
+            let decoded = match ws_payload {
+                _ if legacy => from_legacy_payload(ws_payload),
+                Message::Binary(payload) => from_ws_payload(Bytes::from(payload)),
                 _ => continue,
             };
-
-            let (msg, channel) = match from_ws_payload(&payload) {
-                Some(msg) => msg,
-                None => continue,
+            let Some((msg, channel)) = decoded else {
+                continue;
             };
 
             match &*channel {
-                "shell" | "control" => {
-                    if let Some(KernelHeader { msg_id, .. }) = &msg.parent_header {
-                        if let Some((_, tx)) = reply_tx_map.remove(msg_id) {
-                            // Optional, it's not an error if this receiver has been dropped.
-                            _ = tx.send(msg);
-                        }
-                    }
-                }
-                "iopub" => {
-                    _ = iopub_tx.send(msg).await;
-                }
+                "shell" | "control" => router.route_reply(&channel, msg),
+                "iopub" => router.route_iopub(msg).await,
+                "stdin" => router.route_stdin(msg).await,
                 _ => {
                     warn!("received WebSocket message on unexpected channel: {channel}");
                 }
             }
         }
+        // There is no heartbeat channel, so the kernel is only known to be
+        // unreachable once the WebSocket closes.
+        _ = alive_tx.send(false);
     };
 
     // Run both futures until cancellation or completion.
@@ -219,3 +353,56 @@ pub async fn create_websocket_connection(
 
     Ok(conn)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::backend::wire_protocol::KernelMessageType;
+
+    #[test]
+    fn legacy_payload_round_trip() {
+        let mut msg = KernelMessage::new(KernelMessageType::CommMsg, json!({ "data": 1 }));
+        let Some(Message::Text(text)) = to_legacy_payload(&msg, "iopub") else {
+            panic!("expected a text payload without buffers");
+        };
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["channel"], "iopub");
+        assert_eq!(value["parent_header"], json!({}));
+        let (decoded, channel) = from_legacy_payload(Message::Text(text)).unwrap();
+        assert_eq!((decoded, channel.as_str()), (msg.clone(), "iopub"));
+
+        msg.parent_header = Some(msg.header.clone());
+        msg.buffers = vec![Bytes::from_static(b"image"), Bytes::from_static(b"xy")];
+        let Some(Message::Binary(payload)) = to_legacy_payload(&msg, "shell") else {
+            panic!("expected a binary payload with buffers");
+        };
+        // Three parts, with the first starting after the four integers.
+        assert_eq!(payload[..8], [0, 0, 0, 3, 0, 0, 0, 16]);
+        assert!(payload.ends_with(b"imagexy"));
+        let (decoded, channel) = from_legacy_payload(Message::Binary(payload)).unwrap();
+        assert_eq!((decoded, channel.as_str()), (msg, "shell"));
+
+        assert!(from_legacy_payload(Message::Binary(vec![0, 0, 0, 9])).is_none());
+    }
+
+    #[test]
+    fn ws_payload_round_trip() {
+        let mut msg = KernelMessage::new(KernelMessageType::CommMsg, json!({ "data": 1 }));
+        msg.buffers = vec![Bytes::from_static(b"image"), Bytes::new()];
+        msg.metadata = json!({ "engine": "e1" });
+
+        let mut frames = FrameBuffer::default();
+        let payload = Bytes::from(to_ws_payload(&msg, "shell", &mut frames).unwrap());
+        let (decoded, channel) = from_ws_payload(payload.clone()).unwrap();
+        assert_eq!(channel, "shell");
+        assert_eq!(decoded, msg);
+
+        // Buffers point into the received payload, rather than being copied.
+        let start = payload.len() - b"image".len();
+        assert_eq!(decoded.buffers[0].as_ptr(), payload[start..].as_ptr());
+
+        assert!(from_ws_payload(payload.slice(..payload.len() / 2)).is_none());
+    }
+}
@@ -4,19 +4,48 @@
 //! <https://jupyter-client.readthedocs.io/en/stable/messaging.html>. It relies
 //! on 5 dedicated sockets for different types of messages.
 
-use std::sync::Arc;
+use std::{io, sync::Arc, time::Duration};
 
 use bytes::Bytes;
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, time::Instant};
 use tokio_util::sync::CancellationToken;
-use tracing::{error, warn};
-use zeromq::{Socket, SocketRecv, SocketSend, ZmqMessage};
+use tracing::{error, info, warn};
+use zeromq::{
+    util::PeerIdentity, Socket, SocketOptions, SocketRecv, SocketSend, ZmqError, ZmqMessage,
+    ZmqResult,
+};
 
-use super::{KernelConnection, KernelHeader, KernelMessage};
+use super::{
+    ConnectionOptions, FrameBuffer, IopubSubscribers, KernelConnection, KernelMessage,
+    MessageRouter,
+};
 use crate::Error;
 
+/// How often to ping the kernel on the heartbeat channel.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long to wait for a reply to a heartbeat before the kernel is considered
+/// unresponsive.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait before reconnecting a socket that failed, doubled after
+/// each failed attempt.
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest delay between attempts to reconnect a socket.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Attempts to reconnect a socket before the kernel is considered gone, which
+/// takes about 15 seconds with backoff.
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+
+/// How long to wait for the kernel to create an IPC socket file.
+const SOCKET_FILE_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Sign a message using HMAC-SHA256 with the kernel's signing key.
-fn sign_message(signing_key: &str, bytes: &[Bytes]) -> String {
+pub fn sign_message(signing_key: &str, bytes: &[Bytes]) -> String {
     use hmac::{Hmac, Mac};
     use sha2::Sha256;
 
@@ -27,167 +56,547 @@ fn sign_message(signing_key: &str, bytes: &[Bytes]) -> String {
     format!("{:x}", mac.finalize().into_bytes())
 }
 
-fn to_zmq_payload(msg: &KernelMessage, signing_key: &str) -> Option<ZmqMessage> {
-    let header = Bytes::from(serde_json::to_vec(&msg.header).ok()?);
-    let parent_header = Bytes::from(serde_json::to_vec(&msg.parent_header).ok()?);
-    let metadata = Bytes::from_static(b"{}");
-    let content = Bytes::from(serde_json::to_vec(&msg.content).ok()?);
+/// Encode a message as ZeroMQ frames, signed with the kernel's key.
+pub fn to_zmq_payload(
+    msg: &KernelMessage,
+    signing_key: &str,
+    frames: &mut FrameBuffer,
+) -> Option<ZmqMessage> {
+    let header = frames.json(&msg.header)?;
+    let parent_header = frames.json(&msg.parent_header)?;
+    let metadata = frames.json(&msg.metadata)?;
+    let content = frames.json(&msg.content)?;
 
-    let mut payload = vec![header, parent_header, metadata, content];
+    let mut payload = Vec::with_capacity(6 + msg.buffers.len());
+    payload.extend([Bytes::from_static(b"<IDS|MSG>"), Bytes::new()]);
+    payload.extend([header, parent_header, metadata, content]);
     payload.extend(msg.buffers.iter().cloned());
-
-    let signature = sign_message(signing_key, &payload);
-    payload.insert(0, Bytes::from(signature));
-    payload.insert(0, Bytes::from_static(b"<IDS|MSG>"));
+    payload[1] = Bytes::from(sign_message(signing_key, &payload[2..]));
 
     ZmqMessage::try_from(payload).ok()
 }
 
-fn from_zmq_payload(payload: ZmqMessage) -> Option<KernelMessage> {
-    let payload = payload.into_vec();
+/// Decode ZeroMQ frames into a message, skipping the routing identities.
+///
+/// The signature is not checked.
+pub fn from_zmq_payload(payload: ZmqMessage) -> Option<KernelMessage> {
+    let mut payload = payload.into_vec();
 
     let delim_idx = payload.iter().position(|b| *b == b"<IDS|MSG>" as &[u8])?;
+    if payload.len() < delim_idx + 6 {
+        return None;
+    }
+    // Buffers are moved out of the message, since frames are already `Bytes`.
+    let buffers = payload.split_off(delim_idx + 6);
     let header = serde_json::from_slice(&payload[delim_idx + 2]).ok()?;
     let parent_header = serde_json::from_slice(&payload[delim_idx + 3]).ok()?;
-    // serde_json::from_slice(&payload[delim_idx + 4]).ok()?;
+    let metadata = serde_json::from_slice(&payload[delim_idx + 4]).ok()?;
     let content = serde_json::from_slice(&payload[delim_idx + 5]).ok()?;
-    let buffers = payload[delim_idx + 6..].to_vec();
 
     Some(KernelMessage {
         header,
         parent_header,
+        metadata,
         content,
         buffers,
     })
 }
 
+/// How a kernel's ZeroMQ sockets are reached.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// TCP sockets on a network interface, usually the loopback address.
+    #[default]
+    Tcp,
+
+    /// Unix domain sockets, which are files named after the `ip` and port.
+    Ipc,
+}
+
+/// Parameters for connecting to a kernel, as written in its connection file.
+///
+/// See <https://jupyter-client.readthedocs.io/en/stable/kernels.html#connection-files>.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// Transport of the kernel's sockets.
+    #[serde(default)]
+    pub transport: Transport,
+
+    /// IP address for TCP, or the path prefix of socket files for IPC.
+    pub ip: String,
+
+    /// Port of the shell socket.
+    pub shell_port: u16,
+
+    /// Port of the control socket.
+    pub control_port: u16,
+
+    /// Port of the iopub socket.
+    pub iopub_port: u16,
+
+    /// Port of the stdin socket.
+    pub stdin_port: u16,
+
+    /// Port of the heartbeat socket.
+    pub hb_port: u16,
+
+    /// Scheme for signing messages, which is always "hmac-sha256".
+    pub signature_scheme: String,
+
+    /// Key for signing messages.
+    pub key: String,
+}
+
+impl ConnectionInfo {
+    /// The ZeroMQ endpoint of one of the kernel's ports.
+    ///
+    /// For IPC, this is a socket file named after the `ip` and port, as in
+    /// `jupyter_client`.
+    pub fn endpoint(&self, port: u16) -> String {
+        match self.transport {
+            Transport::Tcp => format!("tcp://{}:{port}", self.ip),
+            Transport::Ipc => format!("ipc://{}-{port}", self.ip),
+        }
+    }
+}
+
 /// Connect to Jupyter via ZeroMQ to a local kernel.
 pub async fn create_zeromq_connection(
-    shell_port: u16,
-    control_port: u16,
-    iopub_port: u16,
-    stdin_port: u16,
-    heartbeat_port: u16,
-    signing_key: &str,
+    info: &ConnectionInfo,
+    options: &ConnectionOptions,
 ) -> Result<KernelConnection, Error> {
-    let (shell_tx, shell_rx) = async_channel::bounded(8);
-    let (control_tx, control_rx) = async_channel::bounded(8);
-    let (iopub_tx, iopub_rx) = async_channel::bounded(64);
+    let (shell_tx, shell_rx) = async_channel::bounded(options.shell_capacity.max(1));
+    let (control_tx, control_rx) = async_channel::bounded(options.control_capacity.max(1));
+    let (stdin_tx, stdin_rx) = async_channel::bounded(1);
+    let iopub_subscribers = Arc::new(IopubSubscribers::default());
     let reply_tx_map = Arc::new(DashMap::new());
+    let (alive_tx, alive_rx) = watch::channel(true);
+    let (reopened_tx, reopened_rx) = watch::channel(());
     let signal = CancellationToken::new();
 
     let conn = KernelConnection {
         shell_tx,
         control_tx,
-        iopub_rx,
+        stdin_tx,
+        iopub_subscribers: iopub_subscribers.clone(),
         reply_tx_map: reply_tx_map.clone(),
+        alive_rx,
+        protocol_version: Default::default(),
+        supported_features: Default::default(),
+        tap: Default::default(),
+        options: options.clone(),
         signal: signal.clone(),
         _drop_guard: Arc::new(signal.clone().drop_guard()),
     };
+    let router = Arc::new(MessageRouter {
+        reply_tx_map: reply_tx_map.clone(),
+        iopub_subscribers,
+        tap: conn.tap.clone(),
+    });
 
-    let mut shell = zeromq::DealerSocket::new();
-    shell
-        .connect(&format!("tcp://127.0.0.1:{shell_port}"))
-        .await?;
-    let mut control = zeromq::DealerSocket::new();
-    control
-        .connect(&format!("tcp://127.0.0.1:{control_port}"))
-        .await?;
-    let mut iopub = zeromq::SubSocket::new();
-    iopub
-        .connect(&format!("tcp://127.0.0.1:{iopub_port}"))
-        .await?;
+    // Sockets share an identity, so that the kernel routes replies to them
+    // again after they reconnect.
+    let identity = PeerIdentity::new();
+    let dialer = |port: u16| Dialer {
+        endpoint: info.endpoint(port),
+        identity: identity.clone(),
+    };
+
+    let (shell_dialer, control_dialer) = (dialer(info.shell_port), dialer(info.control_port));
+    let shell = shell_dialer.dial().await?;
+    let control = control_dialer.dial().await?;
+    let iopub_dialer = dialer(info.iopub_port);
+    let mut iopub: zeromq::SubSocket = iopub_dialer.dial().await?;
     iopub.subscribe("").await?;
-    let mut stdin = zeromq::DealerSocket::new();
-    stdin
-        .connect(&format!("tcp://127.0.0.1:{stdin_port}"))
-        .await?;
+    let stdin_dialer = dialer(info.stdin_port);
+    let stdin = stdin_dialer.dial().await?;
+    let heartbeat_endpoint = info.endpoint(info.hb_port);
     let mut heartbeat = zeromq::ReqSocket::new();
-    heartbeat
-        .connect(&format!("tcp://127.0.0.1:{heartbeat_port}"))
-        .await?;
+    connect(&mut heartbeat, &heartbeat_endpoint).await?;
+
+    let shell_fut = run_dealer(
+        "shell",
+        shell,
+        shell_dialer,
+        reopened_rx.clone(),
+        shell_rx,
+        info.key.clone(),
+        router.clone(),
+    );
+
+    let control_fut = run_dealer(
+        "control",
+        control,
+        control_dialer,
+        reopened_rx.clone(),
+        control_rx,
+        info.key.clone(),
+        router.clone(),
+    );
 
-    let _ = (stdin, heartbeat); // Not supported yet.
+    let stdin_fut = run_dealer(
+        "stdin",
+        stdin,
+        stdin_dialer,
+        reopened_rx.clone(),
+        stdin_rx,
+        info.key.clone(),
+        router.clone(),
+    );
 
-    let key = signing_key.to_string();
-    let tx_map = reply_tx_map.clone();
-    let shell_fut = async move {
-        // Send and receive shell messages.
+    let mut iopub_reopened = reopened_rx;
+    let iopub_fut = async move {
+        // Receive iopub messages.
         loop {
             tokio::select! {
-                Ok(msg) = shell_rx.recv() => {
-                    let Some(payload) = to_zmq_payload(&msg, &key) else {
-                        error!("error converting shell message to zmq payload");
-                        continue;
-                    };
-                    if let Err(err) = shell.send(payload).await {
-                        warn!("error sending zmq shell message: {err:?}");
-                    }
+                biased;
+                Ok(()) = iopub_reopened.changed() => {
+                    info!("kernel reopened its ports, reconnecting zmq iopub socket");
                 }
-                Ok(payload) = shell.recv() => {
-                    if let Some(msg) = from_zmq_payload(payload) {
-                        if let Some(KernelHeader { msg_id, .. }) = &msg.parent_header {
-                            if let Some((_, reply_tx)) = tx_map.remove(msg_id) {
-                                _ = reply_tx.send(msg);
-                            }
+                result = iopub.recv() => match result {
+                    Ok(payload) => {
+                        if let Some(msg) = from_zmq_payload(payload) {
+                            router.route_iopub(msg).await;
+                        } else {
+                            warn!("error converting zmq payload to iopub message");
                         }
-                    } else {
-                        warn!("error converting zmq payload to shell reply");
+                        continue;
                     }
-                }
-                else => break,
+                    Err(err) => warn!("zmq iopub socket failed, reconnecting: {err:?}"),
+                },
+            }
+            iopub = match iopub_dialer.redial(iopub).await {
+                Ok(iopub) => iopub,
+                Err(err) => return Err::<(), _>(err),
+            };
+            if let Err(err) = iopub.subscribe("").await {
+                warn!("error subscribing to zmq iopub socket: {err:?}");
             }
         }
     };
 
-    let key = signing_key.to_string();
-    let tx_map = reply_tx_map.clone();
-    let control_fut = async move {
-        // Send and receive control messages.
+    let heartbeat_fut = async move {
+        // Ping the kernel periodically, and report whether it replies in time.
+        // Sockets don't notice when the kernel closes its end, so if the
+        // heartbeat port was closed while it was unresponsive, the other
+        // sockets are reconnected once it replies again.
+        let mut closed = false;
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
         loop {
-            tokio::select! {
-                Ok(msg) = control_rx.recv() => {
-                    let Some(payload) = to_zmq_payload(&msg, &key) else {
-                        error!("error converting control message to zmq payload");
-                        continue;
-                    };
-                    if let Err(err) = control.send(payload).await {
-                        warn!("error sending zmq control message: {err:?}");
-                    }
-                }
-                Ok(payload) = control.recv() => {
-                    if let Some(msg) = from_zmq_payload(payload) {
-                        if let Some(KernelHeader { msg_id, .. }) = &msg.parent_header {
-                            if let Some((_, reply_tx)) = tx_map.remove(msg_id) {
-                                _ = reply_tx.send(msg);
-                            }
-                        }
-                    } else {
-                        warn!("error converting zmq payload to control reply");
-                    }
+            interval.tick().await;
+            let ping = async {
+                heartbeat.send(ZmqMessage::from("ping")).await?;
+                heartbeat.recv().await
+            };
+            let replied = match tokio::time::timeout(HEARTBEAT_TIMEOUT, ping).await {
+                Ok(Ok(_)) => true,
+                // An error rather than a timeout means the port was closed.
+                Ok(Err(_)) => {
+                    closed = true;
+                    false
                 }
-                else => break,
+                Err(_) => false,
+            };
+            if replied && std::mem::take(&mut closed) {
+                reopened_tx.send_replace(());
             }
-        }
-    };
-
-    let iopub_fut = async move {
-        // Receive iopub messages.
-        while let Ok(payload) = iopub.recv().await {
-            if let Some(msg) = from_zmq_payload(payload) {
-                _ = iopub_tx.send(msg).await;
-            } else {
-                warn!("error converting zmq payload to iopub message");
+            alive_tx.send_if_modified(|alive| std::mem::replace(alive, replied) != replied);
+            if !replied {
+                // A REQ socket can't send again until it gets a reply, so the
+                // next ping needs a new socket.
+                heartbeat = zeromq::ReqSocket::new();
+                if let Err(err) = connect(&mut heartbeat, &heartbeat_endpoint).await {
+                    warn!("error reconnecting zmq heartbeat socket: {err:?}");
+                    closed = true;
+                }
             }
         }
     };
 
     tokio::spawn(async move {
         tokio::select! {
-            _ = async { tokio::join!(shell_fut, control_fut, iopub_fut) } => {}
+            Err(err) = async { tokio::try_join!(shell_fut, control_fut, stdin_fut, iopub_fut) } => {
+                error!("lost connection to the kernel: {err:?}");
+            }
+            _ = heartbeat_fut => {}
             _ = signal.cancelled() => {}
         }
+        // Requests still waiting for replies fail with `Error::KernelDisconnect`.
+        reply_tx_map.clear();
     });
 
     Ok(conn)
 }
+
+/// Connects sockets to one of the kernel's ports.
+struct Dialer {
+    endpoint: String,
+    identity: PeerIdentity,
+}
+
+impl Dialer {
+    /// Connect a new socket to the port.
+    async fn dial<S: Socket>(&self) -> ZmqResult<S> {
+        let mut options = SocketOptions::default();
+        options.peer_identity(self.identity.clone());
+        let mut socket = S::with_options(options);
+        connect(&mut socket, &self.endpoint).await?;
+        Ok(socket)
+    }
+
+    /// Replace a socket that failed with a new one connected to the same port,
+    /// retrying with backoff up to [`MAX_RECONNECT_ATTEMPTS`] times.
+    ///
+    /// The old socket is closed first, since the kernel refuses a second
+    /// connection with the same identity.
+    async fn redial<S: Socket>(&self, old: S) -> ZmqResult<S> {
+        drop(old);
+        let mut delay = RECONNECT_DELAY;
+        let mut attempt = 1;
+        loop {
+            match self.dial().await {
+                Ok(socket) => {
+                    info!("reconnected zmq socket to {}", self.endpoint);
+                    return Ok(socket);
+                }
+                Err(err) if attempt == MAX_RECONNECT_ATTEMPTS => return Err(err),
+                Err(err) => warn!(
+                    "error reconnecting zmq socket to {}: {err:?}",
+                    self.endpoint
+                ),
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            attempt += 1;
+        }
+    }
+}
+
+/// Connect a socket to an endpoint, waiting for the kernel to open it.
+///
+/// ZeroMQ retries TCP connections that are refused, but not IPC socket files
+/// that don't exist yet, so those are retried here for up to
+/// [`SOCKET_FILE_TIMEOUT`].
+async fn connect(socket: &mut impl Socket, endpoint: &str) -> ZmqResult<()> {
+    let deadline = Instant::now() + SOCKET_FILE_TIMEOUT;
+    loop {
+        match socket.connect(endpoint).await {
+            Err(ZmqError::Network(err))
+                if err.kind() == io::ErrorKind::NotFound && Instant::now() < deadline =>
+            {
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Send requests and receive replies over a dealer socket, for the shell or
+/// control channel. On the stdin channel, this is reversed: the kernel sends
+/// requests for input, and replies are sent to it.
+///
+/// If the socket fails, it is reconnected with the same identity. Requests
+/// still waiting for replies are kept, since the kernel can route replies to
+/// the new socket, and a request that failed to send is sent again. This
+/// returns an error if the socket can't be reconnected.
+///
+/// Sockets don't notice when the kernel closes its end of the connection, so
+/// they are also reconnected when `reopened_rx` reports that the kernel
+/// opened its ports again.
+async fn run_dealer(
+    channel: &str,
+    mut socket: zeromq::DealerSocket,
+    dialer: Dialer,
+    mut reopened_rx: watch::Receiver<()>,
+    rx: async_channel::Receiver<KernelMessage>,
+    signing_key: String,
+    router: Arc<MessageRouter>,
+) -> ZmqResult<()> {
+    let mut frames = FrameBuffer::default();
+    loop {
+        tokio::select! {
+            biased;
+            Ok(()) = reopened_rx.changed() => {
+                info!("kernel reopened its ports, reconnecting zmq {channel} socket");
+                socket = dialer.redial(socket).await?;
+            }
+            msg = rx.recv() => {
+                let Ok(msg) = msg else { break };
+                let Some(payload) = to_zmq_payload(&msg, &signing_key, &mut frames) else {
+                    error!("error converting {channel} message to zmq payload");
+                    continue;
+                };
+                if let Err(err) = socket.send(payload.clone()).await {
+                    warn!("error sending zmq {channel} message, reconnecting: {err:?}");
+                    socket = dialer.redial(socket).await?;
+                    if let Err(err) = socket.send(payload).await {
+                        warn!("error sending zmq {channel} message: {err:?}");
+                        router.reply_tx_map.remove(&msg.header.msg_id);
+                    }
+                }
+            }
+            result = socket.recv() => match result {
+                Ok(payload) => {
+                    if let Some(msg) = from_zmq_payload(payload) {
+                        if channel == "stdin" {
+                            router.route_stdin(msg).await;
+                        } else {
+                            router.route_reply(channel, msg);
+                        }
+                    } else {
+                        warn!("error converting zmq payload to {channel} reply");
+                    }
+                }
+                Err(err) => {
+                    warn!("zmq {channel} socket failed, reconnecting: {err:?}");
+                    socket = dialer.redial(socket).await?;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::backend::wire_protocol::{KernelMessageType, Reply};
+
+    /// Bind a socket of a fake kernel to a free port.
+    async fn bind<S: Socket>() -> (S, u16) {
+        let mut socket = S::new();
+        let endpoint = socket.bind("tcp://127.0.0.1:0").await.unwrap();
+        let port = endpoint.to_string().rsplit(':').next().unwrap().parse();
+        (socket, port.unwrap())
+    }
+
+    /// Answer heartbeats like a kernel, until stopped.
+    fn answer_heartbeats(mut socket: zeromq::RepSocket) -> oneshot::Sender<oneshot::Sender<()>> {
+        let (stop_tx, mut stop_rx) = oneshot::channel::<oneshot::Sender<()>>();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Ok(ping) = socket.recv() => {
+                        _ = socket.send(ping).await;
+                    }
+                    Ok(done_tx) = &mut stop_rx => {
+                        socket.close().await;
+                        _ = done_tx.send(());
+                        break;
+                    }
+                }
+            }
+        });
+        stop_tx
+    }
+
+    #[test]
+    fn zmq_payload_round_trip() {
+        let mut msg = KernelMessage::new(KernelMessageType::CommMsg, json!({ "data": 1 }));
+        msg.buffers = vec![Bytes::from_static(b"image")];
+        msg.metadata = json!({ "engine": "e1" });
+
+        let mut frames = FrameBuffer::default();
+        let payload = to_zmq_payload(&msg, "key", &mut frames).unwrap();
+        let frames_sent = payload.clone().into_vec();
+        assert_eq!(frames_sent[1], sign_message("key", &frames_sent[2..]));
+        assert_eq!(from_zmq_payload(payload).unwrap(), msg);
+    }
+
+    #[test]
+    fn connection_file_endpoints() {
+        let info: ConnectionInfo = serde_json::from_value(json!({
+            "transport": "ipc",
+            "ip": "/tmp/kernel-ipc",
+            "shell_port": 1,
+            "control_port": 2,
+            "iopub_port": 3,
+            "stdin_port": 4,
+            "hb_port": 5,
+            "signature_scheme": "hmac-sha256",
+            "key": "key",
+        }))
+        .unwrap();
+        assert_eq!(info.endpoint(info.shell_port), "ipc:///tmp/kernel-ipc-1");
+
+        let info = ConnectionInfo {
+            transport: Transport::Tcp,
+            ip: "127.0.0.1".into(),
+            ..info
+        };
+        assert_eq!(info.endpoint(info.hb_port), "tcp://127.0.0.1:5");
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_kernel_socket_restarts() {
+        let (shell, shell_port) = bind::<zeromq::RouterSocket>().await;
+        let (_control, control_port) = bind::<zeromq::RouterSocket>().await;
+        let (_iopub, iopub_port) = bind::<zeromq::PubSocket>().await;
+        let (_stdin, stdin_port) = bind::<zeromq::RouterSocket>().await;
+        let (heartbeat, heartbeat_port) = bind::<zeromq::RepSocket>().await;
+        let stop_heartbeat = answer_heartbeats(heartbeat);
+        let conn = create_zeromq_connection(
+            &ConnectionInfo {
+                transport: Transport::Tcp,
+                ip: "127.0.0.1".into(),
+                shell_port,
+                control_port,
+                iopub_port,
+                stdin_port,
+                hb_port: heartbeat_port,
+                signature_scheme: "hmac-sha256".into(),
+                key: "key".into(),
+            },
+            &ConnectionOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut alive_rx = conn.watch_alive();
+
+        // The kernel closes its sockets, then opens them again on the same
+        // ports. Requests are only sent once it is responsive again.
+        shell.close().await;
+        let (done_tx, done_rx) = oneshot::channel();
+        stop_heartbeat.send(done_tx).unwrap();
+        done_rx.await.unwrap();
+        alive_rx.wait_for(|alive| !alive).await.unwrap();
+
+        let mut shell = zeromq::RouterSocket::new();
+        shell
+            .bind(&format!("tcp://127.0.0.1:{shell_port}"))
+            .await
+            .unwrap();
+        let mut heartbeat = zeromq::RepSocket::new();
+        heartbeat
+            .bind(&format!("tcp://127.0.0.1:{heartbeat_port}"))
+            .await
+            .unwrap();
+        let _stop_heartbeat = answer_heartbeats(heartbeat);
+        alive_rx.wait_for(|alive| *alive).await.unwrap();
+
+        let msg = KernelMessage::new(KernelMessageType::KernelInfoRequest, json!({}));
+        let mut pending = conn.call_shell(msg).await.unwrap();
+        let request = shell.recv().await.unwrap();
+        let identity = request.get(0).unwrap().clone();
+        let request = from_zmq_payload(request).unwrap();
+
+        let mut reply =
+            KernelMessage::new(KernelMessageType::KernelInfoReply, json!({"status": "ok"}));
+        reply.parent_header = Some(request.header);
+        let mut payload = to_zmq_payload(&reply, "key", &mut FrameBuffer::default()).unwrap();
+        payload.push_front(identity);
+        shell.send(payload).await.unwrap();
+
+        let reply = tokio::time::timeout(Duration::from_secs(10), pending.get_reply::<Value>())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(reply.content, Reply::Ok(_)));
+    }
+}
@@ -0,0 +1,422 @@
+//! Queues of iopub messages for subscribers, and what happens when they fill
+//! up because a subscriber falls behind the kernel.
+
+use std::{
+    env, fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use async_channel::TrySendError;
+use bytes::Bytes;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter, SeekFrom},
+    sync::Mutex,
+};
+use tracing::warn;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use super::{
+    from_ws_payload, to_ws_payload, FrameBuffer, KernelHeader, KernelMessage, KernelMessageType,
+};
+use crate::Error;
+
+/// What to do when iopub messages from a running cell arrive faster than they
+/// are received, and its queue is full.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowStrategy {
+    /// Stop reading iopub messages until there is room, so no output is lost.
+    /// Other subscribers wait too, and the kernel eventually blocks.
+    #[default]
+    Pause,
+
+    /// Keep reading, but drop outputs until there is room. The number dropped
+    /// is reported by [`IopubSubscription::take_dropped`]. Other messages,
+    /// like errors, status changes and requests to clear output, are never
+    /// dropped.
+    Drop,
+
+    /// Keep reading, and write messages that don't fit to a temporary file
+    /// until the subscriber catches up, so none are lost.
+    SpillToDisk,
+}
+
+crate::export_bindings!(OverflowStrategy);
+
+/// Queues for iopub messages to subscribers, shared by a connection and the
+/// driver that routes messages into them.
+#[derive(Default)]
+pub(super) struct IopubSubscribers {
+    /// Queues for messages whose parent is a request, by its message ID.
+    pub requests: DashMap<String, Arc<IopubQueue>>,
+
+    /// Queues that receive a copy of every message, by a random ID.
+    pub listeners: DashMap<String, Arc<IopubQueue>>,
+
+    /// Input requests waiting for a reply, by message ID. These are removed
+    /// with the subscription of the request that made them.
+    pub inputs: DashMap<String, PendingInput>,
+
+    /// Set once the driver stops, so no more messages will arrive.
+    pub closed: AtomicBool,
+}
+
+/// An input request from the kernel, waiting for a reply.
+pub(super) struct PendingInput {
+    /// Header of the input request.
+    pub header: KernelHeader,
+
+    /// Message ID of the request that asked for input.
+    pub parent: String,
+
+    /// Prompt shown to the user.
+    pub prompt: String,
+
+    /// Whether the input is a password.
+    pub password: bool,
+}
+
+impl IopubSubscribers {
+    /// Add a queue, closing it right away if the driver has stopped.
+    pub fn insert(
+        self: &Arc<Self>,
+        id: String,
+        listener: bool,
+        queue: IopubQueue,
+    ) -> IopubSubscription {
+        let queue = Arc::new(queue);
+        let map = if listener {
+            &self.listeners
+        } else {
+            &self.requests
+        };
+        map.insert(id.clone(), queue.clone());
+        if self.closed.load(Ordering::SeqCst) {
+            queue.close();
+        }
+        IopubSubscription {
+            subscribers: self.clone(),
+            queue,
+            id,
+            listener,
+        }
+    }
+
+    /// Close all queues, so that they end after their remaining messages.
+    pub fn close(&self) {
+        // Mark this first, so that later subscriptions see it is closed.
+        self.closed.store(true, Ordering::SeqCst);
+        for queue in self.requests.iter().chain(self.listeners.iter()) {
+            queue.close();
+        }
+    }
+}
+
+/// Receives iopub messages from a connection. Subscriptions are either to
+/// messages whose parent is a request, with stdin requests for input while it
+/// runs, from
+/// [`KernelConnection::subscribe_iopub`](super::KernelConnection::subscribe_iopub),
+/// or to every message, from
+/// [`KernelConnection::listen_iopub`](super::KernelConnection::listen_iopub).
+pub struct IopubSubscription {
+    subscribers: Arc<IopubSubscribers>,
+    queue: Arc<IopubQueue>,
+    id: String,
+    listener: bool,
+}
+
+impl IopubSubscription {
+    /// Receive the next iopub message for the subscription.
+    pub async fn recv(&self) -> Result<KernelMessage, Error> {
+        self.queue.recv().await
+    }
+
+    /// Receive the next message if one is queued in memory, without waiting.
+    pub fn try_recv(&self) -> Option<KernelMessage> {
+        self.queue.rx.try_recv().ok()
+    }
+
+    /// Number of outputs dropped since the last call, with
+    /// [`OverflowStrategy::Drop`].
+    pub fn take_dropped(&self) -> u64 {
+        self.queue.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+impl Drop for IopubSubscription {
+    fn drop(&mut self) {
+        match self.listener {
+            true => self.subscribers.listeners.remove(&self.id),
+            false => {
+                // Input requests aren't answered once their request is done.
+                (self.subscribers.inputs).retain(|_, input| input.parent != self.id);
+                self.subscribers.requests.remove(&self.id)
+            }
+        };
+    }
+}
+
+/// A bounded queue of iopub messages for one subscriber, which applies an
+/// [`OverflowStrategy`] when it is full. Queues of listeners have no strategy,
+/// and drop their oldest message instead, so they never hold up the kernel.
+pub(super) struct IopubQueue {
+    tx: async_channel::Sender<KernelMessage>,
+    rx: async_channel::Receiver<KernelMessage>,
+    overflow: Option<OverflowStrategy>,
+
+    /// Messages that were dropped and not yet reported.
+    dropped: AtomicU64,
+
+    /// Messages written to disk, which are all newer than those in the
+    /// channel. While any are left, new messages are written there too.
+    spill: Mutex<Option<SpillFile>>,
+}
+
+impl IopubQueue {
+    /// Create an empty queue.
+    pub fn new(capacity: usize, overflow: Option<OverflowStrategy>) -> Self {
+        let (tx, rx) = async_channel::bounded(capacity.max(1));
+        Self {
+            tx,
+            rx,
+            overflow,
+            dropped: AtomicU64::new(0),
+            spill: Mutex::new(None),
+        }
+    }
+
+    /// Add a message to the queue.
+    pub async fn send(&self, msg: KernelMessage) {
+        match self.overflow {
+            None => {
+                if let Ok(Some(_)) = self.tx.force_send(msg) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            // It's not an error if the subscription was dropped meanwhile.
+            Some(OverflowStrategy::Pause) => _ = self.tx.send(msg).await,
+            Some(OverflowStrategy::Drop) if is_output(&msg.header.msg_type) => {
+                if let Err(TrySendError::Full(_)) = self.tx.try_send(msg) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Some(OverflowStrategy::Drop) => _ = self.tx.send(msg).await,
+            Some(OverflowStrategy::SpillToDisk) => {
+                let mut spill = self.spill.lock().await;
+                let msg = match spill.as_ref().map_or(0, |spill| spill.pending) {
+                    0 => match self.tx.try_send(msg) {
+                        Ok(()) | Err(TrySendError::Closed(_)) => return,
+                        Err(TrySendError::Full(msg)) => msg,
+                    },
+                    _ => msg,
+                };
+                let result = match &mut *spill {
+                    Some(spill) => spill.push(&msg).await,
+                    None => match SpillFile::create().await {
+                        Ok(file) => spill.insert(file).push(&msg).await,
+                        Err(err) => Err(err),
+                    },
+                };
+                if let Err(err) = result {
+                    warn!("could not spill iopub message to disk, dropping it: {err}");
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Receive the oldest message in the queue, waiting for one if it's empty.
+    pub async fn recv(&self) -> Result<KernelMessage, Error> {
+        if let Ok(msg) = self.rx.try_recv() {
+            return Ok(msg);
+        }
+        let mut spill = self.spill.lock().await;
+        if let Some(file) = &mut *spill {
+            match file.pop().await {
+                Ok(Some(msg)) => return Ok(msg),
+                Ok(None) => {}
+                Err(err) => {
+                    warn!("could not read spilled iopub messages, dropping them: {err}");
+                    let lost = file.pending as u64;
+                    self.dropped.fetch_add(lost, Ordering::Relaxed);
+                    *spill = None;
+                }
+            }
+        }
+        drop(spill);
+        self.rx.recv().await.map_err(|_| Error::KernelDisconnect)
+    }
+
+    /// Close the queue, so that it ends after the remaining messages.
+    pub fn close(&self) {
+        self.tx.close();
+    }
+}
+
+/// Whether a message is output that [`OverflowStrategy::Drop`] may drop.
+fn is_output(msg_type: &KernelMessageType) -> bool {
+    matches!(
+        msg_type,
+        KernelMessageType::Stream
+            | KernelMessageType::DisplayData
+            | KernelMessageType::UpdateDisplayData
+            | KernelMessageType::ExecuteResult
+    )
+}
+
+/// A temporary file of messages that didn't fit in a queue, read back in the
+/// order they were written. Only the current user can read it, since outputs
+/// may be private.
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    frames: FrameBuffer,
+    pending: usize,
+}
+
+impl SpillFile {
+    /// Create a new spill file in the temporary directory.
+    async fn create() -> io::Result<Self> {
+        let path = env::temp_dir().join(format!("jute-iopub-{}.spill", Uuid::new_v4()));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let writer = BufWriter::new(options.open(&path).await?);
+        let reader = BufReader::new(File::open(&path).await?);
+        Ok(Self {
+            path,
+            writer,
+            reader,
+            frames: FrameBuffer::default(),
+            pending: 0,
+        })
+    }
+
+    /// Append a message, with a length prefix.
+    async fn push(&mut self, msg: &KernelMessage) -> io::Result<()> {
+        let payload = to_ws_payload(msg, "iopub", &mut self.frames)
+            .ok_or_else(|| io::Error::other("could not encode message"))?;
+        self.writer
+            .write_all(&(payload.len() as u64).to_le_bytes())
+            .await?;
+        self.writer.write_all(&payload).await?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Read the oldest message, if there are any left. Once all are read, the
+    /// file is emptied so that it doesn't grow forever.
+    async fn pop(&mut self) -> io::Result<Option<KernelMessage>> {
+        if self.pending == 0 {
+            return Ok(None);
+        }
+        self.writer.flush().await?;
+        let mut len = [0; 8];
+        self.reader.read_exact(&mut len).await?;
+        let mut payload = vec![0; u64::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut payload).await?;
+        self.pending -= 1;
+        if self.pending == 0 {
+            self.writer.get_ref().set_len(0).await?;
+            self.writer.seek(SeekFrom::Start(0)).await?;
+            self.reader.seek(SeekFrom::Start(0)).await?;
+        }
+        let (msg, _) = from_ws_payload(Bytes::from(payload))
+            .ok_or_else(|| io::Error::other("could not decode message"))?;
+        Ok(Some(msg))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::backend::wire_protocol::KernelMessageType;
+
+    fn output(text: &str) -> KernelMessage {
+        KernelMessage::new(KernelMessageType::Stream, json!({ "text": text }))
+    }
+
+    async fn recv_text(queue: &IopubQueue) -> String {
+        let msg = queue.recv().await.unwrap();
+        msg.content["text"].as_str().unwrap().into()
+    }
+
+    #[tokio::test]
+    async fn drop_only_outputs() {
+        let queue = IopubQueue::new(2, Some(OverflowStrategy::Drop));
+        for text in ["a", "b", "c"] {
+            queue.send(output(text)).await;
+        }
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 1);
+        assert_eq!(recv_text(&queue).await, "a");
+
+        // Other messages wait for room instead of being dropped.
+        let status = KernelMessage::new(KernelMessageType::Status, json!({}));
+        queue.send(status).await;
+        assert_eq!(recv_text(&queue).await, "b");
+        let msg = queue.recv().await.unwrap();
+        assert_eq!(msg.header.msg_type, KernelMessageType::Status);
+    }
+
+    #[tokio::test]
+    async fn listeners_drop_oldest() {
+        let queue = IopubQueue::new(2, None);
+        for text in ["a", "b", "c"] {
+            queue.send(output(text)).await;
+        }
+        assert_eq!(recv_text(&queue).await, "b");
+        assert_eq!(recv_text(&queue).await, "c");
+    }
+
+    #[tokio::test]
+    async fn spill_to_disk_when_full() {
+        let queue = IopubQueue::new(2, Some(OverflowStrategy::SpillToDisk));
+        for text in ["a", "b", "c", "d"] {
+            queue.send(output(text)).await;
+        }
+        assert_eq!(queue.spill.lock().await.as_ref().unwrap().pending, 2);
+        assert_eq!(recv_text(&queue).await, "a");
+
+        // Messages stay in order while the queue is catching up.
+        queue.send(output("e")).await;
+        for text in ["b", "c", "d", "e"] {
+            assert_eq!(recv_text(&queue).await, text);
+        }
+
+        // Once the spill file is empty, messages are queued in memory again.
+        queue.send(output("f")).await;
+        assert_eq!(queue.rx.len(), 1);
+        queue.close();
+        assert_eq!(recv_text(&queue).await, "f");
+        assert!(queue.recv().await.is_err());
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn spill_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let file = SpillFile::create().await.unwrap();
+        let mode = fs::metadata(&file.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,151 @@
+//! Subshells of a kernel, from [JEP 91], which handle shell requests in their
+//! own thread so they can run while the main shell is busy.
+//!
+//! [JEP 91]: https://github.com/jupyter/enhancement-proposals/pull/91
+
+use serde::Serialize;
+
+use super::{
+    CreateSubshellReply, CreateSubshellRequest, DeleteSubshellRequest, IopubSubscription,
+    KernelConnection, KernelMessage, KernelMessageType, OverflowStrategy, PendingRequest, Reply,
+};
+use crate::Error;
+
+/// Name of the feature in `kernel_info_reply` for kernels that support
+/// subshells.
+pub const SUBSHELLS_FEATURE: &str = "kernel subshells";
+
+impl KernelConnection {
+    /// Create a subshell, which handles shell requests concurrently with the
+    /// main shell and other subshells.
+    ///
+    /// This needs a kernel that reports [`SUBSHELLS_FEATURE`] in its info.
+    /// Other kernels ignore the request, so it is not sent to them.
+    pub async fn create_subshell(&self) -> Result<Subshell, Error> {
+        if !self.supports_feature(SUBSHELLS_FEATURE) {
+            return Err(Error::KernelUnsupported("subshells".into()));
+        }
+        let mut req = self
+            .call_control(KernelMessage::new(
+                KernelMessageType::CreateSubshellRequest,
+                CreateSubshellRequest {},
+            ))
+            .await?;
+        let msg = req.get_reply::<CreateSubshellReply>().await?;
+        match msg.content {
+            Reply::Ok(reply) => Ok(Subshell {
+                conn: self.clone(),
+                id: reply.subshell_id,
+            }),
+            Reply::Error(_) | Reply::Abort => Err(Error::KernelDisconnect),
+        }
+    }
+}
+
+/// A subshell of a kernel, which is deleted when this is dropped.
+pub struct Subshell {
+    conn: KernelConnection,
+    id: String,
+}
+
+impl Subshell {
+    /// ID of the subshell in the kernel.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The connection to the kernel of the subshell.
+    pub fn conn(&self) -> &KernelConnection {
+        &self.conn
+    }
+
+    /// Send a message to the subshell over the shell channel.
+    ///
+    /// Replies and iopub messages for it are received like those of the main
+    /// shell.
+    pub async fn call_shell<T: Serialize>(
+        &self,
+        mut message: KernelMessage<T>,
+    ) -> Result<PendingRequest, Error> {
+        message.header.subshell_id = Some(self.id.clone());
+        self.conn.call_shell(message).await
+    }
+
+    /// Subscribe to iopub messages for a request to the subshell. See
+    /// [`KernelConnection::subscribe_iopub`].
+    pub fn subscribe_iopub(&self, msg_id: &str, overflow: OverflowStrategy) -> IopubSubscription {
+        self.conn.subscribe_iopub(msg_id, overflow)
+    }
+}
+
+impl Drop for Subshell {
+    fn drop(&mut self) {
+        let conn = self.conn.clone();
+        let msg = KernelMessage::new(
+            KernelMessageType::DeleteSubshellRequest,
+            DeleteSubshellRequest {
+                subshell_id: self.id.clone(),
+            },
+        );
+        // The reply isn't awaited, so the connection isn't held until then.
+        tokio::spawn(async move {
+            _ = conn.call_control(msg).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::backend::wire_protocol::tests::{test_connection, TestDriver};
+
+    #[tokio::test]
+    async fn create_and_delete_subshell() {
+        let (conn, driver) = test_connection(Default::default());
+        let TestDriver {
+            router,
+            shell_rx,
+            control_rx,
+            ..
+        } = driver;
+        assert!(matches!(
+            conn.create_subshell().await,
+            Err(Error::KernelUnsupported(_))
+        ));
+
+        conn.set_supported_features(&[SUBSHELLS_FEATURE.into()]);
+        let kernel = async {
+            let request = control_rx.recv().await.unwrap();
+            assert_eq!(
+                request.header.msg_type,
+                KernelMessageType::CreateSubshellRequest
+            );
+            let mut reply = KernelMessage::new(
+                KernelMessageType::CreateSubshellReply,
+                json!({ "status": "ok", "subshell_id": "sub1" }),
+            );
+            reply.parent_header = Some(request.header);
+            router.route_reply("control", reply);
+        };
+        let (subshell, ()) = tokio::join!(conn.create_subshell(), kernel);
+        let subshell = subshell.unwrap();
+        assert_eq!(subshell.id(), "sub1");
+
+        let msg = KernelMessage::new(KernelMessageType::ExecuteRequest, json!({}));
+        subshell.call_shell(msg).await.unwrap();
+        let request = shell_rx.recv().await.unwrap();
+        assert_eq!(request.header.subshell_id.as_deref(), Some("sub1"));
+        let header = serde_json::to_value(&request.header).unwrap();
+        assert_eq!(header["subshell_id"], "sub1");
+
+        drop(subshell);
+        let request = control_rx.recv().await.unwrap();
+        assert_eq!(
+            request.header.msg_type,
+            KernelMessageType::DeleteSubshellRequest
+        );
+        assert_eq!(request.content["subshell_id"], "sub1");
+    }
+}
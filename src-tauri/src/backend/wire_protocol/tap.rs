@@ -0,0 +1,202 @@
+//! Opt-in recording of the messages sent to and received from a kernel, for
+//! debugging kernels that misbehave.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use ts_rs::TS;
+
+use super::{KernelMessage, KernelMessageType};
+
+/// How many messages are kept in the tap's ring buffer.
+pub const TAP_CAPACITY: usize = 1000;
+
+/// Longest message content recorded, in bytes of JSON.
+pub const MAX_TAPPED_CONTENT: usize = 4096;
+
+/// Whether a message was sent to the kernel or received from it.
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageDirection {
+    /// Sent from the app to the kernel.
+    Sent,
+
+    /// Received by the app from the kernel.
+    Received,
+}
+
+/// A message recorded by a [`MessageTap`].
+#[derive(Serialize, Clone, Debug, PartialEq, TS)]
+pub struct TappedMessage {
+    /// The channel of the message, like "shell" or "iopub".
+    pub channel: String,
+
+    /// Whether the message was sent or received.
+    pub direction: MessageDirection,
+
+    /// The type of the message.
+    pub msg_type: KernelMessageType,
+
+    /// ID of the message.
+    pub msg_id: String,
+
+    /// ID of the request that this message is in response to, if any.
+    pub parent_msg_id: Option<String>,
+
+    /// When the message was recorded, in milliseconds since the Unix epoch.
+    pub time_ms: f64,
+
+    /// Milliseconds between sending the parent request and receiving this,
+    /// if the request was recorded.
+    pub latency_ms: Option<f64>,
+
+    /// Content of the message as JSON, cut off at [`MAX_TAPPED_CONTENT`].
+    pub content: String,
+
+    /// Whether the content was cut off.
+    pub truncated: bool,
+}
+
+crate::export_bindings!(TappedMessage);
+
+/// Records messages on a kernel connection into a ring buffer, and broadcasts
+/// them to subscribers. This does nothing until it is enabled.
+#[derive(Debug)]
+pub struct MessageTap {
+    enabled: AtomicBool,
+    messages: Mutex<VecDeque<TappedMessage>>,
+    events: broadcast::Sender<TappedMessage>,
+}
+
+impl Default for MessageTap {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            messages: Mutex::new(VecDeque::new()),
+            events: broadcast::channel(256).0,
+        }
+    }
+}
+
+impl MessageTap {
+    /// Start or stop recording messages. Stopping clears recorded messages.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.messages.lock().unwrap().clear();
+        }
+    }
+
+    /// Whether messages are being recorded.
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// The recorded messages, oldest first.
+    pub fn messages(&self) -> Vec<TappedMessage> {
+        self.messages.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Subscribe to messages as they are recorded.
+    pub fn subscribe(&self) -> broadcast::Receiver<TappedMessage> {
+        self.events.subscribe()
+    }
+
+    /// Record a message, if the tap is enabled.
+    pub(super) fn record(&self, channel: &str, direction: MessageDirection, msg: &KernelMessage) {
+        if !self.enabled() {
+            return;
+        }
+        let time_ms = OffsetDateTime::now_utc().unix_timestamp_nanos() as f64 / 1e6;
+        let mut content = msg.content.to_string();
+        let truncated = content.len() > MAX_TAPPED_CONTENT;
+        if truncated {
+            let mut end = MAX_TAPPED_CONTENT;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            content.truncate(end);
+        }
+        let parent_msg_id = msg
+            .parent_header
+            .as_ref()
+            .map(|parent| parent.msg_id.clone());
+
+        let mut messages = self.messages.lock().unwrap();
+        let latency_ms = parent_msg_id.as_ref().and_then(|parent_id| {
+            messages
+                .iter()
+                .rev()
+                .find(|sent| sent.direction == MessageDirection::Sent && &sent.msg_id == parent_id)
+                .map(|sent| time_ms - sent.time_ms)
+        });
+        let tapped = TappedMessage {
+            channel: channel.into(),
+            direction,
+            msg_type: msg.header.msg_type.clone(),
+            msg_id: msg.header.msg_id.clone(),
+            parent_msg_id,
+            time_ms,
+            latency_ms,
+            content,
+            truncated,
+        };
+        if messages.len() == TAP_CAPACITY {
+            messages.pop_front();
+        }
+        messages.push_back(tapped.clone());
+        drop(messages);
+        _ = self.events.send(tapped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn record_messages() {
+        let tap = MessageTap::default();
+        let request = KernelMessage::new(KernelMessageType::ExecuteRequest, json!({}));
+        tap.record("shell", MessageDirection::Sent, &request);
+        assert!(tap.messages().is_empty());
+
+        tap.set_enabled(true);
+        let mut rx = tap.subscribe();
+        tap.record("shell", MessageDirection::Sent, &request);
+        let mut output = KernelMessage::new(
+            KernelMessageType::Stream,
+            json!({ "text": "é".repeat(MAX_TAPPED_CONTENT) }),
+        );
+        output.parent_header = Some(request.header.clone());
+        tap.record("iopub", MessageDirection::Received, &output);
+
+        let messages = tap.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(rx.try_recv().unwrap(), messages[0]);
+        assert_eq!(
+            messages[1].parent_msg_id,
+            Some(request.header.msg_id.clone())
+        );
+        assert!(messages[1].latency_ms.is_some());
+        assert!(messages[1].truncated);
+        assert!(messages[1].content.len() <= MAX_TAPPED_CONTENT);
+
+        for _ in 0..TAP_CAPACITY {
+            tap.record("shell", MessageDirection::Sent, &request);
+        }
+        assert_eq!(tap.messages().len(), TAP_CAPACITY);
+        tap.set_enabled(false);
+        assert!(tap.messages().is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+//! Print the searchable metadata of a notebook to stdout as JSON.
+//!
+//! This is bundled with the Spotlight importer on macOS, which runs it for
+//! each notebook that Spotlight indexes.
+
+use std::{env, fs, path::Path, process::exit};
+
+use jute::{import::NotebookFormat, indexing::search_metadata};
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: jute-index <notebook.ipynb>");
+        exit(2);
+    };
+    let path = Path::new(&path);
+
+    let notebook = match fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            NotebookFormat::from_path(path)
+                .read(&contents)
+                .map_err(|err| err.to_string())
+        }) {
+        Ok(notebook) => notebook,
+        Err(err) => {
+            eprintln!("could not read notebook: {err}");
+            exit(1);
+        }
+    };
+
+    match serde_json::to_string(&search_metadata(&notebook)) {
+        Ok(json) => println!("{json}"),
+        Err(err) => {
+            eprintln!("could not write metadata: {err}");
+            exit(1);
+        }
+    }
+}
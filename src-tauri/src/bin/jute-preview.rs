@@ -0,0 +1,30 @@
+//! Render a static HTML preview of a notebook to stdout.
+//!
+//! This is bundled with the Quick Look extension on macOS, which runs it for
+//! each notebook previewed in Finder.
+
+use std::{env, fs, path::Path, process::exit};
+
+use jute::{backend::notebook::NotebookRoot, export::preview::export_preview};
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: jute-preview <notebook.ipynb>");
+        exit(2);
+    };
+    let path = Path::new(&path);
+
+    let notebook: NotebookRoot = match fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|err| err.to_string()))
+    {
+        Ok(notebook) => notebook,
+        Err(err) => {
+            eprintln!("could not read notebook: {err}");
+            exit(1);
+        }
+    };
+
+    let title = path.file_name().unwrap_or_default().to_string_lossy();
+    print!("{}", export_preview(&notebook, &title));
+}
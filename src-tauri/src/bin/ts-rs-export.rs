@@ -4,9 +4,6 @@ use std::{
     process::{exit, Command},
 };
 
-use jute::backend::{commands::RunCellEvent, notebook::NotebookRoot};
-use ts_rs::TS;
-
 fn main() {
     let export_path = Path::new("../src/bindings");
 
@@ -27,8 +24,10 @@ fn main() {
     // Generate TypeScript bindings
     println!("Exporting TypeScript bindings...");
 
-    NotebookRoot::export_all_to(export_path).unwrap();
-    RunCellEvent::export_all_to(export_path).unwrap();
+    for binding in jute::bindings::all() {
+        println!("  {}", binding.name);
+        (binding.export)(export_path).unwrap();
+    }
 
     // Generate `index.ts` file
     println!("Generating index.ts...");
@@ -0,0 +1,48 @@
+//! Registry of types exported as TypeScript bindings for the frontend.
+//!
+//! Types shared with the frontend, such as command inputs and outputs,
+//! settings, and event payloads, are registered next to their definitions with
+//! [`export_bindings!`]. The `ts-rs-export` binary then writes every registered
+//! type, along with the types it depends on, to `src/bindings`.
+
+use std::path::Path;
+
+use ts_rs::{ExportError, TS};
+
+/// A type registered for export to TypeScript.
+pub struct Binding {
+    /// Name of the Rust type.
+    pub name: &'static str,
+
+    /// Export the type and its dependencies to a directory.
+    pub export: fn(&Path) -> Result<(), ExportError>,
+}
+
+inventory::collect!(Binding);
+
+/// Export a type and its dependencies to a directory.
+pub fn export<T: TS + 'static>(out_dir: &Path) -> Result<(), ExportError> {
+    T::export_all_to(out_dir)
+}
+
+/// Iterate over all registered types, sorted by name.
+pub fn all() -> Vec<&'static Binding> {
+    let mut bindings: Vec<_> = inventory::iter::<Binding>.into_iter().collect();
+    bindings.sort_by_key(|binding| binding.name);
+    bindings
+}
+
+/// Register types to be exported as TypeScript bindings.
+#[macro_export]
+macro_rules! export_bindings {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            ::inventory::submit! {
+                $crate::bindings::Binding {
+                    name: stringify!($ty),
+                    export: $crate::bindings::export::<$ty>,
+                }
+            }
+        )*
+    };
+}
@@ -0,0 +1,256 @@
+//! Checkpoints of notebooks, copies of the file as it was last saved that the
+//! user can roll back to, like Jupyter's.
+//!
+//! Checkpoints are kept next to the notebook in an `.ipynb_checkpoints`
+//! folder, named `<stem>-checkpoint-<id>.<ext>` where the ID is the time they
+//! were created in milliseconds, moved ahead if another checkpoint was created
+//! in the same millisecond. If the notebook's folder is read-only, they
+//! are kept in the app data directory instead, in a folder named by a hash of
+//! the notebook's path.
+
+use std::{
+    cmp::Reverse,
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use ts_rs::TS;
+
+use crate::Error;
+
+/// Name of the folder next to a notebook that holds its checkpoints.
+const CHECKPOINT_DIR: &str = ".ipynb_checkpoints";
+
+/// Number of checkpoints kept for each notebook. Older ones are deleted.
+const MAX_CHECKPOINTS: usize = 10;
+
+/// A saved copy of a notebook.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct Checkpoint {
+    /// Identifier of the checkpoint, unique for the notebook.
+    pub id: String,
+
+    /// When the checkpoint was created.
+    #[serde(with = "time::serde::rfc3339")]
+    #[ts(type = "string")]
+    pub created: OffsetDateTime,
+}
+
+crate::export_bindings!(Checkpoint);
+
+/// Stores checkpoints of notebooks, with a fallback directory for notebooks
+/// in read-only folders.
+#[derive(Clone, Debug)]
+pub struct CheckpointStore {
+    fallback_dir: PathBuf,
+}
+
+impl CheckpointStore {
+    /// Create a store that falls back to the given directory.
+    pub fn new(fallback_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            fallback_dir: fallback_dir.into(),
+        }
+    }
+
+    /// Directories where a notebook's checkpoints may be, in order.
+    fn dirs(&self, notebook: &Path) -> [PathBuf; 2] {
+        let parent = notebook.parent().unwrap_or(Path::new("."));
+        let hash = Sha256::digest(notebook.to_string_lossy().as_bytes());
+        [
+            parent.join(CHECKPOINT_DIR),
+            self.fallback_dir.join(format!("{hash:x}")),
+        ]
+    }
+
+    /// Prefix and suffix of the names of a notebook's checkpoint files.
+    fn file_affixes(notebook: &Path) -> (String, String) {
+        let stem = notebook.file_stem().unwrap_or_default().to_string_lossy();
+        let suffix = match notebook.extension() {
+            Some(ext) => format!(".{}", ext.to_string_lossy()),
+            None => String::new(),
+        };
+        (format!("{stem}-checkpoint-"), suffix)
+    }
+
+    /// Copy the notebook on disk to a new checkpoint, deleting the oldest ones
+    /// past the limit.
+    pub async fn create(&self, notebook: &Path) -> Result<Checkpoint, Error> {
+        let (prefix, suffix) = Self::file_affixes(notebook);
+        let [local, fallback] = self.dirs(notebook);
+        let mut millis = OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000;
+        let checkpoint = loop {
+            let id = millis.to_string();
+            let name = format!("{prefix}{id}{suffix}");
+            let result = match copy_into(notebook, &local, &name).await {
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+                    ) =>
+                {
+                    copy_into(notebook, &fallback, &name).await
+                }
+                result => result,
+            };
+            match result {
+                Ok(()) => break parse_id(&id).expect("checkpoint IDs are timestamps"),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => millis += 1,
+                Err(err) => return Err(Error::Filesystem(err)),
+            }
+        };
+
+        for old in self.list(notebook).await?.iter().skip(MAX_CHECKPOINTS) {
+            if let Some(file) = self.find(notebook, &old.id).await {
+                _ = tokio::fs::remove_file(file).await;
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    /// List the checkpoints of a notebook, newest first.
+    pub async fn list(&self, notebook: &Path) -> Result<Vec<Checkpoint>, Error> {
+        let (prefix, suffix) = Self::file_affixes(notebook);
+        let mut checkpoints = Vec::new();
+        for dir in self.dirs(notebook) {
+            let mut it = match tokio::fs::read_dir(&dir).await {
+                Ok(it) => it,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(Error::Filesystem(err)),
+            };
+            while let Some(entry) = it.next_entry().await.map_err(Error::Filesystem)? {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let id = (name.strip_prefix(&prefix)).and_then(|rest| rest.strip_suffix(&suffix));
+                if let Some(checkpoint) = id.and_then(parse_id) {
+                    checkpoints.push(checkpoint);
+                }
+            }
+        }
+        checkpoints.sort_by_key(|c| Reverse(c.created));
+        Ok(checkpoints)
+    }
+
+    /// Replace the notebook on disk with a checkpoint.
+    ///
+    /// The current file is checkpointed first, so that the restore can be
+    /// undone, and then replaced at once by renaming a copy of the checkpoint.
+    pub async fn restore(&self, notebook: &Path, id: &str) -> Result<(), Error> {
+        let Some(file) = self.find(notebook, id).await else {
+            return Err(Error::Filesystem(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("checkpoint {id} not found"),
+            )));
+        };
+        let name = notebook.file_name().unwrap_or_default().to_string_lossy();
+        let temp = notebook.with_file_name(format!(".{name}.restore-{id}"));
+        tokio::fs::copy(&file, &temp)
+            .await
+            .map_err(Error::Filesystem)?;
+
+        let mut result = Ok(());
+        if tokio::fs::try_exists(notebook).await.unwrap_or(false) {
+            result = self.create(notebook).await.map(|_| ());
+        }
+        if result.is_ok() {
+            result = (tokio::fs::rename(&temp, notebook).await).map_err(Error::Filesystem);
+        }
+        if result.is_err() {
+            _ = tokio::fs::remove_file(&temp).await;
+        }
+        result
+    }
+
+    /// Find the file of a checkpoint by ID.
+    async fn find(&self, notebook: &Path, id: &str) -> Option<PathBuf> {
+        // Checking the ID also keeps it from naming a file elsewhere.
+        parse_id(id)?;
+        let (prefix, suffix) = Self::file_affixes(notebook);
+        for dir in self.dirs(notebook) {
+            let file = dir.join(format!("{prefix}{id}{suffix}"));
+            if tokio::fs::try_exists(&file).await.unwrap_or(false) {
+                return Some(file);
+            }
+        }
+        None
+    }
+}
+
+/// Parse a checkpoint ID, the time it was created in milliseconds.
+fn parse_id(id: &str) -> Option<Checkpoint> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis: i128 = id.parse().ok()?;
+    let created = OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).ok()?;
+    Some(Checkpoint {
+        id: id.into(),
+        created,
+    })
+}
+
+/// Copy a file into a directory under a new name, creating the directory.
+/// Fails with [`io::ErrorKind::AlreadyExists`] if the name is taken.
+async fn copy_into(file: &Path, dir: &Path, name: &str) -> io::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let dest = dir.join(name);
+    let mut output = (tokio::fs::OpenOptions::new().write(true).create_new(true))
+        .open(&dest)
+        .await?;
+    let copied = async {
+        let mut input = tokio::fs::File::open(file).await?;
+        tokio::io::copy(&mut input, &mut output).await?;
+        output.sync_all().await
+    };
+    if let Err(err) = copied.await {
+        _ = tokio::fs::remove_file(&dest).await;
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn create_and_restore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let store = CheckpointStore::new(dir.join("checkpoints"));
+        let notebook = dir.join("notebook.ipynb");
+        tokio::fs::write(&notebook, "first").await.unwrap();
+
+        assert!(store.list(&notebook).await.unwrap().is_empty());
+        let first = store.create(&notebook).await.unwrap();
+        tokio::fs::write(&notebook, "second").await.unwrap();
+        let second = store.create(&notebook).await.unwrap();
+        assert!(dir.join(".ipynb_checkpoints").is_dir());
+        assert_eq!(
+            store.list(&notebook).await.unwrap(),
+            [second, first.clone()]
+        );
+
+        // Restoring checkpoints the current file, so it can be undone.
+        tokio::fs::write(&notebook, "unsaved").await.unwrap();
+        store.restore(&notebook, &first.id).await.unwrap();
+        assert_eq!(tokio::fs::read_to_string(&notebook).await.unwrap(), "first");
+        let undo = store.list(&notebook).await.unwrap()[0].clone();
+        store.restore(&notebook, &undo.id).await.unwrap();
+        assert_eq!(
+            tokio::fs::read_to_string(&notebook).await.unwrap(),
+            "unsaved"
+        );
+        assert!(store.restore(&notebook, "../notebook").await.is_err());
+
+        // Only the newest checkpoints are kept.
+        for _ in 0..MAX_CHECKPOINTS {
+            store.create(&notebook).await.unwrap();
+        }
+        let checkpoints = store.list(&notebook).await.unwrap();
+        assert_eq!(checkpoints.len(), MAX_CHECKPOINTS);
+        assert!(!checkpoints.contains(&first));
+    }
+}
@@ -0,0 +1,619 @@
+//! Real-time collaborative editing of notebooks, built on Yjs CRDTs.
+//!
+//! While collaborating, the notebook is kept in a shared [`NotebookDoc`] that
+//! every peer has a replica of. Edits on any peer are applied to its replica
+//! and sent to the others as CRDT updates, which merge without conflicts in
+//! any order. The document follows the schema of `jupyter_ydoc`, so that Jute
+//! can join the same document as JupyterLab through `jupyter-collaboration`: a
+//! `cells` array of maps with the source as text, and a `meta` map with the
+//! notebook's metadata.
+//!
+//! The frontend sends typing as text changes to a cell's source, so that
+//! concurrent typing in one cell interleaves instead of overwriting. Other
+//! changes, like adding cells or new outputs, are sent as whole notebooks and
+//! reconciled cell by cell against the last notebook sent, so only the fields
+//! changed on this peer are written. Outputs belong to whichever peer ran the
+//! cell, and concurrent runs resolve to one peer's outputs on every replica.
+//!
+//! Peers connect over WebSockets, with one peer hosting and the others
+//! joining it, in [`session`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value};
+use ts_rs::TS;
+use yrs::{
+    types::ToJson,
+    updates::{decoder::Decode, encoder::Encode},
+    Any, Array, ArrayPrelim, ArrayRef, Doc, Map, MapPrelim, MapRef, OffsetKind, Options, Out,
+    ReadTxn, StateVector, Text, TextPrelim, Transact, TransactionMut, Update,
+};
+
+use crate::{
+    backend::notebook::{Cell, NotebookRoot, Output},
+    Error,
+};
+
+pub mod protocol;
+pub mod session;
+
+/// A change to the source of a cell, replacing a range with new text.
+///
+/// Positions are in UTF-16 code units, as in JavaScript strings and editors.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct TextChange {
+    /// Start of the replaced range.
+    pub from: u32,
+
+    /// End of the replaced range.
+    pub to: u32,
+
+    /// Text inserted in place of the range.
+    pub insert: String,
+}
+
+crate::export_bindings!(TextChange);
+
+/// Another client in a collaboration session.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct Peer {
+    /// Client ID of the peer in the shared document.
+    #[ts(type = "number")]
+    pub client_id: u64,
+
+    /// Display name of the peer's user.
+    pub name: String,
+
+    /// Cell that the peer has focused, if any.
+    pub cell_id: Option<String>,
+}
+
+crate::export_bindings!(Peer);
+
+/// Events sent to the frontend while collaborating.
+#[derive(Serialize, Clone, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case", tag = "event", content = "data")]
+pub enum CollabEvent {
+    /// Cells were added, removed, or reordered by a peer, so the whole
+    /// notebook should be reloaded.
+    Notebook(NotebookRoot),
+
+    /// A peer edited the source of a cell.
+    Source {
+        /// ID of the edited cell.
+        cell_id: String,
+
+        /// The edit to apply to the cell's source.
+        change: TextChange,
+    },
+
+    /// A peer changed the outputs or metadata of a cell, which is sent in full.
+    Cell(Cell),
+
+    /// The other clients in the session changed.
+    Peers(Vec<Peer>),
+
+    /// The session ended, with the reason.
+    Closed(String),
+}
+
+crate::export_bindings!(CollabEvent);
+
+fn collab_error(err: impl ToString) -> Error {
+    Error::Collab(err.to_string())
+}
+
+/// A notebook as a shared CRDT document.
+pub struct NotebookDoc {
+    doc: Doc,
+    cells: ArrayRef,
+    meta: MapRef,
+}
+
+impl NotebookDoc {
+    /// Create an empty document, to be filled by syncing with a peer.
+    pub fn new() -> Self {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let cells = doc.get_or_insert_array("cells");
+        let meta = doc.get_or_insert_map("meta");
+        Self { doc, cells, meta }
+    }
+
+    /// Create a document with the contents of a notebook.
+    pub fn from_notebook(notebook: &NotebookRoot) -> Result<Self, Error> {
+        let this = Self::new();
+        {
+            let mut txn = this.doc.transact_mut();
+            this.meta.insert(
+                &mut txn,
+                "nbformat",
+                Any::from(f64::from(notebook.nbformat)),
+            );
+            this.meta.insert(
+                &mut txn,
+                "nbformat_minor",
+                Any::from(f64::from(notebook.nbformat_minor)),
+            );
+            let metadata = serde_json::to_value(&notebook.metadata)?;
+            insert_map(&mut txn, &this.meta, "metadata", metadata)?;
+            for (index, cell) in notebook.cells.iter().enumerate() {
+                this.insert_cell(&mut txn, index as u32, cell)?;
+            }
+        }
+        Ok(this)
+    }
+
+    /// Client ID of this replica, which identifies its edits.
+    pub fn client_id(&self) -> u64 {
+        self.doc.client_id()
+    }
+
+    /// Read the document as a notebook.
+    pub fn to_notebook(&self) -> Result<NotebookRoot, Error> {
+        let txn = self.doc.transact();
+        let meta = any_to_json(&self.meta.to_json(&txn))?;
+        let mut notebook = json!({
+            "nbformat": meta.get("nbformat").cloned().unwrap_or(json!(4)),
+            "nbformat_minor": meta.get("nbformat_minor").cloned().unwrap_or(json!(5)),
+            "metadata": meta.get("metadata").cloned().unwrap_or(json!({})),
+            "cells": any_to_json(&self.cells.to_json(&txn))?,
+        });
+        normalize_numbers(&mut notebook);
+        Ok(serde_json::from_value(notebook)?)
+    }
+
+    /// Encode the state vector of this replica, for sync step 1.
+    pub fn state_vector(&self) -> Vec<u8> {
+        self.doc.transact().state_vector().encode_v1()
+    }
+
+    /// Encode the updates missing from a peer's state vector, for sync step 2.
+    pub fn diff(&self, state_vector: &[u8]) -> Result<Vec<u8>, Error> {
+        let state_vector = StateVector::decode_v1(state_vector).map_err(collab_error)?;
+        Ok(self.doc.transact().encode_diff_v1(&state_vector))
+    }
+
+    /// Encode the whole document as one update.
+    pub fn full_update(&self) -> Vec<u8> {
+        self.doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default())
+    }
+
+    /// Apply an update from a peer, returning the changes to show.
+    pub fn apply_update(&self, update: &[u8]) -> Result<Vec<CollabEvent>, Error> {
+        let before = self.to_notebook()?;
+        let update = Update::decode_v1(update).map_err(collab_error)?;
+        self.doc
+            .transact_mut()
+            .apply_update(update)
+            .map_err(collab_error)?;
+        let after = self.to_notebook()?;
+        Ok(remote_changes(&before, &after))
+    }
+
+    /// Apply changes to the source of a cell, returning the update to send.
+    pub fn edit_source(&self, cell_id: &str, changes: &[TextChange]) -> Result<Vec<u8>, Error> {
+        let mut txn = self.doc.transact_mut();
+        let (_, cell) = self
+            .find_cell(&txn, cell_id)
+            .ok_or_else(|| collab_error(format!("no cell with ID {cell_id:?}")))?;
+        let Some(Out::YText(source)) = cell.get(&txn, "source") else {
+            return Err(collab_error("cell source is not text"));
+        };
+        let len = source.len(&txn);
+        for change in changes {
+            let (from, to) = (change.from.min(len), change.to.min(len));
+            if to > from {
+                source.remove_range(&mut txn, from, to - from);
+            }
+            if !change.insert.is_empty() {
+                source.insert(&mut txn, from, &change.insert);
+            }
+        }
+        Ok(txn.encode_update_v1())
+    }
+
+    /// Write the changes between the last notebook from the frontend and the
+    /// current one, returning the update to send if anything changed.
+    ///
+    /// Sources of existing cells are left alone, since they are edited with
+    /// [`NotebookDoc::edit_source`]. Cells and fields that didn't change on
+    /// this peer are also left alone, so edits from peers aren't reverted.
+    pub fn reconcile(
+        &self,
+        base: &NotebookRoot,
+        local: &NotebookRoot,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let base_cell = |id: &str| base.cells.iter().find(|cell| cell.id() == Some(id));
+        let mut txn = self.doc.transact_mut();
+        let mut changed = false;
+
+        // Remove cells deleted on this peer.
+        for cell in &base.cells {
+            let Some(id) = cell.id() else { continue };
+            if local.cells.iter().all(|c| c.id() != Some(id)) {
+                if let Some((index, _)) = self.find_cell(&txn, id) {
+                    self.cells.remove(&mut txn, index);
+                    changed = true;
+                }
+            }
+        }
+
+        // Insert, move, and update cells to follow the local order.
+        let mut prev: Option<u32> = None;
+        for cell in &local.cells {
+            let Some(id) = cell.id() else { continue };
+            let after_prev = prev.map_or(0, |p| p + 1);
+            let index = match self.find_cell(&txn, id) {
+                // Deleted by a peer since the last notebook was sent.
+                None if base_cell(id).is_some() => continue,
+                None => {
+                    self.insert_cell(&mut txn, after_prev, cell)?;
+                    changed = true;
+                    after_prev
+                }
+                Some((index, _)) if prev.is_some_and(|p| index < p) => {
+                    self.cells.move_to(&mut txn, index, after_prev);
+                    changed = true;
+                    self.find_cell(&txn, id).map_or(after_prev, |(i, _)| i)
+                }
+                Some((index, map)) => {
+                    let base = base_cell(id);
+                    let doc_cell = read_cell(&txn, &map)?;
+                    let cell = &with_peer_outputs(cell, &doc_cell);
+                    if doc_cell.cell_type() != cell.cell_type()
+                        && base.is_some_and(|b| b.cell_type() != cell.cell_type())
+                    {
+                        // A new type has different fields, so the cell is
+                        // replaced, keeping the source from the document.
+                        let mut cell = serde_json::to_value(cell)?;
+                        cell["source"] = Value::String(doc_cell.source());
+                        self.cells.remove(&mut txn, index);
+                        self.insert_cell(&mut txn, index, &serde_json::from_value(cell)?)?;
+                        changed = true;
+                    } else if base.map_or(true, |b| fields(b) != fields(cell))
+                        && fields(&doc_cell) != fields(cell)
+                    {
+                        write_fields(&mut txn, &map, cell)?;
+                        changed = true;
+                    }
+                    index
+                }
+            };
+            prev = Some(index);
+        }
+
+        let metadata = serde_json::to_value(&local.metadata)?;
+        if metadata != serde_json::to_value(&base.metadata)? {
+            insert_map(&mut txn, &self.meta, "metadata", metadata)?;
+            changed = true;
+        }
+        Ok(changed.then(|| txn.encode_update_v1()))
+    }
+
+    /// Find a cell by ID, with its index.
+    fn find_cell<T: ReadTxn>(&self, txn: &T, cell_id: &str) -> Option<(u32, MapRef)> {
+        self.cells
+            .iter(txn)
+            .enumerate()
+            .find_map(|(index, value)| match value {
+                Out::YMap(map) => match map.get(txn, "id") {
+                    Some(Out::Any(Any::String(id))) if &*id == cell_id => Some((index as u32, map)),
+                    _ => None,
+                },
+                _ => None,
+            })
+    }
+
+    /// Insert a cell at an index, with its source as shared text.
+    fn insert_cell(&self, txn: &mut TransactionMut, index: u32, cell: &Cell) -> Result<(), Error> {
+        let map = self.cells.insert(txn, index, MapPrelim::default());
+        map.insert(txn, "source", TextPrelim::new(cell.source()));
+        write_fields(txn, &map, cell)
+    }
+}
+
+impl Default for NotebookDoc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fields of a cell other than its source, as JSON.
+fn fields(cell: &Cell) -> JsonMap<String, Value> {
+    let mut fields = match serde_json::to_value(cell) {
+        Ok(Value::Object(fields)) => fields,
+        _ => JsonMap::new(),
+    };
+    fields.remove("source");
+    fields
+}
+
+/// Write the fields of a cell other than its source to a map in the document,
+/// as maps and arrays like `jupyter_ydoc`.
+fn write_fields(txn: &mut TransactionMut, map: &MapRef, cell: &Cell) -> Result<(), Error> {
+    let fields = fields(cell);
+    let stale: Vec<String> = map
+        .keys(txn)
+        .filter(|key| *key != "source" && !fields.contains_key(*key))
+        .map(String::from)
+        .collect();
+    for key in stale {
+        map.remove(txn, &key);
+    }
+    for (key, value) in fields {
+        match value {
+            value @ Value::Object(_) if key == "metadata" => insert_map(txn, map, &key, value)?,
+            Value::Array(items) if key == "outputs" => {
+                let outputs = map.insert(txn, key, ArrayPrelim::default());
+                for item in items {
+                    outputs.push_back(txn, json_to_any(item)?);
+                }
+            }
+            value => _ = map.insert(txn, key, json_to_any(value)?),
+        }
+    }
+    Ok(())
+}
+
+/// Insert a JSON object into a map as a nested map, like metadata in
+/// `jupyter_ydoc`, so that peers can observe changes to its keys.
+fn insert_map(
+    txn: &mut TransactionMut,
+    parent: &MapRef,
+    key: &str,
+    value: Value,
+) -> Result<(), Error> {
+    let map = parent.insert(txn, key, MapPrelim::default());
+    if let Value::Object(entries) = value {
+        for (key, value) in entries {
+            map.insert(txn, key, json_to_any(value)?);
+        }
+    }
+    Ok(())
+}
+
+/// Read a cell from a map in the document.
+fn read_cell<T: ReadTxn>(txn: &T, map: &MapRef) -> Result<Cell, Error> {
+    let mut cell = any_to_json(&map.to_json(txn))?;
+    normalize_numbers(&mut cell);
+    Ok(serde_json::from_value(cell)?)
+}
+
+fn json_to_any(value: Value) -> Result<Any, Error> {
+    Ok(serde_json::from_value(value)?)
+}
+
+fn any_to_json(any: &Any) -> Result<Value, Error> {
+    Ok(serde_json::to_value(any)?)
+}
+
+/// Convert whole floating-point numbers to integers.
+///
+/// Yjs stores all numbers from JavaScript as floats, but nbformat has integer
+/// fields like `execution_count`.
+fn normalize_numbers(value: &mut Value) {
+    match value {
+        Value::Number(n) => {
+            if let Some(f) = n.as_f64().filter(|f| f.fract() == 0.0 && f.abs() < 1e15) {
+                if !n.is_i64() && !n.is_u64() {
+                    *value = Value::from(f as i64);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(normalize_numbers),
+        Value::Object(entries) => entries.values_mut().for_each(normalize_numbers),
+        _ => {}
+    }
+}
+
+/// Changes from applying a peer's update, to show in the frontend.
+fn remote_changes(before: &NotebookRoot, after: &NotebookRoot) -> Vec<CollabEvent> {
+    let shape = |notebook: &NotebookRoot| -> Vec<(Option<String>, &'static str)> {
+        notebook
+            .cells
+            .iter()
+            .map(|cell| (cell.id().map(String::from), cell.cell_type()))
+            .collect()
+    };
+    if shape(before) != shape(after) || before.metadata != after.metadata {
+        let mut notebook = after.clone();
+        notebook.cells.iter_mut().for_each(sandbox_outputs);
+        return vec![CollabEvent::Notebook(notebook)];
+    }
+    let mut events = Vec::new();
+    for (old, new) in before.cells.iter().zip(&after.cells) {
+        let Some(cell_id) = new.id() else { continue };
+        if let Some(change) = text_change(&old.source(), &new.source()) {
+            events.push(CollabEvent::Source {
+                cell_id: cell_id.into(),
+                change,
+            });
+        }
+        if fields(old) != fields(new) {
+            let mut cell = new.clone();
+            sandbox_outputs(&mut cell);
+            events.push(CollabEvent::Cell(cell));
+        }
+    }
+    events
+}
+
+/// MIME types of outputs that run scripts or markup when shown.
+const ACTIVE_MIME_TYPES: &[&str] = &[
+    "text/html",
+    "image/svg+xml",
+    "application/javascript",
+    "text/javascript",
+];
+
+/// Remove active content from the outputs of a cell from a peer, since the
+/// frontend shows outputs without a sandbox and peers are other machines.
+pub fn sandbox_outputs(cell: &mut Cell) {
+    let Cell::Code(cell) = cell else { return };
+    for output in &mut cell.outputs {
+        let data = match output {
+            Output::ExecuteResult(result) => &mut result.data,
+            Output::DisplayData(display) => &mut display.data,
+            Output::Stream(_) | Output::Error(_) => continue,
+        };
+        data.retain(|mime, _| !ACTIVE_MIME_TYPES.contains(&mime.as_str()));
+    }
+}
+
+/// A local cell with the full outputs from the document, if its outputs are
+/// the sandboxed copy the frontend was sent, so they aren't written back.
+fn with_peer_outputs(local: &Cell, doc_cell: &Cell) -> Cell {
+    let mut cell = local.clone();
+    if let (Cell::Code(cell), Cell::Code(doc)) = (&mut cell, doc_cell) {
+        let mut sandboxed = doc_cell.clone();
+        sandbox_outputs(&mut sandboxed);
+        if matches!(&sandboxed, Cell::Code(s) if s.outputs == cell.outputs) {
+            cell.outputs = doc.outputs.clone();
+        }
+    }
+    cell
+}
+
+/// The smallest single change that turns one text into another, if they
+/// differ, found by trimming their common prefix and suffix.
+pub fn text_change(old: &str, new: &str) -> Option<TextChange> {
+    if old == new {
+        return None;
+    }
+    let prefix: usize = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let utf16_len = |s: &str| s.encode_utf16().count() as u32;
+    let from = utf16_len(&old[..prefix]);
+    Some(TextChange {
+        from,
+        to: from + utf16_len(&old[prefix..old.len() - suffix]),
+        insert: new[prefix..new.len() - suffix].into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::NotebookFormat;
+
+    fn notebook() -> NotebookRoot {
+        let mut notebook = NotebookFormat::Percent
+            .read("# %%\nx = 1\n\n# %% [markdown]\n# Notes\n")
+            .unwrap();
+        for (i, cell) in notebook.cells.iter_mut().enumerate() {
+            let id = Some(format!("c{i}"));
+            match cell {
+                Cell::Code(cell) => cell.id = id,
+                Cell::Markdown(cell) => cell.id = id,
+                Cell::Raw(cell) => cell.id = id,
+            }
+        }
+        notebook
+    }
+
+    #[test]
+    fn minimal_text_changes() {
+        assert_eq!(text_change("abc", "abc"), None);
+        let change = text_change("x = 1\n", "x = 12\n").unwrap();
+        assert_eq!((change.from, change.to, &*change.insert), (5, 5, "2"));
+        // Positions count UTF-16 code units, so emoji take two.
+        let change = text_change("🦀 ab", "🦀 b").unwrap();
+        assert_eq!((change.from, change.to, &*change.insert), (3, 4, ""));
+        let change = text_change("aaa", "aa").unwrap();
+        assert_eq!(change.to - change.from, 1);
+    }
+
+    #[test]
+    fn sync_between_replicas() {
+        let local = notebook();
+        let host = NotebookDoc::from_notebook(&local).unwrap();
+        let guest = NotebookDoc::new();
+        let update = host.diff(&guest.state_vector()).unwrap();
+        assert!(matches!(
+            &guest.apply_update(&update).unwrap()[..],
+            [CollabEvent::Notebook(_)]
+        ));
+        assert_eq!(guest.to_notebook().unwrap(), local);
+
+        // Concurrent typing in the same cell merges.
+        let a = host
+            .edit_source("c0", &[text_change("x = 1", "x = 10").unwrap()])
+            .unwrap();
+        let b = guest
+            .edit_source("c0", &[text_change("x = 1", "y = 1").unwrap()])
+            .unwrap();
+        host.apply_update(&b).unwrap();
+        let events = guest.apply_update(&a).unwrap();
+        assert!(matches!(&events[..], [CollabEvent::Source { .. }]));
+        let source = |doc: &NotebookDoc| doc.to_notebook().unwrap().cells[0].source();
+        assert_eq!(source(&host), "y = 10");
+        assert_eq!(source(&guest), "y = 10");
+
+        // Deleting a cell on one peer keeps edits from the other.
+        let mut edited = guest.to_notebook().unwrap();
+        edited.cells.remove(1);
+        let update = guest
+            .reconcile(&host.to_notebook().unwrap(), &edited)
+            .unwrap()
+            .unwrap();
+        host.apply_update(&update).unwrap();
+        assert_eq!(host.to_notebook().unwrap().cells.len(), 1);
+        assert_eq!(guest.reconcile(&edited, &edited).unwrap(), None);
+    }
+
+    #[test]
+    fn sandbox_peer_outputs() {
+        let mut local = notebook();
+        let Cell::Code(code) = &mut local.cells[0] else {
+            unreachable!()
+        };
+        code.outputs = vec![serde_json::from_value(json!({
+            "output_type": "display_data",
+            "data": {"text/html": "<script></script>", "text/plain": "x"},
+            "metadata": {},
+        }))
+        .unwrap()];
+        let host = NotebookDoc::from_notebook(&local).unwrap();
+
+        let mut cell = local.cells[0].clone();
+        sandbox_outputs(&mut cell);
+        let Cell::Code(code) = &cell else {
+            unreachable!()
+        };
+        let Output::DisplayData(display) = &code.outputs[0] else {
+            unreachable!()
+        };
+        assert_eq!(display.data.keys().collect::<Vec<_>>(), ["text/plain"]);
+
+        // Sending back the sandboxed copy keeps the full outputs.
+        let mut sandboxed = local.clone();
+        sandboxed.cells[0] = cell;
+        assert_eq!(host.reconcile(&sandboxed, &sandboxed).unwrap(), None);
+        let edited = |notebook: &mut NotebookRoot| {
+            let Cell::Code(code) = &mut notebook.cells[0] else {
+                unreachable!()
+            };
+            code.execution_count = Some(1);
+        };
+        let base = sandboxed.clone();
+        edited(&mut sandboxed);
+        host.reconcile(&base, &sandboxed).unwrap().unwrap();
+        edited(&mut local);
+        assert_eq!(host.to_notebook().unwrap(), local);
+    }
+}
@@ -0,0 +1,356 @@
+//! Encoding of the `y-websocket` sync and awareness protocol.
+//!
+//! This is the protocol spoken by Yjs providers, so Jute can sync with other
+//! Yjs-based editors as well as itself. Each WebSocket message is one of:
+//!
+//! - A sync message, exchanging state vectors and document updates. Peers first
+//!   send `Step1` with their state vector, and reply with `Step2` holding the
+//!   updates the other peer is missing. Later edits are sent as `Update`
+//!   messages.
+//! - An awareness message, with the presence of clients, like their names and
+//!   cursors. These are not part of the document, and are kept per client with
+//!   a clock so that the latest state wins.
+//!
+//! Integers are encoded as variable-length unsigned integers, and byte arrays
+//! and strings are prefixed with their length, as in the `lib0` encoding.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::Error;
+
+const MESSAGE_SYNC: u64 = 0;
+const MESSAGE_AWARENESS: u64 = 1;
+const MESSAGE_AUTH: u64 = 2;
+const MESSAGE_QUERY_AWARENESS: u64 = 3;
+
+const SYNC_STEP1: u64 = 0;
+const SYNC_STEP2: u64 = 1;
+const SYNC_UPDATE: u64 = 2;
+
+/// A message sent between peers over a WebSocket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// A message syncing the shared document.
+    Sync(SyncMessage),
+
+    /// An encoded [`AwarenessUpdate`] with the presence of some clients.
+    Awareness(Vec<u8>),
+
+    /// The server refused access to the document, with a reason.
+    AuthDenied(String),
+
+    /// A request for the awareness states of all clients.
+    QueryAwareness,
+
+    /// A message of another type, with its payload. Servers like
+    /// `jupyter-collaboration` add their own types, which are passed through.
+    Custom(u64, Vec<u8>),
+}
+
+/// A message syncing the shared document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncMessage {
+    /// The sender's state vector, asking for updates it is missing.
+    Step1(Vec<u8>),
+
+    /// Updates that the receiver was missing, in reply to `Step1`.
+    Step2(Vec<u8>),
+
+    /// An update from a new edit.
+    Update(Vec<u8>),
+}
+
+impl Message {
+    /// Encode the message into bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Message::Sync(sync) => {
+                write_var(&mut buf, MESSAGE_SYNC);
+                let (kind, payload) = match sync {
+                    SyncMessage::Step1(sv) => (SYNC_STEP1, sv),
+                    SyncMessage::Step2(update) => (SYNC_STEP2, update),
+                    SyncMessage::Update(update) => (SYNC_UPDATE, update),
+                };
+                write_var(&mut buf, kind);
+                write_bytes(&mut buf, payload);
+            }
+            Message::Awareness(update) => {
+                write_var(&mut buf, MESSAGE_AWARENESS);
+                write_bytes(&mut buf, update);
+            }
+            Message::AuthDenied(reason) => {
+                write_var(&mut buf, MESSAGE_AUTH);
+                write_var(&mut buf, 0);
+                write_bytes(&mut buf, reason.as_bytes());
+            }
+            Message::QueryAwareness => write_var(&mut buf, MESSAGE_QUERY_AWARENESS),
+            Message::Custom(kind, payload) => {
+                write_var(&mut buf, *kind);
+                buf.extend_from_slice(payload);
+            }
+        }
+        buf
+    }
+
+    /// Decode a message from bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+        let message = match reader.var()? {
+            MESSAGE_SYNC => {
+                let kind = reader.var()?;
+                let payload = reader.bytes()?.to_vec();
+                Message::Sync(match kind {
+                    SYNC_STEP1 => SyncMessage::Step1(payload),
+                    SYNC_STEP2 => SyncMessage::Step2(payload),
+                    SYNC_UPDATE => SyncMessage::Update(payload),
+                    _ => return Err(invalid("unknown sync message")),
+                })
+            }
+            MESSAGE_AWARENESS => Message::Awareness(reader.bytes()?.to_vec()),
+            MESSAGE_AUTH => {
+                reader.var()?;
+                Message::AuthDenied(reader.string()?)
+            }
+            MESSAGE_QUERY_AWARENESS => Message::QueryAwareness,
+            kind => Message::Custom(kind, reader.0.to_vec()),
+        };
+        Ok(message)
+    }
+}
+
+/// Presence states of some clients, sent in an awareness message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AwarenessUpdate {
+    /// State of each client, with its clock. A `None` state means that the
+    /// client has left.
+    pub clients: Vec<(u64, u32, Option<Value>)>,
+}
+
+impl AwarenessUpdate {
+    /// Encode the update into bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_var(&mut buf, self.clients.len() as u64);
+        for (client_id, clock, state) in &self.clients {
+            write_var(&mut buf, *client_id);
+            write_var(&mut buf, u64::from(*clock));
+            let json = state.as_ref().unwrap_or(&Value::Null).to_string();
+            write_bytes(&mut buf, json.as_bytes());
+        }
+        buf
+    }
+
+    /// Decode an update from bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+        let len = reader.var()?;
+        let mut clients = Vec::new();
+        for _ in 0..len {
+            let client_id = reader.var()?;
+            let clock = u32::try_from(reader.var()?).map_err(|_| invalid("clock overflow"))?;
+            let state: Value = serde_json::from_str(&reader.string()?)?;
+            clients.push((client_id, clock, Some(state).filter(|s| !s.is_null())));
+        }
+        Ok(Self { clients })
+    }
+}
+
+/// Presence states of all clients in a session, including this one.
+#[derive(Clone, Debug)]
+pub struct Awareness {
+    client_id: u64,
+    clock: u32,
+    state: Option<Value>,
+    peers: BTreeMap<u64, (u32, Value)>,
+}
+
+impl Awareness {
+    /// Create the awareness of a client, with its initial state.
+    pub fn new(client_id: u64, state: Value) -> Self {
+        Self {
+            client_id,
+            clock: 0,
+            state: Some(state),
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// ID of this client.
+    pub fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    /// Replace the state of this client, returning an update to send.
+    pub fn set_local(&mut self, state: Value) -> AwarenessUpdate {
+        // Stay at the last clock rather than wrap, so peers keep the state.
+        self.clock = self.clock.checked_add(1).unwrap_or(self.clock);
+        self.state = Some(state);
+        self.local_update()
+    }
+
+    /// An update with the current state of this client.
+    pub fn local_update(&self) -> AwarenessUpdate {
+        AwarenessUpdate {
+            clients: vec![(self.client_id, self.clock, self.state.clone())],
+        }
+    }
+
+    /// An update with the states of all clients, for a peer that just joined.
+    pub fn full_update(&self) -> AwarenessUpdate {
+        let mut update = self.local_update();
+        for (&client_id, (clock, state)) in &self.peers {
+            update
+                .clients
+                .push((client_id, *clock, Some(state.clone())));
+        }
+        update
+    }
+
+    /// Apply an update from a peer, returning the IDs of clients whose state
+    /// changed. Updates older than the known state of a client are ignored.
+    pub fn apply(&mut self, update: &AwarenessUpdate) -> Vec<u64> {
+        let mut changed = Vec::new();
+        for (client_id, clock, state) in &update.clients {
+            if *client_id == self.client_id {
+                continue;
+            }
+            if let Some((known, _)) = self.peers.get(client_id) {
+                // A removal with the same clock still applies, as in Yjs.
+                if *clock < *known || (*clock == *known && state.is_some()) {
+                    continue;
+                }
+            }
+            match state {
+                Some(state) => _ = self.peers.insert(*client_id, (*clock, state.clone())),
+                None => _ = self.peers.remove(client_id),
+            }
+            changed.push(*client_id);
+        }
+        changed
+    }
+
+    /// Remove clients that disconnected, returning an update telling other
+    /// peers that they left.
+    pub fn remove(&mut self, client_ids: &[u64]) -> AwarenessUpdate {
+        let clients = client_ids
+            .iter()
+            .filter_map(|id| {
+                let (clock, _) = self.peers.remove(id)?;
+                // A removal with the same clock still applies, so a peer's
+                // clock of `u32::MAX` is removed without overflowing.
+                Some((*id, clock.checked_add(1).unwrap_or(clock), None))
+            })
+            .collect();
+        AwarenessUpdate { clients }
+    }
+
+    /// States of the other clients, by client ID.
+    pub fn peers(&self) -> impl Iterator<Item = (u64, &Value)> {
+        self.peers.iter().map(|(id, (_, state))| (*id, state))
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Collab(format!("invalid message: {reason}"))
+}
+
+fn write_var(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_var(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+/// Reads values from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn var(&mut self) -> Result<u64, Error> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.0.split_first().ok_or_else(|| invalid("too short"))?;
+            self.0 = rest;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(n);
+            }
+        }
+        Err(invalid("integer overflow"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = usize::try_from(self.var()?).map_err(|_| invalid("too long"))?;
+        if len > self.0.len() {
+            return Err(invalid("too short"));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| invalid("invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn message_round_trip() {
+        let messages = [
+            Message::Sync(SyncMessage::Step1(vec![1, 2, 3])),
+            Message::Sync(SyncMessage::Step2(vec![0; 300])),
+            Message::Sync(SyncMessage::Update(vec![])),
+            Message::Awareness(vec![4, 5]),
+            Message::AuthDenied("no access".into()),
+            Message::QueryAwareness,
+            Message::Custom(127, vec![6, 7]),
+        ];
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+        }
+        // Matches the encoding of Yjs: a sync step 2 with a 2-byte length.
+        let encoded = Message::Sync(SyncMessage::Step2(vec![0; 300])).encode();
+        assert_eq!(encoded[..4], [0, 1, 0xac, 0x02]);
+        assert!(Message::decode(&[0, 1, 5, 1]).is_err());
+    }
+
+    #[test]
+    fn awareness_states() {
+        let mut host = Awareness::new(1, json!({"user": {"name": "Ada"}}));
+        let mut guest = Awareness::new(2, json!({"user": {"name": "Grace"}}));
+
+        let update = AwarenessUpdate::decode(&guest.full_update().encode()).unwrap();
+        assert_eq!(host.apply(&update), [2]);
+        assert_eq!(host.apply(&update), Vec::<u64>::new());
+
+        let update = guest.set_local(json!({"user": {"name": "Grace"}, "cell": "c1"}));
+        assert_eq!(host.apply(&update), [2]);
+        assert_eq!(host.peers().next().unwrap().1["cell"], "c1");
+
+        assert_eq!(guest.apply(&host.full_update()), [1]);
+        let left = host.remove(&[2]);
+        assert_eq!(left.clients, [(2, 2, None)]);
+        assert_eq!(host.peers().count(), 0);
+
+        // A peer at the largest clock can still be removed.
+        let update = AwarenessUpdate {
+            clients: vec![(3, u32::MAX, Some(json!({})))],
+        };
+        assert_eq!(host.apply(&update), [3]);
+        assert_eq!(host.remove(&[3]).clients, [(3, u32::MAX, None)]);
+    }
+}
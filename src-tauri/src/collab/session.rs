@@ -0,0 +1,505 @@
+//! Sessions syncing a [`NotebookDoc`] with peers over WebSockets.
+//!
+//! One peer hosts a session, listening on the local network like
+//! [`ShareServer`](crate::share::ShareServer), and the others join it with its
+//! URL, which carries a random token. The host relays every message it
+//! receives to its other peers, so all peers stay in sync through it.
+//!
+//! Each connection begins with both sides sending their state vector, and
+//! replying with the updates the other side is missing. After that, edits and
+//! presence are sent as they happen.
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        client::IntoClientRequest,
+        handshake::server::{ErrorResponse, Request, Response},
+        http::StatusCode,
+        Message as WsMessage,
+    },
+    WebSocketStream,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use super::{
+    collab_error,
+    protocol::{Awareness, AwarenessUpdate, Message, SyncMessage},
+    sandbox_outputs, CollabEvent, NotebookDoc, Peer, TextChange,
+};
+use crate::{backend::notebook::NotebookRoot, share, Error};
+
+/// Time to wait for the host to send its notebook when joining.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Number of messages buffered for each connection before it falls behind.
+const RELAY_CAPACITY: usize = 256;
+
+/// Connection ID of edits made on this peer, which are sent to every peer.
+const LOCAL: u64 = 0;
+
+/// A collaboration session, as its host or a guest, which ends when dropped.
+pub struct CollabSession {
+    shared: Arc<Shared>,
+    url: Option<String>,
+    shutdown: CancellationToken,
+}
+
+impl CollabSession {
+    /// Host a session for a notebook on the local network, sending events from
+    /// peers to `on_event`.
+    pub async fn host(
+        notebook: NotebookRoot,
+        on_event: impl Fn(CollabEvent) + Send + Sync + 'static,
+    ) -> Result<Self, Error> {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .map_err(collab_error)?;
+        let port = listener.local_addr().map_err(collab_error)?.port();
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(share::TOKEN_LEN)
+            .map(char::from)
+            .collect();
+        let host = share::lan_address().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let url = format!("ws://{host}:{port}/?token={token}");
+        info!("hosting collaboration session at ws://{host}:{port}/");
+
+        let doc = NotebookDoc::from_notebook(&notebook)?;
+        let shared = Shared::new(doc, Some(notebook), on_event);
+        let shutdown = CancellationToken::new();
+        tokio::spawn({
+            let shared = shared.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                loop {
+                    let stream = tokio::select! {
+                        result = listener.accept() => match result {
+                            Ok((stream, _)) => stream,
+                            Err(err) => {
+                                warn!("could not accept collaboration peer: {err}");
+                                continue;
+                            }
+                        },
+                        _ = shutdown.cancelled() => break,
+                    };
+                    let shared = shared.clone();
+                    let token = token.clone();
+                    let shutdown = shutdown.clone();
+                    tokio::spawn(async move {
+                        let check = |request: &Request, response: Response| {
+                            if has_token(request, &token) {
+                                Ok(response)
+                            } else {
+                                let mut response = ErrorResponse::new(None);
+                                *response.status_mut() = StatusCode::FORBIDDEN;
+                                Err(response)
+                            }
+                        };
+                        let mut ws = match tokio_tungstenite::accept_hdr_async(stream, check).await
+                        {
+                            Ok(ws) => ws,
+                            Err(err) => {
+                                warn!("could not accept collaboration peer: {err}");
+                                return;
+                            }
+                        };
+                        let relay = shared.relay.subscribe();
+                        let result = match shared.greet(&mut ws).await {
+                            Ok(()) => shared.serve(ws, relay, shutdown).await,
+                            Err(err) => Err(err),
+                        };
+                        if let Err(err) = result {
+                            warn!("collaboration peer disconnected: {err}");
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            shared,
+            url: Some(url),
+            shutdown,
+        })
+    }
+
+    /// Join a session, returning the session and the notebook received from
+    /// the host.
+    ///
+    /// The request is the URL of a session hosted by Jute, or a room on a
+    /// Jupyter server from [`JupyterClient::collab_room`].
+    ///
+    /// [`JupyterClient::collab_room`]: crate::backend::remote::JupyterClient::collab_room
+    pub async fn join(
+        request: impl IntoClientRequest + Unpin,
+        on_event: impl Fn(CollabEvent) + Send + Sync + 'static,
+    ) -> Result<(Self, NotebookRoot), Error> {
+        let (mut ws, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(collab_error)?;
+        let shared = Shared::new(NotebookDoc::new(), None, on_event);
+        let connection = shared.next_connection.fetch_add(1, Ordering::Relaxed);
+        let mut clients = Vec::new();
+        shared.greet(&mut ws).await?;
+
+        // Wait for the host's reply to our state vector, with its notebook.
+        let handshake = async {
+            loop {
+                let Some(message) = next_message(&mut ws).await? else {
+                    return Err(collab_error("the host closed the connection"));
+                };
+                let done = matches!(message, Message::Sync(SyncMessage::Step2(_)));
+                for reply in shared.handle(connection, message, &mut clients)? {
+                    send(&mut ws, &reply).await?;
+                }
+                if done {
+                    return shared.doc.lock().unwrap().to_notebook();
+                }
+            }
+        };
+        let mut notebook = tokio::time::timeout(JOIN_TIMEOUT, handshake)
+            .await
+            .map_err(|_| collab_error("timed out waiting for the host"))??;
+        notebook.cells.iter_mut().for_each(sandbox_outputs);
+        *shared.base.lock().unwrap() = Some(notebook.clone());
+
+        let shutdown = CancellationToken::new();
+        tokio::spawn({
+            let shared = shared.clone();
+            let shutdown = shutdown.clone();
+            let relay = shared.relay.subscribe();
+            async move {
+                let result = shared
+                    .run(&mut ws, connection, &mut clients, relay, &shutdown)
+                    .await;
+                shared.disconnect(connection, &clients);
+                if !shutdown.is_cancelled() {
+                    let reason = match result {
+                        Ok(()) => "The host ended the session.".into(),
+                        Err(err) => err.to_string(),
+                    };
+                    (shared.on_event)(CollabEvent::Closed(reason));
+                }
+            }
+        });
+
+        let session = Self {
+            shared,
+            url: None,
+            shutdown,
+        };
+        Ok((session, notebook))
+    }
+
+    /// URL for peers to join the session, if this peer is hosting it.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Sync the latest version of the notebook from the frontend, sending the
+    /// changes made since the last sync to peers.
+    pub fn sync(&self, notebook: NotebookRoot) -> Result<(), Error> {
+        let update = {
+            let mut base = self.shared.base.lock().unwrap();
+            let update = match base.as_ref() {
+                Some(base) => self.shared.doc.lock().unwrap().reconcile(base, &notebook)?,
+                None => None,
+            };
+            *base = Some(notebook);
+            update
+        };
+        if let Some(update) = update {
+            self.shared
+                .send(LOCAL, Message::Sync(SyncMessage::Update(update)));
+        }
+        Ok(())
+    }
+
+    /// Apply changes typed into the source of a cell, sending them to peers.
+    pub fn edit_source(&self, cell_id: &str, changes: &[TextChange]) -> Result<(), Error> {
+        let update = self
+            .shared
+            .doc
+            .lock()
+            .unwrap()
+            .edit_source(cell_id, changes)?;
+        self.shared
+            .send(LOCAL, Message::Sync(SyncMessage::Update(update)));
+        Ok(())
+    }
+
+    /// Show peers which cell this peer has focused.
+    pub fn set_cursor(&self, cell_id: Option<&str>) {
+        let update = self
+            .shared
+            .awareness
+            .lock()
+            .unwrap()
+            .set_local(local_state(cell_id));
+        self.shared.send(LOCAL, Message::Awareness(update.encode()));
+    }
+}
+
+impl Drop for CollabSession {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+/// State of a session shared with the tasks serving its connections.
+struct Shared {
+    doc: Mutex<NotebookDoc>,
+
+    /// The last notebook synced from the frontend, to find what it changed.
+    base: Mutex<Option<NotebookRoot>>,
+
+    awareness: Mutex<Awareness>,
+
+    /// Messages to send to peers, with the connection they came from, which
+    /// doesn't get them back.
+    relay: broadcast::Sender<(u64, Message)>,
+
+    next_connection: AtomicU64,
+    on_event: Box<dyn Fn(CollabEvent) + Send + Sync>,
+}
+
+impl Shared {
+    fn new(
+        doc: NotebookDoc,
+        base: Option<NotebookRoot>,
+        on_event: impl Fn(CollabEvent) + Send + Sync + 'static,
+    ) -> Arc<Self> {
+        let awareness = Awareness::new(doc.client_id(), local_state(None));
+        Arc::new(Self {
+            doc: Mutex::new(doc),
+            base: Mutex::new(base),
+            awareness: Mutex::new(awareness),
+            relay: broadcast::channel(RELAY_CAPACITY).0,
+            next_connection: AtomicU64::new(LOCAL + 1),
+            on_event: Box::new(on_event),
+        })
+    }
+
+    /// Send a message to all peers, except on the connection it came from.
+    fn send(&self, origin: u64, message: Message) {
+        // This only fails if no peers are connected.
+        _ = self.relay.send((origin, message));
+    }
+
+    /// Start a connection by sending our state vector and presence.
+    async fn greet<S>(&self, ws: &mut WebSocketStream<S>) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let state_vector = self.doc.lock().unwrap().state_vector();
+        send(ws, &Message::Sync(SyncMessage::Step1(state_vector))).await?;
+        let awareness = self.awareness.lock().unwrap().full_update();
+        send(ws, &Message::Awareness(awareness.encode())).await
+    }
+
+    /// Serve a new connection until either side closes it.
+    async fn serve<S>(
+        &self,
+        mut ws: WebSocketStream<S>,
+        relay: broadcast::Receiver<(u64, Message)>,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let connection = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        let mut clients = Vec::new();
+        let result = self
+            .run(&mut ws, connection, &mut clients, relay, &shutdown)
+            .await;
+        self.disconnect(connection, &clients);
+        result
+    }
+
+    /// Exchange messages on a connection until either side closes it.
+    async fn run<S>(
+        &self,
+        ws: &mut WebSocketStream<S>,
+        connection: u64,
+        clients: &mut Vec<u64>,
+        mut relay: broadcast::Receiver<(u64, Message)>,
+        shutdown: &CancellationToken,
+    ) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        loop {
+            tokio::select! {
+                message = next_message(ws) => {
+                    let Some(message) = message? else { return Ok(()) };
+                    for reply in self.handle(connection, message, clients)? {
+                        send(ws, &reply).await?;
+                    }
+                }
+                relayed = relay.recv() => match relayed {
+                    Ok((origin, message)) => {
+                        if origin != connection {
+                            send(ws, &message).await?;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {
+                        // Some updates were dropped, so send the whole document
+                        // instead. Applying updates twice has no effect.
+                        let update = self.doc.lock().unwrap().full_update();
+                        send(ws, &Message::Sync(SyncMessage::Update(update))).await?;
+                        let awareness = self.awareness.lock().unwrap().full_update();
+                        send(ws, &Message::Awareness(awareness.encode())).await?;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                _ = shutdown.cancelled() => {
+                    _ = ws.close(None).await;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Handle a message from a peer, returning the replies to send back.
+    ///
+    /// Clients whose presence was received on the connection are added to
+    /// `clients`, to remove them when it closes.
+    fn handle(
+        &self,
+        connection: u64,
+        message: Message,
+        clients: &mut Vec<u64>,
+    ) -> Result<Vec<Message>, Error> {
+        match message {
+            Message::Sync(SyncMessage::Step1(state_vector)) => {
+                let update = self.doc.lock().unwrap().diff(&state_vector)?;
+                return Ok(vec![Message::Sync(SyncMessage::Step2(update))]);
+            }
+            Message::Sync(SyncMessage::Step2(update) | SyncMessage::Update(update)) => {
+                let events = self.doc.lock().unwrap().apply_update(&update)?;
+                events.into_iter().for_each(&self.on_event);
+                self.send(connection, Message::Sync(SyncMessage::Update(update)));
+            }
+            Message::Awareness(bytes) => {
+                let update = AwarenessUpdate::decode(&bytes)?;
+                for (client_id, _, state) in &update.clients {
+                    if state.is_some() && !clients.contains(client_id) {
+                        clients.push(*client_id);
+                    }
+                }
+                let changed = self.awareness.lock().unwrap().apply(&update);
+                if !changed.is_empty() {
+                    self.emit_peers();
+                    self.send(connection, Message::Awareness(bytes));
+                }
+            }
+            Message::QueryAwareness => {
+                let update = self.awareness.lock().unwrap().full_update();
+                return Ok(vec![Message::Awareness(update.encode())]);
+            }
+            Message::AuthDenied(reason) => {
+                return Err(collab_error(format!("access denied: {reason}")));
+            }
+            Message::Custom(..) => {}
+        }
+        Ok(Vec::new())
+    }
+
+    /// Remove the clients of a closed connection, telling the other peers.
+    fn disconnect(&self, connection: u64, clients: &[u64]) {
+        let update = self.awareness.lock().unwrap().remove(clients);
+        if !update.clients.is_empty() {
+            self.emit_peers();
+            self.send(connection, Message::Awareness(update.encode()));
+        }
+    }
+
+    /// Send the current peers to the frontend.
+    fn emit_peers(&self) {
+        let peers = self
+            .awareness
+            .lock()
+            .unwrap()
+            .peers()
+            .map(|(client_id, state)| Peer {
+                client_id,
+                // JupyterLab sends a display name along with the username.
+                name: (state["user"]["display_name"].as_str())
+                    .or(state["user"]["name"].as_str())
+                    .unwrap_or("Anonymous")
+                    .into(),
+                cell_id: state["cell"].as_str().map(String::from),
+            })
+            .collect();
+        (self.on_event)(CollabEvent::Peers(peers));
+    }
+}
+
+/// Presence of this peer, with the user's name and focused cell.
+///
+/// The user has the fields that JupyterLab expects, so Jute users show up in
+/// its list of collaborators.
+fn local_state(cell_id: Option<&str>) -> Value {
+    let name = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Anonymous".into());
+    let initials: String = name.chars().take(1).flat_map(char::to_uppercase).collect();
+    json!({
+        "user": {
+            "username": name,
+            "name": name,
+            "display_name": name,
+            "initials": initials,
+            "color": "var(--jp-collaborator-color1)",
+        },
+        "cell": cell_id,
+    })
+}
+
+/// Whether a request to join a session has the session's token.
+fn has_token(request: &Request, token: &str) -> bool {
+    let query = request.uri().query().unwrap_or_default();
+    url::form_urlencoded::parse(query.as_bytes())
+        .any(|(key, value)| key == "token" && value == token)
+}
+
+async fn send<S>(ws: &mut WebSocketStream<S>, message: &Message) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    ws.send(WsMessage::Binary(message.encode()))
+        .await
+        .map_err(collab_error)
+}
+
+/// Receive the next message from a peer, or `None` once the connection closes.
+async fn next_message<S>(ws: &mut WebSocketStream<S>) -> Result<Option<Message>, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    while let Some(frame) = ws.next().await {
+        match frame.map_err(collab_error)? {
+            WsMessage::Binary(bytes) => return Message::decode(&bytes).map(Some),
+            WsMessage::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(None)
+}
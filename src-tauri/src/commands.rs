@@ -1,22 +1,82 @@
 //! Invoke handlers for commands callable from the frontend.
 
-use std::env;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
 
+use serde::Serialize;
 use sysinfo::System;
-use tauri::ipc::Channel;
-use tracing::info;
+use tauri::{
+    async_runtime,
+    ipc::{Channel, InvokeResponseBody},
+    AppHandle, Emitter, Manager, Runtime, WebviewWindow,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+use ts_rs::TS;
+use uuid::Uuid;
 
 use crate::{
     backend::{
-        commands::{self, RunCellEvent},
-        local::{environment, LocalKernel},
-        notebook::NotebookRoot,
+        attachments,
+        commands::{self, BatchCell, BatchEvent, CellBatch, RunCellEvent, RunCellOptions},
+        diff::{self, NotebookDiff},
+        local::{
+            environment::{self, KernelInterruptMode},
+            LocalKernel,
+        },
+        merge::{self, NotebookMerge},
+        notebook::{Cell, KernelSpec, NotebookRoot},
+        queue::{CellState, ExecutionQueue, QueueTicket},
+        truncate::{OutputChunk, OutputTruncator, TruncatedOutputs},
+        variables::{self, Variable, VariableQuery},
+        wire_protocol::{HistoryEntry, HistoryRequest, IsCompleteReply, KernelInfoReply},
     },
+    entity::{Entity, EntityId},
+    import::NotebookFormat,
+    ipc::Payload,
+    launch::{self, LaunchOptions, LaunchedKernel},
+    loading::NotebookOutline,
+    menu,
+    project::Project,
     state::State,
     Error,
 };
 
+pub mod attachments;
+pub mod checkpoints;
+pub mod collab;
+pub mod database;
+pub mod dataflow;
+pub mod deep_link;
+pub mod devtools;
+pub mod document;
+pub mod export;
+pub mod gist;
+pub mod git;
+pub mod indexing;
+pub mod jupyter_config;
+pub mod keymap;
+pub mod logs;
+pub mod monitor;
+pub mod onboarding;
+pub mod recovery;
+pub mod renderers;
+pub mod reveal;
+pub mod rust_kernel;
+pub mod scratchpad;
+pub mod servers;
+pub mod settings;
+pub mod share;
+pub mod tasks;
+pub mod terminal;
+pub mod updates;
 pub mod venv;
+pub mod window;
+pub mod workspace;
 
 /// Measure the current system CPU usage. (unused, for future reference)
 #[tauri::command]
@@ -28,61 +88,399 @@ pub async fn cpu_usage() -> f32 {
     system.global_cpu_info().cpu_usage()
 }
 
+/// Get the prefix of the default managed environment, if one was set up.
+fn default_venv_prefix<R: Runtime>(app: &AppHandle<R>) -> Result<Option<PathBuf>, Error> {
+    let default_environment = app
+        .state::<State>()
+        .settings
+        .read()
+        .unwrap()
+        .default_environment;
+    match default_environment {
+        Some(venv_id) => Ok(Some(venv::venv_path(app, venv_id)?)),
+        None => Ok(None),
+    }
+}
+
+/// List the kernel specs that can be used to start a new kernel.
+#[tauri::command]
+pub async fn list_kernel_specs(app: AppHandle) -> Result<Vec<KernelSpec>, Error> {
+    let venv_prefix = default_venv_prefix(&app)?;
+    let prefix = venv_prefix.as_ref().map(|p| p.to_string_lossy());
+
+    // Kernels earlier in the search path take precedence.
+    let mut seen = HashSet::new();
+    let specs = environment::list_kernels(prefix.as_deref())
+        .await
+        .into_iter()
+        .filter_map(|(path, spec)| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            if !seen.insert(name.clone()) {
+                return None;
+            }
+            let mut other = serde_json::Map::new();
+            other.insert("language".into(), spec.language.into());
+            Some(KernelSpec {
+                name,
+                display_name: spec.display_name,
+                other,
+            })
+        })
+        .collect();
+    Ok(specs)
+}
+
+/// Open a window to create a new notebook, where the user picks a kernel.
+#[tauri::command]
+pub async fn new_notebook(app: AppHandle) -> Result<(), Error> {
+    crate::window::open_new_notebook(&app)?;
+    Ok(())
+}
+
+/// How long a kernel started by [`warm_kernel`] waits to be claimed by the
+/// notebook's window, before it is shut down.
+const WARM_KERNEL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A kernel starting in the background for a notebook being opened.
+#[derive(Debug)]
+pub struct WarmKernel {
+    /// Identifies this warm-up, so that its timeout doesn't stop a later one
+    /// for the same path.
+    token: Uuid,
+
+    /// The task starting the kernel.
+    handle: async_runtime::JoinHandle<Result<EntityId, Error>>,
+}
+
 /// Start a new Jupyter kernel.
+///
+/// If the kernel is for a notebook inside a project, the project's
+/// `jute.toml` provides the default kernel spec, environment variables, and
+/// startup code. Otherwise, the default kernel from settings is used. Kernels
+/// run in the default managed environment, if one was set up.
+///
+/// Without a spec name, the spec stored in the notebook's metadata is used,
+/// like for kernels warmed up while the notebook was opening. If one was
+/// warmed up for it, that kernel is returned instead.
 #[tauri::command]
 pub async fn start_kernel(
-    spec_name: &str,
-    state: tauri::State<'_, State>,
-) -> Result<String, Error> {
-    // TODO: Save the client in a better place.
-    // let client = JupyterClient::new("", "")?;
-
-    // Temporary hack to just start a kernel locally with ZeroMQ.
-    let kernels = environment::list_kernels(None).await;
-    let mut kernel_spec = match kernels
-        .iter()
-        .find(|(path, _spec)| path.file_name().and_then(|s| s.to_str()) == Some(spec_name))
-    {
-        Some((_, kernel_spec)) => kernel_spec.clone(),
-        None => {
-            return Err(Error::KernelConnect(format!(
-                "no kernel named {spec_name:?} found"
-            )))
+    spec_name: Option<&str>,
+    notebook_path: Option<&str>,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<EntityId, Error> {
+    let warm = notebook_path.and_then(|path| state.warm_kernels.remove(Path::new(path)));
+    if let Some((_, warm)) = warm {
+        if spec_name.is_none() {
+            match warm.handle.await {
+                Ok(Ok(kernel_id)) => return Ok(kernel_id),
+                Ok(Err(err)) => warn!("could not warm up kernel: {err}"),
+                Err(err) => warn!("kernel warm-up task failed: {err}"),
+            }
+        } else {
+            // The user picked a different kernel, so this one isn't needed.
+            stop_warm_kernel(&app, warm.handle);
         }
+    }
+    let notebook_path = notebook_path.map(Path::new);
+    let stored_spec = match (spec_name, notebook_path) {
+        (None, Some(path)) => stored_kernel_spec(path).await.unwrap_or_else(|err| {
+            warn!("could not read kernel spec of {}: {err}", path.display());
+            None
+        }),
+        _ => None,
     };
+    launch_kernel(&app, spec_name, notebook_path, stored_spec.as_deref()).await
+}
 
-    if kernel_spec.argv[0] == "python" {
-        if let Ok(python_path) = env::var("PYTHON_PATH") {
-            kernel_spec.argv[0] = python_path;
-        } else {
-            // Temporary hack
-            kernel_spec.argv[0] = "/opt/homebrew/bin/python3.11".into();
-        }
+/// Name of the kernel spec stored in a notebook's metadata, if any.
+async fn stored_kernel_spec(path: &Path) -> Result<Option<String>, Error> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(Error::Filesystem)?;
+    let notebook = NotebookFormat::from_path(path).read(&contents)?;
+    Ok(notebook.metadata.kernelspec.map(|spec| spec.name))
+}
+
+/// Start a kernel for a notebook in the background while its window opens, so
+/// that it is usually ready by the time the notebook is shown.
+///
+/// The kernel uses the spec stored in the notebook's metadata, unless the
+/// project sets one. It is claimed by the next call to [`start_kernel`] for the
+/// same path, or shut down if that doesn't happen soon.
+pub fn warm_kernel<R: Runtime>(app: &AppHandle<R>, path: &Path) {
+    let state = app.state::<State>();
+    if state.warm_kernels.contains_key(path) {
+        return;
     }
 
-    let kernel = LocalKernel::start(&kernel_spec).await?;
+    let path = path.to_path_buf();
+    let handle = async_runtime::spawn({
+        let app = app.clone();
+        let path = path.clone();
+        async move {
+            let stored_spec = stored_kernel_spec(&path).await?;
+            launch_kernel(&app, None, Some(&path), stored_spec.as_deref()).await
+        }
+    });
+    let token = Uuid::new_v4();
+    state
+        .warm_kernels
+        .insert(path.clone(), WarmKernel { token, handle });
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        tokio::time::sleep(WARM_KERNEL_TIMEOUT).await;
+        let warm_kernels = &app.state::<State>().warm_kernels;
+        let unclaimed = warm_kernels.remove_if(&path, |_, warm| warm.token == token);
+        if let Some((_, warm)) = unclaimed {
+            info!("shutting down unclaimed kernel for {}", path.display());
+            stop_warm_kernel(&app, warm.handle);
+        }
+    });
+}
+
+/// Shut down a warmed-up kernel that won't be used, once it has started.
+fn stop_warm_kernel<R: Runtime>(
+    app: &AppHandle<R>,
+    warm: async_runtime::JoinHandle<Result<EntityId, Error>>,
+) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Ok(Ok(kernel_id)) = warm.await {
+            _ = stop_kernel(kernel_id, app.clone(), app.state()).await;
+        }
+    });
+}
 
-    let info = commands::kernel_info(kernel.conn()).await?;
-    info!(banner = info.banner, "started new jute kernel");
+/// Start a kernel, choosing its spec from the arguments or the defaults.
+///
+/// The spec is the first of `spec_name`, the project's environment, the spec
+/// stored in the notebook, the default kernel from settings, and `python3`.
+async fn launch_kernel<R: Runtime>(
+    app: &AppHandle<R>,
+    spec_name: Option<&str>,
+    notebook_path: Option<&Path>,
+    stored_spec: Option<&str>,
+) -> Result<EntityId, Error> {
+    let state = app.state::<State>();
+    let default_kernel = state.settings.read().unwrap().default_kernel.clone();
+    let venv_prefix = default_venv_prefix(app)?;
+    let python = match &venv_prefix {
+        Some(prefix) => Some(prefix.join("bin/python")),
+        // Temporary hack
+        None => launch::python_from_env().or_else(|| Some("/opt/homebrew/bin/python3.11".into())),
+    };
+    let LaunchedKernel {
+        kernel,
+        info,
+        startup,
+    } = launch::launch(LaunchOptions {
+        spec_name,
+        notebook_path,
+        stored_spec,
+        default_kernel: default_kernel.as_deref(),
+        venv_prefix: venv_prefix.as_deref(),
+        python,
+        trusted: !notebook_path.is_some_and(|path| state.untrusted_notebooks.contains_key(path)),
+    })
+    .await?;
 
-    let kernel_id = String::from(kernel.id());
-    state.kernels.insert(kernel_id.clone(), kernel);
+    let kernel_id = state.entities.create(Entity::Kernel);
+    state.kernel_startup.insert(kernel_id, startup);
+    monitor::forward_kernel_liveness(app, kernel_id, kernel.conn());
+    let queue = Arc::new(ExecutionQueue::default());
+    forward_cell_states(app, kernel_id, &queue);
+    state.execution_queues.insert(kernel_id, queue);
+    state.kernel_info.insert(kernel_id, info);
+    state.kernels.insert(kernel_id, kernel);
     Ok(kernel_id)
 }
 
+/// Forget the state of a kernel that is gone, detaching it from windows.
+fn forget_kernel<R: Runtime>(app: &AppHandle<R>, kernel_id: EntityId) -> Result<(), Error> {
+    let state = app.state::<State>();
+    state.entities.remove(kernel_id);
+    state.outputs.remove(&kernel_id);
+    state.truncated_outputs.remove(&kernel_id);
+    state.kernel_info.remove(&kernel_id);
+    state.kernel_startup.remove(&kernel_id);
+    if let Some((_, queue)) = state.execution_queues.remove(&kernel_id) {
+        queue.clear();
+    }
+    for mut window in state.notebook_windows.iter_mut() {
+        if window.kernel_id == Some(kernel_id) {
+            window.kernel_id = None;
+        }
+    }
+    menu::update_menu_state(app)?;
+    Ok(())
+}
+
 /// Stop a Jupyter kernel.
 #[tauri::command]
-pub async fn stop_kernel(kernel_id: &str, state: tauri::State<'_, State>) -> Result<(), Error> {
+pub async fn stop_kernel<R: Runtime>(
+    kernel_id: EntityId,
+    app: AppHandle<R>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
     info!("stopping jute kernel {kernel_id}");
     let (_, mut kernel) = state
         .kernels
-        .remove(kernel_id)
+        .remove(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?;
+    forget_kernel(&app, kernel_id)?;
+    kernel.kill().await?;
+    Ok(())
+}
+
+/// Interrupt the code running in a Jupyter kernel.
+#[tauri::command]
+pub async fn interrupt_kernel(
+    kernel_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    info!("interrupting jute kernel {kernel_id}");
+    let kernel = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?;
+    // Don't hold the kernel while waiting for a reply to an interrupt message.
+    let conn = match kernel.spec().interrupt_mode {
+        KernelInterruptMode::Signal => return kernel.interrupt_signal(),
+        KernelInterruptMode::Message => kernel.conn().clone(),
+    };
+    drop(kernel);
+    commands::interrupt(&conn).await
+}
+
+/// Check whether code typed into a console is complete, so the frontend can
+/// decide whether Enter runs it or continues the input.
+#[tauri::command]
+pub async fn is_complete(
+    kernel_id: EntityId,
+    code: &str,
+    state: tauri::State<'_, State>,
+) -> Result<IsCompleteReply, Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let conn = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .conn()
+        .clone();
+    commands::is_complete(&conn, code).await
+}
+
+/// Get recent inputs from a kernel's history, optionally only those containing
+/// a search string, for the user to recall them.
+#[tauri::command]
+pub async fn get_kernel_history(
+    kernel_id: EntityId,
+    search: Option<&str>,
+    limit: u32,
+    state: tauri::State<'_, State>,
+) -> Result<Vec<HistoryEntry>, Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let conn = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .conn()
+        .clone();
+    let request = match search {
+        Some(search) => HistoryRequest::search(&format!("*{search}*"), limit),
+        None => HistoryRequest::tail(limit),
+    };
+    commands::history(&conn, request).await
+}
+
+/// Get the `kernel_info_reply` of a Jupyter kernel, with its language, banner,
+/// and whether it supports debugging.
+///
+/// The reply is fetched when the kernel starts or restarts, so this doesn't
+/// wait for a busy kernel.
+#[tauri::command]
+pub async fn get_kernel_info(
+    kernel_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<KernelInfoReply, Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    if let Some(info) = state.kernel_info.get(&kernel_id) {
+        return Ok(info.clone());
+    }
+    let conn = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .conn()
+        .clone();
+    let info = commands::kernel_info(&conn).await?;
+    state.kernel_info.insert(kernel_id, info.clone());
+    Ok(info)
+}
+
+/// Restart a Jupyter kernel from the same spec, keeping its ID, and run its
+/// startup code again.
+///
+/// If the new kernel fails to start, the ID is forgotten like after
+/// [`stop_kernel`], so windows don't keep a kernel that is gone.
+#[tauri::command]
+pub async fn restart_kernel<R: Runtime>(
+    kernel_id: EntityId,
+    app: AppHandle<R>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    info!("restarting jute kernel {kernel_id}");
+    // Display IDs and queued cells don't carry over to the new kernel.
+    state.outputs.remove(&kernel_id);
+    state.truncated_outputs.remove(&kernel_id);
+    if let Some(queue) = state.execution_queues.get(&kernel_id) {
+        queue.clear();
+    }
+    let (_, mut kernel) = state
+        .kernels
+        .remove(&kernel_id)
         .ok_or(Error::KernelDisconnect)?;
     kernel.kill().await?;
+    let startup = (state.kernel_startup.get(&kernel_id))
+        .map(|startup| startup.clone())
+        .unwrap_or_default();
+    let restarted = async {
+        let kernel = LocalKernel::start(kernel.spec()).await?;
+        let info = commands::kernel_info(kernel.conn()).await?;
+        launch::run_startup_code(kernel.conn(), &startup).await?;
+        Ok::<_, Error>((kernel, info))
+    };
+    let (kernel, info) = match restarted.await {
+        Ok(restarted) => restarted,
+        Err(err) => {
+            forget_kernel(&app, kernel_id)?;
+            return Err(err);
+        }
+    };
+    state.kernel_info.insert(kernel_id, info);
+    monitor::forward_kernel_liveness(&app, kernel_id, kernel.conn());
+    state.kernels.insert(kernel_id, kernel);
     Ok(())
 }
 
+/// Get the project configuration that applies to a notebook, if any.
+#[tauri::command]
+pub async fn get_project(path: &str) -> Result<Option<Project>, Error> {
+    Project::discover(Path::new(path)).await
+}
+
 /// Get the contents of a Jupyter notebook on disk.
+///
+/// Percent scripts and Quarto documents are converted to notebooks, based on
+/// the file extension. Adjacent stream outputs are merged, so that notebooks
+/// from other tools don't render an output for every printed line.
+/// Missing or duplicate cell IDs are filled in, since the frontend relies on
+/// them.
 #[tauri::command]
 pub async fn get_notebook(path: &str) -> Result<NotebookRoot, Error> {
     info!("getting notebook at {path}");
@@ -90,29 +488,493 @@ pub async fn get_notebook(path: &str) -> Result<NotebookRoot, Error> {
     let contents = tokio::fs::read_to_string(path)
         .await
         .map_err(Error::Filesystem)?;
-    Ok(serde_json::from_str(&contents)?)
+    let mut notebook = NotebookFormat::from_path(Path::new(path)).read(&contents)?;
+    notebook.merge_streams();
+    notebook.normalize_cell_ids();
+    Ok(notebook)
+}
+
+/// Start loading a notebook in pages of cells, for files too large to send in
+/// one message.
+///
+/// The notebook is read like [`get_notebook`], but only its outline is
+/// returned. Fetch its cells with [`get_notebook_cells`], then call
+/// [`finish_notebook_load`] to free them.
+#[tauri::command]
+pub async fn get_notebook_outline(
+    path: &str,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<NotebookOutline, Error> {
+    let notebook = get_notebook(path).await?;
+    Ok(state.notebook_loads.start(window.label(), notebook))
+}
+
+/// Fetch the cells from `start` up to `end` of a notebook being loaded.
+///
+/// Returns `None` if the load was finished or never started.
+#[tauri::command]
+pub async fn get_notebook_cells(
+    load_id: &str,
+    start: usize,
+    end: usize,
+    state: tauri::State<'_, State>,
+) -> Result<Option<Vec<Cell>>, Error> {
+    Ok(state.notebook_loads.cells(load_id, start, end))
+}
+
+/// Free the cells of a notebook that finished loading, or whose load was
+/// given up.
+#[tauri::command]
+pub async fn finish_notebook_load(
+    load_id: &str,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    state.notebook_loads.finish(load_id);
+    Ok(())
+}
+
+/// Write a Jupyter notebook to disk, replacing the file at `path`.
+///
+/// The notebook is written in the format given by the file extension, so that
+/// scripts and documents keep their original format. Adjacent stream outputs
+/// are merged, cell IDs are checked, and attachments that no cell mentions are
+/// removed before it is written. `.ipynb` files are formatted like Jupyter
+/// writes them.
+///
+/// Returns the new modification time of the file, in milliseconds since the
+/// Unix epoch.
+#[tauri::command]
+pub async fn save_notebook(path: &str, mut notebook: NotebookRoot) -> Result<u64, Error> {
+    info!("saving notebook at {path}");
+
+    notebook.merge_streams();
+    notebook.normalize_cell_ids();
+    attachments::remove_unused_attachments(&mut notebook);
+    let contents = NotebookFormat::from_path(Path::new(path)).write(&notebook)?;
+    tokio::fs::write(path, contents)
+        .await
+        .map_err(Error::Filesystem)?;
+    modified_millis(Path::new(path)).await
+}
+
+/// The modification time of a file, in milliseconds since the Unix epoch.
+pub(crate) async fn modified_millis(path: &Path) -> Result<u64, Error> {
+    let modified = tokio::fs::metadata(path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .map_err(Error::Filesystem)?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok(since_epoch.as_millis() as u64)
+}
+
+/// Compare two notebooks on disk cell by cell, from `path_a` to `path_b`.
+///
+/// Either may be a script or document, which is converted like in
+/// [`get_notebook`].
+#[tauri::command]
+pub async fn diff_notebooks(path_a: &str, path_b: &str) -> Result<NotebookDiff, Error> {
+    let (old, new) = tokio::try_join!(get_notebook(path_a), get_notebook(path_b))?;
+    Ok(diff::diff_notebooks(&old, &new))
+}
+
+/// Merge a notebook with git conflict markers, which can't be opened as is.
+///
+/// Returns `None` if the file has no conflicts. Otherwise, each side of the
+/// conflicts is parsed and merged cell by cell, and the cells whose source
+/// still conflicts are returned with the merged notebook.
+#[tauri::command]
+pub async fn resolve_notebook_conflicts(path: &str) -> Result<Option<NotebookMerge>, Error> {
+    info!("resolving conflicts in notebook at {path}");
+
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(Error::Filesystem)?;
+    let Some(mut merge) = merge::resolve_conflicts(&contents)? else {
+        return Ok(None);
+    };
+    merge.notebook.merge_streams();
+    Ok(Some(merge))
+}
+
+/// Name of the event emitted to the frontend when a cell in a kernel's
+/// execution queue changes state.
+pub const CELL_STATE_EVENT: &str = "cell-state";
+
+/// Payload of [`CELL_STATE_EVENT`].
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct CellStateEvent {
+    /// ID of the kernel running the cell.
+    #[ts(type = "string")]
+    pub kernel_id: EntityId,
+
+    /// ID of the cell.
+    pub cell_id: String,
+
+    /// The new state of the cell.
+    pub state: CellState,
+}
+
+crate::export_bindings!(CellStateEvent);
+
+/// Emit [`CELL_STATE_EVENT`] for changes in a kernel's execution queue, until
+/// the queue is dropped.
+fn forward_cell_states<R: Runtime>(
+    app: &AppHandle<R>,
+    kernel_id: EntityId,
+    queue: &ExecutionQueue,
+) {
+    let mut changes = queue.subscribe();
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            let change = match changes.recv().await {
+                Ok(change) => change,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let event = CellStateEvent {
+                kernel_id,
+                cell_id: change.cell_id,
+                state: change.state,
+            };
+            _ = app.emit(CELL_STATE_EVENT, event);
+        }
+    });
 }
 
 /// Run a code cell in a Jupyter kernel.
+///
+/// Options control what happens when the frontend falls behind, and default
+/// to the `output_overflow` setting. See [`RunCellOptions`].
+///
+/// With the ID of the cell, it waits in the kernel's execution queue until
+/// the cells submitted before it finish, and [`CELL_STATE_EVENT`] is emitted
+/// as it goes from queued to running to finished. If a cell before it fails,
+/// it is aborted without running. Its outputs are also tracked, so that
+/// updates to a display ID are sent as [`RunCellEvent::UpdateOutput`] events
+/// for every output with that ID, including those in other cells.
+///
+/// Outputs of a cell past the `output_limit` setting are truncated, and the
+/// rest can be fetched with [`fetch_output_chunk`].
 #[tauri::command]
 pub async fn run_cell(
-    kernel_id: &str,
+    kernel_id: EntityId,
     code: &str,
-    on_event: Channel<RunCellEvent>,
+    cell_id: Option<String>,
+    options: Option<RunCellOptions>,
+    on_event: Channel<InvokeResponseBody>,
     state: tauri::State<'_, State>,
 ) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let options = RunCellOptions {
+        allow_stdin: true,
+        ..options.unwrap_or_else(|| default_run_options(&state))
+    };
+
+    let Some(cell_id) = cell_id else {
+        let conn = state
+            .kernels
+            .get(&kernel_id)
+            .ok_or(Error::KernelDisconnect)?
+            .conn()
+            .clone();
+        let rx = commands::run_cell(&conn, code, options).await?;
+        return forward_events(&state, rx, &on_event).await;
+    };
+    let ticket = execution_queue(&state, kernel_id)?.enqueue(&cell_id, true);
+    run_queued_cell(
+        &state,
+        kernel_id,
+        ticket,
+        &cell_id,
+        code,
+        options,
+        |event| Ok(on_event.send(encode_event(event)?).is_ok()),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Options for running code from the app, with the user's settings.
+fn default_run_options(state: &State) -> RunCellOptions {
+    RunCellOptions {
+        overflow: state.settings.read().unwrap().output_overflow,
+        ..Default::default()
+    }
+}
+
+/// Run code cells in order on a Jupyter kernel, streaming their events over
+/// one channel, tagged with the index of the cell in the batch.
+///
+/// The cells are added to the kernel's execution queue together, so cells
+/// run with [`run_cell`] in the meantime wait until the batch finishes, and
+/// clearing the queue stops it. With `stop_on_error`, which is the default,
+/// the cells after one that fails are aborted.
+#[tauri::command]
+pub async fn run_all_cells(
+    kernel_id: EntityId,
+    batch: CellBatch,
+    stop_on_error: Option<bool>,
+    on_event: Channel<InvokeResponseBody>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let cells = match batch {
+        CellBatch::Cells(cells) => cells,
+        CellBatch::Path(path) => {
+            info!("running all cells of notebook at {path}");
+            let contents = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(Error::Filesystem)?;
+            let notebook = NotebookFormat::from_path(Path::new(&path)).read(&contents)?;
+            (notebook.cells.into_iter().enumerate())
+                .filter_map(|(index, cell)| match cell {
+                    Cell::Code(cell) => Some(BatchCell {
+                        cell_id: cell.id.unwrap_or_else(|| index.to_string()),
+                        source: cell.source.into(),
+                    }),
+                    _ => None,
+                })
+                .collect()
+        }
+    };
+
+    let options = RunCellOptions {
+        allow_stdin: true,
+        ..default_run_options(&state)
+    };
+    let queue = execution_queue(&state, kernel_id)?;
+    let stop_on_error = stop_on_error.unwrap_or(true);
+    let tickets: Vec<_> = (cells.iter())
+        .map(|cell| queue.enqueue(&cell.cell_id, stop_on_error))
+        .collect();
+    for (cell_index, (cell, ticket)) in cells.iter().zip(tickets).enumerate() {
+        let send = |event: &RunCellEvent| {
+            let event = BatchEvent {
+                cell_index,
+                event: event.clone(),
+            };
+            Ok(on_event.send(encode_event(&event)?).is_ok())
+        };
+        run_queued_cell(
+            &state,
+            kernel_id,
+            ticket,
+            &cell.cell_id,
+            &cell.source,
+            options,
+            send,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// The execution queue of a kernel.
+fn execution_queue(state: &State, kernel_id: EntityId) -> Result<Arc<ExecutionQueue>, Error> {
+    Ok(state
+        .execution_queues
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .clone())
+}
+
+/// Run a cell once its ticket's turn comes in the kernel's execution queue,
+/// tracking its outputs.
+///
+/// Events are passed to `send`, which returns whether the frontend is still
+/// listening. Once it stops, the cell keeps its place in the queue until it
+/// finishes running.
+async fn run_queued_cell(
+    state: &State,
+    kernel_id: EntityId,
+    mut ticket: QueueTicket,
+    cell_id: &str,
+    code: &str,
+    options: RunCellOptions,
+    mut send: impl FnMut(&RunCellEvent) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    if !ticket.wait().await {
+        send(&RunCellEvent::Aborted)?;
+        return Ok(());
+    }
+
+    let conn = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .conn()
+        .clone();
+    let rx = commands::run_cell(&conn, code, options).await?;
+    state.outputs.entry(kernel_id).or_default().clear(cell_id);
+    state
+        .truncated_outputs
+        .entry(kernel_id)
+        .or_default()
+        .clear(cell_id);
+    let limit = state.settings.read().unwrap().output_limit.0 as usize;
+    let mut truncator = OutputTruncator::new(cell_id, limit);
+    let mut listening = true;
+    while let Ok(event) = rx.recv().await {
+        ticket.record(&event);
+        let events = truncator.apply(
+            event,
+            &mut state.truncated_outputs.entry(kernel_id).or_default(),
+        );
+        for event in events {
+            let updates = state
+                .outputs
+                .entry(kernel_id)
+                .or_default()
+                .apply(cell_id, &event);
+            let events = match event {
+                RunCellEvent::UpdateDisplayData(_) => updates
+                    .into_iter()
+                    .map(RunCellEvent::UpdateOutput)
+                    .collect(),
+                event => vec![event],
+            };
+            for event in &events {
+                if listening {
+                    listening = send(event)?;
+                }
+            }
+        }
+    }
+    ticket.complete();
+    Ok(())
+}
+
+/// Fetch a chunk of the text cut from a large output, from a byte offset.
+///
+/// Returns `None` if the text is no longer kept, because the cell was run
+/// again or the kernel was restarted.
+#[tauri::command]
+pub async fn fetch_output_chunk(
+    kernel_id: EntityId,
+    output_id: &str,
+    offset: usize,
+    state: tauri::State<'_, State>,
+) -> Result<Option<OutputChunk>, Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let length = state.settings.read().unwrap().output_limit.0 as usize;
+    Ok(state
+        .truncated_outputs
+        .get(&kernel_id)
+        .and_then(|outputs| outputs.chunk(output_id, offset, length)))
+}
+
+/// Abort the cells waiting in a kernel's execution queue. A cell that is
+/// already running is left to finish.
+#[tauri::command]
+pub async fn clear_execution_queue(
+    kernel_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    if let Some(queue) = state.execution_queues.get(&kernel_id) {
+        queue.clear();
+    }
+    Ok(())
+}
+
+/// Run a small piece of code in a Jupyter kernel, even while a cell is busy.
+///
+/// The code runs in a subshell, which is deleted when it finishes. It is not
+/// stored in the kernel's history. This fails if the kernel does not support
+/// subshells.
+#[tauri::command]
+pub async fn run_in_subshell(
+    kernel_id: EntityId,
+    code: &str,
+    options: Option<RunCellOptions>,
+    on_event: Channel<InvokeResponseBody>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
     let conn = state
         .kernels
-        .get(kernel_id)
+        .get(&kernel_id)
         .ok_or(Error::KernelDisconnect)?
         .conn()
         .clone();
 
-    let rx = commands::run_cell(&conn, code).await?;
+    let subshell = conn.create_subshell().await?;
+    let options = RunCellOptions {
+        allow_stdin: true,
+        ..options.unwrap_or_else(|| default_run_options(&state))
+    };
+    let rx = commands::run_in_subshell(&subshell, code, options).await?;
+    forward_events(&state, rx, &on_event).await
+}
+
+/// List the variables in the namespace of a Jupyter kernel, for the variable
+/// explorer. This fails if the kernel's language is not supported.
+#[tauri::command]
+pub async fn list_variables(
+    kernel_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<Vec<Variable>, Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let (conn, language) = {
+        let kernel = state
+            .kernels
+            .get(&kernel_id)
+            .ok_or(Error::KernelDisconnect)?;
+        (kernel.conn().clone(), kernel.spec().language.clone())
+    };
+    let query = VariableQuery::for_language(&language)
+        .ok_or_else(|| Error::KernelUnsupported(format!("listing variables in {language}")))?;
+    variables::list_variables(&conn, query).await
+}
+
+/// Reply to a request from the kernel for input while running code.
+///
+/// The value may be a password, so it is never logged.
+#[tauri::command]
+pub async fn reply_input(
+    kernel_id: EntityId,
+    request_id: &str,
+    value: String,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let conn = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .conn()
+        .clone();
+
+    conn.reply_input(request_id, value).await
+}
+
+/// Send events from running code to the frontend, until there are no more or
+/// the frontend stops listening. Outputs past the `output_limit` setting are
+/// truncated, but there is no cell to expand, so the rest is dropped.
+async fn forward_events(
+    state: &State,
+    rx: async_channel::Receiver<RunCellEvent>,
+    on_event: &Channel<InvokeResponseBody>,
+) -> Result<(), Error> {
+    let limit = state.settings.read().unwrap().output_limit.0 as usize;
+    let mut truncator = OutputTruncator::without_cell(limit);
+    let mut store = TruncatedOutputs::default();
     while let Ok(event) = rx.recv().await {
-        if on_event.send(event).is_err() {
-            break;
+        for event in truncator.apply(event, &mut store) {
+            if on_event.send(encode_event(&event)?).is_err() {
+                return Ok(());
+            }
         }
     }
     Ok(())
 }
+
+/// Encode an event from running code to send it to the frontend.
+fn encode_event(event: &impl Serialize) -> Result<InvokeResponseBody, Error> {
+    Ok(match Payload::encode(event)? {
+        Payload::Json(json) => InvokeResponseBody::Json(json),
+        Payload::Gzip(bytes) => InvokeResponseBody::Raw(bytes),
+    })
+}
@@ -0,0 +1,59 @@
+//! Commands for attaching images to Markdown cells.
+
+use tauri::ipc::{InvokeBody, Request};
+
+use crate::{
+    backend::{
+        attachments::{self, AttachmentInfo, NewAttachment},
+        notebook::CellAttachments,
+    },
+    Error,
+};
+
+/// Attach an image pasted into a cell.
+///
+/// The image is sent as the raw body of the request, so it isn't encoded as
+/// JSON. The `Jute-Attachment` header has its `file_name` and the `existing`
+/// attachments of the cell, URL-encoded like a query string. The data is
+/// stored as base64 in a MIME bundle, under a name based on `file_name` that
+/// isn't taken.
+#[tauri::command]
+pub async fn create_attachment(request: Request<'_>) -> Result<NewAttachment, Error> {
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err(Error::InvalidAttachment("expected raw image data".into()));
+    };
+    let params = (request.headers().get("jute-attachment"))
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    let mut file_name = String::new();
+    let mut existing = Vec::new();
+    for (key, value) in url::form_urlencoded::parse(params) {
+        match &*key {
+            "file_name" => file_name = value.into_owned(),
+            "existing" => existing.push(value.into_owned()),
+            _ => {}
+        }
+    }
+    attachments::create_attachment(&existing, &file_name, data)
+}
+
+/// Attach an image file dropped into a cell, like [`create_attachment`].
+#[tauri::command]
+pub async fn attach_file(path: &str, existing: Vec<String>) -> Result<NewAttachment, Error> {
+    let data = tokio::fs::read(path).await.map_err(Error::Filesystem)?;
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    attachments::create_attachment(&existing, &file_name, &data)
+}
+
+/// List the attachments of a cell with the given source, noting which ones it
+/// still mentions.
+#[tauri::command]
+pub async fn list_attachments(
+    source: &str,
+    attachments: CellAttachments,
+) -> Result<Vec<AttachmentInfo>, Error> {
+    Ok(attachments::list_attachments(source, &attachments))
+}
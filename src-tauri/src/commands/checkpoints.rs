@@ -0,0 +1,45 @@
+//! Commands for creating notebook checkpoints and rolling back to them.
+
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::{
+    backend::notebook::NotebookRoot,
+    checkpoints::{Checkpoint, CheckpointStore},
+    Error,
+};
+
+/// Get the checkpoint store, which falls back to the app data directory.
+pub fn checkpoint_store<R: Runtime>(app: &AppHandle<R>) -> Result<CheckpointStore, Error> {
+    Ok(CheckpointStore::new(
+        app.path().app_data_dir()?.join("checkpoints"),
+    ))
+}
+
+/// Save a checkpoint of the notebook as it is on disk, called after the user
+/// saves it.
+#[tauri::command]
+pub async fn create_checkpoint(path: &str, app: AppHandle) -> Result<Checkpoint, Error> {
+    checkpoint_store(&app)?.create(Path::new(path)).await
+}
+
+/// List the checkpoints of a notebook, newest first.
+#[tauri::command]
+pub async fn list_checkpoints(path: &str, app: AppHandle) -> Result<Vec<Checkpoint>, Error> {
+    checkpoint_store(&app)?.list(Path::new(path)).await
+}
+
+/// Replace a notebook on disk with one of its checkpoints, returning the
+/// restored notebook.
+#[tauri::command]
+pub async fn restore_checkpoint(
+    path: &str,
+    checkpoint_id: &str,
+    app: AppHandle,
+) -> Result<NotebookRoot, Error> {
+    checkpoint_store(&app)?
+        .restore(Path::new(path), checkpoint_id)
+        .await?;
+    super::get_notebook(path).await
+}
@@ -0,0 +1,128 @@
+//! Commands for editing a notebook together with peers in real time.
+
+use tauri::{ipc::Channel, WebviewWindow};
+
+use crate::{
+    backend::{notebook::NotebookRoot, remote::JupyterClient},
+    collab::{session::CollabSession, CollabEvent, TextChange},
+    entity::{Entity, EntityId},
+    state::State,
+    Error,
+};
+
+/// Host a collaboration session for the window's notebook, returning the URL
+/// for peers to join it.
+///
+/// Any session the window was already in is left first.
+#[tauri::command]
+pub async fn host_collab(
+    notebook: NotebookRoot,
+    on_event: Channel<CollabEvent>,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<String, Error> {
+    state.collab_sessions.remove(window.label());
+    let session = CollabSession::host(notebook, move |event| _ = on_event.send(event)).await?;
+    let url = session.url().unwrap_or_default().to_string();
+    state.collab_sessions.insert(window.label().into(), session);
+    Ok(url)
+}
+
+/// Join a collaboration session from its URL, returning the shared notebook
+/// to show in the window.
+#[tauri::command]
+pub async fn join_collab(
+    url: &str,
+    on_event: Channel<CollabEvent>,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<NotebookRoot, Error> {
+    state.collab_sessions.remove(window.label());
+    let (session, notebook) =
+        CollabSession::join(url, move |event| _ = on_event.send(event)).await?;
+    state.collab_sessions.insert(window.label().into(), session);
+    Ok(notebook)
+}
+
+/// Open a notebook on a saved Jupyter server in its shared collaboration
+/// session, returning the notebook to show in the window.
+///
+/// Edits are synced with JupyterLab users who have the same notebook open, and
+/// the server saves them. The path is relative to the server's root folder.
+#[tauri::command]
+pub async fn join_jupyter_collab(
+    server_id: EntityId,
+    path: &str,
+    on_event: Channel<CollabEvent>,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<NotebookRoot, Error> {
+    let server_id = state.entities.get(server_id, Entity::Server)?;
+    let server = state
+        .servers
+        .read()
+        .unwrap()
+        .get(server_id)
+        .cloned()
+        .ok_or(Error::InvalidEntity(server_id.to_string()))?;
+    let client = JupyterClient::new(&server.url, server.token.as_deref().unwrap_or_default())?;
+    let room = client.collab_room(path).await?;
+
+    state.collab_sessions.remove(window.label());
+    let (session, notebook) =
+        CollabSession::join(room, move |event| _ = on_event.send(event)).await?;
+    state.collab_sessions.insert(window.label().into(), session);
+    Ok(notebook)
+}
+
+/// Send changes to the window's notebook other than typing, like new cells
+/// or outputs, to peers.
+#[tauri::command]
+pub async fn collab_sync(
+    notebook: NotebookRoot,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    if let Some(session) = state.collab_sessions.get(window.label()) {
+        session.sync(notebook)?;
+    }
+    Ok(())
+}
+
+/// Send changes typed into the source of a cell to peers.
+#[tauri::command]
+pub async fn collab_edit_source(
+    cell_id: &str,
+    changes: Vec<TextChange>,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    if let Some(session) = state.collab_sessions.get(window.label()) {
+        session.edit_source(cell_id, &changes)?;
+    }
+    Ok(())
+}
+
+/// Show peers which cell is focused in the window.
+#[tauri::command]
+pub async fn collab_set_cursor(
+    cell_id: Option<&str>,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    if let Some(session) = state.collab_sessions.get(window.label()) {
+        session.set_cursor(cell_id);
+    }
+    Ok(())
+}
+
+/// Leave the window's collaboration session, ending it for peers if this
+/// window is hosting it.
+#[tauri::command]
+pub async fn leave_collab(
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    state.collab_sessions.remove(window.label());
+    Ok(())
+}
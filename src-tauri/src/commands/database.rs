@@ -0,0 +1,192 @@
+//! Commands for saved database connections and SQL cells.
+
+use std::{path::PathBuf, sync::Arc};
+
+use tauri::{ipc::Channel, AppHandle, Manager, Runtime};
+use tokio::sync::mpsc;
+
+use crate::{
+    backend::commands::{self, RunCellEvent},
+    database::{
+        dataframe_code, pool::DatabasePool, DatabaseConnection, DatabaseKind, SqlCell, SqlEvent,
+        SqlResults, DATABASES_FILE,
+    },
+    entity::{Entity, EntityId},
+    secrets::{self, SecretNamespace},
+    state::State,
+    Error,
+};
+
+/// Get the path to the database connections file in the app config directory.
+pub fn databases_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_config_dir()?.join(DATABASES_FILE))
+}
+
+/// List the saved database connections.
+#[tauri::command]
+pub async fn list_databases(
+    state: tauri::State<'_, State>,
+) -> Result<Vec<DatabaseConnection>, Error> {
+    Ok(state.databases.read().unwrap().list().to_vec())
+}
+
+/// Save a database connection, or update the existing connection with the
+/// same name.
+#[tauri::command]
+pub async fn add_database(
+    name: &str,
+    kind: DatabaseKind,
+    url: &str,
+    password: Option<&str>,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<DatabaseConnection, Error> {
+    let (conn, databases) = {
+        let mut databases = state.databases.write().unwrap();
+        let conn = databases.add(&state.entities, name, kind, url, password)?;
+        (conn, databases.clone())
+    };
+    databases.save(&databases_path(&app)?).await?;
+    // Reconnect with the new settings on the next query.
+    if let Some((_, pool)) = state.database_pools.remove(&conn.id) {
+        pool.close().await;
+    }
+    Ok(conn)
+}
+
+/// Remove a saved database connection, returning whether it was found.
+#[tauri::command]
+pub async fn remove_database(
+    database_id: EntityId,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<bool, Error> {
+    let database_id = database_id.expect_kind(Entity::Database)?;
+    let (removed, databases) = {
+        let mut databases = state.databases.write().unwrap();
+        let removed = databases.remove(&state.entities, database_id);
+        (removed, databases.clone())
+    };
+    databases.save(&databases_path(&app)?).await?;
+    if removed {
+        let password = (database_id.to_string(), None);
+        secrets::store_secrets(SecretNamespace::DatabaseCredential, vec![password]).await;
+    }
+    if let Some((_, pool)) = state.database_pools.remove(&database_id) {
+        pool.close().await;
+    }
+    Ok(removed)
+}
+
+/// Run a query on a saved database, streaming results to the frontend.
+#[tauri::command]
+pub async fn execute_sql(
+    connection_id: EntityId,
+    query: &str,
+    on_event: Channel<SqlEvent>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let connection_id = state.entities.get(connection_id, Entity::Database)?;
+    let pool = database_pool(&state, connection_id).await?;
+    stream_query(&pool, query, &on_event, false).await?;
+    Ok(())
+}
+
+/// Run a SQL cell, whose source starts with a `%%sql` header.
+///
+/// If the header names a variable, the results are then bound to it in the
+/// kernel as a pandas DataFrame. See [`crate::database`] for the syntax.
+#[tauri::command]
+pub async fn run_sql_cell(
+    source: &str,
+    kernel_id: Option<EntityId>,
+    on_event: Channel<SqlEvent>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let cell = SqlCell::parse(source).ok_or_else(|| Error::Database("not a SQL cell".into()))??;
+    let connection_id = state
+        .databases
+        .read()
+        .unwrap()
+        .find(&cell.connection)
+        .map(|conn| conn.id)
+        .ok_or_else(|| Error::Database(format!("no connection named {:?}", cell.connection)))?;
+    let pool = database_pool(&state, connection_id).await?;
+    let results = stream_query(&pool, &cell.query, &on_event, cell.variable.is_some()).await?;
+
+    let Some(variable) = cell.variable else {
+        return Ok(());
+    };
+    let Some(kernel_id) = kernel_id else {
+        _ = on_event.send(SqlEvent::BindError("no kernel is running".into()));
+        return Ok(());
+    };
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let conn = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?
+        .conn()
+        .clone();
+    let code = dataframe_code(&variable, &results)?;
+    let rx = commands::run_cell(&conn, &code, Default::default()).await?;
+    let mut failure = None;
+    while let Ok(event) = rx.recv().await {
+        match event {
+            RunCellEvent::Error(err) => failure = Some(format!("{}: {}", err.ename, err.evalue)),
+            RunCellEvent::Disconnect(reason) => failure = Some(reason),
+            _ => {}
+        }
+    }
+    _ = on_event.send(match failure {
+        Some(err) => SqlEvent::BindError(err),
+        None => SqlEvent::Bound(variable),
+    });
+    Ok(())
+}
+
+/// Get the pool of a saved database, connecting to it on first use.
+async fn database_pool(state: &State, id: EntityId) -> Result<Arc<DatabasePool>, Error> {
+    if let Some(pool) = state.database_pools.get(&id) {
+        return Ok(pool.clone());
+    }
+    let conn = state
+        .databases
+        .read()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or(Error::InvalidEntity(id.to_string()))?;
+    let pool = Arc::new(DatabasePool::connect(&conn).await?);
+    Ok(state.database_pools.entry(id).or_insert(pool).clone())
+}
+
+/// Run a query and forward its events to the frontend, keeping the results if
+/// they will be bound into the kernel.
+async fn stream_query(
+    pool: &DatabasePool,
+    query: &str,
+    on_event: &Channel<SqlEvent>,
+    keep: bool,
+) -> Result<SqlResults, Error> {
+    let (tx, mut rx) = mpsc::channel(4);
+    // The receiver is moved in, so the query stops if the frontend goes away.
+    let forward = async move {
+        let mut results = SqlResults::default();
+        while let Some(event) = rx.recv().await {
+            if keep {
+                match &event {
+                    SqlEvent::Columns(columns) => results.columns = columns.clone(),
+                    SqlEvent::Rows(rows) => results.rows.extend_from_slice(rows),
+                    _ => {}
+                }
+            }
+            if on_event.send(event).is_err() {
+                break;
+            }
+        }
+        results
+    };
+    let (result, results) = tokio::join!(pool.query(query, tx), forward);
+    result.map(|()| results)
+}
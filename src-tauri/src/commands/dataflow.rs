@@ -0,0 +1,18 @@
+//! Commands for analyzing dependencies between cells, used by reactive mode.
+
+use crate::{backend::notebook::NotebookRoot, dataflow::DependencyGraph, Error};
+
+/// Build the dependency graph of a notebook's code cells.
+#[tauri::command]
+pub async fn get_dependency_graph(notebook: NotebookRoot) -> Result<DependencyGraph, Error> {
+    Ok(DependencyGraph::build(&notebook))
+}
+
+/// List the cells to re-run after a cell changes, in notebook order.
+#[tauri::command]
+pub async fn get_dependent_cells(
+    notebook: NotebookRoot,
+    cell_id: String,
+) -> Result<Vec<String>, Error> {
+    Ok(DependencyGraph::build(&notebook).dependents(&cell_id))
+}
@@ -0,0 +1,156 @@
+//! Routing of `jute://` deep links to the right window.
+//!
+//! Any web page can open a link, so the user confirms each action first.
+//! Notebooks opened from links don't run their project's startup code unless
+//! the user trusts the project.
+
+use std::path::PathBuf;
+
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tokio::sync::oneshot;
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    commands::{servers::add_server, settings::modify_settings},
+    deep_link::DeepLink,
+    project::Project,
+    state::State,
+    window, Error,
+};
+
+/// Handle deep links that are opened while the app is running.
+pub fn listen_for_deep_links(app: &AppHandle) {
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            open_deep_link(&handle, &url);
+        }
+    });
+}
+
+/// Route a deep link to the right window, showing an error if it is invalid.
+pub fn open_deep_link(app: &AppHandle, url: &Url) {
+    let result = DeepLink::parse(url).and_then(|link| route(app, link));
+    if let Err(err) = result {
+        warn!("could not open {url}: {err}");
+        show_error(app, err);
+    }
+}
+
+fn show_error(app: &AppHandle, err: Error) {
+    app.dialog()
+        .message(err.to_string())
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
+
+fn route(app: &AppHandle, link: DeepLink) -> Result<(), Error> {
+    match link {
+        DeepLink::Open { path, cell } => {
+            let app = app.clone();
+            app.dialog()
+                .message(format!(
+                    "Do you want to open the notebook at {}?",
+                    path.display()
+                ))
+                .title("Open Notebook")
+                .buttons(MessageDialogButtons::OkCancelCustom(
+                    "Open".into(),
+                    "Cancel".into(),
+                ))
+                .show(move |confirmed| {
+                    if confirmed {
+                        async_runtime::spawn(open_notebook(app, path, cell));
+                    }
+                });
+        }
+        DeepLink::ConnectServer { url, token } => {
+            // Any web page can open a link, so the user confirms the server.
+            let app = app.clone();
+            app.dialog()
+                .message(format!(
+                    "Do you want to connect to the Jupyter server at {url}?"
+                ))
+                .title("Connect to Server")
+                .buttons(MessageDialogButtons::OkCancelCustom(
+                    "Connect".into(),
+                    "Cancel".into(),
+                ))
+                .show(move |confirmed| {
+                    if confirmed {
+                        async_runtime::spawn(connect_server(app, url, token));
+                    }
+                });
+        }
+    }
+    Ok(())
+}
+
+/// Ask a question in a dialog, returning whether the user confirmed it.
+async fn confirm(app: &AppHandle, title: &str, message: String, ok: &str) -> bool {
+    let (tx, rx) = oneshot::channel();
+    app.dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            ok.into(),
+            "Cancel".into(),
+        ))
+        .show(move |confirmed| _ = tx.send(confirmed));
+    rx.await.unwrap_or(false)
+}
+
+/// Open a notebook from a deep link, after asking whether to trust its
+/// project if the project would run code in the kernel.
+async fn open_notebook(app: AppHandle, path: PathBuf, cell: Option<String>) {
+    if let Err(err) = try_open_notebook(&app, path, cell).await {
+        show_error(&app, err);
+    }
+}
+
+async fn try_open_notebook(
+    app: &AppHandle,
+    path: PathBuf,
+    cell: Option<String>,
+) -> Result<(), Error> {
+    let project = Project::discover(&path).await?;
+    if let Some(project) = project.filter(|project| project.config.runs_code()) {
+        let state = app.state::<State>();
+        let trusted = (state.settings.read().unwrap().trusted_projects).contains(&project.root);
+        let message = format!(
+            "The project at {} sets up kernels with code from its jute.toml. Do you trust this \
+             project to run code on your computer?",
+            project.root
+        );
+        if trusted {
+            state.untrusted_notebooks.remove(&path);
+        } else if confirm(app, "Trust Project", message, "Trust").await {
+            state.untrusted_notebooks.remove(&path);
+            modify_settings(app, |settings| settings.trusted_projects.push(project.root)).await?;
+        } else {
+            state.untrusted_notebooks.insert(path.clone(), ());
+        }
+    }
+    match cell {
+        Some(cell) => window::focus_notebook_cell(app, &path, &cell)?,
+        None => window::open_notebook(app, &path)?,
+    }
+    Ok(())
+}
+
+/// Save a server from a deep link, then show it in a home window.
+async fn connect_server(app: AppHandle, url: String, token: Option<String>) {
+    let name = match Url::parse(&url) {
+        Ok(parsed) => format!("Jupyter Server ({})", parsed.authority()),
+        Err(_) => "Jupyter Server".into(),
+    };
+    let result = add_server(&name, &url, token.as_deref(), app.clone(), app.state()).await;
+    match result {
+        Ok(_) => _ = window::open_home(&app),
+        Err(err) => show_error(&app, err),
+    }
+}
@@ -0,0 +1,68 @@
+//! Commands for the kernel devtools panel, which shows the messages sent to
+//! and received from a kernel as they happen.
+
+use tauri::ipc::Channel;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    backend::wire_protocol::TappedMessage,
+    entity::{Entity, EntityId},
+    state::State,
+    Error,
+};
+
+/// Start or stop recording the messages of a kernel.
+///
+/// Recording is off by default, since it keeps a copy of recent messages.
+#[tauri::command]
+pub async fn set_kernel_tap(
+    kernel_id: EntityId,
+    enabled: bool,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    let kernel = state
+        .kernels
+        .get(&kernel_id)
+        .ok_or(Error::KernelDisconnect)?;
+    kernel.conn().tap().set_enabled(enabled);
+    Ok(())
+}
+
+/// Stream the recorded messages of a kernel, starting with the messages that
+/// were already recorded.
+///
+/// This runs until the channel is closed or the kernel is stopped.
+#[tauri::command]
+pub async fn watch_kernel_messages(
+    kernel_id: EntityId,
+    on_message: Channel<TappedMessage>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let kernel_id = state.entities.get(kernel_id, Entity::Kernel)?;
+    // The kernel isn't held while waiting, so that it can still be dropped.
+    let (mut rx, recorded) = {
+        let kernel = state
+            .kernels
+            .get(&kernel_id)
+            .ok_or(Error::KernelDisconnect)?;
+        let tap = kernel.conn().tap();
+        (tap.subscribe(), tap.messages())
+    };
+    for msg in recorded {
+        if on_message.send(msg).is_err() {
+            return Ok(());
+        }
+    }
+    loop {
+        match rx.recv().await {
+            Ok(msg) => {
+                if on_message.send(msg).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
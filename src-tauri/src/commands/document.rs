@@ -0,0 +1,197 @@
+//! Commands for editing notebook documents held by the backend.
+
+use std::{path::PathBuf, sync::Arc};
+
+use dashmap::mapref::one::RefMut;
+use tauri::{AppHandle, Emitter, Manager, Runtime, WebviewWindow};
+
+use crate::{
+    autosave::SnapshotFn,
+    backend::notebook::NotebookRoot,
+    commands::recovery::recovery_journal,
+    document::{DocChangeEvent, DocSnapshot, NotebookDoc, NotebookOp, DOC_CHANGE_EVENT},
+    entity::{Entity, EntityId},
+    state::State,
+    Error,
+};
+
+/// Look up an open document by ID.
+fn get_document_mut(
+    state: &State,
+    doc_id: EntityId,
+) -> Result<RefMut<'_, EntityId, NotebookDoc>, Error> {
+    let doc_id = state.entities.get(doc_id, Entity::Document)?;
+    (state.documents.get_mut(&doc_id))
+        .ok_or_else(|| Error::InvalidEntity(format!("document {doc_id} is closed")))
+}
+
+/// The current contents of a document, to save or export it.
+pub fn document_notebook(state: &State, doc_id: EntityId) -> Result<NotebookRoot, Error> {
+    Ok(get_document_mut(state, doc_id)?.notebook().clone())
+}
+
+/// Open a document for a notebook, shown by `viewer` in the calling window.
+///
+/// If another window or tab shows the notebook at `path`, and the file hasn't
+/// changed on disk since it was read, its document is shared, with any changes
+/// that are not saved yet. Otherwise, or if `share` is false, a document is
+/// created with the contents of `notebook`.
+#[tauri::command]
+pub async fn open_document(
+    path: Option<PathBuf>,
+    notebook: NotebookRoot,
+    viewer: String,
+    share: bool,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<DocSnapshot, Error> {
+    let modified = match &path {
+        Some(path) => super::modified_millis(path).await.ok(),
+        None => None,
+    };
+    if let (Some(path), true) = (&path, share) {
+        for mut doc in state.documents.iter_mut() {
+            let shown =
+                (doc.viewers.values()).any(|label| window.get_webview_window(label).is_some());
+            if doc.path.as_ref() == Some(path) && doc.modified == modified && shown {
+                doc.viewers.insert(viewer, window.label().into());
+                return Ok(doc.snapshot(*doc.key()));
+            }
+        }
+    }
+    let doc_id = state.entities.create(Entity::Document);
+    let mut doc = NotebookDoc::new(path, notebook);
+    doc.modified = modified;
+    doc.viewers.insert(viewer, window.label().into());
+    let snapshot = doc.snapshot(doc_id);
+    state.documents.insert(doc_id, doc);
+    Ok(snapshot)
+}
+
+/// Get the current contents of a document.
+#[tauri::command]
+pub async fn get_document(
+    doc_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<DocSnapshot, Error> {
+    Ok(get_document_mut(&state, doc_id)?.snapshot(doc_id))
+}
+
+/// Apply operations from the notebook `origin` to a document in order, and
+/// broadcast them to every window as [`DOC_CHANGE_EVENT`]. Returns the new
+/// version of the document.
+///
+/// If an operation fails, the ones before it stay applied and are broadcast.
+/// Changes are broadcast before the document is unlocked, so they arrive in
+/// the order of their versions. A document with a path is then autosaved for
+/// crash recovery, see [`crate::autosave`].
+#[tauri::command]
+pub async fn apply_document_ops(
+    doc_id: EntityId,
+    ops: Vec<NotebookOp>,
+    origin: String,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<u64, Error> {
+    let mut result = Ok(());
+    let (version, path) = {
+        let mut doc = get_document_mut(&state, doc_id)?;
+        let mut applied = Vec::new();
+        for op in ops {
+            if let Err(err) = doc.apply(&op) {
+                result = Err(err);
+                break;
+            }
+            applied.push(op);
+        }
+        let version = doc.version();
+        if applied.is_empty() {
+            return result.map(|()| version);
+        }
+        let event = DocChangeEvent {
+            doc_id,
+            version,
+            ops: applied,
+            origin,
+        };
+        window.emit(DOC_CHANGE_EVENT, event)?;
+        (version, doc.path.clone())
+    };
+    if let Some(path) = path {
+        autosave_document(window.app_handle(), doc_id, path)?;
+    }
+    result.map(|()| version)
+}
+
+/// Record a change to a document for autosave. The snapshot is taken from the
+/// document when it is written.
+fn autosave_document<R: Runtime>(
+    app: &AppHandle<R>,
+    doc_id: EntityId,
+    path: PathBuf,
+) -> Result<(), Error> {
+    let state = app.state::<State>();
+    let doc_id = state.entities.get(doc_id, Entity::Document)?;
+    let journal = recovery_journal(app)?;
+    let app = app.clone();
+    let snapshot: SnapshotFn = Arc::new(move || {
+        let state = app.state::<State>();
+        let doc = state.documents.get(&doc_id)?;
+        Some(doc.notebook().clone())
+    });
+    state.autosaver.update(journal, path, snapshot);
+    Ok(())
+}
+
+/// Save a document to `path`, which becomes its path once written, like
+/// [`save_notebook`](super::save_notebook).
+///
+/// Returns the new modification time of the file, in milliseconds since the
+/// Unix epoch.
+#[tauri::command]
+pub async fn save_document(
+    doc_id: EntityId,
+    path: PathBuf,
+    state: tauri::State<'_, State>,
+) -> Result<u64, Error> {
+    let notebook = document_notebook(&state, doc_id)?;
+    let modified = super::save_notebook(&path.to_string_lossy(), notebook).await?;
+    // The document only moves to the new path once the file is written.
+    if let Ok(mut doc) = get_document_mut(&state, doc_id) {
+        doc.path = Some(path);
+        doc.modified = Some(modified);
+    }
+    Ok(modified)
+}
+
+/// Stop showing a document in the notebook `viewer`, such as when its tab
+/// closes. The document is dropped once nothing shows it.
+#[tauri::command]
+pub async fn close_document(
+    doc_id: EntityId,
+    viewer: String,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let doc_id = state.entities.get(doc_id, Entity::Document)?;
+    let unused = state.documents.remove_if_mut(&doc_id, |_, doc| {
+        doc.viewers.remove(&viewer);
+        doc.viewers.is_empty()
+    });
+    if unused.is_some() {
+        state.entities.remove(doc_id);
+    }
+    Ok(())
+}
+
+/// Close the documents shown in a window, such as when it is closed.
+pub fn close_window_documents<R: Runtime>(app: &AppHandle<R>, label: &str) {
+    let state = app.state::<State>();
+    state.documents.retain(|doc_id, doc| {
+        doc.viewers.retain(|_, window| window != label);
+        let unused = doc.viewers.is_empty();
+        if unused {
+            state.entities.remove(*doc_id);
+        }
+        !unused
+    });
+}
@@ -0,0 +1,62 @@
+//! Commands for exporting notebooks to other formats.
+
+use std::path::PathBuf;
+
+use tauri::{path::BaseDirectory, AppHandle, Manager, WebviewWindow};
+use tracing::info;
+
+use crate::{
+    commands::document::document_notebook,
+    entity::EntityId,
+    export::{self, slides::SlideAssets},
+    menu,
+    state::State,
+    tasks::TaskKind,
+    Error,
+};
+
+/// Export a notebook document as a reveal.js slideshow, writing it to `path`.
+///
+/// The slideshow is titled by the notebook's metadata, or by its file name,
+/// and inlines the copy of reveal.js bundled with the app. The path is
+/// remembered for the window, so it can be shown from the menu.
+#[tauri::command]
+pub async fn export_slides(
+    path: PathBuf,
+    doc_id: EntityId,
+    window: WebviewWindow,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    info!("exporting slides to {}", path.display());
+    let notebook = document_notebook(&state, doc_id)?;
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let task = state
+        .tasks
+        .start(TaskKind::Export, format!("Exporting {name}"), false);
+
+    let title = notebook.metadata.title.as_deref().unwrap_or(&name);
+    let assets_dir = app.path().resolve("slides", BaseDirectory::Resource)?;
+    let result = async {
+        let assets = SlideAssets::load(&assets_dir).await?;
+        let html = export::slides::export_slides(&notebook, title, &assets);
+        tokio::fs::write(&path, html)
+            .await
+            .map_err(Error::Filesystem)
+    }
+    .await;
+    match result {
+        Ok(()) => {
+            task.complete();
+            if let Some(mut notebook) = state.notebook_windows.get_mut(window.label()) {
+                notebook.last_export = Some(path);
+            }
+            menu::update_menu_state(&app)?;
+            Ok(())
+        }
+        Err(err) => {
+            task.fail(err.to_string());
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Commands for sharing notebooks as GitHub Gists.
+
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::{
+    gist::{self, DeviceLogin, SharedGist, SharedGists, GITHUB_TOKEN_KEY, SHARED_GISTS_FILE},
+    import::NotebookFormat,
+    secrets::{self, SecretNamespace},
+    Error,
+};
+
+/// Get the path to the record of shared gists in the app data directory.
+pub fn shared_gists_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_data_dir()?.join(SHARED_GISTS_FILE))
+}
+
+/// Start logging in to GitHub, returning the code for the user to enter.
+#[tauri::command]
+pub async fn start_github_login() -> Result<DeviceLogin, Error> {
+    gist::start_device_login().await
+}
+
+/// Wait for the user to enter the code on GitHub, then store the token.
+#[tauri::command]
+pub async fn finish_github_login(login: DeviceLogin) -> Result<(), Error> {
+    let token = gist::finish_device_login(&login).await?;
+    secrets::set_secret(SecretNamespace::GithubToken, GITHUB_TOKEN_KEY, &token)
+}
+
+/// Share a notebook as a GitHub Gist, returning its URLs.
+///
+/// Sharing the same notebook again updates its gist. Outputs are removed from
+/// the shared copy if `strip_outputs` is set.
+#[tauri::command]
+pub async fn share_gist(
+    path: &str,
+    public: bool,
+    strip_outputs: Option<bool>,
+    app: AppHandle,
+) -> Result<SharedGist, Error> {
+    let token = secrets::get_secret(SecretNamespace::GithubToken, GITHUB_TOKEN_KEY)?
+        .ok_or_else(|| Error::GithubLogin("not logged in to GitHub".into()))?;
+
+    let path = Path::new(path);
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(Error::Filesystem)?;
+    let mut notebook = NotebookFormat::from_path(path).read(&contents)?;
+    if strip_outputs.unwrap_or(false) {
+        gist::strip_outputs(&mut notebook);
+    }
+
+    let gists_path = shared_gists_path(&app)?;
+    let mut gists = SharedGists::load(&gists_path).await;
+    let shared = gist::share_notebook(&token, &mut gists, path, &notebook, public).await?;
+    gists.save(&gists_path).await?;
+    Ok(shared)
+}
@@ -0,0 +1,94 @@
+//! Commands for version control of notebooks and workspaces with git.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tauri::{ipc::Channel, Manager, WebviewWindow};
+
+use crate::{
+    backend::diff::NotebookDiff,
+    git::{CellBlame, GitHead, GitStatus, Repository},
+    Error,
+};
+
+/// How often the checked-out branch is checked for changes.
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Find the repository containing a path, failing if there is none.
+async fn repository(path: &Path) -> Result<Repository, Error> {
+    Repository::discover(path)
+        .await?
+        .ok_or_else(|| Error::Git(format!("{} is not in a git repository", path.display())))
+}
+
+/// Get the status of the repository containing a notebook or workspace,
+/// limited to files under that path. Returns `null` if it isn't in one.
+#[tauri::command]
+pub async fn git_status(path: PathBuf) -> Result<Option<GitStatus>, Error> {
+    match Repository::discover(&path).await? {
+        Some(repo) => Ok(Some(repo.status(Some(&path)).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Compare a notebook cell by cell, showing either its staged or unstaged
+/// changes.
+#[tauri::command]
+pub async fn git_diff_notebook(path: PathBuf, staged: bool) -> Result<NotebookDiff, Error> {
+    repository(&path).await?.diff_notebook(&path, staged).await
+}
+
+/// Commit changes in the repository containing `path`, first staging the
+/// given files. Returns the hash of the new commit.
+#[tauri::command]
+pub async fn git_commit(
+    path: PathBuf,
+    message: String,
+    paths: Vec<PathBuf>,
+) -> Result<String, Error> {
+    repository(&path).await?.commit(&message, &paths).await
+}
+
+/// Find the commit that last changed each cell of a notebook.
+#[tauri::command]
+pub async fn git_blame_cells(path: PathBuf) -> Result<Vec<CellBlame>, Error> {
+    repository(&path).await?.blame_cells(&path).await
+}
+
+/// Send the checked-out branch and commit of the repository containing
+/// `path`, then send them again whenever the branch is switched, such as by
+/// `git checkout` in a terminal.
+///
+/// This runs until the window that asked for it is closed, or the channel
+/// stops accepting messages.
+#[tauri::command]
+pub async fn watch_git_head(
+    path: PathBuf,
+    on_change: Channel<GitHead>,
+    window: WebviewWindow,
+) -> Result<(), Error> {
+    let repo = repository(&path).await?;
+    let head_file = repo.head_file().await?;
+    let mut last = tokio::fs::read(&head_file).await.ok();
+    if on_change.send(repo.head().await?).is_err() {
+        return Ok(());
+    }
+    loop {
+        tokio::time::sleep(HEAD_POLL_INTERVAL).await;
+        // Sending to a closed window doesn't always fail, so check for it.
+        let app = window.app_handle();
+        if app.get_webview_window(window.label()).is_none() {
+            return Ok(());
+        }
+        let current = tokio::fs::read(&head_file).await.ok();
+        if current == last {
+            continue;
+        }
+        last = current;
+        if on_change.send(repo.head().await?).is_err() {
+            return Ok(());
+        }
+    }
+}
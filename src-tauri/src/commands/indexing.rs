@@ -0,0 +1,134 @@
+//! Making notebooks in workspaces searchable from Spotlight or Windows Search.
+//!
+//! This is off unless the `search_indexing` setting is enabled. On macOS, the
+//! Spotlight import extension in the app bundle describes each notebook, and
+//! workspaces are imported with `mdimport` so that notebooks already on disk
+//! are indexed. On Windows, notebooks are registered with the plain-text filter
+//! of Windows Search, which indexes them if they are in an indexed location,
+//! like the user's profile folder.
+//!
+//! The extension is turned off with `pluginkit`, and the Windows registration
+//! removed, whenever the setting is disabled.
+
+use std::path::Path;
+
+use tauri::{async_runtime, AppHandle, Manager, Runtime};
+use tracing::{info, warn};
+
+use crate::{state::State, Error};
+
+/// Class ID of the plain-text filter of Windows Search.
+#[cfg(windows)]
+const PLAIN_TEXT_FILTER: &str = "{5e941d80-bf96-11cd-b579-08002b30bfeb}";
+
+/// Registry key that chooses the Windows Search filter for notebooks.
+#[cfg(windows)]
+const PERSISTENT_HANDLER_KEY: &str = r"HKCU\Software\Classes\.ipynb\PersistentHandler";
+
+/// Bundle ID of the Spotlight import extension.
+#[cfg(target_os = "macos")]
+const IMPORTER_ID: &str = "com.ekzhang.jute.index";
+
+/// Turn the Spotlight importer or the Windows Search filter on or off to
+/// match the `search_indexing` setting.
+///
+/// This runs at startup, and whenever the setting changes with `reindex` set,
+/// which also indexes recent workspaces if the setting is on.
+pub fn apply_indexing_setting<R: Runtime>(app: &AppHandle<R>, reindex: bool) {
+    let enabled = app
+        .state::<State>()
+        .settings
+        .read()
+        .unwrap()
+        .search_indexing;
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = register(enabled).await {
+            warn!("could not update search indexing: {err}");
+        }
+        if enabled && reindex {
+            index_recent_workspaces(&app);
+        }
+    });
+}
+
+/// Register or unregister Jute with the OS search indexer.
+#[allow(unused_variables)]
+async fn register(enabled: bool) -> Result<(), Error> {
+    #[cfg(target_os = "macos")]
+    {
+        let election = if enabled { "use" } else { "ignore" };
+        run("pluginkit", &["-e", election, "-i", IMPORTER_ID]).await?;
+    }
+
+    #[cfg(windows)]
+    {
+        if enabled {
+            let args = [
+                "add",
+                PERSISTENT_HANDLER_KEY,
+                "/ve",
+                "/d",
+                PLAIN_TEXT_FILTER,
+                "/f",
+            ];
+            run("reg", &args).await?;
+        } else if run("reg", &["query", PERSISTENT_HANDLER_KEY]).await.is_ok() {
+            run("reg", &["delete", PERSISTENT_HANDLER_KEY, "/f"]).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Index the notebooks in a workspace folder, if search indexing is enabled.
+pub async fn index_workspace<R: Runtime>(app: &AppHandle<R>, dir: &Path) -> Result<(), Error> {
+    if !app
+        .state::<State>()
+        .settings
+        .read()
+        .unwrap()
+        .search_indexing
+    {
+        return Ok(());
+    }
+    info!("indexing notebooks in {}", dir.display());
+
+    // Windows Search finds notebooks in indexed locations by itself.
+    #[cfg(target_os = "macos")]
+    run("mdimport", &[dir]).await?;
+
+    Ok(())
+}
+
+/// Index the notebooks in all recently opened workspaces in the background,
+/// such as after search indexing is enabled.
+fn index_recent_workspaces<R: Runtime>(app: &AppHandle<R>) {
+    let folders = app.state::<State>().recent_folders.read().unwrap().clone();
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        for dir in folders.list() {
+            if let Err(err) = index_workspace(&app, dir).await {
+                warn!("could not index {}: {err}", dir.display());
+            }
+        }
+    });
+}
+
+/// Run an indexing command, failing if it exits unsuccessfully.
+#[cfg(any(target_os = "macos", windows))]
+async fn run(program: &str, args: &[impl AsRef<std::ffi::OsStr>]) -> Result<(), Error> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(Error::Subprocess)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Subprocess(std::io::Error::other(format!(
+            "{program} failed: {}",
+            stderr.trim()
+        ))));
+    }
+    Ok(())
+}
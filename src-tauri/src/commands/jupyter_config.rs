@@ -0,0 +1,42 @@
+//! Commands for importing configuration from an existing Jupyter install.
+
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::info;
+
+use crate::{
+    backend::local::environment,
+    commands::{servers::servers_path, settings::settings_path},
+    jupyter_config::{ImportSummary, JupyterImport},
+    state::State,
+    Error,
+};
+
+/// Import servers and settings from the user's Jupyter config, persisting
+/// them. Existing Jute settings are not overridden.
+pub async fn import_jupyter_config<R: Runtime>(app: &AppHandle<R>) -> Result<ImportSummary, Error> {
+    let import = JupyterImport::read(
+        Path::new(&environment::config_dir()),
+        Path::new(&environment::runtime_dir()),
+    )
+    .await;
+
+    let state = app.state::<State>();
+    let (settings, servers, summary) = {
+        let mut settings = state.settings.write().unwrap();
+        let mut servers = state.servers.write().unwrap();
+        let summary = import.apply(&mut settings, &mut servers, &state.entities);
+        (settings.clone(), servers.clone(), summary)
+    };
+    settings.save(&settings_path(app)?).await?;
+    servers.save(&servers_path(app)?).await?;
+    info!(?summary, "imported jupyter configuration");
+    Ok(summary)
+}
+
+/// Re-run the import of Jupyter configuration, e.g., from the settings page.
+#[tauri::command]
+pub async fn import_jupyter_settings(app: AppHandle) -> Result<ImportSummary, Error> {
+    import_jupyter_config(&app).await
+}
@@ -0,0 +1,65 @@
+//! Commands for listing and customizing keyboard shortcuts.
+
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::{
+    keymap::{Action, KeyBinding, Keymap, ShortcutGroup, KEYMAP_FILE},
+    state::State,
+    Error,
+};
+
+/// Get the path to the keymap overrides file in the app config directory.
+pub fn keymap_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_config_dir()?.join(KEYMAP_FILE))
+}
+
+/// Persist a new keymap and rebuild the menu so accelerators are updated.
+async fn apply_keymap(keymap: Keymap, app: &AppHandle) -> Result<Vec<KeyBinding>, Error> {
+    keymap.save(&keymap_path(app)?).await?;
+    let bindings = keymap.bindings();
+    *app.state::<State>().keymap.write().unwrap() = keymap;
+    app.set_menu(crate::menu::build_menu(app)?)?;
+    Ok(bindings)
+}
+
+/// List the effective keyboard shortcuts for all actions.
+#[tauri::command]
+pub async fn list_keybindings(state: tauri::State<'_, State>) -> Result<Vec<KeyBinding>, Error> {
+    Ok(state.keymap.read().unwrap().bindings())
+}
+
+/// List the bound keyboard shortcuts grouped by category, for the reference.
+#[tauri::command]
+pub async fn list_shortcuts(state: tauri::State<'_, State>) -> Result<Vec<ShortcutGroup>, Error> {
+    Ok(state.keymap.read().unwrap().shortcuts())
+}
+
+/// Change the shortcut for an action, or unbind it if `accelerator` is null.
+///
+/// Conflicting bindings are allowed, but they are reported in the returned
+/// list of bindings so the user can resolve them.
+#[tauri::command]
+pub async fn update_keybinding(
+    action: Action,
+    accelerator: Option<String>,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<Vec<KeyBinding>, Error> {
+    let mut keymap = state.keymap.read().unwrap().clone();
+    keymap.set(action, accelerator.as_deref())?;
+    apply_keymap(keymap, &app).await
+}
+
+/// Reset the shortcut for an action, or all shortcuts if `action` is null.
+#[tauri::command]
+pub async fn reset_keybindings(
+    action: Option<Action>,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<Vec<KeyBinding>, Error> {
+    let mut keymap = state.keymap.read().unwrap().clone();
+    keymap.reset(action);
+    apply_keymap(keymap, &app).await
+}
@@ -0,0 +1,12 @@
+//! Commands for retrieving application logs, e.g., to attach to bug reports.
+
+use crate::{state::State, Error};
+
+/// Return the last `n` lines of the application logs.
+#[tauri::command]
+pub async fn get_recent_logs(n: usize, state: tauri::State<'_, State>) -> Result<String, Error> {
+    let Some(logger) = state.logger.get() else {
+        return Ok(String::new());
+    };
+    Ok(logger.recent_lines(n).await?.join("\n"))
+}
@@ -0,0 +1,198 @@
+//! Commands for the kernel monitor window, which lists running kernels along
+//! with the resources they use.
+
+use std::{cmp::Reverse, path::PathBuf, time::Duration};
+
+use serde::Serialize;
+use sysinfo::{Pid, System};
+use tauri::{async_runtime, ipc::Channel, AppHandle, Emitter, Runtime};
+use ts_rs::TS;
+
+use crate::{
+    backend::wire_protocol::KernelConnection, entity::EntityId, menu::MenuEvent, state::State,
+    Error,
+};
+
+/// How often resource usage is sent to the kernel monitor.
+const USAGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of the event emitted to the frontend when a kernel stops or resumes
+/// answering heartbeats.
+pub const KERNEL_LIVENESS_EVENT: &str = "kernel-liveness";
+
+/// Whether a kernel process is still running.
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum KernelStatus {
+    /// The kernel process is running.
+    Running,
+
+    /// The kernel process is running, but not answering heartbeats.
+    Unresponsive,
+
+    /// The kernel process has exited, such as after crashing.
+    Exited,
+}
+
+crate::export_bindings!(KernelStatus);
+
+/// A running kernel, returned by [`list_running_kernels`].
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct RunningKernel {
+    /// ID of the kernel.
+    #[ts(type = "string")]
+    pub kernel_id: EntityId,
+
+    /// Display name of the kernel spec, like "Python 3".
+    pub display_name: String,
+
+    /// Notebooks attached to the kernel, which are `null` if unsaved.
+    pub notebooks: Vec<Option<PathBuf>>,
+
+    /// Whether the kernel process is still running.
+    pub status: KernelStatus,
+
+    /// Seconds since the kernel was started.
+    #[ts(type = "number")]
+    pub uptime_secs: u64,
+}
+
+crate::export_bindings!(RunningKernel);
+
+/// Resources used by a kernel process, sent by [`watch_kernel_usage`].
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct KernelUsage {
+    /// ID of the kernel.
+    #[ts(type = "string")]
+    pub kernel_id: EntityId,
+
+    /// CPU usage as a percentage of one core, which may be over 100.
+    pub cpu_percent: f32,
+
+    /// Resident memory in bytes.
+    #[ts(type = "number")]
+    pub memory_bytes: u64,
+
+    /// Seconds since the kernel was started.
+    #[ts(type = "number")]
+    pub uptime_secs: u64,
+}
+
+crate::export_bindings!(KernelUsage);
+
+/// Payload of [`KERNEL_LIVENESS_EVENT`].
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct KernelLiveness {
+    /// ID of the kernel.
+    #[ts(type = "string")]
+    pub kernel_id: EntityId,
+
+    /// Whether the kernel answered its last heartbeat.
+    pub alive: bool,
+}
+
+crate::export_bindings!(KernelLiveness);
+
+/// Emit [`KERNEL_LIVENESS_EVENT`] whenever a kernel stops or resumes answering
+/// heartbeats, until its connection is closed.
+///
+/// This lets the frontend show that a kernel is unresponsive, rather than
+/// waiting for output that may never come.
+pub fn forward_kernel_liveness<R: Runtime>(
+    app: &AppHandle<R>,
+    kernel_id: EntityId,
+    conn: &KernelConnection,
+) {
+    let mut alive_rx = conn.watch_alive();
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        while alive_rx.changed().await.is_ok() {
+            let alive = *alive_rx.borrow_and_update();
+            _ = app.emit(KERNEL_LIVENESS_EVENT, KernelLiveness { kernel_id, alive });
+        }
+    });
+}
+
+/// List all running kernels with the notebooks attached to them.
+#[tauri::command]
+pub async fn list_running_kernels(
+    state: tauri::State<'_, State>,
+) -> Result<Vec<RunningKernel>, Error> {
+    let mut kernels: Vec<RunningKernel> = state
+        .kernels
+        .iter_mut()
+        .map(|mut entry| {
+            let kernel_id = *entry.key();
+            let kernel = entry.value_mut();
+            let notebooks = state
+                .notebook_windows
+                .iter()
+                .filter(|notebook| notebook.kernel_id == Some(kernel_id))
+                .map(|notebook| notebook.path.clone())
+                .collect();
+            RunningKernel {
+                kernel_id,
+                display_name: kernel.spec().display_name.clone(),
+                notebooks,
+                status: if !kernel.is_alive() {
+                    KernelStatus::Exited
+                } else if !kernel.conn().alive() {
+                    KernelStatus::Unresponsive
+                } else {
+                    KernelStatus::Running
+                },
+                uptime_secs: kernel.started_at().elapsed().as_secs(),
+            }
+        })
+        .collect();
+    kernels.sort_by_key(|kernel| Reverse(kernel.uptime_secs));
+    Ok(kernels)
+}
+
+/// Stream the resource usage of all running kernels, every second.
+///
+/// This runs until the channel is closed, such as when the kernel monitor
+/// window is closed.
+#[tauri::command]
+pub async fn watch_kernel_usage(
+    on_usage: Channel<Vec<KernelUsage>>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    // CPU usage is measured between refreshes, so the system is kept around.
+    let mut system = System::new();
+    loop {
+        let processes: Vec<_> = state
+            .kernels
+            .iter()
+            .map(|kernel| (*kernel.key(), kernel.pid(), kernel.started_at()))
+            .collect();
+        let mut usage = Vec::new();
+        for (kernel_id, pid, started_at) in processes {
+            let pid = pid.map(Pid::from_u32);
+            if let Some(pid) = pid {
+                system.refresh_process(pid);
+            }
+            let process = pid.and_then(|pid| system.process(pid));
+            usage.push(KernelUsage {
+                kernel_id,
+                cpu_percent: process.map_or(0.0, |p| p.cpu_usage()),
+                memory_bytes: process.map_or(0, |p| p.memory()),
+                uptime_secs: started_at.elapsed().as_secs(),
+            });
+        }
+        if on_usage.send(usage).is_err() {
+            return Ok(());
+        }
+        tokio::time::sleep(USAGE_INTERVAL).await;
+    }
+}
+
+/// Interrupt, restart, or shut down a kernel from the kernel monitor.
+#[tauri::command]
+pub async fn kernel_action(
+    kernel_id: EntityId,
+    action: MenuEvent,
+    app: AppHandle,
+) -> Result<(), Error> {
+    crate::menu::run_kernel_action(&app, kernel_id, action).await
+}
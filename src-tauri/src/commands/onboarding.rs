@@ -0,0 +1,78 @@
+//! Commands powering the first-run onboarding flow.
+//!
+//! Onboarding detects what the user already has installed, checks that the
+//! bundled `uv` works, and can create a managed default environment, so that
+//! new users can run a cell without touching a terminal.
+
+use tauri::AppHandle;
+use tauri_plugin_shell::ShellExt;
+
+use crate::{
+    commands::{settings::modify_settings, venv::venv_create},
+    entity::EntityId,
+    onboarding::{self, JupyterInstall, PythonInstall, UvStatus},
+    state::State,
+    Error,
+};
+
+/// Find Python interpreters installed on the system.
+#[tauri::command]
+pub async fn detect_pythons() -> Result<Vec<PythonInstall>, Error> {
+    Ok(onboarding::find_system_pythons().await)
+}
+
+/// Find an existing Jupyter install and its kernels.
+#[tauri::command]
+pub async fn detect_jupyter() -> Result<JupyterInstall, Error> {
+    Ok(onboarding::find_jupyter().await)
+}
+
+/// Check that the bundled `uv` sidecar can be run.
+#[tauri::command]
+pub async fn verify_uv(app: AppHandle) -> Result<UvStatus, Error> {
+    let output = match app.shell().sidecar("uv")?.arg("--version").output().await {
+        Ok(output) => output,
+        Err(err) => {
+            return Ok(UvStatus {
+                error: Some(err.to_string()),
+                ..Default::default()
+            })
+        }
+    };
+    if output.status.success() {
+        // Output looks like `uv 0.5.11 (c4d0caaee 2024-12-19)`.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(UvStatus {
+            available: true,
+            version: stdout.split_whitespace().nth(1).map(String::from),
+            error: None,
+        })
+    } else {
+        Ok(UvStatus {
+            error: Some(String::from_utf8_lossy(&output.stderr).trim().into()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Create a managed virtual environment and make it the default for kernels.
+#[tauri::command]
+pub async fn create_default_environment(
+    python_version: &str,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<EntityId, Error> {
+    let venv_id = venv_create(python_version, app.clone(), state).await?;
+    modify_settings(&app, |settings| {
+        settings.default_environment = Some(venv_id);
+    })
+    .await?;
+    Ok(venv_id)
+}
+
+/// Record that the user has finished onboarding.
+#[tauri::command]
+pub async fn complete_onboarding(app: AppHandle) -> Result<(), Error> {
+    modify_settings(&app, |settings| settings.onboarding_completed = true).await?;
+    Ok(())
+}
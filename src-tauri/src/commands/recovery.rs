@@ -0,0 +1,58 @@
+//! Commands for journaling and autosaving unsaved changes, and recovering them
+//! after a crash.
+
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::{
+    recovery::{Recovery, RecoveryJournal},
+    state::State,
+    Error,
+};
+
+/// Get the recovery journals, stored in the app data directory.
+pub fn recovery_journal<R: Runtime>(app: &AppHandle<R>) -> Result<RecoveryJournal, Error> {
+    Ok(RecoveryJournal::new(
+        app.path().app_data_dir()?.join("recovery"),
+    ))
+}
+
+/// Record an unsaved edit to a cell, called by the frontend as the user types.
+#[tauri::command]
+pub async fn record_cell_edit(
+    path: &str,
+    cell_id: &str,
+    source: &str,
+    app: AppHandle,
+) -> Result<(), Error> {
+    recovery_journal(&app)?
+        .record(Path::new(path), cell_id, source)
+        .await
+}
+
+/// List notebooks with unsaved edits newer than their saved files.
+///
+/// This is called on launch to offer recovery after a crash.
+#[tauri::command]
+pub async fn list_recoverable_notebooks(app: AppHandle) -> Result<Vec<Recovery>, Error> {
+    recovery_journal(&app)?.list().await
+}
+
+/// Get unsaved edits for a notebook when it is opened, if there are any.
+#[tauri::command]
+pub async fn get_notebook_recovery(path: &str, app: AppHandle) -> Result<Option<Recovery>, Error> {
+    recovery_journal(&app)?.recover(Path::new(path)).await
+}
+
+/// Discard the journal and snapshot for a notebook, after it is saved or
+/// recovery declined.
+#[tauri::command]
+pub async fn discard_recovery(
+    path: &str,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    state.autosaver.cancel(Path::new(path));
+    recovery_journal(&app)?.clear(Path::new(path)).await
+}
@@ -0,0 +1,73 @@
+//! Serving libraries for rendering rich outputs to the webview.
+
+use std::io;
+
+use tauri::{
+    async_runtime,
+    http::{header, Request, Response, StatusCode},
+    AppHandle, Manager, Runtime, UriSchemeContext, UriSchemeResponder,
+};
+use tracing::warn;
+
+use crate::{
+    renderers::{self, RendererCache},
+    state::State,
+    Error,
+};
+
+/// Get the cache of renderer libraries, stored in the app data directory.
+pub fn renderer_cache<R: Runtime>(app: &AppHandle<R>) -> Result<RendererCache, Error> {
+    Ok(RendererCache::new(
+        app.path().app_data_dir()?.join("renderers"),
+    ))
+}
+
+/// Download the math engine chosen in settings in the background, if it isn't
+/// cached yet, so that math renders offline.
+pub fn prefetch_math_renderer<R: Runtime>(app: &AppHandle<R>) {
+    let engine = app.state::<State>().settings.read().unwrap().math_renderer;
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let result = match renderer_cache(&app) {
+            Ok(cache) => cache.prefetch(engine.asset()).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            warn!("could not download math renderer: {err}");
+        }
+    });
+}
+
+/// Handle a request for a library over the `renderer://` protocol.
+///
+/// Responds with 404 if the library is unknown or can't be loaded, so that the
+/// frontend falls back to another MIME type in the output.
+pub fn handle_request<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    let path = request.uri().path().trim_start_matches('/').to_string();
+    async_runtime::spawn(async move {
+        let contents = match renderers::find_asset(&path) {
+            Some((asset, file)) => match renderer_cache(&app) {
+                Ok(cache) => cache.get(asset, file).await,
+                Err(err) => Err(err),
+            },
+            None => Err(Error::Filesystem(io::ErrorKind::NotFound.into())),
+        };
+        let response = match contents {
+            Ok(contents) => Response::builder()
+                .header(header::CONTENT_TYPE, renderers::content_type(&path))
+                .body(contents),
+            Err(err) => {
+                warn!("could not load renderer file {path}: {err}");
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Vec::new())
+            }
+        };
+        responder.respond(response.expect("renderer response should be valid"));
+    });
+}
@@ -0,0 +1,106 @@
+//! Commands for showing files in the OS file manager, or opening a terminal.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::{commands::venv::venv_path, entity::EntityId, Error};
+
+/// Terminal emulators tried in order on Linux, after the `$TERMINAL` variable.
+#[cfg(not(any(target_os = "macos", windows)))]
+const LINUX_TERMINALS: &[&str] = &[
+    "x-terminal-emulator",
+    "gnome-terminal",
+    "konsole",
+    "xfce4-terminal",
+    "kitty",
+    "alacritty",
+    "xterm",
+];
+
+/// Show a file or directory selected in Finder, Explorer, or the file manager.
+pub fn reveal<R: Runtime>(app: &AppHandle<R>, path: &Path) -> Result<(), Error> {
+    Ok(app.opener().reveal_item_in_dir(path)?)
+}
+
+/// Open the system terminal in a directory.
+pub fn spawn_terminal(directory: &Path) -> Result<(), Error> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.args(["-a", "Terminal"]).arg(directory);
+        command
+    };
+
+    #[cfg(windows)]
+    let mut command = {
+        // `start` opens the shell in a new console window.
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", "cmd"]);
+        command
+    };
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = {
+        let mut terminals = std::env::var("TERMINAL")
+            .ok()
+            .filter(|name| !name.is_empty())
+            .into_iter()
+            .chain(
+                LINUX_TERMINALS
+                    .iter()
+                    .filter(|name| which(name))
+                    .map(|name| name.to_string()),
+            );
+        let Some(terminal) = terminals.next() else {
+            return Err(Error::Subprocess(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no terminal emulator was found",
+            )));
+        };
+        Command::new(terminal)
+    };
+
+    command
+        .current_dir(directory)
+        .spawn()
+        .map_err(Error::Subprocess)?;
+    Ok(())
+}
+
+/// Check if a program is on the `PATH`.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn which(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(name).is_file()))
+}
+
+/// The directory that a notebook is in, for opening a terminal.
+pub fn notebook_directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Show a file, such as a notebook or an exported file, in the file manager.
+#[tauri::command]
+pub async fn reveal_path(path: PathBuf, app: AppHandle) -> Result<(), Error> {
+    reveal(&app, &path)
+}
+
+/// Show the directory of a managed virtual environment in the file manager.
+#[tauri::command]
+pub async fn reveal_venv(venv_id: EntityId, app: AppHandle) -> Result<(), Error> {
+    reveal(&app, &venv_path(&app, venv_id)?)
+}
+
+/// Open the system terminal in the directory containing a notebook.
+#[tauri::command]
+pub async fn open_terminal(path: PathBuf) -> Result<(), Error> {
+    spawn_terminal(&notebook_directory(&path))
+}
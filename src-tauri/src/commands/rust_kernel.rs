@@ -0,0 +1,55 @@
+//! Commands for setting up the evcxr kernel for Rust notebooks.
+
+use std::io;
+
+use crate::{
+    backend::local::evcxr::{self, RustKernelStatus},
+    state::State,
+    tasks::{TaskHandle, TaskKind},
+    Error,
+};
+
+/// Find the tools for running Rust notebooks, to show what needs setting up.
+#[tauri::command]
+pub async fn rust_kernel_status() -> Result<RustKernelStatus, Error> {
+    Ok(evcxr::status().await)
+}
+
+/// Install `evcxr_jupyter` if needed and register its kernel spec, so that
+/// Rust can be picked as a kernel. This needs a Rust toolchain.
+#[tauri::command]
+pub async fn setup_rust_kernel(state: tauri::State<'_, State>) -> Result<RustKernelStatus, Error> {
+    let task = state
+        .tasks
+        .start(TaskKind::InstallKernel, "Setting up the Rust kernel", false);
+    match setup(&task).await {
+        Ok(status) => {
+            task.complete();
+            Ok(status)
+        }
+        Err(err) => {
+            task.fail(err.to_string());
+            Err(err)
+        }
+    }
+}
+
+async fn setup(task: &TaskHandle) -> Result<RustKernelStatus, Error> {
+    let status = evcxr::status().await;
+    let binary = match (status.evcxr, status.cargo) {
+        (Some(binary), _) => binary,
+        (None, Some(cargo)) => {
+            task.set_progress(0.1, "Compiling evcxr_jupyter, which takes a few minutes");
+            evcxr::install(&cargo).await?
+        }
+        (None, None) => {
+            return Err(Error::Subprocess(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Rust is not installed, see https://rustup.rs to install it",
+            )));
+        }
+    };
+    task.set_progress(0.9, "Registering the kernel");
+    evcxr::register(&binary).await?;
+    Ok(evcxr::status().await)
+}
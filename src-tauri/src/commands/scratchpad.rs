@@ -0,0 +1,89 @@
+//! Commands for the scratchpad, a notebook summoned by a system-wide shortcut.
+//!
+//! The scratchpad is a quick place to run code without opening a file. Its
+//! kernel is started in the background as soon as the shortcut is registered,
+//! so the first cell runs right away. Closing the window keeps the kernel
+//! running, and it is reused the next time the scratchpad is opened.
+
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tracing::{info, warn};
+
+use crate::{
+    entity::EntityId,
+    state::State,
+    window::{self, SCRATCHPAD_LABEL},
+    Error,
+};
+
+/// Register the scratchpad shortcut from settings, replacing any previous one.
+///
+/// If a shortcut is set, the scratchpad's kernel is also started in the
+/// background.
+pub fn register_shortcut(app: &AppHandle) -> Result<(), Error> {
+    let shortcuts = app.global_shortcut();
+    shortcuts.unregister_all()?;
+    let accelerator = app
+        .state::<State>()
+        .settings
+        .read()
+        .unwrap()
+        .scratchpad_shortcut
+        .clone();
+    let Some(accelerator) = accelerator else {
+        return Ok(());
+    };
+    info!("registering scratchpad shortcut {accelerator}");
+    shortcuts.on_shortcut(accelerator.as_str(), |app, _shortcut, event| {
+        if event.state() == ShortcutState::Pressed {
+            if let Err(err) = toggle_scratchpad(app) {
+                warn!("could not open scratchpad: {err}");
+            }
+        }
+    })?;
+    warm_kernel(app);
+    Ok(())
+}
+
+/// Open the scratchpad, or close it if it is already focused.
+pub fn toggle_scratchpad(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(SCRATCHPAD_LABEL) {
+        if window.is_focused()? {
+            return window.close();
+        }
+        window.unminimize()?;
+    }
+    window::open_scratchpad(app)?;
+    Ok(())
+}
+
+/// Start the scratchpad's kernel in the background, if it isn't running.
+fn warm_kernel(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = scratchpad_kernel(&app).await {
+            warn!("could not start scratchpad kernel: {err}");
+        }
+    });
+}
+
+/// Get the scratchpad's kernel, starting it if it isn't running.
+async fn scratchpad_kernel(app: &AppHandle) -> Result<EntityId, Error> {
+    let state = app.state::<State>();
+    // Held while the kernel starts, so that only one is started at a time.
+    let mut kernel_id = state.scratchpad_kernel.lock().await;
+    if let Some(id) = *kernel_id {
+        if state.kernels.contains_key(&id) {
+            return Ok(id);
+        }
+    }
+    let id = super::start_kernel(None, None, app.clone(), app.state()).await?;
+    *kernel_id = Some(id);
+    Ok(id)
+}
+
+/// Return the kernel for the scratchpad to attach to.
+#[tauri::command]
+pub async fn get_scratchpad_kernel(app: AppHandle) -> Result<EntityId, Error> {
+    scratchpad_kernel(&app).await
+}
@@ -0,0 +1,63 @@
+//! Commands for managing the registry of remote Jupyter servers.
+
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::{
+    entity::{Entity, EntityId},
+    secrets::{self, SecretNamespace},
+    servers::{JupyterServer, SERVERS_FILE},
+    state::State,
+    Error,
+};
+
+/// Get the path to the server registry file in the app config directory.
+pub fn servers_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_config_dir()?.join(SERVERS_FILE))
+}
+
+/// List the saved Jupyter servers.
+#[tauri::command]
+pub async fn list_servers(state: tauri::State<'_, State>) -> Result<Vec<JupyterServer>, Error> {
+    Ok(state.servers.read().unwrap().list().to_vec())
+}
+
+/// Save a Jupyter server, or update the existing server with the same URL.
+#[tauri::command]
+pub async fn add_server(
+    name: &str,
+    url: &str,
+    token: Option<&str>,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<JupyterServer, Error> {
+    let (server, servers) = {
+        let mut servers = state.servers.write().unwrap();
+        let server = servers.add(&state.entities, name, url, token)?;
+        (server, servers.clone())
+    };
+    servers.save(&servers_path(&app)?).await?;
+    Ok(server)
+}
+
+/// Remove a saved Jupyter server, returning whether it was found.
+#[tauri::command]
+pub async fn remove_server(
+    server_id: EntityId,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<bool, Error> {
+    let server_id = server_id.expect_kind(Entity::Server)?;
+    let (removed, servers) = {
+        let mut servers = state.servers.write().unwrap();
+        let removed = servers.remove(&state.entities, server_id);
+        (removed, servers.clone())
+    };
+    servers.save(&servers_path(&app)?).await?;
+    if removed {
+        let token = (server_id.to_string(), None);
+        secrets::store_secrets(SecretNamespace::ServerToken, vec![token]).await;
+    }
+    Ok(removed)
+}
@@ -0,0 +1,71 @@
+//! Commands for reading and updating user settings.
+
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::{
+    commands::{indexing, renderers, scratchpad},
+    settings::{Settings, SETTINGS_FILE},
+    state::State,
+    Error,
+};
+
+/// Get the path to the settings file in the app config directory.
+pub fn settings_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_config_dir()?.join(SETTINGS_FILE))
+}
+
+/// Return the current user settings.
+#[tauri::command]
+pub async fn get_settings(state: tauri::State<'_, State>) -> Result<Settings, Error> {
+    Ok(state.settings.read().unwrap().clone())
+}
+
+/// Apply a change to the user settings and persist them.
+pub async fn modify_settings<R: Runtime>(
+    app: &AppHandle<R>,
+    f: impl FnOnce(&mut Settings),
+) -> Result<Settings, Error> {
+    let settings = {
+        let state = app.state::<State>();
+        let mut settings = state.settings.write().unwrap();
+        f(&mut settings);
+        settings.clone()
+    };
+    settings.save(&settings_path(app)?).await?;
+    Ok(settings)
+}
+
+/// Replace the user settings, persisting them and applying any changes.
+#[tauri::command]
+pub async fn update_settings(
+    settings: Settings,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    settings.save(&settings_path(&app)?).await?;
+    if let Some(logger) = state.logger.get() {
+        logger.set_level(settings.log_level);
+    }
+    let (shortcut_changed, indexing_changed, math_changed) = {
+        let mut current = state.settings.write().unwrap();
+        let changed = (
+            current.scratchpad_shortcut != settings.scratchpad_shortcut,
+            current.search_indexing != settings.search_indexing,
+            current.math_renderer != settings.math_renderer,
+        );
+        *current = settings;
+        changed
+    };
+    if shortcut_changed {
+        scratchpad::register_shortcut(&app)?;
+    }
+    if indexing_changed {
+        indexing::apply_indexing_setting(&app, true);
+    }
+    if math_changed {
+        renderers::prefetch_math_renderer(&app);
+    }
+    Ok(())
+}
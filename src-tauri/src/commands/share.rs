@@ -0,0 +1,81 @@
+//! Commands for sharing a notebook read-only on the local network.
+
+use tauri::{AppHandle, Manager, Runtime, WebviewWindow};
+
+use crate::{
+    backend::notebook::NotebookRoot, export::live::render_cells, menu, share::ShareServer,
+    state::State, Error,
+};
+
+/// Start sharing the window's notebook, returning the URL to give out.
+///
+/// If the window is already sharing, its notebook is updated and the same URL
+/// is returned.
+#[tauri::command]
+pub async fn start_share(
+    notebook: NotebookRoot,
+    window: WebviewWindow,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<String, Error> {
+    let cells = render_cells(&notebook);
+    if let Some(server) = state.share_servers.get(window.label()) {
+        server.update(cells);
+        return Ok(server.url().into());
+    }
+
+    let path = state
+        .notebook_windows
+        .get(window.label())
+        .and_then(|notebook| notebook.path.clone());
+    let title = notebook
+        .metadata
+        .title
+        .clone()
+        .unwrap_or_else(|| match path {
+            Some(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+            None => "Untitled".into(),
+        });
+    let server = ShareServer::start(title, cells).await?;
+    let url = server.url().to_string();
+    state.share_servers.insert(window.label().into(), server);
+    if let Some(mut notebook) = state.notebook_windows.get_mut(window.label()) {
+        notebook.share_url = Some(url.clone());
+    }
+    menu::update_menu_state(&app)?;
+    Ok(url)
+}
+
+/// Send the latest version of the window's notebook to pages following it.
+#[tauri::command]
+pub async fn update_share(
+    notebook: NotebookRoot,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    if let Some(server) = state.share_servers.get(window.label()) {
+        server.update(render_cells(&notebook));
+    }
+    Ok(())
+}
+
+/// Stop sharing the window's notebook.
+#[tauri::command]
+pub async fn stop_share(window: WebviewWindow, app: AppHandle) -> Result<(), Error> {
+    stop_window_share(&app, window.label());
+    menu::update_menu_state(&app)?;
+    Ok(())
+}
+
+/// Stop sharing a window's notebook, such as when the window is closed.
+pub fn stop_window_share<R: Runtime>(app: &AppHandle<R>, label: &str) {
+    let state = app.state::<State>();
+    state.share_servers.remove(label);
+    if let Some(mut notebook) = state.notebook_windows.get_mut(label) {
+        notebook.share_url = None;
+    }
+}
@@ -0,0 +1,188 @@
+//! Commands for inspecting and cancelling background tasks.
+
+use std::sync::atomic::Ordering;
+
+use tauri::{
+    async_runtime,
+    window::{ProgressBarState, ProgressBarStatus},
+    AppHandle, Emitter, Manager, Runtime, WebviewWindow,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use crate::{
+    entity::{Entity, EntityId},
+    state::State,
+    tasks::{TaskInfo, TaskKind, TaskStatus, TASK_EVENT},
+    Error,
+};
+
+/// Forward all task updates to the frontend as events, and show them on the
+/// Dock icon or taskbar.
+pub fn forward_task_events<R: Runtime>(app: &AppHandle<R>) {
+    let mut rx = app.state::<State>().tasks.subscribe();
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(info) => {
+                    let in_background = !app
+                        .webview_windows()
+                        .values()
+                        .any(|window| window.is_focused().unwrap_or(false));
+                    if info.status != TaskStatus::Running && info.failures > 0 && in_background {
+                        let state = app.state::<State>();
+                        state
+                            .unseen_task_failures
+                            .fetch_add(info.failures, Ordering::Relaxed);
+                    }
+                    update_taskbar(&app);
+                    _ = app.emit(TASK_EVENT, info);
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Clear the badge of failures from tasks that finished in the background,
+/// called when a window is focused.
+pub fn clear_unseen_failures<R: Runtime>(app: &AppHandle<R>) {
+    let state = app.state::<State>();
+    if state.unseen_task_failures.swap(0, Ordering::Relaxed) > 0 {
+        update_taskbar(app);
+    }
+}
+
+/// Show the progress of running tasks on the Dock icon or taskbar, with a
+/// badge counting failures.
+///
+/// Windows doesn't support badges on the taskbar, so failures turn the
+/// progress bar red there instead.
+fn update_taskbar<R: Runtime>(app: &AppHandle<R>) {
+    let state = app.state::<State>();
+    let summary = state.tasks.summary();
+    let failures = summary.as_ref().map_or(0, |summary| summary.failures)
+        + state.unseen_task_failures.load(Ordering::Relaxed);
+    for window in app.webview_windows().values() {
+        let state = match &summary {
+            None => ProgressBarState {
+                status: Some(ProgressBarStatus::None),
+                progress: None,
+            },
+            Some(summary) => ProgressBarState {
+                status: Some(match summary.progress {
+                    None => ProgressBarStatus::Indeterminate,
+                    Some(_) if cfg!(windows) && failures > 0 => ProgressBarStatus::Error,
+                    Some(_) => ProgressBarStatus::Normal,
+                }),
+                progress: summary
+                    .progress
+                    .map(|progress| (progress.clamp(0.0, 1.0) * 100.0).round() as u64),
+            },
+        };
+        if let Err(err) = window.set_progress_bar(state) {
+            warn!("failed to set taskbar progress: {err}");
+        }
+        if !cfg!(windows) {
+            let count = (failures > 0).then_some(failures.into());
+            if let Err(err) = window.set_badge_count(count) {
+                warn!("failed to set badge count: {err}");
+            }
+        }
+    }
+}
+
+/// List all background tasks that are currently running.
+#[tauri::command]
+pub async fn list_tasks(state: tauri::State<'_, State>) -> Result<Vec<TaskInfo>, Error> {
+    Ok(state.tasks.list())
+}
+
+/// Request cancellation of a background task, returning whether the task was
+/// found and could be cancelled.
+#[tauri::command]
+pub async fn cancel_task(task_id: EntityId, state: tauri::State<'_, State>) -> Result<bool, Error> {
+    Ok(state.tasks.cancel(task_id.expect_kind(Entity::Task)?))
+}
+
+/// Start a task whose work happens in the frontend, returning its ID.
+///
+/// The task fails if its window closes before finishing it.
+#[tauri::command]
+pub async fn start_task(
+    kind: TaskKind,
+    title: String,
+    window: WebviewWindow,
+    state: tauri::State<'_, State>,
+) -> Result<EntityId, Error> {
+    let handle = state.tasks.start(kind, title, false);
+    let id = handle.id();
+    state
+        .client_tasks
+        .insert(id, (window.label().into(), handle));
+    Ok(id)
+}
+
+/// Fail the tasks that a window started and didn't finish, when it closes.
+pub fn fail_window_tasks<R: Runtime>(app: &AppHandle<R>, label: &str) {
+    let state = app.state::<State>();
+    let ids: Vec<EntityId> = (state.client_tasks.iter())
+        .filter(|task| task.0 == label)
+        .map(|task| *task.key())
+        .collect();
+    for id in ids {
+        if let Some((_, (_, handle))) = state.client_tasks.remove(&id) {
+            handle.fail("window was closed");
+        }
+    }
+}
+
+/// Report progress on a task started by the frontend.
+#[tauri::command]
+pub async fn set_task_progress(
+    task_id: EntityId,
+    progress: f64,
+    message: String,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let task = state
+        .client_tasks
+        .get(&task_id)
+        .ok_or_else(|| Error::InvalidEntity(format!("no task with ID {task_id}")))?;
+    task.1.set_progress(progress, message);
+    Ok(())
+}
+
+/// Count a failed step of a task started by the frontend.
+#[tauri::command]
+pub async fn add_task_failure(
+    task_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let task = state
+        .client_tasks
+        .get(&task_id)
+        .ok_or_else(|| Error::InvalidEntity(format!("no task with ID {task_id}")))?;
+    task.1.add_failure();
+    Ok(())
+}
+
+/// Finish a task started by the frontend, with an error if it failed.
+#[tauri::command]
+pub async fn finish_task(
+    task_id: EntityId,
+    error: Option<String>,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let (_, (_, handle)) = state
+        .client_tasks
+        .remove(&task_id)
+        .ok_or_else(|| Error::InvalidEntity(format!("no task with ID {task_id}")))?;
+    match error {
+        Some(error) => handle.fail(error),
+        None => handle.complete(),
+    }
+    Ok(())
+}
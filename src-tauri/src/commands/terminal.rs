@@ -0,0 +1,135 @@
+//! Commands for the integrated terminal panel next to a notebook.
+
+use std::path::PathBuf;
+
+use tauri::{ipc::Channel, AppHandle, Manager, Runtime, WebviewWindow};
+
+use crate::{
+    entity::{Entity, EntityId},
+    state::State,
+    terminal::{Terminal, TerminalEvent, TerminalOptions},
+    Error,
+};
+
+/// Spawn a shell in a new terminal, returning its ID.
+///
+/// The shell starts in the notebook's directory if it has been saved. With
+/// `activate`, it also has the notebook's virtual environment activated: a
+/// `.venv` folder next to the notebook, or else the default environment.
+/// Events are sent until the shell exits, and then the terminal is removed.
+/// It is killed when the window closes.
+#[tauri::command]
+pub async fn spawn_terminal(
+    notebook_path: Option<PathBuf>,
+    activate: bool,
+    cols: u16,
+    rows: u16,
+    on_event: Channel<TerminalEvent>,
+    window: WebviewWindow,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<EntityId, Error> {
+    let cwd = notebook_path
+        .as_deref()
+        .and_then(|path| path.parent())
+        .filter(|dir| dir.is_dir())
+        .map(PathBuf::from);
+    let venv = if activate {
+        match cwd.as_ref().map(|dir| dir.join(".venv")) {
+            Some(venv) if venv.is_dir() => Some(venv),
+            _ => super::default_venv_prefix(&app)?,
+        }
+    } else {
+        None
+    };
+    let options = TerminalOptions {
+        shell: None,
+        cwd,
+        venv,
+        cols,
+        rows,
+    };
+
+    let terminal_id = state.entities.create(Entity::Terminal);
+    let terminal = Terminal::spawn(&options, move |event| {
+        let exited = matches!(event, TerminalEvent::Exit(_));
+        let sent = on_event.send(event).is_ok();
+        if exited || !sent {
+            let state = app.state::<State>();
+            state.terminals.remove(&terminal_id);
+            state.entities.remove(terminal_id);
+        }
+        sent
+    });
+    match terminal {
+        Ok(terminal) => {
+            let label = window.label().to_string();
+            state.terminals.insert(terminal_id, (label, terminal));
+            Ok(terminal_id)
+        }
+        Err(err) => {
+            state.entities.remove(terminal_id);
+            Err(err)
+        }
+    }
+}
+
+/// Write input to a terminal, such as typed keys or pasted text.
+#[tauri::command]
+pub async fn write_terminal(
+    terminal_id: EntityId,
+    data: &str,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let terminal_id = state.entities.get(terminal_id, Entity::Terminal)?;
+    let terminal = state
+        .terminals
+        .get(&terminal_id)
+        .ok_or(Error::InvalidEntity(terminal_id.to_string()))?;
+    terminal.1.write(data.as_bytes())
+}
+
+/// Resize a terminal to fit its panel.
+#[tauri::command]
+pub async fn resize_terminal(
+    terminal_id: EntityId,
+    cols: u16,
+    rows: u16,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let terminal_id = state.entities.get(terminal_id, Entity::Terminal)?;
+    let terminal = state
+        .terminals
+        .get(&terminal_id)
+        .ok_or(Error::InvalidEntity(terminal_id.to_string()))?;
+    terminal.1.resize(cols, rows)
+}
+
+/// Kill the shell of a terminal and remove it.
+#[tauri::command]
+pub async fn kill_terminal(
+    terminal_id: EntityId,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let terminal_id = state.entities.get(terminal_id, Entity::Terminal)?;
+    state.entities.remove(terminal_id);
+    if let Some((_, (_, terminal))) = state.terminals.remove(&terminal_id) {
+        terminal.kill();
+    }
+    Ok(())
+}
+
+/// Kill the terminals that a window spawned, when it closes.
+pub fn kill_window_terminals<R: Runtime>(app: &AppHandle<R>, label: &str) {
+    let state = app.state::<State>();
+    let ids: Vec<EntityId> = (state.terminals.iter())
+        .filter(|terminal| terminal.0 == label)
+        .map(|terminal| *terminal.key())
+        .collect();
+    for id in ids {
+        state.entities.remove(id);
+        if let Some((_, (_, terminal))) = state.terminals.remove(&id) {
+            terminal.kill();
+        }
+    }
+}
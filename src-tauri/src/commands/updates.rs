@@ -0,0 +1,233 @@
+//! Commands for checking whether a newer version of Jute is available, and for
+//! installing it.
+//!
+//! Updates are found in the background or from the Help menu, and the user is
+//! told about them in a dialog. Downloaded updates are installed by restarting
+//! from the dialog or the menu.
+
+use std::time::{Duration, Instant};
+
+use tauri::{async_runtime, AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_updater::{Update, UpdaterExt};
+use tracing::{info, warn};
+
+use crate::{
+    menu,
+    state::State,
+    unsaved,
+    updates::{self, UpdateCheck, UpdateInfo, UPDATER_PUBKEY, UPDATE_EVENT, UPDATE_READY_EVENT},
+    Error,
+};
+
+/// How often the background task re-reads the update check setting.
+const POLL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// An update that has been downloaded and verified, waiting to be installed.
+pub struct PendingUpdate {
+    update: Update,
+    bytes: Vec<u8>,
+}
+
+/// Check for a newer release, notifying the frontend if one is found.
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<Option<UpdateInfo>, Error> {
+    let setting = app.state::<State>().settings.read().unwrap().update_check;
+    if setting == UpdateCheck::Disabled {
+        return Ok(None);
+    }
+    let update = updates::check_for_updates(&app.package_info().version).await?;
+    if let Some(update) = &update {
+        app.emit(UPDATE_EVENT, update)?;
+    }
+    Ok(update)
+}
+
+/// Install the update that was downloaded in the background, then restart.
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<(), Error> {
+    install_pending(&app)
+}
+
+/// Install the downloaded update and restart, if there is one.
+///
+/// Restarting doesn't ask to save notebooks, so this does nothing but tell the
+/// user to save while any notebook has unsaved changes.
+pub fn install_pending<R: Runtime>(app: &AppHandle<R>) -> Result<(), Error> {
+    if unsaved::has_unsaved_changes(app) {
+        app.dialog()
+            .message("Save or close your notebooks with unsaved changes before restarting.")
+            .title("Restart to Update")
+            .kind(MessageDialogKind::Warning)
+            .show(|_| {});
+        return Ok(());
+    }
+    let pending = app.state::<State>().pending_update.lock().unwrap().take();
+    let Some(PendingUpdate { update, bytes }) = pending else {
+        return Ok(());
+    };
+    info!("installing update to version {}", update.version);
+    update.install(bytes)?;
+    app.restart();
+}
+
+/// Check for updates now, as asked from the menu, and show the result.
+///
+/// Release builds also download the update, so it can be installed right away.
+pub async fn check_now<R: Runtime>(app: &AppHandle<R>) -> Result<(), Error> {
+    let setting = app.state::<State>().settings.read().unwrap().update_check;
+    if setting == UpdateCheck::Disabled {
+        app.dialog()
+            .message("Checking for updates has been disabled.")
+            .title("Check for Updates")
+            .show(|_| {});
+        return Ok(());
+    }
+    let version = &app.package_info().version;
+    let update = match UPDATER_PUBKEY {
+        Some(pubkey) => download_update(app, pubkey).await?,
+        None => updates::check_for_updates(version).await?,
+    };
+    match update {
+        Some(update) => announce_update(app, &update),
+        None => {
+            app.dialog()
+                .message(format!("Jute {version} is the latest version."))
+                .title("Check for Updates")
+                .show(|_| {});
+        }
+    }
+    Ok(())
+}
+
+/// Tell the user about a new version, offering to restart if it has been
+/// downloaded, or else to open its release notes.
+fn announce_update<R: Runtime>(app: &AppHandle<R>, update: &UpdateInfo) {
+    _ = menu::update_menu_state(app);
+    let ready = app
+        .state::<State>()
+        .pending_update
+        .lock()
+        .unwrap()
+        .is_some();
+    let (message, action) = if ready {
+        let message = format!("Jute {} is ready to install. Restart now?", update.version);
+        (message, "Restart")
+    } else {
+        let message = format!("Jute {} is available.", update.version);
+        (message, "View Release")
+    };
+    let app = app.clone();
+    let url = update.url.clone();
+    app.dialog()
+        .message(message)
+        .title("Update Available")
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            action.into(),
+            "Later".into(),
+        ))
+        .show(move |confirmed| {
+            if !confirmed {
+                return;
+            }
+            let result = if ready {
+                install_pending(&app)
+            } else {
+                app.opener()
+                    .open_url(url, None::<&str>)
+                    .map_err(Error::from)
+            };
+            if let Err(err) = result {
+                warn!("failed to update: {err}");
+            }
+        });
+}
+
+/// Periodically check for updates in the background, as often as the user's
+/// settings allow. Each new version is only announced once per session.
+pub fn spawn_update_checks<R: Runtime>(app: &AppHandle<R>) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let mut last_check: Option<Instant> = None;
+        let mut notified: Option<String> = None;
+        loop {
+            let setting = app.state::<State>().settings.read().unwrap().update_check;
+            let due = match (setting.interval(), last_check) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(interval), Some(last)) => last.elapsed() >= interval,
+            };
+            if due {
+                last_check = Some(Instant::now());
+                let result = match UPDATER_PUBKEY {
+                    Some(pubkey) => download_update(&app, pubkey).await,
+                    None => updates::check_for_updates(&app.package_info().version).await,
+                };
+                match result {
+                    Ok(Some(update)) if notified.as_ref() != Some(&update.version) => {
+                        notified = Some(update.version.clone());
+                        let event = match UPDATER_PUBKEY {
+                            Some(_) => UPDATE_READY_EVENT,
+                            None => UPDATE_EVENT,
+                        };
+                        _ = app.emit(event, &update);
+                        announce_update(&app, &update);
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("failed to check for updates: {err}"),
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// Download and verify the latest update on the user's channel, keeping it to
+/// be installed on restart.
+async fn download_update<R: Runtime>(
+    app: &AppHandle<R>,
+    pubkey: &str,
+) -> Result<Option<UpdateInfo>, Error> {
+    let state = app.state::<State>();
+    let channel = state.settings.read().unwrap().update_channel;
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![channel.manifest_url().parse()?])?
+        .pubkey(pubkey)
+        .build()?;
+    let Some(update) = updater.check().await? else {
+        return Ok(None);
+    };
+
+    let downloaded = state
+        .pending_update
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|pending| pending.update.version.clone());
+    let info = update_info(&update);
+    if downloaded.as_ref() != Some(&update.version) {
+        info!("downloading update to version {}", update.version);
+        let bytes = update.download(|_, _| {}, || {}).await?;
+        *state.pending_update.lock().unwrap() = Some(PendingUpdate { update, bytes });
+    }
+    Ok(Some(info))
+}
+
+/// Describe an update from the updater manifest for the frontend.
+fn update_info(update: &Update) -> UpdateInfo {
+    UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone().unwrap_or_default(),
+        url: format!(
+            "https://github.com/ekzhang/jute/releases/tag/v{}",
+            update.version
+        ),
+        published_at: update.date.and_then(|date| {
+            date.format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        }),
+    }
+}
@@ -1,18 +1,31 @@
 //! Commands for the management of local virtual environments with `uv`.
 
-use std::io;
+use std::{io, path::PathBuf};
 
 use ini::Ini;
 use serde::Serialize;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Manager, Runtime};
 use tauri_plugin_shell::ShellExt;
 use tracing::{error, info};
+use ts_rs::TS;
 
 use crate::{
+    commands::settings::modify_settings,
     entity::{Entity, EntityId},
+    state::State,
+    tasks::{TaskHandle, TaskKind},
     Error,
 };
 
+/// Get the directory of a managed virtual environment.
+pub fn venv_path<R: Runtime>(app: &AppHandle<R>, venv_id: EntityId) -> Result<PathBuf, Error> {
+    Ok(app
+        .path()
+        .app_data_dir()?
+        .join("venv")
+        .join(venv_id.to_string()))
+}
+
 /// Return a list of Python versions that can be used to create a virtual
 /// environment.
 #[tauri::command]
@@ -52,13 +65,36 @@ pub async fn venv_list_python_versions(app: AppHandle) -> Result<Vec<String>, Er
 
 /// Create a new virtual environment, and return its ID.
 #[tauri::command]
-pub async fn venv_create(python_version: &str, app: AppHandle) -> Result<EntityId, Error> {
+pub async fn venv_create(
+    python_version: &str,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<EntityId, Error> {
+    let task = state.tasks.start(
+        TaskKind::CreateEnvironment,
+        format!("Creating Python {python_version} environment"),
+        false,
+    );
+    match create_venv(python_version, &app, &task).await {
+        Ok(venv_id) => {
+            task.complete();
+            Ok(venv_id)
+        }
+        Err(err) => {
+            task.fail(err.to_string());
+            Err(err)
+        }
+    }
+}
+
+async fn create_venv(
+    python_version: &str,
+    app: &AppHandle,
+    task: &TaskHandle,
+) -> Result<EntityId, Error> {
+    task.set_progress(0.0, "Creating virtual environment");
     let venv_id = EntityId::new(Entity::Venv);
-    let venv_path = app
-        .path()
-        .app_data_dir()?
-        .join("venv")
-        .join(venv_id.to_string());
+    let venv_path = venv_path(app, venv_id)?;
 
     let output = app
         .shell()
@@ -84,6 +120,7 @@ pub async fn venv_create(python_version: &str, app: AppHandle) -> Result<EntityI
     }
 
     info!("created venv at {venv_path:?}");
+    task.set_progress(0.3, "Installing packages");
     let venv_python_path = venv_path.join("bin/python");
 
     let packages = ["ipykernel", "black", "basedpyright"];
@@ -113,8 +150,9 @@ pub async fn venv_create(python_version: &str, app: AppHandle) -> Result<EntityI
 }
 
 /// List item returned by [`venv_list`].
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, TS)]
 pub struct VenvListItem {
+    #[ts(type = "string")]
     venv_id: EntityId,
     python_version: Option<String>,
     uv_version: Option<String>,
@@ -122,6 +160,8 @@ pub struct VenvListItem {
     home: Option<String>,
 }
 
+crate::export_bindings!(VenvListItem);
+
 /// Return a list of virtual environments managed by Jute.
 #[tauri::command]
 pub async fn venv_list(app: AppHandle) -> Result<Vec<VenvListItem>, Error> {
@@ -135,7 +175,8 @@ pub async fn venv_list(app: AppHandle) -> Result<Vec<VenvListItem>, Error> {
     while let Some(entry) = it.next_entry().await.map_err(Error::Filesystem)? {
         if entry.file_type().await.is_ok_and(|f| f.is_dir()) {
             if let Ok(venv_id) = entry.file_name().into_string() {
-                if let Ok(venv_id) = venv_id.parse::<EntityId>() {
+                let venv_id = venv_id.parse::<EntityId>().ok();
+                if let Some(venv_id) = venv_id.filter(|id| id.kind == Entity::Venv) {
                     // Read the venv metadata file to get the Python version.
                     let metadata_path = entry.path().join("pyvenv.cfg");
                     let mut python_version = None;
@@ -168,8 +209,17 @@ pub async fn venv_list(app: AppHandle) -> Result<Vec<VenvListItem>, Error> {
 /// Delete a virtual environment by ID.
 #[tauri::command]
 pub async fn venv_delete(venv_id: EntityId, app: AppHandle) -> Result<bool, Error> {
-    let venv_dir = app.path().app_data_dir()?.join("venv");
-    let venv_path = venv_dir.join(venv_id.to_string());
+    let venv_id = venv_id.expect_kind(Entity::Venv)?;
+    let venv_path = venv_path(&app, venv_id)?;
+    let default_environment = app
+        .state::<State>()
+        .settings
+        .read()
+        .unwrap()
+        .default_environment;
+    if default_environment == Some(venv_id) {
+        modify_settings(&app, |settings| settings.default_environment = None).await?;
+    }
     if tokio::fs::metadata(&venv_path).await.is_ok() {
         tokio::fs::remove_dir_all(&venv_path)
             .await
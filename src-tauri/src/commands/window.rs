@@ -0,0 +1,205 @@
+//! Commands for tracking what each window is showing, to route menu events
+//! and update the state of menu items, and for managing notebook tabs.
+
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Emitter, EventTarget, Manager, Runtime, WebviewWindow};
+
+use crate::{
+    entity::EntityId,
+    menu,
+    recent::RECENT_FILE,
+    state::State,
+    unsaved::{self, CloseChoice},
+    window::{self, SaveTab, Tab, TabList, SAVE_TAB_EVENT},
+    window_state::WINDOW_STATE_FILE,
+    Error,
+};
+
+/// Get the path to the saved window geometry in the app data directory.
+pub fn window_state_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_data_dir()?.join(WINDOW_STATE_FILE))
+}
+
+/// Write the saved window geometry of notebooks to disk.
+pub async fn save_window_states<R: Runtime>(app: &AppHandle<R>) -> Result<(), Error> {
+    let states = app.state::<State>().window_states.read().unwrap().clone();
+    states.save(&window_state_path(app)?).await
+}
+
+/// Get the path to the recent notebooks file in the app data directory.
+pub fn recent_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_data_dir()?.join(RECENT_FILE))
+}
+
+/// Mark the calling window as showing a notebook, with the state of its
+/// active tab: its path if saved, its kernel, and whether it has unsaved
+/// changes.
+///
+/// This is called again whenever any of these change, or the active tab does.
+/// Saved notebooks are also recorded as recently opened.
+#[tauri::command]
+pub async fn attach_notebook_window(
+    path: Option<PathBuf>,
+    kernel_id: Option<EntityId>,
+    dirty: bool,
+    window: WebviewWindow,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    let recent = path.as_ref().and_then(|path| {
+        let mut recent = state.recent_notebooks.write().unwrap();
+        recent.add(path).then(|| recent.clone())
+    });
+    {
+        let mut notebook = state
+            .notebook_windows
+            .entry(window.label().into())
+            .or_default();
+        notebook.path = path;
+        notebook.kernel_id = kernel_id;
+        notebook.dirty = dirty;
+    }
+    menu::update_menu_state(&app)?;
+    if let Some(recent) = recent {
+        recent.save(&recent_path(&app)?).await?;
+    }
+    Ok(())
+}
+
+/// Mark the calling window as no longer showing a notebook.
+#[tauri::command]
+pub async fn detach_notebook_window(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: tauri::State<'_, State>,
+) -> Result<(), Error> {
+    super::share::stop_window_share(&app, window.label());
+    state.notebook_windows.remove(window.label());
+    window.set_title("Jute")?;
+    menu::update_menu_state(&app)?;
+    Ok(())
+}
+
+/// Open a notebook file as a tab in the calling window, returning its tabs.
+#[tauri::command]
+pub async fn open_tab(path: PathBuf, window: WebviewWindow) -> Result<TabList, Error> {
+    window::update_tabs(&window, |tabs| {
+        tabs.open(&path);
+        tabs.clone()
+    })
+    .map_err(Error::from)
+}
+
+/// Switch to a tab in the calling window.
+#[tauri::command]
+pub async fn select_tab(index: usize, window: WebviewWindow) -> Result<(), Error> {
+    window::update_tabs(&window, |tabs| tabs.select(index))?;
+    Ok(())
+}
+
+/// Record the path and unsaved state of the notebook in a tab of the calling
+/// window, like [`attach_notebook_window`] does for the active tab.
+#[tauri::command]
+pub async fn update_tab(
+    id: String,
+    path: PathBuf,
+    dirty: bool,
+    window: WebviewWindow,
+) -> Result<(), Error> {
+    window::update_tabs(&window, |tabs| {
+        if let Some(tab) = tabs.get_mut(&id) {
+            tab.path = path;
+            tab.dirty = dirty;
+        }
+    })?;
+    Ok(())
+}
+
+/// Find a tab in a window by its ID.
+fn find_tab<R: Runtime>(window: &WebviewWindow<R>, id: &str) -> Option<Tab> {
+    let state = window.state::<State>();
+    let notebook = state.notebook_windows.get(window.label())?;
+    notebook.tabs.tabs.iter().find(|tab| tab.id == id).cloned()
+}
+
+/// Ask whether to save a tab's changes before it is closed or moved, and
+/// return whether to go ahead now.
+///
+/// If the user chooses to save, the frontend is sent a [`SAVE_TAB_EVENT`], and
+/// it repeats the action once the notebook is saved.
+async fn confirm_tab_action<R: Runtime>(
+    window: &WebviewWindow<R>,
+    tab: &Tab,
+    moving: bool,
+) -> Result<bool, Error> {
+    if !tab.dirty {
+        return Ok(true);
+    }
+    match unsaved::ask_to_save(window, &[Some(tab.path.clone())]).await {
+        CloseChoice::Discard => Ok(true),
+        CloseChoice::Cancel => Ok(false),
+        CloseChoice::Save => {
+            let payload = SaveTab {
+                id: tab.id.clone(),
+                moving,
+            };
+            let target = EventTarget::webview_window(window.label());
+            window.emit_to(target, SAVE_TAB_EVENT, payload)?;
+            Ok(false)
+        }
+    }
+}
+
+/// Close a tab in the calling window, asking to save it first if needed.
+#[tauri::command]
+pub async fn close_tab(id: String, window: WebviewWindow) -> Result<(), Error> {
+    let Some(tab) = find_tab(&window, &id) else {
+        return Ok(());
+    };
+    if confirm_tab_action(&window, &tab, false).await? {
+        window::update_tabs(&window, |tabs| tabs.close_id(&id))?;
+    }
+    Ok(())
+}
+
+/// Close the calling window if its notebooks have all been saved, after the
+/// user chose to save them when closing it.
+#[tauri::command]
+pub async fn close_saved_window(window: WebviewWindow) -> Result<(), Error> {
+    if unsaved::unsaved_notebooks(&window).is_empty() {
+        window.destroy()?;
+    }
+    Ok(())
+}
+
+/// Scroll a notebook to a cell, in the window that has it open if any.
+///
+/// The cell is given by its ID or by the anchor of a Markdown heading.
+#[tauri::command]
+pub async fn focus_notebook_cell(path: PathBuf, cell: String, app: AppHandle) -> Result<(), Error> {
+    window::focus_notebook_cell(&app, &path, &cell)?;
+    Ok(())
+}
+
+/// Move a tab out of the calling window, into a new window of its own.
+///
+/// The new window opens the notebook from disk, so this asks to save it first
+/// if it has unsaved changes.
+#[tauri::command]
+pub async fn move_tab_to_new_window(
+    id: String,
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<(), Error> {
+    let Some(tab) = find_tab(&window, &id) else {
+        return Ok(());
+    };
+    if !confirm_tab_action(&window, &tab, true).await? {
+        return Ok(());
+    }
+    if let Some(tab) = window::update_tabs(&window, |tabs| tabs.close_id(&id))? {
+        window::open_notebook_path(&app, &tab.path, None)?;
+    }
+    Ok(())
+}
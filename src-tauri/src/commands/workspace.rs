@@ -0,0 +1,85 @@
+//! Commands for workspaces, which are windows that show a folder in a file
+//! browser sidebar next to the notebooks opened from it.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::{commands::indexing, recent::RECENT_FOLDERS_FILE, state::State, window, Error};
+
+/// An entry in a folder, returned by [`list_directory`].
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct DirEntry {
+    /// File name of the entry.
+    pub name: String,
+
+    /// Full path to the entry.
+    pub path: PathBuf,
+
+    /// Whether the entry is a folder.
+    pub is_dir: bool,
+}
+
+crate::export_bindings!(DirEntry);
+
+/// Get the path to the recent folders file in the app data directory.
+pub fn recent_folders_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    Ok(app.path().app_data_dir()?.join(RECENT_FOLDERS_FILE))
+}
+
+/// Open a folder as a workspace, and record it as recently opened.
+///
+/// If a window is already showing the folder, it is focused instead. Newly
+/// recorded folders are indexed for system search, if that is enabled.
+pub async fn open_workspace<R: Runtime>(app: &AppHandle<R>, dir: &Path) -> Result<(), Error> {
+    window::open_workspace(app, dir)?;
+    let recent = {
+        let state = app.state::<State>();
+        let mut recent = state.recent_folders.write().unwrap();
+        recent.add(dir).then(|| recent.clone())
+    };
+    if let Some(recent) = recent {
+        recent.save(&recent_folders_path(app)?).await?;
+        if let Err(err) = indexing::index_workspace(app, dir).await {
+            warn!("could not index {}: {err}", dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// Open a folder as a workspace, from the frontend.
+#[tauri::command]
+pub async fn open_folder(path: PathBuf, app: AppHandle) -> Result<(), Error> {
+    open_workspace(&app, &path).await
+}
+
+/// List the entries of a folder for the file browser, with folders first.
+///
+/// Hidden files, whose names start with a dot, are left out.
+#[tauri::command]
+pub async fn list_directory(path: PathBuf) -> Result<Vec<DirEntry>, Error> {
+    let mut it = tokio::fs::read_dir(&path)
+        .await
+        .map_err(Error::Filesystem)?;
+    let mut entries = Vec::new();
+    while let Some(entry) = it.next_entry().await.map_err(Error::Filesystem)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        // Follow symlinks, so that linked folders can be expanded.
+        let is_dir = tokio::fs::metadata(entry.path())
+            .await
+            .is_ok_and(|metadata| metadata.is_dir());
+        entries.push(DirEntry {
+            name,
+            path: entry.path(),
+            is_dir,
+        });
+    }
+    entries.sort_by_cached_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+    Ok(entries)
+}
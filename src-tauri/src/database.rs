@@ -0,0 +1,433 @@
+//! Saved database connections, and SQL cells that query them.
+//!
+//! Connections are persisted as JSON in the config dir like Jupyter servers,
+//! and each one is assigned an [`EntityId`]. Their passwords are kept in the
+//! OS keychain instead of the file. Queries run in the app, not the kernel,
+//! through the pools in [`pool`], and results are streamed to the frontend in
+//! pages.
+//!
+//! A code cell is a SQL cell if its first line is a `%%sql` header, naming the
+//! connection to query. Results can also be bound into the kernel as a pandas
+//! DataFrame, by naming a variable after an arrow:
+//!
+//! ```text
+//! %%sql warehouse -> df
+//! SELECT * FROM orders WHERE total > 100
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::{
+    dataflow::KEYWORDS,
+    entity::{Entity, EntityId, EntityRegistry},
+    secrets::{self, SecretNamespace},
+    store, Error,
+};
+
+pub mod pool;
+
+/// The name of the database connections file within the app config directory.
+pub const DATABASES_FILE: &str = "databases.json";
+
+/// Number of rows sent to the frontend in each page of results.
+pub const PAGE_SIZE: usize = 500;
+
+/// Maximum number of rows kept from a query, to bound memory use.
+pub const MAX_ROWS: usize = 100_000;
+
+/// Kind of database that a connection is to.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseKind {
+    /// PostgreSQL server.
+    Postgres,
+
+    /// MySQL or MariaDB server.
+    Mysql,
+
+    /// SQLite database file.
+    Sqlite,
+
+    /// DuckDB database file.
+    Duckdb,
+}
+
+crate::export_bindings!(DatabaseKind);
+
+/// A database connection saved by the user.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct DatabaseConnection {
+    /// Unique identifier of the connection.
+    #[ts(type = "string")]
+    pub id: EntityId,
+
+    /// Name of the connection, used in `%%sql` headers.
+    pub name: String,
+
+    /// Kind of database.
+    pub kind: DatabaseKind,
+
+    /// URL of a database server without the password, like
+    /// `postgres://user@localhost/db`, or the path of a SQLite or DuckDB file.
+    pub url: String,
+
+    /// Password for the database server, if any.
+    ///
+    /// This is stored in the keychain, and never written to the connections
+    /// file or sent to the frontend.
+    #[serde(default, skip_serializing)]
+    #[ts(skip)]
+    pub password: Option<String>,
+}
+
+crate::export_bindings!(DatabaseConnection);
+
+/// The list of saved database connections.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct DatabaseRegistry {
+    connections: Vec<DatabaseConnection>,
+
+    /// Connections whose passwords could not be read from the keychain. Their
+    /// stored passwords are left alone when saving, rather than deleted.
+    #[serde(skip)]
+    unread_passwords: HashSet<EntityId>,
+}
+
+impl DatabaseRegistry {
+    /// Load the connections from a file, registering each one's ID and reading
+    /// passwords from the keychain.
+    pub async fn load(path: &Path, entities: &EntityRegistry) -> Self {
+        let mut registry: Self = store::load_json(path).await;
+        registry
+            .connections
+            .retain(|conn| conn.id.kind == Entity::Database && entities.insert(conn.id));
+        let keys = (registry.connections.iter())
+            .map(|conn| conn.id.to_string())
+            .collect();
+        let mut passwords: HashMap<String, _> =
+            secrets::load_secrets(SecretNamespace::DatabaseCredential, keys)
+                .await
+                .into_iter()
+                .collect();
+        for conn in &mut registry.connections {
+            match passwords.remove(&conn.id.to_string()) {
+                Some(Ok(password)) => conn.password = password,
+                Some(Err(err)) => {
+                    warn!("could not read password of database {:?}: {err}", conn.name);
+                    registry.unread_passwords.insert(conn.id);
+                }
+                None => _ = registry.unread_passwords.insert(conn.id),
+            }
+        }
+        registry
+    }
+
+    /// Write the connections to a file, and their passwords to the keychain.
+    ///
+    /// Keychain errors are logged without failing, so connections can be
+    /// saved on machines without a keychain.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        let updates = self.password_updates();
+        secrets::store_secrets(SecretNamespace::DatabaseCredential, updates).await;
+        store::save_json(path, self).await
+    }
+
+    /// Passwords to write to the keychain for each connection, or `None` to
+    /// delete a connection's password. Passwords that could not be read are
+    /// skipped.
+    fn password_updates(&self) -> Vec<(String, Option<String>)> {
+        (self.connections.iter())
+            .filter(|conn| !self.unread_passwords.contains(&conn.id))
+            .map(|conn| (conn.id.to_string(), conn.password.clone()))
+            .collect()
+    }
+
+    /// List all saved connections.
+    pub fn list(&self) -> &[DatabaseConnection] {
+        &self.connections
+    }
+
+    /// Get a saved connection by ID.
+    pub fn get(&self, id: EntityId) -> Option<&DatabaseConnection> {
+        self.connections.iter().find(|conn| conn.id == id)
+    }
+
+    /// Find a saved connection by name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<&DatabaseConnection> {
+        self.connections
+            .iter()
+            .find(|conn| conn.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Add a connection, or update the existing connection with the same name,
+    /// ignoring case like [`DatabaseRegistry::find`].
+    pub fn add(
+        &mut self,
+        entities: &EntityRegistry,
+        name: &str,
+        kind: DatabaseKind,
+        url: &str,
+        password: Option<&str>,
+    ) -> Result<DatabaseConnection, Error> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) || name.contains("->") {
+            return Err(Error::Database(format!("invalid connection name {name:?}")));
+        }
+        if matches!(kind, DatabaseKind::Postgres | DatabaseKind::Mysql) {
+            url::Url::parse(url)?;
+        }
+        let existing = (self.connections.iter_mut()).find(|c| c.name.eq_ignore_ascii_case(name));
+        if let Some(conn) = existing {
+            conn.name = name.into();
+            conn.kind = kind;
+            conn.url = url.into();
+            conn.password = password.map(String::from);
+            self.unread_passwords.remove(&conn.id);
+            return Ok(conn.clone());
+        }
+        let conn = DatabaseConnection {
+            id: entities.create(Entity::Database),
+            name: name.into(),
+            kind,
+            url: url.into(),
+            password: password.map(String::from),
+        };
+        self.connections.push(conn.clone());
+        Ok(conn)
+    }
+
+    /// Remove a connection by ID, returning whether it was found.
+    pub fn remove(&mut self, entities: &EntityRegistry, id: EntityId) -> bool {
+        let len = self.connections.len();
+        self.connections.retain(|conn| conn.id != id);
+        self.unread_passwords.remove(&id);
+        entities.remove(id);
+        self.connections.len() < len
+    }
+}
+
+/// A column in the results of a query.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct SqlColumn {
+    /// Name of the column.
+    pub name: String,
+
+    /// Name of the column's type in the database, like `"INT8"`.
+    pub type_name: String,
+}
+
+crate::export_bindings!(SqlColumn);
+
+/// Events sent to the frontend while running a query.
+#[derive(Serialize, Clone, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case", tag = "event", content = "data")]
+pub enum SqlEvent {
+    /// The columns of the results, sent before any rows.
+    Columns(Vec<SqlColumn>),
+
+    /// A page of rows, each with a JSON value for each column.
+    Rows(#[ts(type = "unknown[][]")] Vec<Vec<Value>>),
+
+    /// The query finished, after returning or affecting this many rows.
+    Done {
+        /// Number of rows returned, or affected by a statement like `UPDATE`.
+        #[ts(type = "number")]
+        row_count: u64,
+
+        /// Whether rows were left out after the first [`MAX_ROWS`].
+        truncated: bool,
+    },
+
+    /// The results were bound to a variable in the kernel.
+    Bound(String),
+
+    /// The results could not be bound to a variable in the kernel.
+    BindError(String),
+}
+
+crate::export_bindings!(SqlEvent);
+
+/// Results of a query, kept to bind them into the kernel.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SqlResults {
+    /// Columns of the results.
+    pub columns: Vec<SqlColumn>,
+
+    /// Rows of the results, up to [`MAX_ROWS`].
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// A SQL cell, parsed from the source of a code cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SqlCell {
+    /// Name of the connection to query.
+    pub connection: String,
+
+    /// Variable in the kernel to bind the results to, if any.
+    pub variable: Option<String>,
+
+    /// The query, after the header line.
+    pub query: String,
+}
+
+impl SqlCell {
+    /// Parse a cell whose first line is a `%%sql` header, or return `None` if
+    /// it isn't a SQL cell.
+    pub fn parse(source: &str) -> Option<Result<Self, Error>> {
+        let source = source.trim_start();
+        let (header, query) = source.split_once('\n').unwrap_or((source, ""));
+        let args = header.strip_prefix("%%sql")?;
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let (connection, variable) = match args.split_once("->") {
+            Some((connection, variable)) => (connection.trim(), Some(variable.trim())),
+            None => (args.trim(), None),
+        };
+        Some(Self::new(connection, variable, query))
+    }
+
+    fn new(connection: &str, variable: Option<&str>, query: &str) -> Result<Self, Error> {
+        if connection.is_empty() {
+            return Err(Error::Database(
+                "name a connection after %%sql, like `%%sql warehouse`".into(),
+            ));
+        }
+        if let Some(variable) = variable {
+            if !is_identifier(variable) {
+                return Err(Error::Database(format!(
+                    "{variable:?} is not a valid variable name"
+                )));
+            }
+        }
+        Ok(Self {
+            connection: connection.into(),
+            variable: variable.map(String::from),
+            query: query.trim().into(),
+        })
+    }
+}
+
+/// Whether a string is a valid Python identifier, and not a keyword.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Python code that binds query results to a variable as a pandas DataFrame.
+///
+/// The rows are embedded as a JSON string literal, which is also a valid
+/// Python string literal. Temporary names are deleted afterward, so they don't
+/// clutter the kernel's namespace.
+pub fn dataframe_code(variable: &str, results: &SqlResults) -> Result<String, Error> {
+    let columns: Vec<&str> = results.columns.iter().map(|c| c.name.as_str()).collect();
+    let rows = serde_json::to_string(&serde_json::to_string(&results.rows)?)?;
+    let columns = serde_json::to_string(&serde_json::to_string(&columns)?)?;
+    Ok(format!(
+        "import json as _jute_json, pandas as _jute_pd\n{variable} = \
+         _jute_pd.DataFrame(_jute_json.loads({rows}), columns=_jute_json.loads({columns}))\ndel \
+         _jute_json, _jute_pd\n"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sql_cells() {
+        let cell = SqlCell::parse("%%sql warehouse -> df\nSELECT 1\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(cell.connection, "warehouse");
+        assert_eq!(cell.variable.as_deref(), Some("df"));
+        assert_eq!(cell.query, "SELECT 1");
+
+        let cell = SqlCell::parse("%%sql local\nSELECT 2").unwrap().unwrap();
+        assert_eq!(cell.variable, None);
+
+        assert!(SqlCell::parse("print(1)").is_none());
+        assert!(SqlCell::parse("%%sqlite\nSELECT 1").is_none());
+        assert!(SqlCell::parse("%%sql\nSELECT 1").unwrap().is_err());
+        assert!(SqlCell::parse("%%sql db -> 1df\nSELECT 1")
+            .unwrap()
+            .is_err());
+        assert!(SqlCell::parse("%%sql db -> yield\nSELECT 1")
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn registry_and_binding() {
+        let entities = EntityRegistry::new();
+        let mut registry = DatabaseRegistry::default();
+        let conn = registry
+            .add(&entities, "local", DatabaseKind::Sqlite, "data.db", None)
+            .unwrap();
+        assert_eq!(registry.find("LOCAL"), Some(&conn));
+        let renamed = registry
+            .add(&entities, "Local", DatabaseKind::Sqlite, "other.db", None)
+            .unwrap();
+        assert_eq!((renamed.id, renamed.name.as_str()), (conn.id, "Local"));
+        assert_eq!(registry.list().len(), 1);
+        assert!(registry
+            .add(&entities, "my db", DatabaseKind::Sqlite, "data.db", None)
+            .is_err());
+        assert!(registry
+            .add(&entities, "pg", DatabaseKind::Postgres, "not a url", None)
+            .is_err());
+        assert!(registry.remove(&entities, conn.id));
+        assert!(!entities.contains(conn.id));
+
+        let results = SqlResults {
+            columns: vec![SqlColumn {
+                name: "name".into(),
+                type_name: "TEXT".into(),
+            }],
+            rows: vec![vec![Value::String("O'Brien \"Jr\"".into())]],
+        };
+        let code = dataframe_code("df", &results).unwrap();
+        assert!(code
+            .contains(r#"df = _jute_pd.DataFrame(_jute_json.loads("[[\"O'Brien \\\"Jr\\\"\"]]")"#));
+    }
+
+    #[test]
+    fn skip_unread_passwords() {
+        let entities = EntityRegistry::new();
+        let mut registry = DatabaseRegistry::default();
+        let pg = registry
+            .add(
+                &entities,
+                "pg",
+                DatabaseKind::Postgres,
+                "postgres://db",
+                Some("pw"),
+            )
+            .unwrap();
+        let local = registry
+            .add(&entities, "local", DatabaseKind::Sqlite, "data.db", None)
+            .unwrap();
+
+        // A password that failed to load must not be deleted by the next save.
+        registry.unread_passwords.insert(local.id);
+        assert_eq!(
+            registry.password_updates(),
+            [(pg.id.to_string(), Some("pw".into()))],
+        );
+        registry
+            .add(&entities, "local", DatabaseKind::Sqlite, "data.db", None)
+            .unwrap();
+        assert_eq!(registry.password_updates().len(), 2);
+    }
+}
@@ -0,0 +1,398 @@
+//! Connection pools for each kind of database, and decoding of their rows.
+//!
+//! PostgreSQL, MySQL and SQLite are queried through `sqlx`. DuckDB has no
+//! `sqlx` driver, so it is queried through its own bindings on a blocking
+//! thread. Values are decoded into JSON by trying the Rust types that each
+//! driver supports in order, falling back to the name of the column's type.
+
+use std::{
+    mem,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::TryStreamExt;
+use serde_json::Value;
+use sqlx::{
+    mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions, MySqlRow},
+    postgres::{PgConnectOptions, PgPool, PgPoolOptions, PgRow},
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow},
+    types::{Decimal, Uuid},
+    Column, Executor, Row, Statement, TypeInfo, ValueRef,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::sync::mpsc;
+
+use super::{DatabaseConnection, DatabaseKind, SqlColumn, SqlEvent, MAX_ROWS, PAGE_SIZE};
+use crate::Error;
+
+/// Maximum number of connections kept open to each database server.
+const MAX_CONNECTIONS: u32 = 4;
+
+impl From<sqlx::Error> for Error {
+    fn from(err: sqlx::Error) -> Self {
+        Error::Database(err.to_string())
+    }
+}
+
+impl From<duckdb::Error> for Error {
+    fn from(err: duckdb::Error) -> Self {
+        Error::Database(err.to_string())
+    }
+}
+
+/// Run a query with `sqlx`, streaming its rows in pages.
+///
+/// The statement is prepared first to learn its columns. Statements without
+/// any, like `UPDATE`, are executed to report the number of rows affected.
+/// This is a macro since `sqlx` has no trait for the results of `execute()`.
+macro_rules! stream_rows {
+    ($pool:expr, $query:expr, $events:expr, $decode:expr) => {{
+        let (pool, query, events) = ($pool, $query, $events);
+        let statement = pool.prepare(query).await?;
+        let columns: Vec<SqlColumn> = statement
+            .columns()
+            .iter()
+            .map(|column| SqlColumn {
+                name: column.name().into(),
+                type_name: column.type_info().name().into(),
+            })
+            .collect();
+
+        if columns.is_empty() {
+            let result = sqlx::query(query).execute(pool).await?;
+            let done = SqlEvent::Done {
+                row_count: result.rows_affected(),
+                truncated: false,
+            };
+            _ = events.send(done).await;
+            return Ok(());
+        }
+
+        let width = columns.len();
+        if events.send(SqlEvent::Columns(columns)).await.is_err() {
+            return Ok(());
+        }
+        let mut pager = Pager::default();
+        let mut rows = sqlx::query(query).fetch(pool);
+        let mut truncated = false;
+        while let Some(row) = rows.try_next().await? {
+            if pager.is_full() {
+                truncated = true;
+                break;
+            }
+            let values = (0..width).map(|i| $decode(&row, i)).collect();
+            if let Some(page) = pager.push(values) {
+                if events.send(page).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        for event in pager.finish(truncated) {
+            _ = events.send(event).await;
+        }
+        Ok(())
+    }};
+}
+
+/// A pool of open connections to a saved database.
+pub enum DatabasePool {
+    /// Connections to a PostgreSQL server.
+    Postgres(PgPool),
+
+    /// Connections to a MySQL or MariaDB server.
+    Mysql(MySqlPool),
+
+    /// Connections to a SQLite database file.
+    Sqlite(SqlitePool),
+
+    /// A connection to a DuckDB database file, which only allows one writer.
+    Duckdb(Arc<Mutex<duckdb::Connection>>),
+}
+
+impl DatabasePool {
+    /// Open a pool of connections to a saved database.
+    pub async fn connect(conn: &DatabaseConnection) -> Result<Self, Error> {
+        let password = conn.password.as_deref().unwrap_or_default();
+        Ok(match conn.kind {
+            DatabaseKind::Postgres => {
+                let mut options = PgConnectOptions::from_str(&conn.url)?;
+                if !password.is_empty() {
+                    options = options.password(password);
+                }
+                let pool = PgPoolOptions::new()
+                    .max_connections(MAX_CONNECTIONS)
+                    .connect_with(options)
+                    .await?;
+                Self::Postgres(pool)
+            }
+            DatabaseKind::Mysql => {
+                let mut options = MySqlConnectOptions::from_str(&conn.url)?;
+                if !password.is_empty() {
+                    options = options.password(password);
+                }
+                let pool = MySqlPoolOptions::new()
+                    .max_connections(MAX_CONNECTIONS)
+                    .connect_with(options)
+                    .await?;
+                Self::Mysql(pool)
+            }
+            DatabaseKind::Sqlite => {
+                let options = SqliteConnectOptions::new()
+                    .filename(&conn.url)
+                    .create_if_missing(true);
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(MAX_CONNECTIONS)
+                    .connect_with(options)
+                    .await?;
+                Self::Sqlite(pool)
+            }
+            DatabaseKind::Duckdb => {
+                let path = conn.url.clone();
+                let db = tokio::task::spawn_blocking(move || duckdb::Connection::open(path))
+                    .await
+                    .map_err(|err| Error::Database(err.to_string()))??;
+                Self::Duckdb(Arc::new(Mutex::new(db)))
+            }
+        })
+    }
+
+    /// Run a query, sending its columns, pages of rows, and a final
+    /// [`SqlEvent::Done`] to `events`.
+    ///
+    /// Rows past [`MAX_ROWS`] are not read. The query stops early without an
+    /// error if the receiver is dropped.
+    pub async fn query(&self, query: &str, events: mpsc::Sender<SqlEvent>) -> Result<(), Error> {
+        match self {
+            Self::Postgres(pool) => stream_rows!(pool, query, events, pg_value),
+            Self::Mysql(pool) => stream_rows!(pool, query, events, mysql_value),
+            Self::Sqlite(pool) => stream_rows!(pool, query, events, sqlite_value),
+            Self::Duckdb(db) => {
+                let (db, query) = (db.clone(), query.to_string());
+                tokio::task::spawn_blocking(move || {
+                    let db = db.lock().unwrap();
+                    duckdb_rows(&db, &query, &events)
+                })
+                .await
+                .map_err(|err| Error::Database(err.to_string()))?
+            }
+        }
+    }
+
+    /// Close all connections in the pool.
+    pub async fn close(&self) {
+        match self {
+            Self::Postgres(pool) => pool.close().await,
+            Self::Mysql(pool) => pool.close().await,
+            Self::Sqlite(pool) => pool.close().await,
+            Self::Duckdb(_) => {}
+        }
+    }
+}
+
+/// Collects rows into pages of [`PAGE_SIZE`] to send to the frontend.
+#[derive(Default)]
+struct Pager {
+    page: Vec<Vec<Value>>,
+    row_count: u64,
+}
+
+impl Pager {
+    /// Add a row, returning a page of rows once it is full.
+    fn push(&mut self, row: Vec<Value>) -> Option<SqlEvent> {
+        self.page.push(row);
+        self.row_count += 1;
+        (self.page.len() >= PAGE_SIZE).then(|| SqlEvent::Rows(mem::take(&mut self.page)))
+    }
+
+    /// Whether [`MAX_ROWS`] rows have been read.
+    fn is_full(&self) -> bool {
+        self.row_count >= MAX_ROWS as u64
+    }
+
+    /// The last partial page of rows, if any, and the final event.
+    fn finish(self, truncated: bool) -> impl Iterator<Item = SqlEvent> {
+        let done = SqlEvent::Done {
+            row_count: self.row_count,
+            truncated,
+        };
+        let page = (!self.page.is_empty()).then_some(SqlEvent::Rows(self.page));
+        page.into_iter().chain([done])
+    }
+}
+
+/// Try decoding a column as each type in order, returning the first that
+/// matches after converting it into JSON.
+macro_rules! try_decode {
+    ($row:expr, $i:expr, $($ty:ty => $convert:expr),+ $(,)?) => {
+        match $row.try_get_raw($i) {
+            Ok(value) if !value.is_null() => {}
+            _ => return Value::Null,
+        }
+        $(
+            if let Ok(value) = $row.try_get::<$ty, _>($i) {
+                return ($convert)(value);
+            }
+        )+
+        return unsupported($row.column($i).type_info().name());
+    };
+}
+
+/// Placeholder shown for values of a type that can't be decoded.
+fn unsupported(type_name: &str) -> Value {
+    Value::String(format!("<{type_name}>"))
+}
+
+/// Placeholder shown for binary values, which are not sent to the frontend.
+fn bytes(value: Vec<u8>) -> Value {
+    Value::String(format!("<{} bytes>", value.len()))
+}
+
+/// Format a timestamp with an offset as RFC 3339.
+fn timestamp(value: OffsetDateTime) -> Value {
+    Value::String(value.format(&Rfc3339).unwrap_or_else(|_| value.to_string()))
+}
+
+/// Format a value with its `Display` implementation.
+fn display<T: ToString>(value: T) -> Value {
+    Value::String(value.to_string())
+}
+
+fn pg_value(row: &PgRow, i: usize) -> Value {
+    try_decode!(row, i,
+        bool => Value::from,
+        i16 => Value::from,
+        i32 => Value::from,
+        i64 => Value::from,
+        f32 => Value::from,
+        f64 => Value::from,
+        Decimal => display,
+        String => Value::from,
+        Value => |value| value,
+        Uuid => display,
+        OffsetDateTime => timestamp,
+        time::PrimitiveDateTime => display,
+        time::Date => display,
+        time::Time => display,
+        Vec<u8> => bytes,
+    );
+}
+
+fn mysql_value(row: &MySqlRow, i: usize) -> Value {
+    try_decode!(row, i,
+        i64 => Value::from,
+        u64 => Value::from,
+        f64 => Value::from,
+        Decimal => display,
+        String => Value::from,
+        Value => |value| value,
+        OffsetDateTime => timestamp,
+        time::PrimitiveDateTime => display,
+        time::Date => display,
+        time::Time => display,
+        Vec<u8> => bytes,
+    );
+}
+
+fn sqlite_value(row: &SqliteRow, i: usize) -> Value {
+    try_decode!(row, i,
+        i64 => Value::from,
+        f64 => Value::from,
+        String => Value::from,
+        Vec<u8> => bytes,
+    );
+}
+
+/// Run a query on DuckDB, streaming its rows in pages from a blocking thread.
+fn duckdb_rows(
+    db: &duckdb::Connection,
+    query: &str,
+    events: &mpsc::Sender<SqlEvent>,
+) -> Result<(), Error> {
+    let mut statement = db.prepare(query)?;
+    let mut rows = statement.query([])?;
+    let Some(statement) = rows.as_ref() else {
+        return Ok(());
+    };
+    let width = statement.column_count();
+    let columns = (0..width)
+        .map(|i| SqlColumn {
+            name: statement
+                .column_name(i)
+                .map(String::from)
+                .unwrap_or_default(),
+            type_name: statement.column_type(i).to_string(),
+        })
+        .collect();
+    if events.blocking_send(SqlEvent::Columns(columns)).is_err() {
+        return Ok(());
+    }
+
+    let mut pager = Pager::default();
+    let mut truncated = false;
+    while let Some(row) = rows.next()? {
+        if pager.is_full() {
+            truncated = true;
+            break;
+        }
+        let values = (0..width)
+            .map(|i| match row.get_ref(i) {
+                Ok(value) => duckdb_value(duckdb::types::Value::from(value)),
+                Err(_) => Value::Null,
+            })
+            .collect();
+        if let Some(page) = pager.push(values) {
+            if events.blocking_send(page).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    for event in pager.finish(truncated) {
+        _ = events.blocking_send(event);
+    }
+    Ok(())
+}
+
+fn duckdb_value(value: duckdb::types::Value) -> Value {
+    use duckdb::types::{TimeUnit, Value as V};
+
+    let micros = |unit: TimeUnit, n: i64| match unit {
+        TimeUnit::Second => n.saturating_mul(1_000_000),
+        TimeUnit::Millisecond => n.saturating_mul(1_000),
+        TimeUnit::Microsecond => n,
+        TimeUnit::Nanosecond => n / 1_000,
+    };
+    match value {
+        V::Null => Value::Null,
+        V::Boolean(b) => Value::from(b),
+        V::TinyInt(n) => Value::from(n),
+        V::SmallInt(n) => Value::from(n),
+        V::Int(n) => Value::from(n),
+        V::BigInt(n) => Value::from(n),
+        V::HugeInt(n) => display(n),
+        V::UTinyInt(n) => Value::from(n),
+        V::USmallInt(n) => Value::from(n),
+        V::UInt(n) => Value::from(n),
+        V::UBigInt(n) => Value::from(n),
+        V::Float(n) => Value::from(n),
+        V::Double(n) => Value::from(n),
+        V::Decimal(n) => display(n),
+        V::Text(s) | V::Enum(s) => Value::String(s),
+        V::Blob(b) => bytes(b),
+        V::Timestamp(unit, n) => {
+            let nanos = i128::from(micros(unit, n)) * 1_000;
+            match OffsetDateTime::from_unix_timestamp_nanos(nanos) {
+                Ok(ts) => display(ts.date().with_time(ts.time())),
+                Err(_) => Value::from(n),
+            }
+        }
+        V::Date32(days) => match time::Date::from_julian_day(2_440_588 + days) {
+            Ok(date) => display(date),
+            Err(_) => Value::from(days),
+        },
+        V::List(items) | V::Array(items) => {
+            Value::Array(items.into_iter().map(duckdb_value).collect())
+        }
+        other => Value::String(format!("{other:?}")),
+    }
+}
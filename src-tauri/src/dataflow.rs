@@ -0,0 +1,789 @@
+//! Dependency graph between notebook cells, from the variables they use.
+//!
+//! Each Python code cell is scanned for the global names it defines and the
+//! names it reads. A cell depends on the most recent cell above it that defines
+//! each name it reads, like the order the kernel would see them in when running
+//! the notebook top to bottom. In reactive mode, running a cell also re-runs
+//! the cells that depend on it, to keep the notebook consistent.
+//!
+//! This is a lightweight scan of Python tokens rather than a full parser, so it
+//! runs without the kernel and tolerates syntax errors while editing. It misses
+//! dynamic tricks like `globals()` or `exec`, and mutations through method
+//! calls like `df.drop(..., inplace=True)` only count as reads.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::backend::notebook::{Cell, NotebookRoot};
+
+/// Python keywords, which are never variable names.
+pub(crate) const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Global names defined and read by a cell.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CellNames {
+    /// Names bound at the top level, by assignment, imports, `def` or `class`.
+    pub defines: BTreeSet<String>,
+
+    /// Names read that aren't bound locally, in the order-free sense.
+    pub uses: BTreeSet<String>,
+}
+
+/// A lexical token of Python code, with strings and comments dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Op(String),
+    Other,
+}
+
+impl Token {
+    fn is_op(&self, op: &str) -> bool {
+        matches!(self, Token::Op(o) if o == op)
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Token::Name(name) if !KEYWORDS.contains(&name.as_str()) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Name(name) if name == keyword)
+    }
+}
+
+/// A logical line of code, with its indentation.
+struct Line {
+    indent: usize,
+    tokens: Vec<Token>,
+}
+
+/// Operators made of more than one character, longest first.
+const OPERATORS: &[&str] = &[
+    "**=", "//=", ">>=", "<<=", "...", "->", ":=", "==", "!=", "<=", ">=", "+=", "-=", "*=", "/=",
+    "%=", "&=", "|=", "^=", "@=", "**", "//", "<<", ">>",
+];
+
+/// Split Python source into logical lines of tokens.
+///
+/// Lines starting with `%` or `!` are IPython magics and shell commands, and
+/// are skipped.
+fn tokenize(source: &str) -> Vec<Line> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lines = Vec::new();
+    let mut tokens = Vec::new();
+    let mut indent = 0;
+    let mut depth = 0usize;
+    let mut at_line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if at_line_start {
+            let start = i;
+            while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+                i += 1;
+            }
+            indent = i - start;
+            at_line_start = false;
+            if matches!(chars.get(i), Some('%' | '!')) {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            continue;
+        }
+        match c {
+            '\n' => {
+                i += 1;
+                if depth == 0 {
+                    if !tokens.is_empty() {
+                        lines.push(Line {
+                            indent,
+                            tokens: std::mem::take(&mut tokens),
+                        });
+                    }
+                    at_line_start = true;
+                }
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => i += 2,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\'' | '"' => i = skip_string(&chars, i, false, &mut tokens),
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let prefix = word.to_ascii_lowercase();
+                let is_prefix = matches!(
+                    prefix.as_str(),
+                    "r" | "b" | "u" | "f" | "rb" | "br" | "fr" | "rf"
+                );
+                if is_prefix && matches!(chars.get(i), Some('\'' | '"')) {
+                    i = skip_string(&chars, i, prefix.contains('f'), &mut tokens);
+                } else {
+                    tokens.push(Token::Name(word));
+                }
+            }
+            c if c.is_ascii_digit() => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                    i += 1;
+                }
+                tokens.push(Token::Other);
+            }
+            c if c.is_whitespace() => i += 1,
+            _ => {
+                let rest: String = chars[i..chars.len().min(i + 3)].iter().collect();
+                let op = OPERATORS
+                    .iter()
+                    .find(|op| rest.starts_with(*op))
+                    .map(|op| op.to_string())
+                    .unwrap_or_else(|| c.to_string());
+                match op.as_str() {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                i += op.chars().count();
+                tokens.push(Token::Op(op));
+            }
+        }
+    }
+    if !tokens.is_empty() {
+        lines.push(Line { indent, tokens });
+    }
+    lines
+}
+
+/// Skip a string literal starting at its opening quote, returning the index
+/// after it. Expressions in f-strings are tokenized into `tokens`.
+fn skip_string(chars: &[char], start: usize, fstring: bool, tokens: &mut Vec<Token>) -> usize {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = start + if triple { 3 } else { 1 };
+    let mut brace_depth = 0;
+    let mut expr = String::new();
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' {
+            i += 2;
+            continue;
+        }
+        if brace_depth == 0 {
+            if triple && chars[i..].starts_with(&[quote, quote, quote]) {
+                i += 3;
+                break;
+            }
+            if !triple && (c == quote || c == '\n') {
+                i += 1;
+                break;
+            }
+        }
+        if fstring {
+            match c {
+                '{' if brace_depth == 0 && chars.get(i + 1) == Some(&'{') => i += 1,
+                '{' => brace_depth += 1,
+                '}' if brace_depth > 0 => {
+                    brace_depth -= 1;
+                    if brace_depth == 0 {
+                        let inner = expr.split(['!', ':']).next().unwrap_or_default();
+                        tokens.push(Token::Op("(".into()));
+                        for line in tokenize(inner) {
+                            tokens.extend(line.tokens);
+                        }
+                        tokens.push(Token::Op(")".into()));
+                        expr.clear();
+                    }
+                }
+                _ if brace_depth > 0 => expr.push(c),
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    tokens.push(Token::Other);
+    i
+}
+
+/// A scope that names are bound in while scanning.
+struct Scope {
+    /// Indentation of the statement that opened the scope.
+    indent: usize,
+
+    /// Whether this is a function or class body, rather than the cell itself.
+    local: bool,
+
+    /// Names bound in the scope.
+    bound: BTreeSet<String>,
+
+    /// Names declared `global` in the scope.
+    globals: BTreeSet<String>,
+
+    /// Names read in the scope, or in scopes nested inside it.
+    used: BTreeSet<String>,
+}
+
+impl Scope {
+    fn new(indent: usize, local: bool) -> Self {
+        Self {
+            indent,
+            local,
+            bound: BTreeSet::new(),
+            globals: BTreeSet::new(),
+            used: BTreeSet::new(),
+        }
+    }
+}
+
+/// Find the global names that a cell of Python code defines and reads.
+pub fn analyze(source: &str) -> CellNames {
+    // Cell magics like `%%bash` usually hold code in another language.
+    if source.trim_start().starts_with("%%") {
+        return CellNames::default();
+    }
+
+    let mut scopes = vec![Scope::new(0, false)];
+    let mut cell = CellNames::default();
+    let mut pending: Option<Scope> = None;
+
+    for line in tokenize(source) {
+        // A `def` or `class` opens a scope for the lines indented under it.
+        if let Some(scope) = pending.take() {
+            if line.indent > scope.indent {
+                scopes.push(Scope {
+                    indent: line.indent,
+                    ..scope
+                });
+            } else {
+                close_scope(&mut scopes, scope, &mut cell);
+            }
+        }
+        while scopes.len() > 1 && line.indent < scopes.last().unwrap().indent {
+            let scope = scopes.pop().unwrap();
+            close_scope(&mut scopes, scope, &mut cell);
+        }
+
+        let mut names = LineNames::default();
+        let opens = scan_statement(&line.tokens, &mut names);
+        let scope = scopes.last_mut().unwrap();
+        add_uses(scope, names.used, &cell);
+        for name in names.bound {
+            if !scope.local || scope.globals.contains(&name) {
+                cell.defines.insert(name);
+            } else {
+                scope.bound.insert(name);
+            }
+        }
+        scope.globals.extend(names.globals);
+
+        if let Some(params) = opens {
+            let mut scope = Scope::new(line.indent, true);
+            scope.bound = params;
+            // A one-line body, like `def f(): return x`, is in the new scope.
+            if let Some(colon) = body_start(&line.tokens) {
+                let mut body = LineNames::default();
+                scan_statement(&line.tokens[colon..], &mut body);
+                scope.bound.extend(body.bound);
+                scope.used.extend(body.used);
+            }
+            pending = Some(scope);
+        }
+    }
+    if let Some(scope) = pending.take() {
+        close_scope(&mut scopes, scope, &mut cell);
+    }
+    while let Some(scope) = scopes.pop() {
+        close_scope(&mut scopes, scope, &mut cell);
+    }
+    cell
+}
+
+/// Close a scope, passing the names it reads but doesn't bind to its parent.
+fn close_scope(scopes: &mut [Scope], scope: Scope, cell: &mut CellNames) {
+    let free = scope
+        .used
+        .into_iter()
+        .filter(|name| !scope.bound.contains(name) || scope.globals.contains(name));
+    match scopes.last_mut() {
+        Some(parent) => add_uses(parent, free, cell),
+        None => cell.uses.extend(free),
+    }
+}
+
+/// Record names read in a scope. At the top level, names the cell has already
+/// defined refer to its own values, so they aren't dependencies.
+fn add_uses(scope: &mut Scope, used: impl IntoIterator<Item = String>, cell: &CellNames) {
+    let own = |name: &String| !scope.local && cell.defines.contains(name);
+    let used: Vec<String> = used.into_iter().filter(|name| !own(name)).collect();
+    scope.used.extend(used);
+}
+
+/// Index of the first token after the colon ending a `def` or `class` header,
+/// if there are tokens after it.
+fn body_start(tokens: &[Token]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Op(op) if matches!(op.as_str(), "(" | "[" | "{") => depth += 1,
+            Token::Op(op) if matches!(op.as_str(), ")" | "]" | "}") => depth -= 1,
+            Token::Op(op) if op == ":" && depth == 0 => {
+                return (i + 1 < tokens.len()).then_some(i + 1);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Names bound and read by a single statement.
+#[derive(Default)]
+struct LineNames {
+    bound: BTreeSet<String>,
+    globals: BTreeSet<String>,
+    used: BTreeSet<String>,
+}
+
+/// Scan a statement for the names it binds and reads.
+///
+/// If the statement is a `def` or `class` header, this returns the names bound
+/// in the body it opens, like function parameters.
+fn scan_statement(tokens: &[Token], names: &mut LineNames) -> Option<BTreeSet<String>> {
+    let first = tokens.first()?;
+    let tokens = if first.is_keyword("async") {
+        &tokens[1..]
+    } else {
+        tokens
+    };
+    let first = tokens.first()?;
+
+    if first.is_op("@") {
+        scan_expression(&tokens[1..], names);
+        return None;
+    }
+    if first.is_keyword("def") || first.is_keyword("class") {
+        let name = tokens.get(1)?.name()?;
+        names.bound.insert(name.into());
+        let header_end = body_start(tokens).map_or(tokens.len(), |i| i - 1);
+        let header = &tokens[2..header_end];
+        if first.is_keyword("def") {
+            return Some(scan_parameters(header, names));
+        }
+        scan_expression(header, names);
+        return Some(BTreeSet::new());
+    }
+    if first.is_keyword("import") {
+        for clause in split_top_level(&tokens[1..], ",") {
+            let bound = match clause {
+                [.., as_, Token::Name(alias)] if as_.is_keyword("as") => alias,
+                [Token::Name(module), ..] => module,
+                _ => continue,
+            };
+            names.bound.insert(bound.clone());
+        }
+        return None;
+    }
+    if first.is_keyword("from") {
+        let import = tokens.iter().position(|t| t.is_keyword("import"))?;
+        let imported: Vec<Token> = tokens[import + 1..]
+            .iter()
+            .filter(|t| !t.is_op("(") && !t.is_op(")"))
+            .cloned()
+            .collect();
+        for clause in split_top_level(&imported, ",") {
+            let bound = match clause {
+                [.., as_, Token::Name(alias)] if as_.is_keyword("as") => alias,
+                [Token::Name(name)] => name,
+                _ => continue,
+            };
+            names.bound.insert(bound.clone());
+        }
+        return None;
+    }
+    if first.is_keyword("global") || first.is_keyword("nonlocal") {
+        let declared = tokens[1..].iter().filter_map(Token::name).map(String::from);
+        if first.is_keyword("global") {
+            names.globals.extend(declared);
+        }
+        return None;
+    }
+    if first.is_keyword("for") {
+        let in_ = tokens.iter().position(|t| t.is_keyword("in"))?;
+        bind_targets(&tokens[1..in_], names);
+        scan_expression(&tokens[in_ + 1..], names);
+        return None;
+    }
+    if first.is_keyword("with") || first.is_keyword("except") {
+        let mut rest = &tokens[1..];
+        while let Some(as_) = rest.iter().position(|t| t.is_keyword("as")) {
+            scan_expression(&rest[..as_], names);
+            let end = rest[as_..]
+                .iter()
+                .position(|t| t.is_op(",") || t.is_op(":"))
+                .map_or(rest.len(), |i| as_ + i);
+            bind_targets(&rest[as_ + 1..end], names);
+            rest = &rest[end..];
+        }
+        scan_expression(rest, names);
+        return None;
+    }
+
+    // Annotated assignments, like `x: int = 1`.
+    if let (Some(name), Some(colon)) = (first.name(), tokens.get(1)) {
+        if colon.is_op(":") {
+            names.bound.insert(name.into());
+            scan_expression(&tokens[2..], names);
+            return None;
+        }
+    }
+
+    // Augmented assignments, like `x += 1`, both read and bind the target.
+    if let Some(op) = tokens.iter().position(|t| {
+        matches!(t, Token::Op(op) if op.len() >= 2 && op.ends_with('=')
+            && !matches!(op.as_str(), "==" | "!=" | "<=" | ">=" | ":="))
+    }) {
+        scan_expression(tokens, names);
+        bind_targets(&tokens[..op], names);
+        return None;
+    }
+
+    // Assignments, like `a = b = f(x)`, where all but the last part are targets.
+    let parts = split_top_level(tokens, "=");
+    let (value, targets) = parts.split_last()?;
+    for target in targets {
+        bind_targets(target, names);
+    }
+    scan_expression(value, names);
+    None
+}
+
+/// Scan the parameter list of a `def`, returning the parameter names. Default
+/// values and annotations are read in the enclosing scope.
+fn scan_parameters(header: &[Token], names: &mut LineNames) -> BTreeSet<String> {
+    let mut params = BTreeSet::new();
+    let mut depth = 0;
+    for (i, token) in header.iter().enumerate() {
+        match token {
+            Token::Op(op) if matches!(op.as_str(), "(" | "[" | "{") => depth += 1,
+            Token::Op(op) if matches!(op.as_str(), ")" | "]" | "}") => depth -= 1,
+            Token::Name(_) if depth == 1 => {
+                let after_separator = i > 0
+                    && matches!(&header[i - 1], Token::Op(op)
+                        if matches!(op.as_str(), "(" | "," | "*" | "**"));
+                if let (true, Some(name)) = (after_separator, token.name()) {
+                    params.insert(name.to_string());
+                    continue;
+                }
+            }
+            _ => {}
+        }
+    }
+    let rest: Vec<Token> = header
+        .iter()
+        .filter(|t| !matches!(t.name(), Some(name) if params.contains(name)))
+        .cloned()
+        .collect();
+    scan_expression(&rest, names);
+    params
+}
+
+/// Bind the names in an assignment target, like `a, (b, c)` or `*rest`.
+///
+/// Names used to reach a target, like `a` in `a[i] = 1` or `a.x = 1`, are read
+/// instead of bound.
+fn bind_targets(tokens: &[Token], names: &mut LineNames) {
+    let mut subscript_depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|i| &tokens[i]);
+        let next = tokens.get(i + 1);
+        match token {
+            Token::Op(op) if op == "[" || op == "(" => {
+                let is_subscript = subscript_depth > 0
+                    || prev.is_some_and(|p| p.name().is_some() || p.is_op(")") || p.is_op("]"));
+                if is_subscript {
+                    subscript_depth += 1;
+                }
+            }
+            Token::Op(op) if (op == "]" || op == ")") && subscript_depth > 0 => {
+                subscript_depth -= 1;
+            }
+            _ => {}
+        }
+        let Some(name) = token.name() else {
+            continue;
+        };
+        let attribute = prev.is_some_and(|p| p.is_op("."));
+        let reached = next.is_some_and(|n| n.is_op(".") || n.is_op("[") || n.is_op("("));
+        if attribute {
+            continue;
+        } else if subscript_depth > 0 || reached {
+            names.used.insert(name.into());
+        } else {
+            names.bound.insert(name.into());
+        }
+    }
+}
+
+/// Read the names in an expression, skipping attributes, keyword arguments,
+/// and variables local to lambdas and comprehensions.
+fn scan_expression(tokens: &[Token], names: &mut LineNames) {
+    let mut local = BTreeSet::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_keyword("lambda") {
+            let end = tokens[i..]
+                .iter()
+                .position(|t| t.is_op(":"))
+                .map_or(tokens.len(), |j| i + j);
+            local.extend(
+                tokens[i + 1..end]
+                    .iter()
+                    .filter_map(Token::name)
+                    .map(String::from),
+            );
+        } else if token.is_keyword("for") {
+            let end = tokens[i..]
+                .iter()
+                .position(|t| t.is_keyword("in"))
+                .map_or(tokens.len(), |j| i + j);
+            local.extend(
+                tokens[i + 1..end]
+                    .iter()
+                    .filter_map(Token::name)
+                    .map(String::from),
+            );
+        }
+    }
+
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Op(op) if matches!(op.as_str(), "(" | "[" | "{") => depth += 1,
+            Token::Op(op) if matches!(op.as_str(), ")" | "]" | "}") => depth -= 1,
+            _ => {}
+        }
+        let Some(name) = token.name() else {
+            continue;
+        };
+        let prev = i.checked_sub(1).map(|i| &tokens[i]);
+        let next = tokens.get(i + 1);
+        if prev.is_some_and(|p| p.is_op(".")) || local.contains(name) {
+            continue;
+        }
+        if depth > 0 && next.is_some_and(|n| n.is_op("=")) {
+            continue; // Keyword argument.
+        }
+        if next.is_some_and(|n| n.is_op(":=")) {
+            names.bound.insert(name.into());
+        } else {
+            names.used.insert(name.into());
+        }
+    }
+}
+
+/// Split tokens on an operator outside of brackets.
+fn split_top_level<'a>(tokens: &'a [Token], separator: &str) -> Vec<&'a [Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Op(op) if matches!(op.as_str(), "(" | "[" | "{") => depth += 1,
+            Token::Op(op) if matches!(op.as_str(), ")" | "]" | "}") => depth -= 1,
+            Token::Op(op) if op == separator && depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// Dependencies of one code cell on the cells above it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CellDependencies {
+    /// ID of the cell.
+    pub id: String,
+
+    /// Global names defined by the cell.
+    pub defines: Vec<String>,
+
+    /// Names read by the cell, including builtins and names defined nowhere.
+    pub uses: Vec<String>,
+
+    /// IDs of the cells that define names read by this cell, in notebook order.
+    pub depends_on: Vec<String>,
+}
+
+crate::export_bindings!(CellDependencies);
+
+/// Dependency graph of the code cells in a notebook.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct DependencyGraph {
+    /// Code cells with IDs, in notebook order.
+    pub cells: Vec<CellDependencies>,
+}
+
+crate::export_bindings!(DependencyGraph);
+
+impl DependencyGraph {
+    /// Build the dependency graph of a notebook's code cells.
+    pub fn build(notebook: &NotebookRoot) -> Self {
+        let mut graph = Self::default();
+        let mut definers: BTreeMap<String, String> = BTreeMap::new();
+        for cell in &notebook.cells {
+            let Cell::Code(cell) = cell else {
+                continue;
+            };
+            let Some(id) = cell.id.clone() else {
+                continue;
+            };
+            let names = analyze(&String::from(cell.source.clone()));
+            let mut depends_on = Vec::new();
+            for name in &names.uses {
+                if let Some(definer) = definers.get(name) {
+                    if !depends_on.contains(definer) {
+                        depends_on.push(definer.clone());
+                    }
+                }
+            }
+            let order = |id: &String| graph.cells.iter().position(|c| &c.id == id);
+            depends_on.sort_by_key(order);
+            for name in &names.defines {
+                definers.insert(name.clone(), id.clone());
+            }
+            graph.cells.push(CellDependencies {
+                id,
+                defines: names.defines.into_iter().collect(),
+                uses: names.uses.into_iter().collect(),
+                depends_on,
+            });
+        }
+        graph
+    }
+
+    /// Cells that depend on a cell, directly or through other cells, in the
+    /// order they should be re-run.
+    pub fn dependents(&self, id: &str) -> Vec<String> {
+        let mut stale = BTreeSet::from([id]);
+        let mut dependents = Vec::new();
+        for cell in &self.cells {
+            if cell.depends_on.iter().any(|d| stale.contains(d.as_str())) {
+                stale.insert(&cell.id);
+                dependents.push(cell.id.clone());
+            }
+        }
+        dependents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::NotebookFormat;
+
+    fn names(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn analyze_names() {
+        let cell = analyze(
+            r#"
+import numpy as np
+from os import path, sep as separator
+x, (y, z) = np.zeros(3), f(key=w)
+data[i] += 1
+total: int = sum(v for v in values)
+
+def scale(a, factor=default_factor):
+    local = a * factor * global_scale
+    return local
+
+class Model(Base):
+    size = 3
+
+@decorator
+async def run():
+    global counter
+    counter = 1
+
+print(f"{message!r} and {{literal}}")  # comment = ignored
+%matplotlib inline
+"#,
+        );
+        assert_eq!(
+            cell.defines,
+            names(&[
+                "Model",
+                "counter",
+                "np",
+                "path",
+                "run",
+                "scale",
+                "separator",
+                "total",
+                "x",
+                "y",
+                "z"
+            ])
+        );
+        assert_eq!(
+            cell.uses,
+            names(&[
+                "Base",
+                "data",
+                "decorator",
+                "default_factor",
+                "f",
+                "global_scale",
+                "i",
+                "int",
+                "message",
+                "print",
+                "sum",
+                "values",
+                "w",
+            ])
+        );
+        assert_eq!(analyze("%%bash\nx=1\n"), CellNames::default());
+    }
+
+    #[test]
+    fn reactive_dependents() {
+        let mut notebook = NotebookFormat::Percent
+            .read(concat!(
+                "# %%\nx = 1\n\n",
+                "# %%\ny = x + 1\n\n",
+                "# %%\nprint(y)\n\n",
+                "# %%\nx = 10\n\n",
+                "# %%\nprint(x)\n",
+            ))
+            .unwrap();
+        for (i, cell) in notebook.cells.iter_mut().enumerate() {
+            if let Cell::Code(cell) = cell {
+                cell.id = Some(format!("c{i}"));
+            }
+        }
+        let graph = DependencyGraph::build(&notebook);
+        assert_eq!(graph.cells[1].depends_on, ["c0"]);
+        assert_eq!(graph.cells[4].depends_on, ["c3"]);
+        assert_eq!(graph.dependents("c0"), ["c1", "c2"]);
+        assert_eq!(graph.dependents("c3"), ["c4"]);
+        assert!(graph.dependents("c4").is_empty());
+    }
+}
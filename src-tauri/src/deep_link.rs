@@ -0,0 +1,123 @@
+//! Parsing of `jute://` deep links, which open the app from other tools.
+//!
+//! Links can open a notebook, like `jute://open?path=/home/me/a.ipynb`,
+//! optionally at a cell given by its ID or heading anchor with `&cell=`, or
+//! connect to a Jupyter server, like
+//! `jute://server/connect?url=http://localhost:8888/&token=abc`. Routing them
+//! to a window is handled by [`commands::deep_link`].
+//!
+//! [`commands::deep_link`]: crate::commands::deep_link
+
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::Error;
+
+/// The URL scheme registered for deep links.
+pub const SCHEME: &str = "jute";
+
+/// An action requested by a deep link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeepLink {
+    /// Open a notebook file.
+    Open {
+        /// Path to the notebook, which is an `.ipynb` file.
+        path: PathBuf,
+
+        /// Cell to scroll to, by ID or heading anchor.
+        cell: Option<String>,
+    },
+
+    /// Save a Jupyter server and connect to it.
+    ConnectServer {
+        /// Base URL of the server.
+        url: String,
+
+        /// Token used to authenticate with the server, if any.
+        token: Option<String>,
+    },
+}
+
+impl DeepLink {
+    /// Parse a deep link from its URL.
+    pub fn parse(url: &Url) -> Result<Self, Error> {
+        let invalid = || Error::InvalidDeepLink(url.to_string());
+        if url.scheme() != SCHEME {
+            return Err(invalid());
+        }
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .filter(|value| !value.is_empty())
+        };
+        match (url.host_str(), url.path().trim_end_matches('/')) {
+            (Some("open"), "") => {
+                let path = PathBuf::from(param("path").ok_or_else(invalid)?);
+                let is_notebook =
+                    (path.extension()).is_some_and(|ext| ext.eq_ignore_ascii_case("ipynb"));
+                if !is_notebook {
+                    return Err(invalid());
+                }
+                Ok(DeepLink::Open {
+                    path,
+                    cell: param("cell"),
+                })
+            }
+            (Some("server"), "/connect") => Ok(DeepLink::ConnectServer {
+                url: Url::parse(&param("url").ok_or_else(invalid)?)?.to_string(),
+                token: param("token"),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Result<DeepLink, Error> {
+        DeepLink::parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parse_deep_links() {
+        assert_eq!(
+            parse("jute://open?path=%2Fhome%2Fme%2Fa%20b.ipynb").unwrap(),
+            DeepLink::Open {
+                path: "/home/me/a b.ipynb".into(),
+                cell: None,
+            },
+        );
+        assert_eq!(
+            parse("jute://open?path=a.ipynb&cell=getting-started").unwrap(),
+            DeepLink::Open {
+                path: "a.ipynb".into(),
+                cell: Some("getting-started".into()),
+            },
+        );
+        assert_eq!(
+            parse("jute://server/connect?url=http%3A%2F%2Flocalhost%3A8888&token=abc").unwrap(),
+            DeepLink::ConnectServer {
+                url: "http://localhost:8888/".into(),
+                token: Some("abc".into()),
+            },
+        );
+        assert_eq!(
+            parse("jute://server/connect/?url=https://example.com/lab/").unwrap(),
+            DeepLink::ConnectServer {
+                url: "https://example.com/lab/".into(),
+                token: None,
+            },
+        );
+
+        assert!(parse("jute://open").is_err());
+        assert!(parse("jute://open?path=%2Fbin%2Fsh").is_err());
+        assert!(parse("jute://open?path=a.ipynb.sh").is_err());
+        assert!(parse("jute://server/connect?url=not%20a%20url").is_err());
+        assert!(parse("jute://delete?path=a.ipynb").is_err());
+        assert!(parse("https://open?path=a.ipynb").is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! Notebook documents held by the backend, which are the source of truth for
+//! the contents of open notebooks.
+//!
+//! Windows change a document by sending [`NotebookOp`]s, which are applied in
+//! order and broadcast as [`DOC_CHANGE_EVENT`], so that every window showing
+//! the notebook stays up to date. Saving and exporting read the document,
+//! rather than a copy of the notebook sent by one window.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    backend::notebook::{Cell, NotebookRoot, Output},
+    entity::EntityId,
+    Error,
+};
+
+/// Name of the event emitted to every window when a document changes.
+pub const DOC_CHANGE_EVENT: &str = "notebook-doc-change";
+
+/// A change to the cells of a notebook document.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum NotebookOp {
+    /// Insert a new cell at an index, which must have a unique ID.
+    InsertCell {
+        /// Index of the cell after it is inserted.
+        index: usize,
+        /// The new cell.
+        cell: Cell,
+    },
+
+    /// Delete a cell.
+    DeleteCell {
+        /// ID of the cell.
+        cell_id: String,
+    },
+
+    /// Move a cell to another index.
+    MoveCell {
+        /// ID of the cell.
+        cell_id: String,
+        /// Index of the cell after it is moved.
+        index: usize,
+    },
+
+    /// Replace the source of a cell.
+    EditSource {
+        /// ID of the cell.
+        cell_id: String,
+        /// The new source.
+        source: String,
+    },
+
+    /// Replace the outputs of a code cell, as after it runs.
+    SetOutputs {
+        /// ID of the cell.
+        cell_id: String,
+        /// The new outputs.
+        outputs: Vec<Output>,
+        /// The new execution count.
+        execution_count: Option<u32>,
+    },
+
+    /// Replace a whole cell with the same ID, as when its type or metadata
+    /// change.
+    ReplaceCell {
+        /// The new cell.
+        cell: Cell,
+    },
+}
+
+crate::export_bindings!(NotebookOp);
+
+/// Payload of [`DOC_CHANGE_EVENT`].
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct DocChangeEvent {
+    /// ID of the document that changed.
+    #[ts(type = "string")]
+    pub doc_id: EntityId,
+
+    /// Version of the document after the change.
+    #[ts(type = "number")]
+    pub version: u64,
+
+    /// Operations that were applied, in order.
+    pub ops: Vec<NotebookOp>,
+
+    /// ID of the notebook that sent the operations, which already has them.
+    /// Each tab has its own, so tabs in a window showing the same document
+    /// stay in sync too.
+    pub origin: String,
+}
+
+crate::export_bindings!(DocChangeEvent);
+
+/// The contents of a document, sent to windows when they open it.
+#[derive(Serialize, Clone, Debug, TS)]
+pub struct DocSnapshot {
+    /// ID of the document.
+    #[ts(type = "string")]
+    pub doc_id: EntityId,
+
+    /// Version of the document, incremented by each operation.
+    #[ts(type = "number")]
+    pub version: u64,
+
+    /// The notebook.
+    pub notebook: NotebookRoot,
+}
+
+crate::export_bindings!(DocSnapshot);
+
+/// A notebook open in one or more windows.
+#[derive(Debug)]
+pub struct NotebookDoc {
+    /// Path to the notebook file, if it has been saved.
+    pub path: Option<PathBuf>,
+
+    /// Labels of the windows showing the document, by the ID of the notebook
+    /// that shows it, since a window can show it in more than one tab.
+    pub viewers: HashMap<String, String>,
+
+    /// Modification time of the file when the document was read from it or
+    /// last saved, in milliseconds since the Unix epoch.
+    pub modified: Option<u64>,
+
+    notebook: NotebookRoot,
+    version: u64,
+}
+
+impl NotebookDoc {
+    /// Create a document from a notebook, giving its cells IDs if needed.
+    pub fn new(path: Option<PathBuf>, mut notebook: NotebookRoot) -> Self {
+        notebook.normalize_cell_ids();
+        Self {
+            path,
+            viewers: HashMap::new(),
+            modified: None,
+            notebook,
+            version: 0,
+        }
+    }
+
+    /// The current contents of the document.
+    pub fn notebook(&self) -> &NotebookRoot {
+        &self.notebook
+    }
+
+    /// The number of operations applied to the document.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Take a snapshot of the document, to send to a window.
+    pub fn snapshot(&self, doc_id: EntityId) -> DocSnapshot {
+        DocSnapshot {
+            doc_id,
+            version: self.version,
+            notebook: self.notebook.clone(),
+        }
+    }
+
+    /// Index of the cell with an ID.
+    fn index(&self, cell_id: &str) -> Result<usize, Error> {
+        (self.notebook.cells.iter())
+            .position(|cell| cell.id() == Some(cell_id))
+            .ok_or_else(|| Error::InvalidNotebookOp(format!("no cell with ID {cell_id}")))
+    }
+
+    /// Apply an operation, leaving the document unchanged if it fails.
+    pub fn apply(&mut self, op: &NotebookOp) -> Result<(), Error> {
+        match op {
+            NotebookOp::InsertCell { index, cell } => {
+                let Some(cell_id) = cell.id() else {
+                    return Err(Error::InvalidNotebookOp("new cell has no ID".into()));
+                };
+                if *index > self.notebook.cells.len() {
+                    return Err(Error::InvalidNotebookOp(format!(
+                        "index {index} is past the end"
+                    )));
+                }
+                if self.index(cell_id).is_ok() {
+                    return Err(Error::InvalidNotebookOp(format!(
+                        "a cell with ID {cell_id} already exists"
+                    )));
+                }
+                self.notebook.cells.insert(*index, cell.clone());
+            }
+            NotebookOp::DeleteCell { cell_id } => {
+                let index = self.index(cell_id)?;
+                self.notebook.cells.remove(index);
+            }
+            NotebookOp::MoveCell { cell_id, index } => {
+                let from = self.index(cell_id)?;
+                let cell = self.notebook.cells.remove(from);
+                let to = (*index).min(self.notebook.cells.len());
+                self.notebook.cells.insert(to, cell);
+            }
+            NotebookOp::EditSource { cell_id, source } => {
+                let index = self.index(cell_id)?;
+                self.notebook.cells[index].set_source(source.clone());
+            }
+            NotebookOp::SetOutputs {
+                cell_id,
+                outputs,
+                execution_count,
+            } => {
+                let index = self.index(cell_id)?;
+                let Cell::Code(cell) = &mut self.notebook.cells[index] else {
+                    return Err(Error::InvalidNotebookOp(format!(
+                        "cell {cell_id} is not a code cell"
+                    )));
+                };
+                cell.outputs.clone_from(outputs);
+                cell.execution_count = *execution_count;
+            }
+            NotebookOp::ReplaceCell { cell } => {
+                let cell_id = cell.id().unwrap_or_default();
+                let index = self.index(cell_id)?;
+                self.notebook.cells[index] = cell.clone();
+            }
+        }
+        self.version += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(id: &str, cell_type: &str) -> Cell {
+        let mut cell = serde_json::json!({
+            "cell_type": cell_type,
+            "id": id,
+            "metadata": {},
+            "source": "",
+        });
+        if cell_type == "code" {
+            cell["outputs"] = serde_json::json!([]);
+            cell["execution_count"] = serde_json::Value::Null;
+        }
+        serde_json::from_value(cell).unwrap()
+    }
+
+    fn ids(doc: &NotebookDoc) -> Vec<&str> {
+        (doc.notebook().cells.iter())
+            .map(|cell| cell.id().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn apply_ops() {
+        let mut doc = NotebookDoc::new(None, NotebookRoot::from_cells(vec![]));
+
+        for (index, id) in ["a", "b", "c"].into_iter().enumerate() {
+            let op = NotebookOp::InsertCell {
+                index,
+                cell: cell(id, "code"),
+            };
+            doc.apply(&op).unwrap();
+        }
+        let op = NotebookOp::MoveCell {
+            cell_id: "c".into(),
+            index: 0,
+        };
+        doc.apply(&op).unwrap();
+        assert_eq!(ids(&doc), ["c", "a", "b"]);
+
+        let op = NotebookOp::EditSource {
+            cell_id: "a".into(),
+            source: "print(1)".into(),
+        };
+        doc.apply(&op).unwrap();
+        assert_eq!(doc.notebook().cells[1].source(), "print(1)");
+
+        let op = NotebookOp::SetOutputs {
+            cell_id: "a".into(),
+            outputs: vec![],
+            execution_count: Some(3),
+        };
+        doc.apply(&op).unwrap();
+        let op = NotebookOp::DeleteCell {
+            cell_id: "b".into(),
+        };
+        doc.apply(&op).unwrap();
+        assert_eq!(ids(&doc), ["c", "a"]);
+        assert_eq!(doc.version(), 7);
+
+        // Invalid operations leave the document unchanged.
+        let op = NotebookOp::InsertCell {
+            index: 0,
+            cell: cell("a", "markdown"),
+        };
+        assert!(doc.apply(&op).is_err());
+        doc.apply(&NotebookOp::ReplaceCell {
+            cell: cell("c", "markdown"),
+        })
+        .unwrap();
+        let op = NotebookOp::SetOutputs {
+            cell_id: "c".into(),
+            outputs: vec![],
+            execution_count: None,
+        };
+        assert!(doc.apply(&op).is_err());
+        assert_eq!(doc.version(), 8);
+
+        // Operations from the frontend are tagged by name.
+        let op: NotebookOp = serde_json::from_value(serde_json::json!({
+            "op": "delete_cell",
+            "cell_id": "c",
+        }))
+        .unwrap();
+        assert_eq!(
+            op,
+            NotebookOp::DeleteCell {
+                cell_id: "c".into()
+            }
+        );
+    }
+}
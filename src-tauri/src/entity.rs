@@ -1,17 +1,42 @@
 //! Types for generating and representing unique, labelled IDs.
 
-use std::{array, fmt, str::FromStr};
+use std::{array, fmt, str::FromStr, sync::Arc};
 
 use anyhow::bail;
+use dashmap::DashSet;
 use rand::Rng;
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use strum::{EnumIter, IntoEnumIterator};
 
+use crate::Error;
+
 /// Entity category for generated IDs.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
 pub enum Entity {
     /// Python virtual environments created by Jute.
     Venv,
+
+    /// Running Jupyter kernels.
+    Kernel,
+
+    /// Remote Jupyter servers that kernels can be started on.
+    Server,
+
+    /// Notebook sessions, each open in a window.
+    Session,
+
+    /// Long-running background tasks.
+    Task,
+
+    /// Saved database connections for SQL cells.
+    Database,
+
+    /// Shells running in the integrated terminal.
+    Terminal,
+
+    /// Notebook documents held by the backend, shared by the windows showing
+    /// them.
+    Document,
 }
 
 impl Entity {
@@ -19,12 +44,19 @@ impl Entity {
     pub const fn id_prefix(&self) -> &'static str {
         match self {
             Entity::Venv => "ve-",
+            Entity::Kernel => "kn-",
+            Entity::Server => "sv-",
+            Entity::Session => "ss-",
+            Entity::Task => "tk-",
+            Entity::Database => "db-",
+            Entity::Terminal => "tm-",
+            Entity::Document => "nd-",
         }
     }
 }
 
 /// An entity ID generated for a specific category of object.
-#[derive(Clone, Copy, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
 pub struct EntityId {
     /// Which kind of entity this ID represents.
     pub kind: Entity,
@@ -46,6 +78,18 @@ impl EntityId {
         let id = array::from_fn(|_| charset[rng.gen_range(0..charset.len())]);
         EntityId { kind, id }
     }
+
+    /// Check that this ID is of the expected kind, e.g., when received over
+    /// IPC.
+    pub fn expect_kind(self, kind: Entity) -> Result<Self, Error> {
+        if self.kind == kind {
+            Ok(self)
+        } else {
+            Err(Error::InvalidEntity(format!(
+                "expected {kind:?} ID, got {self}"
+            )))
+        }
+    }
 }
 
 impl fmt::Display for EntityId {
@@ -89,6 +133,75 @@ impl FromStr for EntityId {
     }
 }
 
+/// Registry of live entities, which owns the creation of their IDs.
+///
+/// IDs passed from the frontend are looked up here, so a stale ID or one of
+/// the wrong kind is rejected with an error before it reaches other state.
+#[derive(Clone, Debug, Default)]
+pub struct EntityRegistry {
+    live: Arc<DashSet<EntityId>>,
+}
+
+impl EntityRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate a new unique ID of the given kind and register it.
+    pub fn create(&self, kind: Entity) -> EntityId {
+        loop {
+            let id = EntityId::new(kind);
+            if self.live.insert(id) {
+                return id;
+            }
+        }
+    }
+
+    /// Register an existing ID, such as one loaded from disk, returning
+    /// whether it was newly inserted.
+    pub fn insert(&self, id: EntityId) -> bool {
+        self.live.insert(id)
+    }
+
+    /// Look up a live entity, checking that it has the expected kind.
+    pub fn get(&self, id: EntityId, kind: Entity) -> Result<EntityId, Error> {
+        let id = id.expect_kind(kind)?;
+        if self.live.contains(&id) {
+            Ok(id)
+        } else {
+            Err(Error::InvalidEntity(format!("no such entity {id}")))
+        }
+    }
+
+    /// Parse and look up a live entity from a string ID.
+    pub fn parse(&self, id: &str, kind: Entity) -> Result<EntityId, Error> {
+        let id = id
+            .parse::<EntityId>()
+            .map_err(|err| Error::InvalidEntity(err.to_string()))?;
+        self.get(id, kind)
+    }
+
+    /// Check whether an entity is live.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.live.contains(&id)
+    }
+
+    /// Unregister an entity, returning whether it was live.
+    pub fn remove(&self, id: EntityId) -> bool {
+        self.live.remove(&id).is_some()
+    }
+
+    /// List all live entities of a kind.
+    pub fn list(&self, kind: Entity) -> Vec<EntityId> {
+        self.live
+            .iter()
+            .filter(|id| id.kind == kind)
+            .map(|id| *id)
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,4 +235,29 @@ mod tests {
         assert_eq!(parsed.kind, Entity::Venv);
         assert_eq!(parsed.to_string(), "ve-1234567890ab");
     }
+
+    #[test]
+    fn test_entity_prefixes_unique() {
+        let prefixes: DashSet<_> = Entity::iter().map(|kind| kind.id_prefix()).collect();
+        assert_eq!(prefixes.len(), Entity::iter().count());
+    }
+
+    #[test]
+    fn test_entity_registry() {
+        let registry = EntityRegistry::new();
+        let kernel = registry.create(Entity::Kernel);
+        assert!(kernel.to_string().starts_with("kn-"));
+        assert_eq!(registry.get(kernel, Entity::Kernel).unwrap(), kernel);
+        assert!(registry.get(kernel, Entity::Task).is_err());
+        assert_eq!(
+            registry.parse(&kernel.to_string(), Entity::Kernel).unwrap(),
+            kernel
+        );
+        assert!(registry.parse("kn-bad", Entity::Kernel).is_err());
+        assert_eq!(registry.list(Entity::Kernel), [kernel]);
+
+        assert!(registry.remove(kernel));
+        assert!(registry.get(kernel, Entity::Kernel).is_err());
+        assert!(registry.list(Entity::Kernel).is_empty());
+    }
 }
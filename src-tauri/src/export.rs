@@ -0,0 +1,194 @@
+//! Exporting notebooks to other formats, such as slideshows and previews.
+//!
+//! Each exporter takes a [`NotebookRoot`] and produces a single file. Cell
+//! sources and outputs are rendered to HTML by the shared functions here, with
+//! images embedded as data URLs, so exported files don't depend on anything
+//! next to them.
+
+use pulldown_cmark::{html, Event, Options, Parser};
+use serde_json::Value;
+
+use crate::backend::notebook::{MimeBundle, MultilineString, NotebookRoot, Output};
+
+pub mod live;
+pub mod preview;
+pub mod slides;
+
+/// Escape text for use in HTML content or attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Remove ANSI escape sequences, such as colors in error tracebacks.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Control sequences end with a byte in the range `@` to `~`.
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Name of the notebook's programming language, used to highlight code.
+pub fn language(notebook: &NotebookRoot) -> &str {
+    let metadata = &notebook.metadata;
+    metadata
+        .language_info
+        .as_ref()
+        .map(|info| info.name.as_str())
+        .or_else(|| {
+            let spec = metadata.kernelspec.as_ref()?;
+            spec.other.get("language")?.as_str()
+        })
+        .unwrap_or("python")
+}
+
+/// Render the source of a code cell as a highlighted code block.
+pub fn render_code(source: &MultilineString, language: &str) -> String {
+    let source = String::from(source.clone());
+    format!(
+        "<pre class=\"input\"><code class=\"language-{}\">{}</code></pre>",
+        escape_html(language),
+        escape_html(&source),
+    )
+}
+
+/// Render the source of a Markdown cell as HTML.
+///
+/// Raw HTML in the source is escaped and shown as text, so that rendered
+/// Markdown never runs scripts.
+pub fn render_markdown(source: &MultilineString) -> String {
+    let source = String::from(source.clone());
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let parser = Parser::new_ext(&source, options).map(|event| match event {
+        Event::Html(text) | Event::InlineHtml(text) => Event::Text(text),
+        event => event,
+    });
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, parser);
+    format!("<div class=\"markdown\">{rendered}</div>")
+}
+
+/// Render an output of a code cell.
+pub fn render_output(output: &Output) -> String {
+    match output {
+        Output::Stream(stream) => format!(
+            "<pre class=\"output output-{}\">{}</pre>",
+            escape_html(&stream.name),
+            escape_html(&strip_ansi(&String::from(stream.text.clone()))),
+        ),
+        Output::Error(error) => format!(
+            "<pre class=\"output output-error\">{}</pre>",
+            escape_html(&strip_ansi(&error.traceback.join("\n"))),
+        ),
+        Output::ExecuteResult(result) => render_mime_bundle(&result.data),
+        Output::DisplayData(display) => render_mime_bundle(&display.data),
+    }
+}
+
+/// Render the richest representation in a MIME bundle that can be shown in a
+/// static page, or nothing if there is none.
+fn render_mime_bundle(data: &MimeBundle) -> String {
+    let text = |mime: &str| data.get(mime).and_then(mime_text);
+    if let Some(html) = text("text/html") {
+        return format!("<div class=\"output output-html\">{html}</div>");
+    }
+    if let Some(svg) = text("image/svg+xml") {
+        return format!("<div class=\"output output-image\">{svg}</div>");
+    }
+    for mime in ["image/png", "image/jpeg", "image/gif"] {
+        if let Some(image) = text(mime) {
+            // Images are stored as base64, sometimes broken across lines.
+            let image: String = image.split_whitespace().collect();
+            return format!(
+                "<div class=\"output output-image\"><img src=\"data:{mime};base64,{image}\"></div>"
+            );
+        }
+    }
+    if let Some(plain) = text("text/plain") {
+        return format!(
+            "<pre class=\"output output-text\">{}</pre>",
+            escape_html(&strip_ansi(&plain)),
+        );
+    }
+    String::new()
+}
+
+/// Read text data from a MIME bundle, which may be split into lines.
+fn mime_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(lines) => lines.iter().map(|line| line.as_str()).collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_and_strip() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;",
+        );
+        assert_eq!(
+            strip_ansi("\x1b[0;31mValueError\x1b[0m: bad value"),
+            "ValueError: bad value",
+        );
+    }
+
+    #[test]
+    fn render_outputs() {
+        let output: Output = serde_json::from_value(serde_json::json!({
+            "output_type": "display_data",
+            "data": {
+                "image/png": "iVBORw0K\nGgo=\n",
+                "text/plain": "<Figure size 640x480>",
+            },
+            "metadata": {},
+        }))
+        .unwrap();
+        assert_eq!(
+            render_output(&output),
+            "<div class=\"output output-image\"><img \
+             src=\"data:image/png;base64,iVBORw0KGgo=\"></div>",
+        );
+
+        let output: Output = serde_json::from_value(serde_json::json!({
+            "output_type": "execute_result",
+            "execution_count": 1,
+            "data": { "text/plain": ["[1, 2,\n", " 3] < 4"] },
+            "metadata": {},
+        }))
+        .unwrap();
+        assert_eq!(
+            render_output(&output),
+            "<pre class=\"output output-text\">[1, 2,\n 3] &lt; 4</pre>",
+        );
+    }
+}
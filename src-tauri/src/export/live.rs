@@ -0,0 +1,77 @@
+//! Export notebooks as live pages, for following along in a browser.
+//!
+//! The page is served on the local network by a [`ShareServer`], and replaces
+//! its cells each time the notebook changes. It has no scripts or styles from
+//! outside, so it works on networks without internet access. Markdown is
+//! rendered ahead of time, as in previews.
+//!
+//! [`ShareServer`]: crate::share::ShareServer
+
+use std::fmt::Write;
+
+use super::{escape_html, language, render_code, render_markdown, render_output};
+use crate::backend::notebook::{Cell, NotebookRoot};
+
+/// Render the cells of a notebook as HTML, to replace the body of the page.
+pub fn render_cells(notebook: &NotebookRoot) -> String {
+    let language = language(notebook);
+    let mut body = String::new();
+    for cell in &notebook.cells {
+        match cell {
+            Cell::Markdown(cell) => {
+                writeln!(body, "{}", render_markdown(&cell.source)).unwrap();
+            }
+            Cell::Code(cell) => {
+                body += &render_code(&cell.source, language);
+                for output in &cell.outputs {
+                    body += &render_output(output);
+                }
+                body.push('\n');
+            }
+            Cell::Raw(_) => {}
+        }
+    }
+    body
+}
+
+/// Render the page around the cells, which listens for updates at `events_url`.
+pub fn live_page(title: &str, cells: &str, events_url: &str) -> String {
+    format!(
+        r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font: 14px -apple-system, sans-serif; max-width: 960px; margin: 24px auto; padding: 0 16px; color: #111827; }}
+pre {{ font: 13px ui-monospace, Menlo, monospace; white-space: pre-wrap; margin: 0; padding: 8px; }}
+pre.input {{ background: #f9fafb; border-radius: 4px; margin-top: 16px; }}
+pre.output-stderr, pre.output-error {{ background: #fdecea; }}
+.output-image img {{ max-width: 100%; }}
+.markdown {{ margin-top: 16px; }}
+#status {{ position: fixed; top: 8px; right: 12px; font-size: 12px; color: #6b7280; }}
+</style>
+</head>
+<body>
+<div id="status">Live</div>
+<main id="cells">
+{cells}</main>
+<script>
+const status = document.getElementById("status");
+const events = new EventSource({events_url});
+events.onmessage = (event) => {{
+  document.getElementById("cells").innerHTML = JSON.parse(event.data);
+  status.textContent = "Live";
+}};
+events.onerror = () => {{
+  status.textContent = "Reconnecting…";
+}};
+</script>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        events_url = serde_json::to_string(events_url).unwrap(),
+    )
+}
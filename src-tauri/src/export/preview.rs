@@ -0,0 +1,126 @@
+//! Export notebooks as lightweight static previews, for Quick Look on macOS.
+//!
+//! Previews must render quickly and without scripts, so Markdown is rendered
+//! ahead of time with raw HTML escaped, and only text outputs are included.
+//! Long notebooks are cut off after [`MAX_CELLS`] cells.
+
+use std::fmt::Write;
+
+use super::{
+    escape_html, language, mime_text, render_code, render_markdown, render_output, strip_ansi,
+};
+use crate::backend::notebook::{Cell, NotebookRoot, Output};
+
+/// Number of cells shown before a preview is cut off.
+pub const MAX_CELLS: usize = 200;
+
+/// Export a notebook as a static HTML preview with the given title.
+pub fn export_preview(notebook: &NotebookRoot, title: &str) -> String {
+    let language = language(notebook);
+    let mut body = String::new();
+    for cell in notebook.cells.iter().take(MAX_CELLS) {
+        match cell {
+            Cell::Markdown(cell) => {
+                writeln!(body, "{}", render_markdown(&cell.source)).unwrap();
+            }
+            Cell::Code(cell) => {
+                body += &render_code(&cell.source, language);
+                for output in &cell.outputs {
+                    body += &render_text_output(output);
+                }
+                body.push('\n');
+            }
+            Cell::Raw(_) => {}
+        }
+    }
+    if notebook.cells.len() > MAX_CELLS {
+        let rest = notebook.cells.len() - MAX_CELLS;
+        writeln!(body, "<p class=\"more\">{rest} more cells</p>").unwrap();
+    }
+
+    format!(
+        r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font: 13px -apple-system, sans-serif; margin: 16px; color: #111827; }}
+pre {{ font: 12px ui-monospace, Menlo, monospace; white-space: pre-wrap; margin: 0; padding: 8px; }}
+pre.input {{ background: #f9fafb; border-radius: 4px; margin-top: 12px; }}
+pre.output-stderr, pre.output-error {{ background: #fdecea; }}
+.markdown {{ margin-top: 12px; }}
+.markdown pre {{ background: #f9fafb; border-radius: 4px; }}
+.more {{ color: #6b7280; }}
+@media (prefers-color-scheme: dark) {{
+  body {{ background: #1f2937; color: #f3f4f6; }}
+  pre.input {{ background: #111827; }}
+  pre.output-stderr, pre.output-error {{ background: #450a0a; }}
+}}
+</style>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        title = escape_html(title),
+    )
+}
+
+/// Render an output of a code cell, keeping only its text.
+fn render_text_output(output: &Output) -> String {
+    let data = match output {
+        Output::Stream(_) | Output::Error(_) => return render_output(output),
+        Output::ExecuteResult(result) => &result.data,
+        Output::DisplayData(display) => &display.data,
+    };
+    match data.get("text/plain").and_then(mime_text) {
+        Some(plain) => format!(
+            "<pre class=\"output output-text\">{}</pre>",
+            escape_html(&strip_ansi(&plain)),
+        ),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn preview_text_only() {
+        let notebook = NotebookRoot::from_cells(vec![
+            json!({
+                "cell_type": "markdown",
+                "metadata": {},
+                "source": ["# Plot <1>\n", "\n", "Some *data*.<script>alert(2)</script>"],
+            }),
+            json!({
+                "cell_type": "code",
+                "metadata": {},
+                "execution_count": 1,
+                "source": "plt.plot(x)",
+                "outputs": [{
+                    "output_type": "display_data",
+                    "data": {
+                        "image/png": "iVBORw0KGgo=",
+                        "text/html": "<script>alert(1)</script>",
+                        "text/plain": "<Figure size 640x480>",
+                    },
+                    "metadata": {},
+                }],
+            }),
+        ]);
+
+        let html = export_preview(&notebook, "plot.ipynb");
+        assert!(html.contains("<h1>Plot &lt;1&gt;</h1>"));
+        assert!(html.contains("<p>Some <em>data</em>.&lt;script&gt;alert(2)&lt;/script&gt;</p>"));
+        assert!(html.contains("<code class=\"language-python\">plt.plot(x)</code>"));
+        assert!(html.contains("&lt;Figure size 640x480&gt;"));
+        // Neither the HTML output nor the image is included, even escaped.
+        assert!(!html.contains("alert(1)"));
+        assert!(!html.contains("iVBORw0KGgo"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Export notebooks as reveal.js slideshows.
+//!
+//! Cells are grouped into slides by their `slideshow` metadata, following the
+//! conventions of nbconvert and RISE. A "slide" cell starts a new slide, a
+//! "subslide" cell starts a new slide below the current one, "fragment" cells
+//! appear one step at a time, "notes" cells become speaker notes, and "skip"
+//! cells are left out. Other cells continue the current slide.
+//!
+//! The presentation is a single HTML file that works offline. reveal.js and
+//! the Markdown renderer are bundled with the app and inlined into the file,
+//! along with notebook content and images.
+
+use std::{fmt::Write, path::Path};
+
+use super::{escape_html, language, render_code, render_output};
+use crate::{
+    backend::notebook::{Cell, NotebookRoot, SlideType},
+    Error,
+};
+
+/// Stylesheets bundled in the app's `slides` resource directory.
+const STYLES: [&str; 3] = ["reveal.css", "white.css", "monokai.css"];
+
+/// Scripts bundled in the app's `slides` resource directory, in load order.
+const SCRIPTS: [&str; 4] = ["marked.min.js", "reveal.js", "highlight.js", "notes.js"];
+
+/// Slides of a presentation, each made of one or more vertical subslides.
+type Slides = Vec<Vec<String>>;
+
+/// Styles and scripts of reveal.js that are inlined into each presentation.
+#[derive(Clone, Debug, Default)]
+pub struct SlideAssets {
+    /// Contents of the stylesheets, concatenated.
+    pub styles: String,
+
+    /// Contents of the scripts, each in its own `<script>` element.
+    pub scripts: String,
+}
+
+impl SlideAssets {
+    /// Read the bundled assets from a resource directory.
+    pub async fn load(dir: &Path) -> Result<Self, Error> {
+        let mut assets = Self::default();
+        for name in STYLES {
+            let style =
+                (tokio::fs::read_to_string(dir.join(name)).await).map_err(Error::Filesystem)?;
+            writeln!(assets.styles, "{}", inline_raw(&style, "style")).unwrap();
+        }
+        for name in SCRIPTS {
+            let script =
+                (tokio::fs::read_to_string(dir.join(name)).await).map_err(Error::Filesystem)?;
+            let script = inline_raw(&script, "script");
+            writeln!(assets.scripts, "<script>{script}</script>").unwrap();
+        }
+        Ok(assets)
+    }
+}
+
+/// Escape closing tags in the text of a raw `<script>` or `<style>` element.
+fn inline_raw(text: &str, tag: &str) -> String {
+    text.replace(&format!("</{tag}"), &format!("<\\/{tag}"))
+}
+
+/// Export a notebook as a reveal.js presentation with the given title.
+pub fn export_slides(notebook: &NotebookRoot, title: &str, assets: &SlideAssets) -> String {
+    let mut body = String::new();
+    for subslides in group_slides(notebook) {
+        if let [slide] = &subslides[..] {
+            writeln!(body, "<section>{slide}</section>").unwrap();
+        } else {
+            body.push_str("<section>\n");
+            for subslide in subslides {
+                writeln!(body, "<section>{subslide}</section>").unwrap();
+            }
+            body.push_str("</section>\n");
+        }
+    }
+
+    format!(
+        r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+{styles}</style>
+<style>
+.reveal .slides {{ text-align: left; }}
+.reveal pre {{ width: 100%; box-shadow: none; font-size: 0.5em; }}
+.reveal pre.output {{ padding: 0.5em; white-space: pre-wrap; }}
+.reveal pre.output-stderr, .reveal pre.output-error {{ background: #fdecea; }}
+.reveal .output-image img {{ max-height: 60vh; margin: 0; }}
+</style>
+</head>
+<body>
+<div class="reveal">
+<div class="slides">
+{body}</div>
+</div>
+{scripts}<script>
+for (const source of document.querySelectorAll("textarea.markdown")) {{
+  const div = document.createElement("div");
+  div.className = "markdown";
+  div.innerHTML = marked.parse(source.value);
+  source.replaceWith(div);
+}}
+Reveal.initialize({{ hash: true, plugins: [RevealHighlight, RevealNotes] }});
+</script>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        styles = assets.styles,
+        scripts = assets.scripts,
+    )
+}
+
+/// Group the rendered cells of a notebook into slides.
+fn group_slides(notebook: &NotebookRoot) -> Slides {
+    let language = language(notebook);
+    let mut slides: Slides = Vec::new();
+    for cell in &notebook.cells {
+        let (metadata, html) = match cell {
+            Cell::Markdown(cell) => {
+                // Markdown is kept as text, and rendered when the page loads.
+                let source = String::from(cell.source.clone());
+                let html = format!(
+                    "<textarea class=\"markdown\" hidden>{}</textarea>",
+                    escape_html(&source),
+                );
+                (&cell.metadata, html)
+            }
+            Cell::Code(cell) => {
+                let mut html = render_code(&cell.source, language);
+                for output in &cell.outputs {
+                    html += &render_output(output);
+                }
+                (&cell.metadata, html)
+            }
+            Cell::Raw(_) => continue,
+        };
+        let slide_type = metadata
+            .slideshow
+            .as_ref()
+            .map_or(SlideType::Continue, |slideshow| slideshow.slide_type);
+
+        match slide_type {
+            SlideType::Skip => continue,
+            SlideType::Slide => slides.push(vec![String::new()]),
+            SlideType::Subslide if !slides.is_empty() => {
+                slides.last_mut().unwrap().push(String::new());
+            }
+            _ if slides.is_empty() => slides.push(vec![String::new()]),
+            _ => {}
+        }
+        let current = slides.last_mut().unwrap().last_mut().unwrap();
+        match slide_type {
+            SlideType::Fragment => write!(current, "<div class=\"fragment\">{html}</div>"),
+            SlideType::Notes => write!(current, "<aside class=\"notes\">{html}</aside>"),
+            _ => write!(current, "{html}"),
+        }
+        .unwrap();
+    }
+    slides
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn markdown(source: &str, slide_type: Option<&str>) -> serde_json::Value {
+        let metadata = match slide_type {
+            Some(slide_type) => json!({ "slideshow": { "slide_type": slide_type } }),
+            None => json!({}),
+        };
+        json!({ "cell_type": "markdown", "metadata": metadata, "source": source })
+    }
+
+    #[test]
+    fn group_cells_into_slides() {
+        let notebook = NotebookRoot::from_cells(vec![
+            markdown("# Intro", None),
+            markdown("more", Some("-")),
+            markdown("secret", Some("skip")),
+            markdown("# Second", Some("slide")),
+            markdown("below", Some("subslide")),
+            markdown("step", Some("fragment")),
+            markdown("say this", Some("notes")),
+        ]);
+
+        let slides = group_slides(&notebook);
+        let text = |html: &str| html.replace("<textarea class=\"markdown\" hidden>", "|");
+        let slides: Vec<Vec<String>> = slides
+            .iter()
+            .map(|subslides| subslides.iter().map(|html| text(html)).collect())
+            .collect();
+        assert_eq!(
+            slides,
+            [
+                vec!["|# Intro</textarea>|more</textarea>"],
+                vec![
+                    "|# Second</textarea>",
+                    "|below</textarea><div class=\"fragment\">|step</textarea></div><aside \
+                     class=\"notes\">|say this</textarea></aside>",
+                ],
+            ],
+        );
+
+        let assets = SlideAssets {
+            styles: ".reveal {}\n".into(),
+            scripts: "<script>var Reveal;</script>\n".into(),
+        };
+        let html = export_slides(&notebook, "Talk <1>", &assets);
+        assert!(html.contains("<title>Talk &lt;1&gt;</title>"));
+        assert!(html.contains("<script>var Reveal;</script>"));
+        assert!(!html.contains("secret"));
+        assert!(!html.contains("https://"));
+    }
+
+    #[test]
+    fn inline_closing_tags() {
+        assert_eq!(
+            inline_raw("s = '</script>';", "script"),
+            "s = '<\\/script>';"
+        );
+    }
+}
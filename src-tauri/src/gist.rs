@@ -0,0 +1,304 @@
+//! Sharing notebooks as GitHub Gists, which nbviewer can render.
+//!
+//! Requests are authenticated with a GitHub token kept in the keychain. The
+//! token is either pasted by the user, or obtained with GitHub's device flow:
+//! the user enters a short code at github.com/login/device, while the app polls
+//! for the token. The device flow needs the client ID of an OAuth app, which is
+//! set with the `JUTE_GITHUB_CLIENT_ID` environment variable at compile time.
+//!
+//! Each shared notebook is recorded by path, so sharing it again updates the
+//! same gist instead of creating a new one.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use ts_rs::TS;
+
+use crate::{
+    backend::notebook::{Cell, NotebookRoot},
+    store, Error,
+};
+
+/// Client ID of the GitHub OAuth app used for the device flow, if any.
+pub const GITHUB_CLIENT_ID: Option<&str> = option_env!("JUTE_GITHUB_CLIENT_ID");
+
+/// Key of the GitHub token in the [`SecretNamespace::GithubToken`] namespace.
+///
+/// [`SecretNamespace::GithubToken`]: crate::secrets::SecretNamespace::GithubToken
+pub const GITHUB_TOKEN_KEY: &str = "github.com";
+
+/// The name of the file recording shared gists, within the app data directory.
+pub const SHARED_GISTS_FILE: &str = "shared_gists.json";
+
+/// Base URL of the GitHub REST API.
+const GITHUB_API: &str = "https://api.github.com";
+
+/// An HTTP client with the user agent that GitHub requires.
+fn github_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(concat!("jute/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("HTTP client should build")
+}
+
+/// A pending device flow login, shown to the user while waiting for approval.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct DeviceLogin {
+    /// Code identifying this login to GitHub, used when polling.
+    pub device_code: String,
+
+    /// Code for the user to enter on GitHub.
+    pub user_code: String,
+
+    /// Page where the user enters the code.
+    pub verification_uri: String,
+
+    /// Seconds until the codes expire.
+    #[ts(type = "number")]
+    pub expires_in: u64,
+
+    /// Minimum seconds to wait between polls.
+    #[ts(type = "number")]
+    pub interval: u64,
+}
+
+crate::export_bindings!(DeviceLogin);
+
+/// Start logging in to GitHub with the device flow.
+pub async fn start_device_login() -> Result<DeviceLogin, Error> {
+    let client_id = GITHUB_CLIENT_ID.ok_or_else(|| {
+        Error::GithubLogin("this build can't log in, use a personal access token".into())
+    })?;
+    let login = github_client()
+        .post("https://github.com/login/device/code")
+        .header(header::ACCEPT, "application/json")
+        .form(&[("client_id", client_id), ("scope", "gist")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(login)
+}
+
+/// Wait for the user to approve a device flow login, returning the token.
+pub async fn finish_device_login(login: &DeviceLogin) -> Result<String, Error> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        access_token: Option<String>,
+        error: Option<String>,
+        interval: Option<u64>,
+    }
+
+    let client_id = GITHUB_CLIENT_ID.unwrap_or_default();
+    let client = github_client();
+    let mut interval = login.interval;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(login.expires_in);
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let resp: TokenResponse = client
+            .post("https://github.com/login/oauth/access_token")
+            .header(header::ACCEPT, "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", &login.device_code),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match (resp.access_token, resp.error.as_deref()) {
+            (Some(token), _) => return Ok(token),
+            (None, Some("authorization_pending")) => {}
+            (None, Some("slow_down")) => interval = resp.interval.unwrap_or(interval + 5),
+            (None, Some(error)) => return Err(Error::GithubLogin(error.into())),
+            (None, None) => return Err(Error::GithubLogin("no token in response".into())),
+        }
+    }
+    Err(Error::GithubLogin("expired_token".into()))
+}
+
+/// A notebook shared as a gist.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct SharedGist {
+    /// ID of the gist.
+    pub id: String,
+
+    /// Page of the gist on GitHub.
+    pub url: String,
+
+    /// Page rendering the notebook on nbviewer.
+    pub nbviewer_url: String,
+
+    /// Whether an existing gist was updated, rather than a new one created.
+    pub updated: bool,
+}
+
+crate::export_bindings!(SharedGist);
+
+/// Record of a gist created for a notebook, to update it when shared again.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct GistRecord {
+    id: String,
+    public: bool,
+}
+
+/// Gists created for notebooks, keyed by the notebook's path.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SharedGists {
+    gists: BTreeMap<PathBuf, GistRecord>,
+}
+
+impl SharedGists {
+    /// Load the records from a file, or start empty if it is missing.
+    pub async fn load(path: &Path) -> Self {
+        store::load_json(path).await
+    }
+
+    /// Write the records to a file.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        store::save_json(path, self).await
+    }
+}
+
+/// Response from the GitHub API for a gist.
+#[derive(Deserialize)]
+struct GistResponse {
+    id: String,
+    html_url: String,
+    owner: Option<GistOwner>,
+}
+
+#[derive(Deserialize)]
+struct GistOwner {
+    login: String,
+}
+
+/// Share a notebook as a gist, updating the gist it was shared to before if
+/// there is one with the same visibility.
+///
+/// Gists can't change between public and secret, so changing it creates a new
+/// gist. A new one is also created if the previous gist was deleted.
+pub async fn share_notebook(
+    token: &str,
+    gists: &mut SharedGists,
+    path: &Path,
+    notebook: &NotebookRoot,
+    public: bool,
+) -> Result<SharedGist, Error> {
+    let client = github_client();
+    let file_name = format!(
+        "{}.ipynb",
+        path.file_stem().unwrap_or_default().to_string_lossy()
+    );
+    let contents = serde_json::to_string_pretty(notebook)?;
+    let files = json!({ file_name.clone(): { "content": contents } });
+
+    let previous = gists
+        .gists
+        .get(path)
+        .filter(|record| record.public == public);
+    if let Some(record) = previous {
+        let resp = client
+            .patch(format!("{GITHUB_API}/gists/{}", record.id))
+            .bearer_auth(token)
+            .header(header::ACCEPT, "application/vnd.github+json")
+            .json(&json!({ "files": files }))
+            .send()
+            .await?;
+        if resp.status() != StatusCode::NOT_FOUND {
+            let gist: GistResponse = resp.error_for_status()?.json().await?;
+            return Ok(shared_gist(gist, true));
+        }
+    }
+
+    let gist: GistResponse = client
+        .post(format!("{GITHUB_API}/gists"))
+        .bearer_auth(token)
+        .header(header::ACCEPT, "application/vnd.github+json")
+        .json(&json!({
+            "description": notebook.metadata.title.as_deref().unwrap_or(&file_name),
+            "public": public,
+            "files": files,
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    gists.gists.insert(
+        path.to_path_buf(),
+        GistRecord {
+            id: gist.id.clone(),
+            public,
+        },
+    );
+    Ok(shared_gist(gist, false))
+}
+
+/// Describe a gist from the API for the frontend.
+fn shared_gist(gist: GistResponse, updated: bool) -> SharedGist {
+    SharedGist {
+        nbviewer_url: nbviewer_url(gist.owner.as_ref().map(|o| o.login.as_str()), &gist.id),
+        id: gist.id,
+        url: gist.html_url,
+        updated,
+    }
+}
+
+/// URL of a gist on nbviewer.
+pub fn nbviewer_url(owner: Option<&str>, id: &str) -> String {
+    match owner {
+        Some(owner) => format!("https://nbviewer.org/gist/{owner}/{id}"),
+        None => format!("https://nbviewer.org/gist/{id}"),
+    }
+}
+
+/// Remove the outputs and execution counts of all code cells.
+pub fn strip_outputs(notebook: &mut NotebookRoot) {
+    for cell in &mut notebook.cells {
+        if let Cell::Code(cell) = cell {
+            cell.outputs.clear();
+            cell.execution_count = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::NotebookFormat;
+
+    #[test]
+    fn strip_and_link() {
+        let mut notebook = NotebookFormat::Percent
+            .read("# %%\nprint(1)\n\n# %% [markdown]\n# Notes\n")
+            .unwrap();
+        if let Cell::Code(cell) = &mut notebook.cells[0] {
+            cell.execution_count = Some(3);
+        }
+        strip_outputs(&mut notebook);
+        let Cell::Code(cell) = &notebook.cells[0] else {
+            panic!("first cell should be code");
+        };
+        assert_eq!(cell.execution_count, None);
+        assert_eq!(notebook.cells.len(), 2);
+
+        assert_eq!(
+            nbviewer_url(Some("octocat"), "abc123"),
+            "https://nbviewer.org/gist/octocat/abc123"
+        );
+        assert_eq!(
+            nbviewer_url(None, "abc123"),
+            "https://nbviewer.org/gist/abc123"
+        );
+    }
+}
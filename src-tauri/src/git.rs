@@ -0,0 +1,638 @@
+//! Version control of notebooks and workspaces with git.
+//!
+//! This runs the `git` command-line tool, rather than linking a git library,
+//! so it respects the user's configuration, credential helpers, and hooks,
+//! and works with any repository their git can read. Output is requested in
+//! the stable, machine-readable "porcelain" formats.
+//!
+//! Notebooks are compared cell by cell with [`diff_cells`], and blamed cell by
+//! cell, by mapping each cell to the lines of the file that hold its source.
+
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::{
+    backend::{
+        diff::{diff_cells, NotebookDiff},
+        notebook::Cell,
+    },
+    import::NotebookFormat,
+    Error,
+};
+
+/// A git repository, identified by the root of its working tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repository {
+    root: PathBuf,
+}
+
+/// The commit and branch checked out in a repository.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct GitHead {
+    /// Name of the current branch, or `null` if the head is detached.
+    pub branch: Option<String>,
+
+    /// Hash of the current commit, or `null` if there are no commits yet.
+    pub commit: Option<String>,
+}
+
+crate::export_bindings!(GitHead);
+
+/// How a file changed, in the index or the working tree.
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    /// The file was added.
+    Added,
+
+    /// The contents of the file changed.
+    Modified,
+
+    /// The file was deleted.
+    Deleted,
+
+    /// The file was renamed from another path.
+    Renamed,
+
+    /// The file was copied from another path.
+    Copied,
+
+    /// The file changed type, such as from a file to a symlink.
+    TypeChanged,
+
+    /// The file isn't tracked by git.
+    Untracked,
+
+    /// The file has unresolved merge conflicts.
+    Conflicted,
+}
+
+crate::export_bindings!(FileChange);
+
+impl FileChange {
+    /// Parse a status letter from `git status`, where `.` means unchanged.
+    fn from_code(code: char) -> Option<Self> {
+        match code {
+            'A' => Some(Self::Added),
+            'M' => Some(Self::Modified),
+            'D' => Some(Self::Deleted),
+            'R' => Some(Self::Renamed),
+            'C' => Some(Self::Copied),
+            'T' => Some(Self::TypeChanged),
+            _ => None,
+        }
+    }
+}
+
+/// A file with changes in a repository.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct FileStatus {
+    /// Path of the file.
+    pub path: PathBuf,
+
+    /// Path the file was renamed or copied from, if it was.
+    pub original_path: Option<PathBuf>,
+
+    /// Change staged in the index, to be committed.
+    pub staged: Option<FileChange>,
+
+    /// Change in the working tree that isn't staged.
+    pub unstaged: Option<FileChange>,
+}
+
+crate::export_bindings!(FileStatus);
+
+/// Status of a repository, or of some paths in it.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct GitStatus {
+    /// Root of the repository's working tree.
+    pub root: PathBuf,
+
+    /// The commit and branch checked out.
+    pub head: GitHead,
+
+    /// Files with staged or unstaged changes, including untracked files.
+    pub files: Vec<FileStatus>,
+}
+
+crate::export_bindings!(GitStatus);
+
+/// The commit that last changed a cell, found with `git blame`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CellBlame {
+    /// Position of the cell in the notebook.
+    #[ts(type = "number")]
+    pub index: usize,
+
+    /// Identifier of the cell, if it has one.
+    pub cell_id: Option<String>,
+
+    /// Most recent commit that changed a line of the cell, or `null` if the
+    /// cell has uncommitted changes.
+    pub commit: Option<BlameCommit>,
+}
+
+crate::export_bindings!(CellBlame);
+
+/// A commit shown in blame information.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct BlameCommit {
+    /// Hash of the commit.
+    pub hash: String,
+
+    /// Name of the author.
+    pub author: String,
+
+    /// Time the commit was authored, in seconds since the Unix epoch.
+    #[ts(type = "number")]
+    pub time: i64,
+
+    /// First line of the commit message.
+    pub summary: String,
+}
+
+crate::export_bindings!(BlameCommit);
+
+impl Repository {
+    /// Find the repository containing a file or directory, if any.
+    pub async fn discover(path: &Path) -> Result<Option<Self>, Error> {
+        let dir = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let output = tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .await
+            .map_err(Error::Subprocess)?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Some(Self { root: root.into() }))
+    }
+
+    /// Root of the repository's working tree.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Run git in the repository, returning its output if it succeeds.
+    async fn git(&self, args: &[&OsStr]) -> Result<Vec<u8>, Error> {
+        let output = tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            // Don't take locks for read-only commands, which would make the
+            // user's own git commands fail while they run.
+            .env("GIT_OPTIONAL_LOCKS", "0")
+            .output()
+            .await
+            .map_err(Error::Subprocess)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Git(stderr.trim().into()));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Path of a file relative to the root, with forward slashes, as used in
+    /// revision specifiers like `HEAD:<path>`.
+    fn relative_path(&self, path: &Path) -> Result<String, Error> {
+        // Both paths are canonicalized, in case the root was reached through a
+        // symlink. The file itself may have been deleted.
+        let root = self.root.canonicalize().map_err(Error::Filesystem)?;
+        let parent = path.parent().unwrap_or(path);
+        let parent = parent.canonicalize().map_err(Error::Filesystem)?;
+        let path = parent.join(path.file_name().unwrap_or_default());
+        let relative = path
+            .strip_prefix(&root)
+            .map_err(|_| Error::Git(format!("{} is not in the repository", path.display())))?;
+        let parts: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        Ok(parts.join("/"))
+    }
+
+    /// Get the commit and branch checked out.
+    pub async fn head(&self) -> Result<GitHead, Error> {
+        Ok(self.status(None).await?.head)
+    }
+
+    /// Get the status of the repository, or only of the files under a path.
+    pub async fn status(&self, path: Option<&Path>) -> Result<GitStatus, Error> {
+        let mut args: Vec<&OsStr> = ["status", "--porcelain=v2", "--branch", "-z", "--"]
+            .map(OsStr::new)
+            .to_vec();
+        args.extend(path.map(Path::as_os_str));
+        let output = self.git(&args).await?;
+        let (head, files) = parse_status(&String::from_utf8_lossy(&output));
+        Ok(GitStatus {
+            root: self.root.clone(),
+            head,
+            files: files
+                .into_iter()
+                .map(|file| FileStatus {
+                    path: self.root.join(&file.path),
+                    original_path: file.original_path.map(|p| self.root.join(p)),
+                    ..file
+                })
+                .collect(),
+        })
+    }
+
+    /// Read a file at a revision, like `HEAD:<path>`, or `:<path>` for the
+    /// index. Returns `None` if the file isn't in that revision.
+    async fn show(&self, revision: &str, path: &str) -> Result<Option<String>, Error> {
+        let spec = format!("{revision}:{path}");
+        match self.git(&[OsStr::new("show"), OsStr::new(&spec)]).await {
+            Ok(contents) => Ok(Some(String::from_utf8_lossy(&contents).into_owned())),
+            Err(Error::Git(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Compare a notebook cell by cell, either between the last commit and
+    /// the index (`staged`), or between the index and the working tree.
+    pub async fn diff_notebook(&self, path: &Path, staged: bool) -> Result<NotebookDiff, Error> {
+        let relative = self.relative_path(path)?;
+        let format = NotebookFormat::from_path(path);
+        let (old, new) = if staged {
+            let old = self.show("HEAD", &relative).await?;
+            let new = self.show("", &relative).await?;
+            (old, new)
+        } else {
+            let old = match self.show("", &relative).await? {
+                Some(contents) => Some(contents),
+                None => self.show("HEAD", &relative).await?,
+            };
+            let new = match tokio::fs::read_to_string(path).await {
+                Ok(contents) => Some(contents),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => return Err(Error::Filesystem(err)),
+            };
+            (old, new)
+        };
+        let cells = |contents: Option<String>| -> Result<Vec<Cell>, Error> {
+            match contents {
+                Some(contents) => Ok(format.read(&contents)?.cells),
+                None => Ok(Vec::new()),
+            }
+        };
+        Ok(diff_cells(&cells(old)?, &cells(new)?))
+    }
+
+    /// Stage the given files, then commit everything staged, returning the
+    /// hash of the new commit.
+    pub async fn commit(&self, message: &str, paths: &[PathBuf]) -> Result<String, Error> {
+        if !paths.is_empty() {
+            let mut args: Vec<&OsStr> = vec![OsStr::new("add"), OsStr::new("--")];
+            args.extend(paths.iter().map(|p| p.as_os_str()));
+            self.git(&args).await?;
+        }
+        self.git(&["commit", "-m", message].map(OsStr::new)).await?;
+        let hash = self.git(&["rev-parse", "HEAD"].map(OsStr::new)).await?;
+        Ok(String::from_utf8_lossy(&hash).trim().into())
+    }
+
+    /// Path of the file git updates when switching branches, to watch it for
+    /// changes. This handles linked worktrees, where `.git` is a file.
+    pub async fn head_file(&self) -> Result<PathBuf, Error> {
+        let output = self
+            .git(&["rev-parse", "--git-path", "HEAD"].map(OsStr::new))
+            .await?;
+        let path = PathBuf::from(String::from_utf8_lossy(&output).trim());
+        Ok(self.root.join(path))
+    }
+
+    /// Find the commit that last changed each cell of a notebook, as it is in
+    /// the working tree.
+    ///
+    /// This is supported for `.ipynb` files and percent scripts. For other
+    /// formats, no cells are returned.
+    pub async fn blame_cells(&self, path: &Path) -> Result<Vec<CellBlame>, Error> {
+        let relative = self.relative_path(path)?;
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(Error::Filesystem)?;
+        let format = NotebookFormat::from_path(path);
+        let cells = format.read(&contents)?.cells;
+        let ranges = match format {
+            NotebookFormat::Ipynb => ipynb_source_lines(&contents),
+            NotebookFormat::Percent => percent_cell_lines(&contents),
+            NotebookFormat::Quarto => return Ok(Vec::new()),
+        };
+        if ranges.len() != cells.len() {
+            return Ok(Vec::new());
+        }
+
+        let output = self
+            .git(&["blame", "--porcelain", "--", &relative].map(OsStr::new))
+            .await?;
+        let (lines, commits) = parse_blame(&String::from_utf8_lossy(&output));
+        let blames = cells
+            .iter()
+            .zip(ranges)
+            .enumerate()
+            .map(|(index, (cell, (start, end)))| {
+                let hashes = &lines[start.min(lines.len())..end.min(lines.len())];
+                let commit = if hashes.iter().any(|hash| is_uncommitted(hash)) {
+                    None
+                } else {
+                    hashes
+                        .iter()
+                        .filter_map(|hash| commits.get(hash))
+                        .max_by_key(|commit| commit.time)
+                        .cloned()
+                };
+                CellBlame {
+                    index,
+                    cell_id: cell.id().map(String::from),
+                    commit,
+                }
+            })
+            .collect();
+        Ok(blames)
+    }
+}
+
+/// Parse the output of `git status --porcelain=v2 --branch -z`, with paths
+/// relative to the root.
+fn parse_status(output: &str) -> (GitHead, Vec<FileStatus>) {
+    let mut head = GitHead::default();
+    let mut files = Vec::new();
+    let mut entries = output.split('\0');
+    while let Some(entry) = entries.next() {
+        if let Some(oid) = entry.strip_prefix("# branch.oid ") {
+            head.commit = (oid != "(initial)").then(|| oid.into());
+            continue;
+        }
+        if let Some(branch) = entry.strip_prefix("# branch.head ") {
+            head.branch = (branch != "(detached)").then(|| branch.into());
+            continue;
+        }
+        let fields: Vec<&str> = entry.splitn(2, ' ').collect();
+        let [kind, rest] = fields[..] else {
+            continue;
+        };
+        // Ordinary entries have 8 fields before the path, renames and copies
+        // have 9 followed by the original path, and conflicts have 10.
+        let (fields, path) = match kind {
+            "1" => split_fields(rest, 7),
+            "2" => split_fields(rest, 8),
+            "u" => split_fields(rest, 9),
+            "?" => {
+                files.push(FileStatus {
+                    path: rest.into(),
+                    original_path: None,
+                    staged: None,
+                    unstaged: Some(FileChange::Untracked),
+                });
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(path) = path else {
+            continue;
+        };
+        let mut codes = fields.first().copied().unwrap_or_default().chars();
+        let (x, y) = (codes.next().unwrap_or('.'), codes.next().unwrap_or('.'));
+        let (staged, unstaged) = if kind == "u" {
+            (Some(FileChange::Conflicted), Some(FileChange::Conflicted))
+        } else {
+            (FileChange::from_code(x), FileChange::from_code(y))
+        };
+        let original_path = if kind == "2" {
+            entries.next().map(PathBuf::from)
+        } else {
+            None
+        };
+        files.push(FileStatus {
+            path: path.into(),
+            original_path,
+            staged,
+            unstaged,
+        });
+    }
+    (head, files)
+}
+
+/// Split `count` space-separated fields from the start of a status entry,
+/// returning them and the path after them, which may contain spaces.
+fn split_fields(entry: &str, count: usize) -> (Vec<&str>, Option<&str>) {
+    let mut parts: Vec<&str> = entry.splitn(count + 1, ' ').collect();
+    let path = (parts.len() == count + 1).then(|| parts.pop()).flatten();
+    (parts, path)
+}
+
+/// Parse the output of `git blame --porcelain`, returning the commit hash of
+/// each line and information about each commit.
+fn parse_blame(output: &str) -> (Vec<String>, HashMap<String, BlameCommit>) {
+    let mut lines = Vec::new();
+    let mut commits: HashMap<String, BlameCommit> = HashMap::new();
+    let mut current = String::new();
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            lines.push(current.clone());
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key.len() == 40 && key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            current = key.into();
+            commits
+                .entry(current.clone())
+                .or_insert_with(|| BlameCommit {
+                    hash: current.clone(),
+                    author: String::new(),
+                    time: 0,
+                    summary: String::new(),
+                });
+            continue;
+        }
+        let Some(commit) = commits.get_mut(&current) else {
+            continue;
+        };
+        match key {
+            "author" => commit.author = value.into(),
+            "author-time" => commit.time = value.parse().unwrap_or_default(),
+            "summary" => commit.summary = value.into(),
+            _ => {}
+        }
+    }
+    (lines, commits)
+}
+
+/// Whether a blamed line has changes that aren't committed yet.
+fn is_uncommitted(hash: &str) -> bool {
+    hash.bytes().all(|b| b == b'0')
+}
+
+/// Find the lines holding each cell's source in a notebook's JSON, as ranges
+/// of zero-based line indices.
+///
+/// This relies on the source being a JSON array with one line of code on each
+/// line of the file, as Jupyter and Jute write it. Strings in outputs can't be
+/// mistaken for a `"source"` key, since their quotes are escaped.
+fn ipynb_source_lines(contents: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(first) = start {
+            if line.starts_with(']') {
+                ranges.push((first, i));
+                start = None;
+            }
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("\"source\":") {
+            let value = value.trim();
+            if value == "[" {
+                start = Some(i + 1);
+            } else {
+                ranges.push((i, i + 1));
+            }
+        }
+    }
+    ranges
+}
+
+/// Find the lines of each cell in a percent script, as ranges of zero-based
+/// line indices. A cell starts at each `# %%` marker, and any lines before the
+/// first marker are a cell of their own.
+fn percent_cell_lines(contents: &str) -> Vec<(usize, usize)> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut starts: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("# %%"))
+        .map(|(i, _)| i)
+        .collect();
+    let has_preamble = lines[..starts.first().copied().unwrap_or(lines.len())]
+        .iter()
+        .any(|line| !line.trim().is_empty());
+    if has_preamble || starts.is_empty() {
+        starts.insert(0, 0);
+    }
+    let mut ends: Vec<usize> = starts.iter().skip(1).copied().collect();
+    ends.push(lines.len());
+    starts.into_iter().zip(ends).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_porcelain_status() {
+        let output = concat!(
+            "# branch.oid 1234567890abcdef1234567890abcdef12345678\0",
+            "# branch.head main\0",
+            "1 .M N... 100644 100644 100644 aaa aaa analysis.ipynb\0",
+            "2 R. N... 100644 100644 100644 bbb bbb R100 new name.py\0old.py\0",
+            "u UU N... 100644 100644 100644 100644 ccc ddd eee merge.ipynb\0",
+            "? scratch.ipynb\0",
+        );
+        let (head, files) = parse_status(output);
+        assert_eq!(head.branch.as_deref(), Some("main"));
+        assert!(head.commit.is_some());
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].path, Path::new("analysis.ipynb"));
+        assert_eq!(
+            (files[0].staged, files[0].unstaged),
+            (None, Some(FileChange::Modified))
+        );
+        assert_eq!(files[1].path, Path::new("new name.py"));
+        assert_eq!(files[1].original_path.as_deref(), Some(Path::new("old.py")));
+        assert_eq!(files[1].staged, Some(FileChange::Renamed));
+        assert_eq!(files[2].staged, Some(FileChange::Conflicted));
+        assert_eq!(files[3].unstaged, Some(FileChange::Untracked));
+
+        let (head, _) = parse_status("# branch.oid (initial)\0# branch.head (detached)\0");
+        assert_eq!(head, GitHead::default());
+    }
+
+    #[test]
+    fn cell_line_ranges() {
+        let notebook = r##"{
+ "cells": [
+  {
+   "cell_type": "code",
+   "outputs": [{"text": ["\"source\": [\n"]}],
+   "source": [
+    "import os\n",
+    "print(os.sep)"
+   ]
+  },
+  {
+   "cell_type": "markdown",
+   "source": "# Title"
+  }
+ ]
+}"##;
+        assert_eq!(ipynb_source_lines(notebook), [(6, 8), (12, 13)]);
+        assert_eq!(
+            percent_cell_lines("import os\n\n# %%\nx = 1\n# %% [markdown]\n# Hi\n"),
+            [(0, 2), (2, 4), (4, 6)]
+        );
+        assert_eq!(percent_cell_lines("# %%\nx = 1\n"), [(0, 2)]);
+    }
+
+    #[tokio::test]
+    async fn status_diff_and_blame() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let run = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        if !run(&["init", "-q", "-b", "main"]).status.success() {
+            return; // git isn't installed.
+        }
+        run(&["config", "user.name", "Ada"]);
+        run(&["config", "user.email", "ada@example.com"]);
+
+        let path = dir.join("analysis.py");
+        tokio::fs::write(&path, "# %%\nx = 1\n\n# %%\ny = 2\n")
+            .await
+            .unwrap();
+        let repo = Repository::discover(&path).await.unwrap().unwrap();
+        let hash = repo
+            .commit("Add analysis", std::slice::from_ref(&path))
+            .await
+            .unwrap();
+        assert_eq!(repo.head().await.unwrap().commit, Some(hash.clone()));
+
+        tokio::fs::write(&path, "# %%\nx = 1\n\n# %%\ny = 3\n")
+            .await
+            .unwrap();
+        let status = repo.status(Some(dir)).await.unwrap();
+        assert_eq!(status.head.branch.as_deref(), Some("main"));
+        assert_eq!(status.files[0].unstaged, Some(FileChange::Modified));
+
+        let diff = repo.diff_notebook(&path, false).await.unwrap();
+        assert_eq!(diff.cells.len(), 2);
+        assert!(diff.has_changes());
+        assert!(!repo.diff_notebook(&path, true).await.unwrap().has_changes());
+
+        let blame = repo.blame_cells(&path).await.unwrap();
+        assert_eq!(blame[0].commit.as_ref().unwrap().hash, hash);
+        assert_eq!(blame[0].commit.as_ref().unwrap().author, "Ada");
+        assert_eq!(blame[1].commit, None);
+    }
+}
@@ -0,0 +1,284 @@
+//! Headless execution of notebooks from the command line, for CI and
+//! scheduled runs.
+//!
+//! Running `jute --headless run notebook.ipynb --output out.ipynb` starts the
+//! notebook's kernel, runs each code cell in order, and writes the notebook
+//! with its new outputs, much like `nbclient`. The process exits with a nonzero
+//! code if a cell raises an error or times out. This starts kernels the same
+//! way as the app, with the notebook's `jute.toml`, but does not start Tauri.
+
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    backend::{
+        commands::{self, RunCellEvent},
+        local::LocalKernel,
+        notebook::{Cell, NotebookRoot},
+        outputs::OutputCollector,
+    },
+    launch::{self, LaunchOptions},
+    Error,
+};
+
+/// Command-line flag that selects headless mode.
+pub const HEADLESS_FLAG: &str = "--headless";
+
+/// Default time limit for each cell, matching `nbclient`.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+const USAGE: &str = "\
+usage: jute --headless run <notebook.ipynb> [options]
+
+options:
+  -o, --output <path>   where to write the executed notebook (default: overwrite input)
+  --timeout <seconds>   time limit for each cell (default: 600, 0 for none)
+  --kernel <name>       kernel spec to use (default: from jute.toml or notebook metadata)
+  --allow-errors        keep running cells after one raises an error";
+
+/// Options for running a notebook headlessly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunOptions {
+    /// Notebook file to run.
+    pub input: PathBuf,
+
+    /// Where to write the notebook with outputs.
+    pub output: PathBuf,
+
+    /// Time limit for each cell, if any.
+    pub timeout: Option<Duration>,
+
+    /// Kernel spec to use instead of the notebook's.
+    pub kernel: Option<String>,
+
+    /// Whether to keep running after a cell raises an error.
+    pub allow_errors: bool,
+}
+
+impl RunOptions {
+    /// Parse options from the arguments after [`HEADLESS_FLAG`].
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        match args.next().as_deref() {
+            Some("run") => {}
+            Some(command) => return Err(format!("unknown command {command:?}")),
+            None => return Err("missing command".into()),
+        }
+
+        let mut input = None;
+        let mut output = None;
+        let mut timeout = Some(DEFAULT_TIMEOUT);
+        let mut kernel = None;
+        let mut allow_errors = false;
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                "--timeout" => {
+                    let seconds: u64 = value()?
+                        .parse()
+                        .map_err(|_| "timeout must be a number of seconds".to_string())?;
+                    timeout = (seconds > 0).then(|| Duration::from_secs(seconds));
+                }
+                "--kernel" => kernel = Some(value()?),
+                "--allow-errors" => allow_errors = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+                _ if input.is_none() => input = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument {arg:?}")),
+            }
+        }
+
+        let input = input.ok_or("missing notebook path")?;
+        Ok(Self {
+            output: output.unwrap_or_else(|| input.clone()),
+            input,
+            timeout,
+            kernel,
+            allow_errors,
+        })
+    }
+}
+
+/// Run headless mode with the arguments after [`HEADLESS_FLAG`], returning
+/// the process exit code.
+pub fn main(args: impl IntoIterator<Item = String>) -> i32 {
+    let options = match RunOptions::parse(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return 2;
+        }
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+    match runtime.block_on(run(&options)) {
+        Ok(None) => 0,
+        Ok(Some(failure)) => {
+            eprintln!("error: {failure}");
+            1
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            1
+        }
+    }
+}
+
+/// Run a notebook and write it with outputs, returning a description of the
+/// cell that failed, if any.
+///
+/// The notebook is written even if a cell fails, so its outputs can be
+/// inspected.
+pub async fn run(options: &RunOptions) -> Result<Option<String>, Error> {
+    let contents = tokio::fs::read_to_string(&options.input)
+        .await
+        .map_err(Error::Filesystem)?;
+    let mut notebook: NotebookRoot = serde_json::from_str(&contents)?;
+
+    let stored_spec = (notebook.metadata.kernelspec.as_ref()).map(|spec| spec.name.as_str());
+    let mut kernel = launch::launch(LaunchOptions {
+        spec_name: options.kernel.as_deref(),
+        notebook_path: Some(&options.input),
+        stored_spec,
+        python: launch::python_from_env(),
+        trusted: true,
+        ..Default::default()
+    })
+    .await?
+    .kernel;
+    let failure = run_cells(&kernel, &mut notebook, options).await;
+    _ = kernel.kill().await;
+
+    let contents = serde_json::to_string_pretty(&notebook)?;
+    tokio::fs::write(&options.output, contents)
+        .await
+        .map_err(Error::Filesystem)?;
+    failure
+}
+
+/// Run the code cells of a notebook in order, replacing their outputs.
+async fn run_cells(
+    kernel: &LocalKernel,
+    notebook: &mut NotebookRoot,
+    options: &RunOptions,
+) -> Result<Option<String>, Error> {
+    // Cells are keyed by index, since older notebooks have no cell IDs.
+    let mut store = OutputCollector::default();
+    let failure = run_cells_into(kernel, notebook, options, &mut store).await;
+    for (index, cell) in notebook.cells.iter_mut().enumerate() {
+        if let Cell::Code(cell) = cell {
+            if let Some(outputs) = store.outputs(&index.to_string()) {
+                cell.outputs = outputs;
+            }
+        }
+    }
+    failure
+}
+
+/// Run the code cells of a notebook, collecting their outputs in a store so
+/// that updates to displays in earlier cells are kept.
+async fn run_cells_into(
+    kernel: &LocalKernel,
+    notebook: &mut NotebookRoot,
+    options: &RunOptions,
+    store: &mut OutputCollector,
+) -> Result<Option<String>, Error> {
+    let mut execution_count = 0;
+    for (index, cell) in notebook.cells.iter_mut().enumerate() {
+        let Cell::Code(cell) = cell else {
+            continue;
+        };
+        let cell_id = index.to_string();
+        store.clear(&cell_id);
+        cell.execution_count = None;
+        let code = String::from(cell.source.clone());
+        if code.trim().is_empty() {
+            continue;
+        }
+        execution_count += 1;
+        cell.execution_count = Some(execution_count);
+
+        let rx = commands::run_cell(kernel.conn(), &code, Default::default()).await?;
+        let mut result = CellResult::default();
+        let collect = async {
+            while let Ok(event) = rx.recv().await {
+                store.apply(&cell_id, &event);
+                result.record(event);
+            }
+        };
+        let timed_out = match options.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, collect).await.is_err(),
+            None => {
+                collect.await;
+                false
+            }
+        };
+
+        if timed_out {
+            _ = kernel.interrupt().await;
+            return Ok(Some(format!("cell {index} timed out")));
+        }
+        if let Some(err) = result.disconnect {
+            return Ok(Some(format!("kernel disconnected in cell {index}: {err}")));
+        }
+        if result.aborted {
+            return Ok(Some(format!("cell {index} was aborted by the kernel")));
+        }
+        if let Some((ename, evalue)) = result.error {
+            if !options.allow_errors {
+                return Ok(Some(format!("cell {index} raised {ename}: {evalue}")));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// How running a code cell ended, from the events received while running it.
+#[derive(Default)]
+struct CellResult {
+    /// Name and value of the error raised by the cell, if any.
+    error: Option<(String, String)>,
+
+    /// Reason that the kernel disconnected, if it did.
+    disconnect: Option<String>,
+
+    /// Whether the kernel skipped the cell without running it.
+    aborted: bool,
+}
+
+impl CellResult {
+    fn record(&mut self, event: RunCellEvent) {
+        match event {
+            RunCellEvent::Error(err) => self.error = Some((err.ename, err.evalue)),
+            RunCellEvent::Disconnect(reason) => self.disconnect = Some(reason),
+            RunCellEvent::Aborted => self.aborted = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_options() {
+        let options = RunOptions::parse(args("run a.ipynb -o out.ipynb --timeout 30")).unwrap();
+        assert_eq!(options.input, PathBuf::from("a.ipynb"));
+        assert_eq!(options.output, PathBuf::from("out.ipynb"));
+        assert_eq!(options.timeout, Some(Duration::from_secs(30)));
+        assert!(!options.allow_errors);
+
+        let options = RunOptions::parse(args("run a.ipynb --timeout 0 --allow-errors")).unwrap();
+        assert_eq!(options.output, PathBuf::from("a.ipynb"));
+        assert_eq!(options.timeout, None);
+        assert!(options.allow_errors);
+
+        assert!(RunOptions::parse(args("run")).is_err());
+        assert!(RunOptions::parse(args("exec a.ipynb")).is_err());
+        assert!(RunOptions::parse(args("run a.ipynb --output")).is_err());
+        assert!(RunOptions::parse(args("run a.ipynb --timeout soon")).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Reading notebooks from text formats, such as percent scripts and Quarto
+//! documents.
+//!
+//! Each format is read into a [`NotebookRoot`] and written back from one, so
+//! files opened in Jute are saved in the format they came from. The format of
+//! a file is chosen by its extension. Text formats don't store outputs, so
+//! those are dropped when saving.
+
+use std::path::Path;
+
+use serde_json::Map;
+
+use crate::{
+    backend::notebook::{
+        Cell, CellMetadata, CodeCell, LanguageInfo, MarkdownCell, MultilineString,
+        NotebookMetadata, NotebookRoot, RawCell,
+    },
+    export, Error,
+};
+
+pub mod percent;
+pub mod quarto;
+
+/// File extensions that can be opened as notebooks.
+pub const EXTENSIONS: &[&str] = &["ipynb", "py", "qmd", "Rmd"];
+
+/// Format of a notebook file on disk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NotebookFormat {
+    /// Jupyter notebook JSON, the default.
+    Ipynb,
+
+    /// Python script with cells marked by `# %%` comments.
+    Percent,
+
+    /// Quarto or R Markdown document, with code in fenced chunks.
+    Quarto,
+}
+
+impl NotebookFormat {
+    /// Choose the format of a file from its extension.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        match extension.to_lowercase().as_str() {
+            "py" => Self::Percent,
+            "qmd" | "rmd" => Self::Quarto,
+            _ => Self::Ipynb,
+        }
+    }
+
+    /// Read a notebook from the contents of a file in this format.
+    pub fn read(self, contents: &str) -> Result<NotebookRoot, Error> {
+        match self {
+            Self::Ipynb => Ok(NotebookRoot::from_ipynb(contents)?),
+            Self::Percent => Ok(new_notebook(percent::parse(contents), "python")),
+            Self::Quarto => {
+                let (cells, language) = quarto::parse(contents);
+                Ok(new_notebook(cells, language.as_deref().unwrap_or("python")))
+            }
+        }
+    }
+
+    /// Write a notebook as the contents of a file in this format.
+    pub fn write(self, notebook: &NotebookRoot) -> Result<String, Error> {
+        match self {
+            Self::Ipynb => Ok(notebook.to_ipynb()?),
+            Self::Percent => Ok(percent::write(&notebook.cells)),
+            Self::Quarto => {
+                let language = export::language(notebook).to_lowercase();
+                Ok(quarto::write(&notebook.cells, &language))
+            }
+        }
+    }
+}
+
+/// Type of a cell read from a text format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CellKind {
+    /// Code to run in the kernel.
+    Code,
+
+    /// Markdown text.
+    Markdown,
+
+    /// Raw text, left as-is.
+    Raw,
+}
+
+/// Create a cell read from a text format, without outputs.
+fn new_cell(kind: CellKind, source: &str, metadata: CellMetadata) -> Cell {
+    let source = MultilineString::Single(source.into()).normalize();
+    match kind {
+        CellKind::Code => Cell::Code(CodeCell {
+            id: None,
+            metadata,
+            source,
+            execution_count: None,
+            outputs: Vec::new(),
+        }),
+        CellKind::Markdown => Cell::Markdown(MarkdownCell {
+            id: None,
+            metadata,
+            source,
+            attachments: None,
+        }),
+        CellKind::Raw => Cell::Raw(RawCell {
+            id: None,
+            metadata,
+            source,
+            attachments: None,
+        }),
+    }
+}
+
+/// Create a notebook from imported cells, in the given language.
+fn new_notebook(cells: Vec<Cell>, language: &str) -> NotebookRoot {
+    NotebookRoot {
+        metadata: NotebookMetadata {
+            kernelspec: None,
+            language_info: Some(LanguageInfo {
+                name: language.into(),
+                codemirror_mode: None,
+                file_extension: None,
+                mimetype: None,
+                pygments_lexer: None,
+                other: Map::new(),
+            }),
+            orig_nbformat: None,
+            title: None,
+            authors: None,
+            other: Map::new(),
+        },
+        nbformat_minor: 5,
+        nbformat: 4,
+        cells,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            NotebookFormat::from_path(Path::new("a.ipynb")),
+            NotebookFormat::Ipynb
+        );
+        assert_eq!(
+            NotebookFormat::from_path(Path::new("a.py")),
+            NotebookFormat::Percent
+        );
+        assert_eq!(
+            NotebookFormat::from_path(Path::new("a.Rmd")),
+            NotebookFormat::Quarto
+        );
+        assert_eq!(
+            NotebookFormat::from_path(Path::new("a.qmd")),
+            NotebookFormat::Quarto
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Python scripts in the percent format of Jupytext, with cells marked by
+//! `# %%` comments.
+//!
+//! A code cell starts with `# %%`, optionally followed by a title. Markdown and
+//! raw cells start with `# %% [markdown]` and `# %% [raw]`, and each of their
+//! lines is commented out. Anything before the first marker, like a license
+//! header, is kept in a raw cell if it is all comments, or else in a code cell.
+//! Scripts without any markers are read as a single code cell.
+//!
+//! Cell metadata is written after the marker and title as `key=value` pairs
+//! with JSON values, like `# %% [markdown] tags=["intro"]`, as Jupytext does.
+//! Metadata that only matters to the notebook UI, like whether outputs are
+//! collapsed, is left out.
+
+use std::io;
+
+use serde_json::{ser::Formatter, Map, Value};
+
+use super::{new_cell, CellKind};
+use crate::backend::notebook::{Cell, CellMetadata};
+
+/// Comment that starts a new cell.
+const MARKER: &str = "# %%";
+
+/// Cell metadata that is not written to scripts, like Jupytext's default
+/// filter.
+const IGNORED_METADATA: &[&str] = &[
+    "autoscroll",
+    "collapsed",
+    "scrolled",
+    "trusted",
+    "execution",
+    "ExecuteTime",
+];
+
+/// Read the cells of a percent script.
+pub fn parse(text: &str) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    let mut kind = None;
+    let mut metadata = CellMetadata::default();
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in text.lines() {
+        let Some(rest) = line.strip_prefix(MARKER) else {
+            lines.push(line);
+            continue;
+        };
+        match kind {
+            Some(kind) => push_cell(&mut cells, kind, &lines, metadata),
+            None => push_preamble(&mut cells, &lines),
+        }
+        lines.clear();
+
+        let rest = rest.trim();
+        let (cell_kind, options) = if let Some(options) = rest.strip_prefix("[markdown]") {
+            (CellKind::Markdown, options)
+        } else if let Some(options) = rest.strip_prefix("[md]") {
+            (CellKind::Markdown, options)
+        } else if let Some(options) = rest.strip_prefix("[raw]") {
+            (CellKind::Raw, options)
+        } else {
+            (CellKind::Code, rest)
+        };
+        metadata = parse_options(options);
+        kind = Some(cell_kind);
+    }
+
+    match kind {
+        Some(kind) => push_cell(&mut cells, kind, &lines, metadata),
+        None => push_preamble(&mut cells, &lines),
+    }
+    cells
+}
+
+/// Write cells as a percent script.
+pub fn write(cells: &[Cell]) -> String {
+    let mut text = String::new();
+    for (index, cell) in cells.iter().enumerate() {
+        if index > 0 {
+            text.push('\n');
+        }
+        let (marker, metadata, source) = match cell {
+            Cell::Code(cell) => (MARKER, &cell.metadata, &cell.source),
+            Cell::Markdown(cell) => ("# %% [markdown]", &cell.metadata, &cell.source),
+            Cell::Raw(cell) => ("# %% [raw]", &cell.metadata, &cell.source),
+        };
+        let source = String::from(source.clone());
+
+        // A leading raw cell of comments is the preamble, written as-is.
+        if index == 0 && matches!(cell, Cell::Raw(_)) && is_comments(source.lines()) {
+            text += &source;
+            text.push('\n');
+            continue;
+        }
+
+        text += marker;
+        text += &write_options(metadata);
+        text.push('\n');
+        for line in source.lines() {
+            match cell {
+                Cell::Code(_) => text += line,
+                _ if line.is_empty() => text.push('#'),
+                _ => {
+                    text += "# ";
+                    text += line;
+                }
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Read the title and metadata after a cell marker.
+fn parse_options(options: &str) -> CellMetadata {
+    let options = options.trim();
+
+    // The metadata starts at the first word that begins the pairs.
+    let split = (options.char_indices())
+        .filter(|&(i, _)| i == 0 || options[..i].ends_with(char::is_whitespace))
+        .find_map(|(i, _)| Some((i, parse_pairs(&options[i..])?)));
+    let (title, mut map) = match split {
+        Some((i, map)) => (options[..i].trim(), map),
+        None => (options, Map::new()),
+    };
+    if !title.is_empty() {
+        map.insert("title".into(), title.into());
+    }
+    match serde_json::from_value(Value::Object(map.clone())) {
+        Ok(metadata) => metadata,
+        Err(_) => CellMetadata {
+            other: map,
+            ..Default::default()
+        },
+    }
+}
+
+/// Parse `key=value` pairs with JSON values, separated by spaces.
+fn parse_pairs(mut text: &str) -> Option<Map<String, Value>> {
+    let mut map = Map::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return (!map.is_empty()).then_some(map);
+        }
+        let (key, rest) = text.split_once('=')?;
+        let is_key_char = |c: char| c.is_ascii_alphanumeric() || "_-.".contains(c);
+        if key.is_empty() || !key.chars().all(is_key_char) {
+            return None;
+        }
+        let mut values = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        let value = values.next()?.ok()?;
+        text = &rest[values.byte_offset()..];
+        if !text.is_empty() && !text.starts_with(char::is_whitespace) {
+            return None;
+        }
+        map.insert(key.into(), value);
+    }
+}
+
+/// Write the title and metadata of a cell after its marker, with a leading
+/// space if there are any.
+fn write_options(metadata: &CellMetadata) -> String {
+    let mut text = String::new();
+    let Ok(Value::Object(mut map)) = serde_json::to_value(metadata) else {
+        return text;
+    };
+    if let Some(Value::String(title)) = map.remove("title") {
+        text.push(' ');
+        text += &title;
+    }
+    for (key, value) in map {
+        if IGNORED_METADATA.contains(&key.as_str()) {
+            continue;
+        }
+        let mut json = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(&mut json, PythonFormatter);
+        if serde::Serialize::serialize(&value, &mut serializer).is_ok() {
+            text += &format!(" {key}={}", String::from_utf8_lossy(&json));
+        }
+    }
+    text
+}
+
+/// Formats JSON on one line with spaces after separators, like Python's
+/// `json.dumps`, so that metadata written by Jupytext round-trips.
+struct PythonFormatter;
+
+impl Formatter for PythonFormatter {
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if first {
+            Ok(())
+        } else {
+            w.write_all(b", ")
+        }
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if first {
+            Ok(())
+        } else {
+            w.write_all(b", ")
+        }
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        w.write_all(b": ")
+    }
+}
+
+/// Add a cell from the lines after its marker, which are uncommented for
+/// Markdown and raw cells.
+fn push_cell(cells: &mut Vec<Cell>, kind: CellKind, lines: &[&str], metadata: CellMetadata) {
+    let lines = trim_blank_lines(lines);
+    let source = match kind {
+        CellKind::Code => lines.join("\n"),
+        CellKind::Markdown | CellKind::Raw => lines
+            .iter()
+            .map(|line| {
+                line.strip_prefix("# ")
+                    .or_else(|| line.strip_prefix('#'))
+                    .unwrap_or(line)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    cells.push(new_cell(kind, &source, metadata));
+}
+
+/// Add a cell from the lines before the first marker, if there are any.
+fn push_preamble(cells: &mut Vec<Cell>, lines: &[&str]) {
+    let lines = trim_blank_lines(lines);
+    if lines.is_empty() {
+        return;
+    }
+    let kind = if is_comments(lines.iter().copied()) {
+        CellKind::Raw
+    } else {
+        CellKind::Code
+    };
+    cells.push(new_cell(kind, &lines.join("\n"), CellMetadata::default()));
+}
+
+/// Whether every line that isn't blank is a comment.
+fn is_comments<'a>(mut lines: impl Iterator<Item = &'a str>) -> bool {
+    lines.all(|line| line.trim().is_empty() || line.trim_start().starts_with('#'))
+}
+
+/// Remove blank lines from the start and end, which separate cells.
+fn trim_blank_lines<'a, 'b>(mut lines: &'b [&'a str]) -> &'b [&'a str] {
+    while let [first, rest @ ..] = lines {
+        if !first.trim().is_empty() {
+            break;
+        }
+        lines = rest;
+    }
+    while let [rest @ .., last] = lines {
+        if !last.trim().is_empty() {
+            break;
+        }
+        lines = rest;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "\
+# Copyright 2025
+
+# %% Load data
+import pandas as pd
+
+df = pd.read_csv(\"data.csv\")
+
+# %% [markdown]
+# # Results
+#
+# The mean is:
+
+# %%
+df.mean()
+";
+
+    #[test]
+    fn percent_round_trip() {
+        let cells = parse(SCRIPT);
+        assert_eq!(cells.len(), 4);
+        let sources: Vec<String> = cells
+            .iter()
+            .map(|cell| match cell {
+                Cell::Code(cell) => String::from(cell.source.clone()),
+                Cell::Markdown(cell) => format!("md:{}", String::from(cell.source.clone())),
+                Cell::Raw(cell) => format!("raw:{}", String::from(cell.source.clone())),
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                "raw:# Copyright 2025",
+                "import pandas as pd\n\ndf = pd.read_csv(\"data.csv\")",
+                "md:# Results\n\nThe mean is:",
+                "df.mean()",
+            ],
+        );
+        assert_eq!(write(&cells), SCRIPT);
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let script = "\
+# %% Setup tags=[\"parameters\"] jute={\"b\": [1, 2], \"a\": 1}
+x = 1
+
+# %% [markdown] slideshow={\"slide_type\": \"slide\"}
+# Title
+
+# %% a = b
+y = 2
+";
+        let cells = parse(script);
+        let Cell::Code(setup) = &cells[0] else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(setup.metadata.other["title"], "Setup");
+        assert_eq!(
+            setup.metadata.other["tags"],
+            serde_json::json!(["parameters"])
+        );
+        let Cell::Markdown(slide) = &cells[1] else {
+            panic!("expected a Markdown cell");
+        };
+        assert!(slide.metadata.slideshow.is_some());
+        let Cell::Code(last) = &cells[2] else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(last.metadata.other["title"], "a = b");
+        assert_eq!(write(&cells), script);
+    }
+
+    #[test]
+    fn script_without_markers() {
+        let cells = parse("x = 1\nprint(x)\n");
+        let [Cell::Code(cell)] = &cells[..] else {
+            panic!("expected a single code cell");
+        };
+        assert_eq!(String::from(cell.source.clone()), "x = 1\nprint(x)");
+    }
+}
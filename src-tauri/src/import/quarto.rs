@@ -0,0 +1,149 @@
+//! Quarto and R Markdown documents, which are Markdown with fenced code chunks.
+//!
+//! Code cells are chunks fenced like ```` ```{python} ````, where the braces
+//! hold the chunk's language and options. These are kept in cell metadata, so
+//! that chunks are written back with the same header. Options written as `#|`
+//! comments stay in the cell source, as Quarto expects. Text between chunks
+//! becomes Markdown cells, and YAML front matter is kept in a raw cell.
+
+use serde_json::Value;
+
+use super::{new_cell, CellKind};
+use crate::backend::notebook::{Cell, CellMetadata};
+
+/// Key in cell metadata that holds the header of a code chunk.
+const CHUNK_KEY: &str = "chunk";
+
+/// Read the cells of a document, and the language of its first code chunk.
+pub fn parse(text: &str) -> (Vec<Cell>, Option<String>) {
+    let mut cells = Vec::new();
+    let mut language = None;
+    let mut lines = text.lines().peekable();
+
+    // Front matter must be at the very start of the document.
+    if lines.peek() == Some(&"---") {
+        let mut front_matter = vec![lines.next().unwrap()];
+        for line in lines.by_ref() {
+            front_matter.push(line);
+            if line == "---" {
+                break;
+            }
+        }
+        cells.push(new_cell(
+            CellKind::Raw,
+            &front_matter.join("\n"),
+            CellMetadata::default(),
+        ));
+    }
+
+    let mut markdown: Vec<&str> = Vec::new();
+    while let Some(line) = lines.next() {
+        let Some(header) = chunk_header(line) else {
+            markdown.push(line);
+            continue;
+        };
+        push_markdown(&mut cells, &markdown);
+        markdown.clear();
+
+        let chunk_language = header
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        language.get_or_insert(chunk_language);
+
+        let mut source = Vec::new();
+        for line in lines.by_ref() {
+            if line.trim_end() == "```" {
+                break;
+            }
+            source.push(line);
+        }
+        let mut metadata = CellMetadata::default();
+        metadata.other.insert(CHUNK_KEY.into(), header.into());
+        cells.push(new_cell(CellKind::Code, &source.join("\n"), metadata));
+    }
+    push_markdown(&mut cells, &markdown);
+
+    (cells, language)
+}
+
+/// Write cells as a document, with code chunks in the given language.
+pub fn write(cells: &[Cell], language: &str) -> String {
+    let mut blocks = Vec::new();
+    for cell in cells {
+        match cell {
+            Cell::Code(cell) => {
+                let header = match cell.metadata.other.get(CHUNK_KEY) {
+                    Some(Value::String(header)) => header.as_str(),
+                    _ => language,
+                };
+                let source = String::from(cell.source.clone());
+                blocks.push(format!("```{{{header}}}\n{source}\n```"));
+            }
+            Cell::Markdown(cell) => blocks.push(cell.source.clone().into()),
+            Cell::Raw(cell) => blocks.push(cell.source.clone().into()),
+        }
+    }
+    let mut text = blocks.join("\n\n");
+    text.push('\n');
+    text
+}
+
+/// The text between the braces of a line that starts a code chunk.
+fn chunk_header(line: &str) -> Option<&str> {
+    line.trim_end()
+        .strip_prefix("```{")?
+        .strip_suffix('}')
+        .map(str::trim)
+}
+
+/// Add a Markdown cell with the lines between chunks, unless they are blank.
+fn push_markdown(cells: &mut Vec<Cell>, lines: &[&str]) {
+    let text = lines.join("\n");
+    let text = text.trim_matches('\n');
+    if !text.trim().is_empty() {
+        cells.push(new_cell(CellKind::Markdown, text, CellMetadata::default()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "\
+---
+title: Report
+---
+
+# Analysis
+
+```{python setup, echo=FALSE}
+#| label: load
+import numpy as np
+```
+
+The result is below.
+
+```{python}
+np.arange(3)
+```
+";
+
+    #[test]
+    fn quarto_round_trip() {
+        let (cells, language) = parse(DOCUMENT);
+        assert_eq!(language.as_deref(), Some("python"));
+        assert_eq!(cells.len(), 5);
+        assert!(matches!(&cells[0], Cell::Raw(_)));
+        let (Cell::Markdown(text), Cell::Code(chunk)) = (&cells[1], &cells[2]) else {
+            panic!("expected a Markdown cell and a code chunk");
+        };
+        assert_eq!(String::from(text.source.clone()), "# Analysis");
+        assert_eq!(
+            String::from(chunk.source.clone()),
+            "#| label: load\nimport numpy as np",
+        );
+        assert_eq!(write(&cells, "python"), DOCUMENT);
+    }
+}
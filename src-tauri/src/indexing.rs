@@ -0,0 +1,115 @@
+//! Searchable metadata of notebooks, for indexers like Spotlight.
+//!
+//! Notebooks are JSON, so system search either skips them or matches on
+//! escaped strings and base64 images. This extracts what a person would search
+//! for: the title, Markdown headings, the kernel language, and the plain text
+//! of each cell. On macOS, the Spotlight importer bundled with the app reads it
+//! from the `jute-index` helper. On Windows, notebooks are registered with the
+//! plain-text filter of Windows Search instead.
+
+use serde::Serialize;
+
+use crate::{
+    backend::notebook::{Cell, NotebookRoot},
+    export,
+};
+
+/// Maximum length of the text content, in bytes, to keep indexes small.
+const MAX_TEXT_LEN: usize = 256 * 1024;
+
+/// Metadata about a notebook for a search index.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SearchMetadata {
+    /// Title from the notebook's metadata, or else its first heading.
+    pub title: Option<String>,
+
+    /// Text of the headings in Markdown cells, in order.
+    pub headings: Vec<String>,
+
+    /// Name of the kernel's programming language.
+    pub language: String,
+
+    /// Source of all cells, separated by blank lines.
+    pub text: String,
+}
+
+/// Extract the searchable metadata of a notebook.
+pub fn search_metadata(notebook: &NotebookRoot) -> SearchMetadata {
+    let mut headings = Vec::new();
+    let mut text = String::new();
+    for cell in &notebook.cells {
+        let source = match cell {
+            Cell::Code(cell) => String::from(cell.source.clone()),
+            Cell::Markdown(cell) => {
+                let source = String::from(cell.source.clone());
+                headings.extend(markdown_headings(&source));
+                source
+            }
+            Cell::Raw(cell) => String::from(cell.source.clone()),
+        };
+        if text.len() + source.len() > MAX_TEXT_LEN {
+            break;
+        }
+        if !text.is_empty() {
+            text += "\n\n";
+        }
+        text += &source;
+    }
+
+    SearchMetadata {
+        title: notebook
+            .metadata
+            .title
+            .clone()
+            .or_else(|| headings.first().cloned()),
+        headings,
+        language: export::language(notebook).into(),
+        text,
+    }
+}
+
+/// Headings in Markdown text, skipping lines inside fenced code blocks.
+fn markdown_headings(source: &str) -> Vec<String> {
+    let mut headings = Vec::new();
+    let mut in_fence = false;
+    for line in source.lines() {
+        let line = line.trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            let heading = line.trim_start_matches('#');
+            if heading.len() < line.len() && line.len() - heading.len() <= 6 {
+                if let Some(heading) = heading.strip_prefix(' ') {
+                    headings.push(heading.trim().trim_end_matches('#').trim().into());
+                }
+            }
+        }
+    }
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::NotebookFormat;
+
+    #[test]
+    fn headings_and_text() {
+        let document = "\
+# Sales Report
+
+```{python}
+# not a heading
+total = 42
+```
+
+## Summary ##
+";
+        let notebook = NotebookFormat::Quarto.read(document).unwrap();
+        let metadata = search_metadata(&notebook);
+        assert_eq!(metadata.title.as_deref(), Some("Sales Report"));
+        assert_eq!(metadata.headings, ["Sales Report", "Summary"]);
+        assert_eq!(metadata.language, "python");
+        assert!(metadata.text.contains("total = 42"));
+    }
+}
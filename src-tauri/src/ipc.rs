@@ -0,0 +1,75 @@
+//! Encoding of large messages sent to the frontend over IPC.
+//!
+//! Outputs like Plotly or Altair charts can be several megabytes of HTML and
+//! JSON, which is slow to pass to the webview and held in memory on both sides
+//! as a string. Messages over [`COMPRESS_THRESHOLD`] are gzip-compressed, which
+//! usually shrinks them by an order of magnitude, and sent as raw bytes. The
+//! frontend tells them apart from JSON messages because they arrive as an
+//! `ArrayBuffer`, and decompresses them with `DecompressionStream`.
+
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+
+use crate::Error;
+
+/// Size of serialized messages, in bytes, above which they are compressed.
+pub const COMPRESS_THRESHOLD: usize = 1 << 20;
+
+/// A message encoded to be sent over IPC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    /// Small message, serialized as JSON.
+    Json(String),
+
+    /// Large message, serialized as JSON and then gzip-compressed.
+    Gzip(Vec<u8>),
+}
+
+impl Payload {
+    /// Encode a message, compressing it if it is large.
+    pub fn encode(value: &impl Serialize) -> Result<Self, Error> {
+        let json = serde_json::to_string(value)?;
+        if json.len() <= COMPRESS_THRESHOLD {
+            return Ok(Payload::Json(json));
+        }
+        // Outputs are sent while the cell is running, so favor speed.
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder
+            .write_all(json.as_bytes())
+            .map_err(Error::Filesystem)?;
+        Ok(Payload::Gzip(encoder.finish().map_err(Error::Filesystem)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn compresses_large_messages() {
+        let small = serde_json::json!({ "event": "stdout", "data": "hello" });
+        assert_eq!(
+            Payload::encode(&small).unwrap(),
+            Payload::Json(small.to_string())
+        );
+
+        let html = "<div class=\"plot\"></div>".repeat(100_000);
+        let large = serde_json::json!({ "event": "display_data", "data": html });
+        let Payload::Gzip(bytes) = Payload::encode(&large).unwrap() else {
+            panic!("large message should be compressed");
+        };
+        assert!(bytes.len() < COMPRESS_THRESHOLD / 10);
+
+        let mut json = String::new();
+        GzDecoder::new(&bytes[..])
+            .read_to_string(&mut json)
+            .unwrap();
+        assert_eq!(json, large.to_string());
+    }
+}
@@ -0,0 +1,224 @@
+//! Import of existing Jupyter configuration, to ease migration from JupyterLab.
+//!
+//! On first run, Jute reads the user's Jupyter config directory and runtime
+//! directory. Servers that are configured or currently running are added to
+//! the [`ServerRegistry`], and the default kernel becomes a Jute setting.
+//!
+//! Only JSON config files are read, since Python config files can run
+//! arbitrary code. Export templates from nbconvert are not imported, as Jute
+//! does not use them.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::{entity::EntityRegistry, servers::ServerRegistry, settings::Settings};
+
+/// Config files read in order, so that later files take precedence.
+const CONFIG_FILES: [&str; 2] = ["jupyter_notebook_config.json", "jupyter_server_config.json"];
+
+/// Configuration found in a Jupyter installation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JupyterImport {
+    /// Servers that are configured or running.
+    pub servers: Vec<ImportedServer>,
+
+    /// Name of the default kernel spec.
+    pub default_kernel: Option<String>,
+}
+
+/// A server found in the Jupyter config or runtime directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedServer {
+    /// Name to show for the server.
+    pub name: String,
+
+    /// Base URL of the server.
+    pub url: String,
+
+    /// Token used to authenticate with the server, if any.
+    pub token: Option<String>,
+}
+
+/// Summary of what was imported, returned to the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct ImportSummary {
+    /// Number of servers added to the registry.
+    pub servers: u32,
+
+    /// Default kernel that was imported, if any.
+    pub default_kernel: Option<String>,
+}
+
+crate::export_bindings!(ImportSummary);
+
+impl JupyterImport {
+    /// Read configuration from the Jupyter config and runtime directories.
+    pub async fn read(config_dir: &Path, runtime_dir: &Path) -> Self {
+        let mut import = Self::default();
+        for file in CONFIG_FILES {
+            if let Some(config) = read_json(&config_dir.join(file)).await {
+                import.read_config(&config);
+            }
+        }
+        import.read_runtime(runtime_dir).await;
+        import
+    }
+
+    /// Read settings from the contents of a traitlets JSON config file.
+    fn read_config(&mut self, config: &Value) {
+        for section in ["MultiKernelManager", "MappingKernelManager"] {
+            if let Some(name) = config[section]["default_kernel_name"].as_str() {
+                self.default_kernel = Some(name.into());
+            }
+        }
+
+        for app in ["NotebookApp", "ServerApp"] {
+            let app = &config[app];
+            let Some(port) = app["port"].as_u64() else {
+                continue;
+            };
+            let ip = match app["ip"].as_str() {
+                None | Some("" | "*" | "0.0.0.0" | "127.0.0.1") => "localhost",
+                Some(ip) => ip,
+            };
+            let base_url = app["base_url"].as_str().unwrap_or("/");
+            let token = app["token"]
+                .as_str()
+                .or(config["IdentityProvider"]["token"].as_str());
+            self.add_server(
+                "Local Jupyter Server",
+                &format!("http://{ip}:{port}{base_url}"),
+                token,
+            );
+        }
+    }
+
+    /// Read servers that are currently running from their runtime info files.
+    async fn read_runtime(&mut self, runtime_dir: &Path) {
+        let Ok(mut it) = tokio::fs::read_dir(runtime_dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = it.next_entry().await {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let is_server_info = (file_name.starts_with("jpserver-")
+                || file_name.starts_with("nbserver-"))
+                && file_name.ends_with(".json");
+            if !is_server_info {
+                continue;
+            }
+            let Some(info) = read_json(&entry.path()).await else {
+                continue;
+            };
+            let Some(url) = info["url"].as_str() else {
+                continue;
+            };
+            let name = match info["root_dir"].as_str().or(info["notebook_dir"].as_str()) {
+                Some(root) => format!("Jupyter Server ({root})"),
+                None => "Jupyter Server".into(),
+            };
+            self.add_server(&name, url, info["token"].as_str());
+        }
+    }
+
+    fn add_server(&mut self, name: &str, url: &str, token: Option<&str>) {
+        let token = token.filter(|token| !token.is_empty()).map(String::from);
+        match self.servers.iter_mut().find(|server| server.url == url) {
+            Some(server) => server.token = token.or(server.token.take()),
+            None => self.servers.push(ImportedServer {
+                name: name.into(),
+                url: url.into(),
+                token,
+            }),
+        }
+    }
+
+    /// Merge the imported configuration into Jute's settings and servers,
+    /// without overriding any existing settings.
+    pub fn apply(
+        &self,
+        settings: &mut Settings,
+        servers: &mut ServerRegistry,
+        entities: &EntityRegistry,
+    ) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for server in &self.servers {
+            if servers.list().iter().any(|s| s.url == server.url) {
+                continue;
+            }
+            match servers.add(entities, &server.name, &server.url, server.token.as_deref()) {
+                Ok(_) => summary.servers += 1,
+                Err(err) => warn!("skipping imported server {:?}: {err}", server.url),
+            }
+        }
+        if settings.default_kernel.is_none() && self.default_kernel.is_some() {
+            settings.default_kernel.clone_from(&self.default_kernel);
+            summary.default_kernel.clone_from(&self.default_kernel);
+        }
+        summary
+    }
+}
+
+async fn read_json(path: &Path) -> Option<Value> {
+    let contents = tokio::fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&contents)
+        .inspect_err(|err| warn!("could not parse {path:?}: {err}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn import_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let config_dir = dir.join("config");
+        let runtime_dir = dir.join("runtime");
+        tokio::fs::create_dir_all(&config_dir).await.unwrap();
+        tokio::fs::create_dir_all(&runtime_dir).await.unwrap();
+
+        tokio::fs::write(
+            config_dir.join("jupyter_server_config.json"),
+            r#"{
+                "ServerApp": { "port": 9999, "base_url": "/lab/" },
+                "IdentityProvider": { "token": "secret" },
+                "MappingKernelManager": { "default_kernel_name": "ir" }
+            }"#,
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(
+            runtime_dir.join("jpserver-1234.json"),
+            r#"{ "url": "http://localhost:8888/", "token": "abc", "root_dir": "/home/me" }"#,
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(runtime_dir.join("kernel-1.json"), "{}")
+            .await
+            .unwrap();
+
+        let import = JupyterImport::read(&config_dir, &runtime_dir).await;
+        assert_eq!(import.default_kernel.as_deref(), Some("ir"));
+        assert_eq!(import.servers.len(), 2);
+        assert_eq!(import.servers[0].url, "http://localhost:9999/lab/");
+        assert_eq!(import.servers[0].token.as_deref(), Some("secret"));
+        assert_eq!(import.servers[1].name, "Jupyter Server (/home/me)");
+
+        let entities = EntityRegistry::new();
+        let mut settings = Settings::default();
+        let mut servers = ServerRegistry::default();
+        let summary = import.apply(&mut settings, &mut servers, &entities);
+        assert_eq!(summary.servers, 2);
+        assert_eq!(settings.default_kernel.as_deref(), Some("ir"));
+
+        // Importing again does not duplicate servers.
+        let summary = import.apply(&mut settings, &mut servers, &entities);
+        assert_eq!(summary, ImportSummary::default());
+        assert_eq!(servers.list().len(), 2);
+    }
+}
@@ -0,0 +1,437 @@
+//! Keyboard shortcuts for actions in the app, with user overrides.
+//!
+//! Default bindings are defined here on [`Action`], and users can override or
+//! unbind them. Overrides are persisted in the config dir. The effective
+//! bindings are consumed both by menu accelerators and by the frontend's
+//! command palette, so they stay in sync.
+//!
+//! Accelerators use the Tauri format, like `CmdOrCtrl+Shift+K`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
+use ts_rs::TS;
+
+use crate::{store, Error};
+
+/// The name of the keymap overrides file within the app config directory.
+pub const KEYMAP_FILE: &str = "keymap.json";
+
+/// An action in the app that can be bound to a keyboard shortcut.
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, TS,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Create a new notebook in a new window.
+    NewNotebook,
+
+    /// Open a new window with the home page.
+    NewWindow,
+
+    /// Open a notebook file.
+    OpenFile,
+
+    /// Open a folder as a workspace.
+    OpenFolder,
+
+    /// Save the current notebook.
+    Save,
+
+    /// Save the current notebook to a new path.
+    SaveAs,
+
+    /// Switch to the next notebook tab.
+    NextTab,
+
+    /// Switch to the previous notebook tab.
+    PreviousTab,
+
+    /// Show the command palette.
+    CommandPalette,
+
+    /// Make the contents of windows larger.
+    ZoomIn,
+
+    /// Make the contents of windows smaller.
+    ZoomOut,
+
+    /// Reset windows to the default zoom level.
+    ResetZoom,
+
+    /// Quit the app, asking to save any unsaved changes.
+    Quit,
+
+    /// Run the current cell and advance to the next one.
+    RunCell,
+
+    /// Run the current cell without advancing.
+    RunCellInPlace,
+
+    /// Run all cells in the notebook.
+    RunAll,
+
+    /// Run all cells above the current cell.
+    RunAllAbove,
+
+    /// Run the current cell and all cells below it.
+    RunAllBelow,
+
+    /// Run the selected text in the current cell.
+    RunSelectedText,
+}
+
+/// Group of related actions, used for display.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, EnumIter, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// General app-wide actions.
+    General,
+
+    /// Opening and saving files.
+    File,
+
+    /// Running code in the kernel.
+    Run,
+}
+
+impl Action {
+    /// Human-readable name of the action.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Action::NewNotebook => "New Notebook",
+            Action::NewWindow => "New Window",
+            Action::OpenFile => "Open File…",
+            Action::OpenFolder => "Open Folder…",
+            Action::Save => "Save",
+            Action::SaveAs => "Save As…",
+            Action::NextTab => "Next Tab",
+            Action::PreviousTab => "Previous Tab",
+            Action::CommandPalette => "Command Palette",
+            Action::ZoomIn => "Zoom In",
+            Action::ZoomOut => "Zoom Out",
+            Action::ResetZoom => "Actual Size",
+            Action::Quit => "Quit",
+            Action::RunCell => "Run Cell and Advance",
+            Action::RunCellInPlace => "Run Cell",
+            Action::RunAll => "Run All Cells",
+            Action::RunAllAbove => "Run All Above",
+            Action::RunAllBelow => "Run Cell and All Below",
+            Action::RunSelectedText => "Run Selected Text",
+        }
+    }
+
+    /// Category that this action is grouped under.
+    pub const fn category(&self) -> Category {
+        match self {
+            Action::NewNotebook
+            | Action::NewWindow
+            | Action::OpenFile
+            | Action::OpenFolder
+            | Action::Save
+            | Action::SaveAs => Category::File,
+            Action::NextTab
+            | Action::PreviousTab
+            | Action::CommandPalette
+            | Action::ZoomIn
+            | Action::ZoomOut
+            | Action::ResetZoom
+            | Action::Quit => Category::General,
+            Action::RunCell
+            | Action::RunCellInPlace
+            | Action::RunAll
+            | Action::RunAllAbove
+            | Action::RunAllBelow
+            | Action::RunSelectedText => Category::Run,
+        }
+    }
+
+    /// Default keyboard shortcut for this action, if any.
+    pub const fn default_accelerator(&self) -> Option<&'static str> {
+        match self {
+            Action::NewNotebook => Some("CmdOrCtrl+N"),
+            Action::NewWindow => Some("CmdOrCtrl+Shift+N"),
+            Action::OpenFile => Some("CmdOrCtrl+O"),
+            Action::OpenFolder => Some("CmdOrCtrl+Shift+O"),
+            Action::Save => Some("CmdOrCtrl+S"),
+            Action::SaveAs => Some("CmdOrCtrl+Shift+S"),
+            Action::NextTab => Some("Ctrl+Tab"),
+            Action::PreviousTab => Some("Ctrl+Shift+Tab"),
+            Action::CommandPalette => Some("CmdOrCtrl+K"),
+            Action::ZoomIn => Some("CmdOrCtrl+="),
+            Action::ZoomOut => Some("CmdOrCtrl+-"),
+            Action::ResetZoom => Some("CmdOrCtrl+0"),
+            Action::Quit => Some("CmdOrCtrl+Q"),
+            Action::RunCell => Some("Shift+Enter"),
+            Action::RunCellInPlace => Some("CmdOrCtrl+Enter"),
+            Action::RunAll => Some("CmdOrCtrl+Shift+Enter"),
+            Action::RunAllAbove | Action::RunAllBelow | Action::RunSelectedText => None,
+        }
+    }
+}
+
+/// The effective binding for an action, returned to the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct KeyBinding {
+    /// The action being bound.
+    pub action: Action,
+
+    /// Human-readable name of the action.
+    pub label: String,
+
+    /// Category that this action is grouped under.
+    pub category: Category,
+
+    /// Current keyboard shortcut, or null if unbound.
+    pub accelerator: Option<String>,
+
+    /// Default keyboard shortcut, or null if unbound by default.
+    pub default_accelerator: Option<String>,
+
+    /// Other actions that are bound to the same shortcut.
+    pub conflicts: Vec<Action>,
+}
+
+crate::export_bindings!(KeyBinding);
+
+/// Bound shortcuts in one category, shown in the keyboard shortcuts reference.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct ShortcutGroup {
+    /// Category of the actions.
+    pub category: Category,
+
+    /// Effective bindings of the actions in this category that have a shortcut.
+    pub bindings: Vec<KeyBinding>,
+}
+
+crate::export_bindings!(ShortcutGroup);
+
+/// User overrides of the default keyboard shortcuts.
+///
+/// Each entry maps an action to its new accelerator, or to `None` if the user
+/// has removed the binding.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Keymap {
+    overrides: BTreeMap<Action, Option<String>>,
+}
+
+impl Keymap {
+    /// Load keymap overrides from a file, falling back to defaults.
+    pub async fn load(path: &Path) -> Self {
+        store::load_json(path).await
+    }
+
+    /// Write keymap overrides to a file.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        store::save_json(path, self).await
+    }
+
+    /// Get the effective accelerator for an action.
+    pub fn accelerator(&self, action: Action) -> Option<&str> {
+        match self.overrides.get(&action) {
+            Some(accel) => accel.as_deref(),
+            None => action.default_accelerator(),
+        }
+    }
+
+    /// Override the binding for an action, or unbind it with `None`.
+    pub fn set(&mut self, action: Action, accelerator: Option<&str>) -> Result<(), Error> {
+        let accelerator = match accelerator {
+            Some(accel) => Some(
+                normalize_accelerator(accel)
+                    .ok_or_else(|| Error::InvalidAccelerator(accel.into()))?,
+            ),
+            None => None,
+        };
+        if accelerator.as_deref() == action.default_accelerator() {
+            self.overrides.remove(&action);
+        } else {
+            self.overrides.insert(action, accelerator);
+        }
+        Ok(())
+    }
+
+    /// Reset one action, or all actions if `None`, to the default bindings.
+    pub fn reset(&mut self, action: Option<Action>) {
+        match action {
+            Some(action) => _ = self.overrides.remove(&action),
+            None => self.overrides.clear(),
+        }
+    }
+
+    /// List the effective bindings for all actions, with conflicts.
+    pub fn bindings(&self) -> Vec<KeyBinding> {
+        let mut by_accelerator: BTreeMap<String, Vec<Action>> = BTreeMap::new();
+        for action in Action::iter() {
+            if let Some(key) = self.accelerator(action).and_then(conflict_key) {
+                by_accelerator.entry(key).or_default().push(action);
+            }
+        }
+
+        Action::iter()
+            .map(|action| {
+                let accelerator = self.accelerator(action);
+                let conflicts = accelerator
+                    .and_then(conflict_key)
+                    .and_then(|key| by_accelerator.get(&key))
+                    .map(|actions| actions.iter().copied().filter(|a| *a != action).collect())
+                    .unwrap_or_default();
+                KeyBinding {
+                    action,
+                    label: action.label().into(),
+                    category: action.category(),
+                    accelerator: accelerator.map(String::from),
+                    default_accelerator: action.default_accelerator().map(String::from),
+                    conflicts,
+                }
+            })
+            .collect()
+    }
+
+    /// List the bound shortcuts grouped by category, leaving out empty groups.
+    pub fn shortcuts(&self) -> Vec<ShortcutGroup> {
+        let bindings = self.bindings();
+        Category::iter()
+            .map(|category| ShortcutGroup {
+                category,
+                bindings: bindings
+                    .iter()
+                    .filter(|b| b.category == category && b.accelerator.is_some())
+                    .cloned()
+                    .collect(),
+            })
+            .filter(|group| !group.bindings.is_empty())
+            .collect()
+    }
+}
+
+/// Put an accelerator into canonical form, with modifiers in a fixed order.
+///
+/// Returns `None` if the accelerator is malformed, i.e., it does not have
+/// exactly one non-modifier key.
+pub fn normalize_accelerator(accelerator: &str) -> Option<String> {
+    const MODIFIERS: [&str; 5] = ["CmdOrCtrl", "Super", "Ctrl", "Alt", "Shift"];
+
+    let mut modifiers = [false; MODIFIERS.len()];
+    let mut key = None;
+    for part in accelerator.split('+') {
+        let part = part.trim();
+        let index = match part.to_ascii_lowercase().as_str() {
+            "" => return None,
+            "cmdorctrl" | "cmdorcontrol" | "commandorctrl" | "commandorcontrol" => 0,
+            "super" | "cmd" | "command" | "meta" => 1,
+            "ctrl" | "control" => 2,
+            "alt" | "option" => 3,
+            "shift" => 4,
+            _ => {
+                if key.is_some() {
+                    return None;
+                }
+                // Capitalize the key, e.g., `k` -> `K` and `enter` -> `Enter`.
+                let mut chars = part.chars();
+                key = chars
+                    .next()
+                    .map(|c| c.to_uppercase().chain(chars).collect::<String>());
+                continue;
+            }
+        };
+        modifiers[index] = true;
+    }
+
+    let mut parts: Vec<String> = MODIFIERS
+        .iter()
+        .zip(modifiers)
+        .filter(|(_, present)| *present)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    parts.push(key?);
+    Some(parts.join("+"))
+}
+
+/// Key used to detect conflicts, resolving `CmdOrCtrl` for this platform.
+fn conflict_key(accelerator: &str) -> Option<String> {
+    let platform_mod = if cfg!(target_os = "macos") {
+        "Super"
+    } else {
+        "Ctrl"
+    };
+    let resolved = accelerator.replace("CmdOrCtrl", platform_mod);
+    normalize_accelerator(&resolved).map(|s| s.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            normalize_accelerator("shift+cmdorctrl+k").as_deref(),
+            Some("CmdOrCtrl+Shift+K")
+        );
+        assert_eq!(
+            normalize_accelerator("Alt + Enter").as_deref(),
+            Some("Alt+Enter")
+        );
+        assert_eq!(normalize_accelerator("Ctrl+Shift"), None);
+        assert_eq!(normalize_accelerator("Ctrl+A+B"), None);
+        assert_eq!(normalize_accelerator("Ctrl++"), None);
+    }
+
+    #[test]
+    fn overrides_and_conflicts() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.accelerator(Action::OpenFile), Some("CmdOrCtrl+O"));
+
+        keymap
+            .set(Action::CommandPalette, Some("cmdorctrl+o"))
+            .unwrap();
+        let bindings = keymap.bindings();
+        let palette = bindings
+            .iter()
+            .find(|b| b.action == Action::CommandPalette)
+            .unwrap();
+        assert_eq!(palette.accelerator.as_deref(), Some("CmdOrCtrl+O"));
+        assert_eq!(palette.conflicts, [Action::OpenFile]);
+
+        keymap.set(Action::OpenFile, None).unwrap();
+        assert_eq!(keymap.accelerator(Action::OpenFile), None);
+        assert!(keymap.bindings().iter().all(|b| b.conflicts.is_empty()));
+
+        assert!(keymap.set(Action::RunCell, Some("Shift+")).is_err());
+
+        keymap.reset(None);
+        assert_eq!(keymap, Keymap::default());
+    }
+
+    #[test]
+    fn setting_default_clears_override() {
+        let mut keymap = Keymap::default();
+        keymap.set(Action::RunCell, Some("Alt+Enter")).unwrap();
+        keymap.set(Action::RunCell, Some("shift+enter")).unwrap();
+        assert_eq!(keymap, Keymap::default());
+    }
+
+    #[test]
+    fn shortcuts_by_category() {
+        let mut keymap = Keymap::default();
+        keymap.set(Action::Save, None).unwrap();
+        keymap.set(Action::RunAllAbove, Some("Alt+Up")).unwrap();
+
+        let groups = keymap.shortcuts();
+        let categories: Vec<_> = groups.iter().map(|g| g.category).collect();
+        assert_eq!(
+            categories,
+            [Category::General, Category::File, Category::Run]
+        );
+        let actions = |category| -> Vec<Action> {
+            let group = groups.iter().find(|g| g.category == category).unwrap();
+            group.bindings.iter().map(|b| b.action).collect()
+        };
+        assert!(!actions(Category::File).contains(&Action::Save));
+        assert!(actions(Category::Run).contains(&Action::RunAllAbove));
+        assert!(!actions(Category::Run).contains(&Action::RunAllBelow));
+    }
+}
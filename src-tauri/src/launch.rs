@@ -0,0 +1,144 @@
+//! Starting local kernels for notebooks, shared by the app and headless mode.
+//!
+//! The kernel spec is chosen from the user's choice, the notebook's project,
+//! and its metadata, and the project's `jute.toml` sets up the kernel with
+//! environment variables and startup code.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use tracing::{info, warn};
+
+use crate::{
+    backend::{
+        commands::{self, RunCellEvent},
+        local::{environment, LocalKernel},
+        wire_protocol::{KernelConnection, KernelInfoReply},
+    },
+    project::Project,
+    Error,
+};
+
+/// Options for starting a kernel for a notebook.
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions<'a> {
+    /// Name of the kernel spec chosen by the user, which takes precedence.
+    pub spec_name: Option<&'a str>,
+
+    /// Path to the notebook, whose project supplies defaults.
+    pub notebook_path: Option<&'a Path>,
+
+    /// Name of the kernel spec stored in the notebook's metadata.
+    pub stored_spec: Option<&'a str>,
+
+    /// Name of the kernel spec to use when nothing else chooses one.
+    pub default_kernel: Option<&'a str>,
+
+    /// Prefix of a virtual environment, whose kernels are found first.
+    pub venv_prefix: Option<&'a Path>,
+
+    /// Python interpreter to run `python` kernels with, instead of the one on
+    /// the `PATH`.
+    pub python: Option<PathBuf>,
+
+    /// Whether the project may set environment variables and run startup
+    /// code in the kernel.
+    pub trusted: bool,
+}
+
+/// A kernel that has started and run its startup code.
+pub struct LaunchedKernel {
+    /// The running kernel.
+    pub kernel: LocalKernel,
+
+    /// The kernel's `kernel_info_reply`.
+    pub info: KernelInfoReply,
+
+    /// Setup code that was run in the kernel, to run again if it restarts.
+    pub startup: Vec<String>,
+}
+
+/// Start a kernel for a notebook.
+///
+/// The spec is the first of `spec_name`, the project's environment, the spec
+/// stored in the notebook, `default_kernel`, and `python3`.
+pub async fn launch(options: LaunchOptions<'_>) -> Result<LaunchedKernel, Error> {
+    let project = match options.notebook_path {
+        Some(path) => Project::discover(path).await?,
+        None => None,
+    };
+    let mut config = project.map(|p| p.config).unwrap_or_default();
+    if !options.trusted {
+        info!("skipping startup code of an untrusted project");
+        config.startup = None;
+        config.env.clear();
+    }
+    let spec_name = (options.spec_name)
+        .or(config.environment.as_deref())
+        .or(options.stored_spec)
+        .or(options.default_kernel)
+        .unwrap_or("python3");
+
+    let prefix = options.venv_prefix.map(|p| p.to_string_lossy());
+    let kernels = environment::list_kernels(prefix.as_deref()).await;
+    let mut kernel_spec = kernels
+        .into_iter()
+        .find(|(path, _)| path.file_name().and_then(|s| s.to_str()) == Some(spec_name))
+        .map(|(_, spec)| spec)
+        .ok_or_else(|| Error::KernelConnect(format!("no kernel named {spec_name:?} found")))?;
+    if kernel_spec.argv.first().is_some_and(|arg| arg == "python") {
+        if let Some(python) = &options.python {
+            kernel_spec.argv[0] = python.to_string_lossy().into_owned();
+        }
+    }
+
+    kernel_spec.env.extend(config.env);
+    let quirks = kernel_spec.quirks();
+    let kernel = LocalKernel::start(&kernel_spec).await?;
+
+    let info = tokio::time::timeout(quirks.startup_timeout, commands::kernel_info(kernel.conn()))
+        .await
+        .map_err(|_| {
+            Error::KernelConnect(format!(
+                "{} did not start within {} seconds",
+                kernel_spec.display_name,
+                quirks.startup_timeout.as_secs(),
+            ))
+        })??;
+    info!(banner = info.banner, "started new jute kernel");
+
+    // Setup for the kernel itself runs before the project's startup code.
+    let startup: Vec<String> = (quirks.startup_code.into_iter())
+        .chain(config.startup)
+        .collect();
+    run_startup_code(kernel.conn(), &startup).await?;
+    Ok(LaunchedKernel {
+        kernel,
+        info,
+        startup,
+    })
+}
+
+/// Python interpreter from the `PYTHON_PATH` environment variable, if set.
+pub fn python_from_env() -> Option<PathBuf> {
+    env::var_os("PYTHON_PATH").map(PathBuf::from)
+}
+
+/// Run setup code in a kernel that just started, logging any errors in it.
+pub async fn run_startup_code(conn: &KernelConnection, code: &[String]) -> Result<(), Error> {
+    for startup in code {
+        let rx = commands::run_cell(conn, startup, Default::default()).await?;
+        while let Ok(event) = rx.recv().await {
+            if let RunCellEvent::Error(err) = event {
+                warn!(
+                    ename = err.ename,
+                    evalue = err.evalue,
+                    "kernel startup code failed"
+                );
+            }
+        }
+    }
+    Ok(())
+}
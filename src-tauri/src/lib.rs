@@ -5,13 +5,48 @@
 
 use std::io;
 
+pub mod autosave;
 pub mod backend;
+pub mod bindings;
+pub mod checkpoints;
+pub mod collab;
 pub mod commands;
+pub mod database;
+pub mod dataflow;
+pub mod deep_link;
+pub mod document;
 pub mod entity;
+pub mod export;
+pub mod gist;
+pub mod git;
+pub mod headless;
+pub mod import;
+pub mod indexing;
+pub mod ipc;
+pub mod jupyter_config;
+pub mod keymap;
+pub mod launch;
+pub mod loading;
+pub mod logging;
 pub mod menu;
+pub mod onboarding;
 pub mod plugins;
+pub mod project;
+pub mod recent;
+pub mod recovery;
+pub mod renderers;
+pub mod secrets;
+pub mod servers;
+pub mod settings;
+pub mod share;
 pub mod state;
+pub mod store;
+pub mod tasks;
+pub mod terminal;
+pub mod unsaved;
+pub mod updates;
 pub mod window;
+pub mod window_state;
 
 /// A serializable error type for application errors.
 #[derive(Debug, thiserror::Error)]
@@ -28,6 +63,14 @@ pub enum Error {
     #[error("disconnected from the kernel")]
     KernelDisconnect,
 
+    /// No input request with this ID is waiting for a reply.
+    #[error("no input request {0:?} is waiting for a reply")]
+    InputRequestNotFound(String),
+
+    /// The kernel does not support a feature that was requested.
+    #[error("the kernel does not support {0}")]
+    KernelUnsupported(String),
+
     /// An invalid URL was provided or constructed.
     #[error("invalid URL: {0}")]
     InvalidUrl(#[from] url::ParseError),
@@ -56,9 +99,81 @@ pub enum Error {
     #[error("tauri error: {0}")]
     Tauri(#[from] tauri::Error),
 
+    /// An entity ID was malformed, of the wrong kind, or not found.
+    #[error("invalid entity: {0}")]
+    InvalidEntity(String),
+
+    /// The project configuration file could not be parsed.
+    #[error("invalid jute.toml: {0}")]
+    ProjectConfig(#[from] toml::de::Error),
+
+    /// A `jute://` link was malformed or not recognized.
+    #[error("invalid link: {0}")]
+    InvalidDeepLink(String),
+
+    /// A keyboard shortcut could not be parsed.
+    #[error("invalid keyboard shortcut: {0}")]
+    InvalidAccelerator(String),
+
+    /// A file could not be attached to a cell, with the reason.
+    #[error("invalid attachment: {0}")]
+    InvalidAttachment(String),
+
+    /// An operation could not be applied to a notebook document.
+    #[error("invalid notebook operation: {0}")]
+    InvalidNotebookOp(String),
+
+    /// Error while serving a notebook on the local network.
+    #[error("could not share notebook: {0}")]
+    ShareServer(io::Error),
+
+    /// A git command failed, with its error message.
+    #[error("git error: {0}")]
+    Git(String),
+
+    /// A database connection or SQL query failed, with its error message.
+    #[error("database error: {0}")]
+    Database(String),
+
+    /// Could not list the variables in a kernel, with the reason.
+    #[error("could not list variables: {0}")]
+    Variables(String),
+
+    /// Error while running a shell in the integrated terminal.
+    #[error("terminal error: {0}")]
+    Terminal(String),
+
+    /// A collaboration session failed, with its error message.
+    #[error("collaboration error: {0}")]
+    Collab(String),
+
+    /// Could not log in to GitHub, or no GitHub token is stored.
+    #[error("GitHub login failed: {0}")]
+    GithubLogin(String),
+
+    /// A downloaded renderer file didn't match its pinned hash.
+    #[error("renderer integrity check failed: {0}")]
+    RendererIntegrity(String),
+
+    /// Error while accessing the OS keychain.
+    #[error("keychain error: {0}")]
+    Keyring(#[from] keyring::Error),
+
+    /// Error while registering a global shortcut.
+    #[error("global shortcut error: {0}")]
+    PluginGlobalShortcut(#[from] tauri_plugin_global_shortcut::Error),
+
+    /// Error while opening a file or URL with the opener plugin.
+    #[error("opener plugin error: {0}")]
+    PluginOpener(#[from] tauri_plugin_opener::Error),
+
     /// Error while interacting with the shell plugin.
     #[error("shell plugin error: {0}")]
     PluginShell(#[from] tauri_plugin_shell::Error),
+
+    /// Error while checking for or installing an update.
+    #[error("updater error: {0}")]
+    PluginUpdater(#[from] tauri_plugin_updater::Error),
 }
 
 impl serde::Serialize for Error {
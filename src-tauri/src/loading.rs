@@ -0,0 +1,190 @@
+//! Loading of large notebooks in pages of cells.
+//!
+//! A notebook full of images can be hundreds of megabytes, which stalls the
+//! window if it is sent in one message. Instead, the file is read once and its
+//! cells are kept here, while the frontend gets an outline of the notebook
+//! with a summary of each cell, then fetches the cells a page at a time.
+
+use dashmap::DashMap;
+use serde::Serialize;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::backend::notebook::{Cell, MultilineString, NotebookMetadata, NotebookRoot, Output};
+
+/// A cell in a notebook being loaded, without its contents.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct CellSummary {
+    /// Identifier of the cell.
+    pub id: String,
+
+    /// Type of the cell, as in the `cell_type` field.
+    pub cell_type: String,
+
+    /// Number of outputs of a code cell.
+    #[ts(type = "number")]
+    pub output_count: usize,
+
+    /// Approximate size of the cell as JSON, in bytes, to choose how many to
+    /// fetch at once.
+    #[ts(type = "number")]
+    pub size: usize,
+}
+
+crate::export_bindings!(CellSummary);
+
+/// A notebook being loaded, with everything but the contents of its cells.
+#[derive(Serialize, Clone, Debug, PartialEq, TS)]
+pub struct NotebookOutline {
+    /// ID of the load, to fetch the cells with.
+    pub load_id: String,
+
+    /// Root-level metadata of the notebook.
+    pub metadata: NotebookMetadata,
+
+    /// Notebook format (minor number).
+    pub nbformat_minor: u8,
+
+    /// Notebook format (major number).
+    pub nbformat: u8,
+
+    /// Summaries of the cells, in order.
+    pub cells: Vec<CellSummary>,
+}
+
+crate::export_bindings!(NotebookOutline);
+
+/// Cells of the notebooks being loaded, by load ID.
+#[derive(Default, Debug)]
+pub struct NotebookLoads {
+    loads: DashMap<String, Load>,
+}
+
+/// The cells of a notebook being loaded, and the window loading it.
+#[derive(Debug)]
+struct Load {
+    window: String,
+    cells: Vec<Cell>,
+}
+
+impl NotebookLoads {
+    /// Start loading a notebook in a window, returning its outline. The cells
+    /// are kept until [`finish`](Self::finish) is called or the window
+    /// closes.
+    pub fn start(&self, window: &str, notebook: NotebookRoot) -> NotebookOutline {
+        let summaries = (notebook.cells.iter())
+            .map(|cell| CellSummary {
+                id: cell.id().unwrap_or_default().into(),
+                cell_type: cell.cell_type().into(),
+                output_count: match cell {
+                    Cell::Code(cell) => cell.outputs.len(),
+                    _ => 0,
+                },
+                size: cell_size(cell),
+            })
+            .collect();
+        let load_id = Uuid::new_v4().to_string();
+        let load = Load {
+            window: window.into(),
+            cells: notebook.cells,
+        };
+        self.loads.insert(load_id.clone(), load);
+        NotebookOutline {
+            load_id,
+            metadata: notebook.metadata,
+            nbformat_minor: notebook.nbformat_minor,
+            nbformat: notebook.nbformat,
+            cells: summaries,
+        }
+    }
+
+    /// Return the cells from `start` up to `end`, clamped to the notebook, or
+    /// `None` if the load is not in progress.
+    pub fn cells(&self, load_id: &str, start: usize, end: usize) -> Option<Vec<Cell>> {
+        let load = self.loads.get(load_id)?;
+        let cells = &load.cells;
+        let end = end.min(cells.len());
+        Some(cells[start.min(end)..end].to_vec())
+    }
+
+    /// Drop the cells of a notebook once it is loaded or the load is given up.
+    pub fn finish(&self, load_id: &str) {
+        self.loads.remove(load_id);
+    }
+
+    /// Drop the cells of every notebook a window was loading, once it closes.
+    pub fn finish_window(&self, window: &str) {
+        self.loads.retain(|_, load| load.window != window);
+    }
+}
+
+/// Approximate size of a cell as JSON, from the strings in its source and
+/// outputs, which make up nearly all of a large cell.
+fn cell_size(cell: &Cell) -> usize {
+    match cell {
+        Cell::Raw(cell) => text_size(&cell.source),
+        Cell::Markdown(cell) => text_size(&cell.source),
+        Cell::Code(cell) => {
+            let outputs = cell.outputs.iter().map(|output| match output {
+                Output::ExecuteResult(result) => result.data.values().map(value_size).sum(),
+                Output::DisplayData(display) => display.data.values().map(value_size).sum(),
+                Output::Stream(stream) => text_size(&stream.text),
+                Output::Error(error) => error.traceback.iter().map(String::len).sum(),
+            });
+            text_size(&cell.source) + outputs.sum::<usize>()
+        }
+    }
+}
+
+/// Length of a multiline string, in bytes.
+fn text_size(text: &MultilineString) -> usize {
+    match text {
+        MultilineString::Single(text) => text.len(),
+        MultilineString::Multi(lines) => lines.iter().map(String::len).sum(),
+    }
+}
+
+/// Length of the strings in a JSON value, in bytes, with a little for other
+/// values.
+fn value_size(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::String(text) => text.len(),
+        serde_json::Value::Array(values) => values.iter().map(value_size).sum(),
+        serde_json::Value::Object(map) => map.iter().map(|(k, v)| k.len() + value_size(v)).sum(),
+        _ => 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_in_pages() {
+        let cells: Vec<_> = (0..5)
+            .map(|i| serde_json::json!({ "cell_type": "raw", "id": format!("c{i}"), "metadata": {}, "source": "x".repeat(i) }))
+            .collect();
+        let notebook = NotebookRoot::from_cells(cells);
+
+        let loads = NotebookLoads::default();
+        let outline = loads.start("main", notebook.clone());
+        assert_eq!(outline.cells.len(), 5);
+        assert_eq!(outline.cells[2].id, "c2");
+        assert!(outline.cells[1].size < outline.cells[4].size);
+
+        let page = loads.cells(&outline.load_id, 1, 3).unwrap();
+        assert_eq!(page, notebook.cells[1..3]);
+        let page = loads.cells(&outline.load_id, 3, 100).unwrap();
+        assert_eq!(page, notebook.cells[3..]);
+        assert!(loads.cells(&outline.load_id, 7, 9).unwrap().is_empty());
+
+        loads.finish(&outline.load_id);
+        assert_eq!(loads.cells(&outline.load_id, 0, 1), None);
+
+        let outline = loads.start("main", notebook);
+        loads.finish_window("other");
+        assert!(loads.cells(&outline.load_id, 0, 1).is_some());
+        loads.finish_window("main");
+        assert_eq!(loads.cells(&outline.load_id, 0, 1), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! Logging to the console and to rotating files in the app log directory.
+//!
+//! Console output is invisible in production builds, so logs are also written
+//! to daily files that users can attach to bug reports. The log level can be
+//! changed at runtime from the settings.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use tracing::warn;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    filter::LevelFilter, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, Registry,
+};
+
+use crate::{settings::LogLevel, Error};
+
+/// Prefix of log file names, followed by the date and `.log` extension.
+pub const LOG_FILE_PREFIX: &str = "jute";
+
+/// How many days of log files to keep around before deleting old ones.
+const MAX_LOG_FILES: usize = 7;
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Handle to the global logger, installed once at startup.
+pub struct Logger {
+    log_dir: PathBuf,
+    reload_handle: reload::Handle<LevelFilter, Registry>,
+
+    /// Flushes buffered log lines to the file when dropped.
+    _guard: WorkerGuard,
+}
+
+impl Logger {
+    /// Install the global tracing subscriber, writing to stdout and to rolling
+    /// files in `log_dir`.
+    pub fn init(log_dir: &Path, level: LogLevel) -> Result<Self, Error> {
+        std::fs::create_dir_all(log_dir).map_err(Error::Filesystem)?;
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(log_dir)
+            .map_err(|err| Error::Filesystem(io::Error::other(err)))?;
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+
+        let (filter, reload_handle) = reload::Layer::new(LevelFilter::from(level));
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer())
+            .with(fmt::layer().with_ansi(false).with_writer(writer))
+            .init();
+
+        Ok(Self {
+            log_dir: log_dir.to_path_buf(),
+            reload_handle,
+            _guard: guard,
+        })
+    }
+
+    /// Get the directory where log files are written.
+    pub fn log_dir(&self) -> &Path {
+        &self.log_dir
+    }
+
+    /// Change the minimum level of recorded log messages.
+    pub fn set_level(&self, level: LogLevel) {
+        if let Err(err) = self.reload_handle.reload(LevelFilter::from(level)) {
+            warn!("could not change log level: {err}");
+        }
+    }
+
+    /// Read the last `n` lines of logs, possibly spanning multiple files.
+    pub async fn recent_lines(&self, n: usize) -> Result<Vec<String>, Error> {
+        let mut files = Vec::new();
+        let mut it = tokio::fs::read_dir(&self.log_dir)
+            .await
+            .map_err(Error::Filesystem)?;
+        while let Some(entry) = it.next_entry().await.map_err(Error::Filesystem)? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(LOG_FILE_PREFIX) && name.ends_with(".log") {
+                files.push(entry.path());
+            }
+        }
+        // File names contain the date, so lexicographic order is chronological.
+        files.sort();
+
+        let mut lines = Vec::new();
+        for path in files.iter().rev() {
+            if lines.len() >= n {
+                break;
+            }
+            let contents = tokio::fs::read_to_string(path)
+                .await
+                .map_err(Error::Filesystem)?;
+            let remaining = n - lines.len();
+            let file_lines: Vec<_> = contents.lines().collect();
+            let start = file_lines.len().saturating_sub(remaining);
+            // Prepend since we are walking backwards through the files.
+            lines.splice(0..0, file_lines[start..].iter().map(|s| s.to_string()));
+        }
+        Ok(lines)
+    }
+}
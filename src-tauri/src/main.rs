@@ -1,18 +1,25 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+};
 
-use jute::state::State;
-use tauri::AppHandle;
-#[allow(unused_imports)]
-use tauri::Manager;
+use jute::{
+    database::DatabaseRegistry, keymap::Keymap, logging::Logger, recent::RecentPaths,
+    servers::ServerRegistry, settings::Settings, state::State, window_state::WindowStates,
+};
+use tauri::{AppHandle, Manager, WindowEvent};
 
 /// Handle file associations opened in the application.
 ///
 /// Jute registers itself as an application to open `.ipynb` files, which are
-/// the file type for Jupyter Notebooks. This function is called when the user
-/// double-clicks on a notebook file to open it with Jute.
+/// the file type for Jupyter Notebooks, as well as percent scripts and Quarto
+/// and R Markdown documents, which are converted to notebooks when read. This
+/// function is called when the user double-clicks on a notebook file to open it
+/// with Jute.
 ///
 /// Depending on the operating system, it will either launch a new process with
 /// the file in `argv[1]` or send a [`tauri::RunEvent::Opened`] event. There may
@@ -30,95 +37,406 @@ fn handle_file_associations(
     files: &[PathBuf],
 ) -> Result<(), Box<dyn std::error::Error>> {
     for file in files {
-        jute::window::open_notebook_path(app, file)?;
+        jute::window::open_notebook_path(app, file, None)?;
     }
     Ok(())
 }
 
+/// Split command-line arguments into notebook files and `jute://` deep links.
+///
+/// Flags are skipped, as are URLs with other schemes. Relative paths are
+/// resolved against `cwd`, since arguments forwarded from a second instance
+/// are relative to that process's working directory.
+fn parse_args(args: impl IntoIterator<Item = String>, cwd: &Path) -> (Vec<PathBuf>, Vec<url::Url>) {
+    let mut files = Vec::new();
+    let mut links = Vec::new();
+
+    for maybe_file in args {
+        // Skip flags like -f or --flag
+        if maybe_file.starts_with('-') {
+            continue;
+        }
+        // Handle `file://` path URLs and `jute://` deep links, and
+        // skip other URLs.
+        if let Ok(url) = url::Url::parse(&maybe_file) {
+            if url.scheme() == "file" {
+                if let Ok(path) = url.to_file_path() {
+                    files.push(path);
+                }
+            } else if url.scheme() == jute::deep_link::SCHEME {
+                links.push(url);
+            }
+        } else {
+            files.push(cwd.join(maybe_file));
+        }
+    }
+    (files, links)
+}
+
+/// Handle a second launch of the app on Windows and Linux, such as opening a
+/// notebook from the file manager while Jute is running.
+///
+/// The second process exits right away, and its arguments are forwarded here.
+/// Files and links are opened in new windows, or else the app is focused.
+#[cfg(any(windows, target_os = "linux"))]
+fn handle_second_instance(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    let (files, links) = parse_args(argv.into_iter().skip(1), Path::new(&cwd));
+    if files.is_empty() && links.is_empty() {
+        let window = jute::window::focused_window(app)
+            .or_else(|| app.webview_windows().into_values().next());
+        match window {
+            Some(window) => {
+                _ = window.unminimize();
+                _ = window.set_focus();
+            }
+            None => _ = jute::window::open_home(app),
+        }
+        return;
+    }
+    if let Err(err) = handle_file_associations(app, &files) {
+        tracing::warn!("could not open files from second instance: {err}");
+    }
+    for link in &links {
+        jute::commands::deep_link::open_deep_link(app, link);
+    }
+}
+
 fn main() {
-    tracing_subscriber::fmt().init();
+    // Run a notebook from the command line without opening any windows.
+    if env::args().nth(1).as_deref() == Some(jute::headless::HEADLESS_FLAG) {
+        process::exit(jute::headless::main(env::args().skip(2)));
+    }
 
     #[allow(unused_mut)]
     let mut app = tauri::Builder::default();
 
+    // This plugin must be registered first, so that a second instance exits
+    // before it sets anything else up.
+    #[cfg(any(windows, target_os = "linux"))]
+    {
+        app = app.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            handle_second_instance(app, argv, cwd);
+        }));
+    }
+
     #[cfg(target_os = "macos")]
     {
         app = app.plugin(jute::plugins::macos_traffic_lights::init());
+        app = app.plugin(jute::plugins::macos_dock_menu::init());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        app = app.plugin(jute::plugins::windows_titlebar::init());
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        app = app.plugin(jute::plugins::linux_desktop::init());
     }
 
     app.manage(State::new())
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .register_asynchronous_uri_scheme_protocol(
+            jute::renderers::SCHEME,
+            jute::commands::renderers::handle_request,
+        )
         .invoke_handler(tauri::generate_handler![
             jute::commands::cpu_usage,
+            jute::commands::list_kernel_specs,
+            jute::commands::new_notebook,
             jute::commands::start_kernel,
             jute::commands::stop_kernel,
+            jute::commands::interrupt_kernel,
+            jute::commands::is_complete,
+            jute::commands::get_kernel_history,
+            jute::commands::restart_kernel,
+            jute::commands::get_kernel_info,
             jute::commands::run_cell,
+            jute::commands::run_all_cells,
+            jute::commands::clear_execution_queue,
+            jute::commands::fetch_output_chunk,
+            jute::commands::run_in_subshell,
+            jute::commands::list_variables,
+            jute::commands::reply_input,
             jute::commands::get_notebook,
+            jute::commands::get_notebook_outline,
+            jute::commands::get_notebook_cells,
+            jute::commands::finish_notebook_load,
+            jute::commands::save_notebook,
+            jute::commands::diff_notebooks,
+            jute::commands::resolve_notebook_conflicts,
+            jute::commands::get_project,
             jute::commands::venv::venv_list_python_versions,
             jute::commands::venv::venv_create,
             jute::commands::venv::venv_list,
             jute::commands::venv::venv_delete,
+            jute::commands::settings::get_settings,
+            jute::commands::settings::update_settings,
+            jute::commands::logs::get_recent_logs,
+            jute::commands::reveal::reveal_path,
+            jute::commands::reveal::reveal_venv,
+            jute::commands::reveal::open_terminal,
+            jute::commands::tasks::list_tasks,
+            jute::commands::tasks::cancel_task,
+            jute::commands::tasks::start_task,
+            jute::commands::tasks::set_task_progress,
+            jute::commands::tasks::add_task_failure,
+            jute::commands::tasks::finish_task,
+            jute::commands::keymap::list_keybindings,
+            jute::commands::keymap::list_shortcuts,
+            jute::commands::keymap::update_keybinding,
+            jute::commands::keymap::reset_keybindings,
+            jute::commands::recovery::record_cell_edit,
+            jute::commands::recovery::list_recoverable_notebooks,
+            jute::commands::recovery::get_notebook_recovery,
+            jute::commands::recovery::discard_recovery,
+            jute::commands::attachments::create_attachment,
+            jute::commands::attachments::attach_file,
+            jute::commands::attachments::list_attachments,
+            jute::commands::document::open_document,
+            jute::commands::document::get_document,
+            jute::commands::document::apply_document_ops,
+            jute::commands::document::save_document,
+            jute::commands::document::close_document,
+            jute::commands::checkpoints::create_checkpoint,
+            jute::commands::checkpoints::list_checkpoints,
+            jute::commands::checkpoints::restore_checkpoint,
+            jute::commands::scratchpad::get_scratchpad_kernel,
+            jute::commands::servers::list_servers,
+            jute::commands::servers::add_server,
+            jute::commands::servers::remove_server,
+            jute::commands::jupyter_config::import_jupyter_settings,
+            jute::commands::updates::check_for_updates,
+            jute::commands::updates::install_update,
+            jute::commands::onboarding::detect_pythons,
+            jute::commands::onboarding::detect_jupyter,
+            jute::commands::onboarding::verify_uv,
+            jute::commands::onboarding::create_default_environment,
+            jute::commands::onboarding::complete_onboarding,
+            jute::commands::window::attach_notebook_window,
+            jute::commands::window::detach_notebook_window,
+            jute::commands::window::open_tab,
+            jute::commands::window::select_tab,
+            jute::commands::window::update_tab,
+            jute::commands::window::close_tab,
+            jute::commands::window::close_saved_window,
+            jute::commands::window::move_tab_to_new_window,
+            jute::commands::window::focus_notebook_cell,
+            jute::commands::workspace::list_directory,
+            jute::commands::workspace::open_folder,
+            jute::commands::monitor::list_running_kernels,
+            jute::commands::monitor::watch_kernel_usage,
+            jute::commands::monitor::kernel_action,
+            jute::commands::devtools::set_kernel_tap,
+            jute::commands::devtools::watch_kernel_messages,
+            jute::commands::export::export_slides,
+            jute::commands::gist::start_github_login,
+            jute::commands::gist::finish_github_login,
+            jute::commands::gist::share_gist,
+            jute::commands::share::start_share,
+            jute::commands::share::update_share,
+            jute::commands::share::stop_share,
+            jute::commands::dataflow::get_dependency_graph,
+            jute::commands::dataflow::get_dependent_cells,
+            jute::commands::git::git_status,
+            jute::commands::git::git_diff_notebook,
+            jute::commands::git::git_commit,
+            jute::commands::git::git_blame_cells,
+            jute::commands::git::watch_git_head,
+            jute::commands::database::list_databases,
+            jute::commands::database::add_database,
+            jute::commands::database::remove_database,
+            jute::commands::database::execute_sql,
+            jute::commands::database::run_sql_cell,
+            jute::commands::terminal::spawn_terminal,
+            jute::commands::terminal::write_terminal,
+            jute::commands::terminal::resize_terminal,
+            jute::commands::terminal::kill_terminal,
+            jute::commands::collab::host_collab,
+            jute::commands::collab::join_collab,
+            jute::commands::collab::join_jupyter_collab,
+            jute::commands::collab::collab_sync,
+            jute::commands::collab::collab_edit_source,
+            jute::commands::collab::collab_set_cursor,
+            jute::commands::collab::leave_collab,
+            jute::commands::rust_kernel::rust_kernel_status,
+            jute::commands::rust_kernel::setup_rust_kernel,
         ])
-        .setup(|app| {
-            // Parse files that were opened via CLI arguments (Windows + Linux).
-            if cfg!(any(windows, target_os = "linux")) {
-                let mut files = Vec::new();
-
-                for maybe_file in env::args().skip(1) {
-                    // Skip flags like -f or --flag
-                    if maybe_file.starts_with('-') {
-                        continue;
+        .on_window_event(|window, event| match event {
+            // Menu items depend on whether the focused window has a notebook.
+            // Failures shown on the Dock badge have now been seen.
+            WindowEvent::Focused(true) => {
+                _ = jute::menu::update_menu_state(window.app_handle());
+                jute::commands::tasks::clear_unseen_failures(window.app_handle());
+            }
+            // Remember where notebook windows are, to restore them on reopen.
+            WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+                _ = jute::window::record_geometry(window);
+            }
+            WindowEvent::CloseRequested { api, .. } => {
+                // Ask to save unsaved changes before the window is closed.
+                if let Some(window) = window.get_webview_window(window.label()) {
+                    if jute::unsaved::on_close_requested(&window) {
+                        api.prevent_close();
                     }
-                    // Handle `file://` path URLs and skip other URLs.
-                    if let Ok(url) = url::Url::parse(&maybe_file) {
-                        if url.scheme() == "file" {
-                            if let Ok(path) = url.to_file_path() {
-                                files.push(path);
-                            }
+                }
+                if let Ok(true) = jute::window::record_geometry(window) {
+                    let app = window.app_handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(err) = jute::commands::window::save_window_states(&app).await {
+                            tracing::warn!("could not save window state: {err}");
                         }
-                    } else {
-                        files.push(PathBuf::from(maybe_file));
-                    }
+                    });
+                }
+            }
+            WindowEvent::Destroyed => {
+                let state = window.state::<State>();
+                state.notebook_windows.remove(window.label());
+                state.workspace_windows.remove(window.label());
+                jute::commands::share::stop_window_share(window.app_handle(), window.label());
+                jute::commands::document::close_window_documents(
+                    window.app_handle(),
+                    window.label(),
+                );
+                state.collab_sessions.remove(window.label());
+                state.notebook_loads.finish_window(window.label());
+                jute::commands::tasks::fail_window_tasks(window.app_handle(), window.label());
+                jute::commands::terminal::kill_window_terminals(
+                    window.app_handle(),
+                    window.label(),
+                );
+                _ = jute::menu::update_menu_state(window.app_handle());
+            }
+            _ => {}
+        })
+        .setup(|app| {
+            // Load settings first, since they determine the log level.
+            let settings_path = jute::commands::settings::settings_path(app.handle())?;
+            let first_run = !settings_path.exists();
+            let settings = tauri::async_runtime::block_on(Settings::load(&settings_path));
+            let logger = Logger::init(&app.path().app_log_dir()?, settings.log_level)?;
+            let keymap_path = jute::commands::keymap::keymap_path(app.handle())?;
+            let keymap = tauri::async_runtime::block_on(Keymap::load(&keymap_path));
+            let window_state_path = jute::commands::window::window_state_path(app.handle())?;
+            let window_states =
+                tauri::async_runtime::block_on(WindowStates::load(&window_state_path));
+            let recent_path = jute::commands::window::recent_path(app.handle())?;
+            let recent = tauri::async_runtime::block_on(RecentPaths::load(&recent_path));
+            let recent_folders_path = jute::commands::workspace::recent_folders_path(app.handle())?;
+            let recent_folders =
+                tauri::async_runtime::block_on(RecentPaths::load(&recent_folders_path));
+            let state = app.state::<State>();
+            let servers_path = jute::commands::servers::servers_path(app.handle())?;
+            let servers = tauri::async_runtime::block_on(ServerRegistry::load(
+                &servers_path,
+                &state.entities,
+            ));
+            let databases_path = jute::commands::database::databases_path(app.handle())?;
+            let databases = tauri::async_runtime::block_on(DatabaseRegistry::load(
+                &databases_path,
+                &state.entities,
+            ));
+            *state.settings.write().unwrap() = settings;
+            *state.servers.write().unwrap() = servers;
+            *state.databases.write().unwrap() = databases;
+            *state.keymap.write().unwrap() = keymap;
+            *state.window_states.write().unwrap() = window_states;
+            *state.recent_notebooks.write().unwrap() = recent;
+            *state.recent_folders.write().unwrap() = recent_folders;
+            _ = state.logger.set(logger);
+            jute::commands::tasks::forward_task_events(app.handle());
+            jute::commands::deep_link::listen_for_deep_links(app.handle());
+            jute::commands::updates::spawn_update_checks(app.handle());
+            jute::commands::renderers::prefetch_math_renderer(app.handle());
+            jute::commands::indexing::apply_indexing_setting(app.handle(), false);
+            if let Err(err) = jute::commands::scratchpad::register_shortcut(app.handle()) {
+                tracing::warn!("could not register scratchpad shortcut: {err}");
+            }
+
+            // Ease migration from JupyterLab by importing its configuration.
+            if first_run {
+                let import = jute::commands::jupyter_config::import_jupyter_config(app.handle());
+                if let Err(err) = tauri::async_runtime::block_on(import) {
+                    tracing::warn!("could not import jupyter configuration: {err}");
                 }
+            }
+
+            // The menu is built after loading the keymap, since it determines
+            // the accelerators of menu items.
+            app.set_menu(jute::menu::setup_menu(app.handle())?)?;
 
-                if files.is_empty() {
+            // Installed apps register the URL scheme when bundled, but in
+            // development it needs to be registered at runtime.
+            #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                app.deep_link().register_all()?;
+            }
+
+            // Parse files that were opened via CLI arguments (Windows + Linux).
+            if cfg!(any(windows, target_os = "linux")) {
+                let cwd = env::current_dir().unwrap_or_default();
+                let (files, links) = parse_args(env::args().skip(1), &cwd);
+
+                if files.is_empty() && links.is_empty() {
                     // Open a default window if no files were provided (this is if you opened the
                     // app in the launcher, for instance).
                     jute::window::open_home(app.handle())?;
                 } else {
                     handle_file_associations(app.handle(), &files)?;
+                    for link in &links {
+                        jute::commands::deep_link::open_deep_link(app.handle(), link);
+                    }
                 }
             }
 
             Ok(())
         })
-        .menu(jute::menu::setup_menu)
         .build(tauri::generate_context!())
         .expect("error while running tauri application")
-        .run(
-            #[allow(unused_variables)]
-            |app, event| {
-                // Handle files opened in macOS.
-                #[cfg(target_os = "macos")]
-                match event {
-                    tauri::RunEvent::Opened { urls } => {
-                        let files = urls
-                            .into_iter()
-                            .filter_map(|url| url.to_file_path().ok())
-                            .collect::<Vec<_>>();
-                        handle_file_associations(app, &files).unwrap();
+        .run(|app, event| {
+            match &event {
+                // Exiting with unsaved changes asks to save them first.
+                tauri::RunEvent::ExitRequested { api, .. } => {
+                    if jute::unsaved::has_unsaved_changes(app) {
+                        api.prevent_exit();
+                        tauri::async_runtime::spawn(jute::unsaved::quit(app.clone()));
                     }
-                    tauri::RunEvent::Ready => {
-                        // If no files were opened, open a default window.
-                        if app.webview_windows().is_empty() {
-                            jute::window::open_home(app).unwrap();
-                        }
+                }
+                // Save window geometry that was recorded while moving windows.
+                tauri::RunEvent::Exit => {
+                    let save = jute::commands::window::save_window_states(app);
+                    if let Err(err) = tauri::async_runtime::block_on(save) {
+                        tracing::warn!("could not save window state: {err}");
                     }
-                    _ => {}
                 }
-            },
-        );
+                _ => {}
+            }
+
+            // Handle files opened in macOS.
+            #[cfg(target_os = "macos")]
+            match event {
+                tauri::RunEvent::Opened { urls } => {
+                    let files = urls
+                        .into_iter()
+                        .filter_map(|url| url.to_file_path().ok())
+                        .collect::<Vec<_>>();
+                    handle_file_associations(app, &files).unwrap();
+                }
+                tauri::RunEvent::Ready => {
+                    // If no files were opened, open a default window.
+                    if app.webview_windows().is_empty() {
+                        jute::window::open_home(app).unwrap();
+                    }
+                }
+                _ => {}
+            }
+        });
 }
@@ -7,24 +7,56 @@
 //! There is no way to associate a `MenuEvent` with a specific window other than
 //! creating separate menus for each window with a different UUID. This is
 //! awkward, so we'll instead take the simpler approach of iterating through all
-//! windows of the app and finding the focused one.
+//! windows of the app and finding the focused one. Events that act on a
+//! notebook are forwarded to the focused window as a [`MENU_EVENT`], and their
+//! items are disabled when the focused window is not showing a notebook. Kernel
+//! actions are run here on the kernel associated with the focused window.
+//!
+//! Notebook windows are titled by their file name, with a mark for unsaved
+//! changes, and listed in the Window menu to switch between them. AppKit lists
+//! windows by title on macOS, while other platforms get items added here.
+//!
+//! Zooming is applied to the focused window, and the zoom level is saved in
+//! settings so that new windows open at the same level.
+//!
+//! Quitting is a custom item rather than the predefined one, which would exit
+//! without asking to save unsaved changes. The Help menu checks for updates,
+//! and offers to restart once an update has been downloaded in the background.
 //!
 //! Spacedrive has a good example of using the Menu API.
 //! <https://github.com/spacedriveapp/spacedrive/blob/0.4.3/apps/desktop/src-tauri/src/menu.rs>
 
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 use tauri::{
+    async_runtime,
     menu::{
-        AboutMetadata, Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder,
-        HELP_SUBMENU_ID, WINDOW_SUBMENU_ID,
+        AboutMetadata, Menu, MenuBuilder, MenuItem, MenuItemBuilder, MenuItemKind,
+        PredefinedMenuItem, SubmenuBuilder, HELP_SUBMENU_ID, WINDOW_SUBMENU_ID,
     },
-    AppHandle, Runtime,
+    AppHandle, Emitter, EventTarget, Manager, Runtime,
 };
 use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tauri_plugin_opener::OpenerExt;
 use tracing::warn;
 use ts_rs::TS;
 
+use crate::{
+    commands,
+    entity::EntityId,
+    keymap::{Action, Keymap},
+    settings::Zoom,
+    state::State,
+    window::{focused_window, open_notebook, update_tabs, NotebookWindow},
+    Error,
+};
+
+/// Name of the event emitted to a window when one of its menu items is clicked.
+pub const MENU_EVENT: &str = "menu-event";
+
+/// Prefix of the IDs of Window menu items that focus a notebook window.
+const WINDOW_ITEM_PREFIX: &str = "window:";
+
 /// The events that can be emitted as menu IDs.
 #[derive(
     Debug,
@@ -36,55 +68,579 @@ use ts_rs::TS;
     strum::EnumString,
     strum::AsRefStr,
     strum::Display,
+    strum::EnumIter,
 )]
 pub enum MenuEvent {
+    /// Create a new notebook, choosing its kernel.
+    NewNotebook,
+
+    /// Open a new window with the home page.
+    NewWindow,
+
     /// Open a notebook file.
     OpenFile,
 
+    /// Open a folder as a workspace.
+    OpenFolder,
+
+    /// Save the notebook in the focused window.
+    Save,
+
+    /// Save the notebook in the focused window to a new path.
+    SaveAs,
+
+    /// Roll the focused notebook back to one of its checkpoints.
+    RevertToCheckpoint,
+
+    /// Export the focused notebook as a reveal.js slideshow.
+    ExportSlides,
+
+    /// Share the focused notebook read-only on the local network, or stop
+    /// sharing it.
+    ShareOnNetwork,
+
+    /// Show the focused notebook in the OS file manager.
+    RevealNotebook,
+
+    /// Show the file the focused notebook was last exported to.
+    RevealExport,
+
+    /// Open a terminal in the directory of the focused notebook.
+    OpenTerminal,
+
+    /// Run the current cell of the focused notebook.
+    RunCell,
+
+    /// Run all cells of the focused notebook.
+    RunAll,
+
+    /// Run all cells above the current cell.
+    RunAllAbove,
+
+    /// Run the current cell and all cells below it.
+    RunAllBelow,
+
+    /// Run the selected text in the current cell.
+    RunSelectedText,
+
+    /// Interrupt the kernel of the focused notebook.
+    InterruptKernel,
+
+    /// Restart the kernel of the focused notebook.
+    RestartKernel,
+
+    /// Restart the kernel of the focused notebook, then run all cells.
+    RestartKernelRunAll,
+
+    /// Shut down the kernel of the focused notebook.
+    ShutdownKernel,
+
+    /// Pick a different kernel for the focused notebook.
+    ChangeKernel,
+
+    /// Zoom in on the focused window, and remember the zoom level.
+    ZoomIn,
+
+    /// Zoom out of the focused window, and remember the zoom level.
+    ZoomOut,
+
+    /// Reset the zoom level of the focused window.
+    ResetZoom,
+
+    /// Collapse the outputs of all cells in the focused notebook.
+    CollapseAllOutputs,
+
+    /// Expand the outputs of all cells in the focused notebook.
+    ExpandAllOutputs,
+
+    /// Toggle whether long outputs of the focused cell scroll.
+    ToggleOutputScrolling,
+
+    /// Open the kernel monitor window.
+    KernelMonitor,
+
+    /// Switch to the next tab of the focused window.
+    NextTab,
+
+    /// Switch to the previous tab of the focused window.
+    PreviousTab,
+
+    /// Open the keyboard shortcuts reference.
+    KeyboardShortcuts,
+
     /// Open the issue tracker URL.
     ReportIssue,
+
+    /// Check for a newer version of Jute now.
+    CheckForUpdates,
+
+    /// Restart to install an update that was downloaded in the background.
+    InstallUpdate,
+
+    /// Quit the app, asking to save any unsaved changes.
+    Quit,
 }
 
-/// Set up the menu for application windows.
-///
-/// This code was modified from the original source of [`Menu::default`],
-/// customizing that menu to add new buttons.
+impl MenuEvent {
+    /// The keymap action whose shortcut is used as this item's accelerator.
+    pub const fn action(&self) -> Option<Action> {
+        match self {
+            MenuEvent::NewNotebook => Some(Action::NewNotebook),
+            MenuEvent::NewWindow => Some(Action::NewWindow),
+            MenuEvent::OpenFile => Some(Action::OpenFile),
+            MenuEvent::OpenFolder => Some(Action::OpenFolder),
+            MenuEvent::Save => Some(Action::Save),
+            MenuEvent::SaveAs => Some(Action::SaveAs),
+            MenuEvent::RunCell => Some(Action::RunCellInPlace),
+            MenuEvent::RunAll => Some(Action::RunAll),
+            MenuEvent::RunAllAbove => Some(Action::RunAllAbove),
+            MenuEvent::RunAllBelow => Some(Action::RunAllBelow),
+            MenuEvent::RunSelectedText => Some(Action::RunSelectedText),
+            MenuEvent::ZoomIn => Some(Action::ZoomIn),
+            MenuEvent::ZoomOut => Some(Action::ZoomOut),
+            MenuEvent::ResetZoom => Some(Action::ResetZoom),
+            MenuEvent::NextTab => Some(Action::NextTab),
+            MenuEvent::PreviousTab => Some(Action::PreviousTab),
+            MenuEvent::Quit => Some(Action::Quit),
+            _ => None,
+        }
+    }
+
+    /// Text of this event's item, given the focused notebook.
+    pub fn text(&self, notebook: Option<&NotebookWindow>) -> &'static str {
+        match self {
+            MenuEvent::NewNotebook => "New Notebook",
+            MenuEvent::NewWindow => "New Window",
+            MenuEvent::OpenFile => "Open File…",
+            MenuEvent::OpenFolder => "Open Folder…",
+            MenuEvent::Save if notebook.is_some_and(|n| n.dirty) => "Save ✱",
+            MenuEvent::Save => "Save",
+            MenuEvent::SaveAs => "Save As…",
+            MenuEvent::RevertToCheckpoint => "Revert to Checkpoint…",
+            MenuEvent::ExportSlides => "Reveal.js Slides…",
+            MenuEvent::ShareOnNetwork if notebook.is_some_and(|n| n.share_url.is_some()) => {
+                "Stop Sharing on Local Network"
+            }
+            MenuEvent::ShareOnNetwork => "Share on Local Network…",
+            MenuEvent::RevealNotebook if cfg!(target_os = "macos") => "Reveal in Finder",
+            MenuEvent::RevealNotebook if cfg!(windows) => "Show in Explorer",
+            MenuEvent::RevealNotebook => "Open Containing Folder",
+            MenuEvent::RevealExport => "Show Exported File",
+            MenuEvent::OpenTerminal => "Open in Terminal",
+            MenuEvent::RunCell => "Run Cell",
+            MenuEvent::RunAll => "Run All Cells",
+            MenuEvent::RunAllAbove => "Run All Above",
+            MenuEvent::RunAllBelow => "Run Cell and All Below",
+            MenuEvent::RunSelectedText => "Run Selected Text",
+            MenuEvent::InterruptKernel => "Interrupt",
+            MenuEvent::RestartKernel => "Restart",
+            MenuEvent::RestartKernelRunAll => "Restart & Run All",
+            MenuEvent::ShutdownKernel => "Shut Down",
+            MenuEvent::ChangeKernel => "Change Kernel…",
+            MenuEvent::ZoomIn => "Zoom In",
+            MenuEvent::ZoomOut => "Zoom Out",
+            MenuEvent::ResetZoom => "Actual Size",
+            MenuEvent::CollapseAllOutputs => "Collapse All Outputs",
+            MenuEvent::ExpandAllOutputs => "Expand All Outputs",
+            MenuEvent::ToggleOutputScrolling => "Toggle Scrolling for Long Outputs",
+            MenuEvent::KernelMonitor => "Kernel Monitor",
+            MenuEvent::NextTab => "Next Tab",
+            MenuEvent::PreviousTab => "Previous Tab",
+            MenuEvent::KeyboardShortcuts => "Keyboard Shortcuts",
+            MenuEvent::ReportIssue => "Report Issue",
+            MenuEvent::CheckForUpdates => "Check for Updates…",
+            MenuEvent::InstallUpdate => "Restart to Update",
+            MenuEvent::Quit => "Quit Jute",
+        }
+    }
+
+    /// Whether this event's item is enabled, given the focused notebook.
+    pub fn is_enabled(&self, notebook: Option<&NotebookWindow>) -> bool {
+        match self {
+            MenuEvent::Save
+            | MenuEvent::SaveAs
+            | MenuEvent::ExportSlides
+            | MenuEvent::ShareOnNetwork
+            | MenuEvent::CollapseAllOutputs
+            | MenuEvent::ExpandAllOutputs
+            | MenuEvent::ToggleOutputScrolling
+            | MenuEvent::RunCell
+            | MenuEvent::RunAll
+            | MenuEvent::RunAllAbove
+            | MenuEvent::RunAllBelow
+            | MenuEvent::RunSelectedText
+            | MenuEvent::ChangeKernel => notebook.is_some(),
+            MenuEvent::RevertToCheckpoint | MenuEvent::RevealNotebook | MenuEvent::OpenTerminal => {
+                notebook.is_some_and(|n| n.path.is_some())
+            }
+            MenuEvent::RevealExport => notebook.is_some_and(|n| n.last_export.is_some()),
+            MenuEvent::InterruptKernel
+            | MenuEvent::RestartKernel
+            | MenuEvent::RestartKernelRunAll
+            | MenuEvent::ShutdownKernel => notebook.is_some_and(|n| n.kernel_id.is_some()),
+            MenuEvent::NextTab | MenuEvent::PreviousTab => {
+                notebook.is_some_and(|n| n.tabs.tabs.len() > 1)
+            }
+            _ => true,
+        }
+    }
+}
+
+crate::export_bindings!(MenuEvent);
+
+/// Whether an item is enabled, also checking app state that isn't tied to the
+/// focused notebook.
+fn item_enabled<R: Runtime>(
+    app: &AppHandle<R>,
+    event: MenuEvent,
+    notebook: Option<&NotebookWindow>,
+) -> bool {
+    match event {
+        MenuEvent::InstallUpdate => app
+            .state::<State>()
+            .pending_update
+            .lock()
+            .unwrap()
+            .is_some(),
+        _ => event.is_enabled(notebook),
+    }
+}
+
+/// Returns the label of the focused window, if it is showing a notebook.
+fn focused_notebook<R: Runtime>(app: &AppHandle<R>) -> Option<(String, NotebookWindow)> {
+    let window = focused_window(app)?;
+    let state = app.state::<State>();
+    let notebook = state.notebook_windows.get(window.label())?.clone();
+    Some((window.label().into(), notebook))
+}
+
+/// Show an error from a menu action in a dialog.
+fn show_error<R: Runtime>(app: &AppHandle<R>, err: impl ToString) {
+    app.dialog()
+        .message(err.to_string())
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
+
+/// Set up the menu for application windows, and handle its events.
 pub fn setup_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
     app.on_menu_event(move |app, event| {
+        if let Some(label) = event.id().as_ref().strip_prefix(WINDOW_ITEM_PREFIX) {
+            if let Some(window) = app.get_webview_window(label) {
+                _ = window.unminimize();
+                _ = window.set_focus();
+            }
+            return;
+        }
         let Ok(event) = event.id().as_ref().parse::<MenuEvent>() else {
             warn!("unknown menu event: {:?}", event.id());
             return;
         };
         match event {
+            MenuEvent::NewNotebook => {
+                _ = crate::window::open_new_notebook(app);
+            }
+            MenuEvent::NewWindow => {
+                _ = crate::window::open_home(app);
+            }
             MenuEvent::OpenFile => {
                 let app = app.clone();
                 app.dialog()
                     .file()
-                    .add_filter("Jupyter Notebook", &["ipynb"])
+                    .add_filter("Notebook", crate::import::EXTENSIONS)
                     .pick_file(move |path| {
                         if let Some(path) = path {
                             match path.into_path() {
-                                Ok(path) => {
-                                    _ = crate::window::open_notebook_path(&app, &path);
-                                }
-                                Err(err) => {
-                                    app.dialog()
-                                        .message(err.to_string())
-                                        .kind(MessageDialogKind::Error)
-                                        .show(|_| {});
-                                }
+                                Ok(path) => _ = open_notebook(&app, &path),
+                                Err(err) => show_error(&app, err),
                             }
                         }
                     });
             }
+            MenuEvent::OpenFolder => {
+                let app = app.clone();
+                app.dialog().file().pick_folder(move |path| {
+                    let Some(path) = path else {
+                        return;
+                    };
+                    match path.into_path() {
+                        Ok(path) => {
+                            async_runtime::spawn(async move {
+                                let result = commands::workspace::open_workspace(&app, &path).await;
+                                if let Err(err) = result {
+                                    show_error(&app, err);
+                                }
+                            });
+                        }
+                        Err(err) => show_error(&app, err),
+                    }
+                });
+            }
+            MenuEvent::NextTab | MenuEvent::PreviousTab => {
+                if let Some(window) = focused_window(app) {
+                    let forward = matches!(event, MenuEvent::NextTab);
+                    _ = update_tabs(&window, |tabs| tabs.cycle(forward));
+                }
+            }
+            MenuEvent::ZoomIn | MenuEvent::ZoomOut | MenuEvent::ResetZoom => {
+                let app = app.clone();
+                async_runtime::spawn(async move {
+                    let result = commands::settings::modify_settings(&app, |settings| {
+                        settings.zoom = match event {
+                            MenuEvent::ZoomIn => settings.zoom.zoom_in(),
+                            MenuEvent::ZoomOut => settings.zoom.zoom_out(),
+                            _ => Zoom::default(),
+                        };
+                    })
+                    .await;
+                    match result {
+                        Ok(settings) => {
+                            if let Some(window) = focused_window(&app) {
+                                _ = window.set_zoom(settings.zoom.scale());
+                            }
+                        }
+                        Err(err) => show_error(&app, err),
+                    }
+                });
+            }
+            MenuEvent::RevealNotebook | MenuEvent::RevealExport | MenuEvent::OpenTerminal => {
+                let Some((_, notebook)) = focused_notebook(app) else {
+                    return;
+                };
+                let result = match event {
+                    MenuEvent::RevealNotebook => notebook
+                        .path
+                        .map(|path| commands::reveal::reveal(app, &path)),
+                    MenuEvent::RevealExport => notebook
+                        .last_export
+                        .map(|path| commands::reveal::reveal(app, &path)),
+                    _ => notebook.path.map(|path| {
+                        let directory = commands::reveal::notebook_directory(&path);
+                        commands::reveal::spawn_terminal(&directory)
+                    }),
+                };
+                if let Some(Err(err)) = result {
+                    show_error(app, err);
+                }
+            }
+            MenuEvent::KernelMonitor => {
+                _ = crate::window::open_kernel_monitor(app);
+            }
+            MenuEvent::KeyboardShortcuts => {
+                _ = crate::window::open_shortcuts(app);
+            }
             MenuEvent::ReportIssue => {
                 _ = app
                     .opener()
                     .open_url("https://github.com/ekzhang/jute/issues", None::<&str>);
             }
+            MenuEvent::Quit => {
+                async_runtime::spawn(crate::unsaved::quit(app.clone()));
+            }
+            MenuEvent::CheckForUpdates => {
+                let app = app.clone();
+                async_runtime::spawn(async move {
+                    if let Err(err) = commands::updates::check_now(&app).await {
+                        show_error(&app, err);
+                    }
+                });
+            }
+            MenuEvent::InstallUpdate => {
+                if let Err(err) = commands::updates::install_pending(app) {
+                    show_error(app, err);
+                }
+            }
+            MenuEvent::Save
+            | MenuEvent::SaveAs
+            | MenuEvent::RevertToCheckpoint
+            | MenuEvent::ExportSlides
+            | MenuEvent::ShareOnNetwork
+            | MenuEvent::CollapseAllOutputs
+            | MenuEvent::ExpandAllOutputs
+            | MenuEvent::ToggleOutputScrolling
+            | MenuEvent::RunCell
+            | MenuEvent::RunAll
+            | MenuEvent::RunAllAbove
+            | MenuEvent::RunAllBelow
+            | MenuEvent::RunSelectedText
+            | MenuEvent::ChangeKernel => {
+                if let Some((label, _)) = focused_notebook(app) {
+                    _ = app.emit_to(EventTarget::webview_window(label), MENU_EVENT, event);
+                }
+            }
+            MenuEvent::InterruptKernel
+            | MenuEvent::RestartKernel
+            | MenuEvent::RestartKernelRunAll
+            | MenuEvent::ShutdownKernel => {
+                let Some(kernel_id) = focused_notebook(app).and_then(|(_, n)| n.kernel_id) else {
+                    return;
+                };
+                let app = app.clone();
+                async_runtime::spawn(async move {
+                    if let Err(err) = run_kernel_action(&app, kernel_id, event).await {
+                        show_error(&app, err);
+                    }
+                });
+            }
         }
     });
 
+    build_menu(app)
+}
+
+/// Interrupt, restart, or shut down a kernel for a menu event.
+///
+/// Windows showing a notebook attached to the kernel are notified with the
+/// event after the kernel action is done. Other events are ignored.
+pub async fn run_kernel_action<R: Runtime>(
+    app: &AppHandle<R>,
+    kernel_id: EntityId,
+    event: MenuEvent,
+) -> Result<(), Error> {
+    // Shutting down detaches the kernel, so find its windows beforehand.
+    let labels: Vec<String> = app
+        .state::<State>()
+        .notebook_windows
+        .iter()
+        .filter(|notebook| notebook.kernel_id == Some(kernel_id))
+        .map(|notebook| notebook.key().clone())
+        .collect();
+    match event {
+        MenuEvent::InterruptKernel => commands::interrupt_kernel(kernel_id, app.state()).await?,
+        MenuEvent::ShutdownKernel => {
+            commands::stop_kernel(kernel_id, app.clone(), app.state()).await?
+        }
+        MenuEvent::RestartKernel | MenuEvent::RestartKernelRunAll => {
+            let restarted = commands::restart_kernel(kernel_id, app.clone(), app.state()).await;
+            if let Err(err) = restarted {
+                // The kernel is gone, so its windows should drop its ID.
+                for label in labels {
+                    let target = EventTarget::webview_window(label);
+                    app.emit_to(target, MENU_EVENT, MenuEvent::ShutdownKernel)?;
+                }
+                return Err(err);
+            }
+        }
+        _ => return Ok(()),
+    }
+    for label in labels {
+        app.emit_to(EventTarget::webview_window(label), MENU_EVENT, event)?;
+    }
+    Ok(())
+}
+
+/// Find a menu item by its event, searching through submenus.
+fn find_item<R: Runtime>(items: Vec<MenuItemKind<R>>, event: MenuEvent) -> Option<MenuItem<R>> {
+    items.into_iter().find_map(|item| match item {
+        MenuItemKind::MenuItem(item) if *item.id() == event.as_ref() => Some(item),
+        MenuItemKind::Submenu(submenu) => find_item(submenu.items().ok()?, event),
+        _ => None,
+    })
+}
+
+/// Enable, disable, and re-label menu items based on the focused window.
+///
+/// This should be called when window focus changes, or when a window's
+/// notebook or kernel changes.
+pub fn update_menu_state<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    let Some(menu) = app.menu() else {
+        return Ok(());
+    };
+    let notebook = focused_notebook(app).map(|(_, notebook)| notebook);
+    for event in MenuEvent::iter() {
+        if let Some(item) = find_item(menu.items()?, event) {
+            item.set_enabled(item_enabled(app, event, notebook.as_ref()))?;
+            item.set_text(event.text(notebook.as_ref()))?;
+        }
+    }
+    update_window_list(app, &menu)
+}
+
+/// Title of a notebook window: the file name, marked if it has unsaved changes.
+fn window_title(notebook: &NotebookWindow) -> String {
+    let name = match &notebook.path {
+        Some(path) => path.file_name().unwrap_or_default().to_string_lossy(),
+        None => "Untitled".into(),
+    };
+    if notebook.dirty {
+        format!("{name} ✱")
+    } else {
+        name.into_owned()
+    }
+}
+
+/// Update the titles of notebook windows, and list them in the Window menu.
+fn update_window_list<R: Runtime>(app: &AppHandle<R>, menu: &Menu<R>) -> tauri::Result<()> {
+    let mut windows: Vec<(String, String)> = app
+        .state::<State>()
+        .notebook_windows
+        .iter()
+        .map(|notebook| (window_title(&notebook), notebook.key().clone()))
+        .collect();
+    windows.sort();
+    for (title, label) in &windows {
+        if let Some(window) = app.get_webview_window(label) {
+            if window.title().ok().as_ref() != Some(title) {
+                window.set_title(title)?;
+            }
+        }
+    }
+
+    if cfg!(target_os = "macos") {
+        return Ok(());
+    }
+    let Some(window_menu) = menu.get(WINDOW_SUBMENU_ID) else {
+        return Ok(());
+    };
+    let Some(window_menu) = window_menu.as_submenu() else {
+        return Ok(());
+    };
+    // The list follows "Close Window", two items after "Previous Tab", so
+    // everything after that is the old list.
+    let items = window_menu.items()?;
+    let Some(start) = items
+        .iter()
+        .position(|item| *item.id() == MenuEvent::PreviousTab.as_ref())
+    else {
+        return Ok(());
+    };
+    for item in items.iter().skip(start + 3) {
+        window_menu.remove(item)?;
+    }
+    if !windows.is_empty() {
+        window_menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    for (title, label) in windows {
+        let id = format!("{WINDOW_ITEM_PREFIX}{label}");
+        window_menu.append(&MenuItem::with_id(app, id, title, true, None::<&str>)?)?;
+    }
+    Ok(())
+}
+
+/// Build a menu item for an event, with its accelerator from the keymap.
+fn menu_item<R: Runtime>(
+    app: &AppHandle<R>,
+    keymap: &Keymap,
+    event: MenuEvent,
+) -> tauri::Result<MenuItem<R>> {
+    let notebook = focused_notebook(app).map(|(_, notebook)| notebook);
+    let mut builder = MenuItemBuilder::with_id(event, event.text(notebook.as_ref()))
+        .enabled(item_enabled(app, event, notebook.as_ref()));
+    if let Some(accelerator) = event.action().and_then(|action| keymap.accelerator(action)) {
+        builder = builder.accelerator(accelerator);
+    }
+    builder.build(app)
+}
+
+/// Build the menu for application windows.
+///
+/// This is called again to refresh accelerators when the keymap changes. This
+/// code was modified from the original source of [`Menu::default`],
+/// customizing that menu to add new buttons.
+pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
+    let keymap = app.state::<State>().keymap.read().unwrap().clone();
+    let item = |event| menu_item(app, &keymap, event);
+
     let pkg_info = app.package_info();
     let config = app.config();
     let about_metadata = AboutMetadata {
@@ -112,18 +668,34 @@ pub fn setup_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
             .hide()
             .hide_others()
             .separator()
-            .quit()
+            .item(&item(MenuEvent::Quit)?)
             .build()?;
         menu = menu.item(&app_menu);
     }
 
+    // Export submenu, handled by the focused notebook.
+    let export_menu = SubmenuBuilder::new(app, "Export")
+        .item(&item(MenuEvent::ExportSlides)?)
+        .build()?;
+
     // File submenu.
     let file_menu = SubmenuBuilder::new(app, "File")
-        .item(
-            &MenuItemBuilder::with_id(MenuEvent::OpenFile, "Open File…")
-                .accelerator("CmdOrCtrl+O")
-                .build(app)?,
-        )
+        .item(&item(MenuEvent::NewNotebook)?)
+        .item(&item(MenuEvent::NewWindow)?)
+        .separator()
+        .item(&item(MenuEvent::OpenFile)?)
+        .item(&item(MenuEvent::OpenFolder)?)
+        .separator()
+        .item(&item(MenuEvent::Save)?)
+        .item(&item(MenuEvent::SaveAs)?)
+        .item(&item(MenuEvent::RevertToCheckpoint)?)
+        .item(&export_menu)
+        .item(&item(MenuEvent::ShareOnNetwork)?)
+        .separator()
+        .item(&item(MenuEvent::RevealNotebook)?)
+        .item(&item(MenuEvent::RevealExport)?)
+        .item(&item(MenuEvent::OpenTerminal)?)
+        .separator()
         .items(&[
             // From the default menu: seems like this is not supported on Linux.
             #[cfg(not(any(
@@ -136,7 +708,7 @@ pub fn setup_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
             &PredefinedMenuItem::close_window(app, None)?,
             // This is already in a different menu for macOS.
             #[cfg(not(target_os = "macos"))]
-            &PredefinedMenuItem::quit(app, None)?,
+            &item(MenuEvent::Quit)?,
         ])
         .build()?;
 
@@ -152,16 +724,50 @@ pub fn setup_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
         .build()?;
 
     let view_menu = SubmenuBuilder::new(app, "View")
+        .item(&item(MenuEvent::ZoomIn)?)
+        .item(&item(MenuEvent::ZoomOut)?)
+        .item(&item(MenuEvent::ResetZoom)?)
+        .separator()
+        .item(&item(MenuEvent::CollapseAllOutputs)?)
+        .item(&item(MenuEvent::ExpandAllOutputs)?)
+        .item(&item(MenuEvent::ToggleOutputScrolling)?)
+        .separator()
+        .item(&item(MenuEvent::KernelMonitor)?)
         .items(&[
+            #[cfg(target_os = "macos")]
+            &PredefinedMenuItem::separator(app)?,
             #[cfg(target_os = "macos")]
             &PredefinedMenuItem::fullscreen(app, None)?,
         ])
         .build()?;
 
+    // Run submenu, handled by the focused notebook.
+    let run_menu = SubmenuBuilder::new(app, "Run")
+        .item(&item(MenuEvent::RunCell)?)
+        .item(&item(MenuEvent::RunSelectedText)?)
+        .separator()
+        .item(&item(MenuEvent::RunAllAbove)?)
+        .item(&item(MenuEvent::RunAllBelow)?)
+        .item(&item(MenuEvent::RunAll)?)
+        .build()?;
+
+    // Kernel submenu, for the kernel of the focused notebook.
+    let kernel_menu = SubmenuBuilder::new(app, "Kernel")
+        .item(&item(MenuEvent::InterruptKernel)?)
+        .item(&item(MenuEvent::RestartKernel)?)
+        .item(&item(MenuEvent::RestartKernelRunAll)?)
+        .item(&item(MenuEvent::ShutdownKernel)?)
+        .separator()
+        .item(&item(MenuEvent::ChangeKernel)?)
+        .build()?;
+
     let window_menu = SubmenuBuilder::with_id(app, WINDOW_SUBMENU_ID, "Window")
         .minimize()
         .maximize()
         .separator()
+        .item(&item(MenuEvent::NextTab)?)
+        .item(&item(MenuEvent::PreviousTab)?)
+        .separator()
         .close_window()
         .build()?;
 
@@ -170,13 +776,19 @@ pub fn setup_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
             #[cfg(not(target_os = "macos"))]
             &PredefinedMenuItem::about(app, None, Some(about_metadata))?,
         ])
-        .text(MenuEvent::ReportIssue, "Report Issue")
+        .item(&item(MenuEvent::KeyboardShortcuts)?)
+        .item(&item(MenuEvent::ReportIssue)?)
+        .separator()
+        .item(&item(MenuEvent::CheckForUpdates)?)
+        .item(&item(MenuEvent::InstallUpdate)?)
         .build()?;
 
     let menu = menu
         .item(&file_menu)
         .item(&edit_menu)
         .item(&view_menu)
+        .item(&run_menu)
+        .item(&kernel_menu)
         .item(&window_menu)
         .item(&help_menu)
         .build()?;
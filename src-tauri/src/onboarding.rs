@@ -0,0 +1,180 @@
+//! Detection of existing Python and Jupyter installs for first-run setup.
+//!
+//! The onboarding flow uses this to show what is already on the system, so new
+//! users can pick an existing install or create a managed environment.
+
+use std::{
+    collections::BTreeSet,
+    env,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::backend::local::environment;
+
+/// A Python interpreter found on the system.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct PythonInstall {
+    /// Path to the interpreter executable.
+    pub path: String,
+
+    /// Version reported by the interpreter, like `3.11.7`.
+    pub version: String,
+}
+
+/// An existing Jupyter install found on the system.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct JupyterInstall {
+    /// Path to the `jupyter` command, if it is on the `PATH`.
+    pub command: Option<String>,
+
+    /// Jupyter config directory, if it exists.
+    pub config_dir: Option<String>,
+
+    /// Kernels that are installed, as `(name, display name)` pairs.
+    pub kernels: Vec<(String, String)>,
+}
+
+/// Result of checking that the bundled `uv` sidecar can run.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct UvStatus {
+    /// Whether `uv` ran successfully.
+    pub available: bool,
+
+    /// Version reported by `uv`, like `0.5.11`.
+    pub version: Option<String>,
+
+    /// Error message if `uv` could not be run.
+    pub error: Option<String>,
+}
+
+crate::export_bindings!(PythonInstall, JupyterInstall, UvStatus);
+
+/// Find Python interpreters on the `PATH`, deduplicated by their real path.
+pub async fn find_system_pythons() -> Vec<PythonInstall> {
+    let mut seen = BTreeSet::new();
+    let mut pythons = Vec::new();
+    for dir in env::split_paths(&env::var_os("PATH").unwrap_or_default()) {
+        let Ok(mut it) = tokio::fs::read_dir(&dir).await else {
+            continue;
+        };
+        let mut candidates = Vec::new();
+        while let Ok(Some(entry)) = it.next_entry().await {
+            if is_python_executable(&entry.file_name().to_string_lossy()) {
+                candidates.push(entry.path());
+            }
+        }
+        candidates.sort();
+        for path in candidates {
+            let Ok(real_path) = tokio::fs::canonicalize(&path).await else {
+                continue;
+            };
+            if !seen.insert(real_path) {
+                continue;
+            }
+            if let Some(version) = python_version(&path).await {
+                pythons.push(PythonInstall {
+                    path: path.to_string_lossy().into_owned(),
+                    version,
+                });
+            }
+        }
+    }
+    pythons
+}
+
+/// Check whether a file name looks like a Python 3 interpreter.
+fn is_python_executable(name: &str) -> bool {
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+    let Some(rest) = name.strip_prefix("python") else {
+        return false;
+    };
+    match rest {
+        "" | "3" => true,
+        _ => rest
+            .strip_prefix("3.")
+            .is_some_and(|minor| !minor.is_empty() && minor.bytes().all(|b| b.is_ascii_digit())),
+    }
+}
+
+/// Run an interpreter to get its version, ignoring Python 2.
+async fn python_version(path: &Path) -> Option<String> {
+    let output = tokio::time::timeout(
+        Duration::from_secs(5),
+        tokio::process::Command::new(path)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    let version = version.trim().strip_prefix("Python ")?;
+    version.starts_with("3.").then(|| version.into())
+}
+
+/// Find an existing Jupyter install, including its config and kernels.
+pub async fn find_jupyter() -> JupyterInstall {
+    let command = env::split_paths(&env::var_os("PATH").unwrap_or_default())
+        .map(|dir| {
+            dir.join(if cfg!(windows) {
+                "jupyter.exe"
+            } else {
+                "jupyter"
+            })
+        })
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned());
+
+    let config_dir = PathBuf::from(environment::config_dir());
+    let config_dir = tokio::fs::metadata(&config_dir)
+        .await
+        .is_ok_and(|m| m.is_dir())
+        .then(|| config_dir.to_string_lossy().into_owned());
+
+    let kernels = environment::list_kernels(None)
+        .await
+        .into_iter()
+        .filter_map(|(path, spec)| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            Some((name, spec.display_name))
+        })
+        .collect();
+
+    JupyterInstall {
+        command,
+        config_dir,
+        kernels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn python_executable_names() {
+        for name in [
+            "python",
+            "python3",
+            "python3.11",
+            "python.exe",
+            "python3.12.exe",
+        ] {
+            assert!(is_python_executable(name), "{name}");
+        }
+        for name in [
+            "python2",
+            "python3.",
+            "python3-config",
+            "python3.11-config",
+            "pythonw",
+        ] {
+            assert!(!is_python_executable(name), "{name}");
+        }
+    }
+}
@@ -1,4 +1,10 @@
 //! Module containing custom plugins for the Tauri app.
 
+#[cfg(target_os = "linux")]
+pub mod linux_desktop;
+#[cfg(target_os = "macos")]
+pub mod macos_dock_menu;
 #[cfg(target_os = "macos")]
 pub mod macos_traffic_lights;
+#[cfg(target_os = "windows")]
+pub mod windows_titlebar;
@@ -0,0 +1,109 @@
+//! Desktop integration for Linux, through GTK.
+//!
+//! Under Wayland, the compositor matches windows to their `.desktop` file by
+//! the app ID, which GTK takes from the program name. It defaults to the
+//! binary name, so windows would show a generic icon in GNOME and KDE; this
+//! plugin sets it to the product name used for the installed desktop file,
+//! along with the default icon name.
+//!
+//! The color scheme is read from GTK settings, which follow the desktop portal
+//! on both GNOME and KDE. Changes are sent to all windows as a
+//! [`THEME_EVENT`].
+//!
+//! Windows can also draw a GTK header bar as their titlebar, when enabled in
+//! settings, for desktops whose window manager doesn't decorate windows.
+
+use gtk::{glib, prelude::*};
+use serde::Serialize;
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    AppHandle, Emitter, Manager, Runtime, Window,
+};
+use tracing::warn;
+
+use crate::state::State;
+
+/// Name of the event emitted to all windows when the system theme changes.
+pub const THEME_EVENT: &str = "system-theme-changed";
+
+/// Color scheme of the desktop, sent in a [`THEME_EVENT`].
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemTheme {
+    /// Dark text on light backgrounds.
+    Light,
+
+    /// Light text on dark backgrounds.
+    Dark,
+}
+
+/// Initialize a plugin for Linux desktop integration.
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new("linux_desktop")
+        .setup(|app, _api| {
+            // This must happen before any windows are created.
+            let name = &app.package_info().name;
+            glib::set_prgname(Some(name.as_str()));
+            gtk::Window::set_default_icon_name(&name.to_lowercase());
+            watch_theme(app);
+            Ok(())
+        })
+        .on_window_ready(|window| {
+            let state = window.state::<State>();
+            if state.settings.read().unwrap().client_side_decorations {
+                add_header_bar(&window);
+            }
+        })
+        .build()
+}
+
+/// Read the current color scheme from GTK settings.
+pub fn system_theme() -> SystemTheme {
+    let Some(settings) = gtk::Settings::default() else {
+        return SystemTheme::Light;
+    };
+    let theme_name = settings.gtk_theme_name().unwrap_or_default();
+    if settings.is_gtk_application_prefer_dark_theme()
+        || theme_name.to_lowercase().ends_with("-dark")
+    {
+        SystemTheme::Dark
+    } else {
+        SystemTheme::Light
+    }
+}
+
+/// Emit a [`THEME_EVENT`] whenever the color scheme changes.
+fn watch_theme<R: Runtime>(app: &AppHandle<R>) {
+    let Some(settings) = gtk::Settings::default() else {
+        warn!("no GTK settings, not watching for theme changes");
+        return;
+    };
+    let notify = {
+        let app = app.clone();
+        move |_: &gtk::Settings| {
+            _ = app.emit(THEME_EVENT, system_theme());
+        }
+    };
+    settings.connect_gtk_theme_name_notify(notify.clone());
+    settings.connect_gtk_application_prefer_dark_theme_notify(notify);
+}
+
+/// Replace the titlebar of a window with a GTK header bar.
+fn add_header_bar<R: Runtime>(window: &Window<R>) {
+    let gtk_window = match window.gtk_window() {
+        Ok(gtk_window) => gtk_window,
+        Err(err) => {
+            warn!("could not get GTK window for header bar: {err}");
+            return;
+        }
+    };
+    let header_bar = gtk::HeaderBar::new();
+    header_bar.set_show_close_button(true);
+    // Keep the header bar's title in sync with the window's.
+    gtk_window
+        .bind_property("title", &header_bar, "title")
+        .sync_create()
+        .build();
+    header_bar.show_all();
+    gtk_window.set_titlebar(Some(&header_bar));
+}
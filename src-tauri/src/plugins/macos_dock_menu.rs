@@ -0,0 +1,188 @@
+//! Dock menu for macOS, with quick actions and recently opened notebooks.
+//!
+//! Tauri has no API for the Dock menu, so this plugin adds an
+//! `applicationDockMenu:` method to the app delegate, which AppKit calls each
+//! time the menu is about to be shown. The menu is built fresh from the app
+//! state every time, and clicks on its items are routed back to Rust through a
+//! small Objective-C target class.
+
+#![allow(unsafe_code)]
+
+use std::{
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+use cocoa::{
+    appkit::{NSApp, NSMenu, NSMenuItem},
+    base::{id, nil, NO},
+    foundation::{NSAutoreleasePool, NSInteger, NSString},
+};
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    runtime::{class_addMethod, Class, Imp, Object, Sel},
+    sel, sel_impl,
+};
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    AppHandle, Manager, Wry,
+};
+use tracing::warn;
+
+use crate::{state::State, window};
+
+/// Maximum number of recent notebooks shown in the menu.
+const MAX_RECENT: usize = 10;
+
+/// An action taken when clicking an item in the Dock menu.
+#[derive(Clone, Debug)]
+enum DockAction {
+    NewNotebook,
+    NewWindow,
+    Open(PathBuf),
+}
+
+/// Handle to the app, set when the plugin is initialized.
+static APP: OnceLock<AppHandle<Wry>> = OnceLock::new();
+
+/// Actions of the items in the menu that was last shown, indexed by tag.
+static ACTIONS: Mutex<Vec<DockAction>> = Mutex::new(Vec::new());
+
+/// Initialize a plugin for the macOS Dock menu.
+pub fn init() -> TauriPlugin<Wry> {
+    Builder::new("dock_menu")
+        .setup(|app, _api| {
+            _ = APP.set(app.clone());
+            unsafe { install_dock_menu() };
+            Ok(())
+        })
+        .build()
+}
+
+/// Add the `applicationDockMenu:` method to the class of the app delegate.
+unsafe fn install_dock_menu() {
+    let delegate: id = msg_send![NSApp(), delegate];
+    if delegate == nil {
+        warn!("no app delegate, skipping dock menu");
+        return;
+    }
+    let class: *mut Class = msg_send![delegate, class];
+    let imp: extern "C" fn(&Object, Sel, id) -> id = application_dock_menu;
+    class_addMethod(
+        class,
+        sel!(applicationDockMenu:),
+        std::mem::transmute::<extern "C" fn(&Object, Sel, id) -> id, Imp>(imp),
+        c"@@:@".as_ptr(),
+    );
+}
+
+/// Build the Dock menu, called by AppKit when it is about to be shown.
+extern "C" fn application_dock_menu(_this: &Object, _cmd: Sel, _sender: id) -> id {
+    let Some(app) = APP.get() else {
+        return nil;
+    };
+    let state = app.state::<State>();
+    let recent = state.recent_notebooks.read().unwrap().list().to_vec();
+    let kernels = state.kernels.len();
+
+    let mut actions = ACTIONS.lock().unwrap();
+    actions.clear();
+    unsafe {
+        let menu = NSMenu::new(nil).autorelease();
+        let _: () = msg_send![menu, setAutoenablesItems: NO];
+
+        add_item(
+            menu,
+            "New Notebook",
+            Some(DockAction::NewNotebook),
+            &mut actions,
+        );
+        add_item(
+            menu,
+            "New Window",
+            Some(DockAction::NewWindow),
+            &mut actions,
+        );
+
+        if !recent.is_empty() {
+            menu.addItem_(NSMenuItem::separatorItem(nil));
+            for path in recent.into_iter().take(MAX_RECENT) {
+                let title = path.file_name().unwrap_or_default().to_string_lossy();
+                let title = title.into_owned();
+                add_item(menu, &title, Some(DockAction::Open(path)), &mut actions);
+            }
+        }
+
+        menu.addItem_(NSMenuItem::separatorItem(nil));
+        let status = match kernels {
+            0 => "No kernels running".to_string(),
+            1 => "1 kernel running".to_string(),
+            n => format!("{n} kernels running"),
+        };
+        add_item(menu, &status, None, &mut actions);
+
+        menu
+    }
+}
+
+/// Append an item to the menu, which is disabled if it has no action.
+unsafe fn add_item(
+    menu: id,
+    title: &str,
+    action: Option<DockAction>,
+    actions: &mut Vec<DockAction>,
+) {
+    let title = NSString::alloc(nil).init_str(title).autorelease();
+    let key = NSString::alloc(nil).init_str("").autorelease();
+    let item = NSMenuItem::alloc(nil)
+        .initWithTitle_action_keyEquivalent_(title, sel!(dockMenuItemClicked:), key)
+        .autorelease();
+    match action {
+        Some(action) => {
+            let _: () = msg_send![item, setTarget: menu_target()];
+            let _: () = msg_send![item, setTag: actions.len() as NSInteger];
+            actions.push(action);
+        }
+        None => {
+            let _: () = msg_send![item, setEnabled: NO];
+        }
+    }
+    menu.addItem_(item);
+}
+
+/// Returns the object that receives clicks on Dock menu items.
+fn menu_target() -> id {
+    static TARGET: OnceLock<usize> = OnceLock::new();
+    let target = *TARGET.get_or_init(|| unsafe {
+        let mut decl = ClassDecl::new("JuteDockMenuTarget", class!(NSObject))
+            .expect("dock menu target class should only be declared once");
+        decl.add_method(
+            sel!(dockMenuItemClicked:),
+            dock_menu_item_clicked as extern "C" fn(&Object, Sel, id),
+        );
+        let class = decl.register();
+        let target: id = msg_send![class, new];
+        target as usize
+    });
+    target as id
+}
+
+/// Run the action of a Dock menu item when it is clicked.
+extern "C" fn dock_menu_item_clicked(_this: &Object, _cmd: Sel, sender: id) {
+    let tag: NSInteger = unsafe { msg_send![sender, tag] };
+    let Some(app) = APP.get() else {
+        return;
+    };
+    let action = ACTIONS.lock().unwrap().get(tag as usize).cloned();
+    let result = match action {
+        Some(DockAction::NewNotebook) => window::open_new_notebook(app).map(drop),
+        Some(DockAction::NewWindow) => window::open_home(app).map(drop),
+        Some(DockAction::Open(path)) => window::open_notebook(app, &path),
+        None => Ok(()),
+    };
+    if let Err(err) = result {
+        warn!("could not run dock menu action: {err}");
+    }
+}
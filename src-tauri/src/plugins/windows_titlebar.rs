@@ -0,0 +1,96 @@
+//! Titlebar styling for Windows, matching the app's light chrome.
+//!
+//! This is the Windows counterpart of the traffic light positioner on macOS.
+//! Rather than hiding the frame and drawing our own caption buttons, which
+//! would lose the snap layouts flyout on the maximize button, windows keep
+//! their native frame and have its colors changed through DWM. On Windows 11,
+//! the caption takes the background and text colors of the app header, with
+//! rounded corners and a light border. Dragging and double-clicking the
+//! caption work as usual, and the header's own drag regions still apply below
+//! it.
+//!
+//! Older versions of Windows don't support these attributes, so windows keep
+//! the stock frame there.
+
+#![allow(unsafe_code)]
+
+use std::{ffi::c_void, mem::size_of};
+
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    Runtime, Window,
+};
+use tracing::warn;
+use windows::Win32::{
+    Foundation::{COLORREF, HWND},
+    Graphics::Dwm::{
+        DwmSetWindowAttribute, DWMWA_BORDER_COLOR, DWMWA_CAPTION_COLOR, DWMWA_TEXT_COLOR,
+        DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_ROUND, DWMWINDOWATTRIBUTE,
+    },
+};
+use winver::WindowsVersion;
+
+/// Background color of the caption, matching the app header (white).
+const CAPTION_COLOR: u32 = rgb(0xff, 0xff, 0xff);
+
+/// Color of the window title text (Tailwind gray-900).
+const TEXT_COLOR: u32 = rgb(0x11, 0x18, 0x27);
+
+/// Color of the one-pixel window border (Tailwind gray-200).
+const BORDER_COLOR: u32 = rgb(0xe5, 0xe7, 0xeb);
+
+/// First build of Windows 11, where caption colors can be set.
+const WINDOWS_11_BUILD: u32 = 22000;
+
+/// Pack a color into a `COLORREF` value, which is in `0x00BBGGRR` order.
+const fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    (b as u32) << 16 | (g as u32) << 8 | r as u32
+}
+
+/// Initialize a plugin for styling window titlebars.
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new("windows_titlebar")
+        .on_window_ready(|window| {
+            let supported = WindowsVersion::detect()
+                .is_some_and(|v| v >= WindowsVersion::new(10, 0, WINDOWS_11_BUILD));
+            if supported {
+                style_titlebar(&window);
+            }
+        })
+        .build()
+}
+
+/// Set the caption colors and corner style of a window.
+fn style_titlebar<R: Runtime>(window: &Window<R>) {
+    let hwnd = match window.hwnd() {
+        Ok(hwnd) => HWND(hwnd.0 as _),
+        Err(err) => {
+            warn!("could not get window handle to style titlebar: {err}");
+            return;
+        }
+    };
+    let colors = [
+        (DWMWA_CAPTION_COLOR, CAPTION_COLOR),
+        (DWMWA_TEXT_COLOR, TEXT_COLOR),
+        (DWMWA_BORDER_COLOR, BORDER_COLOR),
+    ];
+    for (attribute, color) in colors {
+        set_attribute(hwnd, attribute, &COLORREF(color));
+    }
+    set_attribute(hwnd, DWMWA_WINDOW_CORNER_PREFERENCE, &DWMWCP_ROUND);
+}
+
+/// Set a DWM attribute on a window, logging any failure.
+fn set_attribute<T>(hwnd: HWND, attribute: DWMWINDOWATTRIBUTE, value: &T) {
+    let result = unsafe {
+        DwmSetWindowAttribute(
+            hwnd,
+            attribute,
+            value as *const T as *const c_void,
+            size_of::<T>() as u32,
+        )
+    };
+    if let Err(err) = result {
+        warn!("could not set window attribute {}: {err}", attribute.0);
+    }
+}
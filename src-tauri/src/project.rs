@@ -0,0 +1,165 @@
+//! Project configuration shared by notebooks in a directory tree.
+//!
+//! A project is any directory containing a `jute.toml` file. When a notebook
+//! is opened, the nearest `jute.toml` in its ancestors is discovered, and its
+//! settings are used as defaults for that notebook. This lets teams check in
+//! shared defaults alongside their notebooks, for example:
+//!
+//! ```toml
+//! environment = "python3"
+//! startup = "import numpy as np"
+//!
+//! [env]
+//! DATA_DIR = "/srv/data"
+//!
+//! [format]
+//! line_length = 88
+//! ```
+
+use std::{collections::BTreeMap, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::Error;
+
+/// The name of the project configuration file.
+pub const PROJECT_FILE: &str = "jute.toml";
+
+/// Settings declared in a `jute.toml` file.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Name of the kernel spec to start by default, like `python3`.
+    pub environment: Option<String>,
+
+    /// Code to run in the kernel after it starts.
+    pub startup: Option<String>,
+
+    /// Environment variables to set for the kernel process.
+    pub env: BTreeMap<String, String>,
+
+    /// Code formatting settings.
+    pub format: FormatConfig,
+}
+
+/// Code formatting settings for a project.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    /// Maximum line length for formatted code.
+    pub line_length: Option<u32>,
+
+    /// Number of spaces per indentation level.
+    pub indent_width: Option<u32>,
+}
+
+/// A project discovered from a notebook's location.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct Project {
+    /// Directory containing the `jute.toml` file.
+    pub root: String,
+
+    /// Parsed contents of the `jute.toml` file.
+    pub config: ProjectConfig,
+}
+
+crate::export_bindings!(Project);
+
+impl ProjectConfig {
+    /// Parse a project configuration from TOML.
+    pub fn parse(contents: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Whether the configuration affects what runs in a kernel, through
+    /// startup code or environment variables.
+    pub fn runs_code(&self) -> bool {
+        self.startup.is_some() || !self.env.is_empty()
+    }
+}
+
+impl Project {
+    /// Find the project that a notebook belongs to, searching upward from its
+    /// directory for the nearest `jute.toml`.
+    ///
+    /// A `jute.toml` that fails to parse is logged and treated as empty, so a
+    /// typo in it doesn't keep the notebook from opening.
+    pub async fn discover(notebook: &Path) -> Result<Option<Self>, Error> {
+        let Some(start) = notebook.parent() else {
+            return Ok(None);
+        };
+        for dir in start.ancestors() {
+            let config_path = dir.join(PROJECT_FILE);
+            match tokio::fs::read_to_string(&config_path).await {
+                Ok(contents) => {
+                    let config = ProjectConfig::parse(&contents).unwrap_or_else(|err| {
+                        warn!(path = %config_path.display(), "ignoring invalid project file: {err}");
+                        ProjectConfig::default()
+                    });
+                    return Ok(Some(Project {
+                        root: dir.to_string_lossy().into_owned(),
+                        config,
+                    }));
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(Error::Filesystem(err)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let config = ProjectConfig::parse(
+            r#"
+            environment = "python3"
+            startup = "import numpy as np"
+
+            [env]
+            DATA_DIR = "/srv/data"
+
+            [format]
+            line_length = 88
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.environment.as_deref(), Some("python3"));
+        assert_eq!(config.env["DATA_DIR"], "/srv/data");
+        assert_eq!(config.format.line_length, Some(88));
+        assert_eq!(config.format.indent_width, None);
+
+        assert_eq!(ProjectConfig::parse("").unwrap(), ProjectConfig::default());
+        assert!(ProjectConfig::parse("enviroment = \"typo\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn discover_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let nested = dir.join("analysis").join("2024");
+        tokio::fs::create_dir_all(&nested).await.unwrap();
+
+        let notebook = nested.join("notebook.ipynb");
+        assert_eq!(Project::discover(&notebook).await.unwrap(), None);
+
+        tokio::fs::write(dir.join(PROJECT_FILE), "startup = \"x = 1\"")
+            .await
+            .unwrap();
+        let project = Project::discover(&notebook).await.unwrap().unwrap();
+        assert_eq!(project.root, dir.to_string_lossy());
+        assert_eq!(project.config.startup.as_deref(), Some("x = 1"));
+
+        tokio::fs::write(dir.join(PROJECT_FILE), "startup = [")
+            .await
+            .unwrap();
+        let project = Project::discover(&notebook).await.unwrap().unwrap();
+        assert_eq!(project.config, ProjectConfig::default());
+    }
+}
@@ -0,0 +1,76 @@
+//! Recently opened notebooks and folders, persisted as JSON in the data dir.
+//!
+//! Notebooks are recorded when a window shows them, and folders when they are
+//! opened as a workspace. Each is kept in its own file and listed most recent
+//! first, such as in the macOS Dock menu.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{store, Error};
+
+/// The name of the recent notebooks file within the app data directory.
+pub const RECENT_FILE: &str = "recent.json";
+
+/// The name of the recent folders file within the app data directory.
+pub const RECENT_FOLDERS_FILE: &str = "recent-folders.json";
+
+/// Maximum number of paths to remember.
+const MAX_RECENT: usize = 20;
+
+/// Paths of recently opened notebooks or folders, most recent first.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct RecentPaths {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentPaths {
+    /// Load recent paths from a file.
+    pub async fn load(path: &Path) -> Self {
+        store::load_json(path).await
+    }
+
+    /// Write recent paths to a file.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        store::save_json(path, self).await
+    }
+
+    /// List recent paths, most recent first.
+    pub fn list(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Record that a path was opened, returning whether the list changed.
+    pub fn add(&mut self, opened: &Path) -> bool {
+        if self.paths.first().is_some_and(|path| path == opened) {
+            return false;
+        }
+        self.paths.retain(|path| path != opened);
+        self.paths.insert(0, opened.into());
+        self.paths.truncate(MAX_RECENT);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_recent() {
+        let mut recent = RecentPaths::default();
+        assert!(recent.add(Path::new("a.ipynb")));
+        assert!(recent.add(Path::new("b.ipynb")));
+        assert!(!recent.add(Path::new("b.ipynb")));
+        assert!(recent.add(Path::new("a.ipynb")));
+        assert_eq!(recent.list(), [Path::new("a.ipynb"), Path::new("b.ipynb")]);
+
+        for i in 0..MAX_RECENT {
+            recent.add(Path::new(&format!("{i}.ipynb")));
+        }
+        assert_eq!(recent.list().len(), MAX_RECENT);
+        assert_eq!(recent.list()[0], Path::new("19.ipynb"));
+    }
+}
@@ -0,0 +1,341 @@
+//! Crash recovery journal for unsaved cell edits.
+//!
+//! As the user types, the frontend records edits to an append-only journal for
+//! each notebook, stored in the app data directory. Journals are cleared when
+//! the notebook is saved or the edits are discarded. If the app crashes or is
+//! force-quit, the journal outlives the session, and on next launch any journal
+//! newer than its saved notebook can be offered for recovery.
+//!
+//! Each journal is a JSON Lines file. The first line records which notebook it
+//! belongs to, and each following line is the full source of an edited cell.
+//! A truncated final line, e.g., from a crash mid-write, is ignored.
+//!
+//! Edits to sources don't capture changes like added cells or new outputs, so
+//! the whole notebook is also autosaved to a snapshot file next to the journal,
+//! see [`crate::autosave`]. Only edits made after the latest snapshot are
+//! recovered on top of it.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::{backend::notebook::NotebookRoot, Error};
+
+/// A line in the recovery journal.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JournalLine {
+    /// Header identifying the notebook this journal belongs to.
+    Open { path: PathBuf },
+
+    /// The new source of a cell after an edit.
+    Edit {
+        cell_id: String,
+        source: String,
+        #[serde(with = "time::serde::rfc3339")]
+        timestamp: OffsetDateTime,
+    },
+}
+
+/// An autosaved copy of a whole notebook.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct Snapshot {
+    /// Path to the notebook on disk.
+    path: PathBuf,
+
+    /// When the notebook was in this state.
+    #[serde(with = "time::serde::rfc3339")]
+    taken: OffsetDateTime,
+
+    /// Contents of the notebook.
+    notebook: NotebookRoot,
+}
+
+/// Unsaved edits to a notebook that can be recovered.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct Recovery {
+    /// Path to the notebook on disk.
+    pub path: String,
+
+    /// When the most recent change was made.
+    #[serde(with = "time::serde::rfc3339")]
+    #[ts(type = "string")]
+    pub modified: OffsetDateTime,
+
+    /// Autosaved copy of the whole notebook, if there is one.
+    pub snapshot: Option<NotebookRoot>,
+
+    /// Latest source of each cell edited after the snapshot, keyed by cell ID.
+    pub edits: BTreeMap<String, String>,
+}
+
+crate::export_bindings!(Recovery);
+
+/// Journals of unsaved edits, stored in a directory with one file each.
+#[derive(Clone, Debug)]
+pub struct RecoveryJournal {
+    dir: PathBuf,
+}
+
+impl RecoveryJournal {
+    /// Create a journal store in the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Get the name of a notebook's recovery files, a hash of its path.
+    fn file_stem(notebook: &Path) -> String {
+        let hash = Sha256::digest(notebook.to_string_lossy().as_bytes());
+        format!("{hash:x}")
+    }
+
+    /// Get the journal file for a notebook.
+    fn journal_path(&self, stem: &str) -> PathBuf {
+        self.dir.join(format!("{stem}.jsonl"))
+    }
+
+    /// Get the snapshot file for a notebook.
+    fn snapshot_path(&self, stem: &str) -> PathBuf {
+        self.dir.join(format!("{stem}.snapshot.json"))
+    }
+
+    /// Append an edit of a cell's source to the notebook's journal.
+    pub async fn record(&self, notebook: &Path, cell_id: &str, source: &str) -> Result<(), Error> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(Error::Filesystem)?;
+        let journal = self.journal_path(&Self::file_stem(notebook));
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal)
+            .await
+            .map_err(Error::Filesystem)?;
+
+        let mut buf = Vec::new();
+        if file.metadata().await.map_err(Error::Filesystem)?.len() == 0 {
+            let header = JournalLine::Open {
+                path: notebook.to_path_buf(),
+            };
+            serde_json::to_writer(&mut buf, &header)?;
+            buf.push(b'\n');
+        }
+        let edit = JournalLine::Edit {
+            cell_id: cell_id.into(),
+            source: source.into(),
+            timestamp: OffsetDateTime::now_utc(),
+        };
+        serde_json::to_writer(&mut buf, &edit)?;
+        buf.push(b'\n');
+
+        // Write each batch of lines at once, so they are appended atomically.
+        file.write_all(&buf).await.map_err(Error::Filesystem)?;
+        file.flush().await.map_err(Error::Filesystem)
+    }
+
+    /// Replace the autosaved snapshot of a notebook, taken at `taken`.
+    ///
+    /// The snapshot is written to a temporary file and renamed into place, so
+    /// a crash mid-write leaves the previous snapshot intact.
+    pub async fn save_snapshot(
+        &self,
+        notebook: &Path,
+        contents: &NotebookRoot,
+        taken: OffsetDateTime,
+    ) -> Result<(), Error> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(Error::Filesystem)?;
+        let snapshot = Snapshot {
+            path: notebook.to_path_buf(),
+            taken,
+            notebook: contents.clone(),
+        };
+        let stem = Self::file_stem(notebook);
+        let temp = self
+            .dir
+            .join(format!("{stem}.{}.tmp", uuid::Uuid::new_v4()));
+        tokio::fs::write(&temp, serde_json::to_vec(&snapshot)?)
+            .await
+            .map_err(Error::Filesystem)?;
+        tokio::fs::rename(&temp, self.snapshot_path(&stem))
+            .await
+            .map_err(Error::Filesystem)
+    }
+
+    /// Delete the journal and snapshot for a notebook, after it is saved or
+    /// discarded.
+    pub async fn clear(&self, notebook: &Path) -> Result<(), Error> {
+        self.remove(&Self::file_stem(notebook)).await
+    }
+
+    async fn remove(&self, stem: &str) -> Result<(), Error> {
+        for file in [self.journal_path(stem), self.snapshot_path(stem)] {
+            match tokio::fs::remove_file(file).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(Error::Filesystem(err)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the unsaved changes to a notebook, if they are newer than the file.
+    pub async fn recover(&self, notebook: &Path) -> Result<Option<Recovery>, Error> {
+        self.read(&Self::file_stem(notebook)).await
+    }
+
+    /// Find all notebooks with recoverable changes, deleting stale files.
+    pub async fn list(&self) -> Result<Vec<Recovery>, Error> {
+        let mut recoveries = Vec::new();
+        let mut it = match tokio::fs::read_dir(&self.dir).await {
+            Ok(it) => it,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(recoveries),
+            Err(err) => return Err(Error::Filesystem(err)),
+        };
+        let mut stems = BTreeSet::new();
+        while let Some(entry) = it.next_entry().await.map_err(Error::Filesystem)? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let stem =
+                (name.strip_suffix(".jsonl")).or_else(|| name.strip_suffix(".snapshot.json"));
+            if let Some(stem) = stem {
+                stems.insert(stem.to_string());
+            }
+        }
+        for stem in stems {
+            match self.read(&stem).await? {
+                Some(recovery) => recoveries.push(recovery),
+                None => _ = self.remove(&stem).await,
+            }
+        }
+        recoveries.sort_by_key(|r| std::cmp::Reverse(r.modified));
+        Ok(recoveries)
+    }
+
+    /// Read a notebook's journal and snapshot, returning `None` if there are
+    /// no changes or they are older than the notebook on disk.
+    async fn read(&self, stem: &str) -> Result<Option<Recovery>, Error> {
+        let journal = self.journal_path(stem);
+        let contents = match tokio::fs::read_to_string(&journal).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::Filesystem(err)),
+        };
+        let snapshot = self.read_snapshot(stem).await?;
+
+        let mut path = snapshot.as_ref().map(|s| s.path.clone());
+        let mut modified = snapshot.as_ref().map(|s| s.taken);
+        let mut edits = BTreeMap::new();
+        for line in contents.lines() {
+            match serde_json::from_str(line) {
+                Ok(JournalLine::Open { path: p }) => path = Some(p),
+                Ok(JournalLine::Edit {
+                    cell_id,
+                    source,
+                    timestamp,
+                }) => {
+                    // Edits made before the snapshot are already in it.
+                    if snapshot.as_ref().is_some_and(|s| s.taken >= timestamp) {
+                        continue;
+                    }
+                    edits.insert(cell_id, source);
+                    modified = Some(timestamp);
+                }
+                Err(err) => warn!("skipping malformed line in {journal:?}: {err}"),
+            }
+        }
+        let (Some(path), Some(modified)) = (path, modified) else {
+            return Ok(None);
+        };
+
+        // If the notebook was deleted, the edits are still worth recovering.
+        if let Ok(saved) = tokio::fs::metadata(&path).await.and_then(|m| m.modified()) {
+            if OffsetDateTime::from(saved) >= modified {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(Recovery {
+            path: path.to_string_lossy().into_owned(),
+            modified,
+            snapshot: snapshot.map(|s| s.notebook),
+            edits,
+        }))
+    }
+
+    /// Read a notebook's snapshot, if it has one. A snapshot that can't be
+    /// parsed is ignored, since the journal may still be recovered.
+    async fn read_snapshot(&self, stem: &str) -> Result<Option<Snapshot>, Error> {
+        let file = self.snapshot_path(stem);
+        let contents = match tokio::fs::read(&file).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Filesystem(err)),
+        };
+        match serde_json::from_slice(&contents) {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            Err(err) => {
+                warn!("skipping malformed snapshot {file:?}: {err}");
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn record_and_recover() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let journal = RecoveryJournal::new(dir.join("recovery"));
+        let notebook = dir.join("notebook.ipynb");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(&notebook, "{}").await.unwrap();
+
+        assert_eq!(journal.recover(&notebook).await.unwrap(), None);
+        journal.record(&notebook, "a", "print(1)").await.unwrap();
+        journal.record(&notebook, "b", "x = 2").await.unwrap();
+        journal.record(&notebook, "a", "print(3)").await.unwrap();
+
+        let recovery = journal.recover(&notebook).await.unwrap().unwrap();
+        assert_eq!(recovery.path, notebook.to_string_lossy());
+        assert_eq!(recovery.edits["a"], "print(3)");
+        assert_eq!(recovery.edits["b"], "x = 2");
+        assert_eq!(journal.list().await.unwrap(), [recovery]);
+
+        // Saving the notebook afterward makes the journal stale.
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        tokio::fs::write(&notebook, "{}").await.unwrap();
+        assert_eq!(journal.recover(&notebook).await.unwrap(), None);
+        assert!(journal.list().await.unwrap().is_empty());
+
+        journal.record(&notebook, "a", "print(4)").await.unwrap();
+        journal.clear(&notebook).await.unwrap();
+        assert_eq!(journal.recover(&notebook).await.unwrap(), None);
+
+        // Only edits after the latest snapshot are recovered on top of it.
+        let snapshot = NotebookRoot::from_cells(vec![]);
+        journal.record(&notebook, "a", "print(5)").await.unwrap();
+        (journal.save_snapshot(&notebook, &snapshot, OffsetDateTime::now_utc()))
+            .await
+            .unwrap();
+        journal.record(&notebook, "b", "x = 6").await.unwrap();
+        let recovery = journal.recover(&notebook).await.unwrap().unwrap();
+        assert_eq!(recovery.snapshot, Some(snapshot));
+        assert_eq!(recovery.edits.keys().collect::<Vec<_>>(), ["b"]);
+        journal.clear(&notebook).await.unwrap();
+        assert!(journal.list().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,410 @@
+//! JavaScript libraries for rendering rich outputs, cached for offline use.
+//!
+//! Outputs like Plotly figures (`application/vnd.plotly.v1+json`) and Vega-Lite
+//! charts are JSON specs that need a library to draw them. Instead of loading
+//! these from a CDN in the notebook, which breaks offline and changes versions
+//! underneath the user, each library is pinned to a specific version here. It's
+//! downloaded the first time an output needs it and cached in the app data
+//! directory, then served to the webview over the `renderer://` protocol.
+//!
+//! The same goes for the KaTeX or MathJax engine that renders LaTeX, chosen by
+//! [`MathRenderer`] in the settings. Its files are prefetched when the setting
+//! changes and on startup, so math renders without network access.
+//!
+//! Each file is pinned to a SHA-256 hash too, and a download that doesn't match
+//! is rejected before it is cached, since the webview runs it.
+//!
+//! If a pinned version can't be downloaded, e.g., when offline after an update
+//! changed the pins, the newest other cached version of the library is used.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use ts_rs::TS;
+
+use crate::Error;
+
+/// URI scheme used by the webview to load libraries, like
+/// `renderer://localhost/plotly/plotly.min.js`.
+pub const SCHEME: &str = "renderer";
+
+/// A library used by the frontend to render outputs or math.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RendererAsset {
+    /// Name of the library, requested by the frontend as `<name>/<file>`.
+    pub name: &'static str,
+
+    /// Pinned version of the library.
+    pub version: &'static str,
+
+    /// URL of the directory containing the files for this version.
+    pub base_url: &'static str,
+
+    /// Files of the library that can be requested, relative to `base_url`,
+    /// each with the hex SHA-256 hash of its contents.
+    pub files: &'static [(&'static str, &'static str)],
+}
+
+/// Hash of a pinned file that hasn't been recorded yet, which no download
+/// matches. Run `cargo test pinned_hashes -- --ignored` to print the hashes
+/// of the pinned files after changing them.
+const PENDING_HASH: &str = "";
+
+/// Libraries that can be loaded by the frontend.
+pub const ASSETS: &[RendererAsset] = &[
+    RendererAsset {
+        name: "plotly",
+        version: "2.35.2",
+        base_url: "https://cdn.jsdelivr.net/npm/plotly.js-dist-min@2.35.2/",
+        files: &[("plotly.min.js", PENDING_HASH)],
+    },
+    RendererAsset {
+        name: "vega",
+        version: "5.30.0",
+        base_url: "https://cdn.jsdelivr.net/npm/vega@5.30.0/build/",
+        files: &[("vega.min.js", PENDING_HASH)],
+    },
+    RendererAsset {
+        name: "vega-lite",
+        version: "5.21.0",
+        base_url: "https://cdn.jsdelivr.net/npm/vega-lite@5.21.0/build/",
+        files: &[("vega-lite.min.js", PENDING_HASH)],
+    },
+    RendererAsset {
+        name: "vega-embed",
+        version: "6.26.0",
+        base_url: "https://cdn.jsdelivr.net/npm/vega-embed@6.26.0/build/",
+        files: &[("vega-embed.min.js", PENDING_HASH)],
+    },
+    RendererAsset {
+        name: "katex",
+        version: "0.16.11",
+        base_url: "https://cdn.jsdelivr.net/npm/katex@0.16.11/dist/",
+        files: &[
+            ("katex.min.js", PENDING_HASH),
+            ("katex.min.css", PENDING_HASH),
+            ("fonts/KaTeX_AMS-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Caligraphic-Bold.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Caligraphic-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Fraktur-Bold.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Fraktur-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Main-Bold.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Main-BoldItalic.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Main-Italic.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Main-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Math-BoldItalic.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Math-Italic.woff2", PENDING_HASH),
+            ("fonts/KaTeX_SansSerif-Bold.woff2", PENDING_HASH),
+            ("fonts/KaTeX_SansSerif-Italic.woff2", PENDING_HASH),
+            ("fonts/KaTeX_SansSerif-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Script-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Size1-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Size2-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Size3-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Size4-Regular.woff2", PENDING_HASH),
+            ("fonts/KaTeX_Typewriter-Regular.woff2", PENDING_HASH),
+        ],
+    },
+    RendererAsset {
+        // The full build includes all TeX extensions, so that none of them
+        // need to be loaded separately.
+        name: "mathjax",
+        version: "3.2.2",
+        base_url: "https://cdn.jsdelivr.net/npm/mathjax@3.2.2/es5/",
+        files: &[("tex-svg-full.js", PENDING_HASH)],
+    },
+];
+
+/// Find a library and file by the path requested from the frontend, like
+/// `plotly/plotly.min.js`.
+pub fn find_asset(path: &str) -> Option<(&'static RendererAsset, &'static str)> {
+    let (name, file) = path.split_once('/')?;
+    let asset = ASSETS.iter().find(|asset| asset.name == name)?;
+    let (file, _) = asset.files.iter().find(|(f, _)| *f == file)?;
+    Some((asset, file))
+}
+
+/// Content type of a file served to the webview, by its extension.
+pub fn content_type(file: &str) -> &'static str {
+    match file.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("js") => "text/javascript",
+        Some("css") => "text/css",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Engine used to render LaTeX in Markdown cells and outputs.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum MathRenderer {
+    /// KaTeX, which is fast but supports fewer commands. The default.
+    #[default]
+    Katex,
+
+    /// MathJax, which is slower but supports nearly all of LaTeX math.
+    Mathjax,
+}
+
+crate::export_bindings!(MathRenderer);
+
+impl MathRenderer {
+    /// The library that renders math with this engine.
+    pub fn asset(self) -> &'static RendererAsset {
+        let name = match self {
+            MathRenderer::Katex => "katex",
+            MathRenderer::Mathjax => "mathjax",
+        };
+        ASSETS.iter().find(|asset| asset.name == name).unwrap()
+    }
+}
+
+/// Cache of downloaded libraries, stored in a directory with a subdirectory
+/// for each version, named `<name>-<version>`.
+#[derive(Clone, Debug)]
+pub struct RendererCache {
+    dir: PathBuf,
+    client: reqwest::Client,
+}
+
+impl RendererCache {
+    /// Create a cache in the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Get the cached directory for a version of a library.
+    fn version_dir(&self, name: &str, version: &str) -> PathBuf {
+        self.dir.join(format!("{name}-{version}"))
+    }
+
+    /// Get a file of a library, downloading it if it isn't cached.
+    pub async fn get(&self, asset: &RendererAsset, file: &str) -> Result<Vec<u8>, Error> {
+        let path = self.version_dir(asset.name, asset.version).join(file);
+        match tokio::fs::read(&path).await {
+            Ok(contents) => return Ok(contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(Error::Filesystem(err)),
+        }
+
+        let err = match self.download(asset, file, &path).await {
+            Ok(contents) => return Ok(contents),
+            Err(err) => err,
+        };
+        match self.fallback(asset.name, file).await {
+            Some(fallback) => {
+                warn!(
+                    "could not download {} {}, using {}: {err}",
+                    asset.name,
+                    asset.version,
+                    fallback.display()
+                );
+                tokio::fs::read(&fallback).await.map_err(Error::Filesystem)
+            }
+            None => Err(err),
+        }
+    }
+
+    /// Download all files of a library that aren't cached yet, so that it is
+    /// available offline later.
+    pub async fn prefetch(&self, asset: &RendererAsset) -> Result<(), Error> {
+        for (file, _) in asset.files {
+            let path = self.version_dir(asset.name, asset.version).join(file);
+            if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+                self.download(asset, file, &path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Download a file of a library into the cache.
+    async fn download(
+        &self,
+        asset: &RendererAsset,
+        file: &str,
+        path: &Path,
+    ) -> Result<Vec<u8>, Error> {
+        info!("downloading {} {}: {file}", asset.name, asset.version);
+        let contents = self
+            .client
+            .get(format!("{}{file}", asset.base_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        verify(asset, file, &contents)?;
+
+        // Write to a temporary file first, so a partial download isn't cached.
+        let parent = path.parent().unwrap_or(&self.dir);
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(Error::Filesystem)?;
+        let partial = parent.join(format!(".{}.partial", uuid::Uuid::new_v4()));
+        tokio::fs::write(&partial, &contents)
+            .await
+            .map_err(Error::Filesystem)?;
+        tokio::fs::rename(&partial, path)
+            .await
+            .map_err(Error::Filesystem)?;
+        Ok(contents.into())
+    }
+
+    /// Find a file in the most recently cached version of a library, if any.
+    async fn fallback(&self, name: &str, file: &str) -> Option<PathBuf> {
+        let prefix = format!("{name}-");
+        let mut entries = tokio::fs::read_dir(&self.dir).await.ok()?;
+        let mut newest = None;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let dir_name = entry.file_name();
+            let Some(version) = dir_name
+                .to_str()
+                .and_then(|dir_name| dir_name.strip_prefix(&prefix))
+            else {
+                continue;
+            };
+            // Skip other libraries whose names start with this one's, like
+            // `vega-lite` for `vega`.
+            if !version.starts_with(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+            let path = entry.path().join(file);
+            let Ok(modified) = tokio::fs::metadata(&path).await.and_then(|m| m.modified()) else {
+                continue;
+            };
+            if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                newest = Some((modified, path));
+            }
+        }
+        newest.map(|(_, path)| path)
+    }
+}
+
+/// Check that the contents of a file match its pinned hash.
+fn verify(asset: &RendererAsset, file: &str, contents: &[u8]) -> Result<(), Error> {
+    let expected = (asset.files.iter())
+        .find(|(f, _)| *f == file)
+        .map_or("", |(_, hash)| *hash);
+    let actual = format!("{:x}", Sha256::digest(contents));
+    if expected.is_empty() || actual != expected {
+        return Err(Error::RendererIntegrity(format!(
+            "{} {} {file} has hash {actual}, expected {expected:?}",
+            asset.name, asset.version
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn offline_fallback() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let cache = RendererCache::new(dir);
+        for (version_dir, contents) in
+            [("vega-1.0.0", "old vega"), ("vega-lite-1.0.0", "vega-lite")]
+        {
+            tokio::fs::create_dir_all(dir.join(version_dir))
+                .await
+                .unwrap();
+            tokio::fs::write(dir.join(version_dir).join("vega.js"), contents)
+                .await
+                .unwrap();
+        }
+
+        // Nothing listens on the discard port, so the download fails.
+        let asset = RendererAsset {
+            name: "vega",
+            version: "2.0.0",
+            base_url: "http://127.0.0.1:9/",
+            files: &[("vega.js", PENDING_HASH)],
+        };
+        assert_eq!(cache.get(&asset, "vega.js").await.unwrap(), b"old vega");
+        assert!(cache.prefetch(&asset).await.is_err());
+
+        tokio::fs::create_dir_all(dir.join("vega-2.0.0"))
+            .await
+            .unwrap();
+        tokio::fs::write(dir.join("vega-2.0.0/vega.js"), "new vega")
+            .await
+            .unwrap();
+        assert_eq!(cache.get(&asset, "vega.js").await.unwrap(), b"new vega");
+        cache.prefetch(&asset).await.unwrap();
+
+        let missing = RendererAsset {
+            name: "plotly",
+            ..asset
+        };
+        assert!(cache.get(&missing, "vega.js").await.is_err());
+    }
+
+    #[test]
+    fn find_by_path() {
+        let (asset, file) = find_asset("katex/fonts/KaTeX_Main-Regular.woff2").unwrap();
+        assert_eq!(
+            (asset.name, file),
+            ("katex", "fonts/KaTeX_Main-Regular.woff2")
+        );
+        assert_eq!(
+            find_asset("vega-lite/vega-lite.min.js").unwrap().0.name,
+            "vega-lite"
+        );
+        assert_eq!(find_asset("vega-lite.min.js"), None);
+        assert_eq!(find_asset("katex/../../secrets.js"), None);
+        assert_eq!(content_type("katex.min.css"), "text/css");
+    }
+
+    #[test]
+    fn verify_hash() {
+        // SHA-256 of "vega".
+        const HASH: &str = "313ce7d71787960e3bb5f8258c173ae466b4e08e1e7d24b9c7a5ba81c9a02d96";
+        let asset = RendererAsset {
+            name: "vega",
+            version: "1.0.0",
+            base_url: "",
+            files: &[("vega.js", HASH), ("vega.css", PENDING_HASH)],
+        };
+        assert!(verify(&asset, "vega.js", b"vega").is_ok());
+        assert!(verify(&asset, "vega.js", b"evil").is_err());
+        assert!(verify(&asset, "vega.css", b"").is_err());
+    }
+
+    /// Print the hashes of the pinned files, to update them after changing
+    /// the pins. This needs network access.
+    #[tokio::test]
+    #[ignore]
+    async fn pinned_hashes() {
+        let client = reqwest::Client::new();
+        let mut mismatched = Vec::new();
+        for asset in ASSETS {
+            for (file, expected) in asset.files {
+                let url = format!("{}{file}", asset.base_url);
+                let contents = client
+                    .get(&url)
+                    .send()
+                    .await
+                    .unwrap()
+                    .bytes()
+                    .await
+                    .unwrap();
+                let actual = format!("{:x}", Sha256::digest(&contents));
+                println!("{} {file}: {actual}", asset.name);
+                if actual != *expected {
+                    mismatched.push(url);
+                }
+            }
+        }
+        assert!(mismatched.is_empty(), "outdated hashes: {mismatched:?}");
+    }
+}
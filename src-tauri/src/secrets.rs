@@ -0,0 +1,117 @@
+//! Credentials kept in the OS keychain, so they are never written to config
+//! files.
+//!
+//! This uses the Keychain on macOS, the Credential Manager on Windows, and the
+//! Secret Service on Linux. Each secret belongs to a [`SecretNamespace`] for
+//! the feature that owns it, and is stored under an account named after the
+//! namespace and a key, like `server-token:<server id>`.
+//!
+//! Secrets are only read and written by the backend. They are never sent to
+//! the frontend, since notebook outputs can run scripts there.
+
+use keyring::Entry;
+use tracing::warn;
+
+use crate::Error;
+
+/// Service name that all of Jute's secrets are stored under.
+const SERVICE: &str = "com.ekzhang.jute";
+
+/// Feature that a secret belongs to, keeping their keys apart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SecretNamespace {
+    /// Tokens of saved Jupyter servers, keyed by server ID.
+    ServerToken,
+
+    /// API keys for AI model providers, keyed by provider.
+    AiApiKey,
+
+    /// Passwords for database connections, keyed by connection.
+    DatabaseCredential,
+
+    /// Token for sharing notebooks as GitHub Gists, keyed by host.
+    GithubToken,
+}
+
+impl SecretNamespace {
+    /// Prefix of the account names of secrets in this namespace.
+    const fn prefix(self) -> &'static str {
+        match self {
+            SecretNamespace::ServerToken => "server-token",
+            SecretNamespace::AiApiKey => "ai-api-key",
+            SecretNamespace::DatabaseCredential => "database-credential",
+            SecretNamespace::GithubToken => "github-token",
+        }
+    }
+}
+
+/// Keychain entry for a secret.
+fn entry(namespace: SecretNamespace, key: &str) -> Result<Entry, Error> {
+    let account = format!("{}:{key}", namespace.prefix());
+    Ok(Entry::new(SERVICE, &account)?)
+}
+
+/// Store a secret, replacing any previous value.
+pub fn set_secret(namespace: SecretNamespace, key: &str, value: &str) -> Result<(), Error> {
+    Ok(entry(namespace, key)?.set_password(value)?)
+}
+
+/// Read a secret, if it has been stored.
+pub fn get_secret(namespace: SecretNamespace, key: &str) -> Result<Option<String>, Error> {
+    match entry(namespace, key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Delete a secret, returning whether it existed.
+pub fn delete_secret(namespace: SecretNamespace, key: &str) -> Result<bool, Error> {
+    match entry(namespace, key)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Read several secrets on a blocking thread, since the keychain may wait on
+/// the OS or a password prompt.
+///
+/// Each key is returned with its secret, or with the error if it could not be
+/// read, such as on a machine without a keychain.
+pub async fn load_secrets(
+    namespace: SecretNamespace,
+    keys: Vec<String>,
+) -> Vec<(String, Result<Option<String>, Error>)> {
+    let task = tokio::task::spawn_blocking(move || {
+        keys.into_iter()
+            .map(|key| {
+                let secret = get_secret(namespace, &key);
+                (key, secret)
+            })
+            .collect()
+    });
+    task.await.unwrap_or_default()
+}
+
+/// Store several secrets on a blocking thread, deleting those that are `None`.
+///
+/// Failures are logged rather than returned, so that settings can still be
+/// saved on a machine without a keychain.
+pub async fn store_secrets(namespace: SecretNamespace, secrets: Vec<(String, Option<String>)>) {
+    let task = tokio::task::spawn_blocking(move || {
+        for (key, secret) in secrets {
+            let result = match &secret {
+                Some(value) => set_secret(namespace, &key, value),
+                None => delete_secret(namespace, &key).map(|_| ()),
+            };
+            if let Err(err) = result {
+                warn!(
+                    "could not update {} in the keychain: {err}",
+                    namespace.prefix()
+                );
+            }
+        }
+    });
+    _ = task.await;
+}
@@ -0,0 +1,224 @@
+//! Registry of remote Jupyter servers that the user has connected to.
+//!
+//! Servers are persisted as JSON in the config dir, and each one is assigned
+//! an [`EntityId`] so it can be referenced from the frontend. Their tokens are
+//! kept in the OS keychain instead of the file, keyed by server ID.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use ts_rs::TS;
+use url::Url;
+
+use crate::{
+    entity::{Entity, EntityId, EntityRegistry},
+    secrets::{self, SecretNamespace},
+    store, Error,
+};
+
+/// The name of the server registry file within the app config directory.
+pub const SERVERS_FILE: &str = "servers.json";
+
+/// A Jupyter server saved in the registry.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct JupyterServer {
+    /// Unique identifier of the server.
+    #[ts(type = "string")]
+    pub id: EntityId,
+
+    /// Human-readable name of the server.
+    pub name: String,
+
+    /// Base URL of the server, like `http://localhost:8888/`.
+    pub url: String,
+
+    /// Token used to authenticate with the server, if any.
+    ///
+    /// This is stored in the keychain, and never written to the registry file
+    /// or sent to the frontend.
+    #[serde(default, skip_serializing)]
+    #[ts(skip)]
+    pub token: Option<String>,
+}
+
+crate::export_bindings!(JupyterServer);
+
+/// The list of saved Jupyter servers.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ServerRegistry {
+    servers: Vec<JupyterServer>,
+
+    /// Servers whose tokens could not be read from the keychain. Their stored
+    /// tokens are left alone when saving, rather than deleted.
+    #[serde(skip)]
+    unread_tokens: HashSet<EntityId>,
+}
+
+impl ServerRegistry {
+    /// Load the registry from a file, registering each server's ID and reading
+    /// tokens from the keychain.
+    ///
+    /// Older versions of Jute wrote tokens to the file. Those are moved to the
+    /// keychain, and the file is rewritten without them.
+    pub async fn load(path: &Path, entities: &EntityRegistry) -> Self {
+        let mut registry: Self = store::load_json(path).await;
+        registry
+            .servers
+            .retain(|server| server.id.kind == Entity::Server && entities.insert(server.id));
+
+        let migrated = registry.servers.iter().any(|server| server.token.is_some());
+        let keys = (registry.servers.iter())
+            .filter(|server| server.token.is_none())
+            .map(|server| server.id.to_string())
+            .collect();
+        let mut tokens: HashMap<String, _> =
+            secrets::load_secrets(SecretNamespace::ServerToken, keys)
+                .await
+                .into_iter()
+                .collect();
+        for server in &mut registry.servers {
+            if server.token.is_some() {
+                continue;
+            }
+            match tokens.remove(&server.id.to_string()) {
+                Some(Ok(token)) => server.token = token,
+                Some(Err(err)) => {
+                    warn!("could not read token of server {:?}: {err}", server.url);
+                    registry.unread_tokens.insert(server.id);
+                }
+                None => _ = registry.unread_tokens.insert(server.id),
+            }
+        }
+        if migrated {
+            if let Err(err) = registry.save(path).await {
+                warn!("could not move server tokens to the keychain: {err}");
+            }
+        }
+        registry
+    }
+
+    /// Write the registry to a file, and the tokens of its servers to the
+    /// keychain.
+    ///
+    /// Keychain errors are logged without failing, so servers can be saved on
+    /// machines without a keychain.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        secrets::store_secrets(SecretNamespace::ServerToken, self.token_updates()).await;
+        store::save_json(path, self).await
+    }
+
+    /// Tokens to write to the keychain for each server, or `None` to delete a
+    /// server's token. Tokens that could not be read are skipped.
+    fn token_updates(&self) -> Vec<(String, Option<String>)> {
+        (self.servers.iter())
+            .filter(|server| !self.unread_tokens.contains(&server.id))
+            .map(|server| (server.id.to_string(), server.token.clone()))
+            .collect()
+    }
+
+    /// List all saved servers.
+    pub fn list(&self) -> &[JupyterServer] {
+        &self.servers
+    }
+
+    /// Get a saved server by ID.
+    pub fn get(&self, id: EntityId) -> Option<&JupyterServer> {
+        self.servers.iter().find(|server| server.id == id)
+    }
+
+    /// Add a server, or update the name and token of an existing server with
+    /// the same URL.
+    pub fn add(
+        &mut self,
+        entities: &EntityRegistry,
+        name: &str,
+        url: &str,
+        token: Option<&str>,
+    ) -> Result<JupyterServer, Error> {
+        let url = Url::parse(url)?.to_string();
+        if let Some(server) = self.servers.iter_mut().find(|server| server.url == url) {
+            server.name = name.into();
+            server.token = token.map(String::from);
+            self.unread_tokens.remove(&server.id);
+            return Ok(server.clone());
+        }
+        let server = JupyterServer {
+            id: entities.create(Entity::Server),
+            name: name.into(),
+            url,
+            token: token.map(String::from),
+        };
+        self.servers.push(server.clone());
+        Ok(server)
+    }
+
+    /// Remove a server by ID, returning whether it was found.
+    pub fn remove(&mut self, entities: &EntityRegistry, id: EntityId) -> bool {
+        let len = self.servers.len();
+        self.servers.retain(|server| server.id != id);
+        self.unread_tokens.remove(&id);
+        entities.remove(id);
+        self.servers.len() < len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove() {
+        let entities = EntityRegistry::new();
+        let mut registry = ServerRegistry::default();
+
+        let server = registry
+            .add(&entities, "Local", "http://localhost:8888", None)
+            .unwrap();
+        assert_eq!(server.url, "http://localhost:8888/");
+        assert!(entities.contains(server.id));
+
+        let updated = registry
+            .add(&entities, "Lab", "http://localhost:8888/", Some("abc"))
+            .unwrap();
+        assert_eq!(updated.id, server.id);
+        assert_eq!(registry.list(), [updated]);
+        assert!(registry.add(&entities, "Bad", "not a url", None).is_err());
+
+        assert!(registry.remove(&entities, server.id));
+        assert!(!entities.contains(server.id));
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn skip_unread_tokens() {
+        let entities = EntityRegistry::new();
+        let mut registry = ServerRegistry::default();
+        let first = (registry.add(&entities, "A", "http://a.test", Some("abc"))).unwrap();
+        let second = (registry.add(&entities, "B", "http://b.test", None)).unwrap();
+        assert_eq!(
+            registry.token_updates(),
+            [
+                (first.id.to_string(), Some("abc".into())),
+                (second.id.to_string(), None),
+            ],
+        );
+
+        // A token that failed to load must not be deleted by the next save.
+        registry.unread_tokens.insert(second.id);
+        assert_eq!(
+            registry.token_updates(),
+            [(first.id.to_string(), Some("abc".into()))],
+        );
+
+        // Setting a new token replaces the one that couldn't be read.
+        registry
+            .add(&entities, "B", "http://b.test", Some("def"))
+            .unwrap();
+        assert_eq!(registry.token_updates().len(), 2);
+    }
+}
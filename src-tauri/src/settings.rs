@@ -0,0 +1,184 @@
+//! User settings for the application, persisted as JSON in the config dir.
+//!
+//! Settings are loaded once during app setup and stored in [`State`]. Every
+//! field has a default so that older or partially-written files still load,
+//! and unknown fields are ignored for forward compatibility.
+//!
+//! [`State`]: crate::state::State
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    backend::wire_protocol::OverflowStrategy,
+    entity::EntityId,
+    renderers::MathRenderer,
+    store,
+    updates::{UpdateChannel, UpdateCheck},
+    Error,
+};
+
+/// The name of the settings file within the app config directory.
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Persistent user settings for Jute.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct Settings {
+    /// Minimum level of log messages to record.
+    pub log_level: LogLevel,
+
+    /// Name of the kernel spec to start when none is chosen.
+    pub default_kernel: Option<String>,
+
+    /// How often to check for new versions of Jute.
+    pub update_check: UpdateCheck,
+
+    /// Which releases to download updates from.
+    pub update_channel: UpdateChannel,
+
+    /// Whether the user has finished the first-run setup.
+    pub onboarding_completed: bool,
+
+    /// Managed virtual environment used to start kernels by default.
+    #[ts(type = "string | null")]
+    pub default_environment: Option<EntityId>,
+
+    /// Zoom level of windows.
+    pub zoom: Zoom,
+
+    /// Whether windows on Linux draw their own titlebar with GTK, rather than
+    /// using the one from the window manager.
+    pub client_side_decorations: bool,
+
+    /// System-wide keyboard shortcut that opens the scratchpad, if any.
+    pub scratchpad_shortcut: Option<String>,
+
+    /// Whether notebooks in workspaces are made searchable from Spotlight or
+    /// Windows Search.
+    pub search_indexing: bool,
+
+    /// Engine used to render LaTeX math.
+    pub math_renderer: MathRenderer,
+
+    /// Whether running a cell also re-runs the cells that depend on it.
+    pub reactive_execution: bool,
+
+    /// Size of the text that each output can show before it is truncated.
+    pub output_limit: OutputLimit,
+
+    /// What to do when a cell's output arrives faster than the notebook can
+    /// show it.
+    pub output_overflow: OverflowStrategy,
+
+    /// Project directories trusted to run their `jute.toml` startup code in
+    /// notebooks opened from `jute://` links.
+    pub trusted_projects: Vec<String>,
+}
+
+crate::export_bindings!(Settings);
+
+/// Verbosity of log messages, from least to most verbose.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    /// Only errors.
+    Error,
+
+    /// Errors and warnings.
+    Warn,
+
+    /// Informational messages, the default.
+    #[default]
+    Info,
+
+    /// Debugging messages.
+    Debug,
+
+    /// Very verbose tracing messages.
+    Trace,
+}
+
+/// Zoom level of windows, as a percentage of the default size.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(transparent)]
+pub struct Zoom(pub u32);
+
+crate::export_bindings!(Zoom);
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self(100)
+    }
+}
+
+impl Zoom {
+    /// Zoom levels stepped through when zooming in and out, as in browsers.
+    const STEPS: [u32; 13] = [50, 67, 75, 80, 90, 100, 110, 125, 150, 175, 200, 250, 300];
+
+    /// The next larger zoom level, if not already at the largest.
+    pub fn zoom_in(self) -> Self {
+        let step = Self::STEPS.iter().find(|&&step| step > self.0);
+        Self(step.copied().unwrap_or(self.0))
+    }
+
+    /// The next smaller zoom level, if not already at the smallest.
+    pub fn zoom_out(self) -> Self {
+        let step = Self::STEPS.iter().rev().find(|&&step| step < self.0);
+        Self(step.copied().unwrap_or(self.0))
+    }
+
+    /// Scale factor for the webview.
+    pub fn scale(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+}
+
+/// Size of the text that each output of a cell can show, in bytes. The rest
+/// is kept until the user expands the output.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(transparent)]
+pub struct OutputLimit(pub u32);
+
+crate::export_bindings!(OutputLimit);
+
+impl Default for OutputLimit {
+    fn default() -> Self {
+        Self(1 << 20)
+    }
+}
+
+impl Settings {
+    /// Load settings from a file, falling back to defaults if it is missing or
+    /// cannot be parsed.
+    pub async fn load(path: &Path) -> Self {
+        store::load_json(path).await
+    }
+
+    /// Write settings to a file, creating parent directories as needed.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        store::save_json(path, self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_steps() {
+        assert_eq!(Zoom::default().zoom_in(), Zoom(110));
+        assert_eq!(Zoom::default().zoom_out(), Zoom(90));
+        assert_eq!(Zoom(300).zoom_in(), Zoom(300));
+        assert_eq!(Zoom(50).zoom_out(), Zoom(50));
+        assert_eq!(Zoom(105).zoom_in(), Zoom(110));
+        assert_eq!(Zoom(105).zoom_out(), Zoom(100));
+
+        let settings: Settings = serde_json::from_str(r#"{"zoom": 125}"#).unwrap();
+        assert_eq!(settings.zoom.scale(), 1.25);
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.zoom, Zoom(100));
+    }
+}
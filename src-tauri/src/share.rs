@@ -0,0 +1,308 @@
+//! Read-only sharing of a notebook with browsers on the local network.
+//!
+//! While sharing, a small HTTP server listens on all interfaces at a random
+//! port, and serves a live page of the notebook to anyone with its URL, such as
+//! teammates following along in a meeting. The URL carries a random token, and
+//! requests without it are refused. Pages receive updated cells as the notebook
+//! changes, through server-sent events.
+//!
+//! This only needs a few fixed routes, so requests are parsed by hand instead
+//! of with a web framework.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, UdpSocket},
+    time::Duration,
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{export::live::live_page, Error};
+
+/// Length of the random token in share URLs.
+pub(crate) const TOKEN_LEN: usize = 24;
+
+/// Interval between comments sent on idle event streams, which detect when the
+/// browser has gone away.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Maximum size of a request head, in bytes.
+const MAX_HEAD_LEN: u64 = 8 * 1024;
+
+/// How long a client has to send the head of its request.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A running server sharing a notebook, which stops when dropped.
+#[derive(Debug)]
+pub struct ShareServer {
+    url: String,
+    cells: watch::Sender<String>,
+    shutdown: CancellationToken,
+}
+
+impl ShareServer {
+    /// Start sharing a notebook's rendered cells under the given title.
+    pub async fn start(title: String, cells: String) -> Result<Self, Error> {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .map_err(Error::ShareServer)?;
+        let port = listener.local_addr().map_err(Error::ShareServer)?.port();
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LEN)
+            .map(char::from)
+            .collect();
+        let host = lan_address().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let url = format!("http://{host}:{port}/?token={token}");
+        info!("sharing notebook at http://{host}:{port}/");
+
+        let (cells, receiver) = watch::channel(cells);
+        let shutdown = CancellationToken::new();
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                loop {
+                    let stream = tokio::select! {
+                        result = listener.accept() => match result {
+                            Ok((stream, _)) => stream,
+                            Err(err) => {
+                                warn!("could not accept share connection: {err}");
+                                continue;
+                            }
+                        },
+                        _ = shutdown.cancelled() => break,
+                    };
+                    let connection = Connection {
+                        token: token.clone(),
+                        title: title.clone(),
+                        cells: receiver.clone(),
+                        shutdown: shutdown.clone(),
+                    };
+                    tokio::spawn(async move {
+                        if let Err(err) = connection.handle(stream).await {
+                            warn!("share connection failed: {err}");
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            url,
+            cells,
+            shutdown,
+        })
+    }
+
+    /// URL of the shared page, including its token.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Replace the shared cells, sending them to all open pages.
+    pub fn update(&self, cells: String) {
+        self.cells.send_replace(cells);
+    }
+}
+
+impl Drop for ShareServer {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+/// Address of this computer on the local network, if it has one.
+///
+/// This connects a UDP socket to a public address to find which interface
+/// would be used. Connecting a UDP socket doesn't send any packets.
+pub(crate) fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 80)).ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+/// State needed to respond to one connection.
+struct Connection {
+    token: String,
+    title: String,
+    cells: watch::Receiver<String>,
+    shutdown: CancellationToken,
+}
+
+impl Connection {
+    /// Read a request and respond to it, then close the connection.
+    async fn handle(mut self, stream: TcpStream) -> io::Result<()> {
+        let mut stream = BufReader::new(stream);
+        let request_line = match tokio::time::timeout(HEAD_TIMEOUT, read_head(&mut stream)).await {
+            Ok(Ok(Some(request_line))) => request_line,
+            Ok(Ok(None)) => {
+                return respond(&mut stream, "431 Request Header Fields Too Large", "").await
+            }
+            Ok(Err(err)) => return Err(err),
+            Err(_) => return respond(&mut stream, "408 Request Timeout", "").await,
+        };
+
+        let mut parts = request_line.split_whitespace();
+        let (Some("GET"), Some(target)) = (parts.next(), parts.next()) else {
+            return respond(&mut stream, "405 Method Not Allowed", "").await;
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+        let token = self.token.as_bytes();
+        if !params
+            .iter()
+            .any(|(k, v)| k == "token" && bool::from(v.as_bytes().ct_eq(token)))
+        {
+            return respond(&mut stream, "403 Forbidden", "").await;
+        }
+
+        match path {
+            "/" => {
+                let events_url = format!("/events?token={}", self.token);
+                let page = live_page(&self.title, &self.cells.borrow(), &events_url);
+                respond(&mut stream, "200 OK", &page).await
+            }
+            "/events" => self.stream_events(&mut stream).await,
+            _ => respond(&mut stream, "404 Not Found", "").await,
+        }
+    }
+
+    /// Send the cells to a page each time they change, until it disconnects.
+    async fn stream_events(&mut self, stream: &mut BufReader<TcpStream>) -> io::Result<()> {
+        let head = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Content-Type: text/event-stream\r\n",
+            "Cache-Control: no-cache\r\n",
+            "Connection: close\r\n\r\n",
+        );
+        stream.write_all(head.as_bytes()).await?;
+        self.cells.mark_changed();
+        loop {
+            tokio::select! {
+                result = tokio::time::timeout(KEEPALIVE_INTERVAL, self.cells.changed()) => {
+                    match result {
+                        Ok(Ok(())) => {
+                            let cells = self.cells.borrow_and_update().clone();
+                            let data = serde_json::to_string(&cells)?;
+                            stream.write_all(format!("data: {data}\n\n").as_bytes()).await?;
+                        }
+                        Ok(Err(_)) => return Ok(()),
+                        Err(_) => stream.write_all(b": keepalive\n\n").await?,
+                    }
+                    stream.flush().await?;
+                }
+                _ = self.shutdown.cancelled() => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Read the head of a request, returning its request line, or `None` if the
+/// head is longer than [`MAX_HEAD_LEN`].
+async fn read_head(stream: &mut BufReader<TcpStream>) -> io::Result<Option<String>> {
+    let mut limited = stream.take(MAX_HEAD_LEN);
+    let mut request_line = None;
+    loop {
+        let mut line = String::new();
+        let len = limited.read_line(&mut line).await?;
+        if limited.limit() == 0 && !line.ends_with('\n') {
+            return Ok(None);
+        }
+        if len == 0 || line.trim().is_empty() {
+            return Ok(Some(request_line.unwrap_or_default()));
+        }
+        request_line.get_or_insert(line);
+    }
+}
+
+/// Write a complete response and close the connection.
+async fn respond(stream: &mut BufReader<TcpStream>, status: &str, html: &str) -> io::Result<()> {
+    let head = format!(
+        concat!(
+            "HTTP/1.1 {}\r\n",
+            "Content-Type: text/html; charset=utf-8\r\n",
+            "Content-Length: {}\r\n",
+            "Connection: close\r\n\r\n",
+        ),
+        status,
+        html.len(),
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(html.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    /// Send a raw request to the server on localhost, returning the response.
+    async fn send(server: &ShareServer, request: &str) -> String {
+        let port = server
+            .url()
+            .split(':')
+            .nth(2)
+            .unwrap()
+            .split('/')
+            .next()
+            .unwrap();
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// Send a GET request to the server on localhost, returning the response.
+    async fn get(server: &ShareServer, target: &str) -> String {
+        send(
+            server,
+            &format!("GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn serves_with_token() {
+        let server = ShareServer::start("demo".into(), "<pre>1 + 1</pre>".into())
+            .await
+            .unwrap();
+        let token = server.url().split("token=").nth(1).unwrap().to_string();
+
+        assert!(get(&server, "/").await.starts_with("HTTP/1.1 403"));
+        assert!(get(&server, "/?token=wrong")
+            .await
+            .starts_with("HTTP/1.1 403"));
+
+        let page = get(&server, &format!("/?token={token}")).await;
+        assert!(page.starts_with("HTTP/1.1 200"));
+        assert!(page.contains("<pre>1 + 1</pre>"));
+        assert!(page.contains(&format!("\"/events?token={token}\"")));
+
+        let missing = get(&server, &format!("/missing?token={token}")).await;
+        assert!(missing.starts_with("HTTP/1.1 404"));
+    }
+
+    #[tokio::test]
+    async fn rejects_long_head() {
+        let server = ShareServer::start("demo".into(), String::new())
+            .await
+            .unwrap();
+        let padding = "x".repeat(MAX_HEAD_LEN as usize);
+        let request = format!("GET / HTTP/1.1\r\nX-Padding: {padding}\r\n\r\n");
+        let response = send(&server, &request).await;
+        assert!(response.starts_with("HTTP/1.1 431"));
+    }
+}
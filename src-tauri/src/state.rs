@@ -1,19 +1,182 @@
 //! Defines state and stores for the Tauri application.
 
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicU32, Arc, Mutex, OnceLock, RwLock},
+};
+
 use dashmap::DashMap;
 
-use crate::backend::local::LocalKernel;
+use crate::{
+    autosave::Autosaver,
+    backend::{
+        local::LocalKernel, outputs::OutputStore, queue::ExecutionQueue, truncate::TruncatedOutputs,
+    },
+    collab::session::CollabSession,
+    commands::{updates::PendingUpdate, WarmKernel},
+    database::{pool::DatabasePool, DatabaseRegistry},
+    document::NotebookDoc,
+    entity::{EntityId, EntityRegistry},
+    keymap::Keymap,
+    loading::NotebookLoads,
+    logging::Logger,
+    recent::RecentPaths,
+    servers::ServerRegistry,
+    settings::Settings,
+    share::ShareServer,
+    tasks::{TaskHandle, TaskRegistry},
+    terminal::Terminal,
+    window::NotebookWindow,
+    window_state::WindowStates,
+};
 
 /// State for the running Tauri application.
-#[derive(Default)]
 pub struct State {
+    /// Live entities, used to validate IDs received from the frontend.
+    pub entities: EntityRegistry,
+
     /// Current kernels running in the application.
-    pub kernels: DashMap<String, LocalKernel>,
+    pub kernels: DashMap<EntityId, LocalKernel>,
+
+    /// Outputs of the cells run on each kernel, to resolve updates to display
+    /// IDs. Keyed by kernel, since display IDs are scoped to it.
+    pub outputs: DashMap<EntityId, OutputStore>,
+
+    /// Text cut from large outputs on each kernel, until the user expands
+    /// them.
+    pub truncated_outputs: DashMap<EntityId, TruncatedOutputs>,
+
+    /// Info from each kernel's `kernel_info_reply`, fetched when it starts.
+    pub kernel_info: DashMap<EntityId, KernelInfoReply>,
+
+    /// Setup code run in each kernel after it starts, and again on restart.
+    pub kernel_startup: DashMap<EntityId, Vec<String>>,
+
+    /// Cells waiting to run on each kernel, so that they run one at a time.
+    pub execution_queues: DashMap<EntityId, Arc<ExecutionQueue>>,
+
+    /// Windows that are showing a notebook, keyed by window label.
+    pub notebook_windows: DashMap<String, NotebookWindow>,
+
+    /// Windows that are showing a folder as a workspace, keyed by window label.
+    pub workspace_windows: DashMap<String, PathBuf>,
+
+    /// User settings, loaded from disk during app setup.
+    pub settings: RwLock<Settings>,
+
+    /// Saved Jupyter servers, loaded during app setup.
+    pub servers: RwLock<ServerRegistry>,
+
+    /// Saved database connections, loaded during app setup.
+    pub databases: RwLock<DatabaseRegistry>,
+
+    /// Open connection pools to saved databases, created on first query.
+    pub database_pools: DashMap<EntityId, Arc<DatabasePool>>,
+
+    /// User overrides of keyboard shortcuts, loaded during app setup.
+    pub keymap: RwLock<Keymap>,
+
+    /// Saved window geometry of notebooks, loaded during app setup.
+    pub window_states: RwLock<WindowStates>,
+
+    /// Recently opened notebooks, loaded during app setup.
+    pub recent_notebooks: RwLock<RecentPaths>,
+
+    /// Snapshots of open notebooks waiting to be autosaved for recovery.
+    pub autosaver: Autosaver,
+
+    /// Cells of notebooks being loaded in pages, by load ID.
+    pub notebook_loads: NotebookLoads,
+
+    /// Notebook documents open in windows, the source of truth for saving.
+    pub documents: DashMap<EntityId, NotebookDoc>,
+
+    /// Recently opened workspace folders, loaded during app setup.
+    pub recent_folders: RwLock<RecentPaths>,
+
+    /// The global logger, installed during app setup.
+    pub logger: OnceLock<Logger>,
+
+    /// Long-running background tasks, such as exports.
+    pub tasks: TaskRegistry,
+
+    /// Tasks driven by the frontend, like running all cells of a notebook,
+    /// with the label of the window that started each one.
+    pub client_tasks: DashMap<EntityId, (String, TaskHandle)>,
+
+    /// Failures in tasks that finished while the app was in the background,
+    /// kept on the Dock badge until a window is focused.
+    pub unseen_task_failures: AtomicU32,
+
+    /// Update downloaded in the background, installed on restart.
+    pub pending_update: Mutex<Option<PendingUpdate>>,
+
+    /// Shells running in the integrated terminal, with the label of the window
+    /// that spawned each one.
+    pub terminals: DashMap<EntityId, (String, Terminal)>,
+
+    /// Servers sharing notebooks on the local network, by window label.
+    pub share_servers: DashMap<String, ShareServer>,
+
+    /// Collaboration sessions that windows are hosting or have joined, by
+    /// window label.
+    pub collab_sessions: DashMap<String, CollabSession>,
+
+    /// Kernels starting in the background for notebooks being opened, by path.
+    /// See [`warm_kernel`](crate::commands::warm_kernel).
+    pub warm_kernels: DashMap<PathBuf, WarmKernel>,
+
+    /// Kernel kept running for the scratchpad, once it has started.
+    pub scratchpad_kernel: tokio::sync::Mutex<Option<EntityId>>,
+
+    /// Notebooks opened from deep links in projects that the user doesn't
+    /// trust, whose kernels don't get the project's startup code or
+    /// environment variables.
+    pub untrusted_notebooks: DashMap<PathBuf, ()>,
 }
 
 impl State {
     /// Create a new state object.
     pub fn new() -> Self {
-        Self::default()
+        let entities = EntityRegistry::new();
+        Self {
+            tasks: TaskRegistry::new(entities.clone()),
+            entities,
+            client_tasks: DashMap::new(),
+            unseen_task_failures: AtomicU32::new(0),
+            kernels: DashMap::new(),
+            outputs: DashMap::new(),
+            truncated_outputs: DashMap::new(),
+            kernel_info: DashMap::new(),
+            kernel_startup: DashMap::new(),
+            execution_queues: DashMap::new(),
+            notebook_windows: DashMap::new(),
+            workspace_windows: DashMap::new(),
+            settings: RwLock::default(),
+            servers: RwLock::default(),
+            databases: RwLock::default(),
+            database_pools: DashMap::new(),
+            keymap: RwLock::default(),
+            window_states: RwLock::default(),
+            recent_notebooks: RwLock::default(),
+            recent_folders: RwLock::default(),
+            autosaver: Autosaver::default(),
+            notebook_loads: NotebookLoads::default(),
+            documents: DashMap::new(),
+            logger: OnceLock::new(),
+            pending_update: Mutex::new(None),
+            terminals: DashMap::new(),
+            share_servers: DashMap::new(),
+            collab_sessions: DashMap::new(),
+            warm_kernels: DashMap::new(),
+            scratchpad_kernel: tokio::sync::Mutex::new(None),
+            untrusted_notebooks: DashMap::new(),
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,37 @@
+//! Helpers for small JSON files persisted in the app config or data dirs.
+
+use std::{io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+use crate::Error;
+
+/// Load a value from a JSON file, falling back to the default if the file is
+/// missing or cannot be parsed.
+pub async fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match tokio::fs::read_to_string(path).await {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+            warn!("could not parse {path:?}, using defaults: {err}");
+            T::default()
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(err) => {
+            warn!("could not read {path:?}, using defaults: {err}");
+            T::default()
+        }
+    }
+}
+
+/// Write a value to a JSON file, creating parent directories as needed.
+pub async fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(Error::Filesystem)?;
+    }
+    let contents = serde_json::to_string_pretty(value)?;
+    tokio::fs::write(path, contents)
+        .await
+        .map_err(Error::Filesystem)
+}
@@ -0,0 +1,370 @@
+//! Registry of long-running background tasks with progress reporting.
+//!
+//! Exports, environment creation, and syncs can take a while. Each of these
+//! registers a task here and reports progress through its [`TaskHandle`], so
+//! the frontend can show a unified activity indicator. Updates are broadcast
+//! to subscribers, which forward them to the frontend as events, and show the
+//! combined [`TaskSummary`] on the Dock icon or taskbar.
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use ts_rs::TS;
+
+use crate::entity::{Entity, EntityId, EntityRegistry};
+
+/// Name of the event emitted to the frontend when a task changes.
+pub const TASK_EVENT: &str = "task-update";
+
+/// The category of work being done by a task.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    /// Exporting a notebook to another format.
+    Export,
+
+    /// Creating a virtual environment.
+    CreateEnvironment,
+
+    /// Installing and registering a kernel, like evcxr for Rust.
+    InstallKernel,
+
+    /// Synchronizing files or state with another location.
+    Sync,
+
+    /// Running many cells of a notebook, like with "Run All".
+    RunCells,
+}
+
+/// Current status of a task.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// The task is still running.
+    Running,
+
+    /// The task finished successfully.
+    Completed,
+
+    /// The task failed with an error.
+    Failed,
+
+    /// The task was cancelled by the user.
+    Cancelled,
+}
+
+/// A snapshot of a task's state, sent to the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, TS)]
+pub struct TaskInfo {
+    /// Unique identifier of the task.
+    #[ts(type = "string")]
+    pub id: EntityId,
+
+    /// What kind of work the task is doing.
+    pub kind: TaskKind,
+
+    /// Human-readable description of the task.
+    pub title: String,
+
+    /// Fraction of work completed from 0 to 1, or null if indeterminate.
+    pub progress: Option<f64>,
+
+    /// Optional message describing the current step.
+    pub message: Option<String>,
+
+    /// Whether the task can be cancelled by the user.
+    pub cancellable: bool,
+
+    /// Current status of the task.
+    pub status: TaskStatus,
+
+    /// Error message if the task failed.
+    pub error: Option<String>,
+
+    /// Number of steps that have failed so far, such as cells that raised an
+    /// error.
+    pub failures: u32,
+}
+
+crate::export_bindings!(TaskInfo);
+
+/// Combined state of all running tasks, shown on the Dock icon or taskbar.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskSummary {
+    /// Average progress of the tasks from 0 to 1, or `None` if no task
+    /// reports its progress.
+    pub progress: Option<f64>,
+
+    /// Total number of failed steps in the tasks.
+    pub failures: u32,
+}
+
+struct TaskEntry {
+    info: TaskInfo,
+    cancel: CancellationToken,
+}
+
+/// Tracks running background tasks and broadcasts their updates.
+#[derive(Clone)]
+pub struct TaskRegistry {
+    tasks: Arc<DashMap<EntityId, TaskEntry>>,
+    entities: EntityRegistry,
+    events: broadcast::Sender<TaskInfo>,
+}
+
+impl TaskRegistry {
+    /// Create an empty task registry, allocating IDs from `entities`.
+    pub fn new(entities: EntityRegistry) -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            tasks: Arc::new(DashMap::new()),
+            entities,
+            events,
+        }
+    }
+
+    /// Register a new running task, returning a handle to report progress.
+    pub fn start(&self, kind: TaskKind, title: impl Into<String>, cancellable: bool) -> TaskHandle {
+        let info = TaskInfo {
+            id: self.entities.create(Entity::Task),
+            kind,
+            title: title.into(),
+            progress: None,
+            message: None,
+            cancellable,
+            status: TaskStatus::Running,
+            error: None,
+            failures: 0,
+        };
+        let cancel = CancellationToken::new();
+        let id = info.id;
+        self.tasks.insert(
+            id,
+            TaskEntry {
+                info: info.clone(),
+                cancel: cancel.clone(),
+            },
+        );
+        _ = self.events.send(info);
+        TaskHandle {
+            id,
+            registry: self.clone(),
+            cancel,
+        }
+    }
+
+    /// List all tasks that are currently running.
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.tasks.iter().map(|entry| entry.info.clone()).collect()
+    }
+
+    /// Summarize the tasks that are currently running, if there are any.
+    ///
+    /// Tasks without a known progress are left out of the average.
+    pub fn summary(&self) -> Option<TaskSummary> {
+        if self.tasks.is_empty() {
+            return None;
+        }
+        let progress: Vec<f64> = self
+            .tasks
+            .iter()
+            .filter_map(|entry| entry.info.progress)
+            .collect();
+        Some(TaskSummary {
+            progress: (!progress.is_empty())
+                .then(|| progress.iter().sum::<f64>() / progress.len() as f64),
+            failures: self.tasks.iter().map(|entry| entry.info.failures).sum(),
+        })
+    }
+
+    /// Request cancellation of a task, returning whether it was cancellable.
+    pub fn cancel(&self, id: EntityId) -> bool {
+        match self.tasks.get(&id) {
+            Some(entry) if entry.info.cancellable => {
+                entry.cancel.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Subscribe to updates from all tasks.
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskInfo> {
+        self.events.subscribe()
+    }
+
+    fn update(&self, id: EntityId, f: impl FnOnce(&mut TaskInfo)) {
+        let info = match self.tasks.get_mut(&id) {
+            Some(mut entry) => {
+                f(&mut entry.info);
+                entry.info.clone()
+            }
+            None => return,
+        };
+        if info.status != TaskStatus::Running {
+            self.tasks.remove(&id);
+            self.entities.remove(id);
+        }
+        _ = self.events.send(info);
+    }
+}
+
+/// Handle held by the code running a task, used to report progress.
+///
+/// If the handle is dropped before the task is finished, the task is marked as
+/// failed so that it does not linger in the registry.
+pub struct TaskHandle {
+    id: EntityId,
+    registry: TaskRegistry,
+    cancel: CancellationToken,
+}
+
+impl TaskHandle {
+    /// Get the ID of this task.
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    /// Report progress as a fraction from 0 to 1, with a message.
+    pub fn set_progress(&self, progress: f64, message: impl Into<String>) {
+        self.registry.update(self.id, |info| {
+            info.progress = Some(progress.clamp(0.0, 1.0));
+            info.message = Some(message.into());
+        });
+    }
+
+    /// Count a step of the task that failed, without stopping the task.
+    pub fn add_failure(&self) {
+        self.registry.update(self.id, |info| info.failures += 1);
+    }
+
+    /// Check if cancellation has been requested for this task.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Wait until cancellation has been requested for this task.
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.cancel.cancelled()
+    }
+
+    /// Mark the task as successfully completed.
+    pub fn complete(self) {
+        self.finish(TaskStatus::Completed, None);
+    }
+
+    /// Mark the task as failed with an error message.
+    pub fn fail(self, error: impl Into<String>) {
+        self.finish(TaskStatus::Failed, Some(error.into()));
+    }
+
+    fn finish(&self, status: TaskStatus, error: Option<String>) {
+        let status = if self.is_cancelled() {
+            TaskStatus::Cancelled
+        } else {
+            status
+        };
+        self.registry.update(self.id, |info| {
+            if status == TaskStatus::Completed {
+                info.progress = Some(1.0);
+            }
+            info.status = status;
+            info.error = error;
+        });
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        // No-op if the task was already finished and removed from the registry.
+        self.finish(TaskStatus::Failed, Some("task ended unexpectedly".into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_lifecycle() {
+        let registry = TaskRegistry::new(EntityRegistry::new());
+        let mut rx = registry.subscribe();
+
+        let task = registry.start(TaskKind::Export, "Exporting", true);
+        assert_eq!(registry.list().len(), 1);
+        task.set_progress(0.5, "halfway");
+        assert_eq!(registry.list()[0].progress, Some(0.5));
+
+        task.complete();
+        assert!(registry.list().is_empty());
+
+        let statuses: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|info| info.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                TaskStatus::Running,
+                TaskStatus::Running,
+                TaskStatus::Completed
+            ]
+        );
+    }
+
+    #[test]
+    fn task_summary() {
+        let registry = TaskRegistry::new(EntityRegistry::new());
+        assert_eq!(registry.summary(), None);
+
+        let export = registry.start(TaskKind::Export, "Exporting", false);
+        let run = registry.start(TaskKind::RunCells, "Running", false);
+        assert_eq!(
+            registry.summary(),
+            Some(TaskSummary {
+                progress: None,
+                failures: 0
+            })
+        );
+
+        run.set_progress(0.5, "Cell 2 of 4");
+        run.add_failure();
+        export.set_progress(1.0, "Writing");
+        assert_eq!(
+            registry.summary(),
+            Some(TaskSummary {
+                progress: Some(0.75),
+                failures: 1
+            })
+        );
+
+        export.complete();
+        run.complete();
+        assert_eq!(registry.summary(), None);
+    }
+
+    #[test]
+    fn task_cancellation() {
+        let registry = TaskRegistry::new(EntityRegistry::new());
+
+        let fixed = registry.start(TaskKind::CreateEnvironment, "Creating", false);
+        assert!(!registry.cancel(fixed.id()));
+        assert!(!fixed.is_cancelled());
+
+        let task = registry.start(TaskKind::Sync, "Syncing", true);
+        assert!(registry.cancel(task.id()));
+        assert!(task.is_cancelled());
+
+        let mut rx = registry.subscribe();
+        task.fail("interrupted");
+        assert_eq!(rx.try_recv().unwrap().status, TaskStatus::Cancelled);
+
+        drop(fixed);
+        let info = rx.try_recv().unwrap();
+        assert_eq!(info.status, TaskStatus::Failed);
+        assert!(info.error.is_some());
+        assert!(registry.list().is_empty());
+    }
+}
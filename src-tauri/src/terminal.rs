@@ -0,0 +1,228 @@
+//! Shells running in a native pseudo-terminal, for the integrated terminal.
+//!
+//! Each terminal owns the controlling side of a PTY from `portable-pty`, with
+//! the shell attached to the other side. Output is read on a dedicated thread,
+//! since PTY reads are blocking on every platform, and passed to a callback as
+//! text. Input and resizes go through the [`Terminal`] handle.
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+};
+
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::Error;
+
+/// Size of the buffer for reading output from the PTY.
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Events sent to the frontend from a running terminal.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case", tag = "event", content = "data")]
+pub enum TerminalEvent {
+    /// Output written by the shell, including escape sequences.
+    Output(String),
+
+    /// The shell exited, with its exit code if known.
+    Exit(Option<u32>),
+}
+
+crate::export_bindings!(TerminalEvent);
+
+/// Options for spawning a shell in a new terminal.
+#[derive(Clone, Debug, Default)]
+pub struct TerminalOptions {
+    /// Shell to run, or the user's default shell if `None`.
+    pub shell: Option<String>,
+
+    /// Working directory of the shell, or the home directory if `None`.
+    pub cwd: Option<PathBuf>,
+
+    /// Virtual environment to activate in the shell, if any.
+    pub venv: Option<PathBuf>,
+
+    /// Number of columns of the terminal.
+    pub cols: u16,
+
+    /// Number of rows of the terminal.
+    pub rows: u16,
+}
+
+/// A shell running in a pseudo-terminal. The shell is killed when dropped.
+pub struct Terminal {
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+}
+
+impl Terminal {
+    /// Spawn a shell in a new terminal, sending its events to `on_event` until
+    /// it exits or the callback returns `false`.
+    pub fn spawn(
+        options: &TerminalOptions,
+        mut on_event: impl FnMut(TerminalEvent) -> bool + Send + 'static,
+    ) -> Result<Self, Error> {
+        let pair = native_pty_system()
+            .openpty(pty_size(options.cols, options.rows))
+            .map_err(terminal_error)?;
+
+        let mut command = match &options.shell {
+            Some(shell) => CommandBuilder::new(shell),
+            None => CommandBuilder::new_default_prog(),
+        };
+        command.env("TERM", "xterm-256color");
+        if let Some(cwd) = &options.cwd {
+            command.cwd(cwd);
+        }
+        if let Some(venv) = &options.venv {
+            activate_venv(&mut command, venv);
+        }
+
+        let mut child = pair.slave.spawn_command(command).map_err(terminal_error)?;
+        // The shell holds its own handle to the terminal, so this one is closed
+        // for reads to end when the shell exits.
+        drop(pair.slave);
+        let killer = child.clone_killer();
+        let mut reader = pair.master.try_clone_reader().map_err(terminal_error)?;
+        let writer = pair.master.take_writer().map_err(terminal_error)?;
+
+        thread::spawn(move || {
+            let mut buf = vec![0; READ_BUFFER_SIZE];
+            let mut decoder = Utf8Decoder::default();
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(err) => {
+                        // Reading fails with EIO on Linux once the shell exits.
+                        if err.raw_os_error() != Some(5) {
+                            warn!("could not read from terminal: {err}");
+                        }
+                        break;
+                    }
+                };
+                let text = decoder.decode(&buf[..n]);
+                if !text.is_empty() && !on_event(TerminalEvent::Output(text)) {
+                    _ = child.kill();
+                    return;
+                }
+            }
+            let code = child.wait().ok().map(|status| status.exit_code());
+            on_event(TerminalEvent::Exit(code));
+        });
+
+        Ok(Self {
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            killer: Mutex::new(killer),
+        })
+    }
+
+    /// Write input to the shell, such as typed keys.
+    pub fn write(&self, data: &[u8]) -> Result<(), Error> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(data).map_err(terminal_error)?;
+        writer.flush().map_err(terminal_error)
+    }
+
+    /// Change the size of the terminal, after its panel is resized.
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), Error> {
+        self.master
+            .lock()
+            .unwrap()
+            .resize(pty_size(cols, rows))
+            .map_err(terminal_error)
+    }
+
+    /// Kill the shell.
+    pub fn kill(&self) {
+        _ = self.killer.lock().unwrap().kill();
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+fn terminal_error(err: impl ToString) -> Error {
+    Error::Terminal(err.to_string())
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Activate a virtual environment in a shell, the same way as its `activate`
+/// script, by putting its scripts first on the `PATH`.
+fn activate_venv(command: &mut CommandBuilder, venv: &Path) {
+    let bin = venv.join(if cfg!(windows) { "Scripts" } else { "bin" });
+    let path = command
+        .get_env("PATH")
+        .map(|path| path.to_os_string())
+        .unwrap_or_default();
+    let paths = std::iter::once(bin).chain(std::env::split_paths(&path));
+    match std::env::join_paths(paths) {
+        Ok(path) => command.env("PATH", path),
+        Err(err) => warn!("could not activate {}: {err}", venv.display()),
+    }
+    command.env("VIRTUAL_ENV", venv);
+    command.env_remove("PYTHONHOME");
+}
+
+/// Decodes UTF-8 text from chunks of bytes, which may split characters.
+#[derive(Default)]
+struct Utf8Decoder {
+    partial: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Decode the next chunk, holding back an incomplete character at its end.
+    /// Invalid bytes are replaced with U+FFFD.
+    fn decode(&mut self, chunk: &[u8]) -> String {
+        self.partial.extend_from_slice(chunk);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            // An error without a length is an incomplete character at the end.
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => self.partial.len(),
+        };
+        let rest = self.partial.split_off(valid);
+        let text = String::from_utf8_lossy(&self.partial).into_owned();
+        self.partial = rest;
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_split_characters() {
+        let mut decoder = Utf8Decoder::default();
+        let bytes = "héllo → ✓".as_bytes();
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            text += &decoder.decode(chunk);
+        }
+        assert_eq!(text, "héllo → ✓");
+        assert!(decoder.partial.is_empty());
+
+        assert_eq!(decoder.decode(b"a\xffb"), "a\u{fffd}b");
+        assert_eq!(decoder.decode(b"\xe2\x86"), "");
+        assert_eq!(decoder.decode(b"\x92"), "→");
+    }
+}
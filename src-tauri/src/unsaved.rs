@@ -0,0 +1,162 @@
+//! Confirmation before closing notebooks that have unsaved changes.
+//!
+//! Closing a window with a dirty notebook in any of its tabs, or quitting the
+//! app while any notebook is dirty, asks the user to save or discard the
+//! changes first. Windows chosen to be saved receive a
+//! [`SAVE_AND_CLOSE_EVENT`], and their frontend destroys the window once its
+//! notebooks are saved.
+//!
+//! The dialogs need three buttons, which the dialog plugin does not offer, so
+//! they are shown with `rfd` directly, the same way the plugin does.
+
+use std::path::PathBuf;
+
+use rfd::{AsyncMessageDialog, MessageButtons, MessageDialogResult, MessageLevel};
+use tauri::{async_runtime, AppHandle, Emitter, EventTarget, Manager, Runtime, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::state::State;
+
+/// Name of the event emitted to a window to save its notebook, then close.
+pub const SAVE_AND_CLOSE_EVENT: &str = "save-and-close";
+
+/// What to do with a notebook's unsaved changes when closing it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CloseChoice {
+    /// Save the changes, then close.
+    Save,
+
+    /// Close without saving.
+    Discard,
+
+    /// Keep the notebook open.
+    Cancel,
+}
+
+/// Ask whether to save the changes to notebooks before closing them, given
+/// their paths, or `None` for one that has never been saved.
+pub async fn ask_to_save<R: Runtime>(
+    window: &WebviewWindow<R>,
+    notebooks: &[Option<PathBuf>],
+) -> CloseChoice {
+    let name = match notebooks {
+        [Some(path)] => format!(
+            "\"{}\"",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ),
+        [None] => "\"Untitled\"".into(),
+        _ => format!("{} notebooks", notebooks.len()),
+    };
+    let description = format!(
+        "Do you want to save the changes you made to {name}?\n\nYour changes will be lost if you \
+         don't save them."
+    );
+
+    let (tx, rx) = oneshot::channel();
+    let parent = window.clone();
+    let shown = window.run_on_main_thread(move || {
+        let dialog = AsyncMessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title("Unsaved Changes")
+            .set_description(description)
+            .set_buttons(MessageButtons::YesNoCancelCustom(
+                "Save".into(),
+                "Don't Save".into(),
+                "Cancel".into(),
+            ))
+            .set_parent(&parent)
+            .show();
+        std::thread::spawn(move || _ = tx.send(async_runtime::block_on(dialog)));
+    });
+    if shown.is_err() {
+        return CloseChoice::Cancel;
+    }
+
+    // Depending on the platform, custom buttons are reported by their label.
+    match rx.await {
+        Ok(MessageDialogResult::Yes) => CloseChoice::Save,
+        Ok(MessageDialogResult::No) => CloseChoice::Discard,
+        Ok(MessageDialogResult::Custom(label)) if label == "Save" => CloseChoice::Save,
+        Ok(MessageDialogResult::Custom(label)) if label == "Don't Save" => CloseChoice::Discard,
+        _ => CloseChoice::Cancel,
+    }
+}
+
+/// Close a window, saving its notebook first if chosen.
+fn close<R: Runtime>(window: &WebviewWindow<R>, choice: CloseChoice) -> tauri::Result<()> {
+    match choice {
+        CloseChoice::Save => window.emit_to(
+            EventTarget::webview_window(window.label()),
+            SAVE_AND_CLOSE_EVENT,
+            (),
+        ),
+        CloseChoice::Discard => window.destroy(),
+        CloseChoice::Cancel => Ok(()),
+    }
+}
+
+/// Returns the notebooks shown in a window that have unsaved changes, in any
+/// of its tabs.
+pub fn unsaved_notebooks<R: Runtime>(window: &WebviewWindow<R>) -> Vec<Option<PathBuf>> {
+    let state = window.state::<State>();
+    (state.notebook_windows.get(window.label()))
+        .map(|notebook| notebook.unsaved())
+        .unwrap_or_default()
+}
+
+/// Handle a request to close a window, returning whether it should be
+/// prevented so the user can be asked about unsaved changes first.
+pub fn on_close_requested<R: Runtime>(window: &WebviewWindow<R>) -> bool {
+    let notebooks = unsaved_notebooks(window);
+    if notebooks.is_empty() {
+        return false;
+    }
+    let window = window.clone();
+    async_runtime::spawn(async move {
+        let choice = ask_to_save(&window, &notebooks).await;
+        _ = close(&window, choice);
+    });
+    true
+}
+
+/// Whether any window has a notebook with unsaved changes.
+pub fn has_unsaved_changes<R: Runtime>(app: &AppHandle<R>) -> bool {
+    app.state::<State>()
+        .notebook_windows
+        .iter()
+        .any(|notebook| !notebook.unsaved().is_empty())
+}
+
+/// Quit the app, first asking about each notebook with unsaved changes.
+///
+/// Each dirty window is focused in turn. If the user cancels any of them,
+/// nothing is closed. Otherwise clean windows are closed right away, and the
+/// app exits once the remaining notebooks have been saved and closed.
+pub async fn quit<R: Runtime>(app: AppHandle<R>) {
+    if !has_unsaved_changes(&app) {
+        app.exit(0);
+        return;
+    }
+
+    let mut choices = Vec::new();
+    for window in app.webview_windows().into_values() {
+        let notebooks = unsaved_notebooks(&window);
+        if notebooks.is_empty() {
+            continue;
+        }
+        _ = window.set_focus();
+        match ask_to_save(&window, &notebooks).await {
+            CloseChoice::Cancel => return,
+            choice => choices.push((window, choice)),
+        }
+    }
+
+    for window in app.webview_windows().into_values() {
+        if unsaved_notebooks(&window).is_empty() {
+            _ = window.destroy();
+        }
+    }
+    for (window, choice) in choices {
+        _ = close(&window, choice);
+    }
+}
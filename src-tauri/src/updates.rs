@@ -0,0 +1,170 @@
+//! Checks for new releases of Jute on GitHub.
+//!
+//! Release builds are signed, and they carry the public key used to verify
+//! updates in the `JUTE_UPDATER_PUBKEY` environment variable at compile time.
+//! Those builds download updates from the user's [`UpdateChannel`] in the
+//! background, then ask to restart once one is ready to install.
+//!
+//! Other builds, such as during development, can't verify updates, so they
+//! only notify the user that an update exists, with its release notes.
+
+use std::time::Duration;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::Error;
+
+/// Name of the event emitted to the frontend when an update is found.
+pub const UPDATE_EVENT: &str = "update-available";
+
+/// Name of the event emitted to the frontend when an update has been
+/// downloaded, and will be applied on restart.
+pub const UPDATE_READY_EVENT: &str = "update-ready";
+
+/// Public key for verifying signed updates, if this is a release build.
+pub const UPDATER_PUBKEY: Option<&str> = option_env!("JUTE_UPDATER_PUBKEY");
+
+/// Endpoint for the latest published release of Jute.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/ekzhang/jute/releases/latest";
+
+/// How often to check for updates in the background.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateCheck {
+    /// Never check automatically.
+    Never,
+
+    /// Check once a day, the default.
+    #[default]
+    Daily,
+
+    /// Check once a week.
+    Weekly,
+
+    /// Never check, even when asked, such as for installs that are managed by
+    /// an administrator.
+    Disabled,
+}
+
+impl UpdateCheck {
+    /// Time between background checks, or `None` if disabled.
+    pub const fn interval(&self) -> Option<Duration> {
+        match self {
+            UpdateCheck::Never | UpdateCheck::Disabled => None,
+            UpdateCheck::Daily => Some(Duration::from_secs(24 * 60 * 60)),
+            UpdateCheck::Weekly => Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        }
+    }
+}
+
+/// Which releases to update to.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    /// Tagged releases, the default.
+    #[default]
+    Stable,
+
+    /// Builds from the latest commit, which may be less stable.
+    Nightly,
+}
+
+impl UpdateChannel {
+    /// URL of the updater manifest for releases on this channel.
+    pub const fn manifest_url(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => {
+                "https://github.com/ekzhang/jute/releases/latest/download/latest.json"
+            }
+            UpdateChannel::Nightly => {
+                "https://github.com/ekzhang/jute/releases/download/nightly/latest.json"
+            }
+        }
+    }
+}
+
+/// A newer release that is available to download.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct UpdateInfo {
+    /// Version of the new release.
+    pub version: String,
+
+    /// Version of Jute that is currently running.
+    pub current_version: String,
+
+    /// Release notes, in Markdown.
+    pub notes: String,
+
+    /// Link to the release page.
+    pub url: String,
+
+    /// When the release was published, in RFC 3339 format.
+    pub published_at: Option<String>,
+}
+
+crate::export_bindings!(UpdateInfo);
+
+/// Subset of a release returned by the GitHub API.
+#[derive(Deserialize, Debug)]
+struct GitHubRelease {
+    tag_name: String,
+    html_url: String,
+    body: Option<String>,
+    published_at: Option<String>,
+}
+
+/// Query GitHub for the latest release, returning it if newer than `current`.
+pub async fn check_for_updates(current: &Version) -> Result<Option<UpdateInfo>, Error> {
+    let release: GitHubRelease = reqwest::Client::new()
+        .get(LATEST_RELEASE_URL)
+        .header(reqwest::header::USER_AGENT, "jute")
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(newer_release(release, current))
+}
+
+fn newer_release(release: GitHubRelease, current: &Version) -> Option<UpdateInfo> {
+    let version = Version::parse(release.tag_name.trim_start_matches('v')).ok()?;
+    (version > *current).then(|| UpdateInfo {
+        version: version.to_string(),
+        current_version: current.to_string(),
+        notes: release.body.unwrap_or_default(),
+        url: release.html_url,
+        published_at: release.published_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str) -> GitHubRelease {
+        GitHubRelease {
+            tag_name: tag.into(),
+            html_url: format!("https://github.com/ekzhang/jute/releases/tag/{tag}"),
+            body: Some("Bug fixes.".into()),
+            published_at: None,
+        }
+    }
+
+    #[test]
+    fn compare_versions() {
+        let current = Version::new(0, 2, 0);
+        let update = newer_release(release("v0.10.0"), &current).unwrap();
+        assert_eq!(update.version, "0.10.0");
+        assert_eq!(update.current_version, "0.2.0");
+        assert_eq!(update.notes, "Bug fixes.");
+
+        assert_eq!(newer_release(release("v0.2.0"), &current), None);
+        assert_eq!(newer_release(release("0.1.9"), &current), None);
+        assert_eq!(newer_release(release("v0.2.0-beta.1"), &current), None);
+        assert_eq!(newer_release(release("nightly"), &current), None);
+    }
+}
@@ -1,29 +1,260 @@
 //! Shared code to open windows in Jute and notebooks.
+//!
+//! Windows showing saved notebooks can hold several of them as tabs. The list
+//! of tabs is kept here, and the frontend renders it after each
+//! [`TABS_EVENT`]. Notebooks can also be opened at a specific cell, which is
+//! passed in the window URL or sent to an open window as a
+//! [`FOCUS_CELL_EVENT`].
+//!
+//! Folders are opened as workspaces, in windows with a file browser next to
+//! their tabs. Each folder is shown by at most one window.
+//!
+//! The size and position of a notebook's window are remembered in
+//! [`WindowStates`], and restored the next time that notebook is opened.
+//!
+//! [`WindowStates`]: crate::window_state::WindowStates
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
-use tauri::{AppHandle, Manager, Runtime, WebviewWindow, WebviewWindowBuilder};
+use serde::{Deserialize, Serialize};
+use tauri::{
+    webview::{PageLoadEvent, PageLoadPayload},
+    AppHandle, Emitter, EventTarget, Manager, Runtime, WebviewWindow, WebviewWindowBuilder, Window,
+};
+use ts_rs::TS;
 use uuid::Uuid;
 
+use crate::{
+    commands,
+    entity::EntityId,
+    state::State,
+    window_state::{place_window, Rect, WindowGeometry},
+};
+
+/// Name of the event emitted to a window when its tabs change.
+pub const TABS_EVENT: &str = "tabs-changed";
+
+/// Name of the event emitted to a window to save a tab, then close or move it.
+pub const SAVE_TAB_EVENT: &str = "save-tab";
+
+/// Name of the event emitted to a window to scroll a notebook to a cell.
+pub const FOCUS_CELL_EVENT: &str = "focus-cell";
+
+/// Label of the kernel monitor window, of which there is at most one.
+pub const KERNEL_MONITOR_LABEL: &str = "kernel-monitor";
+
+/// Label of the keyboard shortcuts window, of which there is at most one.
+pub const SHORTCUTS_LABEL: &str = "shortcuts";
+
+/// Label of the scratchpad window, of which there is at most one.
+pub const SCRATCHPAD_LABEL: &str = "scratchpad";
+
+/// A window that is showing a notebook.
+///
+/// The path, kernel, and dirty state are those of the active tab. Each tab
+/// also keeps its own path and dirty state, so that background tabs aren't
+/// closed with unsaved changes.
+#[derive(Clone, Debug, Default)]
+pub struct NotebookWindow {
+    /// Path to the notebook file, if it has been saved.
+    pub path: Option<PathBuf>,
+
+    /// Kernel attached to the notebook, once it has started.
+    pub kernel_id: Option<EntityId>,
+
+    /// Whether the notebook has changes that have not been saved.
+    pub dirty: bool,
+
+    /// Notebook files open as tabs in the window.
+    pub tabs: TabList,
+
+    /// File that the notebook was most recently exported to.
+    pub last_export: Option<PathBuf>,
+
+    /// URL where the notebook is shared on the local network, while it is.
+    pub share_url: Option<String>,
+}
+
+impl NotebookWindow {
+    /// Paths of the notebooks in the window with unsaved changes, or `None`
+    /// for a notebook that has never been saved.
+    pub fn unsaved(&self) -> Vec<Option<PathBuf>> {
+        if self.tabs.tabs.is_empty() {
+            return self.dirty.then(|| self.path.clone()).into_iter().collect();
+        }
+        (self.tabs.tabs.iter())
+            .filter(|tab| tab.dirty)
+            .map(|tab| Some(tab.path.clone()))
+            .collect()
+    }
+}
+
+/// A notebook file open as a tab, sent to the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct Tab {
+    /// Unique ID of the tab, which stays the same when its notebook is saved
+    /// under another path.
+    pub id: String,
+
+    /// Path to the notebook file.
+    pub path: PathBuf,
+
+    /// Whether the notebook has changes that have not been saved.
+    pub dirty: bool,
+}
+
+crate::export_bindings!(Tab);
+
+/// A tab to save before it is closed or moved to a new window, sent to the
+/// frontend, which repeats the action once the notebook is saved.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct SaveTab {
+    /// ID of the tab.
+    pub id: String,
+
+    /// Whether the tab is moving to a new window, rather than closing.
+    pub moving: bool,
+}
+
+crate::export_bindings!(SaveTab);
+
+/// Notebook files open as tabs in a window, sent to the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, TS)]
+pub struct TabList {
+    /// The open notebooks, in tab order.
+    pub tabs: Vec<Tab>,
+
+    /// Index of the active tab.
+    pub active: usize,
+}
+
+crate::export_bindings!(TabList);
+
+/// A cell to scroll to in an open notebook, sent to the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct FocusCell {
+    /// Path to the notebook file.
+    pub path: PathBuf,
+
+    /// ID of the cell, or the anchor of a Markdown heading.
+    pub cell: String,
+}
+
+crate::export_bindings!(FocusCell);
+
+impl TabList {
+    /// Open a notebook in a new tab, or switch to its tab if already open.
+    pub fn open(&mut self, path: &Path) {
+        match self.tabs.iter().position(|tab| tab.path == path) {
+            Some(index) => self.active = index,
+            None => {
+                self.tabs.push(Tab {
+                    id: Uuid::new_v4().to_string(),
+                    path: path.into(),
+                    dirty: false,
+                });
+                self.active = self.tabs.len() - 1;
+            }
+        }
+    }
+
+    /// Whether a notebook is open in one of the tabs.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.tabs.iter().any(|tab| tab.path == path)
+    }
+
+    /// Find a tab by its ID.
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Tab> {
+        self.tabs.iter_mut().find(|tab| tab.id == id)
+    }
+
+    /// Close a tab, returning it if it existed.
+    pub fn close(&mut self, index: usize) -> Option<Tab> {
+        if index >= self.tabs.len() {
+            return None;
+        }
+        let tab = self.tabs.remove(index);
+        if self.active > index || self.active >= self.tabs.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        Some(tab)
+    }
+
+    /// Close a tab by its ID, returning it if it existed.
+    pub fn close_id(&mut self, id: &str) -> Option<Tab> {
+        let index = self.tabs.iter().position(|tab| tab.id == id)?;
+        self.close(index)
+    }
+
+    /// Switch to a tab, returning whether it exists.
+    pub fn select(&mut self, index: usize) -> bool {
+        let exists = index < self.tabs.len();
+        if exists {
+            self.active = index;
+        }
+        exists
+    }
+
+    /// Switch to the next or previous tab, wrapping around.
+    pub fn cycle(&mut self, forward: bool) {
+        let len = self.tabs.len();
+        if len > 0 {
+            self.active = if forward {
+                (self.active + 1) % len
+            } else {
+                (self.active + len - 1) % len
+            };
+        }
+    }
+}
+
 /// Initializes window size, min width, and other common settings on the
 /// builder.
+///
+/// If the window is for a notebook whose geometry was saved, the window is
+/// restored to that size, and to the same position if its monitor is still
+/// connected. Other windows are cascaded on the monitor of the focused window,
+/// beside any home windows. Pages are shown at the zoom level saved in
+/// settings.
 pub fn initialize_builder<'a, R: Runtime, M: Manager<R>>(
     manager: &'a M,
     path: &str,
+    notebook: Option<&Path>,
 ) -> WebviewWindowBuilder<'a, R, M> {
     // Generate a unique window label since duplicates are not allowed.
     let label = format!("jute-window-{}", Uuid::new_v4());
 
     let url = tauri::WebviewUrl::App(path.trim_start_matches('/').into());
 
-    #[allow(unused_mut)]
     let mut builder = WebviewWindowBuilder::new(manager, &label, url)
         .title("Jute")
-        .inner_size(960.0, 800.0)
         .min_inner_size(720.0, 600.0)
         .fullscreen(false)
-        .resizable(true);
+        .resizable(true)
+        .on_page_load(apply_saved_zoom);
+
+    let saved = notebook.and_then(|file| {
+        let state = manager.state::<State>();
+        let states = state.window_states.read().unwrap();
+        states.get(file).cloned()
+    });
+    let app = manager.app_handle();
+    let (width, height) = match &saved {
+        Some(geometry) => (geometry.width, geometry.height),
+        None => (960.0, 800.0),
+    };
+    builder = builder.inner_size(width, height);
+    match saved {
+        Some(geometry) if is_on_monitor(app, &geometry) => {
+            builder = builder.position(geometry.x, geometry.y);
+        }
+        _ => {
+            if let Some((x, y)) = default_position(app, width, height) {
+                builder = builder.position(x, y);
+            }
+        }
+    }
 
     #[cfg(target_os = "macos")]
     {
@@ -35,17 +266,358 @@ pub fn initialize_builder<'a, R: Runtime, M: Manager<R>>(
     builder
 }
 
+/// Applies the zoom level saved in settings each time a page finishes loading.
+fn apply_saved_zoom<R: Runtime>(window: WebviewWindow<R>, payload: PageLoadPayload<'_>) {
+    if payload.event() == PageLoadEvent::Finished {
+        let zoom = window.state::<State>().settings.read().unwrap().zoom;
+        _ = window.set_zoom(zoom.scale());
+    }
+}
+
 /// Opens a window with the home page.
 pub fn open_home<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
-    initialize_builder(app, "/").build()
+    initialize_builder(app, "/", None).build()
+}
+
+/// Opens a window for a new, unsaved notebook, which asks for its kernel.
+pub fn open_new_notebook<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
+    initialize_builder(app, "/notebook?new=true", None).build()
+}
+
+/// Opens the kernel monitor window, or focuses it if it is already open.
+pub fn open_kernel_monitor<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
+    open_singleton(app, KERNEL_MONITOR_LABEL, "kernels", "Kernel Monitor")
+}
+
+/// Opens the keyboard shortcuts reference, or focuses it if it is already open.
+pub fn open_shortcuts<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
+    open_singleton(app, SHORTCUTS_LABEL, "shortcuts", "Keyboard Shortcuts")
+}
+
+/// Opens the scratchpad notebook, or focuses it if it is already open.
+pub fn open_scratchpad<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
+    open_singleton(
+        app,
+        SCRATCHPAD_LABEL,
+        "notebook?scratchpad=true",
+        "Scratchpad",
+    )
+}
+
+/// Opens a small utility window with a fixed label, or focuses it if it is
+/// already open.
+fn open_singleton<R: Runtime>(
+    app: &AppHandle<R>,
+    label: &str,
+    path: &str,
+    title: &str,
+) -> tauri::Result<WebviewWindow<R>> {
+    if let Some(window) = app.get_webview_window(label) {
+        window.set_focus()?;
+        return Ok(window);
+    }
+    let url = tauri::WebviewUrl::App(path.into());
+    #[allow(unused_mut)]
+    let mut builder = WebviewWindowBuilder::new(app, label, url)
+        .title(title)
+        .inner_size(720.0, 480.0)
+        .min_inner_size(480.0, 320.0)
+        .resizable(true)
+        .on_page_load(apply_saved_zoom);
+
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder.title_bar_style(tauri::TitleBarStyle::Overlay);
+        builder = builder.hidden_title(true);
+    }
+
+    builder.build()
 }
 
 /// Opens a window with the notebook file at the given path.
+///
+/// If a cell is given, either as a cell ID or a heading anchor, the notebook is
+/// scrolled to that cell once it loads.
 pub fn open_notebook_path<R: Runtime>(
     app: &AppHandle<R>,
     file: &Path,
+    cell: Option<&str>,
+) -> tauri::Result<WebviewWindow<R>> {
+    let mut params = vec![("path", file.to_string_lossy())];
+    if let Some(cell) = cell {
+        params.push(("cell", cell.into()));
+    }
+    let query = serde_urlencoded::to_string(params).context("could not encode path")?;
+    commands::warm_kernel(app, file);
+    initialize_builder(app, &format!("/notebook?{query}"), Some(file)).build()
+}
+
+/// Opens a window showing a folder as a workspace, or focuses the window that
+/// is already showing it.
+pub fn open_workspace<R: Runtime>(
+    app: &AppHandle<R>,
+    dir: &Path,
 ) -> tauri::Result<WebviewWindow<R>> {
-    let query = serde_urlencoded::to_string([("path", file.to_string_lossy())])
+    let state = app.state::<State>();
+    let existing = state
+        .workspace_windows
+        .iter()
+        .find(|entry| entry.value() == dir)
+        .map(|entry| entry.key().clone());
+    if let Some(window) = existing.and_then(|label| app.get_webview_window(&label)) {
+        window.unminimize()?;
+        window.set_focus()?;
+        return Ok(window);
+    }
+    let query = serde_urlencoded::to_string([("workspace", dir.to_string_lossy())])
         .context("could not encode path")?;
-    initialize_builder(app, &format!("/notebook?{query}")).build()
+    let window = initialize_builder(app, &format!("/notebook?{query}"), None).build()?;
+    state
+        .workspace_windows
+        .insert(window.label().into(), dir.into());
+    Ok(window)
+}
+
+/// Position for a new window, cascaded from the other windows on the monitor
+/// that the focused window is on.
+fn default_position<R: Runtime>(app: &AppHandle<R>, width: f64, height: f64) -> Option<(f64, f64)> {
+    let monitor = focused_window(app)
+        .and_then(|window| window.current_monitor().ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten())?;
+    let scale = monitor.scale_factor();
+    let position = monitor.position().to_logical::<f64>(scale);
+    let size = monitor.size().to_logical::<f64>(scale);
+    let monitor = Rect {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+
+    let state = app.state::<State>();
+    let mut windows = Vec::new();
+    let mut homes = Vec::new();
+    for (label, window) in app.webview_windows() {
+        let (Ok(position), Ok(size), Ok(scale)) = (
+            window.outer_position(),
+            window.outer_size(),
+            window.scale_factor(),
+        ) else {
+            continue;
+        };
+        let position = position.to_logical::<f64>(scale);
+        let size = size.to_logical::<f64>(scale);
+        let rect = Rect {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        };
+        windows.push(rect);
+        let is_home = label != KERNEL_MONITOR_LABEL
+            && label != SHORTCUTS_LABEL
+            && label != SCRATCHPAD_LABEL
+            && !state.notebook_windows.contains_key(&label)
+            && !state.workspace_windows.contains_key(&label);
+        if is_home {
+            homes.push(rect);
+        }
+    }
+    Some(place_window(monitor, width, height, &windows, &homes))
+}
+
+/// Whether a saved window position lies on a monitor that is connected.
+fn is_on_monitor<R: Runtime>(app: &AppHandle<R>, geometry: &WindowGeometry) -> bool {
+    let Ok(monitors) = app.available_monitors() else {
+        return false;
+    };
+    monitors.iter().any(|monitor| {
+        let scale = monitor.scale_factor();
+        let position = monitor.position().to_logical::<f64>(scale);
+        let size = monitor.size().to_logical::<f64>(scale);
+        (geometry.monitor.is_none() || monitor.name() == geometry.monitor.as_ref())
+            && (position.x..position.x + size.width).contains(&geometry.x)
+            && (position.y..position.y + size.height).contains(&geometry.y)
+    })
+}
+
+/// Record the size and position of a notebook window, returning whether they
+/// changed.
+///
+/// Windows that are maximized, minimized, or fullscreen are skipped, so that
+/// the notebook reopens with its last normal geometry.
+pub fn record_geometry<R: Runtime>(window: &Window<R>) -> tauri::Result<bool> {
+    let state = window.state::<State>();
+    let Some(file) = state
+        .notebook_windows
+        .get(window.label())
+        .and_then(|notebook| notebook.path.clone())
+    else {
+        return Ok(false);
+    };
+    if window.is_maximized()? || window.is_minimized()? || window.is_fullscreen()? {
+        return Ok(false);
+    }
+    let scale = window.scale_factor()?;
+    let position = window.outer_position()?.to_logical::<f64>(scale);
+    let size = window.inner_size()?.to_logical::<f64>(scale);
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        monitor: window
+            .current_monitor()?
+            .and_then(|monitor| monitor.name().cloned()),
+    };
+    let mut states = state.window_states.write().unwrap();
+    Ok(states.set(&file, geometry))
+}
+
+/// Opens a notebook file as a tab in an existing window.
+pub fn open_notebook_in_window<R: Runtime>(
+    window: &WebviewWindow<R>,
+    file: &Path,
+) -> tauri::Result<()> {
+    let is_new = update_tabs(window, |tabs| {
+        let is_new = !tabs.contains(file);
+        tabs.open(file);
+        is_new
+    })?;
+    if is_new {
+        commands::warm_kernel(window.app_handle(), file);
+    }
+    window.set_focus()
+}
+
+/// Opens a notebook file as a tab of the focused window if it has tabs, or
+/// otherwise in a new window.
+pub fn open_notebook<R: Runtime>(app: &AppHandle<R>, file: &Path) -> tauri::Result<()> {
+    if let Some(window) = focused_window(app) {
+        let state = app.state::<State>();
+        let has_tabs = state
+            .notebook_windows
+            .get(window.label())
+            .is_some_and(|notebook| !notebook.tabs.tabs.is_empty());
+        if has_tabs {
+            return open_notebook_in_window(&window, file);
+        }
+    }
+    open_notebook_path(app, file, None)?;
+    Ok(())
+}
+
+/// Scrolls a notebook to a cell, focusing the window that already has it open
+/// or otherwise opening a new window.
+pub fn focus_notebook_cell<R: Runtime>(
+    app: &AppHandle<R>,
+    file: &Path,
+    cell: &str,
+) -> tauri::Result<()> {
+    // Find a window showing the notebook, and whether it is one of its tabs.
+    let found = app
+        .state::<State>()
+        .notebook_windows
+        .iter()
+        .find_map(|entry| {
+            let in_tabs = entry.tabs.contains(file);
+            (in_tabs || entry.path.as_deref() == Some(file)).then(|| (entry.key().clone(), in_tabs))
+        });
+    let Some((window, in_tabs)) =
+        found.and_then(|(label, in_tabs)| Some((app.get_webview_window(&label)?, in_tabs)))
+    else {
+        open_notebook_path(app, file, Some(cell))?;
+        return Ok(());
+    };
+
+    if in_tabs {
+        update_tabs(&window, |tabs| tabs.open(file))?;
+    }
+    window.set_focus()?;
+    let payload = FocusCell {
+        path: file.into(),
+        cell: cell.into(),
+    };
+    window.emit_to(
+        EventTarget::webview_window(window.label()),
+        FOCUS_CELL_EVENT,
+        payload,
+    )
+}
+
+/// Change the tabs of a window, then send the new tabs to its frontend.
+pub fn update_tabs<R: Runtime, T>(
+    window: &WebviewWindow<R>,
+    f: impl FnOnce(&mut TabList) -> T,
+) -> tauri::Result<T> {
+    let (result, tabs) = {
+        let state = window.state::<State>();
+        let mut notebook = state
+            .notebook_windows
+            .entry(window.label().into())
+            .or_default();
+        (f(&mut notebook.tabs), notebook.tabs.clone())
+    };
+    window.emit_to(
+        EventTarget::webview_window(window.label()),
+        TABS_EVENT,
+        tabs,
+    )?;
+    crate::menu::update_menu_state(window.app_handle())?;
+    Ok(result)
+}
+
+/// Returns the window that currently has focus, if any.
+pub fn focused_window<R: Runtime>(app: &AppHandle<R>) -> Option<WebviewWindow<R>> {
+    app.webview_windows()
+        .into_values()
+        .find(|window| window.is_focused().unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_close_and_cycle_tabs() {
+        let mut tabs = TabList::default();
+        tabs.open(Path::new("a.ipynb"));
+        tabs.open(Path::new("b.ipynb"));
+        tabs.open(Path::new("c.ipynb"));
+        assert_eq!(tabs.active, 2);
+
+        tabs.open(Path::new("a.ipynb"));
+        assert_eq!((tabs.tabs.len(), tabs.active), (3, 0));
+
+        tabs.cycle(false);
+        assert_eq!(tabs.active, 2);
+        tabs.cycle(true);
+        assert_eq!(tabs.active, 0);
+
+        assert!(tabs.select(1));
+        assert!(!tabs.select(3));
+        let closed = tabs.close(0).unwrap();
+        assert_eq!(closed.path, Path::new("a.ipynb"));
+        assert_eq!(tabs.active, 0);
+
+        // Tabs keep their ID when their notebook is saved under a new path.
+        let id = tabs.tabs[1].id.clone();
+        let tab = tabs.get_mut(&id).unwrap();
+        tab.path = "d.ipynb".into();
+        tab.dirty = true;
+        assert!(tabs.contains(Path::new("d.ipynb")));
+        assert!(!tabs.contains(Path::new("c.ipynb")));
+
+        let window = NotebookWindow {
+            tabs: tabs.clone(),
+            ..Default::default()
+        };
+        assert_eq!(window.unsaved(), [Some(PathBuf::from("d.ipynb"))]);
+
+        assert_eq!(tabs.close_id(&id).unwrap().path, Path::new("d.ipynb"));
+        assert_eq!(tabs.close(1), None);
+        assert_eq!(tabs.tabs.len(), 1);
+        assert_eq!(tabs.tabs[0].path, Path::new("b.ipynb"));
+        assert_eq!(tabs.active, 0);
+    }
 }
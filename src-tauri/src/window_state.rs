@@ -0,0 +1,241 @@
+//! Window geometry remembered for each notebook, persisted as JSON in the data
+//! dir.
+//!
+//! When a notebook window is moved or resized, its geometry is recorded here
+//! under the notebook's path. Reopening the notebook later restores the window
+//! where the user left it. Other windows are placed by [`place_window`], which
+//! cascades them so that they don't open exactly on top of each other.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{store, Error};
+
+/// The name of the window state file within the app data directory.
+pub const WINDOW_STATE_FILE: &str = "window-state.json";
+
+/// Size and position of a window, in logical pixels.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WindowGeometry {
+    /// Horizontal position of the window's top-left corner.
+    pub x: f64,
+
+    /// Vertical position of the window's top-left corner.
+    pub y: f64,
+
+    /// Width of the window's content area.
+    pub width: f64,
+
+    /// Height of the window's content area.
+    pub height: f64,
+
+    /// Name of the monitor the window was on, if known.
+    pub monitor: Option<String>,
+}
+
+/// A rectangle on screen, in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    /// Horizontal position of the top-left corner.
+    pub x: f64,
+
+    /// Vertical position of the top-left corner.
+    pub y: f64,
+
+    /// Width of the rectangle.
+    pub width: f64,
+
+    /// Height of the rectangle.
+    pub height: f64,
+}
+
+impl Rect {
+    /// Whether this rectangle overlaps another one.
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Whether this rectangle lies entirely within another one.
+    fn is_within(&self, other: &Rect) -> bool {
+        self.x >= other.x
+            && self.y >= other.y
+            && self.x + self.width <= other.x + other.width
+            && self.y + self.height <= other.y + other.height
+    }
+}
+
+/// Distance between the corners of cascaded windows.
+const CASCADE_OFFSET: f64 = 28.0;
+
+/// Choose the position of a new window of the given size on a monitor.
+///
+/// The window is centered on the monitor, unless that would cover a window in
+/// `avoid`, in which case it is placed beside one if there is room. If another
+/// window in `windows` already has its corner there, the new window cascades
+/// down and to the right, wrapping around to the top of the monitor.
+pub fn place_window(
+    monitor: Rect,
+    width: f64,
+    height: f64,
+    windows: &[Rect],
+    avoid: &[Rect],
+) -> (f64, f64) {
+    let at = |x: f64, y: f64| Rect {
+        x,
+        y,
+        width,
+        height,
+    };
+    let centered = at(
+        monitor.x + ((monitor.width - width) / 2.0).max(0.0),
+        monitor.y + ((monitor.height - height) / 2.0).max(0.0),
+    );
+    let is_free = |rect: &Rect| {
+        windows.iter().all(|other| {
+            (rect.x - other.x).abs() >= CASCADE_OFFSET / 2.0
+                || (rect.y - other.y).abs() >= CASCADE_OFFSET / 2.0
+        })
+    };
+
+    // Prefer spots that keep windows in `avoid` visible.
+    let mut preferred = vec![centered];
+    for rect in avoid {
+        preferred.push(at(rect.x + rect.width, centered.y));
+        preferred.push(at(rect.x - width, centered.y));
+    }
+    let found = preferred.into_iter().find(|rect| {
+        rect.is_within(&monitor) && is_free(rect) && !avoid.iter().any(|a| rect.intersects(a))
+    });
+    if let Some(rect) = found {
+        return (rect.x, rect.y);
+    }
+
+    // Otherwise, cascade from the center until there is a free spot.
+    let mut rect = centered;
+    let mut wraps = 0.0;
+    for _ in 0..100 {
+        if is_free(&rect) {
+            return (rect.x, rect.y);
+        }
+        rect.x += CASCADE_OFFSET;
+        rect.y += CASCADE_OFFSET;
+        if !rect.is_within(&monitor) {
+            wraps += 1.0;
+            rect.x = monitor.x + wraps * CASCADE_OFFSET;
+            rect.y = monitor.y;
+        }
+    }
+    (centered.x, centered.y)
+}
+
+/// Saved window geometry of notebooks, keyed by path.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct WindowStates {
+    geometry: BTreeMap<PathBuf, WindowGeometry>,
+}
+
+impl WindowStates {
+    /// Load window states from a file.
+    pub async fn load(path: &Path) -> Self {
+        store::load_json(path).await
+    }
+
+    /// Write window states to a file.
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        store::save_json(path, self).await
+    }
+
+    /// Get the saved geometry of a notebook's window.
+    pub fn get(&self, notebook: &Path) -> Option<&WindowGeometry> {
+        self.geometry.get(notebook)
+    }
+
+    /// Record the geometry of a notebook's window, returning whether it
+    /// changed.
+    pub fn set(&mut self, notebook: &Path, geometry: WindowGeometry) -> bool {
+        let changed = self.geometry.get(notebook) != Some(&geometry);
+        self.geometry.insert(notebook.into(), geometry);
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn save_and_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join(WINDOW_STATE_FILE);
+        let geometry = WindowGeometry {
+            x: 100.0,
+            y: 50.0,
+            width: 1200.0,
+            height: 900.0,
+            monitor: Some("Built-in Retina Display".into()),
+        };
+
+        let mut states = WindowStates::default();
+        assert!(states.set(Path::new("/home/me/a.ipynb"), geometry.clone()));
+        assert!(!states.set(Path::new("/home/me/a.ipynb"), geometry.clone()));
+        states.save(&path).await.unwrap();
+
+        let loaded = WindowStates::load(&path).await;
+        assert_eq!(loaded.get(Path::new("/home/me/a.ipynb")), Some(&geometry));
+        assert_eq!(loaded.get(Path::new("/home/me/b.ipynb")), None);
+    }
+
+    #[test]
+    fn cascade_windows() {
+        let monitor = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 1600.0,
+            height: 1000.0,
+        };
+        let window = |(x, y): (f64, f64)| Rect {
+            x,
+            y,
+            width: 800.0,
+            height: 600.0,
+        };
+
+        let first = place_window(monitor, 800.0, 600.0, &[], &[]);
+        assert_eq!(first, (400.0, 200.0));
+        let second = place_window(monitor, 800.0, 600.0, &[window(first)], &[]);
+        assert_eq!(second, (428.0, 228.0));
+        let third = place_window(monitor, 800.0, 600.0, &[window(first), window(second)], &[]);
+        assert_eq!(third, (456.0, 256.0));
+
+        // A home window in the center is kept visible.
+        let home = Rect {
+            x: 500.0,
+            y: 200.0,
+            width: 600.0,
+            height: 600.0,
+        };
+        let beside = place_window(monitor, 400.0, 600.0, &[home], &[home]);
+        assert_eq!(beside, (1100.0, 200.0));
+
+        // Windows that fit nowhere else cascade, wrapping at the monitor edge.
+        let big = place_window(monitor, 1500.0, 900.0, &[window((50.0, 50.0))], &[]);
+        assert_eq!(big, (78.0, 78.0));
+        let wrapped = place_window(
+            monitor,
+            1500.0,
+            900.0,
+            &[window((50.0, 50.0)), window((78.0, 78.0))],
+            &[],
+        );
+        assert_eq!(wrapped, (28.0, 0.0));
+    }
+}
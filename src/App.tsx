@@ -2,8 +2,10 @@ import { ErrorBoundary, type FallbackProps } from "react-error-boundary";
 import { Route, Switch } from "wouter";
 
 import HomePage from "@/pages/HomePage";
+import KernelMonitorPage from "@/pages/KernelMonitorPage";
 import NotFoundPage from "@/pages/NotFoundPage";
 import NotebookPage from "@/pages/NotebookPage";
+import ShortcutsPage from "@/pages/ShortcutsPage";
 
 import { UnhandledError } from "./ui/shared/UnhandledError";
 
@@ -21,6 +23,8 @@ export default function App() {
       <Switch>
         <Route path="/" component={HomePage} />
         <Route path="/notebook" component={NotebookPage} />
+        <Route path="/kernels" component={KernelMonitorPage} />
+        <Route path="/shortcuts" component={ShortcutsPage} />
 
         <Route component={NotFoundPage} />
       </Switch>
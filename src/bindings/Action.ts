@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An action in the app that can be bound to a keyboard shortcut.
+ */
+export type Action =
+  | "new_notebook"
+  | "new_window"
+  | "open_file"
+  | "open_folder"
+  | "save"
+  | "save_as"
+  | "next_tab"
+  | "previous_tab"
+  | "command_palette"
+  | "zoom_in"
+  | "zoom_out"
+  | "reset_zoom"
+  | "quit"
+  | "run_cell"
+  | "run_cell_in_place"
+  | "run_all"
+  | "run_all_above"
+  | "run_all_below"
+  | "run_selected_text";
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An attachment of a cell, as listed for the user.
+ */
+export type AttachmentInfo = {
+  /**
+   * Name of the attachment, as in `attachment:<name>` URLs.
+   */
+  name: string;
+  /**
+   * MIME type of the attachment.
+   */
+  mime_type: string;
+  /**
+   * Size of the attachment's data, in bytes.
+   */
+  size: number;
+  /**
+   * Whether the source of the cell mentions the attachment. Attachments
+   * that it doesn't are removed on save.
+   */
+  referenced: boolean;
+};
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A code cell to run as part of a batch.
+ */
+export type BatchCell = {
+  /**
+   * ID of the cell, used to track its outputs and place in the queue.
+   */
+  cell_id: string;
+  /**
+   * Source code of the cell.
+   */
+  source: string;
+};
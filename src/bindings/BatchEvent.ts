@@ -0,0 +1,82 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ClearOutput } from "./ClearOutput";
+import type { DisplayData } from "./DisplayData";
+import type { ErrorReply } from "./ErrorReply";
+import type { ExecuteResult } from "./ExecuteResult";
+import type { ExecuteStatus } from "./ExecuteStatus";
+import type { InputPrompt } from "./InputPrompt";
+import type { JsonValue } from "./serde_json/JsonValue";
+import type { KernelMessageType } from "./KernelMessageType";
+import type { OutputUpdate } from "./OutputUpdate";
+import type { TruncatedOutput } from "./TruncatedOutput";
+
+/**
+ * An event from running one of a batch of cells.
+ */
+export type BatchEvent = {
+  /**
+   * Index of the cell in the batch.
+   */
+  cell_index: number;
+} & (
+  | { event: "stdout"; data: string }
+  | { event: "stderr"; data: string }
+  | { event: "execute_result"; data: ExecuteResult }
+  | { event: "display_data"; data: DisplayData }
+  | { event: "update_display_data"; data: DisplayData }
+  | { event: "clear_output"; data: ClearOutput }
+  | { event: "error"; data: ErrorReply }
+  | { event: "disconnect"; data: string }
+  | { event: "aborted" }
+  | { event: "output_dropped"; data: number }
+  | { event: "input_request"; data: InputPrompt }
+  | { event: "password_request"; data: InputPrompt }
+  | { event: "update_output"; data: OutputUpdate }
+  | {
+      event: "started";
+      data: {
+        /**
+         * The execution count of the cell.
+         */
+        execution_count: number | null;
+        /**
+         * When the kernel became busy, by its own clock, as a Unix time in
+         * milliseconds.
+         */
+        timestamp: number;
+      };
+    }
+  | { event: "execution_count"; data: number }
+  | {
+      event: "finished";
+      data: {
+        /**
+         * Wall time from when the kernel became busy until it was idle
+         * again, in milliseconds.
+         */
+        duration: number;
+        /**
+         * How the cell finished, from the kernel's reply.
+         */
+        status: ExecuteStatus;
+      };
+    }
+  | { event: "output_truncated"; data: TruncatedOutput }
+  | {
+      event: "custom";
+      data: {
+        /**
+         * The type of the message.
+         */
+        msg_type: KernelMessageType;
+        /**
+         * Content of the message.
+         */
+        content: JsonValue;
+        /**
+         * Binary buffers of the message, encoded as base64.
+         */
+        buffers: Array<string>;
+      };
+    }
+);
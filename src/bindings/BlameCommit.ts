@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A commit shown in blame information.
+ */
+export type BlameCommit = {
+  /**
+   * Hash of the commit.
+   */
+  hash: string;
+  /**
+   * Name of the author.
+   */
+  author: string;
+  /**
+   * Time the commit was authored, in seconds since the Unix epoch.
+   */
+  time: number;
+  /**
+   * First line of the commit message.
+   */
+  summary: string;
+};
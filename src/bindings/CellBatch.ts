@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BatchCell } from "./BatchCell";
+
+/**
+ * Cells to run as a batch, either given directly or read from a notebook.
+ */
+export type CellBatch = { path: string } | { cells: Array<BatchCell> };
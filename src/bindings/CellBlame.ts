@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BlameCommit } from "./BlameCommit";
+
+/**
+ * The commit that last changed a cell, found with `git blame`.
+ */
+export type CellBlame = {
+  /**
+   * Position of the cell in the notebook.
+   */
+  index: number;
+  /**
+   * Identifier of the cell, if it has one.
+   */
+  cell_id: string | null;
+  /**
+   * Most recent commit that changed a line of the cell, or `null` if the
+   * cell has uncommitted changes.
+   */
+  commit: BlameCommit | null;
+};
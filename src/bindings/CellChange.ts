@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How a cell changed between two versions of a notebook.
+ */
+export type CellChange = "added" | "removed" | "modified" | "unchanged";
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A cell whose source was changed differently on each side of a merge.
+ */
+export type CellConflict = {
+  /**
+   * Identifier of the cell in the merged notebook.
+   */
+  cell_id: string;
+  /**
+   * Source of the cell in the common ancestor, if known and in it.
+   */
+  base: string | null;
+  /**
+   * Source of the cell on our side, or `None` if we deleted it.
+   */
+  ours: string | null;
+  /**
+   * Source of the cell on their side, or `None` if they deleted it.
+   */
+  theirs: string | null;
+};
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Dependencies of one code cell on the cells above it.
+ */
+export type CellDependencies = {
+  /**
+   * ID of the cell.
+   */
+  id: string;
+  /**
+   * Global names defined by the cell.
+   */
+  defines: Array<string>;
+  /**
+   * Names read by the cell, including builtins and names defined nowhere.
+   */
+  uses: Array<string>;
+  /**
+   * IDs of the cells that define names read by this cell, in notebook order.
+   */
+  depends_on: Array<string>;
+};
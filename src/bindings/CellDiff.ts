@@ -0,0 +1,47 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellChange } from "./CellChange";
+import type { DiffHunk } from "./DiffHunk";
+import type { OutputDiff } from "./OutputDiff";
+
+/**
+ * Difference in one cell between two versions of a notebook.
+ */
+export type CellDiff = {
+  /**
+   * Identifier of the cell, from the new version if it is in both.
+   */
+  id: string | null;
+  /**
+   * Type of the cell, like `"code"` or `"markdown"`.
+   */
+  cell_type: string;
+  /**
+   * How the cell changed.
+   */
+  change: CellChange;
+  /**
+   * Source of the cell in the old version, if it is in it.
+   */
+  old_source: string | null;
+  /**
+   * Source of the cell in the new version, if it is in it.
+   */
+  new_source: string | null;
+  /**
+   * Whether the outputs of a code cell changed, ignoring execution counts.
+   */
+  outputs_changed: boolean;
+  /**
+   * Whether the cell is in both versions, but moved relative to the cells
+   * around it. Moves are only found between cells matched by ID.
+   */
+  moved: boolean;
+  /**
+   * Changed lines of the source, if it is in both versions.
+   */
+  hunks: Array<DiffHunk>;
+  /**
+   * Changed outputs of a code cell, compared by position.
+   */
+  output_changes: Array<OutputDiff>;
+};
@@ -1,6 +1,23 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SlideshowMetadata } from "./SlideshowMetadata";
 
 /**
  * Metadata for a cell.
  */
-export type CellMetadata = {};
+export type CellMetadata = {
+  /**
+   * Role of the cell in a slideshow, as used by nbconvert and RISE.
+   */
+  slideshow?: SlideshowMetadata;
+  /**
+   * Whether the outputs of a code cell are collapsed.
+   */
+  collapsed?: boolean;
+  /**
+   * Whether long outputs of a code cell are shown in a scrolling area.
+   *
+   * Jupyter also allows `"auto"`, which is read as a missing value since
+   * that is the default.
+   */
+  scrolled?: boolean;
+};
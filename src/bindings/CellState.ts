@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * State of a cell submitted to an [`ExecutionQueue`].
+ */
+export type CellState = "queued" | "running" | "ok" | "error" | "aborted";
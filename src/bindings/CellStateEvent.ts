@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellState } from "./CellState";
+
+/**
+ * Payload of [`CELL_STATE_EVENT`].
+ */
+export type CellStateEvent = {
+  /**
+   * ID of the kernel running the cell.
+   */
+  kernel_id: string;
+  /**
+   * ID of the cell.
+   */
+  cell_id: string;
+  /**
+   * The new state of the cell.
+   */
+  state: CellState;
+};
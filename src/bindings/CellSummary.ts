@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A cell in a notebook being loaded, without its contents.
+ */
+export type CellSummary = {
+  /**
+   * Identifier of the cell.
+   */
+  id: string;
+  /**
+   * Type of the cell, as in the `cell_type` field.
+   */
+  cell_type: string;
+  /**
+   * Number of outputs of a code cell.
+   */
+  output_count: number;
+  /**
+   * Approximate size of the cell as JSON, in bytes, to choose how many to
+   * fetch at once.
+   */
+  size: number;
+};
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A saved copy of a notebook.
+ */
+export type Checkpoint = {
+  /**
+   * Identifier of the checkpoint, unique for the notebook.
+   */
+  id: string;
+  /**
+   * When the checkpoint was created.
+   */
+  created: string;
+};
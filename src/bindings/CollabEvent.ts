@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Cell } from "./Cell";
+import type { NotebookRoot } from "./NotebookRoot";
+import type { Peer } from "./Peer";
+import type { TextChange } from "./TextChange";
+
+/**
+ * Events sent to the frontend while collaborating.
+ */
+export type CollabEvent =
+  | { event: "notebook"; data: NotebookRoot }
+  | {
+      event: "source";
+      data: {
+        /**
+         * ID of the edited cell.
+         */
+        cell_id: string;
+        /**
+         * The edit to apply to the cell's source.
+         */
+        change: TextChange;
+      };
+    }
+  | { event: "cell"; data: Cell }
+  | { event: "peers"; data: Array<Peer> }
+  | { event: "closed"; data: string };
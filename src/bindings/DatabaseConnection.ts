@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DatabaseKind } from "./DatabaseKind";
+
+/**
+ * A database connection saved by the user.
+ */
+export type DatabaseConnection = {
+  /**
+   * Unique identifier of the connection.
+   */
+  id: string;
+  /**
+   * Name of the connection, used in `%%sql` headers.
+   */
+  name: string;
+  /**
+   * Kind of database.
+   */
+  kind: DatabaseKind;
+  /**
+   * URL of a database server without the password, like
+   * `postgres://user@localhost/db`, or the path of a SQLite or DuckDB file.
+   */
+  url: string;
+};
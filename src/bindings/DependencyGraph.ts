@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellDependencies } from "./CellDependencies";
+
+/**
+ * Dependency graph of the code cells in a notebook.
+ */
+export type DependencyGraph = {
+  /**
+   * Code cells with IDs, in notebook order.
+   */
+  cells: Array<CellDependencies>;
+};
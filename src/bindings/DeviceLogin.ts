@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A pending device flow login, shown to the user while waiting for approval.
+ */
+export type DeviceLogin = {
+  /**
+   * Code identifying this login to GitHub, used when polling.
+   */
+  device_code: string;
+  /**
+   * Code for the user to enter on GitHub.
+   */
+  user_code: string;
+  /**
+   * Page where the user enters the code.
+   */
+  verification_uri: string;
+  /**
+   * Seconds until the codes expire.
+   */
+  expires_in: number;
+  /**
+   * Minimum seconds to wait between polls.
+   */
+  interval: number;
+};
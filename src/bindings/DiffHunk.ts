@@ -0,0 +1,29 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DiffLine } from "./DiffLine";
+
+/**
+ * Changed lines in the source of a cell, with a few unchanged lines around
+ * them, like a hunk of a unified diff.
+ */
+export type DiffHunk = {
+  /**
+   * First line of the hunk in the old source, counting from 1.
+   */
+  old_start: number;
+  /**
+   * Number of lines of the old source in the hunk.
+   */
+  old_lines: number;
+  /**
+   * First line of the hunk in the new source, counting from 1.
+   */
+  new_start: number;
+  /**
+   * Number of lines of the new source in the hunk.
+   */
+  new_lines: number;
+  /**
+   * Lines of the hunk, in order.
+   */
+  lines: Array<DiffLine>;
+};
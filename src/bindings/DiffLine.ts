@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LineChange } from "./LineChange";
+
+/**
+ * A line in a [`DiffHunk`].
+ */
+export type DiffLine = {
+  /**
+   * Whether the line was added, removed, or is context.
+   */
+  change: LineChange;
+  /**
+   * Text of the line, without its newline.
+   */
+  text: string;
+};
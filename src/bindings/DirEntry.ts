@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An entry in a folder, returned by [`list_directory`].
+ */
+export type DirEntry = {
+  /**
+   * File name of the entry.
+   */
+  name: string;
+  /**
+   * Full path to the entry.
+   */
+  path: string;
+  /**
+   * Whether the entry is a folder.
+   */
+  is_dir: boolean;
+};
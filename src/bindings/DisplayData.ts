@@ -18,4 +18,14 @@ export type DisplayData = {
    * Any information not to be persisted to a notebook.
    */
   transient: DisplayDataTransient | null;
+  /**
+   * Metadata of the kernel message that carried the data, separate from the
+   * metadata of the data itself. This is set when running a cell.
+   */
+  message_metadata: JsonValue;
+  /**
+   * Binary buffers of the kernel message that carried the data, encoded as
+   * base64. This is set when running a cell.
+   */
+  buffers: Array<string>;
 };
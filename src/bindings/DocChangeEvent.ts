@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NotebookOp } from "./NotebookOp";
+
+/**
+ * Payload of [`DOC_CHANGE_EVENT`].
+ */
+export type DocChangeEvent = {
+  /**
+   * ID of the document that changed.
+   */
+  doc_id: string;
+  /**
+   * Version of the document after the change.
+   */
+  version: number;
+  /**
+   * Operations that were applied, in order.
+   */
+  ops: Array<NotebookOp>;
+  /**
+   * ID of the notebook that sent the operations, which already has them.
+   * Each tab has its own, so tabs in a window showing the same document
+   * stay in sync too.
+   */
+  origin: string;
+};
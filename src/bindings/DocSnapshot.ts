@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NotebookRoot } from "./NotebookRoot";
+
+/**
+ * The contents of a document, sent to windows when they open it.
+ */
+export type DocSnapshot = {
+  /**
+   * ID of the document.
+   */
+  doc_id: string;
+  /**
+   * Version of the document, incremented by each operation.
+   */
+  version: number;
+  /**
+   * The notebook.
+   */
+  notebook: NotebookRoot;
+};
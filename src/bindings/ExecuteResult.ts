@@ -20,4 +20,14 @@ export type ExecuteResult = {
    * Metadata associated with the data, can be empty.
    */
   metadata: { [key in string]?: JsonValue };
+  /**
+   * Metadata of the kernel message that carried the result, separate from
+   * the metadata of the data itself. This is set when running a cell.
+   */
+  message_metadata: JsonValue;
+  /**
+   * Binary buffers of the kernel message that carried the data, encoded as
+   * base64. This is set when running a cell.
+   */
+  buffers: Array<string>;
 };
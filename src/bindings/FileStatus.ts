@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileChange } from "./FileChange";
+
+/**
+ * A file with changes in a repository.
+ */
+export type FileStatus = {
+  /**
+   * Path of the file.
+   */
+  path: string;
+  /**
+   * Path the file was renamed or copied from, if it was.
+   */
+  original_path: string | null;
+  /**
+   * Change staged in the index, to be committed.
+   */
+  staged: FileChange | null;
+  /**
+   * Change in the working tree that isn't staged.
+   */
+  unstaged: FileChange | null;
+};
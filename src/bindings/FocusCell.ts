@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A cell to scroll to in an open notebook, sent to the frontend.
+ */
+export type FocusCell = {
+  /**
+   * Path to the notebook file.
+   */
+  path: string;
+  /**
+   * ID of the cell, or the anchor of a Markdown heading.
+   */
+  cell: string;
+};
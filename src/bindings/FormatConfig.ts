@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Code formatting settings for a project.
+ */
+export type FormatConfig = {
+  /**
+   * Maximum line length for formatted code.
+   */
+  line_length: number | null;
+  /**
+   * Number of spaces per indentation level.
+   */
+  indent_width: number | null;
+};
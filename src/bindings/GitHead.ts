@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The commit and branch checked out in a repository.
+ */
+export type GitHead = {
+  /**
+   * Name of the current branch, or `null` if the head is detached.
+   */
+  branch: string | null;
+  /**
+   * Hash of the current commit, or `null` if there are no commits yet.
+   */
+  commit: string | null;
+};
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileStatus } from "./FileStatus";
+import type { GitHead } from "./GitHead";
+
+/**
+ * Status of a repository, or of some paths in it.
+ */
+export type GitStatus = {
+  /**
+   * Root of the repository's working tree.
+   */
+  root: string;
+  /**
+   * The commit and branch checked out.
+   */
+  head: GitHead;
+  /**
+   * Files with staged or unstaged changes, including untracked files.
+   */
+  files: Array<FileStatus>;
+};
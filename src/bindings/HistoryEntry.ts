@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HistoryInput } from "./HistoryInput";
+
+/**
+ * An entry in the kernel's history, as a tuple of session number, line number
+ * and input.
+ */
+export type HistoryEntry = [number, number, HistoryInput];
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The input of a history entry, with its output if it was requested.
+ */
+export type HistoryInput = string | [string, string | null];
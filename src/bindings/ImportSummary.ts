@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Summary of what was imported, returned to the frontend.
+ */
+export type ImportSummary = {
+  /**
+   * Number of servers added to the registry.
+   */
+  servers: number;
+  /**
+   * Default kernel that was imported, if any.
+   */
+  default_kernel: string | null;
+};
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A request from the kernel for input while running a cell.
+ */
+export type InputPrompt = {
+  /**
+   * ID of the request, to send with the reply.
+   */
+  request_id: string;
+  /**
+   * Text to show the user.
+   */
+  prompt: string;
+};
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Represents a reply to an is_complete request.
+ *
+ * Unlike other replies, the status field holds the answer, so this is not
+ * wrapped in [`Reply`].
+ */
+export type IsCompleteReply =
+  | { status: "complete" }
+  | {
+      status: "incomplete";
+      /**
+       * Characters to indent the next line with, as a hint to the frontend.
+       */
+      indent: string;
+    }
+  | { status: "invalid" }
+  | { status: "unknown" };
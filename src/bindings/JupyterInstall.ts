@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An existing Jupyter install found on the system.
+ */
+export type JupyterInstall = {
+  /**
+   * Path to the `jupyter` command, if it is on the `PATH`.
+   */
+  command: string | null;
+  /**
+   * Jupyter config directory, if it exists.
+   */
+  config_dir: string | null;
+  /**
+   * Kernels that are installed, as `(name, display name)` pairs.
+   */
+  kernels: Array<[string, string]>;
+};
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A Jupyter server saved in the registry.
+ */
+export type JupyterServer = {
+  /**
+   * Unique identifier of the server.
+   */
+  id: string;
+  /**
+   * Human-readable name of the server.
+   */
+  name: string;
+  /**
+   * Base URL of the server, like `http://localhost:8888/`.
+   */
+  url: string;
+};
@@ -0,0 +1,37 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KernelLanguageInfo } from "./KernelLanguageInfo";
+
+/**
+ * Represents a reply to a kernel_info request, providing details about the
+ * kernel.
+ */
+export type KernelInfoReply = {
+  /**
+   * Version of the messaging protocol used by the kernel.
+   */
+  protocol_version: string;
+  /**
+   * The name of the kernel implementation (e.g., 'ipython').
+   */
+  implementation: string;
+  /**
+   * The version number of the kernel's implementation.
+   */
+  implementation_version: string;
+  /**
+   * Detailed information about the programming language used by the kernel.
+   */
+  language_info: KernelLanguageInfo;
+  /**
+   * A banner of information about the kernel, dispalyed in console.
+   */
+  banner: string;
+  /**
+   * Indicates if the kernel supports debugging.
+   */
+  debugger: boolean;
+  /**
+   * Optional features supported by the kernel, like "kernel subshells".
+   */
+  supported_features: Array<string>;
+};
@@ -0,0 +1,41 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * Detailed information about the programming language of the kernel.
+ *
+ * Renamed in bindings, since notebook metadata has its own `LanguageInfo`.
+ */
+export type KernelLanguageInfo = {
+  /**
+   * Name of the programming language.
+   */
+  name: string;
+  /**
+   * Version number of the language.
+   */
+  version: string;
+  /**
+   * MIME type for script files in this language.
+   */
+  mimetype: string;
+  /**
+   * File extension for script files in this language.
+   */
+  file_extension: string;
+  /**
+   * Nbconvert exporter, if notebooks should be exported differently than the
+   * general script.
+   */
+  nbconvert_exporter: string | null;
+  /**
+   * CodeMirror mode for highlighting the language, either a name or an
+   * object with options, if it differs from the name of the language.
+   */
+  codemirror_mode: JsonValue | null;
+  /**
+   * Pygments lexer for highlighting the language, if it differs from the
+   * name of the language.
+   */
+  pygments_lexer: string | null;
+};
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Payload of [`KERNEL_LIVENESS_EVENT`].
+ */
+export type KernelLiveness = {
+  /**
+   * ID of the kernel.
+   */
+  kernel_id: string;
+  /**
+   * Whether the kernel answered its last heartbeat.
+   */
+  alive: boolean;
+};
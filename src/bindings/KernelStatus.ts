@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Whether a kernel process is still running.
+ */
+export type KernelStatus = "running" | "unresponsive" | "exited";
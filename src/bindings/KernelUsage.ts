@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Resources used by a kernel process, sent by [`watch_kernel_usage`].
+ */
+export type KernelUsage = {
+  /**
+   * ID of the kernel.
+   */
+  kernel_id: string;
+  /**
+   * CPU usage as a percentage of one core, which may be over 100.
+   */
+  cpu_percent: number;
+  /**
+   * Resident memory in bytes.
+   */
+  memory_bytes: number;
+  /**
+   * Seconds since the kernel was started.
+   */
+  uptime_secs: number;
+};
@@ -0,0 +1,33 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Action } from "./Action";
+import type { Category } from "./Category";
+
+/**
+ * The effective binding for an action, returned to the frontend.
+ */
+export type KeyBinding = {
+  /**
+   * The action being bound.
+   */
+  action: Action;
+  /**
+   * Human-readable name of the action.
+   */
+  label: string;
+  /**
+   * Category that this action is grouped under.
+   */
+  category: Category;
+  /**
+   * Current keyboard shortcut, or null if unbound.
+   */
+  accelerator: string | null;
+  /**
+   * Default keyboard shortcut, or null if unbound by default.
+   */
+  default_accelerator: string | null;
+  /**
+   * Other actions that are bound to the same shortcut.
+   */
+  conflicts: Array<Action>;
+};
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Kind of a line in a [`DiffHunk`].
+ */
+export type LineChange = "context" | "added" | "removed";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Verbosity of log messages, from least to most verbose.
+ */
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
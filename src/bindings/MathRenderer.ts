@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Engine used to render LaTeX in Markdown cells and outputs.
+ */
+export type MathRenderer = "katex" | "mathjax";
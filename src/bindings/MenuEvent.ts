@@ -0,0 +1,42 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The events that can be emitted as menu IDs.
+ */
+export type MenuEvent =
+  | "NewNotebook"
+  | "NewWindow"
+  | "OpenFile"
+  | "OpenFolder"
+  | "Save"
+  | "SaveAs"
+  | "RevertToCheckpoint"
+  | "ExportSlides"
+  | "ShareOnNetwork"
+  | "RevealNotebook"
+  | "RevealExport"
+  | "OpenTerminal"
+  | "RunCell"
+  | "RunAll"
+  | "RunAllAbove"
+  | "RunAllBelow"
+  | "RunSelectedText"
+  | "InterruptKernel"
+  | "RestartKernel"
+  | "RestartKernelRunAll"
+  | "ShutdownKernel"
+  | "ChangeKernel"
+  | "ZoomIn"
+  | "ZoomOut"
+  | "ResetZoom"
+  | "CollapseAllOutputs"
+  | "ExpandAllOutputs"
+  | "ToggleOutputScrolling"
+  | "KernelMonitor"
+  | "NextTab"
+  | "PreviousTab"
+  | "KeyboardShortcuts"
+  | "ReportIssue"
+  | "CheckForUpdates"
+  | "InstallUpdate"
+  | "Quit";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Whether a message was sent to the kernel or received from it.
+ */
+export type MessageDirection = "sent" | "received";
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * An image to be added to a cell's attachments.
+ */
+export type NewAttachment = {
+  /**
+   * Name of the attachment, unique in the cell.
+   */
+  name: string;
+  /**
+   * MIME bundle to store under the name.
+   */
+  bundle: { [key in string]?: JsonValue };
+  /**
+   * Markdown that shows the image, to insert into the source.
+   */
+  markdown: string;
+};
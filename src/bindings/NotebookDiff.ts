@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellDiff } from "./CellDiff";
+
+/**
+ * Cell-by-cell difference between two versions of a notebook.
+ */
+export type NotebookDiff = {
+  /**
+   * Cells of both versions, in the order of the new version, with removed
+   * cells placed where they were in the old version.
+   */
+  cells: Array<CellDiff>;
+};
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellConflict } from "./CellConflict";
+import type { NotebookRoot } from "./NotebookRoot";
+
+/**
+ * Result of merging the two sides of a conflicted notebook.
+ */
+export type NotebookMerge = {
+  /**
+   * The merged notebook. Conflicting cells have our source, or their
+   * source if we deleted the cell.
+   */
+  notebook: NotebookRoot;
+  /**
+   * Cells that need to be resolved by hand.
+   */
+  conflicts: Array<CellConflict>;
+};
@@ -0,0 +1,70 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Cell } from "./Cell";
+import type { Output } from "./Output";
+
+/**
+ * A change to the cells of a notebook document.
+ */
+export type NotebookOp =
+  | {
+      op: "insert_cell";
+      /**
+       * Index of the cell after it is inserted.
+       */
+      index: number;
+      /**
+       * The new cell.
+       */
+      cell: Cell;
+    }
+  | {
+      op: "delete_cell";
+      /**
+       * ID of the cell.
+       */
+      cell_id: string;
+    }
+  | {
+      op: "move_cell";
+      /**
+       * ID of the cell.
+       */
+      cell_id: string;
+      /**
+       * Index of the cell after it is moved.
+       */
+      index: number;
+    }
+  | {
+      op: "edit_source";
+      /**
+       * ID of the cell.
+       */
+      cell_id: string;
+      /**
+       * The new source.
+       */
+      source: string;
+    }
+  | {
+      op: "set_outputs";
+      /**
+       * ID of the cell.
+       */
+      cell_id: string;
+      /**
+       * The new outputs.
+       */
+      outputs: Array<Output>;
+      /**
+       * The new execution count.
+       */
+      execution_count: number | null;
+    }
+  | {
+      op: "replace_cell";
+      /**
+       * The new cell.
+       */
+      cell: Cell;
+    };
@@ -0,0 +1,29 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellSummary } from "./CellSummary";
+import type { NotebookMetadata } from "./NotebookMetadata";
+
+/**
+ * A notebook being loaded, with everything but the contents of its cells.
+ */
+export type NotebookOutline = {
+  /**
+   * ID of the load, to fetch the cells with.
+   */
+  load_id: string;
+  /**
+   * Root-level metadata of the notebook.
+   */
+  metadata: NotebookMetadata;
+  /**
+   * Notebook format (minor number).
+   */
+  nbformat_minor: number;
+  /**
+   * Notebook format (major number).
+   */
+  nbformat: number;
+  /**
+   * Summaries of the cells, in order.
+   */
+  cells: Array<CellSummary>;
+};
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A chunk of the text cut from a truncated output.
+ */
+export type OutputChunk = {
+  /**
+   * Text of the chunk.
+   */
+  text: string;
+  /**
+   * Offset of the next chunk, in bytes.
+   */
+  next_offset: number;
+  /**
+   * Number of bytes after this chunk. A stream that is still running may
+   * add more.
+   */
+  remaining: number;
+};
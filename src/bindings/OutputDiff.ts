@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CellChange } from "./CellChange";
+
+/**
+ * Difference in one output of a code cell.
+ */
+export type OutputDiff = {
+  /**
+   * Index of the output in the cell.
+   */
+  index: number;
+  /**
+   * Type of the output, from the new version if it is in both.
+   */
+  output_type: string;
+  /**
+   * How the output changed, which is never [`CellChange::Unchanged`].
+   */
+  change: CellChange;
+};
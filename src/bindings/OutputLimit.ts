@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Size of the text that each output of a cell can show, in bytes. The rest
+ * is kept until the user expands the output.
+ */
+export type OutputLimit = number;
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OutputDisplayData } from "./OutputDisplayData";
+
+/**
+ * A display output that was changed by `update_display_data`, to be updated
+ * in place by the frontend.
+ */
+export type OutputUpdate = {
+  /**
+   * ID of the cell with the output, which may not be the running cell.
+   */
+  cell_id: string;
+  /**
+   * Index of the output in the cell.
+   */
+  index: number;
+  /**
+   * The new contents of the output.
+   */
+  output: OutputDisplayData;
+};
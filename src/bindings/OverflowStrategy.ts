@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What to do when iopub messages from a running cell arrive faster than they
+ * are received, and its queue is full.
+ */
+export type OverflowStrategy = "pause" | "drop" | "spill_to_disk";
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Another client in a collaboration session.
+ */
+export type Peer = {
+  /**
+   * Client ID of the peer in the shared document.
+   */
+  client_id: number;
+  /**
+   * Display name of the peer's user.
+   */
+  name: string;
+  /**
+   * Cell that the peer has focused, if any.
+   */
+  cell_id: string | null;
+};
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProjectConfig } from "./ProjectConfig";
+
+/**
+ * A project discovered from a notebook's location.
+ */
+export type Project = {
+  /**
+   * Directory containing the `jute.toml` file.
+   */
+  root: string;
+  /**
+   * Parsed contents of the `jute.toml` file.
+   */
+  config: ProjectConfig;
+};
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FormatConfig } from "./FormatConfig";
+
+/**
+ * Settings declared in a `jute.toml` file.
+ */
+export type ProjectConfig = {
+  /**
+   * Name of the kernel spec to start by default, like `python3`.
+   */
+  environment: string | null;
+  /**
+   * Code to run in the kernel after it starts.
+   */
+  startup: string | null;
+  /**
+   * Environment variables to set for the kernel process.
+   */
+  env: { [key in string]?: string };
+  /**
+   * Code formatting settings.
+   */
+  format: FormatConfig;
+};
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A Python interpreter found on the system.
+ */
+export type PythonInstall = {
+  /**
+   * Path to the interpreter executable.
+   */
+  path: string;
+  /**
+   * Version reported by the interpreter, like `3.11.7`.
+   */
+  version: string;
+};
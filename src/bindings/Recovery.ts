@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NotebookRoot } from "./NotebookRoot";
+
+/**
+ * Unsaved edits to a notebook that can be recovered.
+ */
+export type Recovery = {
+  /**
+   * Path to the notebook on disk.
+   */
+  path: string;
+  /**
+   * When the most recent change was made.
+   */
+  modified: string;
+  /**
+   * Autosaved copy of the whole notebook, if there is one.
+   */
+  snapshot: NotebookRoot | null;
+  /**
+   * Latest source of each cell edited after the snapshot, keyed by cell ID.
+   */
+  edits: { [key in string]?: string };
+};
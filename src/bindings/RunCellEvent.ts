@@ -3,6 +3,12 @@ import type { ClearOutput } from "./ClearOutput";
 import type { DisplayData } from "./DisplayData";
 import type { ErrorReply } from "./ErrorReply";
 import type { ExecuteResult } from "./ExecuteResult";
+import type { ExecuteStatus } from "./ExecuteStatus";
+import type { InputPrompt } from "./InputPrompt";
+import type { JsonValue } from "./serde_json/JsonValue";
+import type { KernelMessageType } from "./KernelMessageType";
+import type { OutputUpdate } from "./OutputUpdate";
+import type { TruncatedOutput } from "./TruncatedOutput";
 
 /**
  * Events that can be received while running a cell.
@@ -15,4 +21,56 @@ export type RunCellEvent =
   | { event: "update_display_data"; data: DisplayData }
   | { event: "clear_output"; data: ClearOutput }
   | { event: "error"; data: ErrorReply }
-  | { event: "disconnect"; data: string };
+  | { event: "disconnect"; data: string }
+  | { event: "aborted" }
+  | { event: "output_dropped"; data: number }
+  | { event: "input_request"; data: InputPrompt }
+  | { event: "password_request"; data: InputPrompt }
+  | { event: "update_output"; data: OutputUpdate }
+  | {
+      event: "started";
+      data: {
+        /**
+         * The execution count of the cell.
+         */
+        execution_count: number | null;
+        /**
+         * When the kernel became busy, by its own clock, as a Unix time in
+         * milliseconds.
+         */
+        timestamp: number;
+      };
+    }
+  | { event: "execution_count"; data: number }
+  | {
+      event: "finished";
+      data: {
+        /**
+         * Wall time from when the kernel became busy until it was idle
+         * again, in milliseconds.
+         */
+        duration: number;
+        /**
+         * How the cell finished, from the kernel's reply.
+         */
+        status: ExecuteStatus;
+      };
+    }
+  | { event: "output_truncated"; data: TruncatedOutput }
+  | {
+      event: "custom";
+      data: {
+        /**
+         * The type of the message.
+         */
+        msg_type: KernelMessageType;
+        /**
+         * Content of the message.
+         */
+        content: JsonValue;
+        /**
+         * Binary buffers of the message, encoded as base64.
+         */
+        buffers: Array<string>;
+      };
+    };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OverflowStrategy } from "./OverflowStrategy";
+
+/**
+ * Options for running a cell.
+ */
+export type RunCellOptions = {
+  /**
+   * What to do when output arrives faster than it is received, once the
+   * connection's queue of iopub messages for the cell is full.
+   */
+  overflow: OverflowStrategy;
+  /**
+   * Run the code in the background, for helper code rather than a cell of
+   * the notebook. The kernel doesn't count it or store it in its history,
+   * and doesn't send its result.
+   */
+  silent: boolean;
+};
@@ -0,0 +1,28 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KernelStatus } from "./KernelStatus";
+
+/**
+ * A running kernel, returned by [`list_running_kernels`].
+ */
+export type RunningKernel = {
+  /**
+   * ID of the kernel.
+   */
+  kernel_id: string;
+  /**
+   * Display name of the kernel spec, like "Python 3".
+   */
+  display_name: string;
+  /**
+   * Notebooks attached to the kernel, which are `null` if unsaved.
+   */
+  notebooks: Array<string | null>;
+  /**
+   * Whether the kernel process is still running.
+   */
+  status: KernelStatus;
+  /**
+   * Seconds since the kernel was started.
+   */
+  uptime_secs: number;
+};
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Tools found for running Rust notebooks, sent to the frontend.
+ */
+export type RustKernelStatus = {
+  /**
+   * Path to `cargo`, if a Rust toolchain is installed.
+   */
+  cargo: string | null;
+  /**
+   * Path to `evcxr_jupyter`, if it is installed.
+   */
+  evcxr: string | null;
+  /**
+   * Whether the `rust` kernel spec is registered for Jupyter.
+   */
+  registered: boolean;
+  /**
+   * Path to `sccache`, if it is installed. Without it, dependencies of
+   * notebooks are compiled again in every session.
+   */
+  sccache: string | null;
+};
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A tab to save before it is closed or moved to a new window, sent to the
+ * frontend, which repeats the action once the notebook is saved.
+ */
+export type SaveTab = {
+  /**
+   * ID of the tab.
+   */
+  id: string;
+  /**
+   * Whether the tab is moving to a new window, rather than closing.
+   */
+  moving: boolean;
+};
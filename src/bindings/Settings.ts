@@ -0,0 +1,78 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LogLevel } from "./LogLevel";
+import type { MathRenderer } from "./MathRenderer";
+import type { OutputLimit } from "./OutputLimit";
+import type { OverflowStrategy } from "./OverflowStrategy";
+import type { UpdateChannel } from "./UpdateChannel";
+import type { UpdateCheck } from "./UpdateCheck";
+import type { Zoom } from "./Zoom";
+
+/**
+ * Persistent user settings for Jute.
+ */
+export type Settings = {
+  /**
+   * Minimum level of log messages to record.
+   */
+  log_level: LogLevel;
+  /**
+   * Name of the kernel spec to start when none is chosen.
+   */
+  default_kernel: string | null;
+  /**
+   * How often to check for new versions of Jute.
+   */
+  update_check: UpdateCheck;
+  /**
+   * Which releases to download updates from.
+   */
+  update_channel: UpdateChannel;
+  /**
+   * Whether the user has finished the first-run setup.
+   */
+  onboarding_completed: boolean;
+  /**
+   * Managed virtual environment used to start kernels by default.
+   */
+  default_environment: string | null;
+  /**
+   * Zoom level of windows.
+   */
+  zoom: Zoom;
+  /**
+   * Whether windows on Linux draw their own titlebar with GTK, rather than
+   * using the one from the window manager.
+   */
+  client_side_decorations: boolean;
+  /**
+   * System-wide keyboard shortcut that opens the scratchpad, if any.
+   */
+  scratchpad_shortcut: string | null;
+  /**
+   * Whether notebooks in workspaces are made searchable from Spotlight or
+   * Windows Search.
+   */
+  search_indexing: boolean;
+  /**
+   * Engine used to render LaTeX math.
+   */
+  math_renderer: MathRenderer;
+  /**
+   * Whether running a cell also re-runs the cells that depend on it.
+   */
+  reactive_execution: boolean;
+  /**
+   * Size of the text that each output can show before it is truncated.
+   */
+  output_limit: OutputLimit;
+  /**
+   * What to do when a cell's output arrives faster than the notebook can
+   * show it.
+   */
+  output_overflow: OverflowStrategy;
+  /**
+   * Project directories trusted to run their `jute.toml` startup code in
+   * notebooks opened from `jute://` links.
+   */
+  trusted_projects: Array<string>;
+};
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A notebook shared as a gist.
+ */
+export type SharedGist = {
+  /**
+   * ID of the gist.
+   */
+  id: string;
+  /**
+   * Page of the gist on GitHub.
+   */
+  url: string;
+  /**
+   * Page rendering the notebook on nbviewer.
+   */
+  nbviewer_url: string;
+  /**
+   * Whether an existing gist was updated, rather than a new one created.
+   */
+  updated: boolean;
+};
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Category } from "./Category";
+import type { KeyBinding } from "./KeyBinding";
+
+/**
+ * Bound shortcuts in one category, shown in the keyboard shortcuts reference.
+ */
+export type ShortcutGroup = {
+  /**
+   * Category of the actions.
+   */
+  category: Category;
+  /**
+   * Effective bindings of the actions in this category that have a shortcut.
+   */
+  bindings: Array<KeyBinding>;
+};
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How a cell is placed in a slideshow.
+ */
+export type SlideType =
+  | "slide"
+  | "subslide"
+  | "fragment"
+  | "skip"
+  | "notes"
+  | "-";
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SlideType } from "./SlideType";
+
+/**
+ * Slideshow metadata for a cell.
+ */
+export type SlideshowMetadata = {
+  /**
+   * How the cell is placed in the slideshow.
+   */
+  slide_type: SlideType;
+};
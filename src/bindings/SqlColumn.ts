@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A column in the results of a query.
+ */
+export type SqlColumn = {
+  /**
+   * Name of the column.
+   */
+  name: string;
+  /**
+   * Name of the column's type in the database, like `"INT8"`.
+   */
+  type_name: string;
+};
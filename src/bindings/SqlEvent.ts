@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SqlColumn } from "./SqlColumn";
+
+/**
+ * Events sent to the frontend while running a query.
+ */
+export type SqlEvent =
+  | { event: "columns"; data: Array<SqlColumn> }
+  | { event: "rows"; data: unknown[][] }
+  | {
+      event: "done";
+      data: {
+        /**
+         * Number of rows returned, or affected by a statement like `UPDATE`.
+         */
+        row_count: number;
+        /**
+         * Whether rows were left out after the first [`MAX_ROWS`].
+         */
+        truncated: boolean;
+      };
+    }
+  | { event: "bound"; data: string }
+  | { event: "bind_error"; data: string };
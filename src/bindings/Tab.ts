@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A notebook file open as a tab, sent to the frontend.
+ */
+export type Tab = {
+  /**
+   * Unique ID of the tab, which stays the same when its notebook is saved
+   * under another path.
+   */
+  id: string;
+  /**
+   * Path to the notebook file.
+   */
+  path: string;
+  /**
+   * Whether the notebook has changes that have not been saved.
+   */
+  dirty: boolean;
+};
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Tab } from "./Tab";
+
+/**
+ * Notebook files open as tabs in a window, sent to the frontend.
+ */
+export type TabList = {
+  /**
+   * The open notebooks, in tab order.
+   */
+  tabs: Array<Tab>;
+  /**
+   * Index of the active tab.
+   */
+  active: number;
+};
@@ -0,0 +1,46 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KernelMessageType } from "./KernelMessageType";
+import type { MessageDirection } from "./MessageDirection";
+
+/**
+ * A message recorded by a [`MessageTap`].
+ */
+export type TappedMessage = {
+  /**
+   * The channel of the message, like "shell" or "iopub".
+   */
+  channel: string;
+  /**
+   * Whether the message was sent or received.
+   */
+  direction: MessageDirection;
+  /**
+   * The type of the message.
+   */
+  msg_type: KernelMessageType;
+  /**
+   * ID of the message.
+   */
+  msg_id: string;
+  /**
+   * ID of the request that this message is in response to, if any.
+   */
+  parent_msg_id: string | null;
+  /**
+   * When the message was recorded, in milliseconds since the Unix epoch.
+   */
+  time_ms: number;
+  /**
+   * Milliseconds between sending the parent request and receiving this,
+   * if the request was recorded.
+   */
+  latency_ms: number | null;
+  /**
+   * Content of the message as JSON, cut off at [`MAX_TAPPED_CONTENT`].
+   */
+  content: string;
+  /**
+   * Whether the content was cut off.
+   */
+  truncated: boolean;
+};
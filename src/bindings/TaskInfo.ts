@@ -0,0 +1,46 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TaskKind } from "./TaskKind";
+import type { TaskStatus } from "./TaskStatus";
+
+/**
+ * A snapshot of a task's state, sent to the frontend.
+ */
+export type TaskInfo = {
+  /**
+   * Unique identifier of the task.
+   */
+  id: string;
+  /**
+   * What kind of work the task is doing.
+   */
+  kind: TaskKind;
+  /**
+   * Human-readable description of the task.
+   */
+  title: string;
+  /**
+   * Fraction of work completed from 0 to 1, or null if indeterminate.
+   */
+  progress: number | null;
+  /**
+   * Optional message describing the current step.
+   */
+  message: string | null;
+  /**
+   * Whether the task can be cancelled by the user.
+   */
+  cancellable: boolean;
+  /**
+   * Current status of the task.
+   */
+  status: TaskStatus;
+  /**
+   * Error message if the task failed.
+   */
+  error: string | null;
+  /**
+   * Number of steps that have failed so far, such as cells that raised an
+   * error.
+   */
+  failures: number;
+};
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The category of work being done by a task.
+ */
+export type TaskKind =
+  | "export"
+  | "create_environment"
+  | "install_kernel"
+  | "sync"
+  | "run_cells";
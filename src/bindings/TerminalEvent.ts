@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Events sent to the frontend from a running terminal.
+ */
+export type TerminalEvent =
+  | { event: "output"; data: string }
+  | { event: "exit"; data: number | null };
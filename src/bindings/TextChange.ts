@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A change to the source of a cell, replacing a range with new text.
+ *
+ * Positions are in UTF-16 code units, as in JavaScript strings and editors.
+ */
+export type TextChange = {
+  /**
+   * Start of the replaced range.
+   */
+  from: number;
+  /**
+   * End of the replaced range.
+   */
+  to: number;
+  /**
+   * Text inserted in place of the range.
+   */
+  insert: string;
+};
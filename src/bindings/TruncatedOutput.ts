@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An output that was cut short, sent right after the part of it that is
+ * shown.
+ */
+export type TruncatedOutput = {
+  /**
+   * ID of the text that was cut, to fetch in chunks.
+   */
+  id: string;
+  /**
+   * Name of the stream, or the MIME type of a result's data.
+   */
+  name: string;
+  /**
+   * Whether the whole value was left out, since part of it can't be shown,
+   * like an image. Its text is JSON, to parse once all of it is fetched.
+   */
+  withheld: boolean;
+};
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How often to check for updates in the background.
+ */
+export type UpdateCheck = "never" | "daily" | "weekly" | "disabled";
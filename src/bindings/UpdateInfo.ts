@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A newer release that is available to download.
+ */
+export type UpdateInfo = {
+  /**
+   * Version of the new release.
+   */
+  version: string;
+  /**
+   * Version of Jute that is currently running.
+   */
+  current_version: string;
+  /**
+   * Release notes, in Markdown.
+   */
+  notes: string;
+  /**
+   * Link to the release page.
+   */
+  url: string;
+  /**
+   * When the release was published, in RFC 3339 format.
+   */
+  published_at: string | null;
+};
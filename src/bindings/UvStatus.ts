@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Result of checking that the bundled `uv` sidecar can run.
+ */
+export type UvStatus = {
+  /**
+   * Whether `uv` ran successfully.
+   */
+  available: boolean;
+  /**
+   * Version reported by `uv`, like `0.5.11`.
+   */
+  version: string | null;
+  /**
+   * Error message if `uv` could not be run.
+   */
+  error: string | null;
+};
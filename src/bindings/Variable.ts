@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A variable in the user namespace of a kernel.
+ */
+export type Variable = {
+  /**
+   * Name of the variable.
+   */
+  name: string;
+  /**
+   * Name of the type of its value, like `DataFrame`.
+   */
+  type_name: string;
+  /**
+   * Shape of an array or table, or length of a collection, if it has one.
+   */
+  shape: string | null;
+  /**
+   * Approximate size of the value in memory, in bytes, if known.
+   */
+  size: number | null;
+  /**
+   * Short preview of the value.
+   */
+  summary: string;
+};
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * List item returned by [`venv_list`].
+ */
+export type VenvListItem = {
+  venv_id: string;
+  python_version: string | null;
+  uv_version: string | null;
+  implementation: string | null;
+  home: string | null;
+};
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Zoom level of windows, as a percentage of the default size.
+ */
+export type Zoom = number;
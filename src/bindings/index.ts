@@ -21,3 +21,97 @@ export * from "./NotebookRoot";
 export * from "./DisplayDataTransient";
 export * from "./NotebookMetadata";
 export * from "./CellMetadata";
+export * from "./Recovery";
+export * from "./Project";
+export * from "./ProjectConfig";
+export * from "./FormatConfig";
+export * from "./Action";
+export * from "./Category";
+export * from "./ImportSummary";
+export * from "./JupyterInstall";
+export * from "./JupyterServer";
+export * from "./KeyBinding";
+export * from "./LogLevel";
+export * from "./PythonInstall";
+export * from "./Settings";
+export * from "./TaskInfo";
+export * from "./TaskKind";
+export * from "./TaskStatus";
+export * from "./UpdateCheck";
+export * from "./UpdateInfo";
+export * from "./UvStatus";
+export * from "./VenvListItem";
+export * from "./MenuEvent";
+export * from "./Tab";
+export * from "./SaveTab";
+export * from "./TabList";
+export * from "./Zoom";
+export * from "./FocusCell";
+export * from "./KernelStatus";
+export * from "./KernelUsage";
+export * from "./RunningKernel";
+export * from "./SlideType";
+export * from "./SlideshowMetadata";
+export * from "./DirEntry";
+export * from "./ShortcutGroup";
+export * from "./UpdateChannel";
+export * from "./OverflowStrategy";
+export * from "./RunCellOptions";
+export * from "./MathRenderer";
+export * from "./DeviceLogin";
+export * from "./SharedGist";
+export * from "./CellDependencies";
+export * from "./DependencyGraph";
+export * from "./CellChange";
+export * from "./CellDiff";
+export * from "./NotebookDiff";
+export * from "./GitHead";
+export * from "./FileChange";
+export * from "./FileStatus";
+export * from "./GitStatus";
+export * from "./CellBlame";
+export * from "./BlameCommit";
+export * from "./DatabaseConnection";
+export * from "./DatabaseKind";
+export * from "./SqlColumn";
+export * from "./SqlEvent";
+export * from "./TerminalEvent";
+export * from "./TextChange";
+export * from "./Peer";
+export * from "./CollabEvent";
+export * from "./RustKernelStatus";
+export * from "./KernelLiveness";
+export * from "./IsCompleteReply";
+export * from "./HistoryEntry";
+export * from "./HistoryInput";
+export * from "./MessageDirection";
+export * from "./KernelMessageType";
+export * from "./TappedMessage";
+export * from "./OutputUpdate";
+export * from "./InputPrompt";
+export * from "./CellState";
+export * from "./CellStateEvent";
+export * from "./BatchCell";
+export * from "./CellBatch";
+export * from "./BatchEvent";
+export * from "./ExecuteStatus";
+export * from "./TruncatedOutput";
+export * from "./OutputChunk";
+export * from "./OutputLimit";
+export * from "./Variable";
+export * from "./KernelInfoReply";
+export * from "./KernelLanguageInfo";
+export * from "./Checkpoint";
+export * from "./LineChange";
+export * from "./DiffLine";
+export * from "./DiffHunk";
+export * from "./OutputDiff";
+export * from "./CellConflict";
+export * from "./NotebookMerge";
+export * from "./CellSummary";
+export * from "./NotebookOutline";
+export * from "./AttachmentInfo";
+export * from "./NewAttachment";
+export * from "./DocChangeEvent";
+export * from "./DocSnapshot";
+export * from "./NotebookOp";
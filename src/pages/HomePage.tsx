@@ -1,8 +1,10 @@
+import { invoke } from "@tauri-apps/api/core";
 import { open } from "@tauri-apps/plugin-dialog";
 import { ArrowRight } from "lucide-react";
 import { Link, useLocation } from "wouter";
 
 import { NotebookRoot } from "@/bindings";
+import { notebookExtensions } from "@/stores/notebook";
 import Header from "@/ui/shared/Header";
 
 const simpleNotebook = {
@@ -98,7 +100,7 @@ export default function HomePage() {
             const file = await open({
               multiple: false,
               directory: false,
-              filters: [{ name: "Jupyter Notebook", extensions: ["ipynb"] }],
+              filters: [{ name: "Notebook", extensions: notebookExtensions }],
             });
             if (file)
               navigate("/notebook?" + new URLSearchParams({ path: file }));
@@ -106,6 +108,16 @@ export default function HomePage() {
         >
           Open a notebook <ArrowRight size="1em" />
         </button>
+
+        <button
+          className="mt-2 flex items-center gap-2 hover:underline"
+          onClick={async () => {
+            const path = await open({ multiple: false, directory: true });
+            if (path) invoke("open_folder", { path });
+          }}
+        >
+          Open a folder <ArrowRight size="1em" />
+        </button>
       </div>
     </div>
   );
@@ -0,0 +1,168 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+import clsx from "clsx";
+import { PauseIcon, PowerIcon, RotateCwIcon } from "lucide-react";
+import { useEffect, useState } from "react";
+
+import type {
+  KernelStatus,
+  KernelUsage,
+  MenuEvent,
+  RunningKernel,
+} from "@/bindings";
+import Header from "@/ui/shared/Header";
+
+function formatBytes(bytes: number): string {
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(0)} KB`;
+  if (bytes < 1024 * 1024 * 1024)
+    return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  return `${(bytes / (1024 * 1024 * 1024)).toFixed(2)} GB`;
+}
+
+function formatUptime(secs: number): string {
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor((secs % 3600) / 60);
+  const seconds = secs % 60;
+  if (hours > 0) return `${hours}h ${minutes}m`;
+  if (minutes > 0) return `${minutes}m ${seconds}s`;
+  return `${seconds}s`;
+}
+
+function notebookName(path: string | null): string {
+  if (!path) return "Untitled";
+  return path.slice(path.lastIndexOf("/") + 1);
+}
+
+const statusLabels: Record<KernelStatus, string> = {
+  running: "Running",
+  unresponsive: "Unresponsive",
+  exited: "Exited",
+};
+
+/** Lists running kernels with their resource usage, in its own window. */
+export default function KernelMonitorPage() {
+  const [kernels, setKernels] = useState<RunningKernel[]>([]);
+  const [usage, setUsage] = useState<Record<string, KernelUsage>>({});
+
+  // Kernels can start and stop at any time, so the list is polled.
+  useEffect(() => {
+    const refresh = () =>
+      invoke<RunningKernel[]>("list_running_kernels").then(setKernels);
+    refresh();
+    const interval = setInterval(refresh, 2000);
+    return () => clearInterval(interval);
+  }, []);
+
+  useEffect(() => {
+    const onUsage = new Channel<KernelUsage[]>();
+    onUsage.onmessage = (message) => {
+      setUsage(Object.fromEntries(message.map((u) => [u.kernel_id, u])));
+    };
+    invoke("watch_kernel_usage", { onUsage });
+  }, []);
+
+  const runAction = async (kernelId: string, action: MenuEvent) => {
+    await invoke("kernel_action", { kernelId, action });
+    setKernels(await invoke<RunningKernel[]>("list_running_kernels"));
+  };
+
+  return (
+    <div className="h-screen overflow-y-auto">
+      <Header />
+      <div className="px-6 pb-6 pt-14">
+        <h1 className="mb-4 text-2xl">Kernel Monitor</h1>
+
+        {kernels.length === 0 ? (
+          <p className="text-gray-400">No kernels are running.</p>
+        ) : (
+          <table className="w-full text-left text-sm">
+            <thead className="text-gray-500">
+              <tr className="border-b border-gray-200">
+                <th className="py-2 font-normal">Kernel</th>
+                <th className="py-2 font-normal">Notebooks</th>
+                <th className="py-2 font-normal">Status</th>
+                <th className="py-2 text-right font-normal">CPU</th>
+                <th className="py-2 text-right font-normal">Memory</th>
+                <th className="py-2 text-right font-normal">Uptime</th>
+                <th className="py-2" />
+              </tr>
+            </thead>
+            <tbody>
+              {kernels.map((kernel) => {
+                const kernelUsage = usage[kernel.kernel_id];
+                return (
+                  <tr
+                    key={kernel.kernel_id}
+                    className="border-b border-gray-100"
+                  >
+                    <td className="py-2">{kernel.display_name}</td>
+                    <td className="py-2">
+                      {kernel.notebooks.length === 0
+                        ? "—"
+                        : kernel.notebooks.map(notebookName).join(", ")}
+                    </td>
+                    <td
+                      className={clsx(
+                        "py-2",
+                        kernel.status === "running" && "text-green-600",
+                        kernel.status === "unresponsive" && "text-amber-600",
+                        kernel.status === "exited" && "text-red-600",
+                      )}
+                    >
+                      {statusLabels[kernel.status]}
+                    </td>
+                    <td className="py-2 text-right tabular-nums">
+                      {kernelUsage
+                        ? `${kernelUsage.cpu_percent.toFixed(1)}%`
+                        : "—"}
+                    </td>
+                    <td className="py-2 text-right tabular-nums">
+                      {kernelUsage
+                        ? formatBytes(kernelUsage.memory_bytes)
+                        : "—"}
+                    </td>
+                    <td className="py-2 text-right tabular-nums">
+                      {formatUptime(
+                        kernelUsage?.uptime_secs ?? kernel.uptime_secs,
+                      )}
+                    </td>
+                    <td className="py-2">
+                      <div className="flex justify-end gap-0.5">
+                        <button
+                          className="rounded p-1 text-gray-500 hover:bg-gray-200 hover:text-black"
+                          title="Interrupt"
+                          onClick={() =>
+                            runAction(kernel.kernel_id, "InterruptKernel")
+                          }
+                        >
+                          <PauseIcon size={14} />
+                        </button>
+                        <button
+                          className="rounded p-1 text-gray-500 hover:bg-gray-200 hover:text-black"
+                          title="Restart"
+                          onClick={() =>
+                            runAction(kernel.kernel_id, "RestartKernel")
+                          }
+                        >
+                          <RotateCwIcon size={14} />
+                        </button>
+                        <button
+                          className="rounded p-1 text-gray-500 hover:bg-gray-200 hover:text-black"
+                          title="Shut Down"
+                          onClick={() =>
+                            runAction(kernel.kernel_id, "ShutdownKernel")
+                          }
+                        >
+                          <PowerIcon size={14} />
+                        </button>
+                      </div>
+                    </td>
+                  </tr>
+                );
+              })}
+            </tbody>
+          </table>
+        )}
+      </div>
+    </div>
+  );
+}
@@ -1,17 +1,193 @@
-import { useEffect, useMemo } from "react";
-import { useSearch } from "wouter";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import clsx from "clsx";
+import { useEffect, useMemo, useState } from "react";
+import { useLocation, useSearch } from "wouter";
+import { useStore } from "zustand";
+import { useShallow } from "zustand/react/shallow";
 
+import type { FocusCell, MenuEvent, SaveTab, TabList } from "@/bindings";
 import { Notebook, NotebookContext } from "@/stores/notebook";
+import CheckpointPicker from "@/ui/notebook/CheckpointPicker";
+import FileBrowser from "@/ui/notebook/FileBrowser";
+import KernelPicker from "@/ui/notebook/KernelPicker";
 import NotebookCommandMenu from "@/ui/notebook/NotebookCommandMenu";
 import NotebookFooter from "@/ui/notebook/NotebookFooter";
 import NotebookHeader from "@/ui/notebook/NotebookHeader";
+import NotebookTabBar from "@/ui/notebook/NotebookTabBar";
 import NotebookView from "@/ui/notebook/NotebookView";
 
+type SearchParams = {
+  path?: string;
+  inline?: string;
+  kernel?: string;
+  new?: string;
+  cell?: string;
+  workspace?: string;
+  scratchpad?: string;
+};
+
 export default function NotebookPage() {
-  const { path, inline } = Object.fromEntries(new URLSearchParams(useSearch()));
+  const [, navigate] = useLocation();
+  const params: SearchParams = Object.fromEntries(
+    new URLSearchParams(useSearch()),
+  );
+
+  // The window stops showing a notebook when it leaves this page.
+  useEffect(() => {
+    return () => {
+      invoke("detach_notebook_window");
+    };
+  }, []);
+
+  // New notebooks ask for a kernel before they are created.
+  if (params.new && !params.kernel) {
+    return (
+      <main className="h-screen overflow-y-auto bg-white">
+        <KernelPicker
+          title="New Notebook"
+          onSelect={(kernel) =>
+            navigate("/notebook?" + new URLSearchParams({ kernel }), {
+              replace: true,
+            })
+          }
+        />
+      </main>
+    );
+  }
+
+  // Workspaces show a folder's files, with its notebooks opened as tabs.
+  if (params.workspace) {
+    return (
+      <div className="flex h-screen">
+        <FileBrowser
+          root={params.workspace}
+          onOpen={(path) => invoke("open_tab", { path })}
+        />
+        <div className="min-w-0 flex-1">
+          <NotebookTabs inWorkspace />
+        </div>
+      </div>
+    );
+  }
+
+  // Saved notebooks can be opened alongside others in tabs.
+  if (params.path) {
+    return (
+      <NotebookTabs initialPath={params.path} initialCell={params.cell} />
+    );
+  }
+
+  return (
+    <div className="relative h-screen">
+      <OpenNotebook {...params} active />
+    </div>
+  );
+}
+
+function NotebookTabs({
+  initialPath,
+  initialCell,
+  inWorkspace = false,
+}: {
+  initialPath?: string;
+  initialCell?: string;
+  /** Whether the window stays open without tabs, showing a file browser. */
+  inWorkspace?: boolean;
+}) {
+  const [tabs, setTabs] = useState<TabList>();
+
+  useEffect(() => {
+    if (initialPath) {
+      invoke<TabList>("open_tab", { path: initialPath }).then(setTabs);
+    } else {
+      setTabs({ tabs: [], active: 0 });
+    }
+    const unlisten = getCurrentWebviewWindow().listen<TabList>(
+      "tabs-changed",
+      (event) => setTabs(event.payload),
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [initialPath]);
+
+  // Closing the last tab closes the window, unless it is a workspace.
+  useEffect(() => {
+    if (tabs?.tabs.length !== 0) return;
+    if (inWorkspace) invoke("detach_notebook_window");
+    else getCurrentWebviewWindow().close();
+  }, [tabs, inWorkspace]);
+
+  if (!tabs) return null;
+
+  if (tabs.tabs.length === 0) {
+    return (
+      <div
+        className="flex h-full items-center justify-center text-gray-400"
+        data-tauri-drag-region
+      >
+        Open a notebook from the sidebar.
+      </div>
+    );
+  }
+
+  return (
+    <div className="flex h-screen flex-col">
+      {(tabs.tabs.length > 1 || inWorkspace) && (
+        <NotebookTabBar tabs={tabs} />
+      )}
+      <div className="relative min-h-0 flex-1">
+        {tabs.tabs.map((tab, index) => (
+          <OpenNotebook
+            key={tab.id}
+            tabId={tab.id}
+            path={tab.path}
+            cell={tab.path === initialPath ? initialCell : undefined}
+            active={index === tabs.active}
+          />
+        ))}
+      </div>
+    </div>
+  );
+}
+
+type OpenNotebookProps = SearchParams & {
+  /** Whether this notebook is shown and receives menu events. */
+  active: boolean;
+  /** ID of the notebook's tab, if it is open in one. */
+  tabId?: string;
+};
+
+function OpenNotebook({
+  path: openedPath,
+  inline,
+  kernel,
+  cell,
+  scratchpad,
+  active,
+  tabId,
+}: OpenNotebookProps) {
+  // A tab's path changes when it is saved elsewhere, but it stays open.
+  const [path] = useState(openedPath);
 
   // Singleton notebook object used for the lifetime of this component.
-  const notebook = useMemo(() => new Notebook(), []);
+  const notebook = useMemo(
+    () => new Notebook(path, kernel, Boolean(scratchpad)),
+    [path, kernel, scratchpad],
+  );
+  const [changingKernel, setChangingKernel] = useState(false);
+  const [reverting, setReverting] = useState(false);
+
+  const [savedPath, kernelId, isDirty, isLoading] = useStore(
+    notebook.store,
+    useShallow((state) => [
+      state.path,
+      state.kernelId,
+      state.isDirty,
+      state.isLoading,
+    ]),
+  );
 
   useEffect(() => {
     if (path) {
@@ -21,14 +197,205 @@ export default function NotebookPage() {
     }
   }, [notebook, path, inline]);
 
+  // Stop sharing the notebook on the local network when its tab closes.
+  useEffect(() => {
+    return () => {
+      if (notebook.stopSharing) {
+        notebook.stopSharing();
+        invoke("stop_share");
+      }
+    };
+  }, [notebook]);
+
+  // Release the notebook's document when its tab closes, so that reopening
+  // the file reads it from disk again.
+  useEffect(() => {
+    return () => notebook.dispose();
+  }, [notebook]);
+
+  // Scroll to the cell given in the URL, once the notebook has loaded.
+  useEffect(() => {
+    if (cell && !isLoading) notebook.focusCell(cell);
+  }, [notebook, cell, isLoading]);
+
+  // Scroll to a cell when asked, such as from search results or links.
+  useEffect(() => {
+    if (!savedPath) return;
+    const unlisten = getCurrentWebviewWindow().listen<FocusCell>(
+      "focus-cell",
+      (event) => {
+        if (event.payload.path === savedPath) {
+          notebook.focusCell(event.payload.cell);
+        }
+      },
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [notebook, savedPath]);
+
+  // Keep the window's menu in sync with the active notebook. An unsaved
+  // scratchpad is meant to be thrown away, so it never asks to save on close.
+  useEffect(() => {
+    if (!active) return;
+    invoke("attach_notebook_window", {
+      path: savedPath ?? null,
+      kernelId: kernelId ?? null,
+      dirty: isDirty && !(scratchpad && !savedPath),
+    });
+  }, [active, savedPath, kernelId, isDirty, scratchpad]);
+
+  // Tabs keep their own path and unsaved state, even in the background.
+  useEffect(() => {
+    if (!tabId || !savedPath) return;
+    invoke("update_tab", { id: tabId, path: savedPath, dirty: isDirty });
+  }, [tabId, savedPath, isDirty]);
+
+  // Save a tab when asked before closing or moving it, then do that.
+  useEffect(() => {
+    if (!tabId) return;
+    const unlisten = getCurrentWebviewWindow().listen<SaveTab>(
+      "save-tab",
+      async (event) => {
+        if (event.payload.id !== tabId) return;
+        await notebook.save();
+        const { path, isDirty } = notebook.state;
+        if (isDirty || !path) return;
+        await invoke("update_tab", { id: tabId, path, dirty: false });
+        const command = event.payload.moving
+          ? "move_tab_to_new_window"
+          : "close_tab";
+        await invoke(command, { id: tabId });
+      },
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [notebook, tabId]);
+
+  // Receive menu events for this window, like "Save", while active.
+  useEffect(() => {
+    if (!active) return;
+    const unlisten = getCurrentWebviewWindow().listen<MenuEvent>(
+      "menu-event",
+      (event) => {
+        const cellId = notebook.focusedCellId;
+        switch (event.payload) {
+          case "Save":
+            notebook.save();
+            break;
+          case "SaveAs":
+            notebook.saveAs();
+            break;
+          case "RevertToCheckpoint":
+            setReverting(true);
+            break;
+          case "ExportSlides":
+            notebook.exportSlides();
+            break;
+          case "ShareOnNetwork":
+            notebook.toggleSharing();
+            break;
+          case "CollapseAllOutputs":
+            notebook.setAllOutputsCollapsed(true);
+            break;
+          case "ExpandAllOutputs":
+            notebook.setAllOutputsCollapsed(false);
+            break;
+          case "ToggleOutputScrolling":
+            if (cellId) notebook.toggleOutputScrolling(cellId);
+            break;
+          case "RunCell":
+            if (cellId) notebook.runCell(cellId);
+            break;
+          case "RunSelectedText":
+            if (cellId) notebook.runSelectedText(cellId);
+            break;
+          case "RunAllAbove":
+            if (cellId) notebook.runAllAbove(cellId);
+            break;
+          case "RunAllBelow":
+            if (cellId) notebook.runAllBelow(cellId);
+            break;
+          case "RunAll":
+          case "RestartKernelRunAll":
+            notebook.runAll();
+            break;
+          case "ShutdownKernel":
+            notebook.store.setState({ kernelId: undefined });
+            break;
+          case "ChangeKernel":
+            setChangingKernel(true);
+            break;
+        }
+      },
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [notebook, active]);
+
+  // Save before closing the window, when asked about unsaved changes. Each
+  // tab saves its own notebook, and the window closes once all are saved.
+  useEffect(() => {
+    if (!active && !tabId) return;
+    const currentWindow = getCurrentWebviewWindow();
+    const unlisten = currentWindow.listen("save-and-close", async () => {
+      if (!tabId) {
+        await notebook.save();
+        if (!notebook.state.isDirty) await currentWindow.destroy();
+        return;
+      }
+      if (!notebook.state.isDirty) return;
+      await notebook.save();
+      const { path, isDirty } = notebook.state;
+      if (isDirty || !path) return;
+      await invoke("update_tab", { id: tabId, path, dirty: false });
+      await invoke("close_saved_window");
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [notebook, active, tabId]);
+
   return (
-    <main className="h-screen bg-white">
+    <main className={clsx("h-full bg-white", !active && "hidden")}>
       <NotebookContext.Provider value={notebook}>
         <NotebookHeader kernelName="Local Kernel (Python 3.11.7)" />
         <NotebookView />
         <NotebookFooter />
         <NotebookCommandMenu />
       </NotebookContext.Provider>
+
+      {changingKernel && (
+        <div
+          className="fixed inset-0 z-20 overflow-y-auto bg-white/90"
+          onClick={() => setChangingKernel(false)}
+        >
+          <KernelPicker
+            title="Change Kernel"
+            onSelect={(specName) => {
+              setChangingKernel(false);
+              notebook.changeKernel(specName);
+            }}
+          />
+        </div>
+      )}
+
+      {reverting && savedPath && (
+        <div
+          className="fixed inset-0 z-20 overflow-y-auto bg-white/90"
+          onClick={() => setReverting(false)}
+        >
+          <CheckpointPicker
+            path={savedPath}
+            onSelect={(checkpointId) => {
+              setReverting(false);
+              notebook.restoreCheckpoint(checkpointId);
+            }}
+          />
+        </div>
+      )}
     </main>
   );
 }
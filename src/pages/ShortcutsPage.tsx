@@ -0,0 +1,90 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+
+import type { Category, ShortcutGroup } from "@/bindings";
+import Header from "@/ui/shared/Header";
+
+const isMac = navigator.userAgent.includes("Mac");
+
+const categoryNames: Record<Category, string> = {
+  general: "General",
+  file: "File",
+  run: "Run",
+};
+
+const macKeys: Record<string, string> = {
+  CmdOrCtrl: "⌘",
+  Super: "⌘",
+  Ctrl: "⌃",
+  Alt: "⌥",
+  Shift: "⇧",
+  Enter: "↩",
+  Tab: "⇥",
+};
+
+/** Split an accelerator like `CmdOrCtrl+Shift+K` into keys for this platform. */
+function formatAccelerator(accelerator: string): string[] {
+  return accelerator.split("+").map((key) => {
+    if (isMac) return macKeys[key] ?? key;
+    if (key === "CmdOrCtrl") return "Ctrl";
+    if (key === "Super") return "Win";
+    return key;
+  });
+}
+
+/** Reference of the effective keyboard shortcuts, in its own window. */
+export default function ShortcutsPage() {
+  const [groups, setGroups] = useState<ShortcutGroup[]>([]);
+
+  // Refresh on focus, in case shortcuts were changed in another window.
+  useEffect(() => {
+    const refresh = () =>
+      invoke<ShortcutGroup[]>("list_shortcuts").then(setGroups);
+    refresh();
+    window.addEventListener("focus", refresh);
+    return () => window.removeEventListener("focus", refresh);
+  }, []);
+
+  return (
+    <div className="h-screen overflow-y-auto">
+      <Header />
+      <div className="px-6 pb-6 pt-14">
+        <h1 className="mb-4 text-2xl">Keyboard Shortcuts</h1>
+
+        {groups.map((group) => (
+          <section key={group.category} className="mb-6">
+            <h2 className="mb-1 text-sm text-gray-500">
+              {categoryNames[group.category]}
+            </h2>
+            <table className="w-full text-sm">
+              <tbody>
+                {group.bindings.map((binding) => (
+                  <tr
+                    key={binding.action}
+                    className="border-b border-gray-100"
+                  >
+                    <td className="py-1.5">{binding.label}</td>
+                    <td className="py-1.5 text-right">
+                      <span className="inline-flex gap-1">
+                        {formatAccelerator(binding.accelerator!).map(
+                          (key, i) => (
+                            <kbd
+                              key={i}
+                              className="min-w-6 rounded border border-gray-300 bg-gray-50 px-1.5 text-center font-sans text-xs text-gray-700"
+                            >
+                              {key}
+                            </kbd>
+                          ),
+                        )}
+                      </span>
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </section>
+        ))}
+      </div>
+    </div>
+  );
+}
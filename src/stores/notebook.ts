@@ -1,5 +1,7 @@
 import type { EditorView } from "@codemirror/view";
 import { Channel, invoke } from "@tauri-apps/api/core";
+import { UnlistenFn, listen } from "@tauri-apps/api/event";
+import { ask, message, save } from "@tauri-apps/plugin-dialog";
 import { WritableDraft } from "immer";
 import { createContext, useContext } from "react";
 import { v4 as uuidv4 } from "uuid";
@@ -7,13 +9,113 @@ import { StoreApi, createStore } from "zustand";
 import { immer } from "zustand/middleware/immer";
 
 import type {
+  AttachmentInfo,
+  BatchEvent,
   Cell,
+  CellConflict,
+  CellMetadata,
+  CellStateEvent,
+  DocChangeEvent,
+  DocSnapshot,
+  FormatConfig,
+  InputPrompt,
+  MarkdownCell,
+  NewAttachment,
+  NotebookMerge,
+  NotebookOp,
+  NotebookOutline,
   NotebookRoot,
   Output,
+  OutputChunk,
   OutputDisplayData,
+  Project,
+  Recovery,
   RunCellEvent,
+  Settings,
+  SqlColumn,
+  SqlEvent,
+  TruncatedOutput,
 } from "@/bindings";
 
+/**
+ * File extensions that can be opened as notebooks. Scripts and documents are
+ * converted by the backend, and saved back in their own format.
+ */
+export const notebookExtensions = ["ipynb", "py", "qmd", "Rmd"];
+
+/** Source of a conflicting cell, with each side between git's markers. */
+function withConflictMarkers(conflict: CellConflict): string {
+  const lines = (source: string | null) =>
+    source ? source.replace(/\n?$/, "\n") : "";
+  return (
+    "<<<<<<< ours\n" +
+    lines(conflict.ours) +
+    (conflict.base !== null ? "||||||| base\n" + lines(conflict.base) : "") +
+    "=======\n" +
+    lines(conflict.theirs) +
+    ">>>>>>> theirs"
+  );
+}
+
+/**
+ * Parameters of `create_attachment`, which are sent in a header since the
+ * image is the body of the request.
+ */
+function attachmentParams(fileName: string, existing: string[]): string {
+  const params = new URLSearchParams({ file_name: fileName });
+  for (const name of existing) params.append("existing", name);
+  return params.toString();
+}
+
+/** Approximate size of each page of cells fetched when loading a notebook. */
+const NOTEBOOK_PAGE_BYTES = 4 * 1024 * 1024;
+
+/**
+ * Decode an event from `run_cell`. Events with large outputs are sent as
+ * gzip-compressed JSON bytes instead of JSON, to speed up IPC.
+ */
+async function decodeEvent<T>(message: T | ArrayBuffer): Promise<T> {
+  if (!(message instanceof ArrayBuffer)) return message;
+  const stream = new Blob([message])
+    .stream()
+    .pipeThrough(new DecompressionStream("gzip"));
+  return JSON.parse(await new Response(stream).text());
+}
+
+/**
+ * Create a channel for the events of running cells. Await `flushed()` after
+ * the command returns, so that every event has been handled.
+ */
+function eventChannel<T>(handle: (event: T) => void | Promise<void>) {
+  const channel = new Channel<T | ArrayBuffer>();
+
+  // Large events arrive compressed and are decoded asynchronously, so
+  // handling is chained to keep events in order.
+  let pending = Promise.resolve();
+  channel.onmessage = (message) => {
+    pending = pending
+      .then(async () => handle(await decodeEvent(message)))
+      .catch((error) => console.error("Failed to handle event", error));
+  };
+  return { channel, flushed: () => pending };
+}
+
+/**
+ * Save the base64-encoded binary buffers of an output in its metadata, under
+ * `jute.buffers`, since nbformat has no field for them. Other fields under
+ * the `jute` key are kept.
+ */
+function withBuffers(
+  metadata: OutputDisplayData["metadata"],
+  buffers: string[],
+): OutputDisplayData["metadata"] {
+  if (buffers.length === 0) return metadata;
+  const jute = metadata.jute;
+  const fields =
+    jute && typeof jute === "object" && !Array.isArray(jute) ? jute : {};
+  return { ...metadata, jute: { ...fields, buffers } };
+}
+
 type NotebookStore = NotebookStoreState & NotebookStoreActions;
 
 /** Actions are kept private, only to be used from the `Notebook` class. */
@@ -30,6 +132,10 @@ export type NotebookStoreState = {
       type: CellType;
       initialText: string;
       result?: CellResult;
+      /** Metadata loaded with the cell, kept so that it is saved again. */
+      metadata?: CellMetadata;
+      /** Images attached to a Markdown cell, keyed by name. */
+      attachments?: CellAttachments;
     };
   };
 
@@ -44,12 +150,60 @@ export type NotebookStoreState = {
 
   /** ID of the running kernel, populated after the kernel is started. */
   kernelId?: string;
+
+  /** True if there are changes that have not been saved. */
+  isDirty: boolean;
+
+  /** Code formatting settings from the project's `jute.toml`, if any. */
+  format?: FormatConfig;
 };
 
 export type CellType = "code" | "markdown";
 
+/** State of a cell in the store. */
+export type NotebookCell = NotebookStoreState["cells"][string];
+
+/** Attachments of a Markdown cell, as MIME bundles keyed by name. */
+export type CellAttachments = NonNullable<MarkdownCell["attachments"]>;
+
+/** Convert a cell from nbformat to its state in the store. */
+function importCell(cell: Exclude<Cell, { cell_type: "raw" }>): NotebookCell {
+  const imported: NotebookCell = {
+    type: cell.cell_type,
+    initialText: multiline(cell.source),
+    metadata: cell.metadata,
+  };
+  if (cell.cell_type === "markdown" && cell.attachments) {
+    imported.attachments = cell.attachments;
+  }
+
+  if (cell.cell_type === "code") {
+    if (cell.execution_count || cell.outputs.length > 0) {
+      // Infer status based on the outputs of the cell.
+      const status = cell.outputs.some(
+        (output) => output.output_type === "error",
+      )
+        ? "error"
+        : "success";
+      imported.result = {
+        status,
+        outputs: cell.outputs,
+      };
+      if (cell.execution_count) {
+        imported.result.executionCount = cell.execution_count;
+      }
+    }
+  }
+
+  return imported;
+}
+
 export type CellResult = {
-  status: "running" | "success" | "error";
+  /**
+   * Cells are "queued" while waiting for earlier cells to finish, and
+   * "aborted" if they were skipped after an earlier error.
+   */
+  status: "queued" | "running" | "success" | "error" | "aborted";
   timings?: {
     startedAt: number;
     finishedAt?: number;
@@ -57,6 +211,18 @@ export type CellResult = {
   executionCount?: number;
   outputs?: Output[];
   displays?: Record<string, number>;
+  /** A prompt from the kernel waiting for input, like from `input()`. */
+  input?: InputPrompt & { password: boolean };
+  /**
+   * Outputs that were cut short, by the index of the output, with the offset
+   * of the next chunk of the rest to fetch, and the text fetched so far of a
+   * withheld value.
+   */
+  truncated?: (TruncatedOutput & {
+    index: number;
+    offset: number;
+    fetched?: string;
+  })[];
 };
 
 function notebookStoreActions(
@@ -74,12 +240,62 @@ function notebookStoreActions(
         };
       }),
 
+    /** Insert a cell at an index, as when another window adds it. */
+    insertCell: (index: number, cellId: string, cell: NotebookCell) =>
+      set((state) => {
+        state.cellIds.splice(index, 0, cellId);
+        state.cells[cellId] = cell;
+      }),
+
+    /** Remove a cell from the notebook. */
+    removeCell: (cellId: string) =>
+      set((state) => {
+        state.cellIds = state.cellIds.filter((id) => id !== cellId);
+        delete state.cells[cellId];
+      }),
+
+    /** Move a cell to an index. */
+    moveCell: (cellId: string, index: number) =>
+      set((state) => {
+        state.cellIds = state.cellIds.filter((id) => id !== cellId);
+        state.cellIds.splice(index, 0, cellId);
+      }),
+
+    /** Replace the whole state of a cell. */
+    replaceCell: (cellId: string, cell: NotebookCell) =>
+      set((state) => {
+        state.cells[cellId] = cell;
+      }),
+
+    /** Set the text of a cell whose editor hasn't been created yet. */
+    setInitialText: (cellId: string, initialText: string) =>
+      set((state) => {
+        state.cells[cellId].initialText = initialText;
+      }),
+
     /** Set the type of a cell. */
     setCellType: (cellId: string, type: CellType) =>
       set((state) => {
         state.cells[cellId].type = type;
       }),
 
+    /** Update attributes of a cell's metadata, keeping any others. */
+    updateCellMetadata: (cellId: string, update: Partial<CellMetadata>) =>
+      set((state) => {
+        const cell = state.cells[cellId];
+        cell.metadata = { ...cell.metadata, ...update };
+      }),
+
+    /** Add an attachment to a cell, replacing any with the same name. */
+    addAttachment: (cellId: string, attachment: NewAttachment) =>
+      set((state) => {
+        const cell = state.cells[cellId];
+        cell.attachments = {
+          ...cell.attachments,
+          [attachment.name]: attachment.bundle,
+        };
+      }),
+
     /** Clear the result of a cell. */
     clearResult: (cellId: string) =>
       set((state) => {
@@ -146,6 +362,65 @@ function notebookStoreActions(
         if (obj) {
           obj.outputs = [];
           obj.displays = {};
+          obj.truncated = [];
+        }
+      }),
+
+    /** Mark the last output of a cell as truncated. */
+    addTruncatedOutput: (cellId: string, truncated: TruncatedOutput) =>
+      set((state) => {
+        const obj = state.cells[cellId].result;
+        if (obj?.outputs?.length) {
+          obj.truncated ??= [];
+          obj.truncated.push({
+            ...truncated,
+            index: obj.outputs.length - 1,
+            offset: 0,
+          });
+        }
+      }),
+
+    /**
+     * Add a chunk of the rest of a truncated output to it, or with no chunk,
+     * stop tracking it since the rest is no longer available.
+     */
+    appendTruncatedChunk: (cellId: string, id: string, chunk?: OutputChunk) =>
+      set((state) => {
+        const obj = state.cells[cellId].result;
+        const truncated = obj?.truncated?.find((entry) => entry.id === id);
+        if (!obj || !truncated) return;
+        const output = obj.outputs?.[truncated.index];
+        if (chunk && output?.output_type === "stream") {
+          output.text = multiline(output.text) + chunk.text;
+        } else if (
+          chunk &&
+          (output?.output_type === "execute_result" ||
+            output?.output_type === "display_data")
+        ) {
+          const { name } = truncated;
+          if (!truncated.withheld) {
+            const text = output.data[name] as string | string[];
+            output.data[name] = multiline(text) + chunk.text;
+          } else if (chunk.remaining > 0) {
+            truncated.fetched = (truncated.fetched ?? "") + chunk.text;
+          } else {
+            output.data[name] = JSON.parse(
+              (truncated.fetched ?? "") + chunk.text,
+            );
+          }
+        }
+        truncated.offset = chunk?.next_offset ?? truncated.offset;
+        if (!chunk || chunk.remaining === 0) {
+          obj.truncated = obj.truncated?.filter((entry) => entry.id !== id);
+        }
+      }),
+
+    /** Set or clear the prompt for input shown in a cell. */
+    setInputPrompt: (cellId: string, input?: CellResult["input"]) =>
+      set((state) => {
+        const obj = state.cells[cellId].result;
+        if (obj) {
+          obj.input = input;
         }
       }),
 
@@ -169,6 +444,20 @@ function notebookStoreActions(
         }
       }),
 
+    /** Replace the contents of a `display_data` output at an index. */
+    updateOutputAt: (
+      cellId: string,
+      index: number,
+      displayData: OutputDisplayData,
+    ) =>
+      set((state) => {
+        const output = state.cells[cellId]?.result?.outputs?.[index];
+        if (output && output.output_type === "display_data") {
+          output.data = displayData.data;
+          output.metadata = displayData.metadata;
+        }
+      }),
+
     /**
      * Start loading the notebook from an external source.
      *
@@ -183,6 +472,24 @@ function notebookStoreActions(
         state.isLoading = true;
       }),
 
+    /**
+     * Show cells of a notebook that is still loading, as each page arrives.
+     * The first page replaces the current cells.
+     */
+    addLoadingCells: (cells: Cell[], first: boolean) =>
+      set((state) => {
+        if (first) {
+          state.cellIds = [];
+          state.cells = {};
+        }
+        for (const cell of cells) {
+          if (cell.cell_type === "raw") continue;
+          const cellId = cell.id ?? uuidv4();
+          state.cellIds.push(cellId);
+          state.cells[cellId] = importCell(cell);
+        }
+      }),
+
     /** Load the notebook from a JSON object. */
     loadNotebook: (notebook: NotebookRoot) =>
       set((state) => {
@@ -196,32 +503,7 @@ function notebookStoreActions(
 
         state.cellIds = cellIds;
         state.cells = Object.fromEntries(
-          cells.map((cell, i) => {
-            const imported: NotebookStoreState["cells"][string] = {
-              type: cell.cell_type,
-              initialText: multiline(cell.source),
-            };
-
-            if (cell.cell_type === "code") {
-              if (cell.execution_count || cell.outputs.length > 0) {
-                // Infer status based on the outputs of the cell.
-                const status = cell.outputs.some(
-                  (output) => output.output_type === "error",
-                )
-                  ? "error"
-                  : "success";
-                imported.result = {
-                  status,
-                  outputs: cell.outputs,
-                };
-                if (cell.execution_count) {
-                  imported.result.executionCount = cell.execution_count;
-                }
-              }
-            }
-
-            return [cellIds[i], imported];
-          }),
+          cells.map((cell, i) => [cellIds[i], importCell(cell)]),
         );
         state.isLoading = false;
         state.loadError = undefined;
@@ -239,6 +521,12 @@ function notebookStoreActions(
       set((state) => {
         state.path = path;
       }),
+
+    /** Set whether the notebook has unsaved changes. */
+    setDirty: (isDirty: boolean) =>
+      set((state) => {
+        state.isDirty = isDirty;
+      }),
   };
 }
 
@@ -251,6 +539,7 @@ function createNotebookStore(): StoreApi<NotebookStore> {
         cellIds: [],
         cells: {},
         isLoading: false,
+        isDirty: false,
       };
       const actions: NotebookStoreActions = notebookStoreActions(set);
       return { ...initialState, ...actions };
@@ -258,8 +547,23 @@ function createNotebookStore(): StoreApi<NotebookStore> {
   );
 }
 
+/** Parts of a cell as the backend document has them, to find changes. */
+type SyncedCell = {
+  source: string;
+  type: CellType;
+  metadata?: CellMetadata;
+  attachments?: CellAttachments;
+  result?: CellResult;
+};
+
+/** The notebook as the backend document has it. */
+type SyncedNotebook = { cellIds: string[]; cells: Map<string, SyncedCell> };
+
 type CellHandle = {
   editor?: EditorView;
+
+  /** Run the cell as if from its editor, e.g., rendering Markdown cells. */
+  run?: () => void;
 };
 
 /**
@@ -272,6 +576,9 @@ type CellHandle = {
  * Generally, all user actions will go through methods on this class, which may
  * dispatch to Zustand. The UI subscribes to Zustand for updates.
  */
+/** Notebook being shared on the local network from this window, if any. */
+let sharedNotebook: Notebook | null = null;
+
 export class Notebook {
   /** Promise that resolves when the kernel is started. */
   kernelStartPromise: Promise<void>;
@@ -282,17 +589,88 @@ export class Notebook {
   /** Direct handles to editors and other HTML elements after render. */
   refs: Map<string, CellHandle>;
 
-  constructor() {
+  /** ID of the cell whose editor was most recently focused. */
+  focusedCellId?: string;
+
+  /** Pending debounced writes to the crash recovery journal, by cell ID. */
+  recordTimers: Map<string, number> = new Map();
+
+
+  /** Stop sending changes to the local network share, while sharing. */
+  stopSharing?: () => void;
+
+  /** Pending debounced update to the local network share. */
+  shareTimer?: number;
+
+  /** ID of the notebook's document in the backend, once it is opened. */
+  docId?: string;
+
+  /**
+   * Unique ID of this notebook, which shows the document, so that changes it
+   * sent are told apart from those of other windows and tabs.
+   */
+  readonly viewerId = uuidv4();
+
+  /** Version of the backend document that the notebook is up to date with. */
+  version = 0;
+
+  /** The notebook as last sent to the backend document. */
+  synced: SyncedNotebook = { cellIds: [], cells: new Map() };
+
+  /** The notebook as being sent to the backend document, until it is. */
+  pendingSynced?: SyncedNotebook;
+
+  /** Stop listening for changes to the document from elsewhere. */
+  unlistenDocChanges: Promise<UnlistenFn>;
+
+  /** Pending sync to the backend document, sent at most twice a second. */
+  syncTimer?: number;
+
+  /** Syncs to the backend document, chained so that they run in order. */
+  syncing: Promise<void> = Promise.resolve();
+
+  /**
+   * Create a notebook, starting its kernel.
+   *
+   * If the notebook is at `path`, the kernel uses defaults from the project's
+   * `jute.toml`, when there is one. Passing `specName` overrides the defaults.
+   * The scratchpad instead attaches to its kernel that is already running.
+   */
+  constructor(path?: string, specName?: string, scratchpad = false) {
     const store = createNotebookStore();
     this.store = store;
     this.refs = new Map();
+    store.subscribe(() => this.scheduleSync());
+    this.unlistenDocChanges = listen<DocChangeEvent>(
+      "notebook-doc-change",
+      (event) => this.applyRemoteOps(event.payload),
+    );
+
+    this.kernelStartPromise = (async () => {
+      const kernelId = scratchpad
+        ? await invoke<string>("get_scratchpad_kernel")
+        : await invoke<string>("start_kernel", {
+            specName: specName ?? null,
+            notebookPath: path ?? null,
+          });
+      store.setState({ kernelId });
+    })();
+  }
+
+  /** Shut down the current kernel and start a new one from `specName`. */
+  async changeKernel(specName: string) {
+    const kernelId = this.state.kernelId;
+    this.store.setState({ kernelId: undefined });
+    if (kernelId) await invoke("stop_kernel", { kernelId });
 
     this.kernelStartPromise = (async () => {
       const kernelId = await invoke<string>("start_kernel", {
-        specName: "python3",
+        specName,
+        notebookPath: this.state.path ?? null,
       });
-      store.setState({ kernelId });
+      this.store.setState({ kernelId });
     })();
+    await this.kernelStartPromise;
   }
 
   /** Access the current value of the notebook store, non-reactively. */
@@ -302,41 +680,46 @@ export class Notebook {
 
   /** Save this notebook as an nbformat JSON object. */
   export(): NotebookRoot {
-    const cells: Cell[] = [];
-    const state = this.state;
-
-    for (const cellId of state.cellIds) {
-      const cell = state.cells[cellId];
-      const source = this.refs.get(cellId)?.editor?.state.doc.toString() ?? "";
-      if (cell.type === "code") {
-        cells.push({
-          cell_type: "code",
-          id: cellId,
-          source,
-          execution_count: cell.result?.executionCount ?? null,
-          outputs: cell.result?.outputs ?? [],
-          metadata: {},
-        });
-      } else if (cell.type === "markdown") {
-        cells.push({
-          cell_type: "markdown",
-          id: cellId,
-          source,
-          metadata: {},
-        });
-      } else {
-        throw new Error(`Unknown cell type: ${cell.type}`);
-      }
-    }
-
     return {
       nbformat: 4,
       nbformat_minor: 5,
       metadata: {}, // TODO: Add metadata.
-      cells,
+      cells: this.state.cellIds.map((cellId) => this.exportCell(cellId)),
     };
   }
 
+  /** Save a cell as an nbformat JSON object. */
+  exportCell(cellId: string): Cell {
+    const cell = this.state.cells[cellId];
+    const source = this.currentSource(cellId);
+    if (cell.type === "code") {
+      return {
+        cell_type: "code",
+        id: cellId,
+        source,
+        execution_count: cell.result?.executionCount ?? null,
+        outputs: cell.result?.outputs ?? [],
+        metadata: cell.metadata ?? {},
+      };
+    } else if (cell.type === "markdown") {
+      return {
+        cell_type: "markdown",
+        id: cellId,
+        source,
+        metadata: cell.metadata ?? {},
+        attachments: cell.attachments,
+      };
+    } else {
+      throw new Error(`Unknown cell type: ${cell.type}`);
+    }
+  }
+
+  /** Current source of a cell, from its editor once it has one. */
+  currentSource(cellId: string): string {
+    const editor = this.refs.get(cellId)?.editor;
+    return editor?.state.doc.toString() ?? this.state.cells[cellId].initialText;
+  }
+
   /** Load a notebook from a direct object. */
   loadNotebook(notebook: NotebookRoot) {
     this.state.loadNotebook(notebook);
@@ -351,43 +734,897 @@ export class Notebook {
       return;
     }
     try {
-      const notebook = await invoke<NotebookRoot>("get_notebook", { path });
-      this.loadNotebook(notebook);
+      const notebook = await this.fetchNotebook(path, (cells, start) => {
+        this.state.addLoadingCells(cells, start === 0);
+        this.refs = new Map(this.state.cellIds.map((id) => [id, {}]));
+      });
+      await this.recoverEdits(path, notebook);
+      this.loadNotebook(await this.openDocument(path, notebook));
+      this.resetSynced();
       this.state.setPath(path);
+      invoke<Project | null>("get_project", { path })
+        .then((project) =>
+          this.store.setState({ format: project?.config.format }),
+        )
+        .catch((e) => console.warn("failed to read project settings", e));
     } catch (e: any) {
-      this.state.setLoadError(e.toString());
+      if (!(await this.loadConflictedNotebook(path))) {
+        this.state.setLoadError(e.toString());
+      }
+    }
+  }
+
+  /**
+   * Read a notebook from disk, fetching its cells in pages of about
+   * `NOTEBOOK_PAGE_BYTES`, so that a huge file isn't sent in one message.
+   * Each page is passed to `onPage` as it arrives, to show it right away.
+   */
+  async fetchNotebook(
+    path: string,
+    onPage?: (cells: Cell[], start: number) => void,
+  ): Promise<NotebookRoot> {
+    const { load_id: loadId, cells: summaries, ...root } =
+      await invoke<NotebookOutline>("get_notebook_outline", { path });
+    const cells: Cell[] = [];
+    try {
+      for (let start = 0; start < summaries.length; ) {
+        let end = start + 1;
+        let size = summaries[start].size;
+        while (
+          end < summaries.length &&
+          size + summaries[end].size <= NOTEBOOK_PAGE_BYTES
+        ) {
+          size += summaries[end++].size;
+        }
+        const page = await invoke<Cell[] | null>("get_notebook_cells", {
+          loadId,
+          start,
+          end,
+        });
+        if (!page) throw new Error("Notebook load was interrupted");
+        cells.push(...page);
+        onPage?.(page, start);
+        start = end;
+      }
+    } finally {
+      invoke("finish_notebook_load", { loadId }).catch(() => {});
+    }
+    return { ...root, cells };
+  }
+
+  /**
+   * Load a notebook with git conflict markers, merged cell by cell. Returns
+   * false if the file has no conflicts to resolve.
+   */
+  async loadConflictedNotebook(path: string): Promise<boolean> {
+    const merge = await invoke<NotebookMerge | null>(
+      "resolve_notebook_conflicts",
+      { path },
+    ).catch(() => null);
+    if (!merge) return false;
+    // Keep both sides of conflicting cells, so neither is lost on save.
+    const conflicts = new Map(merge.conflicts.map((c) => [c.cell_id, c]));
+    for (const cell of merge.notebook.cells) {
+      const conflict = cell.id ? conflicts.get(cell.id) : undefined;
+      if (conflict) cell.source = withConflictMarkers(conflict);
+    }
+    this.loadNotebook(await this.openDocument(path, merge.notebook));
+    this.resetSynced();
+    this.state.setPath(path);
+    this.markDirty(true);
+    const count = merge.conflicts.length;
+    await message(
+      count === 0
+        ? "The merge conflicts in this notebook were resolved automatically."
+        : `${count} cell${count === 1 ? " has" : "s have"} conflicting ` +
+            "changes, marked in the cell like git does. Resolve them before " +
+            "saving.",
+      { title: "Merge conflicts", kind: count === 0 ? "info" : "warning" },
+    );
+    return true;
+  }
+
+  /** Save the notebook to its path, or prompt for one if it has none. */
+  async save() {
+    const path = this.state.path;
+    if (!path) return await this.saveAs();
+    if (!(await this.restoreTruncatedOutputs())) return;
+    await this.syncDocument();
+    await invoke("save_document", { docId: this.docId, path });
+    await invoke("discard_recovery", { path });
+    this.markDirty(false);
+    invoke("create_checkpoint", { path }).catch((e) =>
+      console.warn("failed to create checkpoint", e),
+    );
+  }
+
+  /**
+   * Roll the notebook back to a checkpoint, after asking to discard unsaved
+   * changes. The file as it was is checkpointed, so this can be undone.
+   */
+  async restoreCheckpoint(checkpointId: string) {
+    const path = this.state.path;
+    if (!path) return;
+    if (
+      this.state.isDirty &&
+      !(await ask("Discard your unsaved changes and revert the notebook?", {
+        title: "Revert to Checkpoint",
+        kind: "warning",
+      }))
+    ) {
+      return;
     }
+    const notebook = await invoke<NotebookRoot>("restore_checkpoint", {
+      path,
+      checkpointId,
+    });
+    await invoke("discard_recovery", { path });
+    this.loadNotebook(notebook);
+    this.markDirty(false);
+  }
+
+  /** Track unsaved changes, so the window's menu can show them. */
+  markDirty(isDirty: boolean) {
+    if (this.state.isDirty !== isDirty) this.state.setDirty(isDirty);
+  }
+
+  /** Prompt for a new path and save the notebook there. */
+  async saveAs() {
+    const path = await save({
+      defaultPath: this.state.path,
+      filters: [
+        { name: "Jupyter Notebook", extensions: ["ipynb"] },
+        { name: "Python Script (Percent Format)", extensions: ["py"] },
+      ],
+    });
+    if (!path) return;
+    const hasOutputs = Object.values(this.state.cells).some(
+      (cell) => cell.result?.outputs?.length,
+    );
+    if (
+      path.endsWith(".py") &&
+      hasOutputs &&
+      !(await ask(
+        "Python scripts do not store outputs, so they will be lost when the notebook is reopened. Save as a script anyway?",
+        { title: "Save As", kind: "warning" },
+      ))
+    ) {
+      return;
+    }
+    this.state.setPath(path);
+    await this.save();
+  }
+
+  /** Export the notebook as a reveal.js slideshow, prompting for a path. */
+  async exportSlides() {
+    const path = await save({
+      defaultPath: this.state.path?.replace(/\.ipynb$/, ".slides.html"),
+      filters: [{ name: "HTML", extensions: ["html"] }],
+    });
+    if (!path) return;
+    await this.syncDocument();
+    await invoke("export_slides", { path, docId: this.docId });
+  }
+
+  /**
+   * Open the notebook's document in the backend, which is the source of truth
+   * for saving and exporting it. Returns the contents of the document, which
+   * are shared with another window that has the same file open.
+   */
+  openDocument(
+    path: string | undefined,
+    notebook: NotebookRoot,
+  ): Promise<NotebookRoot> {
+    const opened = this.syncing
+      .catch(() => {})
+      .then(() => this.replaceDocument(path, notebook));
+    this.syncing = opened.then(() => {});
+    return opened;
+  }
+
+  /**
+   * Close the current document, if any, and open another one. The document
+   * is shared with other windows and tabs that show the file, unless `share`
+   * is false.
+   */
+  private async replaceDocument(
+    path: string | undefined,
+    notebook: NotebookRoot,
+    share = true,
+  ): Promise<NotebookRoot> {
+    this.closeDocument();
+    const doc = await invoke<DocSnapshot>("open_document", {
+      path: path ?? null,
+      notebook,
+      viewer: this.viewerId,
+      share,
+    });
+    this.docId = doc.doc_id;
+    this.version = doc.version;
+    return doc.notebook;
+  }
+
+  /** Stop showing the current document, if any. */
+  private closeDocument() {
+    if (!this.docId) return;
+    const docId = this.docId;
+    this.docId = undefined;
+    invoke("close_document", { docId, viewer: this.viewerId }).catch(() => {});
+  }
+
+  /**
+   * Release the notebook's document and stop listening for changes to it,
+   * once its tab or window closes.
+   */
+  dispose() {
+    clearTimeout(this.syncTimer);
+    this.syncTimer = undefined;
+    this.unlistenDocChanges.then((unlisten) => unlisten());
+    this.syncing = this.syncing
+      .catch(() => {})
+      .then(() => this.closeDocument());
+  }
+
+  /** Send changes to the backend document soon, at most twice a second. */
+  scheduleSync() {
+    if (this.syncTimer !== undefined || this.state.isLoading) return;
+    this.syncTimer = window.setTimeout(() => {
+      this.syncDocument().catch((e) =>
+        console.warn("failed to sync notebook document", e),
+      );
+    }, 500);
+  }
+
+  /** Send changes to the backend document, opening it on first use. */
+  syncDocument(): Promise<void> {
+    clearTimeout(this.syncTimer);
+    this.syncTimer = undefined;
+    this.syncing = this.syncing
+      .catch(() => {})
+      .then(async () => {
+        if (this.state.isLoading) return;
+        if (!this.docId) {
+          await this.replaceDocument(this.state.path, this.export());
+          this.resetSynced();
+          return;
+        }
+        const ops = this.diffOps();
+        if (ops.length === 0) return;
+        this.pendingSynced = this.currentSynced();
+        try {
+          await invoke("apply_document_ops", {
+            docId: this.docId,
+            ops,
+            origin: this.viewerId,
+          });
+          this.synced = this.pendingSynced;
+        } catch (e) {
+          // The document rejected a change, such as an edit to a cell that
+          // another window deleted. Keep this notebook's changes in a
+          // document of its own, so that they aren't lost when it is saved.
+          await this.replaceDocument(this.state.path, this.export(), false);
+          this.resetSynced();
+          throw e;
+        } finally {
+          this.pendingSynced = undefined;
+        }
+      });
+    return this.syncing;
+  }
+
+  /**
+   * Send any changes, then update the notebook from its backend document,
+   * after changes to it from elsewhere were missed.
+   */
+  resync(): Promise<void> {
+    this.syncDocument().catch(() => {});
+    this.syncing = this.syncing
+      .catch(() => {})
+      .then(async () => {
+        if (!this.docId) return;
+        const doc = await invoke<DocSnapshot>("get_document", {
+          docId: this.docId,
+        });
+        if (doc.doc_id !== this.docId) return;
+        const ids = new Set(doc.notebook.cells.map((cell) => cell.id));
+        for (const cellId of this.state.cellIds) {
+          if (ids.has(cellId)) continue;
+          this.state.removeCell(cellId);
+          this.refs.delete(cellId);
+        }
+        let index = 0;
+        for (const cell of doc.notebook.cells) {
+          if (cell.cell_type === "raw" || !cell.id) continue;
+          if (this.state.cells[cell.id]) {
+            this.state.replaceCell(cell.id, importCell(cell));
+            this.setCellSource(cell.id, multiline(cell.source));
+            this.state.moveCell(cell.id, index);
+          } else {
+            this.refs.set(cell.id, {});
+            this.state.insertCell(index, cell.id, importCell(cell));
+          }
+          index++;
+        }
+        this.version = doc.version;
+        this.resetSynced();
+      });
+    return this.syncing;
+  }
+
+  /** A cell as it is now, to compare with later changes. */
+  syncedCell(cellId: string): SyncedCell {
+    const { type, metadata, attachments, result } = this.state.cells[cellId];
+    const source = this.currentSource(cellId);
+    return { source, type, metadata, attachments, result };
+  }
+
+  /** The notebook as it is now, to compare with later changes. */
+  currentSynced(): SyncedNotebook {
+    const cellIds = [...this.state.cellIds];
+    const cells = cellIds.map((id) => [id, this.syncedCell(id)] as const);
+    return { cellIds, cells: new Map(cells) };
+  }
+
+  /** Mark the notebook as it is now as synced with the backend document. */
+  resetSynced() {
+    this.synced = this.currentSynced();
+  }
+
+  /** Find the changes since the last sync, as document operations. */
+  diffOps(): NotebookOp[] {
+    const ops: NotebookOp[] = [];
+    const { cellIds, cells } = this.state;
+    const order = this.synced.cellIds.filter((cellId) => {
+      if (cells[cellId]) return true;
+      ops.push({ op: "delete_cell", cell_id: cellId });
+      return false;
+    });
+    cellIds.forEach((cellId, index) => {
+      const prev = this.synced.cells.get(cellId);
+      const cell = cells[cellId];
+      if (!prev) {
+        ops.push({ op: "insert_cell", index, cell: this.exportCell(cellId) });
+        order.splice(index, 0, cellId);
+        return;
+      }
+      if (order[index] !== cellId) {
+        ops.push({ op: "move_cell", cell_id: cellId, index });
+        order.splice(order.indexOf(cellId), 1);
+        order.splice(index, 0, cellId);
+      }
+      if (
+        cell.type !== prev.type ||
+        cell.metadata !== prev.metadata ||
+        cell.attachments !== prev.attachments
+      ) {
+        ops.push({ op: "replace_cell", cell: this.exportCell(cellId) });
+        return;
+      }
+      const source = this.currentSource(cellId);
+      if (source !== prev.source) {
+        ops.push({ op: "edit_source", cell_id: cellId, source });
+      }
+      if (cell.type === "code" && cell.result !== prev.result) {
+        ops.push({
+          op: "set_outputs",
+          cell_id: cellId,
+          outputs: cell.result?.outputs ?? [],
+          execution_count: cell.result?.executionCount ?? null,
+        });
+      }
+    });
+    return ops;
+  }
+
+  /**
+   * Apply changes to the document that were made in another window or tab.
+   * Changes arrive in order, so one that doesn't follow the version the
+   * notebook has means some were missed, and it is updated from the document.
+   */
+  applyRemoteOps({ doc_id, version, ops, origin }: DocChangeEvent) {
+    if (doc_id !== this.docId || version <= this.version) return;
+    if (version - ops.length !== this.version) {
+      this.resync().catch((e) =>
+        console.warn("failed to resync notebook document", e),
+      );
+      return;
+    }
+    this.version = version;
+    if (origin === this.viewerId) return;
+    // Changes may arrive while this notebook's own are being sent.
+    const synced = this.pendingSynced
+      ? [this.synced, this.pendingSynced]
+      : [this.synced];
+    for (const op of ops) {
+      if (op.op === "insert_cell" || op.op === "replace_cell") {
+        const { cell } = op;
+        if (cell.cell_type === "raw" || !cell.id) continue;
+        const cellId = cell.id;
+        if (op.op === "insert_cell") {
+          this.refs.set(cellId, {});
+          this.state.insertCell(op.index, cellId, importCell(cell));
+          for (const s of synced) s.cellIds.splice(op.index, 0, cellId);
+        } else if (this.state.cells[cellId]) {
+          this.state.replaceCell(cellId, importCell(cell));
+          this.setCellSource(cellId, multiline(cell.source));
+        }
+        for (const s of synced) s.cells.set(cellId, this.syncedCell(cellId));
+      } else if (op.op === "delete_cell") {
+        this.state.removeCell(op.cell_id);
+        this.refs.delete(op.cell_id);
+        for (const s of synced) {
+          s.cellIds = s.cellIds.filter((id) => id !== op.cell_id);
+          s.cells.delete(op.cell_id);
+        }
+      } else if (op.op === "move_cell") {
+        this.state.moveCell(op.cell_id, op.index);
+        for (const s of synced) {
+          s.cellIds = s.cellIds.filter((id) => id !== op.cell_id);
+          s.cellIds.splice(op.index, 0, op.cell_id);
+        }
+      } else if (op.op === "edit_source") {
+        if (!this.state.cells[op.cell_id]) continue;
+        this.setCellSource(op.cell_id, op.source);
+        for (const s of synced) {
+          s.cells.set(op.cell_id, this.syncedCell(op.cell_id));
+        }
+      } else if (op.op === "set_outputs") {
+        const cell = this.state.cells[op.cell_id];
+        if (!cell) continue;
+        const error = op.outputs.some((o) => o.output_type === "error");
+        const result: CellResult = {
+          status: error ? "error" : "success",
+          outputs: op.outputs,
+          executionCount: op.execution_count ?? undefined,
+        };
+        this.state.replaceCell(op.cell_id, { ...cell, result });
+        for (const s of synced) {
+          s.cells.set(op.cell_id, this.syncedCell(op.cell_id));
+        }
+      }
+    }
+  }
+
+  /** Replace the source of a cell, in its editor if it has one. */
+  setCellSource(cellId: string, source: string) {
+    const editor = this.refs.get(cellId)?.editor;
+    if (!editor) return this.state.setInitialText(cellId, source);
+    if (editor.state.doc.toString() === source) return;
+    const changes = { from: 0, to: editor.state.doc.length, insert: source };
+    editor.dispatch({ changes });
+  }
+
+  /**
+   * Share the notebook read-only on the local network, or stop sharing.
+   *
+   * Only one notebook per window is shared, so if another tab is sharing,
+   * this stops that one instead.
+   */
+  async toggleSharing() {
+    if (sharedNotebook) {
+      sharedNotebook.stopSharing?.();
+      await invoke("stop_share");
+      return;
+    }
+    const url = await invoke<string>("start_share", {
+      notebook: this.export(),
+    });
+    sharedNotebook = this;
+    const unsubscribe = this.store.subscribe(() => this.updateShare());
+    this.stopSharing = () => {
+      unsubscribe();
+      clearTimeout(this.shareTimer);
+      this.stopSharing = undefined;
+      if (sharedNotebook === this) sharedNotebook = null;
+    };
+    await message(
+      `Anyone on your network can follow this notebook at:\n\n${url}`,
+      { title: "Sharing on Local Network" },
+    );
+  }
+
+  /** Send the notebook to the local network share, debounced. */
+  updateShare() {
+    if (!this.stopSharing) return;
+    clearTimeout(this.shareTimer);
+    this.shareTimer = window.setTimeout(() => {
+      invoke("update_share", { notebook: this.export() }).catch((e) =>
+        console.warn("failed to update shared notebook", e),
+      );
+    }, 500);
+  }
+
+  /** Offer to restore unsaved changes left over from a crash, if any. */
+  async recoverEdits(path: string, notebook: NotebookRoot) {
+    const recovery = await invoke<Recovery | null>("get_notebook_recovery", {
+      path,
+    });
+    if (!recovery) return;
+
+    const restore = await ask(
+      "This notebook has unsaved changes from a previous session that did not exit cleanly. Do you want to restore them?",
+      { title: "Recover Unsaved Changes", kind: "warning" },
+    );
+    if (restore) {
+      if (recovery.snapshot) Object.assign(notebook, recovery.snapshot);
+      // Cells without IDs in the file are given IDs from their source on
+      // load, so edits to them are matched as long as the file is unchanged.
+      for (const cell of notebook.cells) {
+        const source = cell.id ? recovery.edits[cell.id] : undefined;
+        if (source !== undefined) cell.source = source;
+      }
+    } else {
+      await invoke("discard_recovery", { path });
+    }
+  }
+
+  /** Journal an unsaved edit to a cell, debounced while the user types. */
+  recordEdit(cellId: string) {
+    this.markDirty(true);
+    this.updateShare();
+    this.scheduleSync();
+    const path = this.state.path;
+    if (!path) return;
+    clearTimeout(this.recordTimers.get(cellId));
+    this.recordTimers.set(
+      cellId,
+      window.setTimeout(() => {
+        this.recordTimers.delete(cellId);
+        const source =
+          this.refs.get(cellId)?.editor?.state.doc.toString() ?? "";
+        invoke("record_cell_edit", { path, cellId, source }).catch((e) =>
+          console.warn("failed to record edit for recovery", e),
+        );
+      }, 500),
+    );
+  }
+
+  /** Find a cell by its ID, or by the anchor of a Markdown heading in it. */
+  findCell(anchor: string): string | undefined {
+    const state = this.state;
+    if (state.cells[anchor]) return anchor;
+    return state.cellIds.find((cellId) => {
+      const cell = state.cells[cellId];
+      if (cell.type !== "markdown") return false;
+      const source =
+        this.refs.get(cellId)?.editor?.state.doc.toString() ??
+        cell.initialText;
+      return source.split("\n").some((line) => {
+        const heading = line.match(/^#{1,6}\s+(.*)$/);
+        return heading && headingAnchor(heading[1]) === anchor;
+      });
+    });
+  }
+
+  /** Scroll to a cell by its ID or heading anchor, and focus its editor. */
+  focusCell(anchor: string) {
+    const cellId = this.findCell(anchor);
+    if (!cellId) return;
+    this.focusedCellId = cellId;
+    // Wait for the cells to be rendered, such as right after loading.
+    requestAnimationFrame(() => {
+      document
+        .querySelector(`[data-cell-id="${CSS.escape(cellId)}"]`)
+        ?.scrollIntoView({ block: "start" });
+      this.refs.get(cellId)?.editor?.focus();
+    });
   }
 
   addCell(type: CellType, initialText: string): string {
     const cellId = Math.random().toString(36).slice(2);
     this.refs.set(cellId, {});
     this.state.addCell(cellId, type, initialText);
+    this.markDirty(true);
     return cellId;
   }
 
   setCellType(cellId: string, type: CellType) {
     this.state.setCellType(cellId, type);
+    this.markDirty(true);
   }
 
   clearResult(cellId: string) {
     this.state.clearResult(cellId);
   }
 
-  async execute(cellId: string) {
+  /** Update a cell's metadata, which is saved with the notebook. */
+  updateCellMetadata(cellId: string, update: Partial<CellMetadata>) {
+    this.state.updateCellMetadata(cellId, update);
+    this.markDirty(true);
+  }
+
+  /**
+   * Attach an image to a Markdown cell, and insert a link to it at the cursor.
+   * The image is pasted data, or a file dropped into the cell by path.
+   */
+  async attachImage(
+    cellId: string,
+    image: { fileName: string; data: Uint8Array } | { path: string },
+  ) {
+    const existing = Object.keys(this.state.cells[cellId].attachments ?? {});
+    const attachment =
+      "path" in image
+        ? await invoke<NewAttachment>("attach_file", {
+            path: image.path,
+            existing,
+          })
+        : await invoke<NewAttachment>("create_attachment", image.data, {
+            headers: {
+              "Jute-Attachment": attachmentParams(image.fileName, existing),
+            },
+          });
+    this.state.addAttachment(cellId, attachment);
+    const editor = this.refs.get(cellId)?.editor;
+    editor?.dispatch(editor.state.replaceSelection(attachment.markdown));
+    this.markDirty(true);
+  }
+
+  /** List the attachments of a Markdown cell, and whether each is used. */
+  async listAttachments(cellId: string): Promise<AttachmentInfo[]> {
+    const attachments = this.state.cells[cellId].attachments;
+    if (!attachments) return [];
+    const source = this.refs.get(cellId)?.editor?.state.doc.toString() ?? "";
+    return await invoke("list_attachments", { source, attachments });
+  }
+
+  /** Collapse or expand the outputs of all code cells. */
+  setAllOutputsCollapsed(collapsed: boolean) {
+    for (const cellId of this.state.cellIds) {
+      const cell = this.state.cells[cellId];
+      if (
+        cell.type === "code" &&
+        (cell.metadata?.collapsed ?? false) !== collapsed
+      ) {
+        this.updateCellMetadata(cellId, { collapsed });
+      }
+    }
+  }
+
+  /** Toggle whether long outputs of a cell scroll, which they do by default. */
+  toggleOutputScrolling(cellId: string) {
+    const scrolled = this.state.cells[cellId].metadata?.scrolled ?? true;
+    this.updateCellMetadata(cellId, { scrolled: !scrolled });
+  }
+
+  /** Run a single cell, the same way as the shortcut in its editor. */
+  async runCell(cellId: string) {
+    const run = this.refs.get(cellId)?.run;
+    if (run) run();
+    else await this.execute(cellId);
+  }
+
+  /**
+   * Run a code cell from its editor. In reactive mode, the cells that depend
+   * on its variables are then re-run, to keep their outputs consistent.
+   */
+  async executeReactive(cellId: string) {
+    await this.execute(cellId);
+    const settings = await invoke<Settings>("get_settings");
+    if (!settings.reactive_execution) return;
+    if (this.state.cells[cellId].result?.status !== "success") return;
+    const dependents = await invoke<string[]>("get_dependent_cells", {
+      notebook: this.export(),
+      cellId,
+    });
+    await this.runCells(dependents);
+  }
+
+  /**
+   * Run code cells in order, stopping at the first error.
+   *
+   * This is tracked as a background task, so progress shows on the Dock icon
+   * or taskbar while the user is in another app.
+   */
+  async runCells(cellIds: string[]) {
+    const codeCellIds = cellIds.filter(
+      (cellId) => this.state.cells[cellId].type === "code",
+    );
+    if (codeCellIds.length === 0) return;
+    if (!this.state.kernelId) {
+      await this.kernelStartPromise;
+    }
+
+    // Consecutive kernel cells run as one batch, and SQL cells between them.
+    const groups: { sql: boolean; cellIds: string[] }[] = [];
+    for (const cellId of codeCellIds) {
+      const sql = isSqlCell(this.cellSource(cellId));
+      const last = groups.at(-1);
+      if (last && !last.sql && !sql) last.cellIds.push(cellId);
+      else groups.push({ sql, cellIds: [cellId] });
+    }
+
+    const name = this.state.path?.split(/[/\\]/).pop() ?? "Untitled";
+    const taskId = await invoke<string>("start_task", {
+      kind: "run_cells",
+      title: `Running ${name}`,
+    });
+    let finished = 0;
+    const onFinish = async () => {
+      finished++;
+      if (finished === codeCellIds.length) return;
+      await invoke("set_task_progress", {
+        taskId,
+        progress: finished / codeCellIds.length,
+        message: `Cell ${finished + 1} of ${codeCellIds.length}`,
+      });
+    };
+
+    let error: string | null = null;
+    try {
+      for (const group of groups) {
+        let ok: boolean;
+        if (group.sql) {
+          const [cellId] = group.cellIds;
+          await this.execute(cellId);
+          await onFinish();
+          ok = this.state.cells[cellId].result?.status === "success";
+        } else {
+          ok = await this.runBatch(group.cellIds, onFinish);
+        }
+        if (!ok) {
+          await invoke("add_task_failure", { taskId });
+          break;
+        }
+      }
+    } catch (e: any) {
+      error = e.toString();
+      throw e;
+    } finally {
+      await invoke("finish_task", { taskId, error });
+    }
+  }
+
+  /**
+   * Run kernel cells in order with `run_all_cells`, streaming their events
+   * over one channel, and return whether they all succeeded. The cells after
+   * one that fails are aborted.
+   */
+  private async runBatch(
+    cellIds: string[],
+    onFinish: () => Promise<void>,
+  ): Promise<boolean> {
+    const kernelId = this.state.kernelId;
+    const cells = cellIds.map((cellId) => ({
+      cell_id: cellId,
+      source: this.cellSource(cellId),
+    }));
+    const runs = cellIds.map((cellId) => this.trackRun(cellId));
+
+    // Events arrive in order, so the cells before one with an event are done.
+    let current = 0;
+    const finishUntil = async (index: number) => {
+      for (; current < index; current++) {
+        runs[current].finish();
+        await onFinish();
+      }
+    };
+
+    let unlisten: UnlistenFn | undefined;
+    try {
+      unlisten = await listen<CellStateEvent>("cell-state", (event) => {
+        const { payload } = event;
+        const index = cellIds.indexOf(payload.cell_id);
+        if (
+          payload.kernel_id === kernelId &&
+          index !== -1 &&
+          payload.state === "running"
+        ) {
+          runs[index].start();
+        }
+      });
+
+      const { channel, flushed } = eventChannel<BatchEvent>(async (message) => {
+        const { cell_index, ...event } = message;
+        await finishUntil(cell_index);
+        runs[cell_index].handleEvent(event as RunCellEvent);
+      });
+      await invoke("run_all_cells", {
+        kernelId,
+        batch: { cells },
+        stopOnError: true,
+        onEvent: channel,
+      });
+      await flushed();
+    } catch (error: any) {
+      // Show the error in the cell that was running, and abort the rest.
+      runs[current]?.fail(error);
+      for (const run of runs.slice(current + 1)) {
+        run.handleEvent({ event: "aborted" });
+      }
+    } finally {
+      unlisten?.();
+      await finishUntil(runs.length);
+    }
+    return runs.every((run) => run.status() === "success");
+  }
+
+  /** Run all code cells in the notebook. */
+  async runAll() {
+    await this.runCells(this.state.cellIds);
+  }
+
+  /** Run all code cells above a cell, not including it. */
+  async runAllAbove(cellId: string) {
+    const index = this.state.cellIds.indexOf(cellId);
+    await this.runCells(this.state.cellIds.slice(0, index));
+  }
+
+  /** Run a cell and all code cells below it. */
+  async runAllBelow(cellId: string) {
+    const index = this.state.cellIds.indexOf(cellId);
+    await this.runCells(this.state.cellIds.slice(index));
+  }
+
+  /** Run the selected text in a cell, or the current line if empty. */
+  async runSelectedText(cellId: string) {
+    const editor = this.refs.get(cellId)?.editor;
+    if (!editor || this.state.cells[cellId].type !== "code") return;
+    const { from, to } = editor.state.selection.main;
+    const code =
+      from === to
+        ? editor.state.doc.lineAt(from).text
+        : editor.state.sliceDoc(from, to);
+    await this.execute(cellId, code);
+  }
+
+  /** Execute a code cell, or other `code` with outputs shown in the cell. */
+  async execute(cellId: string, code?: string) {
     if (!this.state.kernelId) {
       await this.kernelStartPromise;
     }
+    code ??= this.cellSource(cellId);
+
+    const run = this.trackRun(cellId);
+    let unlisten: UnlistenFn | undefined;
+    try {
+      if (isSqlCell(code)) {
+        run.start();
+        if (!(await this.executeSql(cellId, code))) run.fail();
+        return;
+      }
+
+      // The cell waits in the kernel's queue until earlier cells finish.
+      const kernelId = this.state.kernelId;
+      unlisten = await listen<CellStateEvent>("cell-state", (event) => {
+        const { payload } = event;
+        if (
+          payload.kernel_id === kernelId &&
+          payload.cell_id === cellId &&
+          payload.state === "running"
+        ) {
+          run.start();
+        }
+      });
+
+      const { channel, flushed } = eventChannel(run.handleEvent);
+      await invoke("run_cell", { kernelId, code, cellId, onEvent: channel });
+      await flushed();
+    } catch (error: any) {
+      run.fail(error);
+    } finally {
+      unlisten?.();
+      run.finish();
+    }
+  }
 
+  /** Return the current source code in the editor of a cell. */
+  private cellSource(cellId: string): string {
     const editor = this.refs.get(cellId)?.editor;
     if (!editor) {
       throw new Error(`Cell ${cellId} not found`);
     }
-    const code = editor.state.doc.toString();
+    return editor.state.doc.toString();
+  }
 
-    let status: CellResult["status"] = "running";
-    let timings: CellResult["timings"] = { startedAt: Date.now() };
+  /**
+   * Track a run of a cell, which starts out queued, and apply its events to
+   * the cell's result and outputs.
+   */
+  private trackRun(cellId: string) {
+    let status: CellResult["status"] = "queued";
+    let timings: CellResult["timings"] = undefined;
     let executionCount: CellResult["executionCount"] = undefined;
+    let duration: number | undefined;
 
     const update = () =>
       this.state.updateResult(cellId, {
@@ -398,94 +1635,318 @@ export class Notebook {
     update();
     this.state.clearOutput(cellId);
 
-    let willClearOutput = false;
-
-    try {
-      const onEvent = new Channel<RunCellEvent>();
+    const start = () => {
+      if (status !== "queued") return;
+      status = "running";
+      timings = { startedAt: Date.now() };
+      update();
+    };
 
-      onEvent.onmessage = (message: RunCellEvent) => {
-        if (willClearOutput) {
-          this.state.clearOutput(cellId);
-          willClearOutput = false;
+    const handleEvent = (message: RunCellEvent) => {
+      if (message.event === "stdout" || message.event === "stderr") {
+        this.state.appendOutput(cellId, {
+          output_type: "stream",
+          name: message.event,
+          text: message.data,
+        });
+      } else if (message.event === "error") {
+        status = "error";
+        update();
+        this.state.appendOutput(cellId, {
+          output_type: "error",
+          ename: message.data.ename,
+          evalue: message.data.evalue,
+          traceback: message.data.traceback,
+        });
+      } else if (message.event === "execute_result") {
+        // This means that there was a return value for the cell.
+        executionCount = message.data.execution_count;
+        update();
+        this.state.appendOutput(cellId, {
+          output_type: "execute_result",
+          execution_count: message.data.execution_count,
+          data: message.data.data,
+          metadata: withBuffers(message.data.metadata, message.data.buffers),
+        });
+      } else if (message.event === "display_data") {
+        const displayId = message.data.transient?.display_id || uuidv4();
+        this.state.appendOutput(
+          cellId,
+          {
+            output_type: "display_data",
+            data: message.data.data,
+            metadata: withBuffers(message.data.metadata, message.data.buffers),
+          },
+          displayId,
+        );
+      } else if (message.event === "update_display_data") {
+        const displayId = message.data.transient?.display_id;
+        if (displayId) {
+          this.state.updateOutputDisplay(cellId, displayId, {
+            data: message.data.data,
+            metadata: withBuffers(message.data.metadata, message.data.buffers),
+          });
         }
+      } else if (message.event === "clear_output") {
+        // The backend holds clears that wait until the next output, and
+        // sends them right before it.
+        this.state.clearOutput(cellId);
+      } else if (message.event === "aborted") {
+        status = "aborted";
+        update();
+      } else if (message.event === "output_dropped") {
+        this.state.appendOutput(cellId, {
+          output_type: "stream",
+          name: "stderr",
+          text: `[${message.data} outputs dropped]\n`,
+        });
+      } else if (message.event === "update_output") {
+        // Resolved by the backend, and may be in an earlier cell.
+        this.state.updateOutputAt(
+          message.data.cell_id,
+          message.data.index,
+          message.data.output,
+        );
+      } else if (
+        message.event === "input_request" ||
+        message.event === "password_request"
+      ) {
+        this.state.setInputPrompt(cellId, {
+          ...message.data,
+          password: message.event === "password_request",
+        });
+      } else if (message.event === "output_truncated") {
+        this.state.addTruncatedOutput(cellId, message.data);
+      } else if (message.event === "started") {
+        start();
+      } else if (message.event === "execution_count") {
+        executionCount = message.data;
+        update();
+      } else if (message.event === "finished") {
+        duration = message.data.duration;
+      } else if (message.event === "custom") {
+        console.debug(
+          `Custom kernel message: ${message.data.msg_type}`,
+          message.data.content,
+        );
+      } else {
+        console.warn("Skipping unhandled event", message);
+      }
+    };
 
-        if (message.event === "stdout" || message.event === "stderr") {
-          this.state.appendOutput(cellId, {
-            output_type: "stream",
-            name: message.event,
-            text: message.data,
-          });
-        } else if (message.event === "error") {
-          status = "error";
-          update();
+    return {
+      start,
+      handleEvent,
+
+      /** The status of the run so far. */
+      status: () => status,
+
+      /**
+       * Mark the run as failed. An error from the backend, such as a kernel
+       * disconnect, is shown as an error output.
+       */
+      fail: (error?: unknown) => {
+        status = "error";
+        update();
+        if (error !== undefined) {
           this.state.appendOutput(cellId, {
             output_type: "error",
-            ename: message.data.ename,
-            evalue: message.data.evalue,
-            traceback: message.data.traceback,
-          });
-        } else if (message.event === "execute_result") {
-          // This means that there was a return value for the cell.
-          executionCount = message.data.execution_count;
-          update();
-          this.state.appendOutput(cellId, {
-            output_type: "execute_result",
-            execution_count: message.data.execution_count,
-            data: message.data.data,
-            metadata: message.data.metadata,
+            ename: "InternalError",
+            evalue: String(error),
+            traceback: [],
           });
-        } else if (message.event === "display_data") {
-          const displayId = message.data.transient?.display_id || uuidv4();
-          this.state.appendOutput(
-            cellId,
-            {
-              output_type: "display_data",
-              data: message.data.data,
-              metadata: message.data.metadata,
-            },
-            displayId,
-          );
-        } else if (message.event === "update_display_data") {
-          const displayId = message.data.transient?.display_id;
-          if (displayId) {
-            this.state.updateOutputDisplay(cellId, displayId, {
-              data: message.data.data,
-              metadata: message.data.metadata,
-            });
-          }
-        } else if (message.event === "clear_output") {
-          if (message.data.wait) {
-            willClearOutput = true;
-          } else {
-            this.state.clearOutput(cellId);
-          }
-        } else {
-          console.warn("Skipping unhandled event", message);
         }
-      };
+      },
+
+      /** Finish the run, after all of its events were handled. */
+      finish: () => {
+        if (status === "queued" || status === "running") {
+          status = "success";
+        }
+        // The kernel's own wall time leaves out the time spent sending
+        // messages, like jupyterlab-execute-time.
+        const now = Date.now();
+        const startedAt = timings?.startedAt ?? now;
+        const finishedAt = startedAt + (duration ?? now - startedAt);
+        timings = { startedAt, finishedAt };
+        update();
+        this.state.setInputPrompt(cellId, undefined);
+      },
+    };
+  }
 
-      await invoke("run_cell", {
+  /**
+   * Fetch the next chunk of the rest of a truncated output in a cell, or all
+   * of it. A withheld value is always fetched whole, since it can't be shown
+   * in part. Returns false if the rest is no longer kept by the kernel.
+   */
+  async expandOutput(cellId: string, outputId: string, all = false) {
+    const find = () =>
+      this.state.cells[cellId].result?.truncated?.find(
+        (entry) => entry.id === outputId,
+      );
+    for (let truncated = find(); truncated; truncated = find()) {
+      const chunk = await invoke<OutputChunk | null>("fetch_output_chunk", {
         kernelId: this.state.kernelId,
-        code,
-        onEvent,
+        outputId,
+        offset: truncated.offset,
       });
-      if (status === "running") {
-        status = "success";
+      this.state.appendTruncatedChunk(cellId, outputId, chunk ?? undefined);
+      if (!chunk) return false;
+      if (!all && !truncated.withheld) break;
+    }
+    return true;
+  }
+
+  /**
+   * Fetch the rest of every truncated output, so that the notebook is saved
+   * with all of them. If some are no longer kept by the kernel, asks whether
+   * to save them shortened, returning false if not.
+   */
+  private async restoreTruncatedOutputs(): Promise<boolean> {
+    let lost = 0;
+    for (const cellId of this.state.cellIds) {
+      const truncated = this.state.cells[cellId].result?.truncated ?? [];
+      for (const { id } of truncated) {
+        if (!(await this.expandOutput(cellId, id, true))) lost++;
       }
-    } catch (error: any) {
-      status = "error";
-      // Synthesize an error output for kernel disconnects or other errors.
-      this.state.appendOutput(cellId, {
-        output_type: "error",
-        ename: "InternalError",
-        evalue: error.toString(),
-        traceback: [],
-      });
-    } finally {
-      timings = { ...timings, finishedAt: Date.now() };
-      update();
     }
+    if (lost === 0) return true;
+    return await ask(
+      `${lost} truncated output${lost === 1 ? " is" : "s are"} no longer ` +
+        "available in full. Save the shortened output anyway?",
+      { title: "Truncated outputs", kind: "warning" },
+    );
   }
+
+  /**
+   * Reply to the prompt for input in a cell. The backend echoes the prompt
+   * into the outputs with the value, unless it is a password.
+   */
+  async replyInput(cellId: string, value: string) {
+    const input = this.state.cells[cellId].result?.input;
+    if (!input) return;
+    this.state.setInputPrompt(cellId, undefined);
+    await invoke("reply_input", {
+      kernelId: this.state.kernelId,
+      requestId: input.request_id,
+      value,
+    });
+  }
+
+  /**
+   * Run a SQL cell against a saved database connection, showing the results
+   * as a table. Returns whether the query and any binding succeeded.
+   */
+  private async executeSql(cellId: string, code: string): Promise<boolean> {
+    const displayId = uuidv4();
+    let columns: SqlColumn[] = [];
+    const rows: unknown[][] = [];
+    let ok = true;
+
+    const show = (footer: string) => {
+      const data = { "text/html": sqlTable(columns, rows, footer) };
+      const displays = this.state.cells[cellId].result?.displays;
+      if (displays?.[displayId] === undefined) {
+        this.state.appendOutput(
+          cellId,
+          { output_type: "display_data", data, metadata: {} },
+          displayId,
+        );
+      } else {
+        this.state.updateOutputDisplay(cellId, displayId, {
+          data,
+          metadata: {},
+        });
+      }
+    };
+
+    const onEvent = new Channel<SqlEvent>();
+    onEvent.onmessage = (message) => {
+      if (message.event === "columns") {
+        columns = message.data;
+        show("Loading…");
+      } else if (message.event === "rows") {
+        rows.push(...message.data);
+        show(`${rows.length} rows so far…`);
+      } else if (message.event === "done") {
+        const { row_count, truncated } = message.data;
+        if (columns.length > 0) {
+          show(
+            truncated
+              ? `${row_count} rows, more were left out`
+              : `${row_count} rows`,
+          );
+        } else {
+          this.state.appendOutput(cellId, {
+            output_type: "stream",
+            name: "stdout",
+            text: `${row_count} rows affected\n`,
+          });
+        }
+      } else if (message.event === "bound") {
+        this.state.appendOutput(cellId, {
+          output_type: "stream",
+          name: "stdout",
+          text: `Results saved to \`${message.data}\`\n`,
+        });
+      } else if (message.event === "bind_error") {
+        ok = false;
+        this.state.appendOutput(cellId, {
+          output_type: "error",
+          ename: "BindError",
+          evalue: message.data,
+          traceback: [],
+        });
+      }
+    };
+
+    await invoke("run_sql_cell", {
+      source: code,
+      kernelId: this.state.kernelId,
+      onEvent,
+    });
+    return ok;
+  }
+}
+
+/** Whether a code cell is a SQL cell, starting with a `%%sql` header. */
+function isSqlCell(code: string): boolean {
+  return /^\s*%%sql(\s|$)/.test(code);
+}
+
+/** Maximum number of rows of SQL results rendered in a cell's table. */
+const SQL_TABLE_ROWS = 1000;
+
+/** Render SQL results as an HTML table, with a footer below it. */
+function sqlTable(columns: SqlColumn[], rows: unknown[][], footer: string) {
+  const escape = (value: unknown) =>
+    String(
+      value === null
+        ? "NULL"
+        : typeof value === "object"
+          ? JSON.stringify(value)
+          : value,
+    )
+      .replace(/&/g, "&amp;")
+      .replace(/</g, "&lt;")
+      .replace(/>/g, "&gt;")
+      .replace(/"/g, "&quot;");
+  const head = columns
+    .map((c) => `<th title="${escape(c.type_name)}">${escape(c.name)}</th>`)
+    .join("");
+  const body = rows
+    .slice(0, SQL_TABLE_ROWS)
+    .map((row) => row.map((v) => `<td>${escape(v)}</td>`).join(""))
+    .map((cells) => `<tr>${cells}</tr>`)
+    .join("");
+  if (rows.length > SQL_TABLE_ROWS) {
+    footer = `Showing ${SQL_TABLE_ROWS} of ${footer}`;
+  }
+  return (
+    `<table><thead><tr>${head}</tr></thead><tbody>${body}</tbody></table>` +
+    `<p>${escape(footer)}</p>`
+  );
 }
 
 /** Helper function to convert a maybe-multiline string to a string. */
@@ -493,6 +1954,15 @@ function multiline(string: string | string[]): string {
   return typeof string === "string" ? string : string.join("");
 }
 
+/** Anchor of a Markdown heading, lowercase with dashes between words. */
+export function headingAnchor(text: string): string {
+  return text
+    .trim()
+    .toLowerCase()
+    .replace(/[^\p{L}\p{N}\s-]/gu, "")
+    .replace(/\s+/g, "-");
+}
+
 export const NotebookContext = createContext<Notebook | undefined>(undefined);
 
 export function useNotebook(): Notebook {
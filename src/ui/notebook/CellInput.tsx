@@ -32,9 +32,11 @@ import {
   lineNumbers,
   rectangularSelection,
 } from "@codemirror/view";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { useEffect, useRef, useState } from "react";
 import { useStore } from "zustand";
 
+import type { FormatConfig } from "@/bindings";
 import { CellType, useNotebook } from "@/stores/notebook";
 
 import CellInputFallback from "./CellInputFallback";
@@ -79,6 +81,31 @@ const editorTheme = EditorView.theme({
 
 const language = new Compartment();
 const lineNumbersDynamic = new Compartment();
+const formatting = new Compartment();
+
+/** Indentation and a line length guide from the project's settings. */
+function extensionForFormat(format?: FormatConfig): Extension {
+  const width = format?.indent_width ?? 4;
+  const extensions = [
+    indentUnit.of(" ".repeat(width)),
+    EditorState.tabSize.of(width),
+  ];
+  const length = format?.line_length;
+  if (length) {
+    const [at, end] = [`${length}ch`, `calc(${length}ch + 1px)`];
+    const guide = `transparent ${at}, #f0f0f0 ${at}, #f0f0f0 ${end}`;
+    extensions.push(
+      EditorView.theme({
+        "& .cm-content": {
+          backgroundImage: `linear-gradient(to right, ${guide}, transparent ${end})`,
+          backgroundOrigin: "content-box",
+          backgroundClip: "content-box",
+        },
+      }),
+    );
+  }
+  return extensions;
+}
 
 function extensionForLanguage(type: CellType): Extension {
   if (type === "code") {
@@ -104,6 +131,11 @@ export default function CellInput({ cellId }: Props) {
     notebook.store,
     (state) => state.cells[cellId].initialText,
   );
+  const attachments = useStore(
+    notebook.store,
+    (state) => state.cells[cellId].attachments,
+  );
+  const format = useStore(notebook.store, (state) => state.format);
 
   // Only updated when the editor view is switched to Markdown rendering mode.
   const [renderedMarkdown, setRenderedMarkdown] = useState(initialText);
@@ -114,7 +146,7 @@ export default function CellInput({ cellId }: Props) {
   useEffect(() => {
     const onRun = () => {
       if (type === "code") {
-        notebook.execute(cellId);
+        notebook.executeReactive(cellId);
       } else if (type === "markdown") {
         if (view) {
           setRenderedMarkdown(view.state.doc.toString());
@@ -124,6 +156,23 @@ export default function CellInput({ cellId }: Props) {
       return true;
     };
 
+    // Images pasted or dropped into Markdown cells are attached to them.
+    const onImages = (event: Event, files?: FileList) => {
+      const images = Array.from(files ?? []).filter((file) =>
+        file.type.startsWith("image/"),
+      );
+      const cell = notebook.state.cells[cellId];
+      if (cell?.type !== "markdown" || images.length === 0) return false;
+      event.preventDefault();
+      (async () => {
+        for (const file of images) {
+          const data = new Uint8Array(await file.arrayBuffer());
+          await notebook.attachImage(cellId, { fileName: file.name, data });
+        }
+      })().catch((e) => console.error("failed to attach image", e));
+      return true;
+    };
+
     const view = new EditorView({
       extensions: [
         highlightSpecialChars(),
@@ -163,9 +212,18 @@ export default function CellInput({ cellId }: Props) {
             : lineNumbers({ formatNumber: () => "" }),
         ),
         language.of(type === "code" ? python() : markdown()),
-        indentUnit.of("    "),
-        EditorState.tabSize.of(4),
+        formatting.of(extensionForFormat(format)),
         editorTheme,
+        EditorView.domEventHandlers({
+          paste: (event) => onImages(event, event.clipboardData?.files),
+          drop: (event) => onImages(event, event.dataTransfer?.files),
+        }),
+        EditorView.updateListener.of((update) => {
+          if (update.docChanged) notebook.recordEdit(cellId);
+          if (update.focusChanged && update.view.hasFocus) {
+            notebook.focusedCellId = cellId;
+          }
+        }),
       ],
       doc: initialText,
       parent: containerEl.current!,
@@ -174,6 +232,7 @@ export default function CellInput({ cellId }: Props) {
     const ref = notebook.refs.get(cellId);
     if (ref) {
       ref.editor = view;
+      ref.run = onRun;
     } else {
       console.warn(`Ref for cell ${cellId} not found`);
     }
@@ -198,6 +257,34 @@ export default function CellInput({ cellId }: Props) {
     }
   }, [view, type]);
 
+  // Project settings are read after the notebook loads.
+  useEffect(() => {
+    view?.dispatch({
+      effects: formatting.reconfigure(extensionForFormat(format)),
+    });
+  }, [view, format]);
+
+  // Files dropped from the desktop arrive as paths instead of DOM events.
+  useEffect(() => {
+    if (type !== "markdown") return;
+    const unlisten = getCurrentWebview().onDragDropEvent(async (event) => {
+      if (event.payload.type !== "drop") return;
+      const { x, y } = event.payload.position.toLogical(
+        window.devicePixelRatio,
+      );
+      const target = document.elementFromPoint(x, y);
+      if (!target || !containerEl.current?.contains(target)) return;
+      for (const path of event.payload.paths) {
+        await notebook
+          .attachImage(cellId, { path })
+          .catch((e) => console.error("failed to attach file", e));
+      }
+    });
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, [cellId, notebook, type]);
+
   const isRenderingMarkdown = type === "markdown" && !editingMarkdown;
 
   return (
@@ -214,6 +301,7 @@ export default function CellInput({ cellId }: Props) {
       {isRenderingMarkdown && (
         <RenderMarkdownCell
           source={renderedMarkdown}
+          attachments={attachments}
           onStartEdit={() => setEditingMarkdown(true)}
         />
       )}
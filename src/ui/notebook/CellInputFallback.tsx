@@ -16,10 +16,14 @@ export default function CellInput({ cellId }: Props) {
     notebook.store,
     (state) => state.cells[cellId].initialText,
   );
+  const attachments = useStore(
+    notebook.store,
+    (state) => state.cells[cellId].attachments,
+  );
 
   // 57px padding left matches the gutter size of the full editor.
   return type === "markdown" ? (
-    <RenderMarkdownCell source={initialText} />
+    <RenderMarkdownCell source={initialText} attachments={attachments} />
   ) : (
     <pre
       className="max-w-full overflow-hidden py-4 pl-[57px] pr-0.5 text-sm leading-[1.2rem]"
@@ -0,0 +1,47 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+
+import type { Checkpoint } from "@/bindings";
+
+type Props = {
+  path: string;
+  onSelect: (checkpointId: string) => void;
+};
+
+/** List the checkpoints of a notebook, to roll back to one of them. */
+export default function CheckpointPicker({ path, onSelect }: Props) {
+  const [checkpoints, setCheckpoints] = useState<Checkpoint[]>();
+  const [error, setError] = useState<string>();
+
+  useEffect(() => {
+    invoke<Checkpoint[]>("list_checkpoints", { path })
+      .then(setCheckpoints)
+      .catch((e) => setError(e.toString()));
+  }, [path]);
+
+  return (
+    <div className="mx-auto max-w-md px-8 py-20">
+      <h1 className="mb-2.5 text-2xl">Revert to Checkpoint</h1>
+      <h2 className="mb-6 text-gray-400">
+        Choose a saved version of this notebook.
+      </h2>
+
+      {error && <p className="text-red-600">{error}</p>}
+      {checkpoints?.length === 0 && (
+        <p className="text-gray-500">No checkpoints were found.</p>
+      )}
+
+      <div className="flex flex-col gap-2">
+        {checkpoints?.map((checkpoint) => (
+          <button
+            key={checkpoint.id}
+            className="rounded border border-gray-300 px-4 py-3 text-left transition-colors hover:border-black"
+            onClick={() => onSelect(checkpoint.id)}
+          >
+            {new Date(checkpoint.created).toLocaleString()}
+          </button>
+        ))}
+      </div>
+    </div>
+  );
+}
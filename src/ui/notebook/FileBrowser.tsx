@@ -0,0 +1,118 @@
+import { invoke } from "@tauri-apps/api/core";
+import clsx from "clsx";
+import {
+  BookOpenIcon,
+  ChevronRightIcon,
+  FileIcon,
+  FolderIcon,
+} from "lucide-react";
+import { useEffect, useState } from "react";
+
+import type { DirEntry } from "@/bindings";
+import { notebookExtensions } from "@/stores/notebook";
+
+type Props = {
+  /** Folder that the workspace is rooted at. */
+  root: string;
+
+  /** Called when a notebook in the folder is clicked. */
+  onOpen: (path: string) => void;
+};
+
+/** Sidebar listing the files of a workspace, for opening its notebooks. */
+export default function FileBrowser({ root, onOpen }: Props) {
+  return (
+    <aside className="flex w-60 shrink-0 flex-col border-r border-gray-200 bg-gray-50">
+      <div
+        className="truncate px-3 pb-2 pt-10 text-xs font-medium uppercase text-gray-500"
+        title={root}
+        data-tauri-drag-region
+      >
+        {root.slice(root.lastIndexOf("/") + 1)}
+      </div>
+      <div className="min-h-0 flex-1 overflow-y-auto pb-2 text-sm">
+        <FolderContents path={root} depth={0} onOpen={onOpen} />
+      </div>
+    </aside>
+  );
+}
+
+function FolderContents({
+  path,
+  depth,
+  onOpen,
+}: {
+  path: string;
+  depth: number;
+  onOpen: (path: string) => void;
+}) {
+  const [entries, setEntries] = useState<DirEntry[]>();
+
+  // Folders are listed when expanded, so large trees load lazily.
+  useEffect(() => {
+    invoke<DirEntry[]>("list_directory", { path })
+      .then(setEntries)
+      .catch(() => setEntries([]));
+  }, [path]);
+
+  if (!entries) return null;
+
+  return entries.map((entry) => (
+    <FileBrowserEntry
+      key={entry.path}
+      entry={entry}
+      depth={depth}
+      onOpen={onOpen}
+    />
+  ));
+}
+
+function FileBrowserEntry({
+  entry,
+  depth,
+  onOpen,
+}: {
+  entry: DirEntry;
+  depth: number;
+  onOpen: (path: string) => void;
+}) {
+  const [expanded, setExpanded] = useState(false);
+  const isNotebook =
+    !entry.is_dir &&
+    notebookExtensions.some((ext) => entry.name.endsWith(`.${ext}`));
+
+  const Icon = entry.is_dir ? FolderIcon : isNotebook ? BookOpenIcon : FileIcon;
+
+  return (
+    <>
+      <button
+        className={clsx(
+          "flex w-full items-center gap-1.5 py-0.5 pr-2 text-left",
+          entry.is_dir || isNotebook
+            ? "hover:bg-gray-200"
+            : "cursor-default text-gray-400",
+        )}
+        style={{ paddingLeft: `${0.5 + depth * 0.75}rem` }}
+        title={entry.path}
+        onClick={() => {
+          if (entry.is_dir) setExpanded(!expanded);
+          else if (isNotebook) onOpen(entry.path);
+        }}
+      >
+        <ChevronRightIcon
+          size={12}
+          className={clsx(
+            "shrink-0 transition-transform",
+            !entry.is_dir && "invisible",
+            expanded && "rotate-90",
+          )}
+        />
+        <Icon size={14} className="shrink-0" />
+        <span className="truncate">{entry.name}</span>
+      </button>
+      {expanded && (
+        <FolderContents path={entry.path} depth={depth + 1} onOpen={onOpen} />
+      )}
+    </>
+  );
+}
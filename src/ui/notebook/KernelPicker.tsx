@@ -0,0 +1,46 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+
+import type { KernelSpec } from "@/bindings";
+
+type Props = {
+  title: string;
+  onSelect: (specName: string) => void;
+};
+
+/** List the available kernels, to choose one for a notebook. */
+export default function KernelPicker({ title, onSelect }: Props) {
+  const [specs, setSpecs] = useState<KernelSpec[]>();
+  const [error, setError] = useState<string>();
+
+  useEffect(() => {
+    invoke<KernelSpec[]>("list_kernel_specs")
+      .then(setSpecs)
+      .catch((e) => setError(e.toString()));
+  }, []);
+
+  return (
+    <div className="mx-auto max-w-md px-8 py-20">
+      <h1 className="mb-2.5 text-2xl">{title}</h1>
+      <h2 className="mb-6 text-gray-400">Choose a kernel to run your code.</h2>
+
+      {error && <p className="text-red-600">{error}</p>}
+      {specs?.length === 0 && (
+        <p className="text-gray-500">No kernels were found.</p>
+      )}
+
+      <div className="flex flex-col gap-2">
+        {specs?.map((spec) => (
+          <button
+            key={spec.name}
+            className="rounded border border-gray-300 px-4 py-3 text-left transition-colors hover:border-black"
+            onClick={() => onSelect(spec.name)}
+          >
+            {spec.display_name}
+            <span className="ml-2 text-sm text-gray-400">{spec.name}</span>
+          </button>
+        ))}
+      </div>
+    </div>
+  );
+}
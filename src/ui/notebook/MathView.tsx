@@ -0,0 +1,21 @@
+import { useEffect, useRef } from "react";
+
+import { type MathSpan, renderMath } from "./renderers";
+
+type Props = MathSpan;
+
+/** LaTeX math, rendered with the engine chosen in settings. */
+export default function MathView({ tex, display }: Props) {
+  const ref = useRef<HTMLSpanElement>(null);
+
+  useEffect(() => {
+    const element = ref.current;
+    if (!element) return;
+    renderMath(element, tex, display).catch((error) => {
+      console.warn("Failed to render math", error);
+      element.textContent = display ? `$$${tex}$$` : `$${tex}$`;
+    });
+  }, [tex, display]);
+
+  return <span ref={ref} className={display ? "block text-center" : ""} />;
+}
@@ -1,3 +1,4 @@
+import clsx from "clsx";
 import {
   BoltIcon,
   CheckIcon,
@@ -6,6 +7,7 @@ import {
   LucideIcon,
   PlusIcon,
   RouteOffIcon,
+  SkipForwardIcon,
   XIcon,
   XSquareIcon,
 } from "lucide-react";
@@ -68,10 +70,15 @@ function CellInputAside({ cellId }: { cellId: string }) {
         {type === "code" && <AsideIconButton Icon={RouteOffIcon} />}
         <AsideIconButton Icon={BoltIcon} />
       </div>
+      {output?.status === "queued" && (
+        <p className="mt-0.5 text-sm text-gray-400">Queued</p>
+      )}
       {output?.timings?.finishedAt && (
         <div className="mt-0.5 flex items-center">
           {output.status === "success" ? (
             <CheckIcon size={16} className="mr-1 text-green-500" />
+          ) : output.status === "aborted" ? (
+            <SkipForwardIcon size={16} className="mr-1 text-gray-400" />
           ) : (
             <XIcon size={16} className="mr-1 text-red-500" />
           )}
@@ -92,7 +99,7 @@ export default function NotebookCells() {
   const cells = useStore(notebook.store, (state) => state.cells);
   const isLoading = useStore(notebook.store, (state) => state.isLoading);
 
-  if (isLoading)
+  if (isLoading && cellIds.length === 0)
     // TODO: add a better loading state
     return (
       <div className="relative p-8 px-14">
@@ -100,10 +107,16 @@ export default function NotebookCells() {
       </div>
     );
 
+  // Cells shown while the rest of the notebook loads can't be edited, and are
+  // created again once it has loaded.
   return (
-    <div className="relative py-8">
+    <div
+      key={isLoading ? "loading" : "loaded"}
+      className="relative py-8"
+      inert={isLoading}
+    >
       {cellIds.map((id) => (
-        <div key={id}>
+        <div key={id} data-cell-id={id}>
           <hr className="border-gray-200" />
 
           <CellInputAside cellId={id} />
@@ -123,28 +136,52 @@ export default function NotebookCells() {
                   <AsideIconButton Icon={BoltIcon} />
                 </div>
               </Aside>
-              <div className="max-h-[680px] overflow-y-auto">
-                {/* TODO: Move this icon into the output view itself. Also it should only be displayed
+              {cells[id].metadata?.collapsed ? (
+                <button
+                  className="w-full px-4 py-1 text-left text-sm text-gray-400 hover:bg-gray-50"
+                  onClick={() =>
+                    notebook.updateCellMetadata(id, { collapsed: false })
+                  }
+                >
+                  Outputs collapsed
+                </button>
+              ) : (
+                <div
+                  className={clsx(
+                    cells[id].metadata?.scrolled !== false &&
+                      "max-h-[680px] overflow-y-auto",
+                  )}
+                >
+                  {/* TODO: Move this icon into the output view itself. Also it should only be displayed
                   when the cell has a return value, and next to the return value. */}
-                {/* <CornerDownRightIcon size={16} className="text-gray-400" /> */}
-                <OutputView value={cells[id].result} />
-              </div>
+                  {/* <CornerDownRightIcon size={16} className="text-gray-400" /> */}
+                  <OutputView
+                    value={cells[id].result}
+                    onInput={(value) => notebook.replyInput(id, value)}
+                    onExpand={(outputId) => notebook.expandOutput(id, outputId)}
+                  />
+                </div>
+              )}
             </>
           )}
         </div>
       ))}
 
-      <div className="mx-2 my-4">
-        <button
-          className="flex w-full items-center justify-center gap-1.5 rounded border border-gray-200 p-2 transition-colors hover:border-gray-300 hover:bg-gray-50"
-          onClick={() => {
-            notebook.addCell("code", "");
-          }}
-        >
-          <PlusIcon size={18} />
-          <span>New cell</span>
-        </button>
-      </div>
+      {isLoading ? (
+        <div className="px-14 py-4 text-gray-400">Loading...</div>
+      ) : (
+        <div className="mx-2 my-4">
+          <button
+            className="flex w-full items-center justify-center gap-1.5 rounded border border-gray-200 p-2 transition-colors hover:border-gray-300 hover:bg-gray-50"
+            onClick={() => {
+              notebook.addCell("code", "");
+            }}
+          >
+            <PlusIcon size={18} />
+            <span>New cell</span>
+          </button>
+        </div>
+      )}
     </div>
   );
 }
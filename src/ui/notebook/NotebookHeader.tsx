@@ -1,3 +1,4 @@
+import { listen } from "@tauri-apps/api/event";
 import clsx from "clsx";
 import {
   ChartLineIcon,
@@ -7,9 +8,11 @@ import {
   RefreshCwIcon,
   SettingsIcon,
 } from "lucide-react";
+import { useEffect, useState } from "react";
 import { Link } from "wouter";
 import { useStore } from "zustand";
 
+import type { KernelLiveness } from "@/bindings";
 import { useNotebook } from "@/stores/notebook";
 
 import Header from "../shared/Header";
@@ -23,6 +26,20 @@ export default function NotebookHeader({ kernelName }: Props) {
 
   const kernelId = useStore(notebook.store, (state) => state.kernelId);
 
+  // The kernel may stop answering heartbeats, such as when it is deadlocked.
+  const [unresponsive, setUnresponsive] = useState(false);
+  useEffect(() => {
+    setUnresponsive(false);
+    const unlisten = listen<KernelLiveness>("kernel-liveness", (event) => {
+      if (event.payload.kernel_id === kernelId) {
+        setUnresponsive(!event.payload.alive);
+      }
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [kernelId]);
+
   return (
     <Header>
       {/* Empty placeholder to take up space where the traffic light buttons are. */}
@@ -37,14 +54,22 @@ export default function NotebookHeader({ kernelName }: Props) {
           <RefreshCwIcon size={16} />
         </button>
 
-        <button className="mx-2 flex w-60 items-center justify-center rounded border border-gray-200 py-[3px] text-xs text-gray-900 transition-all hover:border-gray-400 hover:bg-gray-100 active:scale-105">
+        <button
+          className="mx-2 flex w-60 items-center justify-center rounded border border-gray-200 py-[3px] text-xs text-gray-900 transition-all hover:border-gray-400 hover:bg-gray-100 active:scale-105"
+          title={unresponsive ? "Kernel is unresponsive" : undefined}
+        >
           <div
             className={clsx(
               "mr-2 h-2 w-2 rounded-full",
-              kernelId ? "bg-green-500" : "bg-orange-500",
+              !kernelId
+                ? "bg-orange-500"
+                : unresponsive
+                  ? "bg-red-500"
+                  : "bg-green-500",
             )}
           />
           {kernelName}
+          {unresponsive && " (unresponsive)"}
         </button>
 
         <button className="rounded p-1 text-gray-500 transition-all hover:bg-gray-100 hover:text-black active:scale-110">
@@ -0,0 +1,54 @@
+import { invoke } from "@tauri-apps/api/core";
+import clsx from "clsx";
+import { ExternalLinkIcon, XIcon } from "lucide-react";
+
+import type { TabList } from "@/bindings";
+
+type Props = {
+  tabs: TabList;
+};
+
+/** Tabs for the notebooks that are open in a window. */
+export default function NotebookTabBar({ tabs }: Props) {
+  return (
+    <nav
+      className="flex h-9 shrink-0 items-end gap-1 border-b border-gray-200 bg-gray-100 pl-20 pr-2"
+      data-tauri-drag-region
+    >
+      {tabs.tabs.map((tab, index) => (
+        <div
+          key={tab.id}
+          className={clsx(
+            "group flex max-w-60 items-center gap-1 rounded-t border border-b-0 px-3 py-1 text-sm",
+            index === tabs.active
+              ? "border-gray-200 bg-white text-black"
+              : "border-transparent text-gray-500 hover:text-black",
+          )}
+        >
+          <button
+            className="truncate"
+            title={tab.path}
+            onClick={() => invoke("select_tab", { index })}
+          >
+            {tab.path.slice(tab.path.lastIndexOf("/") + 1)}
+            {tab.dirty && <span className="ml-1 text-gray-400">•</span>}
+          </button>
+          <button
+            className="rounded p-0.5 opacity-0 transition-opacity hover:bg-gray-200 group-hover:opacity-100"
+            title="Move to New Window"
+            onClick={() => invoke("move_tab_to_new_window", { id: tab.id })}
+          >
+            <ExternalLinkIcon size={12} />
+          </button>
+          <button
+            className="rounded p-0.5 hover:bg-gray-200"
+            title="Close Tab"
+            onClick={() => invoke("close_tab", { id: tab.id })}
+          >
+            <XIcon size={12} />
+          </button>
+        </div>
+      ))}
+    </nav>
+  );
+}
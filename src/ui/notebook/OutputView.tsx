@@ -4,11 +4,17 @@ import { memo } from "react";
 import { MultilineString, OutputDisplayData } from "@/bindings";
 import { CellResult } from "@/stores/notebook";
 
+import MathView from "./MathView";
+import RichOutput from "./RichOutput";
+import { richMimeType } from "./renderers";
+
 type Props = {
   value: CellResult | undefined;
+  onInput?: (value: string) => void;
+  onExpand?: (outputId: string) => void;
 };
 
-export default function OutputView({ value }: Props) {
+export default function OutputView({ value, onInput, onExpand }: Props) {
   if (!value) {
     return null;
   }
@@ -29,12 +35,67 @@ export default function OutputView({ value }: Props) {
               {output.ename}: {output.evalue}
             </pre>
           ) : null}
+          {value.truncated
+            ?.filter((truncated) => truncated.index === index)
+            .map((truncated) => (
+              <p key={truncated.id} className="font-sans text-gray-500">
+                {truncated.withheld
+                  ? `Large ${truncated.name} output hidden.`
+                  : "Output truncated."}{" "}
+                <button
+                  className="text-blue-600 hover:underline"
+                  onClick={() => onExpand?.(truncated.id)}
+                >
+                  {truncated.withheld ? "Show" : "Show more"}
+                </button>
+              </p>
+            ))}
         </div>
       ))}
+      {value.input && (
+        <InputPromptView
+          // Keyed so that a new prompt starts out empty.
+          key={value.input.request_id}
+          prompt={value.input.prompt}
+          password={value.input.password}
+          onSubmit={(text) => onInput?.(text)}
+        />
+      )}
     </div>
   );
 }
 
+/** A text box for input requested by the kernel, masked for passwords. */
+function InputPromptView({
+  prompt,
+  password,
+  onSubmit,
+}: {
+  prompt: string;
+  password: boolean;
+  onSubmit: (value: string) => void;
+}) {
+  return (
+    <form
+      className="flex items-baseline font-mono"
+      onSubmit={(event) => {
+        event.preventDefault();
+        const input = event.currentTarget.elements.namedItem("value");
+        onSubmit((input as HTMLInputElement).value);
+      }}
+    >
+      <span>{prompt}</span>
+      <input
+        name="value"
+        type={password ? "password" : "text"}
+        autoComplete="off"
+        autoFocus
+        className="flex-1 border-b border-gray-300 outline-none"
+      />
+    </form>
+  );
+}
+
 function multiline(source: MultilineString): string {
   if (typeof source === "string") {
     return source;
@@ -45,15 +106,58 @@ function multiline(source: MultilineString): string {
 const OutputViewDisplayData = memo(
   ({ output }: { output: OutputDisplayData }) => {
     const html = displayDataToHtml(output.data, output.metadata);
+    const fallback = html ? (
+      <div dangerouslySetInnerHTML={{ __html: html }}></div>
+    ) : null;
 
-    if (html) {
-      return <div dangerouslySetInnerHTML={{ __html: html }}></div>;
-    } else {
-      return null;
+    const mimeType = richMimeType(output.data);
+    if (mimeType) {
+      return (
+        <RichOutput
+          mimeType={mimeType}
+          value={output.data[mimeType]}
+          fallback={fallback}
+        />
+      );
+    }
+    const tex = latexSource(output.data);
+    if (tex !== null) {
+      return <MathView tex={tex} display />;
     }
+    return fallback;
   },
 );
 
+const imageTypes = [
+  "image/png",
+  "image/jpeg",
+  "image/svg+xml",
+  "image/bmp",
+  "image/gif",
+];
+
+/**
+ * Returns the LaTeX of a display data message without its `$` delimiters, if it
+ * has LaTeX and no image, e.g., for SymPy expressions.
+ */
+function latexSource(data: Record<string, any>): string | null {
+  const value = data["text/latex"];
+  if (!value || imageTypes.some((type) => Object.hasOwn(data, type))) {
+    return null;
+  }
+  const tex = (Array.isArray(value) ? value.join("") : String(value)).trim();
+  for (const delimiter of ["$$", "$"]) {
+    if (
+      tex.length > 2 * delimiter.length &&
+      tex.startsWith(delimiter) &&
+      tex.endsWith(delimiter)
+    ) {
+      return tex.slice(delimiter.length, -delimiter.length);
+    }
+  }
+  return tex;
+}
+
 /**
  * Returns the HTML form of a display data message.
  *
@@ -63,13 +167,7 @@ function displayDataToHtml(
   data: Record<string, any>,
   metadata: Record<string, any>,
 ): string | null {
-  for (const imageType of [
-    "image/png",
-    "image/jpeg",
-    "image/svg+xml",
-    "image/bmp",
-    "image/gif",
-  ]) {
+  for (const imageType of imageTypes) {
     if (Object.hasOwn(data, imageType)) {
       const value = data[imageType];
       const alt = String(data["text/plain"] ?? "");
@@ -1,16 +1,40 @@
 import { Edit3Icon } from "lucide-react";
-import Markdown from "react-markdown";
+import { useMemo } from "react";
+import Markdown, { defaultUrlTransform } from "react-markdown";
 import remarkGfm from "remark-gfm";
 
+import type { CellAttachments } from "@/stores/notebook";
+
+import MathView from "./MathView";
 import styles from "./RenderMarkdownCell.module.css";
+import { extractMath, extractedMath } from "./renderers";
 
 type Props = {
   source: string;
+  attachments?: CellAttachments;
   onStartEdit?: () => void;
 };
 
+/** Resolve an `attachment:` URL to a data URL of the cell's attachment. */
+function attachmentUrl(url: string, attachments?: CellAttachments): string {
+  if (!url.startsWith("attachment:")) return defaultUrlTransform(url);
+  const bundle = attachments?.[url.slice("attachment:".length)] ?? {};
+  const [mimeType, value] = Object.entries(bundle)[0] ?? [];
+  if (!mimeType) return "";
+  const data = Array.isArray(value) ? value.join("") : String(value);
+  return mimeType === "image/svg+xml"
+    ? `data:${mimeType};charset=utf-8,${encodeURIComponent(data)}`
+    : `data:${mimeType};base64,${data.replace(/\s/g, "")}`;
+}
+
 /** Display rendered Markdown in a cell. */
-export default function RenderMarkdownCell({ source, onStartEdit }: Props) {
+export default function RenderMarkdownCell({
+  source,
+  attachments,
+  onStartEdit,
+}: Props) {
+  const { markdown, math } = useMemo(() => extractMath(source), [source]);
+
   return (
     <div
       className="group relative ml-14 max-w-full overflow-hidden py-2 pr-4 text-sm"
@@ -19,13 +43,18 @@ export default function RenderMarkdownCell({ source, onStartEdit }: Props) {
       <Markdown
         className={styles.markdown}
         remarkPlugins={[remarkGfm]}
+        urlTransform={(url) => attachmentUrl(url, attachments)}
         components={{
           a: ({ ...props }) => (
             <a {...props} target="_blank" rel="noreferrer" />
           ),
+          code: ({ node: _node, ...props }) => {
+            const span = extractedMath(String(props.children), math);
+            return span ? <MathView {...span} /> : <code {...props} />;
+          },
         }}
       >
-        {source}
+        {markdown}
       </Markdown>
 
       <button
@@ -0,0 +1,70 @@
+import { type ReactNode, useEffect, useRef, useState } from "react";
+
+import { type RichMimeType, loadScript } from "./renderers";
+
+const vega = "vega/vega.min.js";
+const vegaLite = "vega-lite/vega-lite.min.js";
+const vegaEmbed = "vega-embed/vega-embed.min.js";
+
+/** Scripts needed for each MIME type, loaded in order. */
+const richLibraries: Record<RichMimeType, string[]> = {
+  "application/vnd.plotly.v1+json": ["plotly/plotly.min.js"],
+  "application/vnd.vegalite.v5+json": [vega, vegaLite, vegaEmbed],
+  "application/vnd.vegalite.v4+json": [vega, vegaLite, vegaEmbed],
+  "application/vnd.vega.v5+json": [vega, vegaEmbed],
+};
+
+type Props = {
+  mimeType: RichMimeType;
+  value: any;
+
+  /** Rendered instead if the library can't be loaded. */
+  fallback: ReactNode;
+};
+
+/** Draws an output with a JavaScript library, like a Plotly figure. */
+export default function RichOutput({ mimeType, value, fallback }: Props) {
+  const ref = useRef<HTMLDivElement>(null);
+  const [failed, setFailed] = useState(false);
+
+  useEffect(() => {
+    const element = ref.current;
+    if (!element) return;
+    let cancelled = false;
+    let cleanup: (() => void) | undefined;
+
+    (async () => {
+      for (const path of richLibraries[mimeType]) {
+        await loadScript(path);
+      }
+      if (cancelled) return;
+      const global = window as any;
+      if (mimeType === "application/vnd.plotly.v1+json") {
+        await global.Plotly.newPlot(
+          element,
+          value.data,
+          value.layout,
+          value.config,
+        );
+        cleanup = () => global.Plotly.purge(element);
+      } else {
+        const mode = mimeType.includes("vegalite") ? "vega-lite" : "vega";
+        const result = await global.vegaEmbed(element, value, {
+          mode,
+          actions: false,
+        });
+        cleanup = () => result.finalize();
+      }
+    })().catch((error) => {
+      console.warn("Falling back from rich output", error);
+      if (!cancelled) setFailed(true);
+    });
+
+    return () => {
+      cancelled = true;
+      cleanup?.();
+    };
+  }, [mimeType, value]);
+
+  return failed ? fallback : <div ref={ref} className="whitespace-normal" />;
+}
@@ -0,0 +1,199 @@
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+
+import type { MathRenderer, Settings } from "@/bindings";
+
+/**
+ * Libraries for rendering outputs and math are pinned and cached by the
+ * backend, which serves them over the `renderer://` protocol so that they also
+ * work offline. Paths are relative to that protocol, like
+ * `plotly/plotly.min.js`.
+ */
+function rendererUrl(path: string): string {
+  return convertFileSrc(path, "renderer").replace(/%2F/g, "/");
+}
+
+const loaded = new Map<string, Promise<void>>();
+
+/** Load a script or stylesheet into the page once. */
+function load(
+  path: string,
+  element: () => HTMLScriptElement | HTMLLinkElement,
+): Promise<void> {
+  let promise = loaded.get(path);
+  if (!promise) {
+    promise = new Promise((resolve, reject) => {
+      const el = element();
+      el.onload = () => resolve();
+      el.onerror = () => {
+        // Allow retrying later, e.g., once the user is back online.
+        loaded.delete(path);
+        el.remove();
+        reject(new Error(`could not load renderer ${path}`));
+      };
+      document.head.appendChild(el);
+    });
+    loaded.set(path, promise);
+  }
+  return promise;
+}
+
+/** Load a library's script into the page once. */
+export function loadScript(path: string): Promise<void> {
+  return load(path, () => {
+    const script = document.createElement("script");
+    script.src = rendererUrl(path);
+    return script;
+  });
+}
+
+/** Load a library's stylesheet into the page once. */
+export function loadStylesheet(path: string): Promise<void> {
+  return load(path, () => {
+    const link = document.createElement("link");
+    link.rel = "stylesheet";
+    link.href = rendererUrl(path);
+    return link;
+  });
+}
+
+let mathRenderer: Promise<MathRenderer> | undefined;
+
+/** Get the math engine chosen in settings, when this window was opened. */
+function getMathRenderer(): Promise<MathRenderer> {
+  mathRenderer ??= invoke<Settings>("get_settings").then(
+    (settings) => settings.math_renderer,
+    () => "katex",
+  );
+  return mathRenderer;
+}
+
+/** Render LaTeX math into an element, with KaTeX or MathJax. */
+export async function renderMath(
+  element: HTMLElement,
+  tex: string,
+  display: boolean,
+) {
+  const global = window as any;
+  if ((await getMathRenderer()) === "mathjax") {
+    // MathJax reads its configuration when it loads.
+    global.MathJax ??= {
+      startup: { typeset: false },
+      svg: { fontCache: "global" },
+    };
+    await loadScript("mathjax/tex-svg-full.js");
+    await global.MathJax.startup.promise;
+    const node = await global.MathJax.tex2svgPromise(tex, { display });
+    element.replaceChildren(node);
+  } else {
+    await Promise.all([
+      loadStylesheet("katex/katex.min.css"),
+      loadScript("katex/katex.min.js"),
+    ]);
+    global.katex.render(tex, element, {
+      displayMode: display,
+      throwOnError: false,
+    });
+  }
+}
+
+/**
+ * MIME types of outputs drawn by a JavaScript library, in order of preference.
+ */
+const richMimeTypes = [
+  "application/vnd.plotly.v1+json",
+  "application/vnd.vegalite.v5+json",
+  "application/vnd.vegalite.v4+json",
+  "application/vnd.vega.v5+json",
+] as const;
+
+export type RichMimeType = (typeof richMimeTypes)[number];
+
+/** Returns the preferred MIME type in an output that needs a library. */
+export function richMimeType(data: Record<string, any>): RichMimeType | null {
+  return (
+    richMimeTypes.find((mimeType) => Object.hasOwn(data, mimeType)) ?? null
+  );
+}
+
+/** LaTeX math in a Markdown cell or output. */
+export type MathSpan = {
+  tex: string;
+  display: boolean;
+};
+
+/** Private-use character marking math replaced by `extractMath()`. */
+const mathMarker = "\uE000";
+
+/**
+ * Replace `$...$` and `$$...$$` math in Markdown with inline code spans holding
+ * a marker, so that the Markdown parser leaves the LaTeX alone. Math inside
+ * code blocks and code spans is kept as-is.
+ */
+export function extractMath(source: string): {
+  markdown: string;
+  math: MathSpan[];
+} {
+  const math: MathSpan[] = [];
+  let markdown = "";
+  let inFence = false;
+  let i = 0;
+  while (i < source.length) {
+    if (i === 0 || source[i - 1] === "\n") {
+      const lineEnd = source.indexOf("\n", i);
+      const end = lineEnd === -1 ? source.length : lineEnd + 1;
+      const line = source.slice(i, end);
+      const isFence = /^\s*(```|~~~)/.test(line);
+      if (isFence) inFence = !inFence;
+      if (isFence || inFence) {
+        markdown += line;
+        i = end;
+        continue;
+      }
+    }
+
+    const c = source[i];
+    if (c === "\\") {
+      markdown += source.slice(i, i + 2);
+      i += 2;
+    } else if (c === "`") {
+      let n = 1;
+      while (source[i + n] === "`") n++;
+      const close = source.indexOf("`".repeat(n), i + n);
+      const end = close === -1 ? i + n : close + n;
+      markdown += source.slice(i, end);
+      i = end;
+    } else if (c === "$") {
+      const display = source[i + 1] === "$";
+      const delimiter = display ? "$$" : "$";
+      const close = source.indexOf(delimiter, i + delimiter.length);
+      const tex = source.slice(i + delimiter.length, close);
+      // Like Pandoc, inline math can't start or end with a space, so that
+      // prices like "$5 and $10" aren't taken as math.
+      const valid =
+        close !== -1 &&
+        tex.trim() !== "" &&
+        (display ||
+          (!/^\s|\s$/.test(tex) &&
+            !tex.includes("\n\n") &&
+            !/\d/.test(source[close + 1] ?? "")));
+      if (valid) {
+        markdown += `\`${mathMarker}${math.length}\``;
+        math.push({ tex, display });
+        i = close + delimiter.length;
+      } else {
+        markdown += c;
+        i++;
+      }
+    } else {
+      markdown += c;
+      i++;
+    }
+  }
+  return { markdown, math };
+}
+
+/** Returns the math replaced by a code span from `extractMath()`, if any. */
+export function extractedMath(code: string, math: MathSpan[]): MathSpan | null {
+  if (!code.startsWith(mathMarker)) return null;
+  return math[Number(code.slice(mathMarker.length))] ?? null;
+}